@@ -0,0 +1,77 @@
+use wgpu::Backends;
+
+// Picks an adapter from the enumerated list instead of letting wgpu choose
+#[derive(Clone)]
+pub enum AdapterChoice {
+    // Position in the --list-adapters output
+    Index(usize),
+    // Case-insensitive substring of the adapter's name
+    Name(String),
+}
+
+pub fn parse_backends(text: &str) -> Result<Backends, String> {
+    match text {
+        "vulkan" => Ok(Backends::VULKAN),
+        "dx12" => Ok(Backends::DX12),
+        "metal" => Ok(Backends::METAL),
+        "gl" => Ok(Backends::GL),
+        _ => Err(format!("invalid backend '{}' (expected vulkan, dx12, metal or gl)", text)),
+    }
+}
+
+pub fn create_instance(backends: Backends) -> wgpu::Instance {
+    wgpu::Instance::new(wgpu::InstanceDescriptor { backends, ..Default::default() })
+}
+
+// "NVIDIA GeForce RTX 3070 (Vulkan, DiscreteGpu)"
+pub fn describe(info: &wgpu::AdapterInfo) -> String {
+    format!("{} ({:?}, {:?})", info.name, info.backend, info.device_type)
+}
+
+// One line per adapter, numbered the way --adapter takes them
+#[cfg(not(target_arch = "wasm32"))]
+pub fn list(backends: Backends) -> Result<(), String> {
+    let adapters = create_instance(backends).enumerate_adapters(backends);
+    if adapters.is_empty() {
+        return Err("no GPU adapters found".to_string());
+    }
+    for (index, adapter) in adapters.iter().enumerate() {
+        println!("{}: {}", index, describe(&adapter.get_info()));
+    }
+    Ok(())
+}
+
+// The adapter `choice` names, or wgpu's own pick when there's no choice. An explicit pick that can't
+// present to `surface` is an error rather than a silent fallback
+pub async fn select(instance: &wgpu::Instance, surface: Option<&wgpu::Surface<'_>>, choice: Option<&AdapterChoice>) -> Result<wgpu::Adapter, String> {
+    let Some(choice) = choice else {
+        return rust_gpu_life::gpu::request_adapter(instance, surface).await
+            .ok_or_else(|| "no GPU adapter found, not even a software fallback (is a Vulkan, Metal or DX12 driver installed?)".to_string());
+    };
+    let adapter = pick(instance, choice)?;
+    if surface.is_some_and(|surface| !adapter.is_surface_supported(surface)) {
+        return Err(format!("adapter {} cannot present to this window", describe(&adapter.get_info())));
+    }
+    Ok(adapter)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn pick(instance: &wgpu::Instance, choice: &AdapterChoice) -> Result<wgpu::Adapter, String> {
+    let mut adapters = instance.enumerate_adapters(Backends::all());
+    let position = match choice {
+        AdapterChoice::Index(index) => (*index < adapters.len()).then_some(*index)
+            .ok_or_else(|| format!("there is no adapter {} ({} found), see --list-adapters", index, adapters.len()))?,
+        AdapterChoice::Name(name) => {
+            let lower = name.to_lowercase();
+            adapters.iter().position(|adapter| adapter.get_info().name.to_lowercase().contains(&lower))
+                .ok_or_else(|| format!("no adapter name contains '{}', see --list-adapters", name))?
+        }
+    };
+    Ok(adapters.swap_remove(position))
+}
+
+// Browsers only hand out the adapter they choose
+#[cfg(target_arch = "wasm32")]
+fn pick(_: &wgpu::Instance, _: &AdapterChoice) -> Result<wgpu::Adapter, String> {
+    Err("--adapter and --adapter-name are not available in the browser".to_string())
+}
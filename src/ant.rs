@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use crate::boundary::Boundary;
+use crate::WALL;
+
+/// Set on the cell an ant stands on, with its heading in the two bits above; the colour it stands on is
+/// in the low byte. Kept in the grid so the ants are drawn, saved and copied along with the cells.
+pub const ANT: u32 = 1 << 8;
+const HEADING_SHIFT: u32 = 9;
+const COLOUR_MASK: u32 = 0xff;
+// Most colours a rule can cycle through
+const MAX_COLOURS: usize = 16;
+// (dx, dy) of each heading, clockwise from up
+const HEADINGS: [(i64, i64); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+/// Which way an ant turns on each colour, as an LR-rulestring: on colour n it turns right if the n-th
+/// letter is R, left if it's L, then moves the cell on to colour n + 1 (wrapping) and steps forward
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AntRule {
+    // Bit n set: turn right on colour n
+    right: u32,
+    colours: u32,
+}
+
+impl AntRule {
+    /// Langton's original ant, RL
+    pub const LANGTON: AntRule = AntRule { right: 0b01, colours: 2 };
+
+    pub fn parse(text: &str) -> Result<AntRule, String> {
+        let invalid = || format!("invalid ant rule '{}' (expected 2 to {} of L and R, such as RL or LLRR)", text, MAX_COLOURS);
+        if !(2..=MAX_COLOURS).contains(&text.len()) { return Err(invalid()); }
+        let mut right = 0;
+        for (colour, turn) in text.chars().enumerate() {
+            match turn.to_ascii_uppercase() {
+                'R' => right |= 1 << colour,
+                'L' => {}
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(AntRule { right, colours: text.len() as u32 })
+    }
+
+    pub fn colours(self) -> u32 {
+        self.colours
+    }
+
+    // Heading after turning on `colour`
+    fn turn(self, heading: u32, colour: u32) -> u32 {
+        if self.right >> colour & 1 == 1 { (heading + 1) % 4 } else { (heading + 3) % 4 }
+    }
+}
+
+impl fmt::Display for AntRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (0..self.colours).try_for_each(|colour| f.write_str(if self.right >> colour & 1 == 1 { "R" } else { "L" }))
+    }
+}
+
+/// One ant: where it stands and which way it faces (0 up, then clockwise)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Ant {
+    pub x: usize,
+    pub y: usize,
+    pub heading: u32,
+}
+
+/// The colour of a cell, without any ant on it
+pub fn colour(cell: u32) -> u32 {
+    if cell == WALL { 0 } else { cell & COLOUR_MASK }
+}
+
+/// The ants standing on a `width`-wide grid, in row-major order; ants sharing a cell only show up once.
+pub fn find(cells: &[u32], width: usize) -> Vec<Ant> {
+    cells.iter().enumerate()
+        .filter(|&(_, &cell)| cell != WALL && cell & ANT != 0)
+        .map(|(index, &cell)| Ant { x: index % width, y: index / width, heading: cell >> HEADING_SHIFT & 3 })
+        .collect()
+}
+
+/// Brings `ants` up to date after the cells in `written` were written over, keeping the order they move
+/// in: ants there whose cell no longer holds one are gone, and ants the write put on cells nobody stood on
+/// join at the end. Ants outside it are left alone, so two sharing a cell stay two, where `find` would
+/// see one.
+pub fn written(ants: &mut Vec<Ant>, cells: &[u32], width: usize, written: Range<usize>) {
+    let holds_ant = |index: usize| cells[index] != WALL && cells[index] & ANT != 0;
+    ants.retain(|ant| {
+        let index = ant.y * width + ant.x;
+        !written.contains(&index) || holds_ant(index)
+    });
+    let standing: HashSet<usize> = ants.iter().map(|ant| ant.y * width + ant.x).filter(|index| written.contains(index)).collect();
+    ants.extend(written.filter(|&index| holds_ant(index) && !standing.contains(&index))
+        .map(|index| Ant { x: index % width, y: index / width, heading: cells[index] >> HEADING_SHIFT & 3 }));
+}
+
+/// An empty grid with `count` ants at random cells facing random ways.
+pub fn seed_grid(width: u32, height: u32, seed: u64, count: u32) -> Vec<u32> {
+    let (width, height) = (width as usize, height as usize);
+    let mut grid = vec![0; width * height];
+    let mut rng = StdRng::seed_from_u64(seed);
+    for _ in 0..count {
+        let index = rng.gen_range(0..width * height);
+        grid[index] = ANT | rng.gen_range(0..4u32) << HEADING_SHIFT;
+    }
+    grid
+}
+
+/// Moves every ant once, one after the other: each turns by the colour under it, moves that cell on to
+/// the next colour and steps forward. Ants wrap around the edges of a `Wrap` grid and turn back at
+/// the edges of the others, and at walls. Rows whose cells changed are flagged in `changed`.
+pub fn step(cells: &mut [u32], width: usize, rule: AntRule, boundary: Boundary, ants: &mut [Ant], changed: &mut [bool]) {
+    let height = cells.len() / width;
+    for ant in ants.iter_mut() {
+        let index = ant.y * width + ant.x;
+        let under = colour(cells[index]);
+        ant.heading = rule.turn(ant.heading, under);
+        cells[index] = (under + 1) % rule.colours;
+        changed[ant.y] = true;
+        let (dx, dy) = HEADINGS[ant.heading as usize];
+        let ahead = match boundary {
+            Boundary::Wrap => Some(((ant.x as i64 + dx).rem_euclid(width as i64) as usize, (ant.y as i64 + dy).rem_euclid(height as i64) as usize)),
+            Boundary::Dead | Boundary::Mirror => {
+                let (x, y) = (ant.x as i64 + dx, ant.y as i64 + dy);
+                (x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height).then_some((x as usize, y as usize))
+            }
+        };
+        match ahead {
+            Some((x, y)) if cells[y * width + x] != WALL => (ant.x, ant.y) = (x, y),
+            _ => ant.heading = (ant.heading + 2) % 4,
+        }
+    }
+    // Marked once they've all moved, so an ant leaving a cell another one still stands on doesn't unmark it
+    for ant in ants.iter() {
+        let index = ant.y * width + ant.x;
+        cells[index] = colour(cells[index]) | ANT | ant.heading << HEADING_SHIFT;
+        changed[ant.y] = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_round_trip() {
+        assert_eq!(AntRule::parse("RL").unwrap(), AntRule::LANGTON);
+        assert_eq!(AntRule::parse("llrr").unwrap().to_string(), "LLRR");
+        assert!(AntRule::parse("R").is_err());
+        assert!(AntRule::parse("RLX").is_err());
+    }
+
+    #[test]
+    fn langtons_ant_walks_a_square_then_turns_back() {
+        const SIZE: usize = 11;
+        let mut cells = vec![0; SIZE * SIZE];
+        cells[5 * SIZE + 5] = ANT;
+        let mut ants = find(&cells, SIZE);
+        let mut changed = vec![false; SIZE];
+        // Four right turns on fresh cells bring it back to where it started, which it now leaves to the left
+        for _ in 0..5 {
+            step(&mut cells, SIZE, AntRule::LANGTON, Boundary::Wrap, &mut ants, &mut changed);
+        }
+        assert_eq!(ants, vec![Ant { x: 4, y: 5, heading: 3 }]);
+        assert_eq!(find(&cells, SIZE), ants);
+        let coloured: Vec<usize> = (0..cells.len()).filter(|&index| colour(cells[index]) == 1).collect();
+        assert_eq!(coloured, vec![5 * SIZE + 6, 6 * SIZE + 5, 6 * SIZE + 6]);
+        assert!(changed[5] && changed[6] && !changed[4]);
+    }
+
+    #[test]
+    fn ants_turn_back_at_walls_and_dead_edges() {
+        let mut cells = vec![0; 4 * 4];
+        cells[1] = WALL;
+        // Facing up from the top-left corner: turns right into the wall, so back it goes
+        let mut ants = vec![Ant { x: 0, y: 0, heading: 0 }];
+        step(&mut cells, 4, AntRule::LANGTON, Boundary::Dead, &mut ants, &mut [false; 4]);
+        assert_eq!(ants, vec![Ant { x: 0, y: 0, heading: 3 }]);
+        assert_eq!(cells[1], WALL);
+    }
+
+    #[test]
+    fn writes_keep_the_ants_they_miss() {
+        const SIZE: usize = 6;
+        let mut cells = vec![0; SIZE * SIZE];
+        // Two ants on one cell, which the grid shows as one, and another further down
+        cells[2 * SIZE + 2] = ANT | 2 << HEADING_SHIFT;
+        cells[4 * SIZE + 1] = ANT;
+        let mut ants = vec![Ant { x: 2, y: 2, heading: 0 }, Ant { x: 2, y: 2, heading: 2 }, Ant { x: 1, y: 4, heading: 0 }];
+        assert_eq!(find(&cells, SIZE).len(), 2);
+
+        // A row painted over elsewhere leaves both
+        written(&mut ants, &cells, SIZE, 0..SIZE);
+        assert_eq!(ants.len(), 3);
+
+        // Clearing the lone ant drops it, and an ant dropped onto row 5 moves last
+        cells[4 * SIZE + 1] = 0;
+        cells[5 * SIZE + 3] = ANT | 1 << HEADING_SHIFT;
+        written(&mut ants, &cells, SIZE, 4 * SIZE..SIZE * SIZE);
+        assert_eq!(ants, vec![Ant { x: 2, y: 2, heading: 0 }, Ant { x: 2, y: 2, heading: 2 }, Ant { x: 3, y: 5, heading: 1 }]);
+
+        // Writing the shared cell as it was keeps both, with the headings they had
+        written(&mut ants, &cells, SIZE, 2 * SIZE + 2..2 * SIZE + 3);
+        assert_eq!(ants.len(), 3);
+        cells[2 * SIZE + 2] = 1;
+        written(&mut ants, &cells, SIZE, 2 * SIZE..3 * SIZE);
+        assert_eq!(ants, vec![Ant { x: 3, y: 5, heading: 1 }]);
+    }
+}
@@ -0,0 +1,293 @@
+use std::fmt;
+use crate::ant;
+use crate::gray_scott;
+use crate::lenia;
+use crate::rule::Rule;
+use crate::{MAX_AGE, WALL};
+
+// Immigration counts each blue neighbour as this, so one sum carries both colours (at most 8 of each)
+const BLUE_NEIGHBOUR: u32 = 16;
+
+/// Which update rule family runs; the discriminants are the values rules.wgsl expects.
+///
+/// A new automaton needs a variant here, its arms in `counts` and `stepping` (and in `CellAutomaton` or
+/// `GridAutomaton`), and the matching arms in rules.wgsl.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum Automaton {
+    /// Two-state Life-like rule from the B/S masks; live cells store their age
+    Life = 0,
+    /// 0 ready, 1 firing, 2 refractory; ready cells fire with exactly two firing neighbours
+    BriansBrain = 1,
+    /// Life with a radius-r neighbourhood and birth / survival ranges (see `LtlRule`); cells age like Life
+    LargerThanLife = 2,
+    /// 0 empty, 1 conductor, 2 electron head, 3 electron tail; conductors turn into heads next to one or two heads
+    Wireworld = 3,
+    /// 0 dead, 1 red, 2 blue; B3/S23 whatever the colour, newborns take the majority colour of their three parents
+    Immigration = 4,
+    /// Gray-Scott reaction-diffusion; each cell packs two chemical concentrations, stepped at full precision
+    /// beside the grid (see `gray_scott`)
+    GrayScott = 5,
+    /// Generations rule (see `Rule::states`): 0 dead, 1 alive, 2 and up fading; Brian's Brain is /2/3.
+    /// Picked by the rule rather than cycled to
+    Generations = 6,
+    /// Langton's ants (see `ant`): cells hold colours the ants walking over them cycle through, the GPU
+    /// leaves them be. Only picked with --automaton, since switching to it finds no ants to walk
+    LangtonsAnt = 7,
+    /// Cyclic cellular automaton (see `cyclic`): cells hold 0 to N - 1 and advance when enough neighbours
+    /// hold the next value. Only picked with --automaton, since its soups are uniform over all N states
+    Cyclic = 8,
+    /// Lenia (see `lenia`): cells hold an intensity from 0 to 1 that grows or fades by a smooth ring of
+    /// neighbours. Only picked with --automaton, since its creatures grow out of blobs rather than soups
+    Lenia = 9,
+    /// Drossel-Schwabl forest fire (see `forest_fire`): 0 empty, 1 tree, 2 burning; trees grow and lightning
+    /// strikes at random. Only picked with --automaton, like the other automata with their own settings
+    ForestFire = 10,
+    /// Rock-paper-scissors (see `rps`): 0 empty, then three species that each beat the next and take over
+    /// cells with enough of them around. Only picked with --automaton, since its soups hold every species
+    RockPaperScissors = 11,
+}
+
+impl Automaton {
+    pub fn parse(text: &str) -> Result<Automaton, String> {
+        match text {
+            "life" => Ok(Automaton::Life),
+            "brians-brain" => Ok(Automaton::BriansBrain),
+            "ltl" => Ok(Automaton::LargerThanLife),
+            "wireworld" => Ok(Automaton::Wireworld),
+            "immigration" => Ok(Automaton::Immigration),
+            "gray-scott" => Ok(Automaton::GrayScott),
+            "generations" => Ok(Automaton::Generations),
+            "langtons-ant" => Ok(Automaton::LangtonsAnt),
+            "cyclic" => Ok(Automaton::Cyclic),
+            "lenia" => Ok(Automaton::Lenia),
+            "forest-fire" => Ok(Automaton::ForestFire),
+            "rock-paper-scissors" => Ok(Automaton::RockPaperScissors),
+            _ => Err(format!("invalid automaton '{}' (expected life, brians-brain, ltl, wireworld, immigration, gray-scott, generations, langtons-ant, cyclic, lenia, forest-fire or rock-paper-scissors)", text)),
+        }
+    }
+
+    pub fn next(self) -> Automaton {
+        match self {
+            Automaton::Life => Automaton::BriansBrain,
+            Automaton::BriansBrain => Automaton::LargerThanLife,
+            Automaton::LargerThanLife => Automaton::Wireworld,
+            Automaton::Wireworld => Automaton::Immigration,
+            Automaton::Immigration => Automaton::GrayScott,
+            Automaton::GrayScott => Automaton::Life,
+            Automaton::Generations => Automaton::BriansBrain,
+            Automaton::LangtonsAnt | Automaton::Cyclic | Automaton::Lenia | Automaton::ForestFire | Automaton::RockPaperScissors => Automaton::Life,
+        }
+    }
+
+    /// The automaton `rule` runs under: Generations rules need fading states, which Life would take for ages
+    pub fn for_rule(self, rule: Rule) -> Automaton {
+        match self {
+            Automaton::Life if rule.is_generations() => Automaton::Generations,
+            Automaton::Generations if !rule.is_generations() => Automaton::Life,
+            automaton => automaton,
+        }
+    }
+
+    /// Whether a neighbour in this state adds to the neighbour count
+    pub fn counts(self, cell: u32) -> bool {
+        match self {
+            _ if cell == WALL => false,
+            Automaton::Life | Automaton::LargerThanLife | Automaton::Immigration | Automaton::LangtonsAnt | Automaton::Cyclic | Automaton::ForestFire | Automaton::RockPaperScissors => cell > 0,
+            Automaton::BriansBrain | Automaton::Generations => cell == 1,
+            Automaton::Wireworld => cell == 2,
+            Automaton::GrayScott => gray_scott::is_live(cell),
+            Automaton::Lenia => lenia::is_live(cell),
+        }
+    }
+
+    /// What a neighbour in this state adds to the `neighbors` passed to `CellAutomaton::next_state`
+    pub fn neighbor_weight(self, cell: u32) -> u32 {
+        match self {
+            Automaton::Immigration if cell == 2 => BLUE_NEIGHBOUR,
+            _ => self.counts(cell) as u32,
+        }
+    }
+
+    /// Whether this automaton steps cell by cell or a whole grid at a time
+    pub fn stepping(self) -> Stepping {
+        match self {
+            Automaton::Life => Stepping::Cell(CellAutomaton::Life),
+            Automaton::BriansBrain => Stepping::Cell(CellAutomaton::BriansBrain),
+            Automaton::Generations => Stepping::Cell(CellAutomaton::Generations),
+            Automaton::Wireworld => Stepping::Cell(CellAutomaton::Wireworld),
+            Automaton::Immigration => Stepping::Cell(CellAutomaton::Immigration),
+            Automaton::LargerThanLife => Stepping::Grid(GridAutomaton::LargerThanLife),
+            Automaton::GrayScott => Stepping::Grid(GridAutomaton::GrayScott),
+            Automaton::LangtonsAnt => Stepping::Grid(GridAutomaton::LangtonsAnt),
+            Automaton::Cyclic => Stepping::Grid(GridAutomaton::Cyclic),
+            Automaton::Lenia => Stepping::Grid(GridAutomaton::Lenia),
+            Automaton::ForestFire => Stepping::Grid(GridAutomaton::ForestFire),
+            Automaton::RockPaperScissors => Stepping::Grid(GridAutomaton::RockPaperScissors),
+        }
+    }
+
+    /// Whether `BitpackedSimulation` can hold this automaton's states in its two bit planes
+    pub fn packs(self) -> bool {
+        matches!(self, Automaton::Life | Automaton::BriansBrain | Automaton::LargerThanLife | Automaton::Wireworld | Automaton::Immigration)
+    }
+
+    /// Whether two values of a cell are the same state; Life cells getting older doesn't count as a change
+    pub fn same_state(self, a: u32, b: u32) -> bool {
+        match self {
+            Automaton::Life | Automaton::LargerThanLife => (a > 0) == (b > 0),
+            Automaton::BriansBrain | Automaton::Wireworld | Automaton::Immigration | Automaton::GrayScott | Automaton::Generations | Automaton::LangtonsAnt | Automaton::Cyclic | Automaton::Lenia | Automaton::ForestFire | Automaton::RockPaperScissors => a == b,
+        }
+    }
+
+    /// The state a cell is written out as in a pattern, dropping Life ages
+    pub fn pattern_state(self, cell: u32) -> u32 {
+        match self {
+            // Patterns have no walls
+            _ if cell == WALL => 0,
+            Automaton::Life | Automaton::LargerThanLife => (cell > 0) as u32,
+            Automaton::BriansBrain | Automaton::Wireworld | Automaton::Immigration | Automaton::Generations | Automaton::Cyclic | Automaton::ForestFire | Automaton::RockPaperScissors => cell,
+            // Concentrations don't fit a pattern, only where v has built up
+            Automaton::GrayScott => gray_scott::is_live(cell) as u32,
+            Automaton::Lenia => lenia::is_live(cell) as u32,
+            // The colours without the ants
+            Automaton::LangtonsAnt => ant::colour(cell),
+        }
+    }
+
+    /// Bits a cell's state needs in the history; 0 when it doesn't fit there (Gray-Scott's concentrations,
+    /// Generations rules with more than four states, ants and their headings, cyclic rules' many values,
+    /// Lenia's intensities)
+    pub fn state_bits(self, rule: Rule) -> u32 {
+        match self {
+            Automaton::Life | Automaton::LargerThanLife => 1,
+            Automaton::BriansBrain | Automaton::Wireworld | Automaton::Immigration | Automaton::ForestFire | Automaton::RockPaperScissors => 2,
+            Automaton::Generations if rule.states <= 4 => 2,
+            Automaton::GrayScott | Automaton::Generations | Automaton::LangtonsAnt | Automaton::Cyclic | Automaton::Lenia => 0,
+        }
+    }
+
+    /// A cell from the `from` automaton carried over when switching to this one: live (or firing)
+    /// cells start out as newborn live (or firing) cells, everything else is dead (or ready).
+    /// Wireworld wires go live and live cells become wire, so drawn circuits survive the round trip.
+    /// Gray-Scott seeds wherever something was, as does Lenia at full intensity, and their patterns come
+    /// back live. Walls stay walls.
+    pub fn adopt(self, from: Automaton, cell: u32) -> u32 {
+        // 1 is a newborn live cell, a firing one and a conductor alike
+        match (from, self) {
+            _ if cell == WALL => WALL,
+            (_, Automaton::GrayScott) => if from.pattern_state(cell) > 0 { gray_scott::SEED } else { 0 },
+            (_, Automaton::Lenia) => if from.pattern_state(cell) > 0 { lenia::FULL } else { 0 },
+            (Automaton::Wireworld, _) | (_, Automaton::Wireworld) => from.pattern_state(cell).min(1),
+            _ => from.counts(cell) as u32,
+        }
+    }
+}
+
+/// How the CPU steps an automaton, see `Automaton::stepping`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stepping {
+    /// Each cell from its own state and its neighbour count, through `CellAutomaton::next_state`
+    Cell(CellAutomaton),
+    /// A whole grid at once, in the automaton's own module
+    Grid(GridAutomaton),
+}
+
+/// The automata whose cells step from their one-cell neighbourhood's count alone, which `cpu::step_grid`
+/// and the sparse backend run.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CellAutomaton {
+    Life,
+    BriansBrain,
+    Generations,
+    Wireworld,
+    Immigration,
+}
+
+/// The automata that step a whole grid at once: Larger than Life through `ltl::step_grid`, Gray-Scott
+/// through `gray_scott::step_grid`, ants through `ant::step`, cyclic rules through `cyclic::step_grid`,
+/// Lenia through `lenia::step_grid`, forest fires through `forest_fire::step_grid` and rock-paper-scissors
+/// through `rps::step_grid`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GridAutomaton {
+    LargerThanLife,
+    GrayScott,
+    LangtonsAnt,
+    Cyclic,
+    Lenia,
+    ForestFire,
+    RockPaperScissors,
+}
+
+impl CellAutomaton {
+    /// What a neighbour in this state adds to the `neighbors` passed to `next_state`
+    pub fn neighbor_weight(self, cell: u32) -> u32 {
+        Automaton::from(self).neighbor_weight(cell)
+    }
+
+    /// One cell's next state from its neighbour count (summed `neighbor_weight`s)
+    pub fn next_state(self, rule: Rule, cell: u32, neighbors: u32) -> u32 {
+        match self {
+            _ if cell == WALL => WALL,
+            // Newborn cells start at age 1, survivors age by one
+            CellAutomaton::Life => if rule.next_state(cell > 0, neighbors) { (cell + 1).min(MAX_AGE) } else { 0 },
+            CellAutomaton::BriansBrain => match cell {
+                0 => (neighbors == 2) as u32,
+                firing_or_refractory => (firing_or_refractory + 1) % 3,
+            },
+            // Live cells that don't survive start fading, and fading ones fade on until they're dead
+            CellAutomaton::Generations => match cell {
+                0 => rule.next_state(false, neighbors) as u32,
+                1 if rule.next_state(true, neighbors) => 1,
+                fading => (fading + 1) % rule.states,
+            },
+            CellAutomaton::Wireworld => match cell {
+                0 => 0,
+                1 if neighbors == 1 || neighbors == 2 => 2,
+                1 => 1,
+                2 => 3,
+                _ => 1,
+            },
+            CellAutomaton::Immigration => {
+                let blue = neighbors / BLUE_NEIGHBOUR;
+                let live = neighbors % BLUE_NEIGHBOUR + blue;
+                match cell {
+                    0 if live == 3 => if blue >= 2 { 2 } else { 1 },
+                    0 => 0,
+                    colour => if live == 2 || live == 3 { colour } else { 0 },
+                }
+            }
+        }
+    }
+}
+
+impl From<CellAutomaton> for Automaton {
+    fn from(automaton: CellAutomaton) -> Automaton {
+        match automaton {
+            CellAutomaton::Life => Automaton::Life,
+            CellAutomaton::BriansBrain => Automaton::BriansBrain,
+            CellAutomaton::Generations => Automaton::Generations,
+            CellAutomaton::Wireworld => Automaton::Wireworld,
+            CellAutomaton::Immigration => Automaton::Immigration,
+        }
+    }
+}
+
+impl fmt::Display for Automaton {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Automaton::Life => "life",
+            Automaton::BriansBrain => "brians-brain",
+            Automaton::LargerThanLife => "ltl",
+            Automaton::Wireworld => "wireworld",
+            Automaton::Immigration => "immigration",
+            Automaton::GrayScott => "gray-scott",
+            Automaton::Generations => "generations",
+            Automaton::LangtonsAnt => "langtons-ant",
+            Automaton::Cyclic => "cyclic",
+            Automaton::Lenia => "lenia",
+            Automaton::ForestFire => "forest-fire",
+            Automaton::RockPaperScissors => "rock-paper-scissors",
+        })
+    }
+}
@@ -0,0 +1,246 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::regions::RuleMap;
+use crate::rule::Rule;
+use crate::snapshot::{Settings, Snapshot};
+
+/// Autosaves older than this aren't offered at startup.
+pub const RECENT: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+// Map status shared with the map_async callback, as in `StagingRing`
+const PENDING: u8 = 0;
+const MAPPED: u8 = 1;
+const FAILED: u8 = 2;
+
+/// Where autosaves go: `rust_gpu_life/autosave.bin` under `$XDG_CACHE_HOME`, `~/.cache` or `%LOCALAPPDATA%`.
+pub fn path() -> Option<PathBuf> {
+    let cache = std::env::var_os("XDG_CACHE_HOME").map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))?;
+    Some(cache.join("rust_gpu_life").join("autosave.bin"))
+}
+
+/// How long ago the autosave at `path` was written, if it was within `RECENT`.
+pub fn recent(path: &Path) -> Option<Duration> {
+    let age = std::fs::metadata(path).ok()?.modified().ok()?.elapsed().ok()?;
+    (age < RECENT).then_some(age)
+}
+
+/// When an autosave is due: every `generations` generations or `interval` of wall time, whichever comes
+/// first, as long as the grid has moved since the last one. Rewinds count as moving too.
+pub struct Schedule {
+    generations: usize,
+    interval: Duration,
+    last_generation: usize,
+    last_time: Instant,
+}
+
+impl Schedule {
+    pub fn new(generations: usize, interval: Duration, generation: usize, now: Instant) -> Schedule {
+        Schedule { generations, interval, last_generation: generation, last_time: now }
+    }
+
+    pub fn due(&self, generation: usize, now: Instant) -> bool {
+        generation != self.last_generation
+            && (generation.abs_diff(self.last_generation) >= self.generations || now.duration_since(self.last_time) >= self.interval)
+    }
+
+    pub fn saved(&mut self, generation: usize, now: Instant) {
+        self.last_generation = generation;
+        self.last_time = now;
+    }
+}
+
+/// What the grid being saved is, beyond its cells. The rule map is shared rather than copied, since a
+/// header is made every frame whether a save is due or not.
+#[derive(Clone)]
+pub struct Header {
+    pub width: u32,
+    pub height: u32,
+    pub generation: u64,
+    pub rule: Rule,
+    pub regions: Option<Arc<RuleMap>>,
+    pub settings: Settings,
+}
+
+/// Saves the drawn GPU buffer as a `Snapshot` without stalling a frame: the copy is encoded with the frame,
+/// mapped asynchronously, and read, encoded and written by a thread of its own. The file is written next to
+/// its final name and renamed over it, so a crash mid-write leaves the previous autosave whole.
+pub struct Autosaver {
+    path: PathBuf,
+    schedule: Schedule,
+    // Made on the first save, since it's as big as the grid
+    staging: Option<Arc<wgpu::Buffer>>,
+    status: Arc<AtomicU8>,
+    // From `encode` until the thread has written the file and unmapped the buffer
+    busy: Arc<AtomicBool>,
+    encoded: Option<Header>,
+    mapping: Option<Header>,
+    // Generation of the latest save started, whether or not it has reached the file yet
+    started: Option<u64>,
+}
+
+impl Autosaver {
+    pub fn new(path: PathBuf, schedule: Schedule) -> Autosaver {
+        Autosaver { path, schedule, staging: None, status: Arc::new(AtomicU8::new(PENDING)), busy: Arc::new(AtomicBool::new(false)), encoded: None, mapping: None, started: None }
+    }
+
+    /// Copies `cells` out with the frame if a save is due and the last one is done.
+    pub fn encode(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, cells: &wgpu::Buffer, header: Header) {
+        let now = Instant::now();
+        if !self.schedule.due(header.generation as usize, now) || self.busy.load(Ordering::Acquire) { return; }
+        let staging = self.staging.get_or_insert_with(|| Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Autosave Staging"),
+            size: cells.size(),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })));
+        encoder.copy_buffer_to_buffer(cells, 0, staging, 0, cells.size());
+        self.busy.store(true, Ordering::Release);
+        self.started = Some(header.generation);
+        self.schedule.saved(header.generation as usize, now);
+        self.encoded = Some(header);
+    }
+
+    /// The file this run last saved to, if that save has finished; an autosave left by an earlier run, or
+    /// one still being written, is `None`.
+    pub fn latest(&self) -> Option<Snapshot> {
+        let generation = self.started?;
+        if self.busy.load(Ordering::Acquire) { return None; }
+        Snapshot::load(&self.path).ok().filter(|snapshot| snapshot.generation == generation)
+    }
+
+    /// Maps the copy; must be called after the encoder was submitted.
+    pub fn begin_readback(&mut self) {
+        let (Some(header), Some(staging)) = (self.encoded.take(), &self.staging) else { return };
+        self.mapping = Some(header);
+        let status = self.status.clone();
+        staging.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            status.store(if result.is_ok() { MAPPED } else { FAILED }, Ordering::Release);
+        });
+    }
+
+    /// Hands a finished copy to the writing thread; call after `device.poll`.
+    pub fn try_write(&mut self) {
+        let Some(staging) = self.staging.clone() else { return };
+        match self.status.swap(PENDING, Ordering::Acquire) {
+            PENDING => return,
+            FAILED => {
+                self.mapping = None;
+                self.busy.store(false, Ordering::Release);
+                return;
+            }
+            _ => {}
+        }
+        let Some(header) = self.mapping.take() else { return };
+        let (path, busy) = (self.path.clone(), self.busy.clone());
+        std::thread::spawn(move || {
+            let cells = bytemuck::cast_slice(&staging.slice(..).get_mapped_range()).to_vec();
+            staging.unmap();
+            let regions = header.regions.map(Arc::unwrap_or_clone);
+            let snapshot = Snapshot { width: header.width, height: header.height, generation: header.generation, rule: header.rule, regions, settings: Some(header.settings), cells };
+            match write(&path, &snapshot) {
+                Ok(()) => log::info!("Autosaved generation {} to {}", snapshot.generation, path.display()),
+                Err(e) => eprintln!("Autosave failed: {}", e),
+            }
+            busy.store(false, Ordering::Release);
+        });
+    }
+}
+
+fn write(path: &Path, snapshot: &Snapshot) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("could not create '{}': {}", dir.display(), e))?;
+    }
+    let partial = path.with_extension("bin.partial");
+    snapshot.save(&partial)?;
+    std::fs::rename(&partial, path).map_err(|e| format!("could not move '{}' to '{}': {}", partial.display(), path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ant::AntRule;
+    use crate::automaton::Automaton;
+    use crate::boundary::Boundary;
+    use crate::cyclic::CyclicRule;
+    use crate::forest_fire::ForestFire;
+    use crate::gray_scott::GrayScott;
+    use crate::lenia::Lenia;
+    use crate::ltl::LtlRule;
+    use crate::topology::Topology;
+
+    #[test]
+    fn due_after_enough_generations_or_time() {
+        let start = Instant::now();
+        let mut schedule = Schedule::new(5000, Duration::from_secs(300), 100, start);
+        assert!(!schedule.due(4000, start));
+        assert!(schedule.due(5100, start));
+        assert!(schedule.due(101, start + Duration::from_secs(300)));
+        // Nothing new to save, however long it's been
+        assert!(!schedule.due(100, start + Duration::from_secs(3600)));
+        schedule.saved(5100, start + Duration::from_secs(10));
+        assert!(!schedule.due(5200, start + Duration::from_secs(20)));
+        // Rewinding far enough counts
+        assert!(schedule.due(50, start + Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn writes_replace_the_previous_autosave() {
+        let dir = std::env::temp_dir().join(format!("rust_gpu_life_autosave_{}", std::process::id()));
+        let path = dir.join("autosave.bin");
+        for generation in [7, 8] {
+            let snapshot = Snapshot { width: 2, height: 2, generation, rule: Rule::CONWAY, regions: None, settings: None, cells: vec![0, 1, 1, 0] };
+            write(&path, &snapshot).unwrap();
+        }
+        assert_eq!(Snapshot::load(&path).unwrap().generation, 8);
+        assert!(!path.with_extension("bin.partial").exists());
+        assert!(recent(&path).is_some());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn resumes_with_the_automaton_and_settings() {
+        let Some(adapter) = pollster::block_on(crate::gpu::request_adapter(&wgpu::Instance::default(), None)) else { return };
+        let (device, queue) = pollster::block_on(crate::gpu::request_device(&adapter)).unwrap();
+        let dir = std::env::temp_dir().join(format!("rust_gpu_life_resume_{}", std::process::id()));
+        let path = dir.join("autosave.bin");
+        let cells = vec![0u32, 1, 2, 3, 0, 0, 1, 0];
+        let buffer = wgpu::util::DeviceExt::create_buffer_init(&device, &wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&cells),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        });
+        let settings = Settings {
+            automaton: Automaton::Cyclic,
+            boundary: Boundary::Mirror,
+            topology: Topology::Hex,
+            ltl: LtlRule::BOSCO,
+            gray_scott: GrayScott::CORAL,
+            ant_rule: AntRule::LANGTON,
+            cyclic: CyclicRule { states: 4, threshold: 2 },
+            rps_threshold: 3,
+            lenia: Lenia::ORBIUM,
+            forest_fire: ForestFire::DROSSEL_SCHWABL,
+        };
+        let header = Header { width: 4, height: 2, generation: 12, rule: Rule::CONWAY, regions: None, settings };
+
+        let mut autosaver = Autosaver::new(path.clone(), Schedule::new(1, Duration::ZERO, 0, Instant::now()));
+        let mut encoder = device.create_command_encoder(&Default::default());
+        autosaver.encode(&device, &mut encoder, &buffer, header);
+        queue.submit(Some(encoder.finish()));
+        autosaver.begin_readback();
+        let resumed = loop {
+            device.poll(wgpu::Maintain::Wait);
+            autosaver.try_write();
+            if let Some(snapshot) = autosaver.latest() { break snapshot; }
+            std::thread::sleep(Duration::from_millis(5));
+        };
+        assert_eq!((resumed.width, resumed.height, resumed.generation), (4, 2, 12));
+        assert_eq!(resumed.cells, cells);
+        assert_eq!(resumed.settings, Some(settings));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
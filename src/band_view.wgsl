@@ -0,0 +1,49 @@
+// A window's view of a grid cut into bands (see bands.rs): one dispatch per band writes the cell under each
+// pixel that falls in the band's rows, so together they make one cell per pixel for render.wgsl to draw
+struct View {
+    center: vec2<f32>, // In cells, as in render.wgsl's Camera
+    zoom: f32,
+    topology: u32,
+    grid_width: u32,
+    grid_height: u32,
+    first_row: u32, // The band's rows of the grid
+    rows: u32,
+    halo: u32,      // Rows above them in the band's buffer
+    width: u32,     // Pixels of the view
+    height: u32,
+    _pad: u32,
+};
+
+// Matches `Topology` in topology.rs
+const TOPOLOGY_HEX: u32 = 1u;
+
+@group(0) @binding(0) var<storage, read> band: array<u32>;
+@group(0) @binding(1) var<storage, read_write> pixels: array<u32>;
+@group(0) @binding(2) var<uniform> view: View;
+
+// As in render.wgsl, so a hex grid's cells come out the same shape
+fn drawn_cell(point: vec2<f32>) -> vec2<u32> {
+    var row = i32(floor(point.y));
+    var shift = 0.0;
+    if (view.topology == TOPOLOGY_HEX) {
+        let u = fract(point.x - 0.5 * f32(row & 1));
+        if (point.y - f32(row) < 0.25 * abs(2.0 * u - 1.0)) { row -= 1; }
+        shift = 0.5 * f32(row & 1);
+    }
+    let size = vec2<i32>(i32(view.grid_width), i32(view.grid_height));
+    let cell = vec2<i32>(i32(floor(point.x - shift)), row);
+    return vec2<u32>(((cell % size) + size) % size);
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if (global_id.x >= view.width || global_id.y >= view.height) { return; }
+
+    // The camera mapping of render.wgsl's fs_main, at the middle of the pixel
+    let size = vec2<f32>(f32(view.grid_width), f32(view.grid_height));
+    let uv = (vec2<f32>(global_id.xy) + 0.5) / vec2<f32>(f32(view.width), f32(view.height));
+    let point = view.center + (uv - 0.5) * (size / view.zoom);
+    let cell = drawn_cell(point - size * floor(point / size));
+    if (cell.y < view.first_row || cell.y >= view.first_row + view.rows) { return; }
+    pixels[global_id.y * view.width + global_id.x] = band[(cell.y - view.first_row + view.halo) * view.grid_width + cell.x];
+}
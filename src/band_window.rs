@@ -0,0 +1,338 @@
+use std::sync::Arc;
+use std::time::Instant;
+use wgpu::util::DeviceExt;
+use winit::{
+    application::ApplicationHandler,
+    dpi::PhysicalPosition,
+    event::{ElementState, KeyEvent, MouseButton, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    keyboard::{KeyCode, PhysicalKey},
+    window::{Window, WindowId},
+};
+use rust_gpu_life::{automaton::Automaton, bands::{self, BandLayout, BandView, BandedSimulation}, downsample::Downsampler, palette::Theme, snapshot::Snapshot, workgroup::WorkgroupSize};
+use crate::adapter;
+use crate::camera::{self, Camera};
+use crate::cli::Args;
+use crate::hud::TITLE_INTERVAL;
+use crate::present;
+use crate::selection::SelectionRect;
+use crate::stamp::StampPreview;
+
+// A window onto a grid stepped in bands (see bands.rs), for --bands or a grid too big for one of the
+// adapter's buffers. The grid is in no one buffer the renderer could bind, so each frame gathers what the
+// camera sees into a buffer the size of the view (see `BandView`) and draws that with render.wgsl like a grid
+// of its own. Everything that reads the whole grid back (editing, history, stats, saving) stays with the
+// usual window: this one steps, pans and zooms.
+
+// Generations stepped per frame at most, from ] doubling
+const MAX_PER_FRAME: u32 = 1024;
+
+// Whether the grid goes to this window: --bands asks for more than one band, or without it the grid
+// doesn't fit one buffer of the adapter
+pub fn wanted(args: &Args, initial: &Snapshot) -> Result<bool, String> {
+    if let Some(bands) = args.bands { return Ok(bands > 1); }
+    // Every adapter binds buffers as big as WebGPU's defaults, so only bigger grids ask it
+    let whole = BandLayout::whole(initial.width, initial.height);
+    if whole.buffer_size(0) <= wgpu::Limits::default().max_storage_buffer_binding_size as u64 { return Ok(false); }
+    let instance = adapter::create_instance(args.backends);
+    let adapter = pollster::block_on(adapter::select(&instance, None, args.adapter.as_ref()))?;
+    let reach = bands::reach(args.automaton, args.ltl, args.lenia);
+    Ok(bands::plan(None, initial.width, initial.height, reach, &adapter.limits())?.bands > 1)
+}
+
+pub fn run(args: Args, initial: Snapshot) -> Result<(), String> {
+    if args.automaton == Automaton::LangtonsAnt {
+        return Err("Langton's ants only walk on the CPU, which can't hold a grid in bands".to_string());
+    }
+    // Bands are stepped under the grid's rule alone
+    if initial.regions.is_some() {
+        return Err("the loaded state has a rule map, which grids stepped in bands can't follow".to_string());
+    }
+    let event_loop = EventLoop::new().map_err(|e| format!("could not open a window: {}", e))?;
+    let mut app = App { args, initial: Some(initial), state: None, error: None };
+    event_loop.run_app(&mut app).map_err(|e| e.to_string())?;
+    app.error.map_or(Ok(()), Err)
+}
+
+struct App {
+    args: Args,
+    initial: Option<Snapshot>,
+    state: Option<State>,
+    error: Option<String>,
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(initial) = self.initial.take() else { return };
+        let window = match event_loop.create_window(Window::default_attributes().with_title("Initializing...")) {
+            Ok(window) => Arc::new(window),
+            Err(e) => return self.fail(event_loop, format!("could not open a window: {}", e)),
+        };
+        match State::new(window, &self.args, initial) {
+            Ok(state) => {
+                state.window.request_redraw();
+                self.state = Some(state);
+            }
+            Err(e) => self.fail(event_loop, e),
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        let Some(state) = &mut self.state else { return };
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(size) => state.resize((size.width, size.height)),
+            WindowEvent::KeyboardInput { event: KeyEvent { physical_key: PhysicalKey::Code(key), state: ElementState::Pressed, .. }, .. } => {
+                if key == KeyCode::Escape { event_loop.exit(); }
+                state.key_pressed(key);
+            }
+            WindowEvent::CursorMoved { position, .. } => state.cursor_moved(position),
+            WindowEvent::MouseInput { state: pressed, button: MouseButton::Left | MouseButton::Middle, .. } => state.panning = pressed == ElementState::Pressed,
+            WindowEvent::MouseWheel { delta, .. } => state.zoom(crate::scroll_lines(delta)),
+            WindowEvent::RedrawRequested => {
+                if let Err(e) = state.redraw() {
+                    return self.fail(event_loop, format!("could not draw the window: {}", e));
+                }
+            }
+            _ => {}
+        }
+        if let Some(state) = &self.state { state.window.request_redraw(); }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if let Some(state) = &self.state {
+            event_loop.set_control_flow(if state.paused { ControlFlow::Wait } else { ControlFlow::Poll });
+        }
+    }
+}
+
+impl App {
+    fn fail(&mut self, event_loop: &ActiveEventLoop, error: String) {
+        self.error = Some(error);
+        event_loop.exit();
+    }
+}
+
+struct State {
+    window: Arc<Window>,
+    surface: wgpu::Surface<'static>,
+    config: wgpu::SurfaceConfiguration,
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    adapter_name: String,
+    simulation: BandedSimulation,
+    view: BandView,
+    pipeline: wgpu::RenderPipeline,
+    cells_layout: wgpu::BindGroupLayout,
+    cells: wgpu::BindGroup,
+    // render.wgsl's chunk, rule map and fade bindings, which the view has none of; a zeroed rule map is none
+    unused: [wgpu::Buffer; 3],
+    // Looking at the grid, where the view's pixels are gathered from
+    camera: Camera,
+    // Looking at the view, all of it, which is what render.wgsl draws
+    view_camera: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    overlays: wgpu::BindGroup,
+    clear: wgpu::Color,
+    generation: u64,
+    paused: bool,
+    per_frame: u32,
+    cursor: Option<PhysicalPosition<f64>>,
+    panning: bool,
+    title_shown: Instant,
+}
+
+impl State {
+    fn new(window: Arc<Window>, args: &Args, initial: Snapshot) -> Result<State, String> {
+        let instance = adapter::create_instance(args.backends);
+        let surface = instance.create_surface(window.clone()).map_err(|e| format!("could not create a window surface: {}", e))?;
+        let adapter = pollster::block_on(adapter::select(&instance, Some(&surface), args.adapter.as_ref()))?;
+        let info = adapter.get_info();
+        println!("Adapter: {} ({:?}, driver {} {})", info.name, info.backend, info.driver, info.driver_info);
+        let (device, queue) = pollster::block_on(rust_gpu_life::gpu::request_device(&adapter))?;
+        let (device, queue) = (Arc::new(device), Arc::new(queue));
+        let grid = (initial.width, initial.height);
+
+        let reach = bands::reach(args.automaton, args.ltl, args.lenia);
+        let layout = bands::plan(args.bands, grid.0, grid.1, reach, &device.limits())?;
+        println!("Bands: {} of {} rows with {}-row halos, {} MB per buffer, {} MB in all", layout.bands, layout.rows(0).len(), layout.halo, layout.buffer_size(0) >> 20, layout.total_size() >> 20);
+        let mut simulation = BandedSimulation::new(device.clone(), queue.clone(), layout, initial.rule, args.boundary, args.automaton, &initial.cells)?;
+        simulation.set_workgroup_size(args.workgroup.unwrap_or(WorkgroupSize::DEFAULT))?;
+        crate::headless::configure(&mut simulation, args);
+
+        let caps = surface.get_capabilities(&adapter);
+        let format = *caps.formats.first().ok_or("the adapter cannot present to this window")?;
+        let size = window.inner_size();
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode: present::choose(args.present_mode, &caps.present_modes),
+            alpha_mode: caps.alpha_modes[0],
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        surface.configure(&device, &config);
+
+        let themes: Vec<(String, Theme)> = Theme::PRESETS.iter().map(|&(name, theme)| (name.to_string(), theme)).chain(crate::themes::load()).collect();
+        let theme = themes.iter().find(|(name, _)| *name == args.theme).map(|(_, theme)| *theme)
+            .ok_or_else(|| format!("unknown theme '{}'", args.theme))?;
+
+        let [cells_layout, camera_layout, overlays_layout] = crate::render_bind_group_layouts(&device);
+        let view = BandView::new(&simulation, view_size((config.width, config.height), grid));
+        let unused = [0, 1, 2].map(|_| device.create_buffer(&wgpu::BufferDescriptor { label: Some("Band Window Unused"), size: 16, usage: wgpu::BufferUsages::STORAGE, mapped_at_creation: false }));
+        let cells = cells_bind_group(&device, &cells_layout, &view, &unused);
+
+        let view_camera = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Band Window Camera"), contents: bytemuck::bytes_of(&Camera::new(view.size())), usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let palette = device.create_buffer_init(&wgpu::util::BufferInitDescriptor { label: Some("Palette"), contents: bytemuck::bytes_of(&theme.uniform()), usage: wgpu::BufferUsages::UNIFORM });
+        // The view is drawn a cell per pixel, which never reduces; the downsampler only fills in its bindings
+        let reduced = [0, 1].map(|_| device.create_buffer(&wgpu::BufferDescriptor { label: Some("Band Window Unused"), size: 16, usage: wgpu::BufferUsages::STORAGE, mapped_at_creation: false }));
+        let downsampler = Downsampler::new(&device, &reduced, 1, 1, 1);
+        let camera_bind_group = crate::camera_bind_group(&device, &camera_layout, &view_camera, &downsampler, &palette);
+        let stamp = device.create_buffer_init(&wgpu::util::BufferInitDescriptor { label: Some("Stamp"), contents: bytemuck::bytes_of(&StampPreview::default()), usage: wgpu::BufferUsages::UNIFORM });
+        let selection = device.create_buffer_init(&wgpu::util::BufferInitDescriptor { label: Some("Selection"), contents: bytemuck::bytes_of(&SelectionRect::default()), usage: wgpu::BufferUsages::UNIFORM });
+        let mask = device.create_buffer(&wgpu::BufferDescriptor { label: Some("Stamp Mask"), size: 16, usage: wgpu::BufferUsages::STORAGE, mapped_at_creation: false });
+        let overlays = crate::stamp_bind_group(&device, &overlays_layout, &stamp, &mask, &selection);
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("render.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&cells_layout, &camera_layout, &overlays_layout], ..Default::default() });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("fs_main"), layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState { module: &shader, entry_point: "vs_main", buffers: &[], compilation_options: Default::default() },
+            fragment: Some(wgpu::FragmentState { module: &shader, entry_point: "fs_main", targets: &[Some(wgpu::ColorTargetState { format, blend: None, write_mask: wgpu::ColorWrites::ALL })], compilation_options: Default::default() }),
+            primitive: wgpu::PrimitiveState::default(), depth_stencil: None, multisample: wgpu::MultisampleState::default(), multiview: None, cache: None,
+        });
+
+        Ok(State {
+            window, surface, config, device, queue, adapter_name: info.name, simulation, view, pipeline, cells_layout, cells, unused,
+            camera: Camera::new(grid), view_camera, camera_bind_group, overlays, clear: theme.clear_color(),
+            generation: initial.generation, paused: false, per_frame: 1, cursor: None, panning: false, title_shown: Instant::now() - TITLE_INTERVAL,
+        })
+    }
+
+    fn grid(&self) -> (u32, u32) {
+        let layout = self.simulation.layout();
+        (layout.width, layout.height)
+    }
+
+    fn size(&self) -> (u32, u32) {
+        (self.config.width, self.config.height)
+    }
+
+    fn resize(&mut self, size: (u32, u32)) {
+        // Minimized: nothing is drawn until there's a window again
+        if size.0 == 0 || size.1 == 0 { return; }
+        (self.config.width, self.config.height) = size;
+        self.surface.configure(&self.device, &self.config);
+        self.view.resize(&self.simulation, view_size(size, self.grid()));
+        self.cells = cells_bind_group(&self.device, &self.cells_layout, &self.view, &self.unused);
+        self.queue.write_buffer(&self.view_camera, 0, bytemuck::bytes_of(&Camera::new(self.view.size())));
+    }
+
+    fn key_pressed(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Space | KeyCode::KeyP => self.paused = !self.paused,
+            KeyCode::KeyN if self.paused => self.step(1),
+            KeyCode::BracketRight => self.per_frame = (self.per_frame * 2).min(MAX_PER_FRAME),
+            KeyCode::BracketLeft => self.per_frame = (self.per_frame / 2).max(1),
+            KeyCode::Home => self.camera = Camera::new(self.grid()),
+            _ => {}
+        }
+    }
+
+    // Dragging moves the grid along with the cursor, as in the main window
+    fn cursor_moved(&mut self, position: PhysicalPosition<f64>) {
+        let last = self.cursor.replace(position);
+        let Some(last) = last.filter(|_| self.panning) else { return };
+        let grid = self.grid();
+        let cells_per_pixel = self.camera.view_cells(grid).0 / camera::viewport(self.size(), grid).2;
+        self.camera.pan((last.x - position.x) * cells_per_pixel, (last.y - position.y) * cells_per_pixel, grid);
+    }
+
+    // Zooms around the cursor, or the middle of the window without one
+    fn zoom(&mut self, lines: f64) {
+        let size = self.size();
+        let anchor = self.cursor.map_or((size.0 as f64 / 2.0, size.1 as f64 / 2.0), |cursor| (cursor.x, cursor.y));
+        self.camera.zoom_at(1.2f64.powf(lines), anchor, size, self.grid());
+    }
+
+    fn step(&mut self, generations: u32) {
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        self.simulation.encode_steps(&mut encoder, generations);
+        self.queue.submit(Some(encoder.finish()));
+        self.generation += generations as u64;
+    }
+
+    fn redraw(&mut self) -> Result<(), wgpu::SurfaceError> {
+        if !self.paused { self.step(self.per_frame); }
+        if self.title_shown.elapsed() >= TITLE_INTERVAL {
+            let layout = self.simulation.layout();
+            let status = if self.paused { " (paused)" } else { "" };
+            self.window.set_title(&format!("Rust Life | {} | {}x{} in {} bands | {} gens/frame | Gen: {}{}", self.adapter_name, layout.width, layout.height, layout.bands, self.per_frame, self.generation, status));
+            self.title_shown = Instant::now();
+        }
+
+        let frame = match self.surface.get_current_texture() {
+            Ok(frame) => frame,
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(&self.device, &self.config);
+                return Ok(());
+            }
+            Err(wgpu::SurfaceError::Timeout) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let target = frame.texture.create_view(&Default::default());
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        self.view.encode(&mut encoder, &self.simulation, self.camera.center, self.camera.zoom);
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target,
+                    resolve_target: None,
+                    ops: wgpu::Operations { load: wgpu::LoadOp::Clear(self.clear), store: wgpu::StoreOp::Store },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            let (left, top, width, height) = camera::viewport(self.size(), self.grid());
+            rpass.set_viewport(left as f32, top as f32, width as f32, height as f32, 0.0, 1.0);
+            let (x, y, width, height) = camera::pixels_covered((left, top, width, height), self.size());
+            rpass.set_scissor_rect(x, y, width, height);
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.cells, &[]);
+            rpass.set_bind_group(1, &self.camera_bind_group, &[]);
+            rpass.set_bind_group(2, &self.overlays, &[]);
+            rpass.draw(0..6, 0..1);
+        }
+        self.queue.submit(Some(encoder.finish()));
+        frame.present();
+        Ok(())
+    }
+}
+
+// The letterboxed viewport's size in whole pixels, one cell of the view each
+fn view_size(window: (u32, u32), grid: (u32, u32)) -> (u32, u32) {
+    let (_, _, width, height) = camera::viewport(window, grid);
+    ((width.round() as u32).max(1), (height.round() as u32).max(1))
+}
+
+// The view's cells as both generations, since there's no previous one to fade from
+fn cells_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, view: &BandView, unused: &[wgpu::Buffer; 3]) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry { binding: 0, resource: view.pixels().as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 1, resource: view.params_buffer().as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 2, resource: view.pixels().as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 3, resource: unused[0].as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 4, resource: unused[1].as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 5, resource: unused[2].as_entire_binding() },
+        ],
+        label: None,
+    })
+}
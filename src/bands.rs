@@ -0,0 +1,644 @@
+use std::cell::OnceCell;
+use std::ops::Range;
+use std::sync::Arc;
+use wgpu::util::DeviceExt;
+use crate::ant::AntRule;
+use crate::automaton::Automaton;
+use crate::boundary::Boundary;
+use crate::cyclic::CyclicRule;
+use crate::forest_fire::ForestFire;
+use crate::gpu::{self, Params, Pipelines};
+use crate::gray_scott::GrayScott;
+use crate::lenia::Lenia;
+use crate::ltl::LtlRule;
+use crate::rps;
+use crate::rule::Rule;
+use crate::topology::Topology;
+use crate::workgroup::WorkgroupSize;
+use crate::{Simulation, WALL};
+
+/// How many rows away a cell's neighbourhood reaches, which a band's halo has to cover.
+pub fn reach(automaton: Automaton, ltl: LtlRule, lenia: Lenia) -> u32 {
+    match automaton {
+        Automaton::LargerThanLife => ltl.radius,
+        Automaton::Lenia => lenia.radius,
+        _ => 1,
+    }
+}
+
+// The most one storage buffer can hold and be bound as
+fn max_buffer(limits: &wgpu::Limits) -> u64 {
+    limits.max_buffer_size.min(limits.max_storage_buffer_binding_size as u64)
+}
+
+/// A grid cut into horizontal bands for adapters whose buffers can't hold all of it. Each band's buffers
+/// hold its own rows between `halo` rows above and below, copied in from the neighbouring bands before
+/// every generation, so the unchanged step computes the band's rows as if the grid were whole.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BandLayout {
+    pub width: u32,
+    pub height: u32,
+    pub bands: u32,
+    // Rows of every band but the last, which takes what's left. Even, like the halo, so hex rows keep
+    // their parity in the bands' buffers
+    rows: u32,
+    pub halo: u32,
+}
+
+/// Filling a band's halo rows before a generation; rows are counted in the bands' buffers, halo included.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Halo {
+    /// `rows` rows of band `from` from row `source` into band `to` from row `target`
+    Copy { from: u32, source: u32, to: u32, target: u32, rows: u32 },
+    /// Row `source` of `band` repeated into `rows` rows from `target`, past a mirror edge
+    Repeat { band: u32, source: u32, target: u32, rows: u32 },
+    /// Walls past a dead edge, which every automaton counts like cells off the grid
+    Walls { band: u32, target: u32, rows: u32 },
+}
+
+impl BandLayout {
+    /// The grid in one buffer, without halos.
+    pub fn whole(width: u32, height: u32) -> BandLayout {
+        BandLayout { width, height, bands: 1, rows: height, halo: 0 }
+    }
+
+    /// `bands` bands with halos for neighbourhoods `reach` rows high; one band is `whole`.
+    pub fn new(width: u32, height: u32, bands: u32, reach: u32) -> Result<BandLayout, String> {
+        if bands <= 1 { return Ok(BandLayout::whole(width, height)); }
+        let halo = reach.div_ceil(2) * 2;
+        let rows = height.div_ceil(bands).next_multiple_of(2);
+        // Every band has to be at least a halo high, so a halo comes from one neighbour
+        match height.checked_sub(rows * (bands - 1)) {
+            Some(last) if last >= halo => Ok(BandLayout { width, height, bands, rows, halo }),
+            _ => Err(format!("a grid {} rows high can't be cut into {} bands of at least {} rows", height, bands, halo)),
+        }
+    }
+
+    /// The fewest bands whose buffers `limits` allow, one if the grid fits whole.
+    pub fn fitting(width: u32, height: u32, reach: u32, limits: &wgpu::Limits) -> Result<BandLayout, String> {
+        let allowed = max_buffer(limits);
+        // No fewer than the cells alone need; the halos may take a few more
+        let fewest = (width as u64 * height as u64 * 4).div_ceil(allowed).max(1) as u32;
+        (fewest..=height.max(1)).filter_map(|bands| BandLayout::new(width, height, bands, reach).ok())
+            .find(|layout| layout.buffer_size(0) <= allowed)
+            .ok_or_else(|| format!("a {}x{} grid doesn't fit this adapter's {} MB buffers in any number of bands", width, height, allowed >> 20))
+    }
+
+    /// Rows of the grid in `band`.
+    pub fn rows(&self, band: u32) -> Range<u32> {
+        let start = band * self.rows;
+        start..(start + self.rows).min(self.height)
+    }
+
+    /// Rows of `band`'s buffers, halos included.
+    pub fn buffer_rows(&self, band: u32) -> u32 {
+        self.rows(band).len() as u32 + 2 * self.halo
+    }
+
+    /// Bytes in each of `band`'s two buffers; the first band's are the largest.
+    pub fn buffer_size(&self, band: u32) -> u64 {
+        self.width as u64 * self.buffer_rows(band) as u64 * 4
+    }
+
+    /// Bytes in every band's two buffers together.
+    pub fn total_size(&self) -> u64 {
+        (0..self.bands).map(|band| 2 * self.buffer_size(band)).sum()
+    }
+
+    /// Whether the bands' buffers are within `limits`, with the bands that would be otherwise.
+    pub fn check(&self, limits: &wgpu::Limits) -> Result<(), String> {
+        let allowed = max_buffer(limits);
+        let needed = self.buffer_size(0);
+        if needed <= allowed { return Ok(()); }
+        let split = if self.bands == 1 { String::new() } else { format!(" in {} bands", self.bands) };
+        let hint = match BandLayout::fitting(self.width, self.height, self.halo, limits) {
+            Ok(layout) => format!("; --bands {} fits it", layout.bands),
+            Err(e) => format!("; {}", e),
+        };
+        Err(format!(
+            "a {}x{} grid{} needs {} MB per buffer ({} MB in all) but this adapter allows at most {} MB per buffer{}",
+            self.width, self.height, split, needed >> 20, self.total_size() >> 20, allowed >> 20, hint
+        ))
+    }
+
+    /// The copies that fill every band's halos from the rows next to it, across the grid's edges as
+    /// `boundary` says. None for a whole grid.
+    pub fn halos(&self, boundary: Boundary) -> Vec<Halo> {
+        if self.bands == 1 { return Vec::new(); }
+        let (last, halo) = (self.bands - 1, self.halo);
+        // A band's last `halo` rows start at its own row count in its buffer
+        let rows = |band: u32| self.rows(band).len() as u32;
+        let mut halos = Vec::new();
+        for band in 0..self.bands {
+            let below = halo + rows(band);
+            halos.push(match (band, boundary) {
+                (0, Boundary::Wrap) => Halo::Copy { from: last, source: rows(last), to: 0, target: 0, rows: halo },
+                (0, Boundary::Dead) => Halo::Walls { band: 0, target: 0, rows: halo },
+                (0, Boundary::Mirror) => Halo::Repeat { band: 0, source: halo, target: 0, rows: halo },
+                _ => Halo::Copy { from: band - 1, source: rows(band - 1), to: band, target: 0, rows: halo },
+            });
+            halos.push(match (band == last, boundary) {
+                (true, Boundary::Wrap) => Halo::Copy { from: 0, source: halo, to: band, target: below, rows: halo },
+                (true, Boundary::Dead) => Halo::Walls { band, target: below, rows: halo },
+                (true, Boundary::Mirror) => Halo::Repeat { band, source: below - 1, target: below, rows: halo },
+                (false, _) => Halo::Copy { from: band + 1, source: halo, to: band, target: below, rows: halo },
+            });
+        }
+        halos
+    }
+}
+
+/// `--bands`: `Some(n)` cuts the grid into n bands, `None` into the fewest that fit the adapter. Either
+/// way the buffers are checked against its limits, so a grid too big is an error rather than a wgpu panic.
+pub fn plan(bands: Option<u32>, width: u32, height: u32, reach: u32, limits: &wgpu::Limits) -> Result<BandLayout, String> {
+    let layout = match bands {
+        Some(bands) => BandLayout::new(width, height, bands, reach)?,
+        None => BandLayout::fitting(width, height, reach, limits)?,
+    };
+    layout.check(limits)?;
+    Ok(layout)
+}
+
+/// `GpuSimulation` for grids cut into bands (see `BandLayout`): every band has its own pair of buffers,
+/// stepped one dispatch per band after the halo copies. Every cell is computed, without chunk skipping or
+/// history, and forest fires are refused since their dice are hashed with the cell's index in its buffer.
+pub struct BandedSimulation {
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    layout: BandLayout,
+    pipelines: Pipelines,
+    pipeline_layout: wgpu::PipelineLayout,
+    workgroup: WorkgroupSize,
+    buffers: Vec<[wgpu::Buffer; 2]>,
+    params_buffers: Vec<wgpu::Buffer>,
+    bind_groups: Vec<[wgpu::BindGroup; 2]>,
+    halos: Vec<Halo>,
+    // Rows of walls for dead edges, and one row on its way through a mirror edge
+    walls: wgpu::Buffer,
+    scratch: wgpu::Buffer,
+    rule: Rule,
+    boundary: Boundary,
+    topology: Topology,
+    automaton: Automaton,
+    ltl: LtlRule,
+    gray_scott: GrayScott,
+    cyclic: CyclicRule,
+    rps_threshold: u32,
+    lenia: Lenia,
+    parity: usize,
+    mirror: OnceCell<Vec<u32>>,
+}
+
+impl BandedSimulation {
+    /// `cells` is the whole grid; a layout of one band belongs to `GpuSimulation`.
+    pub fn new(device: Arc<wgpu::Device>, queue: Arc<wgpu::Queue>, layout: BandLayout, rule: Rule, boundary: Boundary, automaton: Automaton, cells: &[u32]) -> Result<BandedSimulation, String> {
+        if layout.bands < 2 { return Err("a grid in one band steps with the usual GPU simulation".to_string()); }
+        if automaton == Automaton::ForestFire { return Err("forest fires can't be cut into bands: their dice are hashed with each cell's index".to_string()); }
+        layout.check(&device.limits())?;
+        gpu::check_bindings(&device.limits())?;
+
+        let width = layout.width as usize;
+        let usage = wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC;
+        let buffers: Vec<[wgpu::Buffer; 2]> = (0..layout.bands).map(|band| {
+            let pair = ["Band A", "Band B"].map(|label| device.create_buffer(&wgpu::BufferDescriptor { label: Some(label), size: layout.buffer_size(band), usage, mapped_at_creation: false }));
+            // The halos are filled by the first generation's copies
+            let rows = layout.rows(band);
+            queue.write_buffer(&pair[0], layout.halo as u64 * width as u64 * 4, bytemuck::cast_slice(&cells[rows.start as usize * width..rows.end as usize * width]));
+            pair
+        }).collect();
+        let params_buffers: Vec<wgpu::Buffer> = (0..layout.bands).map(|band| device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Band Params"),
+            contents: bytemuck::bytes_of(&Params::new(layout.width, layout.buffer_rows(band), rule, boundary, Topology::Square, automaton, LtlRule::BOSCO, GrayScott::CORAL, CyclicRule::SPIRALS, rps::THRESHOLD, Lenia::ORBIUM, ForestFire::DROSSEL_SCHWABL)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        })).collect();
+        let walls = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Band Walls"), contents: bytemuck::cast_slice(&vec![WALL; width * layout.halo as usize]), usage: wgpu::BufferUsages::COPY_SRC,
+        });
+        let scratch = device.create_buffer(&wgpu::BufferDescriptor { label: Some("Band Scratch Row"), size: width as u64 * 4, usage: wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST, mapped_at_creation: false });
+
+        // The step's history, chunk, clock, rule map and concentration bindings go unused over whole bands but still need
+        // buffers, each its own so no buffer is bound read-only and writable at once; a zeroed rule map is none
+        let unused = |usage| device.create_buffer(&wgpu::BufferDescriptor { label: Some("Band Unused"), size: 16, usage, mapped_at_creation: false });
+        let storage = wgpu::BufferUsages::STORAGE;
+        let (history, changed, stale, list) = (unused(storage), unused(storage), unused(storage), unused(storage));
+        let chunk_params = unused(wgpu::BufferUsages::UNIFORM);
+        let clocks = [unused(storage), unused(storage)];
+        let regions = unused(storage);
+        // Gray-Scott steps on the quantised cells, the halos only carry those
+        let concentrations = gpu::concentration_buffers(&device, 1);
+        let bind_group_layout = gpu::create_bind_group_layout(&device);
+        let bind_groups: Vec<[wgpu::BindGroup; 2]> = (0..layout.bands as usize).map(|band| [0, 1].map(|n| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: buffers[band][n].as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: buffers[band][n ^ 1].as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: params_buffers[band].as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: history.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 4, resource: changed.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 5, resource: stale.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 6, resource: list.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 7, resource: chunk_params.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 8, resource: clocks[n].as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 9, resource: clocks[n ^ 1].as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 10, resource: regions.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 11, resource: concentrations[n].as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 12, resource: concentrations[n ^ 1].as_entire_binding() },
+            ],
+            label: None,
+        }))).collect();
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&bind_group_layout], ..Default::default() });
+        let workgroup = WorkgroupSize::DEFAULT;
+        let pipelines = gpu::create_pipelines(&device, &pipeline_layout, workgroup, None);
+
+        Ok(BandedSimulation {
+            device, queue, layout, pipelines, pipeline_layout, workgroup, buffers, params_buffers, bind_groups, halos: layout.halos(boundary), walls, scratch,
+            rule, boundary, topology: Topology::Square, automaton, ltl: LtlRule::BOSCO, gray_scott: GrayScott::CORAL, cyclic: CyclicRule::SPIRALS, rps_threshold: rps::THRESHOLD, lenia: Lenia::ORBIUM,
+            parity: 0, mirror: OnceCell::new(),
+        })
+    }
+
+    pub fn layout(&self) -> BandLayout {
+        self.layout
+    }
+
+    /// Recompiles the step for another workgroup size; the grid is untouched.
+    pub fn set_workgroup_size(&mut self, size: WorkgroupSize) -> Result<(), String> {
+        size.check(&self.device.limits())?;
+        self.pipelines = gpu::create_pipelines(&self.device, &self.pipeline_layout, size, None);
+        self.workgroup = size;
+        Ok(())
+    }
+
+    /// Records `generations` steps: each fills the halos with copies, which can't go inside a compute
+    /// pass, then steps every band in one pass.
+    pub fn encode_steps(&mut self, encoder: &mut wgpu::CommandEncoder, generations: u32) {
+        assert!(reach(self.automaton, self.ltl, self.lenia) <= self.layout.halo, "the neighbourhood reaches past the bands' halos");
+        for _ in 0..generations {
+            for &halo in &self.halos {
+                self.encode_halo(encoder, halo);
+            }
+            {
+                let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("Life Bands"), timestamp_writes: None });
+                cpass.set_pipeline(&self.pipelines.step);
+                for (band, bind_groups) in self.bind_groups.iter().enumerate() {
+                    let (groups_x, groups_y) = self.workgroup.groups(self.layout.width, self.layout.buffer_rows(band as u32));
+                    cpass.set_bind_group(0, &bind_groups[self.parity], &[]);
+                    cpass.dispatch_workgroups(groups_x, groups_y, 1);
+                }
+            }
+            self.parity ^= 1;
+        }
+        self.mirror.take();
+    }
+
+    fn encode_halo(&self, encoder: &mut wgpu::CommandEncoder, halo: Halo) {
+        let row = self.layout.width as u64 * 4;
+        let buffer = |band: u32| &self.buffers[band as usize][self.parity];
+        match halo {
+            Halo::Copy { from, source, to, target, rows } => {
+                encoder.copy_buffer_to_buffer(buffer(from), source as u64 * row, buffer(to), target as u64 * row, rows as u64 * row);
+            }
+            // A buffer can't be copied into itself, so the edge row goes through the scratch row
+            Halo::Repeat { band, source, target, rows } => {
+                encoder.copy_buffer_to_buffer(buffer(band), source as u64 * row, &self.scratch, 0, row);
+                for n in 0..rows as u64 {
+                    encoder.copy_buffer_to_buffer(&self.scratch, 0, buffer(band), (target as u64 + n) * row, row);
+                }
+            }
+            Halo::Walls { band, target, rows } => {
+                encoder.copy_buffer_to_buffer(&self.walls, 0, buffer(band), target as u64 * row, rows as u64 * row);
+            }
+        }
+    }
+
+    /// Each band's rows of the latest generation as (buffer, byte offset, bytes), top to bottom.
+    pub fn interiors(&self) -> impl Iterator<Item = (&wgpu::Buffer, u64, u64)> {
+        let row = self.layout.width as u64 * 4;
+        self.buffers.iter().enumerate().map(move |(band, pair)| {
+            (&pair[self.parity], self.layout.halo as u64 * row, self.layout.rows(band as u32).len() as u64 * row)
+        })
+    }
+
+    fn write_params(&self) {
+        for (band, buffer) in self.params_buffers.iter().enumerate() {
+            let params = Params::new(self.layout.width, self.layout.buffer_rows(band as u32), self.rule, self.boundary, self.topology, self.automaton, self.ltl, self.gray_scott, self.cyclic, self.rps_threshold, self.lenia, ForestFire::DROSSEL_SCHWABL);
+            self.queue.write_buffer(buffer, 0, bytemuck::bytes_of(&params));
+        }
+    }
+}
+
+impl Simulation for BandedSimulation {
+    fn step(&mut self) {
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        self.encode_steps(&mut encoder, 1);
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    // Blocks on one readback per band the first time it's called after the grid changes
+    fn cells(&self) -> &[u32] {
+        self.mirror.get_or_init(|| self.interiors().flat_map(|(buffer, offset, size)| {
+            let len = (size / 4) as usize;
+            gpu::read_ranges(&self.device, &self.queue, buffer, len, [((offset / 4) as usize, 0, len)])
+        }).collect())
+    }
+
+    fn set_cells(&mut self, offset: usize, cells: &[u32]) {
+        let width = self.layout.width as usize;
+        let end = offset + cells.len();
+        for band in 0..self.layout.bands {
+            let rows = self.layout.rows(band);
+            let (start, stop) = (offset.max(rows.start as usize * width), end.min(rows.end as usize * width));
+            if start >= stop { continue; }
+            let into = ((self.layout.halo as usize * width + start - rows.start as usize * width) * 4) as u64;
+            self.queue.write_buffer(&self.buffers[band as usize][self.parity], into, bytemuck::cast_slice(&cells[start - offset..stop - offset]));
+        }
+        if let Some(mirror) = self.mirror.get_mut() {
+            mirror[offset..end].copy_from_slice(cells);
+        }
+    }
+
+    fn width(&self) -> u32 {
+        self.layout.width
+    }
+
+    fn height(&self) -> u32 {
+        self.layout.height
+    }
+
+    fn set_rule(&mut self, rule: Rule) {
+        self.rule = rule;
+        self.write_params();
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) {
+        self.boundary = boundary;
+        self.halos = self.layout.halos(boundary);
+        self.write_params();
+    }
+
+    fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+        self.write_params();
+    }
+
+    fn set_automaton(&mut self, automaton: Automaton) {
+        assert!(automaton != Automaton::ForestFire, "forest fires can't be cut into bands");
+        self.automaton = automaton;
+        self.write_params();
+    }
+
+    fn set_ltl_rule(&mut self, ltl: LtlRule) {
+        self.ltl = ltl;
+        self.write_params();
+    }
+
+    fn set_gray_scott(&mut self, params: GrayScott) {
+        self.gray_scott = params;
+        self.write_params();
+    }
+
+    // Ants walk on the CPU, the step leaves their cells alone
+    fn set_ant_rule(&mut self, _rule: AntRule) {}
+
+    fn set_cyclic_rule(&mut self, rule: CyclicRule) {
+        self.cyclic = rule;
+        self.write_params();
+    }
+
+    fn set_rps_threshold(&mut self, threshold: u32) {
+        self.rps_threshold = threshold;
+        self.write_params();
+    }
+
+    fn set_lenia(&mut self, params: Lenia) {
+        self.lenia = params;
+        self.write_params();
+    }
+
+    // Refused in `new`
+    fn set_forest_fire(&mut self, _params: ForestFire) {}
+}
+
+// Shared with band_view.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ViewParams {
+    center: [f32; 2],
+    zoom: f32,
+    topology: u32,
+    grid_width: u32,
+    grid_height: u32,
+    first_row: u32,
+    rows: u32,
+    halo: u32,
+    width: u32,
+    height: u32,
+    _pad: u32,
+}
+
+/// What a window shows of a `BandedSimulation`: one cell per pixel, gathered through the camera from
+/// whichever band holds each pixel's row into a buffer the size of the view. render.wgsl draws that like a
+/// grid of its own (see `params_buffer`), since the grid itself is in no one buffer it could bind.
+pub struct BandView {
+    pipeline: wgpu::ComputePipeline,
+    layout: wgpu::BindGroupLayout,
+    size: (u32, u32),
+    pixels: wgpu::Buffer,
+    params: wgpu::Buffer,
+    // One per band, holding where its rows are
+    views: Vec<wgpu::Buffer>,
+    // Each band's buffer n into the pixels
+    bind_groups: Vec<[wgpu::BindGroup; 2]>,
+}
+
+impl BandView {
+    /// `size` is the view's in pixels.
+    pub fn new(simulation: &BandedSimulation, size: (u32, u32)) -> BandView {
+        let device = &simulation.device;
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry { binding: 0, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: true }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 1, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: false }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 2, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            ],
+            label: None,
+        });
+        let shader = device.create_shader_module(wgpu::include_wgsl!("band_view.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&layout], ..Default::default() });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Band View"), layout: Some(&pipeline_layout), module: &shader, entry_point: "main", compilation_options: Default::default(), cache: None,
+        });
+        let views: Vec<wgpu::Buffer> = (0..simulation.layout.bands).map(|_| device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Band View"), size: std::mem::size_of::<ViewParams>() as u64, usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST, mapped_at_creation: false,
+        })).collect();
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Band View Params"), size: std::mem::size_of::<Params>() as u64, usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST, mapped_at_creation: false,
+        });
+        let (pixels, bind_groups) = view_targets(simulation, &layout, &views, size);
+        BandView { pipeline, layout, size, pixels, params, views, bind_groups }
+    }
+
+    /// Rebuilds the pixels for a view of another size; bind groups holding `pixels()` need recreating.
+    pub fn resize(&mut self, simulation: &BandedSimulation, size: (u32, u32)) {
+        if size == self.size { return; }
+        self.size = size;
+        (self.pixels, self.bind_groups) = view_targets(simulation, &self.layout, &self.views, size);
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Records gathering the latest generation as a camera centred on `center` (in cells) at `zoom` (1
+    /// shows the whole grid) sees it, and the parameters to draw it with.
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, simulation: &BandedSimulation, center: [f32; 2], zoom: f32) {
+        let layout = simulation.layout;
+        for (band, buffer) in self.views.iter().enumerate() {
+            let rows = layout.rows(band as u32);
+            let view = ViewParams {
+                center, zoom, topology: simulation.topology as u32, grid_width: layout.width, grid_height: layout.height,
+                first_row: rows.start, rows: rows.len() as u32, halo: layout.halo, width: self.size.0, height: self.size.1, _pad: 0,
+            };
+            simulation.queue.write_buffer(buffer, 0, bytemuck::bytes_of(&view));
+        }
+        // The pixels are already staggered for a hex grid, so they're drawn as squares
+        let params = Params::new(self.size.0, self.size.1, simulation.rule, simulation.boundary, Topology::Square, simulation.automaton, simulation.ltl, simulation.gray_scott, simulation.cyclic, simulation.rps_threshold, simulation.lenia, ForestFire::DROSSEL_SCHWABL);
+        simulation.queue.write_buffer(&self.params, 0, bytemuck::bytes_of(&params));
+
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("Band View"), timestamp_writes: None });
+        cpass.set_pipeline(&self.pipeline);
+        for bind_groups in &self.bind_groups {
+            cpass.set_bind_group(0, &bind_groups[simulation.parity], &[]);
+            cpass.dispatch_workgroups(self.size.0.div_ceil(8), self.size.1.div_ceil(8), 1);
+        }
+    }
+
+    /// The view's cells, row-major, for render.wgsl's cell bindings.
+    pub fn pixels(&self) -> &wgpu::Buffer {
+        &self.pixels
+    }
+
+    /// The grid's parameters with the view's size in place of its own, for render.wgsl's `params`.
+    pub fn params_buffer(&self) -> &wgpu::Buffer {
+        &self.params
+    }
+}
+
+fn view_targets(simulation: &BandedSimulation, layout: &wgpu::BindGroupLayout, views: &[wgpu::Buffer], size: (u32, u32)) -> (wgpu::Buffer, Vec<[wgpu::BindGroup; 2]>) {
+    let device = &simulation.device;
+    let pixels = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Band View Pixels"),
+        size: (size.0.max(1) as u64 * size.1.max(1) as u64) * 4,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let bind_groups = simulation.buffers.iter().zip(views).map(|(pair, view)| pair.each_ref().map(|band| device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry { binding: 0, resource: band.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 1, resource: pixels.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 2, resource: view.as_entire_binding() },
+        ],
+        label: None,
+    }))).collect();
+    (pixels, bind_groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::CpuSimulation;
+
+    #[test]
+    fn bands_fit_the_limits() {
+        let layout = BandLayout::new(100, 1000, 3, 1).unwrap();
+        assert_eq!((layout.halo, layout.rows(0), layout.rows(1), layout.rows(2)), (2, 0..334, 334..668, 668..1000));
+        assert_eq!(layout.buffer_size(0), 100 * 338 * 4);
+        assert_eq!(layout.total_size(), 2 * 100 * (338 + 338 + 336) * 4);
+        assert!(BandLayout::new(100, 10, 4, 1).is_err());
+
+        let limits = |bytes| wgpu::Limits { max_buffer_size: bytes, max_storage_buffer_binding_size: bytes as u32, ..Default::default() };
+        assert_eq!(BandLayout::fitting(1000, 1000, 1, &limits(4_000_000)), Ok(BandLayout::whole(1000, 1000)));
+        assert_eq!(BandLayout::fitting(1000, 1000, 1, &limits(1_100_000)).unwrap().bands, 4);
+        // Four bands' halos tip them over
+        assert_eq!(BandLayout::fitting(1000, 1000, 1, &limits(1_000_000)).unwrap().bands, 5);
+        let error = plan(Some(2), 1000, 1000, 1, &limits(1_000_000)).unwrap_err();
+        assert!(error.contains("in 2 bands") && error.contains("--bands 5"), "{}", error);
+        assert_eq!(plan(None, 1000, 1000, 5, &limits(1_000_000)).unwrap().halo, 6);
+    }
+
+    // The halos applied to host copies of the bands, each stepped on the CPU like a buffer on the GPU
+    fn step_banded(layout: BandLayout, boundary: Boundary, cells: &[u32], generations: usize) -> Vec<u32> {
+        let width = layout.width as usize;
+        let mut bands: Vec<Vec<u32>> = (0..layout.bands).map(|band| {
+            let rows = layout.rows(band);
+            let mut buffer = vec![0; width * layout.buffer_rows(band) as usize];
+            buffer[layout.halo as usize * width..][..rows.len() * width].copy_from_slice(&cells[rows.start as usize * width..rows.end as usize * width]);
+            buffer
+        }).collect();
+        let span = |row: u32, rows: u32| row as usize * width..(row + rows) as usize * width;
+        for _ in 0..generations {
+            for halo in layout.halos(boundary) {
+                match halo {
+                    Halo::Copy { from, source, to, target, rows } => {
+                        let copied = bands[from as usize][span(source, rows)].to_vec();
+                        bands[to as usize][span(target, rows)].copy_from_slice(&copied);
+                    }
+                    Halo::Repeat { band, source, target, rows } => {
+                        let row = bands[band as usize][span(source, 1)].to_vec();
+                        for n in 0..rows { bands[band as usize][span(target + n, 1)].copy_from_slice(&row); }
+                    }
+                    Halo::Walls { band, target, rows } => bands[band as usize][span(target, rows)].fill(WALL),
+                }
+            }
+            for buffer in &mut bands {
+                let mut cpu = CpuSimulation::new(layout.width, (buffer.len() / width) as u32, Rule::CONWAY, boundary, Automaton::Life, buffer.clone());
+                cpu.step();
+                buffer.copy_from_slice(cpu.cells());
+            }
+        }
+        (0..layout.bands).flat_map(|band| bands[band as usize][span(layout.halo, layout.rows(band).len() as u32)].to_vec()).collect()
+    }
+
+    #[test]
+    fn banded_grids_step_like_whole_ones() {
+        let (width, height) = (24, 30);
+        let cells = crate::random_grid(width * height, 11, 0.35);
+        for boundary in [Boundary::Wrap, Boundary::Dead, Boundary::Mirror] {
+            let mut whole = CpuSimulation::new(width as u32, height as u32, Rule::CONWAY, boundary, Automaton::Life, cells.clone());
+            for _ in 0..12 { whole.step(); }
+            let layout = BandLayout::new(width as u32, height as u32, 3, 1).unwrap();
+            let banded = step_banded(layout, boundary, &cells, 12);
+            assert_eq!(banded, whole.cells(), "{} boundary", boundary);
+        }
+    }
+
+    #[test]
+    fn views_gather_across_bands() {
+        let Some(adapter) = pollster::block_on(gpu::request_adapter(&wgpu::Instance::default(), None)) else { return };
+        let (device, queue) = pollster::block_on(gpu::request_device(&adapter)).unwrap();
+        let (device, queue) = (Arc::new(device), Arc::new(queue));
+        let (width, height) = (64, 48);
+        let cells = crate::random_grid(width * height, 3, 0.35);
+        let layout = BandLayout::new(width as u32, height as u32, 3, 1).unwrap();
+        let mut simulation = BandedSimulation::new(device.clone(), queue.clone(), layout, Rule::CONWAY, Boundary::Wrap, Automaton::Life, &cells).unwrap();
+        let mut encoder = device.create_command_encoder(&Default::default());
+        simulation.encode_steps(&mut encoder, 5);
+        // Twice zoomed, so pixels are cells three quarters of the way across; centred near the bottom so the
+        // view runs through the last band and across the seam into the first
+        let (view_size, center) = ((32, 24), [10.25, 40.25]);
+        let view = BandView::new(&simulation, view_size);
+        view.encode(&mut encoder, &simulation, center, 2.0);
+        queue.submit(Some(encoder.finish()));
+        let pixels = gpu::read_buffer(&device, &queue, view.pixels());
+
+        let latest = step_banded(layout, Boundary::Wrap, &cells, 5);
+        let expected: Vec<u32> = (0..view_size.1).flat_map(|y| (0..view_size.0).map(move |x| (x, y))).map(|(x, y)| {
+            let column = (center[0] as f64 + x as f64 + 0.5 - 16.0).rem_euclid(width as f64) as usize;
+            let row = (center[1] as f64 + y as f64 + 0.5 - 12.0).rem_euclid(height as f64) as usize;
+            latest[row * width + column]
+        }).collect();
+        assert_eq!(pixels, expected);
+    }
+}
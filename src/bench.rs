@@ -0,0 +1,221 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use rust_gpu_life::{ant, automaton::{Automaton, GridAutomaton, Stepping}, changes::ChangeCounter, cpu::step_grid, cyclic, forest_fire, gray_scott, lenia, ltl, population::{PopulationCounter, Reduction}, rps, rule::Rule, snapshot::Snapshot, timer::GpuTimer, CpuSimulation, GpuSimulation, Simulation, TextureSimulation};
+use crate::adapter;
+use crate::cli::Args;
+use crate::tune;
+
+// A whole-grid upload per sample is slow at big sizes, and a hundred already averages out well
+const UPLOAD_SAMPLES: u32 = 100;
+// Submits timed per batch size
+const BATCH_SAMPLES: u32 = 20;
+
+// CPU time spent encoding and submitting one batch, and the wait for the GPU to finish it
+struct BatchTimes {
+    encode: Duration,
+    submit: Duration,
+    wait: Duration,
+}
+
+// Headless run of both backends: no window, no surface, just the compute loop
+pub fn run(args: &Args, initial: Snapshot) -> Result<(), String> {
+    if args.automaton == Automaton::LangtonsAnt {
+        return Err("Langton's ants only walk on the CPU, so there's nothing to bench on the GPU (bench --cpu times them)".to_string());
+    }
+    let instance = adapter::create_instance(args.backends);
+    let adapter = pollster::block_on(adapter::select(&instance, None, args.adapter.as_ref()))?;
+    let info = adapter.get_info();
+    let (device, queue) = pollster::block_on(rust_gpu_life::gpu::request_device(&adapter))?;
+    let (device, queue) = (Arc::new(device), Arc::new(queue));
+    println!("Adapter: {} ({:?}, driver {} {})", info.name, info.backend, info.driver, info.driver_info);
+    let (width, height, rule) = (initial.width, initial.height, initial.rule);
+    println!("Grid: {}x{}, rule {}, {} boundary, {} steps", width, height, rule, args.boundary, args.steps);
+
+    let mut gpu = GpuSimulation::new(device.clone(), queue.clone(), width, height, rule, args.boundary, args.automaton, &initial.cells, None)?;
+    gpu.set_workgroup_size(tune::choose(&device, &queue, args, &initial, &info.name)?)?;
+    gpu.set_topology(args.topology);
+    gpu.set_ltl_rule(args.ltl);
+    gpu.set_gray_scott(args.gray_scott);
+    gpu.set_cyclic_rule(args.cyclic);
+    gpu.set_rps_threshold(args.rps_threshold);
+    gpu.set_lenia(args.lenia);
+    gpu.set_forest_fire(args.forest_fire);
+    if !args.chunk_skipping { gpu.set_chunk_skipping(false)?; }
+    // Wait on every step so each sample covers the GPU work, not just the submit
+    let gpu_times = time_steps(&mut gpu, args.steps, || { device.poll(wgpu::Maintain::Wait); });
+    report("gpu", (width, height), rule, Some(&info), &gpu_times);
+    // Many generations per submit, as the window does at high speeds
+    for &batch in &args.batches {
+        report_batches(batch, (width, height), &info, &time_batches(&device, &queue, &mut gpu, batch));
+    }
+
+    // Both ways of summing the counts the window reads back every frame; --reduction picks one there
+    let cell_count = (width * height) as usize;
+    for reduction in Reduction::ALL {
+        let mut population = PopulationCounter::new(&device, &gpu.buffers()[0], &gpu.buffers()[1], reduction);
+        let times = time_counts(&device, &queue, |encoder, writes| population.encode(encoder, gpu.parity(), cell_count, writes));
+        report(&format!("population-{}", reduction), (width, height), rule, Some(&info), &times);
+        let mut changes = ChangeCounter::new(&device, &gpu, reduction);
+        let times = time_counts(&device, &queue, |encoder, writes| changes.encode(&queue, encoder, gpu.parity(), cell_count, (0, 0), writes));
+        report(&format!("changes-{}", reduction), (width, height), rule, Some(&info), &times);
+    }
+
+    // The same rules on R32Uint textures, to see whether the renderer should move over to them
+    let mut texture = match TextureSimulation::new(device.clone(), queue.clone(), width, height, rule, args.boundary, args.automaton, &initial.cells) {
+        Ok(texture) => Some(texture),
+        Err(e) => { println!("GPU-TEXTURE: skipped, {}", e); None }
+    };
+    if let Some(texture) = &mut texture {
+        texture.set_topology(args.topology);
+        texture.set_ltl_rule(args.ltl);
+        texture.set_gray_scott(args.gray_scott);
+        texture.set_cyclic_rule(args.cyclic);
+        texture.set_rps_threshold(args.rps_threshold);
+        texture.set_lenia(args.lenia);
+        texture.set_forest_fire(args.forest_fire);
+        let texture_times = time_steps(texture, args.steps, || { device.poll(wgpu::Maintain::Wait); });
+        report("gpu-texture", (width, height), rule, Some(&info), &texture_times);
+    }
+
+    // CPU mode uploads the grid every frame; the submit flushes the queued write
+    let flush = || { queue.submit(None); device.poll(wgpu::Maintain::Wait); };
+    report("cpu-upload-buffer", (width, height), rule, Some(&info), &time_uploads(&mut gpu, &initial.cells, flush));
+    if let Some(texture) = &mut texture {
+        report("cpu-upload-texture", (width, height), rule, Some(&info), &time_uploads(texture, &initial.cells, flush));
+    }
+
+    let mut cpu = args.cpu_backend.create(width, height, rule, args.boundary, args.automaton, initial.cells);
+    cpu.set_topology(args.topology);
+    cpu.set_ltl_rule(args.ltl);
+    cpu.set_gray_scott(args.gray_scott);
+    cpu.set_cyclic_rule(args.cyclic);
+    cpu.set_rps_threshold(args.rps_threshold);
+    cpu.set_lenia(args.lenia);
+    cpu.set_forest_fire(args.forest_fire);
+    cpu.set_ant_rule(args.ant_rule);
+    let cpu_times = time_steps(cpu.as_mut(), args.steps, || {});
+    report(&format!("cpu-{}", args.cpu_backend), (width, height), rule, Some(&info), &cpu_times);
+    Ok(())
+}
+
+// No GPU needed: the naive CPU step allocating a fresh grid every generation, as it used to, against
+// the in-place one `CpuSimulation` takes now
+pub fn run_cpu(args: &Args, initial: Snapshot) -> Result<(), String> {
+    let (width, height, rule) = (initial.width, initial.height, initial.rule);
+    println!("Grid: {}x{}, rule {}, {} boundary, {} steps", width, height, rule, args.boundary, args.steps);
+
+    let mut cells = initial.cells.clone();
+    let mut ants = ant::find(&cells, width as usize);
+    let allocating_times: Vec<Duration> = (0..args.steps).map(|step| {
+        let start = Instant::now();
+        cells = match args.automaton.stepping() {
+            Stepping::Cell(automaton) => step_grid(&cells, width as usize, rule, args.boundary, args.topology, automaton),
+            Stepping::Grid(GridAutomaton::LargerThanLife) => ltl::step_grid(&cells, width as usize, args.ltl, args.boundary),
+            Stepping::Grid(GridAutomaton::GrayScott) => gray_scott::step_grid(&cells, width as usize, args.gray_scott, args.boundary),
+            Stepping::Grid(GridAutomaton::Cyclic) => cyclic::step_grid(&cells, width as usize, args.cyclic, args.boundary),
+            Stepping::Grid(GridAutomaton::Lenia) => lenia::step_grid(&cells, width as usize, args.lenia, args.boundary),
+            Stepping::Grid(GridAutomaton::ForestFire) => forest_fire::step_grid(&cells, width as usize, args.forest_fire, args.boundary, initial.generation + step as u64),
+            Stepping::Grid(GridAutomaton::RockPaperScissors) => rps::step_grid(&cells, width as usize, args.rps_threshold, args.boundary),
+            // Ants walk in place, so they walk on a copy
+            Stepping::Grid(GridAutomaton::LangtonsAnt) => {
+                let mut next = cells.clone();
+                ant::step(&mut next, width as usize, args.ant_rule, args.boundary, &mut ants, &mut vec![false; height as usize]);
+                next
+            }
+        };
+        start.elapsed()
+    }).collect();
+    report("cpu-naive-allocating", (width, height), rule, None, &allocating_times);
+
+    let mut in_place = CpuSimulation::new(width, height, rule, args.boundary, args.automaton, initial.cells);
+    in_place.set_topology(args.topology);
+    in_place.set_ltl_rule(args.ltl);
+    in_place.set_gray_scott(args.gray_scott);
+    in_place.set_cyclic_rule(args.cyclic);
+    in_place.set_rps_threshold(args.rps_threshold);
+    in_place.set_lenia(args.lenia);
+    in_place.set_forest_fire(args.forest_fire);
+    in_place.set_ant_rule(args.ant_rule);
+    report("cpu-naive", (width, height), rule, None, &time_steps(&mut in_place, args.steps, || {}));
+    Ok(())
+}
+
+fn time_steps(simulation: &mut dyn Simulation, steps: u32, sync: impl Fn()) -> Vec<Duration> {
+    (0..steps).map(|_| {
+        let start = Instant::now();
+        simulation.step();
+        sync();
+        start.elapsed()
+    }).collect()
+}
+
+fn time_batches(device: &wgpu::Device, queue: &wgpu::Queue, gpu: &mut GpuSimulation, batch: u32) -> Vec<BatchTimes> {
+    (0..BATCH_SAMPLES).map(|_| {
+        let start = Instant::now();
+        let mut encoder = device.create_command_encoder(&Default::default());
+        gpu.encode_steps(&mut encoder, batch, None);
+        let encoded = Instant::now();
+        queue.submit(Some(encoder.finish()));
+        let submitted = Instant::now();
+        device.poll(wgpu::Maintain::Wait);
+        BatchTimes { encode: encoded - start, submit: submitted - encoded, wait: submitted.elapsed() }
+    }).collect()
+}
+
+// GPU time of each counting pass from timestamp queries, or the whole submit and wait without them.
+// `encode` is handed the pass's timestamp writes; its counts are never read back, so the same staging
+// slot is reused every time
+fn time_counts(device: &wgpu::Device, queue: &wgpu::Queue, mut encode: impl FnMut(&mut wgpu::CommandEncoder, Option<wgpu::ComputePassTimestampWrites>)) -> Vec<Duration> {
+    let mut timer = GpuTimer::new(device, queue);
+    (0..UPLOAD_SAMPLES).map(|_| {
+        let start = Instant::now();
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encode(&mut encoder, timer.as_ref().map(GpuTimer::timestamp_writes));
+        if let Some(timer) = &mut timer { timer.resolve(&mut encoder); }
+        queue.submit(Some(encoder.finish()));
+        if let Some(timer) = &mut timer { timer.begin_readback(); }
+        device.poll(wgpu::Maintain::Wait);
+        let elapsed = start.elapsed();
+        timer.as_mut().and_then(GpuTimer::try_read).unwrap_or(elapsed)
+    }).collect()
+}
+
+fn time_uploads(simulation: &mut dyn Simulation, cells: &[u32], sync: impl Fn()) -> Vec<Duration> {
+    (0..UPLOAD_SAMPLES).map(|_| {
+        let start = Instant::now();
+        simulation.set_cells(0, cells);
+        sync();
+        start.elapsed()
+    }).collect()
+}
+
+// Like `report`, with the CPU's share of each submit split out from the wait for the GPU
+fn report_batches(batch: u32, (width, height): (u32, u32), info: &wgpu::AdapterInfo, times: &[BatchTimes]) {
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    let average = |part: fn(&BatchTimes) -> Duration| times.iter().map(part).sum::<Duration>() / times.len().max(1) as u32;
+    let (encode, submit, wait) = (average(|t| t.encode), average(|t| t.submit), average(|t| t.wait));
+    let rate = batch as f64 / (encode + submit + wait).as_secs_f64();
+
+    println!("GPU-BATCH-{}: {:.0} gen/s (per submit: encode {:.3} ms, submit {:.3} ms, GPU wait {:.3} ms)", batch, rate, ms(encode), ms(submit), ms(wait));
+    println!(
+        "{{\"backend\":\"gpu-batch\",\"batch\":{},\"adapter\":{:?},\"driver\":{:?},\"width\":{},\"height\":{},\"submits\":{},\"gen_per_sec\":{:.2},\"encode_ms\":{:.4},\"submit_ms\":{:.4},\"wait_ms\":{:.4}}}",
+        batch, info.name, info.driver_info, width, height, times.len(), rate, ms(encode), ms(submit), ms(wait)
+    );
+}
+
+// One human-readable line, then one JSON line for diffing runs; CPU-only runs have no adapter
+fn report(backend: &str, (width, height): (u32, u32), rule: Rule, info: Option<&wgpu::AdapterInfo>, times: &[Duration]) {
+    let (adapter, driver) = info.map_or(("none", ""), |info| (info.name.as_str(), info.driver_info.as_str()));
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    let total: Duration = times.iter().sum();
+    let min = times.iter().min().copied().unwrap_or_default();
+    let max = times.iter().max().copied().unwrap_or_default();
+    let avg = total / times.len().max(1) as u32;
+    let rate = times.len() as f64 / total.as_secs_f64();
+
+    println!("{}: {:.0} gen/s (min {:.3} ms, avg {:.3} ms, max {:.3} ms per step)", backend.to_uppercase(), rate, ms(min), ms(avg), ms(max));
+    println!(
+        "{{\"backend\":\"{}\",\"adapter\":{:?},\"driver\":{:?},\"width\":{},\"height\":{},\"rule\":\"{}\",\"steps\":{},\"gen_per_sec\":{:.2},\"min_ms\":{:.4},\"avg_ms\":{:.4},\"max_ms\":{:.4}}}",
+        backend, adapter, driver, width, height, rule, times.len(), rate, ms(min), ms(avg), ms(max)
+    );
+}
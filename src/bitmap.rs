@@ -0,0 +1,81 @@
+use std::path::Path;
+use image::imageops::FilterType;
+use crate::pattern::Pattern;
+
+/// An image as a starting pattern: scaled to fit a `grid_width` x `grid_height` grid without stretching,
+/// then pixels at least `threshold` bright (0 to 1) are live, so the grid looks like the picture.
+/// Transparent pixels count as black.
+pub fn load(path: &Path, grid_width: u32, grid_height: u32, threshold: f32, dither: bool) -> Result<Pattern, String> {
+    let image = image::open(path).map_err(|e| format!("could not read image '{}': {}", path.display(), e))?;
+    let scaled = image.resize(grid_width, grid_height, FilterType::Triangle).to_luma_alpha8();
+    let (width, height) = (scaled.width() as usize, scaled.height() as usize);
+    if width == 0 || height == 0 {
+        return Err(format!("image '{}' is empty", path.display()));
+    }
+    let luma: Vec<f32> = scaled.pixels().map(|pixel| pixel[0] as f32 / 255.0 * pixel[1] as f32 / 255.0).collect();
+    Ok(Pattern::from_cells(width, &cells(&luma, width, threshold, dither), None))
+}
+
+/// Live (1) where `luma` reaches `threshold`; with `dither` the rounding error of each pixel is passed on to
+/// its neighbours (Floyd-Steinberg), so grey comes out as a scatter of live cells at the matching density.
+pub fn cells(luma: &[f32], width: usize, threshold: f32, dither: bool) -> Vec<u32> {
+    if !dither {
+        return luma.iter().map(|&value| (value >= threshold) as u32).collect();
+    }
+    let height = luma.len() / width;
+    let mut values = luma.to_vec();
+    let mut cells = vec![0; values.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let live = values[i] >= threshold;
+            cells[i] = live as u32;
+            let error = values[i] - if live { 1.0 } else { 0.0 };
+            let mut spread = |dx: isize, dy: usize, weight: f32| {
+                let nx = x as isize + dx;
+                if nx >= 0 && (nx as usize) < width && y + dy < height {
+                    values[(y + dy) * width + nx as usize] += error * weight;
+                }
+            };
+            spread(1, 0, 7.0 / 16.0);
+            spread(-1, 1, 3.0 / 16.0);
+            spread(0, 1, 5.0 / 16.0);
+            spread(1, 1, 1.0 / 16.0);
+        }
+    }
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thresholds_brightness() {
+        assert_eq!(cells(&[0.0, 0.49, 0.5, 1.0], 2, 0.5, false), vec![0, 0, 1, 1]);
+        assert_eq!(cells(&[0.2, 0.3], 2, 0.25, false), vec![0, 1]);
+    }
+
+    #[test]
+    fn dithering_keeps_the_density_of_grey() {
+        let grey = vec![0.25; 64 * 64];
+        let live: u32 = cells(&grey, 64, 0.5, true).iter().sum();
+        assert!((live as f32 / grey.len() as f32 - 0.25).abs() < 0.02);
+        // Without dithering the whole image falls on one side of the threshold
+        assert_eq!(cells(&grey, 64, 0.5, false).iter().sum::<u32>(), 0);
+    }
+
+    #[test]
+    fn jpegs_load_scaled_to_fit() {
+        // A white left half on black, twice as wide as tall, so it keeps its shape on a square grid
+        let image = image::RgbImage::from_fn(64, 32, |x, _| if x < 32 { image::Rgb([255; 3]) } else { image::Rgb([0; 3]) });
+        let path = std::env::temp_dir().join(format!("rust_gpu_life_bitmap_{}.jpg", std::process::id()));
+        image.save(&path).unwrap();
+        let pattern = load(&path, 16, 16, 0.5, false);
+        std::fs::remove_file(&path).unwrap();
+        let pattern = pattern.unwrap();
+        assert_eq!((pattern.width, pattern.height), (16, 8));
+        assert!(pattern.cells.iter().all(|&(x, _, _)| x < 8));
+        assert!(pattern.cells.len() >= 7 * 8);
+    }
+}
@@ -0,0 +1,348 @@
+use std::cell::OnceCell;
+use rayon::prelude::*;
+use crate::ant::AntRule;
+use crate::automaton::{Automaton, CellAutomaton, GridAutomaton, Stepping};
+use crate::boundary::Boundary;
+use crate::cyclic::CyclicRule;
+use crate::forest_fire::ForestFire;
+use crate::gray_scott::GrayScott;
+use crate::lenia::Lenia;
+use crate::ltl::LtlRule;
+use crate::rule::Rule;
+use crate::topology::Topology;
+use crate::{Simulation, WALL};
+
+/// CPU backend storing 64 cells per `u64` and counting neighbours for a whole word at a time.
+///
+/// Ages aren't kept, so `cells()` reports every live Life cell with age 1.
+pub struct BitpackedSimulation {
+    words: Vec<u64>, // Live (or firing) cells; bit 0 of the state in Wireworld and Immigration
+    refractory: Vec<u64>, // Brian's Brain's third state, all clear in Life; bit 1 of the state in Wireworld and Immigration
+    walls: Vec<u64>, // Set where the other two planes are clear and stay clear
+    width: u32,
+    height: u32,
+    rule: Rule,
+    boundary: Boundary,
+    topology: Topology,
+    automaton: Automaton,
+    ltl: LtlRule,
+    // u32-per-cell copy, only built when someone asks for `cells()`
+    unpacked: OnceCell<Vec<u32>>,
+    // Rows that differed before and after a step, until `take_changed_rows`
+    changed: Vec<bool>,
+}
+
+impl BitpackedSimulation {
+    pub fn new(width: u32, height: u32, rule: Rule, boundary: Boundary, automaton: Automaton, cells: &[u32]) -> BitpackedSimulation {
+        assert_eq!(cells.len(), width as usize * height as usize, "grid must be width x height cells");
+        let words = vec![0; height as usize * (width as usize).div_ceil(64)];
+        let mut simulation = BitpackedSimulation { refractory: words.clone(), walls: words.clone(), words, width, height, rule, boundary, topology: Topology::Square, automaton, ltl: LtlRule::BOSCO, unpacked: OnceCell::new(), changed: vec![false; height as usize] };
+        simulation.set_cells(0, cells);
+        simulation
+    }
+
+    fn row_words(&self) -> usize {
+        (self.width as usize).div_ceil(64)
+    }
+
+    // One generation, without the changed-row bookkeeping
+    fn advance(&mut self) {
+        let (width, height) = (self.width as usize, self.height as usize);
+        // Word-at-a-time counting only covers Life and Brian's Brain on the 3x3 neighbourhood, the rest go
+        // through the u32 path
+        let brians_brain = match (self.automaton.stepping(), self.topology) {
+            (Stepping::Cell(CellAutomaton::Life), Topology::Square) => false,
+            (Stepping::Cell(CellAutomaton::BriansBrain), Topology::Square) => true,
+            (Stepping::Cell(automaton), _) => {
+                let next = crate::cpu::step_grid(self.cells(), width, self.rule, self.boundary, self.topology, automaton);
+                self.set_cells(0, &next);
+                return;
+            }
+            (Stepping::Grid(GridAutomaton::LargerThanLife), _) => {
+                let next = crate::ltl::step_grid(self.cells(), width, self.ltl, self.boundary);
+                self.set_cells(0, &next);
+                return;
+            }
+            // Nothing else fits in two planes (see `Automaton::packs`), so it's never packed to begin with
+            (Stepping::Grid(_), _) => return,
+        };
+        let row_words = self.row_words();
+        let (words, rule, boundary) = (&self.words[..], self.rule, self.boundary);
+        let (refractory, walls) = (&self.refractory, &self.walls);
+        let empty: &[u64] = &vec![0; row_words];
+        let row = |y: i64| match boundary.resolve(y, height) {
+            Some(y) => &words[y * row_words..(y + 1) * row_words],
+            None => empty,
+        };
+
+        let mut next = vec![0; words.len()];
+        next.par_chunks_mut(row_words).enumerate().for_each(|(y, out)| {
+            let (above, current, below) = (row(y as i64 - 1), row(y as i64), row(y as i64 + 1));
+            let (above_west, above_east) = shifted(above, width, boundary);
+            let (west, east) = shifted(current, width, boundary);
+            let (below_west, below_east) = shifted(below, width, boundary);
+
+            for w in 0..row_words {
+                // Bit-sliced 4-bit counter, one lane per cell
+                let mut count = [0u64; 4];
+                for neighbours in [above_west[w], above[w], above_east[w], west[w], east[w], below_west[w], below[w], below_east[w]] {
+                    let mut carry = neighbours;
+                    for bit in &mut count {
+                        let sum = *bit ^ carry;
+                        carry &= *bit;
+                        *bit = sum;
+                    }
+                }
+
+                let equals = |n: u32| (0..4).fold(!0u64, |lanes, b| lanes & if n >> b & 1 == 1 { count[b] } else { !count[b] });
+                let alive = current[w];
+                let wall = walls[y * row_words + w];
+                out[w] = !wall & if brians_brain {
+                    // Only ready cells (neither firing nor refractory) can fire
+                    equals(2) & !alive & !refractory[y * row_words + w]
+                } else {
+                    (0..=8).fold(0, |result, n| {
+                        let birth = if rule.birth >> n & 1 == 1 { equals(n) & !alive } else { 0 };
+                        let survive = if rule.survive >> n & 1 == 1 { equals(n) & alive } else { 0 };
+                        result | birth | survive
+                    })
+                };
+            }
+            // Keep the padding bits past the last column dead
+            out[row_words - 1] &= last_word_mask(width);
+        });
+        // Firing cells become refractory, refractory ones become ready
+        let firing = std::mem::replace(&mut self.words, next);
+        if brians_brain {
+            self.refractory = firing;
+        } else {
+            self.refractory.fill(0);
+        }
+        self.unpacked.take();
+    }
+}
+
+impl Simulation for BitpackedSimulation {
+    fn step(&mut self) {
+        // Rows are compared packed, which is much cheaper than unpacking them
+        let before = (self.words.clone(), self.refractory.clone());
+        self.advance();
+        let row_words = self.row_words();
+        let after = self.words.par_chunks(row_words).zip(self.refractory.par_chunks(row_words));
+        self.changed.par_iter_mut().zip(before.0.par_chunks(row_words).zip(before.1.par_chunks(row_words))).zip(after)
+            .for_each(|((changed, before), after)| *changed |= before != after);
+    }
+
+    fn cells(&self) -> &[u32] {
+        self.unpacked.get_or_init(|| {
+            let (width, height, row_words, words, refractory, walls) = (self.width as usize, self.height as usize, self.row_words(), &self.words, &self.refractory, &self.walls);
+            (0..width * height).into_par_iter().map(|i| {
+                let (x, y) = (i % width, i / width);
+                let (word, bit) = (y * row_words + x / 64, x % 64);
+                if walls[word] >> bit & 1 == 1 { return WALL; }
+                (words[word] >> bit & 1 | (refractory[word] >> bit & 1) << 1) as u32
+            }).collect()
+        })
+    }
+
+    fn set_cells(&mut self, offset: usize, cells: &[u32]) {
+        let width = self.width as usize;
+        let row_words = self.row_words();
+        for (i, &cell) in cells.iter().enumerate() {
+            let (x, y) = ((offset + i) % width, (offset + i) / width);
+            let (word, bit) = (y * row_words + x / 64, x % 64);
+            let (low, high) = match self.automaton {
+                _ if cell == WALL => (false, false),
+                Automaton::Wireworld | Automaton::Immigration => (cell & 1 == 1, cell >> 1 & 1 == 1),
+                _ => {
+                    let live = self.automaton.counts(cell);
+                    (live, cell > 0 && !live)
+                }
+            };
+            self.words[word] = self.words[word] & !(1 << bit) | (low as u64) << bit;
+            self.refractory[word] = self.refractory[word] & !(1 << bit) | (high as u64) << bit;
+            self.walls[word] = self.walls[word] & !(1 << bit) | ((cell == WALL) as u64) << bit;
+        }
+        // Cheaper to rebuild on the next `cells()` than to mirror the encoding here
+        self.unpacked.take();
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn set_rule(&mut self, rule: Rule) {
+        self.rule = rule;
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) {
+        self.boundary = boundary;
+    }
+
+    fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    fn set_automaton(&mut self, automaton: Automaton) {
+        self.automaton = automaton;
+    }
+
+    fn set_ltl_rule(&mut self, ltl: LtlRule) {
+        self.ltl = ltl;
+    }
+
+    // Never runs Gray-Scott, see `advance`
+    fn set_gray_scott(&mut self, _params: GrayScott) {}
+
+    // Never runs ants either
+    fn set_ant_rule(&mut self, _rule: AntRule) {}
+
+    // Nor cyclic rules, whose values don't fit two planes
+    fn set_cyclic_rule(&mut self, _rule: CyclicRule) {}
+
+    // Or the three species
+    fn set_rps_threshold(&mut self, _threshold: u32) {}
+
+    // Or Lenia's intensities
+    fn set_lenia(&mut self, _params: Lenia) {}
+
+    // Or forest fires, which roll a die per cell
+    fn set_forest_fire(&mut self, _params: ForestFire) {}
+
+    fn take_changed_rows(&mut self) -> Option<Vec<bool>> {
+        Some(std::mem::replace(&mut self.changed, vec![false; self.height as usize]))
+    }
+
+    fn population(&self) -> u32 {
+        // Wireworld tails set both planes
+        self.words.par_iter().zip(self.refractory.par_iter()).map(|(word, refractory)| (word | refractory).count_ones()).sum()
+    }
+}
+
+fn last_word_mask(width: usize) -> u64 {
+    match width % 64 {
+        0 => !0,
+        bits => (1 << bits) - 1,
+    }
+}
+
+// Each cell's west and east neighbour moved into that cell's lane, with the edge columns filled in per `boundary`
+fn shifted(row: &[u64], width: usize, boundary: Boundary) -> (Vec<u64>, Vec<u64>) {
+    let last = row.len() - 1;
+    let cell = |x: usize| row[x / 64] >> (x % 64) & 1;
+    let (beyond_west, beyond_east) = match boundary {
+        Boundary::Wrap => (cell(width - 1), cell(0)),
+        Boundary::Dead => (0, 0),
+        Boundary::Mirror => (cell(0), cell(width - 1)),
+    };
+
+    let west = (0..row.len()).map(|w| {
+        let carry = if w == 0 { beyond_west } else { row[w - 1] >> 63 };
+        row[w] << 1 | carry
+    }).collect();
+    let east = (0..row.len()).map(|w| {
+        let carry = if w == last { beyond_east << ((width - 1) % 64) } else { row[w + 1] << 63 };
+        row[w] >> 1 | carry
+    }).collect();
+    (west, east)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::CpuSimulation;
+
+    fn compare(width: u32, height: u32, boundary: Boundary, automaton: Automaton) {
+        let mut cells = crate::random_grid(width as usize * height as usize, 42, 0.35);
+        if automaton == Automaton::Wireworld {
+            // Conductors alone never change, so send some electrons down them
+            cells.iter_mut().step_by(7).filter(|cell| **cell == 1).for_each(|cell| *cell = 2);
+        }
+        if automaton == Automaton::Immigration {
+            crate::split_species(&mut cells, 42);
+        }
+        let mut naive = CpuSimulation::new(width, height, Rule::CONWAY, boundary, automaton, cells.clone());
+        let mut bitpacked = BitpackedSimulation::new(width, height, Rule::CONWAY, boundary, automaton, &cells);
+        for generation in 1..=100 {
+            naive.step();
+            bitpacked.step();
+            // Ages aside, Life cells are 0 or 1; other automata's states match exactly
+            let alive: Vec<u32> = naive.cells().iter().map(|&cell| automaton.pattern_state(cell)).collect();
+            assert!(alive == bitpacked.cells(), "{}x{} {} grids diverge at generation {}", width, height, boundary, generation);
+            assert_eq!(naive.population(), bitpacked.population());
+            // Ages aside, both see the same rows change
+            if automaton != Automaton::Life {
+                assert_eq!(naive.take_changed_rows(), bitpacked.take_changed_rows());
+            }
+        }
+    }
+
+    #[test]
+    fn matches_naive_on_whole_words() {
+        compare(128, 128, Boundary::Wrap, Automaton::Life);
+    }
+
+    #[test]
+    fn matches_naive_on_a_ragged_last_word() {
+        for boundary in [Boundary::Wrap, Boundary::Dead, Boundary::Mirror] {
+            compare(100, 100, boundary, Automaton::Life);
+        }
+    }
+
+    #[test]
+    fn matches_naive_on_non_square_grids() {
+        for boundary in [Boundary::Wrap, Boundary::Dead, Boundary::Mirror] {
+            compare(150, 40, boundary, Automaton::Life);
+            compare(30, 90, boundary, Automaton::Life);
+        }
+    }
+
+    #[test]
+    fn matches_naive_on_brians_brain() {
+        compare(100, 100, Boundary::Wrap, Automaton::BriansBrain);
+    }
+
+    #[test]
+    fn matches_naive_on_wireworld() {
+        compare(100, 100, Boundary::Wrap, Automaton::Wireworld);
+    }
+
+    #[test]
+    fn matches_naive_on_immigration() {
+        compare(100, 100, Boundary::Wrap, Automaton::Immigration);
+    }
+
+    #[test]
+    fn matches_naive_around_walls() {
+        for automaton in [Automaton::Life, Automaton::BriansBrain] {
+            let mut cells = crate::random_grid(80 * 80, 3, 0.4);
+            cells.iter_mut().step_by(11).for_each(|cell| *cell = WALL);
+            let mut naive = CpuSimulation::new(80, 80, Rule::CONWAY, Boundary::Wrap, automaton, cells.clone());
+            let mut bitpacked = BitpackedSimulation::new(80, 80, Rule::CONWAY, Boundary::Wrap, automaton, &cells);
+            for _ in 0..50 {
+                naive.step();
+                bitpacked.step();
+            }
+            let states: Vec<u32> = naive.cells().iter().map(|&cell| if cell == WALL { WALL } else { automaton.pattern_state(cell) }).collect();
+            assert!(states == bitpacked.cells(), "{} diverges around walls", automaton);
+            assert_eq!(naive.population(), bitpacked.population());
+        }
+    }
+
+    #[test]
+    fn matches_naive_on_highlife() {
+        let cells = crate::random_grid(70 * 70, 7, 0.5);
+        let rule = Rule::parse("B36/S23").unwrap();
+        let mut naive = CpuSimulation::new(70, 70, rule, Boundary::Wrap, Automaton::Life, cells.clone());
+        let mut bitpacked = BitpackedSimulation::new(70, 70, rule, Boundary::Wrap, Automaton::Life, &cells);
+        for _ in 0..50 {
+            naive.step();
+            bitpacked.step();
+        }
+        assert!(naive.cells().iter().map(|&cell| (cell > 0) as u32).eq(bitpacked.cells().iter().copied()));
+    }
+}
@@ -0,0 +1,55 @@
+use std::fmt;
+
+/// What lies beyond the edge of the grid; the discriminants are the values rules.wgsl expects
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum Boundary {
+    /// The grid is a torus
+    Wrap = 0,
+    /// Everything outside is dead
+    Dead = 1,
+    /// The edge row or column is repeated outwards
+    Mirror = 2,
+}
+
+impl Boundary {
+    pub fn parse(text: &str) -> Result<Boundary, String> {
+        match text {
+            "wrap" => Ok(Boundary::Wrap),
+            "dead" => Ok(Boundary::Dead),
+            "mirror" => Ok(Boundary::Mirror),
+            _ => Err(format!("invalid boundary '{}' (expected wrap, dead or mirror)", text)),
+        }
+    }
+
+    pub fn next(self) -> Boundary {
+        match self {
+            Boundary::Wrap => Boundary::Dead,
+            Boundary::Dead => Boundary::Mirror,
+            Boundary::Mirror => Boundary::Wrap,
+        }
+    }
+
+    /// Maps a neighbour coordinate off the grid back onto it, `None` if it is dead
+    pub fn resolve(self, coordinate: i64, size: usize) -> Option<usize> {
+        let size = size as i64;
+        if (0..size).contains(&coordinate) {
+            return Some(coordinate as usize);
+        }
+        match self {
+            Boundary::Wrap => Some(coordinate.rem_euclid(size) as usize),
+            Boundary::Dead => None,
+            Boundary::Mirror => Some(coordinate.clamp(0, size - 1) as usize),
+        }
+    }
+}
+
+impl fmt::Display for Boundary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Boundary::Wrap => "wrap",
+            Boundary::Dead => "dead",
+            Boundary::Mirror => "mirror",
+        })
+    }
+}
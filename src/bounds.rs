@@ -0,0 +1,160 @@
+use std::fmt;
+use rayon::prelude::*;
+use wgpu::util::DeviceExt;
+use crate::population::dispatch_size;
+use crate::readback::StagingRing;
+use crate::WALL;
+
+// (~left, ~top, right + 1, bottom + 1), see bounds.wgsl
+const BOUNDS_SIZE: u64 = 4 * 4;
+
+/// The smallest box of cells holding every live one, in grid cells. It doesn't wrap: a pattern
+/// straddling the torus' seam spans the grid from edge to edge.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BoundingBox {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl BoundingBox {
+    /// The box's middle, in fractional cells.
+    pub fn center(&self) -> (f64, f64) {
+        (self.x as f64 + self.width as f64 / 2.0, self.y as f64 + self.height as f64 / 2.0)
+    }
+}
+
+impl fmt::Display for BoundingBox {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}×{} @ ({}, {})", self.width, self.height, self.x, self.y)
+    }
+}
+
+/// The box around the live cells (anything but dead cells and walls, like the population count) of a
+/// `width`-wide grid, rows scanned in parallel; None when nothing is alive.
+pub fn of_cells(cells: &[u32], width: usize) -> Option<BoundingBox> {
+    let live = |cell: &u32| *cell > 0 && *cell != WALL;
+    cells.par_chunks(width).enumerate().filter_map(|(y, row)| {
+        Some((row.iter().position(live)?, row.iter().rposition(live)?, y, y))
+    }).reduce_with(|a, b| (a.0.min(b.0), a.1.max(b.1), a.2.min(b.2), a.3.max(b.3)))
+        .map(|(left, right, top, bottom)| BoundingBox { x: left as u32, y: top as u32, width: (right - left + 1) as u32, height: (bottom - top + 1) as u32 })
+}
+
+// The box from the shader's words; a side left at 0 means no cell was alive
+fn decode(words: [u32; 4]) -> Option<BoundingBox> {
+    let [left, top, right, bottom] = words;
+    if right == 0 { return None; }
+    let (x, y) = (!left, !top);
+    Some(BoundingBox { x, y, width: right - x, height: bottom - y })
+}
+
+/// `of_cells` on the GPU: each workgroup reduces its cells' boxes in shared memory and adds the result
+/// to the grid's with atomics, read back asynchronously like the population count
+pub struct BoundsTracker {
+    pipeline: wgpu::ComputePipeline,
+    bind_groups: [wgpu::BindGroup; 2],
+    bounds: wgpu::Buffer,
+    staging: StagingRing,
+}
+
+impl BoundsTracker {
+    pub fn new(device: &wgpu::Device, buffer_a: &wgpu::Buffer, buffer_b: &wgpu::Buffer, width: u32) -> BoundsTracker {
+        let bounds = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Bounds"),
+            size: BOUNDS_SIZE,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let grid = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Bounds Grid"),
+            contents: bytemuck::cast_slice(&[width, 0, 0, 0]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let staging = StagingRing::new(device, "Bounds Staging", BOUNDS_SIZE);
+
+        let entry = |binding, ty| wgpu::BindGroupLayoutEntry { binding, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty, has_dynamic_offset: false, min_binding_size: None }, count: None };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                entry(0, wgpu::BufferBindingType::Storage { read_only: true }),
+                entry(1, wgpu::BufferBindingType::Storage { read_only: false }),
+                entry(2, wgpu::BufferBindingType::Uniform),
+            ],
+            label: None,
+        });
+        let bind_group = |cells: &wgpu::Buffer| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: cells.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: bounds.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: grid.as_entire_binding() },
+            ],
+            label: None,
+        });
+        let bind_groups = [bind_group(buffer_a), bind_group(buffer_b)];
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("bounds.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&layout], ..Default::default() });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Bounds"), layout: Some(&pipeline_layout), module: &shader, entry_point: "main", compilation_options: Default::default(), cache: None,
+        });
+
+        BoundsTracker { pipeline, bind_groups, bounds, staging }
+    }
+
+    /// Measures the buffer for `parity` (0 = buffer A) if a staging slot is free, otherwise skips this frame
+    pub fn encode(&mut self, encoder: &mut wgpu::CommandEncoder, parity: usize, cell_count: usize) {
+        let Some(staging) = self.staging.next() else { return };
+        encoder.clear_buffer(&self.bounds, 0, None);
+        {
+            let (groups_x, groups_y) = dispatch_size(cell_count);
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("Bounds"), timestamp_writes: None });
+            cpass.set_pipeline(&self.pipeline);
+            cpass.set_bind_group(0, &self.bind_groups[parity], &[]);
+            cpass.dispatch_workgroups(groups_x, groups_y, 1);
+        }
+        encoder.copy_buffer_to_buffer(&self.bounds, 0, staging, 0, BOUNDS_SIZE);
+    }
+
+    /// Maps the slot written by `encode`; must be called after that encoder was submitted
+    pub fn begin_readback(&mut self) {
+        self.staging.begin_readback();
+    }
+
+    /// Newest box whose mapping has completed, if any arrived: `Some(None)` is an empty grid. Call after
+    /// `device.poll`
+    pub fn try_read(&mut self) -> Option<Option<BoundingBox>> {
+        self.staging.try_read(|bytes| {
+            let words = bytemuck::cast_slice::<u8, u32>(bytes);
+            decode([words[0], words[1], words[2], words[3]])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boxes_hold_every_live_cell() {
+        let width = 6;
+        let mut cells = vec![0; width * 5];
+        assert_eq!(of_cells(&cells, width), None);
+        cells[width + 4] = 1;
+        cells[3 * width + 1] = 7;
+        // Walls don't count
+        cells[4 * width + 5] = WALL;
+        let found = of_cells(&cells, width);
+        assert_eq!(found, Some(BoundingBox { x: 1, y: 1, width: 4, height: 3 }));
+        assert_eq!(found.unwrap().to_string(), "4×3 @ (1, 1)");
+        assert_eq!(found.unwrap().center(), (3.0, 2.5));
+    }
+
+    #[test]
+    fn shader_words_decode_to_the_same_box() {
+        // What bounds.wgsl leaves for cells at (1, 1) and (4, 3)
+        assert_eq!(decode([!1, !1, 5, 4]), Some(BoundingBox { x: 1, y: 1, width: 4, height: 3 }));
+        assert_eq!(decode([0; 4]), None);
+        assert_eq!(decode([!0, !0, 1, 1]), Some(BoundingBox { x: 0, y: 0, width: 1, height: 1 }));
+    }
+}
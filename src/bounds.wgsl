@@ -0,0 +1,43 @@
+// Bounding box of the live cells: each workgroup reduces its cells' boxes in shared memory, then adds
+// its box to the grid's with one atomicMax per side. Minimums are kept bitwise inverted so every side
+// is a maximum and a cleared buffer means nothing alive; maximums are kept plus one for the same reason
+// Walls aren't alive (matches WALL in lib.rs)
+const WALL: u32 = 0xffffffffu;
+
+@group(0) @binding(0) var<storage, read> cellState: array<u32>;
+// (~left, ~top, right + 1, bottom + 1)
+@group(0) @binding(1) var<storage, read_write> bounds: array<atomic<u32>, 4>;
+// Width of the grid in x, yzw unused
+@group(0) @binding(2) var<uniform> grid: vec4<u32>;
+
+var<workgroup> tile: array<vec4<u32>, 256>;
+
+// Dispatched as a 2D grid of 1D workgroups like population.wgsl
+@compute @workgroup_size(256)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>, @builtin(local_invocation_index) local: u32, @builtin(num_workgroups) groups: vec3<u32>) {
+    let index = global_id.y * groups.x * 256u + global_id.x;
+    // No early return: the barriers need the whole workgroup
+    var cell_box = vec4<u32>(0u);
+    if (index < arrayLength(&cellState)) {
+        let state = cellState[index];
+        if (state > 0u && state != WALL) {
+            let x = index % grid.x;
+            let y = index / grid.x;
+            cell_box = vec4<u32>(~x, ~y, x + 1u, y + 1u);
+        }
+    }
+    tile[local] = cell_box;
+    workgroupBarrier();
+    for (var stride = 128u; stride > 0u; stride >>= 1u) {
+        if (local < stride) {
+            tile[local] = max(tile[local], tile[local + stride]);
+        }
+        workgroupBarrier();
+    }
+    if (local == 0u && tile[0].z > 0u) {
+        atomicMax(&bounds[0], tile[0].x);
+        atomicMax(&bounds[1], tile[0].y);
+        atomicMax(&bounds[2], tile[0].z);
+        atomicMax(&bounds[3], tile[0].w);
+    }
+}
@@ -0,0 +1,138 @@
+// View transform shared with the fragment shader (see `Camera` in render.wgsl)
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Camera {
+    pub center: [f32; 2], // In cells
+    pub zoom: f32,        // 1.0 shows the whole grid
+    pub display: u32,     // A `DisplayMode`, filled in when uploaded
+    pub chunks: u32,      // 1 tints the chunks the GPU step computed, filled in when uploaded
+    pub seam: u32,        // 1 marks where the torus wraps, filled in when uploaded
+    pub blend: f32,       // 0 draws the previous generation, 1 the latest, filled in when uploaded
+    pub regions: u32,     // 1 tints the rule map's regions, filled in when uploaded
+}
+
+// Never zoom in further than this many cells across the window's shorter side
+const MIN_VIEW_CELLS: f64 = 8.0;
+// Minimap size along the grid's longer side and its gap from the window's corner, in pixels
+const MINIMAP_SIZE: f64 = 256.0;
+const MINIMAP_MARGIN: f64 = 12.0;
+
+impl Camera {
+    pub fn new(grid: (u32, u32)) -> Camera {
+        Camera { center: [grid.0 as f32 / 2.0, grid.1 as f32 / 2.0], zoom: 1.0, display: 0, chunks: 0, seam: 0, blend: 1.0, regions: 0 }
+    }
+
+    // Width and height of the view in cells
+    pub fn view_cells(&self, grid: (u32, u32)) -> (f64, f64) {
+        (grid.0 as f64 / self.zoom as f64, grid.1 as f64 / self.zoom as f64)
+    }
+
+    // Fractional cell coordinates under a window position, not yet wrapped onto the torus
+    pub fn window_to_cell(&self, position: (f64, f64), window: (u32, u32), grid: (u32, u32)) -> (f64, f64) {
+        let view = self.view_cells(grid);
+        let (u, v) = to_viewport(position, window, grid);
+        (self.center[0] as f64 + (u - 0.5) * view.0, self.center[1] as f64 + (v - 0.5) * view.1)
+    }
+
+    // The topology is toroidal, so the view wraps instead of being clamped
+    pub fn pan(&mut self, dx: f64, dy: f64, grid: (u32, u32)) {
+        self.center[0] = (self.center[0] as f64 + dx).rem_euclid(grid.0 as f64) as f32;
+        self.center[1] = (self.center[1] as f64 + dy).rem_euclid(grid.1 as f64) as f32;
+    }
+
+    // Centers the view on a cell, wrapped onto the torus
+    pub fn jump_to(&mut self, cell: (f64, f64), grid: (u32, u32)) {
+        self.center = [cell.0 as f32, cell.1 as f32];
+        self.pan(0.0, 0.0, grid);
+    }
+
+    // Moves `fraction` of the way to centering `cell`, the short way around the torus
+    pub fn ease_toward(&mut self, cell: (f64, f64), fraction: f64, grid: (u32, u32)) {
+        let dx = shortest(self.center[0], cell.0, grid.0);
+        let dy = shortest(self.center[1], cell.1, grid.1);
+        self.pan(dx * fraction, dy * fraction, grid);
+    }
+
+    // The zoom that shows a `size` box of cells with `margin` of its size to spare on every side, within the zoom limits
+    pub fn fit_zoom(size: (f64, f64), margin: f64, grid: (u32, u32)) -> f32 {
+        let max_zoom = (grid.0.min(grid.1) as f64 / MIN_VIEW_CELLS).max(1.0);
+        let padded = (size.0.max(1.0) * (1.0 + 2.0 * margin), size.1.max(1.0) * (1.0 + 2.0 * margin));
+        (grid.0 as f64 / padded.0).min(grid.1 as f64 / padded.1).clamp(1.0, max_zoom) as f32
+    }
+
+    // Moves `fraction` of the way to centering `cell` at `zoom`, the zoom by ratio so zooming in far is as
+    // smooth as a little; true once both are close enough to snap there
+    pub fn ease_to_fit(&mut self, cell: (f64, f64), zoom: f32, fraction: f64, grid: (u32, u32)) -> bool {
+        self.zoom = (self.zoom as f64 * (zoom as f64 / self.zoom as f64).powf(fraction)) as f32;
+        self.ease_toward(cell, fraction, grid);
+        let view = self.view_cells(grid);
+        let close = (self.zoom / zoom - 1.0).abs() < 0.005 && shortest(self.center[0], cell.0, grid.0).abs() < view.0 * 0.002 && shortest(self.center[1], cell.1, grid.1).abs() < view.1 * 0.002;
+        if close {
+            self.zoom = zoom;
+            self.jump_to(cell, grid);
+        }
+        close
+    }
+
+    // Zooms while keeping the cell under `position` fixed on screen
+    pub fn zoom_at(&mut self, factor: f64, position: (f64, f64), window: (u32, u32), grid: (u32, u32)) {
+        let anchor = self.window_to_cell(position, window, grid);
+        let max_zoom = (grid.0.min(grid.1) as f64 / MIN_VIEW_CELLS).max(1.0);
+        self.zoom = (self.zoom as f64 * factor).clamp(1.0, max_zoom) as f32;
+
+        let view = self.view_cells(grid);
+        let (u, v) = to_viewport(position, window, grid);
+        self.center = [(anchor.0 - (u - 0.5) * view.0) as f32, (anchor.1 - (v - 0.5) * view.1) as f32];
+        self.pan(0.0, 0.0, grid);
+    }
+}
+
+// From `from` to `to` the short way around a torus `size` cells across
+fn shortest(from: f32, to: f64, size: u32) -> f64 {
+    (to - from as f64 + size as f64 / 2.0).rem_euclid(size as f64) - size as f64 / 2.0
+}
+
+// The grid is drawn into the largest centered rectangle of its own shape, so cells stay square, as
+// (left, top, width, height) in pixels; the rest is letterboxed
+pub fn viewport(window: (u32, u32), grid: (u32, u32)) -> (f64, f64, f64, f64) {
+    let scale = (window.0 as f64 / grid.0 as f64).min(window.1 as f64 / grid.1 as f64);
+    let (width, height) = (grid.0 as f64 * scale, grid.1 as f64 * scale);
+    ((window.0 as f64 - width) / 2.0, (window.1 as f64 - height) / 2.0, width, height)
+}
+
+// The whole pixels a (left, top, width, height) rectangle touches, rounded outward and kept inside `window`,
+// as a scissor rect
+pub fn pixels_covered(rect: (f64, f64, f64, f64), window: (u32, u32)) -> (u32, u32, u32, u32) {
+    let (left, top) = (rect.0.floor().max(0.0) as u32, rect.1.floor().max(0.0) as u32);
+    let right = ((rect.0 + rect.2).ceil().max(0.0) as u32).min(window.0);
+    let bottom = ((rect.1 + rect.3).ceil().max(0.0) as u32).min(window.1);
+    (left.min(right), top.min(bottom), right.saturating_sub(left), bottom.saturating_sub(top))
+}
+
+// Window position relative to the viewport, 0..1 inside it
+fn to_viewport(position: (f64, f64), window: (u32, u32), grid: (u32, u32)) -> (f64, f64) {
+    let (left, top, width, height) = viewport(window, grid);
+    ((position.0 - left) / width, (position.1 - top) / height)
+}
+
+pub fn in_viewport(position: (f64, f64), window: (u32, u32), grid: (u32, u32)) -> bool {
+    let (u, v) = to_viewport(position, window, grid);
+    (0.0..1.0).contains(&u) && (0.0..1.0).contains(&v)
+}
+
+// The minimap in the bottom-right corner, shaped like the grid and at most a third of the window, as
+// (left, top, width, height) in pixels; None when the window is too small to fit one
+pub fn minimap(window: (u32, u32), grid: (u32, u32)) -> Option<(f64, f64, f64, f64)> {
+    let longest = MINIMAP_SIZE.min(window.0 as f64 / 3.0).min(window.1 as f64 / 3.0);
+    let scale = longest / grid.0.max(grid.1) as f64;
+    let (width, height) = ((grid.0 as f64 * scale).floor(), (grid.1 as f64 * scale).floor());
+    let (left, top) = (window.0 as f64 - width - MINIMAP_MARGIN, window.1 as f64 - height - MINIMAP_MARGIN);
+    (width >= 1.0 && height >= 1.0 && left >= 0.0 && top >= 0.0).then_some((left, top, width, height))
+}
+
+// The cell under a window position inside the minimap, if it's there
+pub fn minimap_cell(position: (f64, f64), window: (u32, u32), grid: (u32, u32)) -> Option<(f64, f64)> {
+    let (left, top, width, height) = minimap(window, grid)?;
+    let (u, v) = ((position.0 - left) / width, (position.1 - top) / height);
+    ((0.0..1.0).contains(&u) && (0.0..1.0).contains(&v)).then_some((u * grid.0 as f64, v * grid.1 as f64))
+}
@@ -0,0 +1,361 @@
+use std::fmt;
+use rayon::prelude::*;
+use crate::automaton::Automaton;
+use crate::population::{dispatch_size, Reduction};
+use crate::readback::StagingRing;
+use crate::{GpuSimulation, WALL};
+
+// One u32 per count of `Changes`, then the two `Activity` sums as 64-bit (low, high) pairs
+const COUNTERS_SIZE: u64 = 9 * 4;
+// Followed in the staging buffer by the `Activity` origin the sums were taken from
+const ORIGIN_SIZE: u64 = 2 * 4;
+// A workgroup's counts and coordinate sums in the workgroup reduction (`Tally` in changes.wgsl)
+const PARTIAL_SIZE: u64 = 7 * 4;
+// Checks in a row without a change before a grid counts as settled; one check is made per frame
+const QUIET_CHECKS: u32 = 8;
+
+/// How many cells changed state in the latest generation. Ages don't count, so a still life has no
+/// changes `since_previous` and a period-2 oscillator none `since_two_back`.
+///
+/// Births and deaths are cells that started or stopped counting as a neighbour since the previous
+/// generation (firing, in Brian's Brain); the population is taken in the same pass, and so is where
+/// the changes happened.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Changes {
+    pub since_previous: u32,
+    pub since_two_back: u32,
+    pub births: u32,
+    pub deaths: u32,
+    pub population: u32,
+    pub activity: Activity,
+}
+
+/// The coordinates of the cells counted in `Changes::since_previous`, summed. They're measured around the
+/// torus from `origin`, so activity across the grid's edges isn't torn apart unless it straddles the origin.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Activity {
+    pub origin: (u32, u32),
+    pub sum_x: u64,
+    pub sum_y: u64,
+}
+
+impl Changes {
+    /// Counts on a `width`-wide grid, with the activity measured from `origin`.
+    pub fn count(automaton: Automaton, current: &[u32], previous: &[u32], two_back: &[u32], width: usize, origin: (u32, u32)) -> Changes {
+        let height = current.len() / width;
+        current.par_iter().zip(previous).zip(two_back).enumerate().map(|(index, ((&cell, &before), &back))| {
+            let (counted, counted_before) = (automaton.counts(cell), automaton.counts(before));
+            let changed = !automaton.same_state(cell, before);
+            let (x, y) = ((index % width + width - origin.0 as usize) % width, (index / width + height - origin.1 as usize) % height);
+            Changes {
+                since_previous: changed as u32,
+                since_two_back: !automaton.same_state(cell, back) as u32,
+                births: (counted && !counted_before) as u32,
+                deaths: (!counted && counted_before) as u32,
+                population: (cell > 0 && cell != WALL) as u32,
+                activity: Activity { origin, sum_x: if changed { x as u64 } else { 0 }, sum_y: if changed { y as u64 } else { 0 } },
+            }
+        }).reduce(|| Changes { activity: Activity { origin, ..Activity::default() }, ..Changes::default() }, |a, b| Changes {
+            since_previous: a.since_previous + b.since_previous,
+            since_two_back: a.since_two_back + b.since_two_back,
+            births: a.births + b.births,
+            deaths: a.deaths + b.deaths,
+            population: a.population + b.population,
+            activity: Activity { origin, sum_x: a.activity.sum_x + b.activity.sum_x, sum_y: a.activity.sum_y + b.activity.sum_y },
+        })
+    }
+
+    /// The average position of the changed cells on a `width` x `height` grid, in cells; `None` without changes.
+    pub fn centroid(&self, width: u32, height: u32) -> Option<(f64, f64)> {
+        let count = self.since_previous as f64;
+        let Activity { origin, sum_x, sum_y } = self.activity;
+        (self.since_previous > 0).then(|| (
+            (origin.0 as f64 + sum_x as f64 / count + 0.5).rem_euclid(width as f64),
+            (origin.1 as f64 + sum_y as f64 / count + 0.5).rem_euclid(height as f64),
+        ))
+    }
+}
+
+/// The two generations before the latest on the CPU, kept for `Changes::count`
+#[derive(Default)]
+pub struct CpuHistory {
+    // Generation numbers alongside the cells, to tell whether they're the ones right before the latest
+    previous: (Option<usize>, Vec<u32>),
+    two_back: (Option<usize>, Vec<u32>),
+}
+
+impl CpuHistory {
+    /// Call with the cells of `generation` just before stepping past it; reuses the oldest allocation
+    pub fn remember(&mut self, generation: usize, cells: &[u32]) {
+        std::mem::swap(&mut self.previous, &mut self.two_back);
+        self.previous.0 = Some(generation);
+        self.previous.1.clear();
+        self.previous.1.extend_from_slice(cells);
+    }
+
+    /// `None` unless both generations right before `generation` were remembered
+    pub fn changes(&self, automaton: Automaton, generation: usize, current: &[u32], width: usize, origin: (u32, u32)) -> Option<Changes> {
+        let complete = generation >= 2 && self.previous.0 == Some(generation - 1) && self.two_back.0 == Some(generation - 2);
+        complete.then(|| Changes::count(automaton, current, &self.previous.1, &self.two_back.1, width, origin))
+    }
+
+    /// The cells of `generation` - 1 if they were remembered
+    pub fn previous(&self, generation: usize) -> Option<&[u32]> {
+        (generation >= 1 && self.previous.0 == Some(generation - 1)).then_some(&self.previous.1[..])
+    }
+
+    /// Forgets both generations, e.g. after the grid was edited
+    pub fn clear(&mut self) {
+        self.previous.0 = None;
+        self.two_back.0 = None;
+    }
+}
+
+/// Why a simulation stopped by itself
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Settled {
+    /// No live cells left
+    Extinct,
+    /// Only still lifes
+    Stable,
+    /// Still lifes and period-2 oscillators
+    Oscillating,
+}
+
+impl fmt::Display for Settled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Settled::Extinct => "EXTINCT",
+            Settled::Stable => "STABLE",
+            Settled::Oscillating => "STABLE (period 2)",
+        })
+    }
+}
+
+/// Watches population and `Changes` checks for a grid that has died out or stopped evolving.
+///
+/// Every verdict takes a few checks in a row, so results read back from before an edit can't trigger one.
+#[derive(Default)]
+pub struct SteadyState {
+    empty: u32,
+    still: u32,
+    period_two: u32,
+}
+
+impl SteadyState {
+    pub fn observe_population(&mut self, population: u32) -> Option<Settled> {
+        self.empty = if population == 0 { self.empty + 1 } else { 0 };
+        (self.empty >= QUIET_CHECKS).then_some(Settled::Extinct)
+    }
+
+    pub fn observe_changes(&mut self, changes: Changes) -> Option<Settled> {
+        self.still = if changes.since_previous == 0 { self.still + 1 } else { 0 };
+        self.period_two = if changes.since_two_back == 0 { self.period_two + 1 } else { 0 };
+        if self.still >= QUIET_CHECKS {
+            Some(Settled::Stable)
+        } else if self.period_two >= QUIET_CHECKS {
+            Some(Settled::Oscillating)
+        } else {
+            None
+        }
+    }
+
+    /// Starts counting from scratch, e.g. on resume
+    pub fn reset(&mut self) {
+        *self = SteadyState::default();
+    }
+}
+
+/// GPU `Changes` of the latest generation, counted the `Reduction` way and read back asynchronously
+pub struct ChangeCounter {
+    pipeline: wgpu::ComputePipeline,
+    // The second dispatch, only in the workgroup reduction
+    partials_pipeline: Option<wgpu::ComputePipeline>,
+    bind_groups: [wgpu::BindGroup; 2],
+    counters: wgpu::Buffer,
+    origin: wgpu::Buffer,
+    staging: StagingRing,
+}
+
+impl ChangeCounter {
+    pub fn new(device: &wgpu::Device, gpu: &GpuSimulation, reduction: Reduction) -> ChangeCounter {
+        let counters = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Change Counters"),
+            size: COUNTERS_SIZE,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let origin = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Change Origin"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let (groups_x, groups_y) = dispatch_size((gpu.current_buffer().size() / 4) as usize);
+        let partials = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Change Partials"),
+            size: groups_x as u64 * groups_y as u64 * PARTIAL_SIZE,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let staging = StagingRing::new(device, "Change Staging", COUNTERS_SIZE + ORIGIN_SIZE);
+
+        let read_only = |binding| wgpu::BindGroupLayoutEntry { binding, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: true }, has_dynamic_offset: false, min_binding_size: None }, count: None };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                read_only(0),
+                read_only(1),
+                read_only(2),
+                wgpu::BindGroupLayoutEntry { binding: 3, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 4, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: false }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 5, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 6, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: false }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            ],
+            label: None,
+        });
+        // Bind group n compares buffer n (the latest generation when parity is n) with the other one
+        let buffers = gpu.buffers();
+        let bind_group = |current: &wgpu::Buffer, previous: &wgpu::Buffer| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: current.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: previous.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: gpu.history_buffer().as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: gpu.params_buffer().as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 4, resource: counters.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 5, resource: origin.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 6, resource: partials.as_entire_binding() },
+            ],
+            label: None,
+        });
+        let bind_groups = [bind_group(&buffers[0], &buffers[1]), bind_group(&buffers[1], &buffers[0])];
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("changes.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(concat!(include_str!("rules.wgsl"), include_str!("changes.wgsl")).into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&layout], ..Default::default() });
+        let create_pipeline = |label, entry_point| device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(label), layout: Some(&pipeline_layout), module: &shader, entry_point, compilation_options: Default::default(), cache: None,
+        });
+        let pipeline = match reduction {
+            Reduction::Atomic => create_pipeline("Changes", "main"),
+            Reduction::Workgroup => create_pipeline("Change Tiles", "reduce_tiles"),
+        };
+        let partials_pipeline = (reduction == Reduction::Workgroup).then(|| create_pipeline("Change Partials", "reduce_partials"));
+
+        ChangeCounter { pipeline, partials_pipeline, bind_groups, counters, origin, staging }
+    }
+
+    /// Counts changes into the buffer for `parity` if a staging slot is free, otherwise skips this frame; the
+    /// activity is measured from `origin`. Only meaningful right after `GpuSimulation::encode_steps`, which fills
+    /// the history buffer. `timestamp_writes` times the pass, as in `PopulationCounter::encode`.
+    pub fn encode(&mut self, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, parity: usize, cell_count: usize, origin: (u32, u32), timestamp_writes: Option<wgpu::ComputePassTimestampWrites>) {
+        let Some(staging) = self.staging.next() else { return };
+
+        queue.write_buffer(&self.origin, 0, bytemuck::cast_slice(&[origin.0, origin.1, 0, 0]));
+        if self.partials_pipeline.is_none() { encoder.clear_buffer(&self.counters, 0, None); }
+        {
+            let (groups_x, groups_y) = dispatch_size(cell_count);
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None, timestamp_writes });
+            cpass.set_pipeline(&self.pipeline);
+            cpass.set_bind_group(0, &self.bind_groups[parity], &[]);
+            cpass.dispatch_workgroups(groups_x, groups_y, 1);
+            if let Some(partials_pipeline) = &self.partials_pipeline {
+                cpass.set_pipeline(partials_pipeline);
+                cpass.dispatch_workgroups(1, 1, 1);
+            }
+        }
+        encoder.copy_buffer_to_buffer(&self.counters, 0, staging, 0, COUNTERS_SIZE);
+        encoder.copy_buffer_to_buffer(&self.origin, 0, staging, COUNTERS_SIZE, ORIGIN_SIZE);
+    }
+
+    /// Maps the slot written by `encode`, which counted `generation`; must be called after that encoder was submitted
+    pub fn begin_readback(&mut self, generation: usize) {
+        self.staging.begin_tagged_readback(generation as u64);
+    }
+
+    /// Newest counts whose mapping has completed and the generation they belong to, if any; call after `device.poll`
+    pub fn try_read(&mut self) -> Option<(usize, Changes)> {
+        self.staging.try_read_tagged(|bytes| {
+            let counts = bytemuck::cast_slice::<u8, u32>(bytes);
+            let wide = |low: usize| counts[low] as u64 | (counts[low + 1] as u64) << 32;
+            let activity = Activity { origin: (counts[9], counts[10]), sum_x: wide(5), sum_y: wide(7) };
+            Changes { since_previous: counts[0], since_two_back: counts[1], births: counts[2], deaths: counts[3], population: counts[4], activity }
+        }).map(|(generation, changes)| (generation as usize, changes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::automaton::CellAutomaton;
+    use super::*;
+    use crate::boundary::Boundary;
+    use crate::cpu::step_grid;
+    use crate::rule::Rule;
+    use crate::topology::Topology;
+
+    const SIZE: usize = 16;
+
+    // Steps `cells` until a verdict, giving up after `limit` generations
+    fn settle(mut cells: Vec<u32>, limit: usize) -> Option<Settled> {
+        let (mut history, mut steady) = (CpuHistory::default(), SteadyState::default());
+        for generation in 0..limit {
+            history.remember(generation, &cells);
+            cells = step_grid(&cells, SIZE, Rule::CONWAY, Boundary::Wrap, Topology::Square, CellAutomaton::Life);
+            let population = cells.iter().filter(|&&cell| cell > 0).count() as u32;
+            if let Some(settled) = steady.observe_population(population) { return Some(settled); }
+            let changes = history.changes(Automaton::Life, generation + 1, &cells, SIZE, (0, 0));
+            if let Some(settled) = changes.and_then(|changes| steady.observe_changes(changes)) { return Some(settled); }
+        }
+        None
+    }
+
+    fn grid(cells: &[(usize, usize)]) -> Vec<u32> {
+        let mut grid = vec![0; SIZE * SIZE];
+        for &(x, y) in cells {
+            grid[y * SIZE + x] = 1;
+        }
+        grid
+    }
+
+    #[test]
+    fn aging_block_is_stable() {
+        assert_eq!(settle(grid(&[(4, 4), (5, 4), (4, 5), (5, 5)]), 20), Some(Settled::Stable));
+    }
+
+    #[test]
+    fn blinker_is_period_two() {
+        assert_eq!(settle(grid(&[(6, 7), (7, 7), (8, 7)]), 20), Some(Settled::Oscillating));
+    }
+
+    #[test]
+    fn blinker_turns_over_two_cells() {
+        let horizontal = grid(&[(6, 7), (7, 7), (8, 7)]);
+        let vertical = step_grid(&horizontal, SIZE, Rule::CONWAY, Boundary::Wrap, Topology::Square, CellAutomaton::Life);
+        let changes = Changes::count(Automaton::Life, &vertical, &horizontal, &vertical, SIZE, (0, 0));
+        let activity = Activity { origin: (0, 0), sum_x: 6 + 8 + 7 + 7, sum_y: 7 + 7 + 6 + 8 };
+        assert_eq!(changes, Changes { since_previous: 4, since_two_back: 0, births: 2, deaths: 2, population: 3, activity });
+        assert_eq!(changes.centroid(SIZE as u32, SIZE as u32), Some((7.5, 7.5)));
+    }
+
+    #[test]
+    fn activity_wraps_around_the_origin() {
+        // A blinker across the left and right edges, measured from the middle column
+        let horizontal = grid(&[(15, 7), (0, 7), (1, 7)]);
+        let vertical = step_grid(&horizontal, SIZE, Rule::CONWAY, Boundary::Wrap, Topology::Square, CellAutomaton::Life);
+        let changes = Changes::count(Automaton::Life, &vertical, &horizontal, &vertical, SIZE, (8, 0));
+        assert_eq!(changes.centroid(SIZE as u32, SIZE as u32), Some((0.5, 7.5)));
+        assert_eq!(Changes::default().centroid(SIZE as u32, SIZE as u32), None);
+    }
+
+    #[test]
+    fn lone_cell_goes_extinct() {
+        assert_eq!(settle(grid(&[(3, 3)]), 20), Some(Settled::Extinct));
+    }
+
+    #[test]
+    fn glider_keeps_going() {
+        assert_eq!(settle(grid(&[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]), 40), None);
+    }
+}
@@ -0,0 +1,166 @@
+// Change detection, composed after rules.wgsl: counts cells of the latest generation whose state
+// differs from the generation before and from the one two back, plus births, deaths and population,
+// and sums the coordinates of the changed cells for the activity centroid. `main` adds with atomics; the
+// workgroup reduction sums each workgroup's cells in `reduce_tiles` and the workgroups' sums in
+// `reduce_partials` instead, like population.wgsl
+@group(0) @binding(0) var<storage, read> current: array<u32>;
+@group(0) @binding(1) var<storage, read> previous: array<u32>;
+@group(0) @binding(2) var<storage, read> two_back: array<u32>;
+@group(0) @binding(3) var<uniform> params: Params;
+// [since previous, since two back, births, deaths, population, x sum low, high, y sum low, high]
+@group(0) @binding(4) var<storage, read_write> changed: array<atomic<u32>, 9>;
+// Where coordinates are measured from, around the torus (xy, zw unused)
+@group(0) @binding(5) var<uniform> origin: vec4<u32>;
+// The workgroup reduction's sums, one per workgroup of the first dispatch
+@group(0) @binding(6) var<storage, read_write> partials: array<Tally>;
+
+// The first five of `changed` and the coordinate sums, which stay within a u32 over one workgroup's cells
+struct Tally {
+    counts: array<u32, 5>,
+    x: u32,
+    y: u32,
+}
+
+// The same over the whole grid, with the coordinate sums as (low, high) pairs
+struct Total {
+    counts: array<u32, 5>,
+    x: vec2<u32>,
+    y: vec2<u32>,
+}
+
+var<workgroup> tiles: array<Tally, 256>;
+var<workgroup> totals: array<Total, 256>;
+
+// Adds to the 64-bit counter at changed[low], changed[low + 1], carrying when the low word wraps
+fn add_wide(low: u32, value: u32) {
+    let before = atomicAdd(&changed[low], value);
+    if (before > 0xffffffffu - value) {
+        atomicAdd(&changed[low + 1u], 1u);
+    }
+}
+
+// (low, high) + (low, high), carrying the same way
+fn add_pair(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    let low = a.x + b.x;
+    return vec2<u32>(low, a.y + b.y + u32(low < a.x));
+}
+
+// What the cell at `index` adds to the counts, with its coordinates only if it changed
+fn tally(index: u32) -> Tally {
+    var t: Tally;
+    let cell = current[index];
+    let before = previous[index];
+    if (!same_state(cell, before)) {
+        t.counts[0] = 1u;
+        t.x = (index % params.width + params.width - origin.x) % params.width;
+        t.y = (index / params.width + params.height - origin.y) % params.height;
+    }
+    if (!same_state(cell, two_back[index])) {
+        t.counts[1] = 1u;
+    }
+    // Born and died mean started and stopped counting as a neighbour (firing, in Brian's Brain)
+    let counted = counts(cell);
+    let counted_before = counts(before);
+    if (counted > counted_before) {
+        t.counts[2] = 1u;
+    }
+    if (counted < counted_before) {
+        t.counts[3] = 1u;
+    }
+    if (cell > 0u && cell != WALL) {
+        t.counts[4] = 1u;
+    }
+    return t;
+}
+
+fn add_tallies(a: Tally, b: Tally) -> Tally {
+    var sum = a;
+    for (var i = 0u; i < 5u; i++) {
+        sum.counts[i] += b.counts[i];
+    }
+    sum.x += b.x;
+    sum.y += b.y;
+    return sum;
+}
+
+fn add_totals(a: Total, b: Total) -> Total {
+    var sum = a;
+    for (var i = 0u; i < 5u; i++) {
+        sum.counts[i] += b.counts[i];
+    }
+    sum.x = add_pair(a.x, b.x);
+    sum.y = add_pair(a.y, b.y);
+    return sum;
+}
+
+// Dispatched as a 2D grid of 1D workgroups like population.wgsl
+@compute @workgroup_size(256)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let index = global_id.y * groups.x * 256u + global_id.x;
+    if (index >= arrayLength(&current)) { return; }
+
+    let t = tally(index);
+    for (var i = 0u; i < 5u; i++) {
+        if (t.counts[i] > 0u) {
+            atomicAdd(&changed[i], 1u);
+        }
+    }
+    if (t.counts[0] > 0u) {
+        add_wide(5u, t.x);
+        add_wide(7u, t.y);
+    }
+}
+
+// First level of the workgroup reduction, dispatched like `main`: one partial sum per workgroup
+@compute @workgroup_size(256)
+fn reduce_tiles(@builtin(global_invocation_id) global_id: vec3<u32>, @builtin(local_invocation_index) local: u32, @builtin(workgroup_id) group: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let index = global_id.y * groups.x * 256u + global_id.x;
+    // No early return: the barriers need the whole workgroup
+    var t: Tally;
+    if (index < arrayLength(&current)) {
+        t = tally(index);
+    }
+    tiles[local] = t;
+    workgroupBarrier();
+    for (var stride = 128u; stride > 0u; stride >>= 1u) {
+        if (local < stride) {
+            tiles[local] = add_tallies(tiles[local], tiles[local + stride]);
+        }
+        workgroupBarrier();
+    }
+    if (local == 0u) {
+        partials[group.y * groups.x + group.x] = tiles[0];
+    }
+}
+
+// Second level, a single workgroup: each invocation sums every 256th partial, then the workgroup sums those
+@compute @workgroup_size(256)
+fn reduce_partials(@builtin(local_invocation_index) local: u32) {
+    var total: Total;
+    for (var i = local; i < arrayLength(&partials); i += 256u) {
+        let partial = partials[i];
+        for (var c = 0u; c < 5u; c++) {
+            total.counts[c] += partial.counts[c];
+        }
+        total.x = add_pair(total.x, vec2<u32>(partial.x, 0u));
+        total.y = add_pair(total.y, vec2<u32>(partial.y, 0u));
+    }
+    totals[local] = total;
+    workgroupBarrier();
+    for (var stride = 128u; stride > 0u; stride >>= 1u) {
+        if (local < stride) {
+            totals[local] = add_totals(totals[local], totals[local + stride]);
+        }
+        workgroupBarrier();
+    }
+    if (local == 0u) {
+        let sum = totals[0];
+        for (var c = 0u; c < 5u; c++) {
+            atomicStore(&changed[c], sum.counts[c]);
+        }
+        atomicStore(&changed[5], sum.x.x);
+        atomicStore(&changed[6], sum.x.y);
+        atomicStore(&changed[7], sum.y.x);
+        atomicStore(&changed[8], sum.y.y);
+    }
+}
@@ -0,0 +1,211 @@
+use std::ops::Range;
+use wgpu::util::DeviceExt;
+use crate::workgroup::WorkgroupSize;
+
+/// Side of the square chunks the GPU step skips or computes as a whole (matches chunks.wgsl and life.wgsl).
+pub const CHUNK_SIZE: u32 = 64;
+
+const LIST_WORKGROUP_SIZE: u32 = 64;
+
+// Uniform shared with chunks.wgsl and life.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ChunkParams {
+    columns: u32,
+    rows: u32,
+    groups: [u32; 2],
+}
+
+/// Chunks across and down a `width` x `height` grid; the ones on the right and bottom edges may be cut short.
+pub fn grid_chunks(width: u32, height: u32) -> (u32, u32) {
+    (width.div_ceil(CHUNK_SIZE), height.div_ceil(CHUNK_SIZE))
+}
+
+/// Indices of the chunks holding cells `offset..offset + len` of a `width`-wide grid `columns` chunks across:
+/// whole rows of chunks, which are contiguous.
+pub fn touched(width: usize, columns: usize, offset: usize, len: usize) -> Range<usize> {
+    if len == 0 { return 0..0; }
+    let chunk = CHUNK_SIZE as usize;
+    let (first, last) = (offset / width / chunk, (offset + len - 1) / width / chunk);
+    first * columns..(last + 1) * columns
+}
+
+/// Which chunks of the grid changed, so the step only computes the ones next to a change. Before each
+/// generation a small pass lists them and writes the workgroup counts for `dispatch_workgroups_indirect`;
+/// the step marks the chunks it changes for the next one. A skipped chunk holds the same cells in both
+/// ping-pong buffers, so leaving it alone is the same as computing it.
+///
+/// Anything written to the cells from outside the step has to `touch` the chunks it wrote.
+pub struct ChunkTracker {
+    columns: u32,
+    rows: u32,
+    // Chunks a generation changed, one per ping-pong buffer: the step writing buffer n marks `changed[n]`
+    changed: [wgpu::Buffer; 2],
+    // Chunks whose history cells may be behind, see `list_history` in chunks.wgsl
+    stale: wgpu::Buffer,
+    list: wgpu::Buffer,
+    dispatch: wgpu::Buffer,
+    // Chunks the latest generation computed, for the overlay
+    active: wgpu::Buffer,
+    params: wgpu::Buffer,
+    reset_pipeline: wgpu::ComputePipeline,
+    list_pipeline: wgpu::ComputePipeline,
+    list_history_pipeline: wgpu::ComputePipeline,
+    // Bind group n lists from `changed[n]` and clears the other one
+    bind_groups: [wgpu::BindGroup; 2],
+}
+
+impl ChunkTracker {
+    /// Everything starts out changed, so the first generation computes every chunk.
+    pub fn new(device: &wgpu::Device, width: u32, height: u32, workgroup: WorkgroupSize) -> ChunkTracker {
+        let (columns, rows) = grid_chunks(width, height);
+        let count = (columns * rows) as usize;
+        let flags = |label, contents: &[u32]| device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label), contents: bytemuck::cast_slice(contents), usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+        let changed = [flags("Changed Chunks A", &vec![1; count]), flags("Changed Chunks B", &vec![1; count])];
+        let stale = flags("Stale Chunks", &vec![1; count]);
+        let list = flags("Chunk List", &vec![0; count]);
+        let active = flags("Active Chunks", &vec![0; count]);
+        let dispatch = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Chunk Dispatch"), contents: bytemuck::cast_slice(&[0u32; 3]), usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::INDIRECT,
+        });
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Chunk Params"),
+            contents: bytemuck::bytes_of(&chunk_params(columns, rows, workgroup)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only }, has_dynamic_offset: false, min_binding_size: None }, count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                storage(0, true),
+                storage(1, false),
+                storage(2, false),
+                storage(3, false),
+                storage(4, false),
+                storage(5, false),
+                wgpu::BindGroupLayoutEntry { binding: 6, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            ],
+            label: None,
+        });
+        let bind_group = |from: &wgpu::Buffer, next: &wgpu::Buffer| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: from.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: next.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: stale.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: list.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 4, resource: dispatch.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 5, resource: active.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 6, resource: params.as_entire_binding() },
+            ],
+            label: None,
+        });
+        let bind_groups = [bind_group(&changed[0], &changed[1]), bind_group(&changed[1], &changed[0])];
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("chunks.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&layout], ..Default::default() });
+        let pipeline = |entry_point| device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Chunks"), layout: Some(&pipeline_layout), module: &shader, entry_point, compilation_options: Default::default(), cache: None,
+        });
+
+        ChunkTracker {
+            columns, rows, changed, stale, list, dispatch, active, params,
+            reset_pipeline: pipeline("reset"), list_pipeline: pipeline("list"), list_history_pipeline: pipeline("list_history"), bind_groups,
+        }
+    }
+
+    /// Whether the list fits in one indirect dispatch, which takes a chunk per workgroup in z.
+    pub fn fits(&self, limits: &wgpu::Limits) -> Result<(), String> {
+        let count = self.columns * self.rows;
+        if count > limits.max_compute_workgroups_per_dimension {
+            return Err(format!("the grid has {} chunks, more than the {} this adapter can dispatch at once", count, limits.max_compute_workgroups_per_dimension));
+        }
+        Ok(())
+    }
+
+    /// Matches the step's workgroups per chunk to a new workgroup size.
+    pub fn set_workgroup_size(&self, queue: &wgpu::Queue, workgroup: WorkgroupSize) {
+        queue.write_buffer(&self.params, 0, bytemuck::bytes_of(&chunk_params(self.columns, self.rows, workgroup)));
+    }
+
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    /// Marks `chunks` as changed in the buffer for `parity`, so the next generation computes them and their
+    /// neighbours, and their history as behind.
+    pub fn touch(&self, queue: &wgpu::Queue, parity: usize, chunks: Range<usize>) {
+        if chunks.is_empty() { return; }
+        let ones = vec![1u32; chunks.len()];
+        queue.write_buffer(&self.changed[parity], (chunks.start * 4) as u64, bytemuck::cast_slice(&ones));
+        queue.write_buffer(&self.stale, (chunks.start * 4) as u64, bytemuck::cast_slice(&ones));
+    }
+
+    pub fn touch_all(&self, queue: &wgpu::Queue, parity: usize) {
+        self.touch(queue, parity, 0..(self.columns * self.rows) as usize);
+    }
+
+    /// Clears the overlay's flags, for when the step stops skipping chunks.
+    pub fn clear_active(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.active, 0, bytemuck::cast_slice(&vec![0u32; (self.columns * self.rows) as usize]));
+    }
+
+    /// Lists the chunks the step reading buffer `parity` has to compute; `history` for the step that also
+    /// saves the history buffer. Leaves the pipeline and bind group to the caller to set again.
+    pub fn encode_list(&self, cpass: &mut wgpu::ComputePass, parity: usize, history: bool) {
+        cpass.set_bind_group(0, &self.bind_groups[parity], &[]);
+        cpass.set_pipeline(&self.reset_pipeline);
+        cpass.dispatch_workgroups(1, 1, 1);
+        cpass.set_pipeline(if history { &self.list_history_pipeline } else { &self.list_pipeline });
+        cpass.dispatch_workgroups((self.columns * self.rows).div_ceil(LIST_WORKGROUP_SIZE), 1, 1);
+    }
+
+    /// Workgroup counts for `dispatch_workgroups_indirect`, as of the last `encode_list`.
+    pub fn dispatch_buffer(&self) -> &wgpu::Buffer {
+        &self.dispatch
+    }
+
+    /// The chunks the generation reading buffer `parity` marks as changed.
+    pub fn changed_buffer(&self, parity: usize) -> &wgpu::Buffer {
+        &self.changed[parity ^ 1]
+    }
+
+    pub fn stale_buffer(&self) -> &wgpu::Buffer {
+        &self.stale
+    }
+
+    pub fn list_buffer(&self) -> &wgpu::Buffer {
+        &self.list
+    }
+
+    pub fn params_buffer(&self) -> &wgpu::Buffer {
+        &self.params
+    }
+
+    /// One u32 per chunk, 1 if the latest generation computed it.
+    pub fn active_buffer(&self) -> &wgpu::Buffer {
+        &self.active
+    }
+}
+
+fn chunk_params(columns: u32, rows: u32, workgroup: WorkgroupSize) -> ChunkParams {
+    ChunkParams { columns, rows, groups: [CHUNK_SIZE.div_ceil(workgroup.x), CHUNK_SIZE.div_ceil(workgroup.y)] }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn touched_covers_whole_chunk_rows() {
+        assert_eq!(grid_chunks(130, 64), (3, 1));
+        // Rows 63 and 64 straddle the first two rows of chunks
+        assert_eq!(touched(130, 3, 63 * 130 + 5, 130), 0..6);
+        assert_eq!(touched(130, 3, 64 * 130, 1), 3..6);
+        assert_eq!(touched(130, 3, 0, 0), 0..0);
+    }
+}
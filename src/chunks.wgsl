@@ -0,0 +1,69 @@
+// Dirty-chunk list: before each generation, picks the 64x64 chunks the step has to compute and writes
+// the indirect dispatch for them (see chunks.rs). A chunk is listed when it or one of its eight neighbours
+// changed in the previous generation; neighbours are taken around the torus whatever the boundary, which
+// only ever lists more than needed
+
+// Must match `ChunkParams` in chunks.rs
+struct Chunks {
+    columns: u32,
+    rows: u32,
+    // Workgroups of the step covering one chunk
+    groups: vec2<u32>,
+};
+
+// Bindings: which chunks changed in the previous generation, the flags the next step sets (cleared here),
+// chunks whose history cells are behind (see `list_history`), the list and dispatch being built, and which
+// chunks were listed, for the overlay
+@group(0) @binding(0) var<storage, read> changed: array<u32>;
+@group(0) @binding(1) var<storage, read_write> changed_next: array<u32>;
+@group(0) @binding(2) var<storage, read_write> stale: array<u32>;
+@group(0) @binding(3) var<storage, read_write> chunk_list: array<u32>;
+@group(0) @binding(4) var<storage, read_write> dispatch: array<atomic<u32>, 3>;
+@group(0) @binding(5) var<storage, read_write> listed: array<u32>;
+@group(0) @binding(6) var<uniform> chunks: Chunks;
+
+// One invocation before each list: the step's workgroups per chunk in x and y, and no chunks yet in z
+@compute @workgroup_size(1)
+fn reset() {
+    atomicStore(&dispatch[0], chunks.groups.x);
+    atomicStore(&dispatch[1], chunks.groups.y);
+    atomicStore(&dispatch[2], 0u);
+}
+
+fn near_change(column: u32, row: u32) -> bool {
+    for (var j = -1; j <= 1; j++) {
+        for (var i = -1; i <= 1; i++) {
+            let x = u32((i32(column) + i + i32(chunks.columns)) % i32(chunks.columns));
+            let y = u32((i32(row) + j + i32(chunks.rows)) % i32(chunks.rows));
+            if (changed[y * chunks.columns + x] != 0u) { return true; }
+        }
+    }
+    return false;
+}
+
+fn add(chunk: u32, compute: bool) {
+    listed[chunk] = select(0u, 1u, compute);
+    changed_next[chunk] = 0u;
+    if (compute) {
+        chunk_list[atomicAdd(&dispatch[2], 1u)] = chunk;
+    }
+}
+
+@compute @workgroup_size(64)
+fn list(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let chunk = global_id.x;
+    if (chunk >= chunks.columns * chunks.rows) { return; }
+    add(chunk, near_change(chunk % chunks.columns, chunk / chunks.columns));
+}
+
+// For the last generation of a frame, which also saves the generation two back into the history buffer.
+// Skipped chunks keep the history they had, so chunks that changed since their history was last written
+// are listed too. Afterwards a chunk is stale if it changed in the generation before this one; the steps
+// mark any later changes
+@compute @workgroup_size(64)
+fn list_history(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let chunk = global_id.x;
+    if (chunk >= chunks.columns * chunks.rows) { return; }
+    add(chunk, near_change(chunk % chunks.columns, chunk / chunks.columns) || stale[chunk] != 0u);
+    stale[chunk] = changed[chunk];
+}
@@ -0,0 +1,652 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use rust_gpu_life::ant::AntRule;
+use rust_gpu_life::automaton::Automaton;
+#[cfg(not(target_arch = "wasm32"))]
+use rust_gpu_life::autosave;
+use rust_gpu_life::boundary::Boundary;
+use rust_gpu_life::config;
+use rust_gpu_life::topology::Topology;
+use rust_gpu_life::cpu::CpuBackend;
+use rust_gpu_life::cyclic::CyclicRule;
+use rust_gpu_life::forest_fire::ForestFire;
+use rust_gpu_life::gray_scott::GrayScott;
+use rust_gpu_life::hooks::Hook;
+use rust_gpu_life::lenia::Lenia;
+use rust_gpu_life::ltl::LtlRule;
+use rust_gpu_life::population::Reduction;
+use rust_gpu_life::rps;
+use rust_gpu_life::rule::{self, Rule};
+use rust_gpu_life::workgroup::WorkgroupSize;
+use crate::adapter::{self, AdapterChoice};
+use crate::present;
+use crate::stats::StatsFormat;
+
+#[cfg(not(target_arch = "wasm32"))]
+const DEFAULT_GRID_SIZE: u32 = 1024 * 4;
+// Browsers cap WebGPU buffer sizes well below native drivers
+#[cfg(target_arch = "wasm32")]
+const DEFAULT_GRID_SIZE: u32 = 1024;
+
+// Options a --config file can't set
+const NOT_SETTINGS: [&str; 2] = ["config", "dump-config"];
+
+// The command line and --config file resolved into what the program runs with
+#[derive(Clone)]
+pub struct Args {
+    pub bench: bool,
+    pub bench_cpu: bool,
+    // `convert`: read the first pattern file and write it out as the second
+    pub convert: Option<(PathBuf, PathBuf)>,
+    pub steps: u32,
+    // Generations per submit in the batched benchmark
+    pub batches: Vec<u32>,
+    pub pattern: Option<PathBuf>,
+    // Where the pattern's top-left corner goes, instead of centering it
+    pub origin: Option<(i64, i64)>,
+    // Start from a picture instead: pixels at least `threshold` bright are live, optionally dithered.
+    // With --automaton cyclic, --threshold is the cyclic rule's instead
+    pub image: Option<PathBuf>,
+    pub threshold: f32,
+    pub dither: bool,
+    pub load: Option<PathBuf>,
+    pub record: Option<String>,
+    pub record_size: Option<(u32, u32)>,
+    pub stats_out: Option<String>,
+    pub stats_every: usize,
+    pub stats_format: StatsFormat,
+    pub rule: Rule,
+    pub boundary: Boundary,
+    pub topology: Topology,
+    pub automaton: Automaton,
+    // Only used with --automaton ltl
+    pub ltl: LtlRule,
+    // Only used with --automaton gray-scott
+    pub gray_scott: GrayScott,
+    // Only used with --automaton langtons-ant: the ants a soup starts with and how they turn
+    pub ants: u32,
+    pub ant_rule: AntRule,
+    // Only used with --automaton cyclic
+    pub cyclic: CyclicRule,
+    // Only used with --automaton rock-paper-scissors: predator neighbours that take a cell over, and
+    // three solid wedges instead of a soup
+    pub rps_threshold: u32,
+    pub wedges: bool,
+    // Only used with --automaton lenia, where --radius is the kernel's rather than Larger than Life's
+    pub lenia: Lenia,
+    // Only used with --automaton forest-fire; the dice are seeded with --seed, or the soup's seed
+    pub forest_fire: ForestFire,
+    pub cpu_backend: CpuBackend,
+    // Grid dimensions in cells; --grid-size sets both
+    pub width: u32,
+    pub height: u32,
+    pub seed: Option<u64>,
+    pub density: f32,
+    // Compare the GPU with a CPU shadow every this many generations
+    pub verify: Option<usize>,
+    // Run --steps generations on the GPU without a window
+    pub headless: bool,
+    // Cut the grid into this many horizontal bands; None takes the fewest the adapter's buffers hold
+    pub bands: Option<u32>,
+    // Draw the grid in the terminal instead of a window
+    pub tui: bool,
+    // Snapshots kept for stepping backwards, one every `history_every` generations at most
+    pub history: usize,
+    pub history_every: usize,
+    pub present_mode: wgpu::PresentMode,
+    // A preset or a theme from themes.toml
+    pub theme: String,
+    // Optional key remapping, read at startup
+    pub keybinds: PathBuf,
+    // Start with I's rain on: a random stamp every so often (see rain.toml)
+    pub rain: bool,
+    // Start with E's evolution mode on: a rule bit flips every `mutate_every` generations, and the rule
+    // before comes back if the population drops below `mutate_floor` of the grid
+    pub mutate: bool,
+    pub mutate_every: usize,
+    pub mutate_floor: f32,
+    // Condition and action pairs checked every frame, from --hook flags and the config file's hook lines
+    pub hooks: Vec<Hook>,
+    // Start in K's split view: the CPU thread's grid on the left, the GPU's on the right
+    pub split: bool,
+    // Print the adapters and exit
+    pub list_adapters: bool,
+    // None lets wgpu pick
+    pub adapter: Option<AdapterChoice>,
+    pub backends: wgpu::Backends,
+    // Log every edit to this file, or play back one logged before
+    pub record_session: Option<String>,
+    pub replay: Option<PathBuf>,
+    // Run soup after soup, `search_generations` each, logging how they end to `search_out`
+    pub soup_search: bool,
+    pub search_generations: usize,
+    pub search_out: String,
+    pub search_draw_every: u32,
+    // Save the soups still evolving at the end and unusually populous ones
+    pub search_save: bool,
+    // Workgroup dimensions of the generation step; --tune times the candidates and keeps the fastest
+    pub workgroup: Option<WorkgroupSize>,
+    pub tune: bool,
+    // Compute only the chunks next to last generation's changes on the GPU; off for benchmarking against every cell
+    pub chunk_skipping: bool,
+    // How the population and change counts are summed on the GPU
+    pub reduction: Reduction,
+    // Save the grid every `autosave_every` generations or `autosave_seconds`, whichever comes first; --resume loads it
+    pub autosave: bool,
+    pub autosave_every: usize,
+    pub autosave_seconds: u64,
+    // Let wgpu panic on GPU errors and a lost device, rather than logging them and setting the GPU up again
+    pub panic_on_error: bool,
+}
+
+// Every option lives here with its default; the subcommands only pick what runs
+#[derive(Parser)]
+#[command(about = "Conway's Game of Life and other cellular automata on the GPU", args_override_self = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Settings file of `key = value` lines named like the options (grid_size = 2048); flags given here win
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Print the settings after merging --config and the flags, in its format, and exit
+    #[arg(long, global = true)]
+    dump_config: bool,
+    #[command(flatten)]
+    options: Options,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Open the window (the default)
+    Run,
+    /// Time the backends without a window, ending with one JSON line per backend
+    Bench {
+        /// Only the CPU step, allocating vs in-place buffers; needs no GPU
+        #[arg(long)]
+        cpu: bool,
+    },
+    /// Run random soups one after another at full speed, logging how each ends
+    SoupSearch,
+    /// Convert a pattern file, by extension: .cells or .txt for plaintext, .lif or .life for Life 1.06, .mc for
+    /// macrocell (read only), RLE otherwise
+    Convert { input: PathBuf, output: PathBuf },
+}
+
+#[derive(clap::Args)]
+struct Options {
+    /// Generations for bench and --headless
+    #[arg(long, global = true, default_value_t = 1000, value_parser = at_least_one::<u32>)]
+    steps: u32,
+    /// Generations per submit in the batched benchmark, instead of 100 and then 1000
+    #[arg(long, global = true, value_parser = at_least_one::<u32>)]
+    batch: Option<u32>,
+    /// Start from a pattern file: RLE, plaintext (.cells), Life 1.06 (.lif) or macrocell (.mc)
+    #[arg(long, global = true)]
+    pattern: Option<PathBuf>,
+    /// Put the pattern's top-left corner at X,Y instead of centering it; cells past the grid's edges are left out
+    #[arg(long, global = true, value_parser = parse_origin)]
+    origin: Option<(i64, i64)>,
+    /// Start from a picture: pixels at least --threshold bright are live
+    #[arg(long, global = true)]
+    image: Option<PathBuf>,
+    /// Brightness for --image (0.5 unless given); with --automaton cyclic, the neighbours it takes to advance
+    #[arg(long, global = true)]
+    threshold: Option<String>,
+    /// Dither --image instead of thresholding it
+    #[arg(long, global = true)]
+    dither: bool,
+    /// Start from a saved state
+    #[arg(long, global = true)]
+    load: Option<PathBuf>,
+    /// Start from the autosave
+    #[arg(long, global = true)]
+    resume: bool,
+    /// Record to a video (or .gif) through ffmpeg
+    #[arg(long, global = true)]
+    record: Option<String>,
+    /// Video size, WIDTHxHEIGHT
+    #[arg(long, global = true, value_parser = parse_size)]
+    record_size: Option<(u32, u32)>,
+    /// Log generation statistics to this file
+    #[arg(long, global = true)]
+    stats_out: Option<String>,
+    #[arg(long, global = true, default_value_t = 1, value_parser = at_least_one::<usize>)]
+    stats_every: usize,
+    /// csv or jsonl
+    #[arg(long, global = true, default_value = "csv", value_parser = StatsFormat::parse)]
+    stats_format: StatsFormat,
+    /// A rule such as B36/S23 or 345/2/4, or a preset name such as day-night
+    #[arg(long, global = true, default_value = "B3/S23", value_parser = parse_rule)]
+    rule: Rule,
+    /// wrap, dead or mirror
+    #[arg(long, global = true, default_value = "wrap", value_parser = Boundary::parse)]
+    boundary: Boundary,
+    /// square or hex
+    #[arg(long, global = true, default_value = "square", value_parser = Topology::parse)]
+    topology: Topology,
+    #[arg(long, global = true, default_value = "life", value_parser = Automaton::parse)]
+    automaton: Automaton,
+    /// Neighbourhood radius for --automaton ltl, kernel radius for --automaton lenia
+    #[arg(long, global = true)]
+    radius: Option<String>,
+    /// Larger than Life birth range, such as 34-45
+    #[arg(long, global = true, value_parser = LtlRule::parse_range)]
+    birth: Option<(u32, u32)>,
+    /// Larger than Life survival range
+    #[arg(long, global = true, value_parser = LtlRule::parse_range)]
+    survive: Option<(u32, u32)>,
+    /// Gray-Scott feed rate
+    #[arg(long, global = true, value_parser = GrayScott::parse_rate)]
+    feed: Option<f32>,
+    /// Gray-Scott kill rate
+    #[arg(long, global = true, value_parser = GrayScott::parse_rate)]
+    kill: Option<f32>,
+    /// Ants a Langton's ant soup starts with
+    #[arg(long, global = true, default_value_t = 1, value_parser = at_least_one::<u32>)]
+    ants: u32,
+    /// How the ants turn on each colour, such as RL or LLRR
+    #[arg(long, global = true, default_value = "RL", value_parser = AntRule::parse)]
+    ant_rule: AntRule,
+    /// States of --automaton cyclic
+    #[arg(long, global = true, value_parser = CyclicRule::parse_states)]
+    states: Option<u32>,
+    /// Neighbours of its predator it takes to take a --automaton rock-paper-scissors cell over
+    #[arg(long, global = true, default_value_t = rps::THRESHOLD, value_parser = CyclicRule::parse_threshold)]
+    rps_threshold: u32,
+    /// Start --automaton rock-paper-scissors from three solid wedges meeting in the middle instead of a soup
+    #[arg(long, global = true)]
+    wedges: bool,
+    /// Lenia growth centre
+    #[arg(long, global = true, value_parser = Lenia::parse_fraction)]
+    mu: Option<f32>,
+    /// Lenia growth width
+    #[arg(long, global = true, value_parser = Lenia::parse_fraction)]
+    sigma: Option<f32>,
+    /// Lenia time step
+    #[arg(long, global = true, value_parser = Lenia::parse_fraction)]
+    dt: Option<f32>,
+    /// Chance an empty forest-fire cell grows a tree each generation
+    #[arg(long, global = true, default_value_t = ForestFire::DROSSEL_SCHWABL.growth, value_parser = ForestFire::parse_probability)]
+    growth: f32,
+    /// Chance a forest-fire tree is struck by lightning each generation
+    #[arg(long, global = true, default_value_t = ForestFire::DROSSEL_SCHWABL.lightning, value_parser = ForestFire::parse_probability)]
+    lightning: f32,
+    /// naive, bitpacked, sparse or auto
+    #[arg(long, global = true, default_value = "naive", value_parser = CpuBackend::parse)]
+    cpu_backend: CpuBackend,
+    /// Grid width and height in cells
+    #[arg(long, global = true, default_value_t = DEFAULT_GRID_SIZE, value_parser = at_least_one::<u32>)]
+    grid_size: u32,
+    #[arg(long, global = true, value_parser = at_least_one::<u32>)]
+    width: Option<u32>,
+    #[arg(long, global = true, value_parser = at_least_one::<u32>)]
+    height: Option<u32>,
+    /// Seed of the random soup
+    #[arg(long, global = true)]
+    seed: Option<u64>,
+    /// Share of live cells in the random soup
+    #[arg(long, global = true, default_value_t = 0.2, value_parser = fraction)]
+    density: f32,
+    /// Compare the GPU with a CPU shadow every this many generations
+    #[arg(long, global = true, value_parser = at_least_one::<usize>)]
+    verify: Option<usize>,
+    /// Run --steps generations on the GPU without a window
+    #[arg(long, global = true)]
+    headless: bool,
+    /// Horizontal bands the grid is cut into, headless or in the window (the fewest the adapter's buffers hold by default)
+    #[arg(long, global = true, value_parser = at_least_one::<u32>)]
+    bands: Option<u32>,
+    /// Draw the grid in the terminal instead of a window
+    #[arg(long, global = true)]
+    tui: bool,
+    /// Snapshots kept for stepping backwards (0 turns it off)
+    #[arg(long, global = true, default_value_t = 256)]
+    history: usize,
+    #[arg(long, global = true, default_value_t = 1, value_parser = at_least_one::<usize>)]
+    history_every: usize,
+    /// fifo, mailbox or immediate
+    #[arg(long, global = true, default_value = "fifo", value_parser = present::parse)]
+    present_mode: wgpu::PresentMode,
+    /// A preset or a theme from themes.toml
+    #[arg(long, global = true, default_value = "classic")]
+    theme: String,
+    /// Key remapping file
+    #[arg(long, global = true, default_value = "keybinds.toml")]
+    keybinds: PathBuf,
+    /// Start with rain on: a random stamp every so often (see rain.toml)
+    #[arg(long, global = true)]
+    rain: bool,
+    /// Start with the rule mutating: one birth or survival bit flips every --mutate-every generations
+    #[arg(long, global = true)]
+    mutate: bool,
+    #[arg(long, global = true, default_value_t = 500, value_parser = at_least_one::<usize>)]
+    mutate_every: usize,
+    /// Share of the grid alive below which a mutation is taken back
+    #[arg(long, global = true, default_value_t = 0.01, value_parser = fraction)]
+    mutate_floor: f32,
+    /// "CONDITION: ACTION", e.g. "population < 1%: reseed", "every 10000: screenshot" or "settled: next_rule"; repeatable
+    #[arg(long, global = true, value_parser = Hook::parse)]
+    hook: Vec<Hook>,
+    /// Start in the split view, the CPU's grid next to the GPU's
+    #[arg(long, global = true)]
+    split: bool,
+    /// Print the adapters and exit
+    #[arg(long, global = true)]
+    list_adapters: bool,
+    /// Adapter by its number in --list-adapters
+    #[arg(long, global = true)]
+    adapter: Option<usize>,
+    /// Adapter by part of its name
+    #[arg(long, global = true)]
+    adapter_name: Option<String>,
+    /// vulkan, dx12, metal or gl
+    #[arg(long, global = true, value_parser = adapter::parse_backends)]
+    backend: Option<wgpu::Backends>,
+    /// Log every edit to this file
+    #[arg(long, global = true)]
+    record_session: Option<String>,
+    /// Play back a session logged with --record-session
+    #[arg(long, global = true)]
+    replay: Option<PathBuf>,
+    /// Generations each soup-search soup runs at most
+    #[arg(long, global = true, default_value_t = 20_000, value_parser = at_least_one::<usize>)]
+    search_generations: usize,
+    #[arg(long, global = true, default_value = "soup_search.csv")]
+    search_out: String,
+    #[arg(long, global = true, default_value_t = 30, value_parser = at_least_one::<u32>)]
+    search_draw_every: u32,
+    /// Save the soups still running at the end and unusually populous ones
+    #[arg(long, global = true)]
+    search_save: bool,
+    /// Workgroup size of the generation step, WxH
+    #[arg(long, global = true, value_parser = WorkgroupSize::parse)]
+    workgroup: Option<WorkgroupSize>,
+    /// Time the workgroup sizes and keep the fastest
+    #[arg(long, global = true)]
+    tune: bool,
+    /// Compute every cell on the GPU, not only the chunks next to changes
+    #[arg(long, global = true)]
+    no_chunk_skipping: bool,
+    /// How the GPU sums the population and change counts: workgroup or atomic
+    #[arg(long, global = true, default_value = "workgroup", value_parser = Reduction::parse)]
+    reduction: Reduction,
+    /// Let wgpu panic on GPU errors and a lost device
+    #[arg(long, global = true)]
+    panic_on_error: bool,
+    #[arg(long, global = true)]
+    no_autosave: bool,
+    #[arg(long, global = true, default_value_t = 5000, value_parser = at_least_one::<usize>)]
+    autosave_every: usize,
+    #[arg(long, global = true, default_value_t = 300, value_parser = at_least_one::<u64>)]
+    autosave_seconds: u64,
+}
+
+impl Args {
+    // The command line over the --config file's settings; --help and --dump-config print and exit here
+    pub fn parse() -> Result<Args, String> {
+        let argv: Vec<OsString> = std::env::args_os().collect();
+        Cli::command().get_matches_from(&argv);
+        let matches = merged_matches(&argv)?;
+        let cli = Cli::from_arg_matches(&matches).map_err(|e| e.to_string())?;
+        if cli.dump_config {
+            print!("{}", dump(&matches));
+            std::process::exit(0);
+        }
+        Args::resolve(cli)
+    }
+
+    fn resolve(cli: Cli) -> Result<Args, String> {
+        let o = cli.options;
+        let (bench, soup_search) = (matches!(cli.command, Some(Command::Bench { .. })), matches!(cli.command, Some(Command::SoupSearch)));
+        let mut args = Args {
+            bench,
+            bench_cpu: matches!(cli.command, Some(Command::Bench { cpu: true })),
+            convert: match cli.command {
+                Some(Command::Convert { input, output }) => Some((input, output)),
+                _ => None,
+            },
+            steps: o.steps,
+            batches: o.batch.map_or(vec![100, 1000], |batch| vec![batch]),
+            pattern: o.pattern,
+            origin: o.origin,
+            image: o.image,
+            threshold: 0.5,
+            dither: o.dither,
+            load: o.load,
+            record: o.record,
+            record_size: o.record_size,
+            stats_out: o.stats_out,
+            stats_every: o.stats_every,
+            stats_format: o.stats_format,
+            rule: o.rule,
+            boundary: o.boundary,
+            topology: o.topology,
+            automaton: o.automaton,
+            ltl: LtlRule { birth: o.birth.unwrap_or(LtlRule::BOSCO.birth), survive: o.survive.unwrap_or(LtlRule::BOSCO.survive), ..LtlRule::BOSCO },
+            gray_scott: GrayScott { feed: o.feed.unwrap_or(GrayScott::CORAL.feed), kill: o.kill.unwrap_or(GrayScott::CORAL.kill), ..GrayScott::CORAL },
+            ants: o.ants,
+            ant_rule: o.ant_rule,
+            cyclic: CyclicRule { states: o.states.unwrap_or(CyclicRule::SPIRALS.states), ..CyclicRule::SPIRALS },
+            rps_threshold: o.rps_threshold,
+            wedges: o.wedges,
+            lenia: Lenia {
+                mu: o.mu.unwrap_or(Lenia::ORBIUM.mu),
+                sigma: o.sigma.unwrap_or(Lenia::ORBIUM.sigma),
+                dt: o.dt.unwrap_or(Lenia::ORBIUM.dt),
+                ..Lenia::ORBIUM
+            },
+            forest_fire: ForestFire { growth: o.growth, lightning: o.lightning, seed: o.seed.unwrap_or_default() },
+            cpu_backend: o.cpu_backend,
+            width: o.width.unwrap_or(o.grid_size),
+            height: o.height.unwrap_or(o.grid_size),
+            seed: o.seed,
+            density: o.density,
+            verify: o.verify,
+            headless: o.headless,
+            bands: o.bands,
+            tui: o.tui,
+            history: o.history,
+            history_every: o.history_every,
+            present_mode: o.present_mode,
+            theme: o.theme,
+            keybinds: o.keybinds,
+            rain: o.rain,
+            mutate: o.mutate,
+            mutate_every: o.mutate_every,
+            mutate_floor: o.mutate_floor,
+            hooks: o.hook,
+            split: o.split,
+            list_adapters: o.list_adapters,
+            adapter: o.adapter.map(AdapterChoice::Index).or(o.adapter_name.map(AdapterChoice::Name)),
+            backends: o.backend.unwrap_or(wgpu::Backends::all()),
+            record_session: o.record_session,
+            replay: o.replay,
+            soup_search,
+            search_generations: o.search_generations,
+            search_out: o.search_out,
+            search_draw_every: o.search_draw_every,
+            search_save: o.search_save,
+            workgroup: o.workgroup,
+            tune: o.tune,
+            chunk_skipping: !o.no_chunk_skipping,
+            reduction: o.reduction,
+            autosave: !o.no_autosave,
+            autosave_every: o.autosave_every,
+            autosave_seconds: o.autosave_seconds,
+            panic_on_error: o.panic_on_error,
+        };
+        if o.resume {
+            #[cfg(not(target_arch = "wasm32"))]
+            { args.load = Some(autosave::path().ok_or_else(|| "--resume can't find the cache directory (set XDG_CACHE_HOME or HOME)".to_string())?); }
+            #[cfg(target_arch = "wasm32")]
+            return Err("--resume only works outside the browser".to_string());
+        }
+        // Read once --automaton is known, wherever it came from
+        match o.threshold {
+            Some(text) if args.automaton == Automaton::Cyclic => args.cyclic.threshold = CyclicRule::parse_threshold(&text)?,
+            Some(_) if args.automaton == Automaton::RockPaperScissors => return Err("rock-paper-scissors takes its threshold from --rps-threshold".to_string()),
+            Some(text) => args.threshold = fraction(&text).map_err(|e| format!("--threshold: {}", e))?,
+            None => {}
+        }
+        if args.wedges && args.automaton != Automaton::RockPaperScissors {
+            return Err("--wedges only works with --automaton rock-paper-scissors".to_string());
+        }
+        if args.bands.is_some() && (args.tui || args.bench || args.bench_cpu) {
+            return Err("--bands only works with --headless and in the window".to_string());
+        }
+        if args.mutate && !matches!(args.automaton, Automaton::Life | Automaton::Generations) {
+            return Err("--mutate only works with Life-like rules".to_string());
+        }
+        if !args.hooks.is_empty() && (args.headless || args.tui) {
+            return Err("--hook only works in the window".to_string());
+        }
+        match o.radius {
+            Some(text) if args.automaton == Automaton::Lenia => args.lenia.radius = Lenia::parse_radius(&text)?,
+            Some(text) => args.ltl.radius = LtlRule::parse_radius(&text)?,
+            None => {}
+        }
+        Ok(args)
+    }
+}
+
+// The flags a --config file stands for: `key = value` as --key=value, with _ or - between words, and
+// `key = true` as a bare --key. Keys that aren't options are warned about and skipped
+// `argv` with the --config file's settings in front, so the same flags on the command line override them
+fn merged_matches(argv: &[OsString]) -> Result<ArgMatches, String> {
+    let matches = Cli::command().try_get_matches_from(argv).map_err(|e| e.to_string())?;
+    let Some(path) = Cli::from_arg_matches(&matches).map_err(|e| e.to_string())?.config else { return Ok(matches) };
+    let merged = argv.iter().take(1).cloned().chain(config_flags(&path)?).chain(argv.iter().skip(1).cloned());
+    Cli::command().try_get_matches_from(merged).map_err(|e| {
+        format!("{}: {}", path.display(), e.to_string().lines().next().unwrap_or_default().trim_start_matches("error: "))
+    })
+}
+
+fn config_flags(path: &Path) -> Result<Vec<OsString>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("could not read config '{}': {}", path.display(), e))?;
+    let command = Cli::command();
+    let mut flags = Vec::new();
+    for setting in config::parse(&text) {
+        let setting = match setting {
+            Ok(setting) => setting,
+            Err(e) => {
+                eprintln!("warning: {}: {}, ignored", path.display(), e);
+                continue;
+            }
+        };
+        let long = setting.key.replace('_', "-");
+        let Some(arg) = command.get_arguments().find(|arg| arg.get_long() == Some(long.as_str()) && !NOT_SETTINGS.contains(&long.as_str())) else {
+            eprintln!("warning: {}: unknown key '{}', ignored", path.display(), setting.key);
+            continue;
+        };
+        // A list is the flag repeated, as with --hook
+        for item in setting.value.items() {
+            let value = item.text().map_err(|e| format!("{}: {}", path.display(), setting.error(e)))?;
+            match (arg.get_action().takes_values(), value) {
+                (true, _) => flags.push(format!("--{}={}", long, value).into()),
+                (false, "true") => flags.push(format!("--{}", long).into()),
+                (false, "false") => {}
+                (false, _) => return Err(format!("{}: {} expects true or false, got '{}'", path.display(), setting.key, value)),
+            }
+        }
+    }
+    Ok(flags)
+}
+
+// Every option with a value, defaults included, as a --config file would set it; a repeated one like
+// --hook gets a line per value
+fn dump(matches: &ArgMatches) -> String {
+    Cli::command().get_arguments()
+        .filter(|arg| !NOT_SETTINGS.contains(&arg.get_id().as_str().replace('_', "-").as_str()))
+        .filter_map(|arg| Some((arg.get_long()?.replace('-', "_"), matches.get_raw(arg.get_id().as_str())?)))
+        .flat_map(|(key, values)| values.map(move |value| format!("{} = {}\n", key, config::quote(&value.to_string_lossy()))))
+        .collect()
+}
+
+fn parse_rule(text: &str) -> Result<Rule, String> {
+    match rule::rule_preset(text) {
+        Some(rule) => Ok(rule),
+        None => Rule::parse(text).map_err(|e| format!("{}, or a preset name such as day-night", e)),
+    }
+}
+
+fn at_least_one<T: std::str::FromStr + PartialOrd + From<u8>>(text: &str) -> Result<T, String> {
+    match text.parse() {
+        Ok(number) if number >= T::from(1) => Ok(number),
+        _ => Err(format!("expected a number of at least 1, got '{}'", text)),
+    }
+}
+
+fn fraction(text: &str) -> Result<f32, String> {
+    match text.parse() {
+        Ok(number) if (0.0..=1.0).contains(&number) => Ok(number),
+        _ => Err(format!("expected a number between 0 and 1, got '{}'", text)),
+    }
+}
+
+// "100,-20" -> (100, -20)
+fn parse_origin(text: &str) -> Result<(i64, i64), String> {
+    let parsed = text.split_once(',').map(|(x, y)| (x.trim().parse(), y.trim().parse()));
+    match parsed {
+        Some((Ok(x), Ok(y))) => Ok((x, y)),
+        _ => Err(format!("expected X,Y, got '{}'", text)),
+    }
+}
+
+// "1920x1080" -> (1920, 1080)
+fn parse_size(text: &str) -> Result<(u32, u32), String> {
+    let (width, height) = text.split_once('x').ok_or_else(|| format!("expected WIDTHxHEIGHT, got '{}'", text))?;
+    match (at_least_one(width), at_least_one(height)) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(format!("expected WIDTHxHEIGHT of at least 1x1, got '{}'", text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Args from a command line, with `config` written to a file for --config when given
+    fn parse_with(name: &str, config: Option<&str>, flags: &[&str]) -> Result<(Args, String), String> {
+        let path = std::env::temp_dir().join(format!("rust_gpu_life_cli_{}_{}.toml", name, std::process::id()));
+        let mut argv: Vec<OsString> = vec!["rust_gpu_life".into()];
+        if let Some(config) = config {
+            std::fs::write(&path, config).unwrap();
+            argv.push("--config".into());
+            argv.push(path.clone().into());
+        }
+        argv.extend(flags.iter().map(OsString::from));
+        let matches = merged_matches(&argv);
+        std::fs::remove_file(&path).ok();
+        let matches = matches?;
+        let dumped = dump(&matches);
+        Ok((Args::resolve(Cli::from_arg_matches(&matches).map_err(|e| e.to_string())?)?, dumped))
+    }
+
+    #[test]
+    fn flags_override_the_config_file() {
+        let config = "seed = 7\ndensity = 0.3\nhook = [\"every 10: screenshot\", \"settled: next_rule\"]\ncolour = 1\n";
+        let (args, _) = parse_with("precedence", Some(config), &["--seed", "9"]).unwrap();
+        assert_eq!((args.seed, args.density, args.hooks.len()), (Some(9), 0.3, 2));
+        let (args, _) = parse_with("untouched", Some(config), &[]).unwrap();
+        assert_eq!(args.seed, Some(7));
+    }
+
+    #[test]
+    fn dumped_settings_read_back_the_same() {
+        let (_, dumped) = parse_with("dump", None, &["--seed", "5", "--automaton", "rock-paper-scissors", "--wedges", "--rps-threshold", "3", "--hook", "every 10: screenshot"]).unwrap();
+        assert!(dumped.contains("seed = 5\n") && dumped.contains("hook = \"every 10: screenshot\"\n"));
+        let (args, again) = parse_with("dump_again", Some(&dumped), &[]).unwrap();
+        assert_eq!(again, dumped);
+        assert!(args.wedges && args.automaton == Automaton::RockPaperScissors && args.rps_threshold == 3);
+    }
+
+    #[test]
+    fn conflicting_settings_are_errors() {
+        let error = |name, config, flags| parse_with(name, config, flags).err().unwrap_or_default();
+        assert!(error("wedges", None, &["--wedges"]).contains("--wedges only works with --automaton rock-paper-scissors"));
+        assert!(error("bands", Some("bands = 4\n"), &["--tui"]).contains("--bands only works with --headless and in the window"));
+        assert!(error("bool", Some("wedges = 3\n"), &[]).contains("wedges expects true or false, got '3'"));
+        assert!(error("rps", None, &["--automaton", "rock-paper-scissors", "--threshold", "3"]).contains("--rps-threshold"));
+    }
+}
@@ -0,0 +1,238 @@
+use std::fmt;
+
+/// A value in a settings file: bare (a number, true or false), a quoted string, or a list of either in
+/// square brackets
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Bare(String),
+    Quoted(String),
+    List(Vec<Value>),
+}
+
+impl Value {
+    /// The text of a single value, whether it was quoted or not
+    pub fn text(&self) -> Result<&str, String> {
+        match self {
+            Value::Bare(text) | Value::Quoted(text) => Ok(text),
+            Value::List(_) => Err(format!("expected a single value, got {}", self)),
+        }
+    }
+
+    /// The text of a quoted value
+    pub fn quoted(&self) -> Result<&str, String> {
+        match self {
+            Value::Quoted(text) => Ok(text),
+            _ => Err(format!("expected a quoted value, got {}", self)),
+        }
+    }
+
+    /// A list's items, or a single value as a list of one
+    pub fn items(&self) -> &[Value] {
+        match self {
+            Value::List(items) => items,
+            single => std::slice::from_ref(single),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Bare(text) => f.write_str(text),
+            Value::Quoted(text) => write!(f, "\"{}\"", text),
+            Value::List(items) => write!(f, "[{}]", items.iter().map(Value::to_string).collect::<Vec<_>>().join(", ")),
+        }
+    }
+}
+
+/// One `key = value` line of a settings file, with the key's quotes taken off
+#[derive(Clone, PartialEq, Debug)]
+pub struct Setting {
+    /// From 1
+    pub number: usize,
+    /// The line without its comment, for messages about it
+    pub text: String,
+    pub key: String,
+    pub value: Value,
+}
+
+impl Setting {
+    /// `message` about this line, worded like the ones `parse` returns
+    pub fn error(&self, message: impl fmt::Display) -> String {
+        line_error(self.number, &self.text, message)
+    }
+}
+
+/// A line of a file read with `parse_sections`
+#[derive(Clone, PartialEq, Debug)]
+pub enum Line {
+    /// `[name]`, which the settings after it belong to
+    Section(String),
+    Setting(Setting),
+}
+
+/// The `key = value` lines of a settings file, in order: the part of TOML every settings file here uses
+/// (--config, keybinds, themes, saved rules, rain), where a key is bare or quoted, a value is bare, quoted
+/// or a list, and # outside quotes starts a comment. A bad line comes back as a message saying what's
+/// wrong with it, as does a `[name]` line, which only a file read with `parse_sections` has.
+pub fn parse(text: &str) -> Vec<Result<Setting, String>> {
+    lines(text).map(|(number, content)| match content.starts_with('[') {
+        true => Err(line_error(number, content, "expected key = value, not a [name] line")),
+        false => parse_line(number, content).map_err(|e| line_error(number, content, e)),
+    }).collect()
+}
+
+/// `parse` for a file of `[name]` lines, each followed by the settings that belong to it
+pub fn parse_sections(text: &str) -> Vec<Result<Line, String>> {
+    lines(text).map(|(number, content)| match content.strip_prefix('[') {
+        Some(name) => name.strip_suffix(']').map(|name| Line::Section(name.trim().to_string())).ok_or_else(|| line_error(number, content, "expected [name]")),
+        None => parse_line(number, content).map(Line::Setting).map_err(|e| line_error(number, content, e)),
+    }).collect()
+}
+
+// The lines with something on them, numbered from 1, without their comments
+fn lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines().enumerate().map(|(number, line)| (number + 1, strip_comment(line).trim())).filter(|(_, content)| !content.is_empty())
+}
+
+fn line_error(number: usize, text: &str, message: impl fmt::Display) -> String {
+    format!("line {} ({}): {}", number, text, message)
+}
+
+fn parse_line(number: usize, line: &str) -> Result<Setting, String> {
+    // A quoted key may hold = and # too
+    let (key, value) = match line.strip_prefix('"') {
+        Some(quoted) => {
+            let (key, rest) = quoted.split_once('"').ok_or("unterminated key")?;
+            (key, rest.trim_start().strip_prefix('=').ok_or("expected key = value")?)
+        }
+        None => line.split_once('=').map(|(key, value)| (key.trim(), value)).ok_or("expected key = value")?,
+    };
+    if key.is_empty() { return Err("expected a key before =".to_string()); }
+    let value = value.trim();
+    let value = match value.strip_prefix('[') {
+        Some(list) => {
+            let items = list.strip_suffix(']').ok_or("unterminated list")?;
+            let mut items: Vec<&str> = split_outside_quotes(items, ',').into_iter().map(str::trim).collect();
+            // [] and a trailing comma leave an empty last item
+            if items.last() == Some(&"") { items.pop(); }
+            Value::List(items.into_iter().map(parse_value).collect::<Result<_, _>>()?)
+        }
+        None => parse_value(value)?,
+    };
+    Ok(Setting { number, text: line.to_string(), key: key.to_string(), value })
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    match text.strip_prefix('"') {
+        Some(quoted) => {
+            let (inner, rest) = quoted.split_once('"').ok_or("unterminated string")?;
+            let rest = rest.trim();
+            if !rest.is_empty() {
+                return Err(format!("unexpected '{}' after the string", rest));
+            }
+            Ok(Value::Quoted(inner.to_string()))
+        }
+        None if text.is_empty() => Err("expected a value".to_string()),
+        None if text.starts_with('[') => Err("lists can't hold lists".to_string()),
+        None => Ok(Value::Bare(text.to_string())),
+    }
+}
+
+// The pieces of `text` between each `separator` outside quotes
+fn split_outside_quotes(text: &str, separator: char) -> Vec<&str> {
+    let mut quoted = false;
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => {
+                pieces.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    pieces.push(&text[start..]);
+    pieces
+}
+
+/// `line` up to the first # outside quotes, so a # in a quoted string (a name, a colour) isn't a comment
+pub fn strip_comment(line: &str) -> &str {
+    split_outside_quotes(line, '#')[0]
+}
+
+/// `value` the way `parse` reads it back: numbers, true and false bare, anything else quoted
+pub fn quote(value: &str) -> String {
+    match value.parse::<f64>().is_ok() || value == "true" || value == "false" {
+        true => value.to_string(),
+        false => format!("\"{}\"", value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(lines: &[Result<Setting, String>]) -> Vec<(&str, String)> {
+        lines.iter().flatten().map(|setting| (setting.key.as_str(), setting.value.to_string())).collect()
+    }
+
+    fn errors<T>(lines: &[Result<T, String>]) -> Vec<&String> {
+        lines.iter().filter_map(|line| line.as_ref().err()).collect()
+    }
+
+    #[test]
+    fn settings_read_back_what_quote_writes() {
+        let text = "# a comment\ngrid_size = 2048\ntheme = \"ocean #2\" # quoted\nrain = true\n\nseed 3\nrule = \"B3/S23\n";
+        let lines = parse(text);
+        assert_eq!(pairs(&lines), [("grid_size", "2048".to_string()), ("theme", "\"ocean #2\"".to_string()), ("rain", "true".to_string())]);
+        assert_eq!(lines[1].as_ref().unwrap().value.quoted(), Ok("ocean #2"));
+        let errors = errors(&lines);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("line 6") && errors[1].contains("unterminated"));
+
+        for value in ["2048", "0.2", "true", "B36/S23", "run.csv"] {
+            let lines = parse(&format!("key = {}", quote(value)));
+            assert_eq!(lines[0].as_ref().unwrap().value.text(), Ok(value));
+        }
+        assert_eq!(quote("classic"), "\"classic\"");
+    }
+
+    #[test]
+    fn quoted_keys_keep_their_equals_and_hashes() {
+        let lines = parse("\"B = 3 #1\" = \"B3/S23\" # comment\n\"open = \"B3/S23\"\n\"\" = 1\n");
+        assert_eq!(pairs(&lines), [("B = 3 #1", "\"B3/S23\"".to_string())]);
+        let errors = errors(&lines);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("expected key = value") && errors[1].contains("expected a key"));
+    }
+
+    #[test]
+    fn lists_split_outside_quotes() {
+        let lines = parse("keys = [\"Ctrl+KeyS\", \"a, b\", 3,] # three\nnone = []\nbad = [\"open\nnested = [[1]]\nhole = [1, , 2]\n");
+        let settings: Vec<&Setting> = lines.iter().flatten().collect();
+        assert_eq!(settings[0].value, Value::List(vec![Value::Quoted("Ctrl+KeyS".to_string()), Value::Quoted("a, b".to_string()), Value::Bare("3".to_string())]));
+        assert_eq!(settings[1].value.items(), []);
+        assert_eq!(Value::Bare("1".to_string()).items(), [Value::Bare("1".to_string())]);
+        assert!(settings[0].value.text().is_err());
+        let errors = errors(&lines);
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].starts_with("line 3") && errors[1].contains("lists can't hold lists") && errors[2].contains("expected a value"));
+    }
+
+    #[test]
+    fn sections_and_settings_come_in_file_order() {
+        let lines = parse_sections("[ocean]\nbase = \"dark\"\n[ dusk ]\n[broken\nlive = [\"#000000\"]\n");
+        assert_eq!(lines[0], Ok(Line::Section("ocean".to_string())));
+        assert!(matches!(&lines[1], Ok(Line::Setting(setting)) if setting.key == "base" && setting.number == 2));
+        assert_eq!(lines[2], Ok(Line::Section("dusk".to_string())));
+        assert!(lines[3].as_ref().unwrap_err().starts_with("line 4"));
+        assert!(matches!(&lines[4], Ok(Line::Setting(setting)) if setting.key == "live"));
+
+        // Files without sections don't take them
+        let lines = parse("[ocean]\nbase = \"dark\"\n");
+        assert!(lines[0].is_err() && lines[1].is_ok());
+    }
+}
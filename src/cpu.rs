@@ -0,0 +1,524 @@
+use std::fmt;
+use rayon::prelude::*;
+use crate::ant::{self, Ant, AntRule};
+use crate::automaton::{Automaton, CellAutomaton, GridAutomaton, Stepping};
+use crate::boundary::Boundary;
+use crate::cyclic::CyclicRule;
+use crate::forest_fire::ForestFire;
+use crate::gray_scott::GrayScott;
+use crate::lenia::Lenia;
+use crate::ltl::LtlRule;
+use crate::regions::RuleMap;
+use crate::rule::Rule;
+use crate::topology::Topology;
+use crate::sparse::AUTO_SHARE;
+use crate::{BitpackedSimulation, Simulation, SparseSimulation};
+
+/// Which CPU implementation runs the simulation
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CpuBackend {
+    /// One `u32` per cell, tracks age
+    Naive,
+    /// 64 cells per `u64`, liveness only
+    Bitpacked,
+    /// Only the occupied cells and their neighbours, from a list of them
+    Sparse,
+    /// Sparse while at most 1 in `AUTO_SHARE` cells is occupied, the whole grid otherwise
+    Auto,
+}
+
+impl CpuBackend {
+    pub fn parse(text: &str) -> Result<CpuBackend, String> {
+        match text {
+            "naive" => Ok(CpuBackend::Naive),
+            "bitpacked" => Ok(CpuBackend::Bitpacked),
+            "sparse" => Ok(CpuBackend::Sparse),
+            "auto" => Ok(CpuBackend::Auto),
+            _ => Err(format!("invalid CPU backend '{}' (expected naive, bitpacked, sparse or auto)", text)),
+        }
+    }
+
+    pub fn next(self) -> CpuBackend {
+        match self {
+            CpuBackend::Naive => CpuBackend::Bitpacked,
+            CpuBackend::Bitpacked => CpuBackend::Sparse,
+            CpuBackend::Sparse => CpuBackend::Auto,
+            CpuBackend::Auto => CpuBackend::Naive,
+        }
+    }
+
+    /// The backend that runs `automaton` when this one is picked: automata that don't fit in two bit planes
+    /// (see `Automaton::packs`) get the naive backend instead of the bitpacked one, and those that step
+    /// whole grids (see `Automaton::stepping`) instead of the sparse or auto one.
+    pub fn for_automaton(self, automaton: Automaton) -> CpuBackend {
+        let listed = matches!(automaton.stepping(), Stepping::Cell(_));
+        match self {
+            CpuBackend::Bitpacked if !automaton.packs() => CpuBackend::Naive,
+            CpuBackend::Sparse | CpuBackend::Auto if !listed => CpuBackend::Naive,
+            backend => backend,
+        }
+    }
+
+    /// A simulation on `for_automaton`'s backend.
+    pub fn create(self, width: u32, height: u32, rule: Rule, boundary: Boundary, automaton: Automaton, cells: Vec<u32>) -> Box<dyn Simulation + Send> {
+        match self.for_automaton(automaton) {
+            CpuBackend::Naive => Box::new(CpuSimulation::new(width, height, rule, boundary, automaton, cells)),
+            CpuBackend::Bitpacked => Box::new(BitpackedSimulation::new(width, height, rule, boundary, automaton, &cells)),
+            CpuBackend::Sparse => Box::new(SparseSimulation::new(width, height, rule, boundary, automaton, cells, None)),
+            CpuBackend::Auto => {
+                let limit = cells.len() / AUTO_SHARE;
+                Box::new(SparseSimulation::new(width, height, rule, boundary, automaton, cells, Some(limit)))
+            }
+        }
+    }
+}
+
+impl fmt::Display for CpuBackend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            CpuBackend::Naive => "naive",
+            CpuBackend::Bitpacked => "bitpacked",
+            CpuBackend::Sparse => "sparse",
+            CpuBackend::Auto => "auto",
+        })
+    }
+}
+
+/// Multi-threaded CPU backend using Rayon.
+pub struct CpuSimulation {
+    cells: Vec<u32>,
+    // Where the next generation is written before the two are swapped, so stepping never allocates
+    spare: Vec<u32>,
+    // Larger than Life's row sums, kept for the next step
+    prefix: Vec<u32>,
+    // Gray-Scott's u and v at full precision and where the next ones go, empty until it runs
+    concentrations: Vec<(f32, f32)>,
+    spare_concentrations: Vec<(f32, f32)>,
+    width: u32,
+    height: u32,
+    rule: Rule,
+    boundary: Boundary,
+    topology: Topology,
+    automaton: Automaton,
+    ltl: LtlRule,
+    gray_scott: GrayScott,
+    ant_rule: AntRule,
+    cyclic: CyclicRule,
+    rps_threshold: u32,
+    lenia: Lenia,
+    forest_fire: ForestFire,
+    // The latest generation's number, which the forest fire's dice are hashed with
+    generation: u64,
+    // In the order they move; found in the grid on the first step, then kept up to date through writes
+    ants: Option<Vec<Ant>>,
+    // Rules of their own for some tiles, while the automaton is Life
+    regions: Option<RuleMap>,
+    // Rows that differed before and after a step, until `take_changed_rows`
+    changed: Vec<bool>,
+}
+
+impl CpuSimulation {
+    pub fn new(width: u32, height: u32, rule: Rule, boundary: Boundary, automaton: Automaton, cells: Vec<u32>) -> CpuSimulation {
+        assert_eq!(cells.len(), width as usize * height as usize, "grid must be width x height cells");
+        CpuSimulation { spare: vec![0; cells.len()], prefix: Vec::new(), concentrations: Vec::new(), spare_concentrations: Vec::new(), cells, width, height, rule, boundary, topology: Topology::Square, automaton, ltl: LtlRule::BOSCO, gray_scott: GrayScott::CORAL, ant_rule: AntRule::LANGTON, cyclic: CyclicRule::SPIRALS, rps_threshold: crate::rps::THRESHOLD, lenia: Lenia::ORBIUM, forest_fire: ForestFire::DROSSEL_SCHWABL, generation: 0, ants: None, regions: None, changed: vec![false; height as usize] }
+    }
+}
+
+impl Simulation for CpuSimulation {
+    fn step(&mut self) {
+        let width = self.width as usize;
+        match (self.automaton.stepping(), &self.regions) {
+            // Ants walk one at a time, in place
+            (Stepping::Grid(GridAutomaton::LangtonsAnt), _) => {
+                let ants = self.ants.get_or_insert_with(|| ant::find(&self.cells, width));
+                ant::step(&mut self.cells, width, self.ant_rule, self.boundary, ants, &mut self.changed);
+                self.generation += 1;
+                return;
+            }
+            (Stepping::Grid(GridAutomaton::LargerThanLife), _) => crate::ltl::step_grid_into(&self.cells, &mut self.spare, &mut self.prefix, width, self.ltl, self.boundary),
+            (Stepping::Grid(GridAutomaton::GrayScott), _) => {
+                // Zeroes are only what a cell without u or v holds, so the first step starts from the cells
+                self.concentrations.resize(self.cells.len(), (0.0, 0.0));
+                self.spare_concentrations.resize(self.cells.len(), (0.0, 0.0));
+                crate::gray_scott::step_grid_into(&self.cells, &mut self.spare, &self.concentrations, &mut self.spare_concentrations, width, self.gray_scott, self.boundary);
+                std::mem::swap(&mut self.concentrations, &mut self.spare_concentrations);
+            }
+            (Stepping::Grid(GridAutomaton::Cyclic), _) => crate::cyclic::step_grid_into(&self.cells, &mut self.spare, width, self.cyclic, self.boundary),
+            (Stepping::Grid(GridAutomaton::Lenia), _) => crate::lenia::step_grid_into(&self.cells, &mut self.spare, width, self.lenia, self.boundary),
+            (Stepping::Grid(GridAutomaton::ForestFire), _) => crate::forest_fire::step_grid_into(&self.cells, &mut self.spare, width, self.forest_fire, self.boundary, self.generation),
+            (Stepping::Grid(GridAutomaton::RockPaperScissors), _) => crate::rps::step_grid_into(&self.cells, &mut self.spare, width, self.rps_threshold, self.boundary),
+            // Rules of their own for some tiles only apply to Life
+            (Stepping::Cell(CellAutomaton::Life), Some(regions)) => step_regions_into(&self.cells, &mut self.spare, width, self.rule, regions, self.boundary, self.topology),
+            (Stepping::Cell(automaton), _) => step_grid_into(&self.cells, &mut self.spare, width, self.rule, self.boundary, self.topology, automaton),
+        }
+        // Live Life cells age every generation, so only rows without any stay unchanged
+        self.changed.par_iter_mut().zip(self.cells.par_chunks(width).zip(self.spare.par_chunks(width)))
+            .for_each(|(changed, (before, after))| *changed |= before != after);
+        std::mem::swap(&mut self.cells, &mut self.spare);
+        self.generation += 1;
+    }
+
+    fn cells(&self) -> &[u32] {
+        &self.cells
+    }
+
+    fn set_cells(&mut self, offset: usize, cells: &[u32]) {
+        self.cells[offset..offset + cells.len()].copy_from_slice(cells);
+        if let Some(ants) = &mut self.ants {
+            ant::written(ants, &self.cells, self.width as usize, offset..offset + cells.len());
+        }
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn set_rule(&mut self, rule: Rule) {
+        self.rule = rule;
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) {
+        self.boundary = boundary;
+    }
+
+    fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    fn set_automaton(&mut self, automaton: Automaton) {
+        self.automaton = automaton;
+        self.ants = None;
+        if automaton != Automaton::GrayScott {
+            self.concentrations = Vec::new();
+            self.spare_concentrations = Vec::new();
+        }
+    }
+
+    fn set_ltl_rule(&mut self, ltl: LtlRule) {
+        self.ltl = ltl;
+    }
+
+    fn set_gray_scott(&mut self, params: GrayScott) {
+        self.gray_scott = params;
+    }
+
+    fn set_ant_rule(&mut self, rule: AntRule) {
+        self.ant_rule = rule;
+    }
+
+    fn set_cyclic_rule(&mut self, rule: CyclicRule) {
+        self.cyclic = rule;
+    }
+
+    fn set_rps_threshold(&mut self, threshold: u32) {
+        self.rps_threshold = threshold;
+    }
+
+    fn set_lenia(&mut self, params: Lenia) {
+        self.lenia = params;
+    }
+
+    fn set_forest_fire(&mut self, params: ForestFire) {
+        self.forest_fire = params;
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    fn set_rule_map(&mut self, map: Option<&RuleMap>) {
+        self.regions = map.cloned();
+    }
+
+    fn take_changed_rows(&mut self) -> Option<Vec<bool>> {
+        Some(std::mem::replace(&mut self.changed, vec![false; self.height as usize]))
+    }
+}
+
+/// One generation of a `width`-wide grid with the one-cell neighbourhood of `topology`; the automata that
+/// step whole grids have a `step_grid` of their own (see `GridAutomaton`).
+pub fn step_grid(input: &[u32], width: usize, rule: Rule, boundary: Boundary, topology: Topology, automaton: CellAutomaton) -> Vec<u32> {
+    let mut output = vec![0; input.len()];
+    step_grid_into(input, &mut output, width, rule, boundary, topology, automaton);
+    output
+}
+
+/// `step_grid` writing into `output` (the same length as `input`) instead of allocating.
+pub fn step_grid_into(input: &[u32], output: &mut [u32], width: usize, rule: Rule, boundary: Boundary, topology: Topology, automaton: CellAutomaton) {
+    step_rows(input, output, width, boundary, topology, automaton, |_, _| rule);
+}
+
+/// One Life generation with each cell under the rule `regions` gives its tile, `rule` where it gives the grid's.
+pub fn step_regions_into(input: &[u32], output: &mut [u32], width: usize, rule: Rule, regions: &RuleMap, boundary: Boundary, topology: Topology) {
+    step_rows(input, output, width, boundary, topology, CellAutomaton::Life, |x, y| regions.rule_at(x, y, rule));
+}
+
+// The step itself, taking each cell's rule from `rule_at(x, y)`
+fn step_rows(input: &[u32], output: &mut [u32], width: usize, boundary: Boundary, topology: Topology, automaton: CellAutomaton, rule_at: impl Fn(usize, usize) -> Rule + Sync) {
+    let height = input.len() / width;
+    output.par_chunks_mut(width).enumerate().for_each(|(y, out)| {
+        // Whole rows as slices, `None` past a dead edge
+        let row = |dy: i64| boundary.resolve(y as i64 + dy, height).map(|ny| &input[ny * width..(ny + 1) * width]);
+        let current = &input[y * width..(y + 1) * width];
+        let rows = [row(-1), Some(current), row(1)];
+        let offsets = topology.offsets(y);
+
+        for (x, cell) in out.iter_mut().enumerate() {
+            let neighbors = offsets.iter().map(|&(dx, dy)| match (rows[(dy + 1) as usize], boundary.resolve(x as i64 + dx, width)) {
+                (Some(row), Some(column)) => automaton.neighbor_weight(row[column]),
+                _ => 0,
+            }).sum();
+            *cell = automaton.next_state(rule_at(x, y), current[x], neighbors);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WALL;
+
+    const SIZE: usize = 16;
+
+    fn grid(cells: &[(usize, usize)]) -> Vec<u32> {
+        let mut grid = vec![0; SIZE * SIZE];
+        for &(x, y) in cells {
+            grid[y * SIZE + x] = 1;
+        }
+        grid
+    }
+
+    fn live_cells(grid: &[u32]) -> Vec<(usize, usize)> {
+        (0..grid.len()).filter(|&i| grid[i] > 0).map(|i| (i % SIZE, i / SIZE)).collect()
+    }
+
+    fn run(cells: &[(usize, usize)], generations: usize) -> Vec<u32> {
+        (0..generations).fold(grid(cells), |g, _| step_grid(&g, SIZE, Rule::CONWAY, Boundary::Wrap, Topology::Square, CellAutomaton::Life))
+    }
+
+    fn sorted(mut cells: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+        cells.sort_by_key(|&(x, y)| (y, x));
+        cells
+    }
+
+    #[test]
+    fn blinker_oscillates() {
+        let horizontal = [(6, 7), (7, 7), (8, 7)];
+        assert_eq!(live_cells(&run(&horizontal, 1)), sorted(vec![(7, 6), (7, 7), (7, 8)]));
+        assert_eq!(live_cells(&run(&horizontal, 2)), sorted(horizontal.to_vec()));
+    }
+
+    #[test]
+    fn blinker_wraps_horizontally() {
+        let horizontal = [(15, 5), (0, 5), (1, 5)];
+        assert_eq!(live_cells(&run(&horizontal, 1)), sorted(vec![(0, 4), (0, 5), (0, 6)]));
+        assert_eq!(live_cells(&run(&horizontal, 2)), sorted(horizontal.to_vec()));
+    }
+
+    #[test]
+    fn block_is_still_and_ages() {
+        let block = [(4, 4), (5, 4), (4, 5), (5, 5)];
+        let after = run(&block, 3);
+        assert_eq!(live_cells(&after), sorted(block.to_vec()));
+        assert!(live_cells(&after).iter().all(|&(x, y)| after[y * SIZE + x] == 4));
+    }
+
+    #[test]
+    fn block_straddling_the_corner_is_still() {
+        let block = [(15, 15), (0, 15), (15, 0), (0, 0)];
+        assert_eq!(live_cells(&run(&block, 4)), sorted(block.to_vec()));
+    }
+
+    #[test]
+    fn glider_moves_diagonally() {
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let moved = glider.iter().map(|&(x, y)| (x + 1, y + 1)).collect();
+        assert_eq!(live_cells(&run(&glider, 4)), sorted(moved));
+    }
+
+    #[test]
+    fn glider_crosses_the_corner() {
+        let glider: Vec<_> = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)].iter().map(|&(x, y)| ((x + 14) % SIZE, (y + 14) % SIZE)).collect();
+        let moved = glider.iter().map(|&(x, y)| ((x + 1) % SIZE, (y + 1) % SIZE)).collect();
+        assert_eq!(live_cells(&run(&glider, 4)), sorted(moved));
+    }
+
+    #[test]
+    fn blinker_wraps_vertically_on_a_wide_grid() {
+        let (width, height) = (24, 10);
+        let mut cells = vec![0; width * height];
+        for y in [9, 0, 1] {
+            cells[y * width + 3] = 1;
+        }
+        let next = step_grid(&cells, width, Rule::CONWAY, Boundary::Wrap, Topology::Square, CellAutomaton::Life);
+        let live: Vec<usize> = (0..next.len()).filter(|&i| next[i] > 0).collect();
+        assert_eq!(live, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn dead_edges_cut_off_the_blinker() {
+        let straddling = grid(&[(15, 5), (0, 5), (1, 5)]);
+        assert!(live_cells(&step_grid(&straddling, SIZE, Rule::CONWAY, Boundary::Dead, Topology::Square, CellAutomaton::Life)).is_empty());
+    }
+
+    #[test]
+    fn mirrored_edges_reflect_the_domino() {
+        // The reflection supplies the missing third cell of a blinker
+        let domino = grid(&[(0, 5), (1, 5)]);
+        assert_eq!(live_cells(&step_grid(&domino, SIZE, Rule::CONWAY, Boundary::Mirror, Topology::Square, CellAutomaton::Life)), vec![(0, 4), (0, 5), (0, 6)]);
+    }
+
+    #[test]
+    fn hex_cells_have_six_neighbours() {
+        // Under B1/S a lone cell dies and its neighbours are born; odd rows are shifted right, so their
+        // diagonal neighbours are the same column and the one to the right
+        let odd = grid(&[(7, 7)]);
+        let next = step_grid(&odd, SIZE, Rule::parse("B1/S").unwrap(), Boundary::Wrap, Topology::Hex, CellAutomaton::Life);
+        assert_eq!(live_cells(&next), vec![(7, 6), (8, 6), (6, 7), (8, 7), (7, 8), (8, 8)]);
+        let even = grid(&[(7, 8)]);
+        let next = step_grid(&even, SIZE, Rule::parse("B1/S").unwrap(), Boundary::Wrap, Topology::Hex, CellAutomaton::Life);
+        assert_eq!(live_cells(&next), vec![(6, 7), (7, 7), (6, 8), (8, 8), (6, 9), (7, 9)]);
+    }
+
+    #[test]
+    fn brians_brain_cycles_through_refractory() {
+        // Two firing cells light up the two cells beside them, then go refractory
+        let pair = grid(&[(7, 7), (8, 7)]);
+        let next = step_grid(&pair, SIZE, Rule::CONWAY, Boundary::Wrap, Topology::Square, CellAutomaton::BriansBrain);
+        assert_eq!((next[7 * SIZE + 7], next[7 * SIZE + 8]), (2, 2));
+        assert_eq!((next[6 * SIZE + 7], next[6 * SIZE + 8], next[8 * SIZE + 7], next[8 * SIZE + 8]), (1, 1, 1, 1));
+        let after = step_grid(&next, SIZE, Rule::CONWAY, Boundary::Wrap, Topology::Square, CellAutomaton::BriansBrain);
+        assert_eq!((after[7 * SIZE + 7], after[7 * SIZE + 8]), (0, 0));
+    }
+
+    #[test]
+    fn generations_cells_fade_before_dying() {
+        // A lone Star Wars cell fades through both extra states; the two cells beside it would be born next
+        // to two live neighbours, but not while they're fading
+        let star_wars = Rule::parse("345/2/4").unwrap();
+        let mut cells = grid(&[(7, 7)]);
+        (cells[7 * SIZE + 6], cells[7 * SIZE + 8]) = (2, 3);
+        cells[6 * SIZE + 7] = 1;
+        let next = step_grid(&cells, SIZE, star_wars, Boundary::Wrap, Topology::Square, CellAutomaton::Generations);
+        assert_eq!(&next[7 * SIZE + 6..7 * SIZE + 9], &[3, 2, 0]);
+        let after = step_grid(&next, SIZE, star_wars, Boundary::Wrap, Topology::Square, CellAutomaton::Generations);
+        assert_eq!(&after[7 * SIZE + 6..7 * SIZE + 9], &[0, 3, 0]);
+    }
+
+    #[test]
+    fn generations_2_3_is_brians_brain() {
+        let brians_brain = Rule::parse("/2/3").unwrap();
+        let mut generations = crate::random_grid(SIZE * SIZE, 7, 0.3);
+        let mut brain = generations.clone();
+        for _ in 0..20 {
+            generations = step_grid(&generations, SIZE, brians_brain, Boundary::Wrap, Topology::Square, CellAutomaton::Generations);
+            brain = step_grid(&brain, SIZE, Rule::CONWAY, Boundary::Wrap, Topology::Square, CellAutomaton::BriansBrain);
+            assert_eq!(generations, brain);
+        }
+    }
+
+    #[test]
+    fn wireworld_electron_runs_along_the_wire() {
+        // Tail, head, then conductor to the right edge of row 7
+        let mut wire = vec![0; SIZE * SIZE];
+        wire[7 * SIZE..8 * SIZE].fill(1);
+        (wire[7 * SIZE], wire[7 * SIZE + 1]) = (3, 2);
+        let next = step_grid(&wire, SIZE, Rule::CONWAY, Boundary::Dead, Topology::Square, CellAutomaton::Wireworld);
+        assert_eq!(&next[7 * SIZE..7 * SIZE + 4], &[1, 3, 2, 1]);
+        assert!(next[..7 * SIZE].iter().chain(&next[8 * SIZE..]).all(|&cell| cell == 0));
+    }
+
+    #[test]
+    fn immigration_births_take_the_majority_colour() {
+        // Two blue parents and one red around (1, 1)
+        let mut cells = vec![0; SIZE * SIZE];
+        (cells[0], cells[2], cells[2 * SIZE + 1]) = (2, 2, 1);
+        let next = step_grid(&cells, SIZE, Rule::CONWAY, Boundary::Dead, Topology::Square, CellAutomaton::Immigration);
+        assert_eq!(next[SIZE + 1], 2);
+        // Two reds and one blue
+        (cells[0], cells[2]) = (1, 1);
+        cells[2 * SIZE + 1] = 2;
+        let next = step_grid(&cells, SIZE, Rule::CONWAY, Boundary::Dead, Topology::Square, CellAutomaton::Immigration);
+        assert_eq!(next[SIZE + 1], 1);
+    }
+
+    #[test]
+    fn walls_never_change_and_count_as_dead() {
+        // A blinker made of walls, with one live cell beside it that has no live neighbours
+        let mut cells = vec![0; SIZE * SIZE];
+        for y in 1..4 {
+            cells[y * SIZE + 2] = WALL;
+        }
+        cells[2 * SIZE + 3] = 1;
+        let next = step_grid(&cells, SIZE, Rule::CONWAY, Boundary::Wrap, Topology::Square, CellAutomaton::Life);
+        let walls: Vec<u32> = cells.iter().map(|&cell| if cell == WALL { WALL } else { 0 }).collect();
+        assert_eq!(next, walls);
+    }
+
+    #[test]
+    fn only_the_rows_a_glider_touches_change() {
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        let mut simulation = CpuSimulation::new(SIZE as u32, SIZE as u32, Rule::CONWAY, Boundary::Wrap, Automaton::Life, grid(&glider));
+        simulation.step();
+        let rows = simulation.take_changed_rows().unwrap();
+        assert_eq!(crate::row_runs(&rows, SIZE), vec![(0, 4 * SIZE)]);
+        assert!(simulation.take_changed_rows().unwrap().iter().all(|&changed| !changed));
+    }
+
+    #[test]
+    fn population_never_exceeds_the_grid() {
+        for seed in 0..32 {
+            let mut cells = crate::random_grid(SIZE * SIZE, seed, seed as f32 / 31.0);
+            for _ in 0..8 {
+                cells = step_grid(&cells, SIZE, Rule::CONWAY, Boundary::Wrap, Topology::Square, CellAutomaton::Life);
+                assert_eq!(cells.len(), SIZE * SIZE);
+                assert!(cells.iter().filter(|&&c| c > 0).count() <= SIZE * SIZE);
+            }
+        }
+    }
+
+    #[test]
+    fn rects_wrap_around_the_corner() {
+        let mut simulation = CpuSimulation::new(SIZE as u32, SIZE as u32, Rule::CONWAY, Boundary::Wrap, Automaton::Life, vec![0; SIZE * SIZE]);
+        let block: Vec<u32> = (1..=9).collect();
+        simulation.set_rect(SIZE - 1, SIZE - 2, 3, &block);
+        assert_eq!(simulation.cells()[(SIZE - 2) * SIZE + SIZE - 1], 1);
+        assert_eq!(simulation.cells()[(SIZE - 2) * SIZE + 1], 3);
+        assert_eq!(simulation.cells()[1], 9);
+        assert_eq!(simulation.read_rect(SIZE - 1, SIZE - 2, 3, 3), block);
+    }
+
+    #[test]
+    fn backends_fall_back_to_naive_for_what_they_cant_run() {
+        assert_eq!(CpuBackend::Bitpacked.for_automaton(Automaton::Wireworld), CpuBackend::Bitpacked);
+        assert_eq!(CpuBackend::Bitpacked.for_automaton(Automaton::GrayScott), CpuBackend::Naive);
+        assert_eq!(CpuBackend::Bitpacked.for_automaton(Automaton::Generations), CpuBackend::Naive);
+        assert_eq!(CpuBackend::Sparse.for_automaton(Automaton::Generations), CpuBackend::Sparse);
+        assert_eq!(CpuBackend::Auto.for_automaton(Automaton::LargerThanLife), CpuBackend::Naive);
+    }
+
+    #[test]
+    fn regions_step_under_their_own_rules() {
+        // The same block in a Conway tile and in a Seeds tile
+        let (width, height) = (64, 32);
+        let mut cells = vec![0; width * height];
+        for (x, y) in [(10, 10), (11, 10), (10, 11), (11, 11)] {
+            cells[y * width + x] = 1;
+            cells[y * width + x + 32] = 1;
+        }
+        let mut regions = RuleMap::new(width as u32, height as u32);
+        regions.paint(40, 10, 0, Some(Rule::parse("B2/S").unwrap())).unwrap();
+        let mut simulation = CpuSimulation::new(width as u32, height as u32, Rule::CONWAY, Boundary::Wrap, Automaton::Life, cells);
+        simulation.set_rule_map(Some(&regions));
+        simulation.step();
+        let next = simulation.cells();
+        assert_eq!(next[10 * width + 10], 2);
+        assert_eq!(next[10 * width + 42], 0);
+        // Seeds births around the block that just died
+        assert_eq!(next[9 * width + 42], 1);
+    }
+}
@@ -0,0 +1,521 @@
+use std::collections::VecDeque;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{self, Receiver, Sender};
+use web_time::{Duration, Instant};
+use rust_gpu_life::{ant::AntRule, automaton::Automaton, boundary::Boundary, changes::{Changes, CpuHistory}, cyclic::CyclicRule, forest_fire::ForestFire, gray_scott::GrayScott, lenia::Lenia, ltl::LtlRule, regions::RuleMap, rule::Rule, topology::Topology, transform::Transform, Simulation};
+
+// The CPU simulation, off the event loop: a thread owns the backend and steps it, sending generations back
+// as they're done. The event loop keeps the latest one it got to read, draw and edit, so a slow step never
+// holds up input or resizing. Edits go to both, in order: the thread makes each once it has stepped the
+// generations asked for before it, and the generations it sends back from before an edit are stale. The
+// browser has no threads, so there the same steps run inline, within a frame budget.
+
+pub type Backend = Box<dyn Simulation + Send>;
+type Edit = Box<dyn FnOnce(&mut dyn Simulation) + Send>;
+
+// Longest the thread steps before sending a generation back, even with more owed
+#[cfg(not(target_arch = "wasm32"))]
+const SEND_INTERVAL: Duration = Duration::from_millis(16);
+// Longest a browser frame spends stepping; whatever is left over is dropped
+#[cfg(target_arch = "wasm32")]
+const FRAME_BUDGET: Duration = Duration::from_millis(50);
+
+// What a frame asks of the CPU: generations to step on top of those still owed, and what to count them with
+#[derive(Clone, Copy)]
+pub struct Run {
+    pub generations: u32,
+    pub automaton: Automaton,
+    // See `ChangeCounter::encode`
+    pub origin: (u32, u32),
+    // The diff view needs the generation before the latest too
+    pub previous: bool,
+}
+
+// Every command but `Run` carries the sequence number of the edit, so generations stepped before it can be told apart
+enum Command {
+    Edit(u64, Edit),
+    // Another backend, or the grid replaced at a generation of the event loop's choosing: steps owed or
+    // not yet sent back are forgotten
+    Jump(u64, Option<Backend>),
+    Run(Run),
+}
+
+struct Generation {
+    seq: u64,
+    // Steps since the last one sent
+    steps: usize,
+    cells: Vec<u32>,
+    rows: Option<Vec<bool>>,
+    changes: Option<Changes>,
+    previous: Option<Vec<u32>>,
+    // Spent stepping them
+    busy: Duration,
+    // See `Simulation::stepping`
+    stepping: Option<&'static str>,
+}
+
+// The side that steps: the backend, the generations owed and the two before the latest for change detection
+struct Engine {
+    sim: Backend,
+    seq: u64,
+    owed: u32,
+    run: Option<Run>,
+    steps: usize,
+    // Counts every step, to number the generations in `history`
+    stepped: usize,
+    history: CpuHistory,
+    // Time spent stepping since the last generation sent
+    busy: Duration,
+    // Commands waiting for the generations asked for before them
+    waiting: VecDeque<Command>,
+}
+
+impl Engine {
+    fn new(sim: Backend) -> Engine {
+        Engine { sim, seq: 0, owed: 0, run: None, steps: 0, stepped: 0, history: CpuHistory::default(), busy: Duration::ZERO, waiting: VecDeque::new() }
+    }
+
+    fn handle(&mut self, command: Command) {
+        // A jump forgets the generations asked for before it, so nothing waits for them; edits made before
+        // it still are
+        if let Command::Jump(..) = command {
+            self.owed = 0;
+            self.waiting.retain(|waiting| !matches!(waiting, Command::Run(_)));
+        }
+        self.waiting.push_back(command);
+        self.advance();
+    }
+
+    // Carries out the waiting commands in order, each once everything owed before it has been stepped
+    fn advance(&mut self) {
+        while self.owed == 0 {
+            let Some(command) = self.waiting.pop_front() else { return };
+            match command {
+                Command::Edit(seq, edit) => {
+                    edit(self.sim.as_mut());
+                    self.seq = seq;
+                    self.history.clear();
+                }
+                Command::Jump(seq, backend) => {
+                    if let Some(backend) = backend { self.sim = backend; }
+                    self.seq = seq;
+                    self.steps = 0;
+                    self.history.clear();
+                }
+                Command::Run(run) => {
+                    self.owed = run.generations;
+                    self.run = Some(run);
+                }
+            }
+        }
+    }
+
+    // Change detection compares the latest generation with the two before it; the diff view needs the one
+    // before whichever step turns out to be the last
+    fn step(&mut self) {
+        if self.owed <= 2 || self.run.is_some_and(|run| run.previous) { self.history.remember(self.stepped, self.sim.cells()); }
+        self.sim.step();
+        self.stepped += 1;
+        self.steps += 1;
+        self.owed -= 1;
+        if self.owed == 0 { self.advance(); }
+    }
+
+    fn send(&mut self) -> Generation {
+        let cells = self.sim.cells();
+        let (width, generation) = (self.sim.width() as usize, self.stepped);
+        let changes = self.run.and_then(|run| self.history.changes(run.automaton, generation, cells, width, run.origin));
+        let previous = self.run.filter(|run| run.previous).and_then(|_| self.history.previous(generation)).map(<[u32]>::to_vec);
+        Generation { seq: self.seq, steps: std::mem::take(&mut self.steps), cells: cells.to_vec(), rows: self.sim.take_changed_rows(), changes, previous, busy: std::mem::take(&mut self.busy), stepping: self.sim.stepping() }
+    }
+}
+
+// Steps until told to stop, sending each batch back; idles while nothing is owed
+#[cfg(not(target_arch = "wasm32"))]
+fn work(mut engine: Engine, commands: Receiver<Command>, generations: Sender<Generation>) {
+    loop {
+        if engine.owed == 0 {
+            let Ok(command) = commands.recv() else { return };
+            engine.handle(command);
+        }
+        for command in commands.try_iter() {
+            engine.handle(command);
+        }
+        if engine.owed == 0 { continue; }
+        let start = Instant::now();
+        while engine.owed > 0 && start.elapsed() < SEND_INTERVAL {
+            engine.step();
+        }
+        engine.busy += start.elapsed();
+        if generations.send(engine.send()).is_err() { return; }
+    }
+}
+
+// What came back since the last `receive`
+pub struct Received {
+    pub steps: usize,
+    // The latest generation replaced the grid, so `take_changed_rows` has rows to upload
+    pub fresh: bool,
+    pub changes: Option<Changes>,
+    pub previous: Option<Vec<u32>>,
+    // Time the thread spent stepping them
+    pub busy: Duration,
+}
+
+// The event loop's side: the latest generation back from the thread, with edits made on top
+pub struct CpuWorker {
+    cells: Vec<u32>,
+    width: u32,
+    height: u32,
+    // Sequence numbers of the latest edit and the latest jump; generations stepped before them are stale
+    seq: u64,
+    jump_seq: u64,
+    // Rows changed by the generations received since the last `take_changed_rows`
+    rows: Option<Vec<bool>>,
+    // Generations asked for and not yet received
+    pending: usize,
+    // Kept for the blocking `step`
+    last_run: Run,
+    // How the thread stepped the latest generation
+    stepping: Option<&'static str>,
+    #[cfg(not(target_arch = "wasm32"))]
+    commands: Sender<Command>,
+    #[cfg(not(target_arch = "wasm32"))]
+    generations: Receiver<Generation>,
+    #[cfg(target_arch = "wasm32")]
+    engine: Engine,
+    #[cfg(target_arch = "wasm32")]
+    generations: VecDeque<Generation>,
+}
+
+impl CpuWorker {
+    pub fn new(sim: Backend) -> CpuWorker {
+        let (cells, width, height, stepping) = (sim.cells().to_vec(), sim.width(), sim.height(), sim.stepping());
+        let rows = Some(vec![false; height as usize]);
+        // Replaced by the first `run`
+        let last_run = Run { generations: 0, automaton: Automaton::Life, origin: (0, 0), previous: false };
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let (commands, received) = mpsc::channel();
+            let (sent, generations) = mpsc::channel();
+            let engine = Engine::new(sim);
+            std::thread::Builder::new().name("cpu simulation".to_string()).spawn(move || work(engine, received, sent)).expect("could not start the CPU simulation thread");
+            CpuWorker { cells, width, height, seq: 0, jump_seq: 0, rows, pending: 0, last_run, stepping, commands, generations }
+        }
+        #[cfg(target_arch = "wasm32")]
+        CpuWorker { cells, width, height, seq: 0, jump_seq: 0, rows, pending: 0, last_run, stepping, engine: Engine::new(sim), generations: VecDeque::new() }
+    }
+
+    fn command(&mut self, command: Command) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            // The thread only stops once this side is dropped
+            let _ = self.commands.send(command);
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let run = matches!(command, Command::Run(_));
+            self.engine.handle(command);
+            if !run { return; }
+            let start = Instant::now();
+            while self.engine.owed > 0 && start.elapsed() < FRAME_BUDGET {
+                self.engine.step();
+            }
+            self.engine.busy += start.elapsed();
+            // Falling behind shows up as the achieved rate, not as an ever-growing debt
+            self.engine.owed = 0;
+            if self.engine.steps > 0 {
+                let generation = self.engine.send();
+                self.generations.push_back(generation);
+            }
+            self.pending = self.generations.iter().map(|generation| generation.steps).sum();
+        }
+    }
+
+    // Runs `edit` on the thread's grid after everything sent before; `cells` says it changes cells, which
+    // makes generations stepped before it stale
+    fn edit(&mut self, cells: bool, edit: impl FnOnce(&mut dyn Simulation) + Send + 'static) {
+        if cells { self.seq += 1; }
+        self.command(Command::Edit(self.seq, Box::new(edit)));
+    }
+
+    // Shifts, turns or mirrors the grid the thread has when it gets to the edit, which may be a later
+    // generation than the one here
+    pub fn transform(&mut self, transform: Transform) {
+        let (width, height) = (self.width as usize, self.height as usize);
+        self.cells = transform.apply(&self.cells, width, height);
+        self.edit(true, move |sim| {
+            let cells = transform.apply(sim.cells(), width, height);
+            sim.set_cells(0, &cells);
+        });
+    }
+
+    // Forgets the generations on their way, for when the grid is replaced wholesale or the GPU takes over;
+    // with `backend` the thread steps that from now on.
+    pub fn jump(&mut self, backend: Option<Backend>) {
+        if let Some(backend) = &backend {
+            self.cells.copy_from_slice(backend.cells());
+            self.stepping = backend.stepping();
+        }
+        self.seq += 1;
+        self.jump_seq = self.seq;
+        self.pending = 0;
+        self.command(Command::Jump(self.seq, backend));
+    }
+
+    // Asks for more generations; at most `run.generations` are ever on their way, so a thread that falls
+    // behind isn't handed an ever-growing debt.
+    pub fn run(&mut self, mut run: Run) {
+        self.last_run = run;
+        run.generations = run.generations.saturating_sub(self.pending.min(u32::MAX as usize) as u32);
+        if run.generations == 0 { return; }
+        self.pending += run.generations as usize;
+        self.command(Command::Run(run));
+    }
+
+    // Generations asked for and not yet received.
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    // Takes in what the thread sent back; `None` if nothing came.
+    pub fn receive(&mut self) -> Option<Received> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let generations: Vec<Generation> = self.generations.try_iter().collect();
+        #[cfg(target_arch = "wasm32")]
+        let generations: Vec<Generation> = self.generations.drain(..).collect();
+        (!generations.is_empty()).then(|| self.take_in(generations))
+    }
+
+    // Like `receive`, but waits for every generation on its way first, so an edit made next lands on the
+    // generation the event loop counts.
+    pub fn settle(&mut self) -> Option<Received> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mut generations = Vec::new();
+            let mut waiting = self.pending;
+            while waiting > 0 {
+                let Ok(generation) = self.generations.recv() else { break };
+                if generation.seq >= self.jump_seq { waiting = waiting.saturating_sub(generation.steps); }
+                generations.push(generation);
+            }
+            generations.extend(self.generations.try_iter());
+            (!generations.is_empty()).then(|| self.take_in(generations))
+        }
+        #[cfg(target_arch = "wasm32")]
+        self.receive()
+    }
+
+    fn take_in(&mut self, generations: Vec<Generation>) -> Received {
+        let mut received = Received { steps: 0, fresh: false, changes: None, previous: None, busy: Duration::ZERO };
+        for generation in generations.into_iter().filter(|generation| generation.seq >= self.jump_seq) {
+            self.pending = self.pending.saturating_sub(generation.steps);
+            received.steps += generation.steps;
+            received.busy += generation.busy;
+            self.stepping = generation.stepping;
+            self.rows = match (self.rows.take(), generation.rows) {
+                (Some(mut rows), Some(changed)) => {
+                    rows.iter_mut().zip(changed).for_each(|(row, changed)| *row |= changed);
+                    Some(rows)
+                }
+                _ => None,
+            };
+            // Anything edited since isn't in these cells yet, the next generation brings both
+            if generation.seq == self.seq {
+                self.cells = generation.cells;
+                received.fresh = true;
+                received.changes = generation.changes;
+                received.previous = generation.previous;
+            }
+        }
+        received
+    }
+}
+
+impl Simulation for CpuWorker {
+    // Blocks until the thread has stepped; the event loop uses `run` and `receive` instead
+    fn step(&mut self) {
+        self.run(Run { generations: self.pending as u32 + 1, ..self.last_run });
+        self.settle();
+    }
+
+    fn cells(&self) -> &[u32] {
+        &self.cells
+    }
+
+    fn set_cells(&mut self, offset: usize, cells: &[u32]) {
+        self.cells[offset..offset + cells.len()].copy_from_slice(cells);
+        let cells = cells.to_vec();
+        self.edit(true, move |sim| sim.set_cells(offset, &cells));
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn set_rule(&mut self, rule: Rule) {
+        self.edit(false, move |sim| sim.set_rule(rule));
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) {
+        self.edit(false, move |sim| sim.set_boundary(boundary));
+    }
+
+    fn set_topology(&mut self, topology: Topology) {
+        self.edit(false, move |sim| sim.set_topology(topology));
+    }
+
+    fn set_automaton(&mut self, automaton: Automaton) {
+        self.edit(false, move |sim| sim.set_automaton(automaton));
+    }
+
+    fn set_ltl_rule(&mut self, ltl: LtlRule) {
+        self.edit(false, move |sim| sim.set_ltl_rule(ltl));
+    }
+
+    fn set_gray_scott(&mut self, params: GrayScott) {
+        self.edit(false, move |sim| sim.set_gray_scott(params));
+    }
+
+    fn set_ant_rule(&mut self, rule: AntRule) {
+        self.edit(false, move |sim| sim.set_ant_rule(rule));
+    }
+
+    fn set_cyclic_rule(&mut self, rule: CyclicRule) {
+        self.edit(false, move |sim| sim.set_cyclic_rule(rule));
+    }
+
+    fn set_rps_threshold(&mut self, threshold: u32) {
+        self.edit(false, move |sim| sim.set_rps_threshold(threshold));
+    }
+
+    fn set_lenia(&mut self, params: Lenia) {
+        self.edit(false, move |sim| sim.set_lenia(params));
+    }
+
+    fn set_forest_fire(&mut self, params: ForestFire) {
+        self.edit(false, move |sim| sim.set_forest_fire(params));
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.edit(false, move |sim| sim.set_generation(generation));
+    }
+
+    fn set_rule_map(&mut self, map: Option<&RuleMap>) {
+        let map = map.cloned();
+        self.edit(false, move |sim| sim.set_rule_map(map.as_ref()));
+    }
+
+    fn take_changed_rows(&mut self) -> Option<Vec<bool>> {
+        self.rows.replace(vec![false; self.height as usize])
+    }
+
+    fn stepping(&self) -> Option<&'static str> {
+        self.stepping
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use super::*;
+    use rust_gpu_life::CpuSimulation;
+
+    const SIZE: u32 = 16;
+
+    fn blinker() -> Vec<u32> {
+        let mut cells = vec![0; (SIZE * SIZE) as usize];
+        for x in 6..9 { cells[(7 * SIZE + x) as usize] = 1; }
+        cells
+    }
+
+    fn backend(cells: Vec<u32>) -> Backend {
+        Box::new(CpuSimulation::new(SIZE, SIZE, Rule::CONWAY, Boundary::Wrap, Automaton::Life, cells))
+    }
+
+    fn run(generations: u32) -> Command {
+        Command::Run(Run { generations, automaton: Automaton::Life, origin: (0, 0), previous: false })
+    }
+
+    // Sets the cell at the top-left corner, far from the blinker
+    fn mark(seq: u64) -> Command {
+        Command::Edit(seq, Box::new(|sim: &mut dyn Simulation| sim.set_cells(0, &[1])))
+    }
+
+    fn step_all(engine: &mut Engine) {
+        while engine.owed > 0 { engine.step(); }
+    }
+
+    #[test]
+    fn edits_land_after_the_generations_asked_for_before_them() {
+        let mut engine = Engine::new(backend(blinker()));
+        // The edit notes the cells it's made on
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let noted = seen.clone();
+        engine.handle(run(3));
+        engine.handle(Command::Edit(1, Box::new(move |sim: &mut dyn Simulation| *noted.lock().unwrap() = sim.cells().to_vec())));
+        engine.handle(run(2));
+        assert_eq!(engine.seq, 0);
+        step_all(&mut engine);
+        assert_eq!((engine.stepped, engine.seq), (5, 1));
+
+        // Three steps turn the blinker upright, two or four leave it lying down
+        let mut expected = CpuSimulation::new(SIZE, SIZE, Rule::CONWAY, Boundary::Wrap, Automaton::Life, blinker());
+        for _ in 0..3 { expected.step(); }
+        assert_eq!(*seen.lock().unwrap(), expected.cells());
+        assert_ne!(expected.cells(), blinker());
+    }
+
+    #[test]
+    fn jumps_drop_what_was_owed_but_keep_earlier_edits() {
+        let mut engine = Engine::new(backend(blinker()));
+        engine.handle(run(4));
+        engine.step();
+        engine.handle(mark(1));
+        engine.handle(run(2));
+        engine.handle(Command::Jump(2, None));
+        // The edit is made at once, the steps still owed and the run waiting behind it never are
+        assert_eq!((engine.owed, engine.seq, engine.stepped, engine.steps), (0, 2, 1, 0));
+        assert_eq!(engine.sim.cells()[0], 1);
+        assert!(engine.waiting.is_empty());
+    }
+
+    fn generation(seq: u64, steps: usize, cell: u32) -> Generation {
+        let mut cells = vec![0; (SIZE * SIZE) as usize];
+        cells[0] = cell;
+        Generation { seq, steps, cells, rows: None, changes: None, previous: None, busy: Duration::ZERO, stepping: None }
+    }
+
+    #[test]
+    fn generations_from_before_an_edit_count_but_are_not_drawn() {
+        let mut worker = CpuWorker::new(backend(vec![0; (SIZE * SIZE) as usize]));
+        worker.pending = 6;
+        worker.set_cells(1, &[1]);
+        // Stepped before the edit reached the thread
+        let received = worker.take_in(vec![generation(0, 2, 7)]);
+        assert_eq!((received.steps, received.fresh, worker.pending), (2, false, 4));
+        assert_eq!(&worker.cells()[..2], [0, 1]);
+        // The edit's own generation replaces the cells
+        let received = worker.take_in(vec![generation(0, 1, 7), generation(1, 3, 9)]);
+        assert_eq!((received.steps, received.fresh, worker.pending), (4, true, 0));
+        assert_eq!(worker.cells()[0], 9);
+    }
+
+    #[test]
+    fn generations_from_before_a_jump_are_dropped() {
+        let mut worker = CpuWorker::new(backend(vec![0; (SIZE * SIZE) as usize]));
+        worker.pending = 5;
+        worker.jump(None);
+        assert_eq!(worker.pending, 0);
+        let received = worker.take_in(vec![generation(0, 5, 7)]);
+        assert_eq!((received.steps, received.fresh), (0, false));
+        assert_eq!(worker.cells()[0], 0);
+        let received = worker.take_in(vec![generation(0, 5, 7), generation(1, 2, 8)]);
+        assert_eq!((received.steps, received.fresh), (2, true));
+        assert_eq!(worker.cells()[0], 8);
+    }
+}
@@ -0,0 +1,83 @@
+use rust_gpu_life::{config, rule::Rule};
+
+// Rules saved from the panel's rule table, by name: one `"Name" = "B36/S23"` per line, # starts a comment
+#[cfg(not(target_arch = "wasm32"))]
+pub const RULES_FILE: &str = "rules.toml";
+
+// A missing file is no error; bad lines are reported and skipped
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load() -> Vec<(String, Rule)> {
+    let text = match std::fs::read_to_string(RULES_FILE) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+        Err(e) => {
+            eprintln!("warning: could not read {}: {}", RULES_FILE, e);
+            return Vec::new();
+        }
+    };
+    let (rules, errors) = parse(&text);
+    for e in errors {
+        eprintln!("{}: {}", RULES_FILE, e);
+    }
+    rules
+}
+
+// The file's rules in order, and a message for each line that isn't one; names are read like the
+// --config file's keys, so a quoted one may hold # and =
+#[cfg(not(target_arch = "wasm32"))]
+fn parse(text: &str) -> (Vec<(String, Rule)>, Vec<String>) {
+    let (mut rules, mut errors) = (Vec::new(), Vec::new());
+    for setting in config::parse(text) {
+        let rule = setting.and_then(|setting| match setting.value.quoted().map_err(|_| format!("expected a quoted rulestring, got {}", setting.value)).and_then(Rule::parse) {
+            Ok(rule) => Ok((setting.key, rule)),
+            Err(e) => Err(setting.error(e)),
+        });
+        match rule {
+            Ok(rule) => rules.push(rule),
+            Err(e) => errors.push(e),
+        }
+    }
+    (rules, errors)
+}
+
+// Rewrites the whole file, so hand-written comments don't survive a save
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(rules: &[(String, Rule)]) -> Result<(), String> {
+    let mut text = String::from("# Custom rules saved from the rule table: \"Name\" = \"rulestring\"\n");
+    for (name, rule) in rules {
+        text.push_str(&format!("\"{}\" = \"{}\"\n", name.replace('"', "'"), rule));
+    }
+    std::fs::write(RULES_FILE, text).map_err(|e| format!("could not write {}: {}", RULES_FILE, e))
+}
+
+// Adds `rule` as `name`, replacing any rule already saved under it
+pub fn insert(rules: &mut Vec<(String, Rule)>, name: &str, rule: Rule) {
+    match rules.iter_mut().find(|(saved, _)| saved == name) {
+        Some(saved) => saved.1 = rule,
+        None => rules.push((name.to_string(), rule)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_quoted_names_and_reports_bad_lines() {
+        let text = "# saved\n\"Seeds #2\" = \"B2/S\" # no survivors\n\"a = b\" = \"B36/S23\"\nbare = \"B3/S23\"\n\"Broken\" = \"B9/S\"\n\"Open = \"B3/S23\"\n";
+        let (rules, errors) = parse(text);
+        let expected = [("Seeds #2", "B2/S"), ("a = b", "B36/S23"), ("bare", "B3/S23")].map(|(name, rule)| (name.to_string(), Rule::parse(rule).unwrap()));
+        assert_eq!(rules, expected);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("line 5") && errors[1].starts_with("line 6"));
+    }
+
+    #[test]
+    fn insert_replaces_a_rule_of_the_same_name() {
+        let mut rules = Vec::new();
+        insert(&mut rules, "Mine", Rule::CONWAY);
+        insert(&mut rules, "Seeds", Rule::parse("B2/S").unwrap());
+        insert(&mut rules, "Mine", Rule::parse("B36/S23").unwrap());
+        assert_eq!(rules, [("Mine".to_string(), Rule::parse("B36/S23").unwrap()), ("Seeds".to_string(), Rule::parse("B2/S").unwrap())]);
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use crate::boundary::Boundary;
+use crate::WALL;
+
+/// Most states a cyclic rule can have; the colour wheel can't tell more apart
+pub const MAX_STATES: u32 = 256;
+
+/// Cyclic cellular automaton: each cell holds 0 to `states` - 1 and moves on to the next value (wrapping)
+/// when at least `threshold` of its eight neighbours hold that value already
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CyclicRule {
+    pub states: u32,
+    pub threshold: u32,
+}
+
+impl CyclicRule {
+    /// Griffeath's classic 14 states at threshold 1, which settles into spirals
+    pub const SPIRALS: CyclicRule = CyclicRule { states: 14, threshold: 1 };
+
+    pub fn parse_states(text: &str) -> Result<u32, String> {
+        match text.parse() {
+            Ok(states) if (2..=MAX_STATES).contains(&states) => Ok(states),
+            _ => Err(format!("invalid state count '{}' (expected 2 to {})", text, MAX_STATES)),
+        }
+    }
+
+    pub fn parse_threshold(text: &str) -> Result<u32, String> {
+        match text.parse() {
+            Ok(threshold) if (1..=8).contains(&threshold) => Ok(threshold),
+            _ => Err(format!("invalid threshold '{}' (expected 1 to 8 neighbours)", text)),
+        }
+    }
+
+    /// A cell's next value given how many of its neighbours hold the value after it
+    pub fn next_state(self, cell: u32, successors: u32) -> u32 {
+        if cell == WALL { return WALL; }
+        if successors >= self.threshold { (cell + 1) % self.states } else { cell }
+    }
+}
+
+impl fmt::Display for CyclicRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} states, threshold {}", self.states, self.threshold)
+    }
+}
+
+/// Every cell an independent uniform value, the usual start: spirals grow out of the spots that happen
+/// to be ordered
+pub fn random_grid(width: u32, height: u32, seed: u64, states: u32) -> Vec<u32> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..width as usize * height as usize).map(|_| rng.gen_range(0..states)).collect()
+}
+
+/// One generation of a `width`-wide grid.
+pub fn step_grid(input: &[u32], width: usize, rule: CyclicRule, boundary: Boundary) -> Vec<u32> {
+    let mut output = vec![0; input.len()];
+    step_grid_into(input, &mut output, width, rule, boundary);
+    output
+}
+
+/// `step_grid` writing into `output` (the same length as `input`) instead of allocating it.
+///
+/// Always the 3x3 Moore neighbourhood; walls and neighbours off a dead edge hold no value.
+pub fn step_grid_into(input: &[u32], output: &mut [u32], width: usize, rule: CyclicRule, boundary: Boundary) {
+    let height = input.len() / width;
+    output.par_iter_mut().enumerate().for_each(|(index, out)| {
+        let cell = input[index];
+        if cell == WALL {
+            *out = WALL;
+            return;
+        }
+        let (x, y) = ((index % width) as i64, (index / width) as i64);
+        let successor = (cell + 1) % rule.states;
+        let mut successors = 0;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if dx == 0 && dy == 0 { continue; }
+                if let (Some(nx), Some(ny)) = (boundary.resolve(x + dx, width), boundary.resolve(y + dy, height)) {
+                    successors += (input[ny * width + nx] == successor) as u32;
+                }
+            }
+        }
+        *out = rule.next_state(cell, successors);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_advance_onto_their_successors() {
+        // A 3-state ring: 0 is eaten by 1, 1 by 2 and 2 by 0
+        let rule = CyclicRule { states: 3, threshold: 1 };
+        let cells = [
+            0, 0, 0, 0,
+            0, 1, 1, 0,
+            0, 2, WALL, 0,
+            0, 0, 0, 0,
+        ];
+        let next = step_grid(&cells, 4, rule, Boundary::Dead);
+        assert_eq!(next, vec![
+            1, 1, 1, 1,
+            1, 2, 2, 1,
+            1, 0, WALL, 1,
+            0, 0, 0, 0,
+        ]);
+
+        // A higher threshold needs more of them
+        let rule = CyclicRule { states: 3, threshold: 2 };
+        let next = step_grid(&cells, 4, rule, Boundary::Dead);
+        assert_eq!(&next[..4], &[0, 1, 1, 0]);
+    }
+
+    #[test]
+    fn soups_cover_every_state() {
+        let grid = random_grid(64, 64, 7, 14);
+        assert!(grid.iter().all(|&cell| cell < 14));
+        assert!((0..14).all(|state| grid.contains(&state)));
+        assert!(CyclicRule::parse_states("1").is_err() && CyclicRule::parse_threshold("9").is_err());
+    }
+}
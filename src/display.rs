@@ -0,0 +1,33 @@
+use std::fmt;
+
+// How the fragment shader colours cells; the discriminants are the DISPLAY_* values in render.wgsl
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum DisplayMode {
+    // Each automaton's own colours, Life cells by age
+    Normal = 0,
+    // Births green, deaths red fading out and survivors white, against the previous generation
+    Diff = 1,
+    // A heat map over the full age range, from newborn blue to red at the age cap
+    Age = 2,
+}
+
+impl DisplayMode {
+    pub fn next(self) -> DisplayMode {
+        match self {
+            DisplayMode::Normal => DisplayMode::Diff,
+            DisplayMode::Diff => DisplayMode::Age,
+            DisplayMode::Age => DisplayMode::Normal,
+        }
+    }
+}
+
+impl fmt::Display for DisplayMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            DisplayMode::Normal => "normal",
+            DisplayMode::Diff => "diff",
+            DisplayMode::Age => "age",
+        })
+    }
+}
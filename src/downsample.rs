@@ -0,0 +1,160 @@
+use wgpu::util::DeviceExt;
+
+const WORKGROUP_SIZE: u32 = 8;
+
+// Shared with downsample.wgsl and render.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct DownsampleParams {
+    width: u32,
+    height: u32,
+    factor: u32,
+    _pad: u32,
+}
+
+/// Reduces the latest generation to a coverage texture, one texel per `factor` x `factor` block of cells
+/// holding the fractions that are alive, in state 2 and walls, so a zoomed-out view can be drawn
+/// without the shimmer of sampling one cell per pixel
+pub struct Downsampler {
+    pipeline: wgpu::ComputePipeline,
+    layout: wgpu::BindGroupLayout,
+    grid_width: u32,
+    grid_height: u32,
+    factor: u32,
+    params: wgpu::Buffer,
+    texture: wgpu::Texture,
+    coverage: wgpu::TextureView,
+    bind_groups: [wgpu::BindGroup; 2],
+}
+
+impl Downsampler {
+    /// `viewport` is the width in pixels the whole grid is drawn into
+    pub fn new(device: &wgpu::Device, buffers: &[wgpu::Buffer; 2], grid_width: u32, grid_height: u32, viewport: u32) -> Downsampler {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry { binding: 0, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: true }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 1, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 2, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::StorageTexture { access: wgpu::StorageTextureAccess::WriteOnly, format: wgpu::TextureFormat::Rgba32Float, view_dimension: wgpu::TextureViewDimension::D2 }, count: None },
+            ],
+            label: None,
+        });
+        let shader = device.create_shader_module(wgpu::include_wgsl!("downsample.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&layout], ..Default::default() });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Downsample"), layout: Some(&pipeline_layout), module: &shader, entry_point: "main", compilation_options: Default::default(), cache: None,
+        });
+
+        let factor = factor_for(grid_width, viewport);
+        let (params, texture, coverage, bind_groups) = targets(device, &layout, buffers, grid_width, grid_height, factor);
+        Downsampler { pipeline, layout, grid_width, grid_height, factor, params, texture, coverage, bind_groups }
+    }
+
+    /// Rebuilds the texture when the window's size calls for a different factor; returns whether it did,
+    /// in which case bind groups holding `coverage()` or `params_buffer()` need recreating
+    pub fn resize(&mut self, device: &wgpu::Device, buffers: &[wgpu::Buffer; 2], viewport: u32) -> bool {
+        self.set_factor(device, buffers, factor_for(self.grid_width, viewport))
+    }
+
+    /// `resize` straight to a number of cells per texel side
+    pub fn set_factor(&mut self, device: &wgpu::Device, buffers: &[wgpu::Buffer; 2], factor: u32) -> bool {
+        if factor == self.factor { return false; }
+        self.factor = factor;
+        (self.params, self.texture, self.coverage, self.bind_groups) = targets(device, &self.layout, buffers, self.grid_width, self.grid_height, factor);
+        true
+    }
+
+    pub fn factor(&self) -> u32 {
+        self.factor
+    }
+
+    /// Reduces the buffer for `parity` (0 = buffer A); nothing to do while cells are at least a pixel wide
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, parity: usize) {
+        if self.factor == 1 { return; }
+        let groups = |cells: u32| cells.div_ceil(self.factor).div_ceil(WORKGROUP_SIZE);
+        let mut cpass = encoder.begin_compute_pass(&Default::default());
+        cpass.set_pipeline(&self.pipeline);
+        cpass.set_bind_group(0, &self.bind_groups[parity], &[]);
+        cpass.dispatch_workgroups(groups(self.grid_width), groups(self.grid_height), 1);
+    }
+
+    pub fn coverage(&self) -> &wgpu::TextureView {
+        &self.coverage
+    }
+
+    /// The texture behind `coverage()`, which can be copied out
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    pub fn params_buffer(&self) -> &wgpu::Buffer {
+        &self.params
+    }
+
+    /// Blocking copy of a `width` x `height` rectangle of texels at (`x`, `y`), row-major, as (live, state 2,
+    /// walls) fractions; the last `encode` has to have been submitted
+    pub fn read(&self, device: &wgpu::Device, queue: &wgpu::Queue, x: u32, y: u32, width: u32, height: u32) -> Vec<[f32; 3]> {
+        // Rows of a texture copy start on 256-byte boundaries
+        let row = (width * 16).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Coverage Readback"),
+            size: (row * height) as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture { texture: &self.texture, mip_level: 0, origin: wgpu::Origin3d { x, y, z: 0 }, aspect: wgpu::TextureAspect::All },
+            wgpu::ImageCopyBuffer { buffer: &staging, layout: wgpu::ImageDataLayout { offset: 0, bytes_per_row: Some(row), rows_per_image: None } },
+            wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let slice = staging.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| sender.send(result).unwrap());
+        device.poll(wgpu::Maintain::Wait);
+        receiver.recv().unwrap().expect("failed to map coverage readback buffer");
+        let texels = slice.get_mapped_range().chunks(row as usize)
+            .flat_map(|bytes| bytemuck::cast_slice::<u8, [f32; 4]>(&bytes[..(width * 16) as usize]).iter().map(|&[live, second, walls, _]| [live, second, walls]).collect::<Vec<_>>())
+            .collect();
+        staging.unmap();
+        texels
+    }
+}
+
+// Cells per texel side so the texture has no more texels across than the viewport has pixels; the
+// viewport has the grid's shape, so the same goes for down
+fn factor_for(grid_width: u32, viewport: u32) -> u32 {
+    grid_width.div_ceil(viewport.max(1)).max(1)
+}
+
+fn targets(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, buffers: &[wgpu::Buffer; 2], grid_width: u32, grid_height: u32, factor: u32) -> (wgpu::Buffer, wgpu::Texture, wgpu::TextureView, [wgpu::BindGroup; 2]) {
+    // Never sampled at factor 1, when cells are drawn straight from the buffer
+    let (width, height) = if factor == 1 { (1, 1) } else { (grid_width.div_ceil(factor), grid_height.div_ceil(factor)) };
+    let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Downsample"),
+        contents: bytemuck::bytes_of(&DownsampleParams { width: grid_width, height: grid_height, factor, _pad: 0 }),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Coverage"),
+        size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba32Float,
+        usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let coverage = texture.create_view(&Default::default());
+    let bind_groups = buffers.each_ref().map(|cells| device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry { binding: 0, resource: cells.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 1, resource: params.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::TextureView(&coverage) },
+        ],
+        label: None,
+    }));
+    (params, texture, coverage, bind_groups)
+}
@@ -0,0 +1,40 @@
+// Zoomed-out view: the fraction of live cells in each factor x factor block, of cells in state 2
+// (Immigration's blue species) and of walls, sampled by render.wgsl
+struct Downsample {
+    width: u32, // Grid width in cells
+    height: u32,
+    factor: u32, // Cells per texel side
+    _pad: u32,
+};
+
+// Matches WALL in lib.rs
+const WALL: u32 = 0xffffffffu;
+
+@group(0) @binding(0) var<storage, read> cellState: array<u32>;
+@group(0) @binding(1) var<uniform> downsample: Downsample;
+@group(0) @binding(2) var coverage: texture_storage_2d<rgba32float, write>;
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if (any(global_id.xy >= textureDimensions(coverage))) { return; }
+
+    // Blocks on the last row and column are cut short when the factor doesn't divide the grid
+    let start = global_id.xy * downsample.factor;
+    let end = min(start + downsample.factor, vec2<u32>(downsample.width, downsample.height));
+    var live = 0u;
+    var second = 0u;
+    var walls = 0u;
+    for (var y = start.y; y < end.y; y++) {
+        for (var x = start.x; x < end.x; x++) {
+            let state = cellState[y * downsample.width + x];
+            if (state == WALL) {
+                walls += 1u;
+                continue;
+            }
+            live += min(state, 1u);
+            second += select(0u, 1u, state == 2u);
+        }
+    }
+    let area = f32((end.x - start.x) * (end.y - start.y));
+    textureStore(coverage, vec2<i32>(global_id.xy), vec4<f32>(f32(live) / area, f32(second) / area, f32(walls) / area, 1.0));
+}
@@ -0,0 +1,148 @@
+use crate::automaton::Automaton;
+use crate::gpu::GpuSimulation;
+use crate::population::dispatch_size;
+
+/// How many times the fade is stepped before a dead cell's tint is gone
+pub const FADE_GENERATIONS: u32 = 8;
+
+/// A cell's fade level after one more step, from the one before and whether the cell counts as alive now
+/// and did before: full when it just died, down by one a step after that and 0 while it lives. fade.wgsl
+/// does the same on the GPU.
+pub fn level(automaton: Automaton, level: u32, cell: u32, before: u32) -> u32 {
+    if automaton.counts(cell) {
+        0
+    } else if automaton.counts(before) {
+        FADE_GENERATIONS
+    } else {
+        level.saturating_sub(1)
+    }
+}
+
+/// The diff view's fading deaths: a level per cell, stepped against the simulation's two buffers after the
+/// frame's generations and bound to the render shader, which tints dead cells red by how recently they died.
+/// Stepped once per frame that advanced, so a frame of many generations fades by one.
+pub struct Fader {
+    pipeline: wgpu::ComputePipeline,
+    bind_groups: [wgpu::BindGroup; 2],
+    levels: wgpu::Buffer,
+}
+
+impl Fader {
+    pub fn new(device: &wgpu::Device, gpu: &GpuSimulation) -> Fader {
+        let buffers = gpu.buffers();
+        let levels = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Fade Levels"),
+            size: buffers[0].size(),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry { binding, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only }, has_dynamic_offset: false, min_binding_size: None }, count: None };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                storage(0, true),
+                storage(1, true),
+                wgpu::BindGroupLayoutEntry { binding: 2, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                storage(3, false),
+            ],
+            label: None,
+        });
+        // Bind group n fades against buffer n as the latest generation, like the change counter's
+        let bind_group = |current: &wgpu::Buffer, previous: &wgpu::Buffer| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: current.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: previous.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: gpu.params_buffer().as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: levels.as_entire_binding() },
+            ],
+            label: None,
+        });
+        let bind_groups = [bind_group(&buffers[0], &buffers[1]), bind_group(&buffers[1], &buffers[0])];
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("fade.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(concat!(include_str!("rules.wgsl"), include_str!("fade.wgsl")).into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&layout], ..Default::default() });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Fade"), layout: Some(&pipeline_layout), module: &shader, entry_point: "main", compilation_options: Default::default(), cache: None,
+        });
+        Fader { pipeline, bind_groups, levels }
+    }
+
+    /// Steps every cell's level against the buffer for `parity` and the other one, which has to hold the
+    /// generation before it (CPU mode uploads it while the diff view is on)
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, parity: usize, cell_count: usize) {
+        let (groups_x, groups_y) = dispatch_size(cell_count);
+        let mut cpass = encoder.begin_compute_pass(&Default::default());
+        cpass.set_pipeline(&self.pipeline);
+        cpass.set_bind_group(0, &self.bind_groups[parity], &[]);
+        cpass.dispatch_workgroups(groups_x, groups_y, 1);
+    }
+
+    /// Forgets every death, for when the levels have stopped following the cells, as while the diff view
+    /// was off
+    pub fn clear(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.clear_buffer(&self.levels, 0, None);
+    }
+
+    /// The levels, read by render.wgsl
+    pub fn levels_buffer(&self) -> &wgpu::Buffer {
+        &self.levels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use super::*;
+    use crate::boundary::Boundary;
+    use crate::rule::Rule;
+
+    #[test]
+    fn deaths_fade_over_the_generations_after() {
+        let mut fade = 0;
+        // Dies, stays dead for the whole fade and then some, comes back and dies again
+        for (step, (before, cell)) in [(1, 0)].into_iter().chain(std::iter::repeat_n((0, 0), 10)).chain([(0, 1), (1, 0)]).enumerate() {
+            fade = level(Automaton::Life, fade, cell, before);
+            let expected = match step {
+                0 | 12 => FADE_GENERATIONS,
+                11 => 0,
+                _ => FADE_GENERATIONS.saturating_sub(step as u32),
+            };
+            assert_eq!(fade, expected, "step {}", step);
+        }
+        // Whatever counts as alive: a Brian's Brain cell dying goes from firing to dying, and walls never live
+        assert_eq!(level(Automaton::BriansBrain, 0, 2, 1), FADE_GENERATIONS);
+        assert_eq!(level(Automaton::BriansBrain, 3, 2, 2), 2);
+        assert_eq!(level(Automaton::Life, 5, crate::WALL, crate::WALL), 4);
+    }
+
+    #[test]
+    fn gpu_fades_like_the_cpu() {
+        // Machines without any adapter, not even a software one, have nothing to compare
+        let Some(adapter) = pollster::block_on(crate::gpu::request_adapter(&wgpu::Instance::default(), None)) else { return };
+        let (device, queue) = pollster::block_on(crate::gpu::request_device(&adapter)).unwrap();
+        let (device, queue) = (Arc::new(device), Arc::new(queue));
+        let (width, height) = (48, 40);
+        let cells = crate::random_grid((width * height) as usize, 11, 0.35);
+        let mut gpu = GpuSimulation::new(device.clone(), queue.clone(), width, height, Rule::CONWAY, Boundary::Wrap, Automaton::Life, &cells, None).unwrap();
+        let fader = Fader::new(&device, &gpu);
+        let mut expected = vec![0; cells.len()];
+        let mut before = cells;
+        for _ in 0..12 {
+            let mut encoder = device.create_command_encoder(&Default::default());
+            gpu.encode_steps(&mut encoder, 1, None);
+            fader.encode(&mut encoder, gpu.parity(), before.len());
+            queue.submit(Some(encoder.finish()));
+            let now = crate::gpu::read_buffer(&device, &queue, gpu.current_buffer());
+            for ((fade, &cell), &was) in expected.iter_mut().zip(&now).zip(&before) {
+                *fade = level(Automaton::Life, *fade, cell, was);
+            }
+            assert_eq!(crate::gpu::read_buffer(&device, &queue, fader.levels_buffer()), expected);
+            before = now;
+        }
+        assert!(expected.iter().any(|&fade| fade > 0 && fade < FADE_GENERATIONS));
+    }
+}
@@ -0,0 +1,25 @@
+// Diff view fade, composed after rules.wgsl: a level per cell that jumps to FADE_GENERATIONS when the cell
+// dies, decays by one each time the fade is stepped and drops to 0 while the cell lives, sampled by
+// render.wgsl to tint dead cells less and less red. Matches `fade::level` in fade.rs
+@group(0) @binding(0) var<storage, read> current: array<u32>;
+@group(0) @binding(1) var<storage, read> previous: array<u32>;
+@group(0) @binding(2) var<uniform> params: Params;
+@group(0) @binding(3) var<storage, read_write> levels: array<u32>;
+
+// Matches FADE_GENERATIONS in fade.rs
+const FADE_GENERATIONS: u32 = 8u;
+
+// Dispatched as a 2D grid of 1D workgroups like population.wgsl
+@compute @workgroup_size(256)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let index = global_id.y * groups.x * 256u + global_id.x;
+    if (index >= arrayLength(&levels)) { return; }
+
+    if (counts(current[index]) > 0u) {
+        levels[index] = 0u;
+    } else if (counts(previous[index]) > 0u) {
+        levels[index] = FADE_GENERATIONS;
+    } else {
+        levels[index] = max(levels[index], 1u) - 1u;
+    }
+}
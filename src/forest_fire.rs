@@ -0,0 +1,122 @@
+use std::fmt;
+use rayon::prelude::*;
+use crate::boundary::Boundary;
+use crate::soup::{self, pcg};
+
+/// The three states a forest-fire cell can be in (anything else is a wall)
+pub const EMPTY: u32 = 0;
+pub const TREE: u32 = 1;
+pub const BURNING: u32 = 2;
+
+/// Drossel-Schwabl forest fire: burning trees burn down, trees next to a burning one catch fire and the
+/// rest are struck by lightning with probability `lightning`, and empty cells grow a tree with probability
+/// `growth`. The dice come from hashing the cell, the generation and `seed`, so a seed always burns the same
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ForestFire {
+    pub growth: f32,
+    pub lightning: f32,
+    pub seed: u64,
+}
+
+impl ForestFire {
+    /// Growth a hundred times faster than lightning strikes, well into the self-organized critical regime
+    pub const DROSSEL_SCHWABL: ForestFire = ForestFire { growth: 0.01, lightning: 0.00001, seed: 0 };
+
+    /// p and f alike: a probability per cell and generation
+    pub fn parse_probability(text: &str) -> Result<f32, String> {
+        match text.parse() {
+            Ok(chance) if (0.0..=1.0).contains(&chance) => Ok(chance),
+            _ => Err(format!("invalid probability '{}' (expected 0 to 1)", text)),
+        }
+    }
+
+    /// The key the dice are hashed with (see `roll`)
+    pub fn key(self) -> u32 {
+        soup::key(self.seed)
+    }
+
+    /// A cell's next state given whether a neighbour is burning and its `roll` for the generation
+    pub fn next_state(self, cell: u32, fire_nearby: bool, roll: u32) -> u32 {
+        match cell {
+            EMPTY => if roll < soup::threshold(self.growth) { TREE } else { EMPTY },
+            TREE => if fire_nearby || roll < soup::threshold(self.lightning) { BURNING } else { TREE },
+            BURNING => EMPTY,
+            wall => wall,
+        }
+    }
+}
+
+impl fmt::Display for ForestFire {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "p {}, f {}", self.growth, self.lightning)
+    }
+}
+
+/// Cell `index`'s die for `generation`: 24 random bits, compared against `soup::threshold` of a
+/// probability so both sides decide exactly alike (matches rules.wgsl)
+pub fn roll(index: u32, generation: u32, key: u32) -> u32 {
+    pcg(index ^ pcg(generation ^ key)) >> 8
+}
+
+/// One generation of a `width`-wide grid; `generation` is the one being stepped from.
+pub fn step_grid(input: &[u32], width: usize, params: ForestFire, boundary: Boundary, generation: u64) -> Vec<u32> {
+    let mut output = vec![0; input.len()];
+    step_grid_into(input, &mut output, width, params, boundary, generation);
+    output
+}
+
+/// `step_grid` writing into `output` (the same length as `input`) instead of allocating it.
+pub fn step_grid_into(input: &[u32], output: &mut [u32], width: usize, params: ForestFire, boundary: Boundary, generation: u64) {
+    let height = input.len() / width;
+    let key = params.key();
+    output.par_iter_mut().enumerate().for_each(|(index, out)| {
+        let cell = input[index];
+        if cell != TREE {
+            *out = params.next_state(cell, false, roll(index as u32, generation as u32, key));
+            return;
+        }
+        let (x, y) = ((index % width) as i64, (index / width) as i64);
+        let fire_nearby = (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy))).any(|(dx, dy)| {
+            (dx, dy) != (0, 0) && match (boundary.resolve(x + dx, width), boundary.resolve(y + dy, height)) {
+                (Some(nx), Some(ny)) => input[ny * width + nx] == BURNING,
+                _ => false,
+            }
+        });
+        *out = params.next_state(cell, fire_nearby, roll(index as u32, generation as u32, key));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WALL;
+
+    #[test]
+    fn fire_spreads_and_burns_out() {
+        let calm = ForestFire { growth: 0.0, lightning: 0.0, seed: 1 };
+        let cells = vec![
+            TREE, TREE, EMPTY, TREE,
+            TREE, BURNING, EMPTY, TREE,
+            WALL, TREE, EMPTY, TREE,
+        ];
+        let next = step_grid(&cells, 4, calm, Boundary::Dead, 0);
+        assert_eq!(next, [BURNING, BURNING, EMPTY, TREE, BURNING, EMPTY, EMPTY, TREE, WALL, BURNING, EMPTY, TREE]);
+        let next = step_grid(&next, 4, calm, Boundary::Dead, 1);
+        assert_eq!(next, [EMPTY, EMPTY, EMPTY, TREE, EMPTY, EMPTY, EMPTY, TREE, WALL, EMPTY, EMPTY, TREE]);
+    }
+
+    #[test]
+    fn dice_follow_the_seed_and_generation() {
+        let params = ForestFire { growth: 0.3, ..ForestFire::DROSSEL_SCHWABL };
+        let empty = vec![EMPTY; 64 * 64];
+        let grown = step_grid(&empty, 64, params, Boundary::Wrap, 7);
+        let trees = grown.iter().filter(|&&cell| cell == TREE).count();
+        assert!((1000..1500).contains(&trees), "{} trees", trees);
+        assert_eq!(grown, step_grid(&empty, 64, params, Boundary::Wrap, 7));
+        assert_ne!(grown, step_grid(&empty, 64, params, Boundary::Wrap, 8));
+        assert_ne!(grown, step_grid(&empty, 64, ForestFire { seed: 5, ..params }, Boundary::Wrap, 7));
+
+        let struck = step_grid(&[TREE; 16], 4, ForestFire { lightning: 1.0, ..params }, Boundary::Wrap, 0);
+        assert!(struck.iter().all(|&cell| cell == BURNING));
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Frames each timing's statistics are taken over
+pub const WINDOW: usize = 600;
+
+/// What a frame's time went on
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Timing {
+    /// The whole frame, as the HUD's Update Time
+    Frame,
+    /// Stepping: the GPU timestamps around the compute passes, or the CPU thread's steps
+    Compute,
+    /// Changed rows sent to the GPU in CPU mode
+    Upload,
+    /// Submitting the frame's commands and presenting it
+    Present,
+    /// The GPU timestamps around the render pass that draws the grid
+    Render,
+}
+
+impl Timing {
+    pub const ALL: [Timing; 5] = [Timing::Frame, Timing::Compute, Timing::Upload, Timing::Present, Timing::Render];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Timing::Frame => "frame",
+            Timing::Compute => "compute",
+            Timing::Upload => "upload",
+            Timing::Present => "present",
+            Timing::Render => "render",
+        }
+    }
+}
+
+/// Mean and percentiles over the frames in a window
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Summary {
+    pub frames: usize,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p99: Duration,
+}
+
+#[derive(Default)]
+struct Samples(VecDeque<Duration>);
+
+impl Samples {
+    fn push(&mut self, duration: Duration) {
+        if self.0.len() == WINDOW { self.0.pop_front(); }
+        self.0.push_back(duration);
+    }
+
+    // Nearest-rank percentiles, so each is a time some frame really took
+    fn summary(&self) -> Option<Summary> {
+        if self.0.is_empty() { return None; }
+        let mut sorted: Vec<Duration> = self.0.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = |percent: usize| sorted[(sorted.len() * percent).div_ceil(100).max(1) - 1];
+        let mean = sorted.iter().sum::<Duration>() / sorted.len() as u32;
+        Some(Summary { frames: sorted.len(), mean, p50: rank(50), p99: rank(99) })
+    }
+}
+
+/// Each `Timing` over the last `WINDOW` frames that had one, kept apart for CPU and GPU mode so
+/// switching between them doesn't mix the two.
+#[derive(Default)]
+pub struct FrameTimings {
+    // [GPU, CPU], then by `Timing`
+    samples: [[Samples; 5]; 2],
+}
+
+impl FrameTimings {
+    pub fn record(&mut self, cpu: bool, timing: Timing, duration: Duration) {
+        self.samples[cpu as usize][timing as usize].push(duration);
+    }
+
+    pub fn summary(&self, cpu: bool, timing: Timing) -> Option<Summary> {
+        self.samples[cpu as usize][timing as usize].summary()
+    }
+
+    /// Every timing recorded in either mode, one per line, for the console on exit.
+    pub fn table(&self) -> String {
+        let mut table = format!("{:<16}{:>8}{:>12}{:>12}{:>12}\n", "Timings", "frames", "mean", "p50", "p99");
+        for cpu in [false, true] {
+            for timing in Timing::ALL {
+                let Some(summary) = self.summary(cpu, timing) else { continue };
+                let label = format!("{} {}", if cpu { "CPU" } else { "GPU" }, timing.name());
+                let time = |duration: Duration| format!("{:.2?}", duration);
+                let _ = writeln!(table, "{:<16}{:>8}{:>12}{:>12}{:>12}", label, summary.frames, time(summary.mean), time(summary.p50), time(summary.p99));
+            }
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_cover_the_last_window() {
+        let mut timings = FrameTimings::default();
+        // A stutter long ago falls out of the window
+        timings.record(false, Timing::Frame, Duration::from_secs(1));
+        for ms in 1..=WINDOW as u64 {
+            timings.record(false, Timing::Frame, Duration::from_millis(ms));
+        }
+        let summary = timings.summary(false, Timing::Frame).unwrap();
+        assert_eq!(summary.frames, WINDOW);
+        assert_eq!(summary.p50, Duration::from_millis(300));
+        assert_eq!(summary.p99, Duration::from_millis(594));
+        assert_eq!(summary.mean, Duration::from_micros(300_500));
+        // CPU mode keeps its own
+        assert_eq!(timings.summary(true, Timing::Frame), None);
+        timings.record(true, Timing::Upload, Duration::from_millis(2));
+        assert_eq!(timings.summary(true, Timing::Upload).unwrap().p99, Duration::from_millis(2));
+        assert_eq!(timings.table().lines().count(), 3);
+    }
+}
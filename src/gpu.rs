@@ -0,0 +1,646 @@
+use std::cell::OnceCell;
+use std::sync::Arc;
+use wgpu::util::DeviceExt;
+use crate::ant::AntRule;
+use crate::automaton::Automaton;
+use crate::bands::BandLayout;
+use crate::boundary::Boundary;
+use crate::cyclic::CyclicRule;
+use crate::chunks::{self, ChunkTracker};
+use crate::forest_fire::ForestFire;
+use crate::gray_scott::GrayScott;
+use crate::lenia::Lenia;
+use crate::ltl::LtlRule;
+use crate::regions::{self, RuleMap};
+use crate::rps;
+use crate::rule::Rule;
+use crate::soup::{self, SoupWriter};
+use crate::transform::{GridTransformer, Transform};
+use crate::topology::Topology;
+use crate::workgroup::WorkgroupSize;
+use crate::Simulation;
+
+// Uniform shared with the compute shaders (see `Params` in rules.wgsl)
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct Params {
+    width: u32,
+    height: u32,
+    birth: u32,
+    survive: u32,
+    boundary: u32,
+    automaton: u32,
+    radius: u32,
+    birth_range: [u32; 2],
+    survive_range: [u32; 2],
+    feed: f32,
+    kill: f32,
+    diffusion: [f32; 2],
+    dt: f32,
+    states: u32,
+    topology: u32,
+    cyclic_states: u32,
+    cyclic_threshold: u32,
+    rps_threshold: u32,
+    lenia_radius: u32,
+    lenia_mu: f32,
+    lenia_sigma: f32,
+    lenia_dt: f32,
+    forest_growth: u32,
+    forest_lightning: u32,
+    forest_key: u32,
+}
+
+impl Params {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(width: u32, height: u32, rule: Rule, boundary: Boundary, topology: Topology, automaton: Automaton, ltl: LtlRule, gray_scott: GrayScott, cyclic: CyclicRule, rps_threshold: u32, lenia: Lenia, forest_fire: ForestFire) -> Params {
+        Params {
+            width, height, birth: rule.birth, survive: rule.survive, boundary: boundary as u32, automaton: automaton as u32,
+            radius: ltl.radius, birth_range: [ltl.birth.0, ltl.birth.1], survive_range: [ltl.survive.0, ltl.survive.1],
+            feed: gray_scott.feed, kill: gray_scott.kill, diffusion: [gray_scott.diffusion_u, gray_scott.diffusion_v], dt: gray_scott.dt,
+            states: rule.states, topology: topology as u32, cyclic_states: cyclic.states, cyclic_threshold: cyclic.threshold, rps_threshold,
+            lenia_radius: lenia.radius, lenia_mu: lenia.mu, lenia_sigma: lenia.sigma, lenia_dt: lenia.dt,
+            forest_growth: soup::threshold(forest_fire.growth), forest_lightning: soup::threshold(forest_fire.lightning), forest_key: forest_fire.key(),
+        }
+    }
+}
+
+// The generation step compiled for one workgroup size, over the whole grid and over listed chunks
+pub(crate) struct Pipelines {
+    pub(crate) step: wgpu::ComputePipeline,
+    // Same step, also saving the generation two back into `history`
+    history: wgpu::ComputePipeline,
+    chunks: wgpu::ComputePipeline,
+    chunks_history: wgpu::ComputePipeline,
+}
+
+/// Compute shader backend: two storage buffers ping-ponged every generation. By default only the chunks
+/// next to last generation's changes are computed (see `ChunkTracker`).
+pub struct GpuSimulation {
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    pipelines: Pipelines,
+    // Kept to rebuild the pipelines for another workgroup size
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline_cache: Option<Arc<wgpu::PipelineCache>>,
+    workgroup: WorkgroupSize,
+    chunks: ChunkTracker,
+    skip_chunks: bool,
+    bind_groups: [wgpu::BindGroup; 2],
+    buffers: [wgpu::Buffer; 2],
+    history: wgpu::Buffer,
+    params_buffer: wgpu::Buffer,
+    // The generation's number on the GPU, ping-ponged like the cells so every step in a pass counts it up
+    clocks: [wgpu::Buffer; 2],
+    // See `RuleMap::words`; zeroed while every tile follows the grid's rule
+    regions: wgpu::Buffer,
+    // Gray-Scott's u and v at full precision, ping-ponged like the cells (see `concentration_buffers`)
+    concentrations: [wgpu::Buffer; 2],
+    // Kept to bind other concentration buffers
+    bind_group_layout: wgpu::BindGroupLayout,
+    soup: SoupWriter,
+    transformer: GridTransformer,
+    width: u32,
+    height: u32,
+    rule: Rule,
+    boundary: Boundary,
+    topology: Topology,
+    automaton: Automaton,
+    ltl: LtlRule,
+    gray_scott: GrayScott,
+    cyclic: CyclicRule,
+    rps_threshold: u32,
+    lenia: Lenia,
+    forest_fire: ForestFire,
+    // The latest generation's number, which the forest fire's dice are hashed with
+    generation: u64,
+    parity: usize,
+    // Host copy of the latest generation, read back lazily by `cells()`
+    mirror: OnceCell<Vec<u32>>,
+}
+
+impl GpuSimulation {
+    /// `cache` is handed to the step's pipelines, now and when they're rebuilt for another workgroup size.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(device: Arc<wgpu::Device>, queue: Arc<wgpu::Queue>, width: u32, height: u32, rule: Rule, boundary: Boundary, automaton: Automaton, cells: &[u32], cache: Option<Arc<wgpu::PipelineCache>>) -> Result<GpuSimulation, String> {
+        check_limits(&device, width, height)?;
+
+        let usage = wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC;
+        let buffers = [
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor { label: Some("Buffer A"), contents: bytemuck::cast_slice(cells), usage }),
+            device.create_buffer(&wgpu::BufferDescriptor { label: Some("Buffer B"), size: (cells.len() * 4) as u64, usage, mapped_at_creation: false }),
+        ];
+        let history = device.create_buffer(&wgpu::BufferDescriptor { label: Some("History"), size: (cells.len() * 4) as u64, usage: wgpu::BufferUsages::STORAGE, mapped_at_creation: false });
+        let workgroup = WorkgroupSize::DEFAULT;
+        let chunks = ChunkTracker::new(&device, width, height, workgroup);
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Params"),
+            contents: bytemuck::bytes_of(&Params::new(width, height, rule, boundary, Topology::Square, automaton, LtlRule::BOSCO, GrayScott::CORAL, CyclicRule::SPIRALS, rps::THRESHOLD, Lenia::ORBIUM, ForestFire::DROSSEL_SCHWABL)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let clocks = ["Clock A", "Clock B"].map(|label| device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label), size: 4, usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST, mapped_at_creation: false,
+        }));
+        let regions = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Rule Map"), size: (regions::buffer_words(width, height) * 4) as u64, usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST, mapped_at_creation: false,
+        });
+
+        let concentrations = concentration_buffers(&device, concentration_cells(&device, automaton, cells.len()));
+        let bind_group_layout = create_bind_group_layout(&device);
+        let bind_groups = create_bind_groups(&device, &bind_group_layout, &buffers, &params_buffer, &history, &chunks, &clocks, &regions, &concentrations);
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&bind_group_layout], ..Default::default() });
+        let pipelines = create_pipelines(&device, &pipeline_layout, workgroup, cache.as_deref());
+        let soup = SoupWriter::new(&device, &buffers[0], &buffers[1]);
+        let transformer = GridTransformer::new(&device, &buffers[0], &buffers[1]);
+        // Grids too big to list in one dispatch compute every cell
+        let skip_chunks = chunks.fits(&device.limits()).is_ok();
+
+        Ok(GpuSimulation { device, queue, pipelines, pipeline_layout, pipeline_cache: cache, workgroup, chunks, skip_chunks, bind_groups, buffers, history, params_buffer, clocks, regions, concentrations, bind_group_layout, soup, transformer, width, height, rule, boundary, topology: Topology::Square, automaton, ltl: LtlRule::BOSCO, gray_scott: GrayScott::CORAL, cyclic: CyclicRule::SPIRALS, rps_threshold: rps::THRESHOLD, lenia: Lenia::ORBIUM, forest_fire: ForestFire::DROSSEL_SCHWABL, generation: 0, parity: 0, mirror: OnceCell::new() })
+    }
+
+    /// Recompiles the step for another workgroup size; the grid is untouched.
+    pub fn set_workgroup_size(&mut self, size: WorkgroupSize) -> Result<(), String> {
+        size.check(&self.device.limits())?;
+        self.pipelines = create_pipelines(&self.device, &self.pipeline_layout, size, self.pipeline_cache.as_deref());
+        self.chunks.set_workgroup_size(&self.queue, size);
+        self.workgroup = size;
+        Ok(())
+    }
+
+    pub fn workgroup_size(&self) -> WorkgroupSize {
+        self.workgroup
+    }
+
+    /// Turns computing only the chunks next to changes on or off; off computes every cell, for comparison.
+    pub fn set_chunk_skipping(&mut self, on: bool) -> Result<(), String> {
+        if on == self.skip_chunks { return Ok(()); }
+        if on {
+            self.chunks.fits(&self.device.limits())?;
+            // Nothing was tracked meanwhile
+            self.chunks.touch_all(&self.queue, self.parity);
+        } else {
+            self.chunks.clear_active(&self.queue);
+        }
+        self.skip_chunks = on;
+        Ok(())
+    }
+
+    pub fn chunk_skipping(&self) -> bool {
+        self.skip_chunks
+    }
+
+    /// One u32 per chunk, row-major and `chunks::grid_chunks` across: 1 if the latest generation computed it.
+    pub fn active_chunks_buffer(&self) -> &wgpu::Buffer {
+        self.chunks.active_buffer()
+    }
+
+    /// Records `generations` steps into one compute pass so they can share a submit with rendering.
+    /// wgpu orders the dispatches, so each one sees the buffer the previous one wrote. The last one also
+    /// saves the generation two back in `history_buffer()`. Each dispatch only costs a bind group switch and the
+    /// dispatch itself; wgpu has no reusable bundles for compute passes, so there's nothing to record once.
+    /// While skipping chunks each generation is two small dispatches listing the chunks, then the step over them.
+    /// Forest fires can catch anywhere, so they always step the whole grid, which counts the clock up as it goes;
+    /// so does Gray-Scott, whose concentrations change below what the cells can show.
+    pub fn encode_steps(&mut self, encoder: &mut wgpu::CommandEncoder, generations: u32, timestamp_writes: Option<wgpu::ComputePassTimestampWrites>) {
+        let random = self.automaton == Automaton::ForestFire;
+        let whole = random || self.automaton == Automaton::GrayScott;
+        if random {
+            // Lands before this submit's passes; later steps in the pass read what the one before wrote
+            self.queue.write_buffer(&self.clocks[self.parity], 0, bytemuck::bytes_of(&(self.generation as u32)));
+        }
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("Life"), timestamp_writes });
+        let (groups_x, groups_y) = self.workgroup.groups(self.width, self.height);
+        for generation in 0..generations {
+            let last = generation + 1 == generations;
+            if self.skip_chunks && !whole {
+                self.chunks.encode_list(&mut cpass, self.parity, last);
+                cpass.set_pipeline(if last { &self.pipelines.chunks_history } else { &self.pipelines.chunks });
+                cpass.set_bind_group(0, &self.bind_groups[self.parity], &[]);
+                cpass.dispatch_workgroups_indirect(self.chunks.dispatch_buffer(), 0);
+            } else {
+                cpass.set_pipeline(if last { &self.pipelines.history } else { &self.pipelines.step });
+                cpass.set_bind_group(0, &self.bind_groups[self.parity], &[]);
+                cpass.dispatch_workgroups(groups_x, groups_y, 1);
+            }
+            self.parity ^= 1;
+        }
+        self.generation += generations as u64;
+        self.mirror.take();
+    }
+
+    /// Replaces every cell but the walls with `random_grid(.., seed, density)`, generated on the GPU instead of uploaded.
+    pub fn write_soup(&mut self, seed: u64, density: f32) {
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        self.soup.encode(&self.queue, &mut encoder, self.parity, self.width as usize * self.height as usize, seed, density);
+        self.queue.submit(Some(encoder.finish()));
+        self.chunks.touch_all(&self.queue, self.parity);
+        self.mirror.take();
+    }
+
+    /// Shifts, turns or mirrors the whole grid into the other buffer, which then holds the latest generation.
+    /// The caller checks the transform fits the grid.
+    pub fn transform(&mut self, transform: Transform) {
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        self.transformer.encode(&self.queue, &mut encoder, self.parity, self.width, self.height, transform);
+        self.queue.submit(Some(encoder.finish()));
+        self.parity ^= 1;
+        self.chunks.touch_all(&self.queue, self.parity);
+        self.mirror.take();
+    }
+
+    fn write_params(&self) {
+        let params = Params::new(self.width, self.height, self.rule, self.boundary, self.topology, self.automaton, self.ltl, self.gray_scott, self.cyclic, self.rps_threshold, self.lenia, self.forest_fire);
+        self.queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+        // Chunks that were settled under the old settings may not be under the new ones
+        self.chunks.touch_all(&self.queue, self.parity);
+    }
+
+    /// Index into `buffers()` of the buffer holding the latest generation.
+    pub fn parity(&self) -> usize {
+        self.parity
+    }
+
+    pub fn buffers(&self) -> &[wgpu::Buffer; 2] {
+        &self.buffers
+    }
+
+    pub fn current_buffer(&self) -> &wgpu::Buffer {
+        &self.buffers[self.parity]
+    }
+
+    pub fn params_buffer(&self) -> &wgpu::Buffer {
+        &self.params_buffer
+    }
+
+    /// The rule map as the step reads it (see `RuleMap::words`), for tinting the regions.
+    pub fn rule_map_buffer(&self) -> &wgpu::Buffer {
+        &self.regions
+    }
+
+    /// The generation before the one preceding the latest, as of the last `encode_steps`.
+    pub fn history_buffer(&self) -> &wgpu::Buffer {
+        &self.history
+    }
+}
+
+impl Simulation for GpuSimulation {
+    fn step(&mut self) {
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        self.encode_steps(&mut encoder, 1, None);
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    // Blocks on a readback the first time it's called after the grid changes
+    fn cells(&self) -> &[u32] {
+        self.mirror.get_or_init(|| read_buffer(&self.device, &self.queue, self.current_buffer()))
+    }
+
+    // Only the rectangle is copied back when there's no mirror of the whole grid yet
+    fn read_rect(&self, x: usize, y: usize, width: usize, height: usize) -> Vec<u32> {
+        let spans = crate::rect_spans(self.width as usize, self.height as usize, x, y, width, height);
+        match self.mirror.get() {
+            Some(cells) => {
+                let mut rect = vec![0; width * height];
+                for (index, offset, len) in spans {
+                    rect[offset..offset + len].copy_from_slice(&cells[index..index + len]);
+                }
+                rect
+            }
+            None => read_ranges(&self.device, &self.queue, self.current_buffer(), width * height, spans),
+        }
+    }
+
+    fn set_cells(&mut self, offset: usize, cells: &[u32]) {
+        self.queue.write_buffer(self.current_buffer(), (offset * 4) as u64, bytemuck::cast_slice(cells));
+        self.chunks.touch(&self.queue, self.parity, chunks::touched(self.width as usize, self.chunks.columns() as usize, offset, cells.len()));
+        if let Some(mirror) = self.mirror.get_mut() {
+            mirror[offset..offset + cells.len()].copy_from_slice(cells);
+        }
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn set_rule(&mut self, rule: Rule) {
+        self.rule = rule;
+        self.write_params();
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) {
+        self.boundary = boundary;
+        self.write_params();
+    }
+
+    fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+        self.write_params();
+    }
+
+    // Gray-Scott's concentrations only take up memory while it runs
+    fn set_automaton(&mut self, automaton: Automaton) {
+        self.automaton = automaton;
+        let cells = concentration_cells(&self.device, automaton, self.width as usize * self.height as usize);
+        if self.concentrations[0].size() != (cells * 8) as u64 {
+            self.concentrations = concentration_buffers(&self.device, cells);
+            self.bind_groups = create_bind_groups(&self.device, &self.bind_group_layout, &self.buffers, &self.params_buffer, &self.history, &self.chunks, &self.clocks, &self.regions, &self.concentrations);
+        }
+        self.write_params();
+    }
+
+    fn set_ltl_rule(&mut self, ltl: LtlRule) {
+        self.ltl = ltl;
+        self.write_params();
+    }
+
+    fn set_gray_scott(&mut self, params: GrayScott) {
+        self.gray_scott = params;
+        self.write_params();
+    }
+
+    // Ants walk on the CPU, the step leaves their cells alone
+    fn set_ant_rule(&mut self, _rule: AntRule) {}
+
+    fn set_cyclic_rule(&mut self, rule: CyclicRule) {
+        self.cyclic = rule;
+        self.write_params();
+    }
+
+    fn set_rps_threshold(&mut self, threshold: u32) {
+        self.rps_threshold = threshold;
+        self.write_params();
+    }
+
+    fn set_lenia(&mut self, params: Lenia) {
+        self.lenia = params;
+        self.write_params();
+    }
+
+    fn set_forest_fire(&mut self, params: ForestFire) {
+        self.forest_fire = params;
+        self.write_params();
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    // An empty map only needs its tiles across zeroed for the step to skip it
+    fn set_rule_map(&mut self, map: Option<&RuleMap>) {
+        match map {
+            Some(map) => self.queue.write_buffer(&self.regions, 0, bytemuck::cast_slice(&map.words())),
+            None => self.queue.write_buffer(&self.regions, 0, bytemuck::bytes_of(&0u32)),
+        }
+        self.chunks.touch_all(&self.queue, self.parity);
+    }
+}
+
+// The step's bindings, see life.wgsl
+pub(crate) fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            wgpu::BindGroupLayoutEntry { binding: 0, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: true }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            wgpu::BindGroupLayoutEntry { binding: 1, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: false }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            wgpu::BindGroupLayoutEntry { binding: 2, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            wgpu::BindGroupLayoutEntry { binding: 3, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: false }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            wgpu::BindGroupLayoutEntry { binding: 4, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: false }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            wgpu::BindGroupLayoutEntry { binding: 5, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: false }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            wgpu::BindGroupLayoutEntry { binding: 6, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: true }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            wgpu::BindGroupLayoutEntry { binding: 7, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            wgpu::BindGroupLayoutEntry { binding: 8, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: true }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            wgpu::BindGroupLayoutEntry { binding: 9, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: false }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            wgpu::BindGroupLayoutEntry { binding: 10, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: true }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            wgpu::BindGroupLayoutEntry { binding: 11, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: true }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            wgpu::BindGroupLayoutEntry { binding: 12, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: false }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+        ],
+        label: None,
+    })
+}
+
+// Bind group n reads buffer n and writes the other one, and the same for the clocks and concentrations
+#[allow(clippy::too_many_arguments)]
+fn create_bind_groups(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, buffers: &[wgpu::Buffer; 2], params: &wgpu::Buffer, history: &wgpu::Buffer, chunks: &ChunkTracker, clocks: &[wgpu::Buffer; 2], regions: &wgpu::Buffer, concentrations: &[wgpu::Buffer; 2]) -> [wgpu::BindGroup; 2] {
+    [0, 1].map(|n| device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry { binding: 0, resource: buffers[n].as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 1, resource: buffers[n ^ 1].as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 2, resource: params.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 3, resource: history.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 4, resource: chunks.changed_buffer(n).as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 5, resource: chunks.stale_buffer().as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 6, resource: chunks.list_buffer().as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 7, resource: chunks.params_buffer().as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 8, resource: clocks[n].as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 9, resource: clocks[n ^ 1].as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 10, resource: regions.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 11, resource: concentrations[n].as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 12, resource: concentrations[n ^ 1].as_entire_binding() },
+        ],
+        label: None,
+    }))
+}
+
+// How many cells' concentrations to keep: the grid's while Gray-Scott runs and they fit in a buffer, none otherwise
+fn concentration_cells(device: &wgpu::Device, automaton: Automaton, cells: usize) -> usize {
+    let limits = device.limits();
+    let fits = (cells * 8) as u64 <= limits.max_buffer_size.min(limits.max_storage_buffer_binding_size as u64);
+    if automaton == Automaton::GrayScott && fits { cells } else { 1 }
+}
+
+/// Gray-Scott's concentrations, u and v as two f32s for each of `cells` cells, twice over for the two
+/// generations; the cells hold them quantised (see `gray_scott::encode`). A single cell's worth tells the
+/// step there are none, as in bands and while another automaton runs.
+pub(crate) fn concentration_buffers(device: &wgpu::Device, cells: usize) -> [wgpu::Buffer; 2] {
+    ["Concentrations A", "Concentrations B"].map(|label| device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label), size: (cells * 8) as u64, usage: wgpu::BufferUsages::STORAGE, mapped_at_creation: false,
+    }))
+}
+
+// The plain step and the one that also saves history, over the grid and over chunks, compiled for `size`
+pub(crate) fn create_pipelines(device: &wgpu::Device, layout: &wgpu::PipelineLayout, size: WorkgroupSize, cache: Option<&wgpu::PipelineCache>) -> Pipelines {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("life.wgsl"),
+        source: wgpu::ShaderSource::Wgsl(size.shader_source(concat!(include_str!("rules.wgsl"), include_str!("life.wgsl"))).into()),
+    });
+    let compute_pipeline = |entry_point| device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None, layout: Some(layout), module: &shader, entry_point, compilation_options: Default::default(), cache,
+    });
+    Pipelines {
+        step: compute_pipeline("main"),
+        history: compute_pipeline("main_history"),
+        chunks: compute_pipeline("main_chunks"),
+        chunks_history: compute_pipeline("main_chunks_history"),
+    }
+}
+
+/// Tries the discrete GPU, then the integrated one, then the software fallback adapter (llvmpipe, WARP).
+/// `None` means not even the fallback is available.
+pub async fn request_adapter(instance: &wgpu::Instance, surface: Option<&wgpu::Surface<'_>>) -> Option<wgpu::Adapter> {
+    let attempts = [
+        (wgpu::PowerPreference::HighPerformance, false),
+        (wgpu::PowerPreference::LowPower, false),
+        (wgpu::PowerPreference::LowPower, true),
+    ];
+    for (power_preference, force_fallback_adapter) in attempts {
+        let options = wgpu::RequestAdapterOptions { power_preference, force_fallback_adapter, compatible_surface: surface };
+        if let Some(adapter) = instance.request_adapter(&options).await {
+            return Some(adapter);
+        }
+    }
+    None
+}
+
+/// Requests a device with the adapter's real buffer limits; the defaults cap storage bindings at 128 MB and
+/// at eight per shader stage, three short of the step's. Timestamp queries and pipeline caching are enabled
+/// when the adapter has them. The error includes the adapter's limits.
+pub async fn request_device(adapter: &wgpu::Adapter) -> Result<(wgpu::Device, wgpu::Queue), String> {
+    let adapter_limits = adapter.limits();
+    let info = adapter.get_info();
+    adapter.request_device(&wgpu::DeviceDescriptor {
+        required_features: adapter.features() & (wgpu::Features::TIMESTAMP_QUERY | wgpu::Features::PIPELINE_CACHE),
+        required_limits: wgpu::Limits {
+            max_buffer_size: adapter_limits.max_buffer_size,
+            max_storage_buffer_binding_size: adapter_limits.max_storage_buffer_binding_size,
+            max_storage_buffers_per_shader_stage: adapter_limits.max_storage_buffers_per_shader_stage,
+            ..Default::default()
+        },
+        ..Default::default()
+    }, None).await.map_err(|e| format!("could not create a device on {} ({:?}): {}\nadapter limits: {:#?}", info.name, info.backend, e, adapter_limits))
+}
+
+// Storage buffers the step binds, see life.wgsl
+const STORAGE_BINDINGS: u32 = 11;
+
+// The whole grid in one buffer; the error says how many bands would fit instead
+fn check_limits(device: &wgpu::Device, width: u32, height: u32) -> Result<(), String> {
+    let limits = device.limits();
+    check_bindings(&limits)?;
+    BandLayout::whole(width, height).check(&limits)
+}
+
+// Whether the device takes the step's bind group layout at all
+pub(crate) fn check_bindings(limits: &wgpu::Limits) -> Result<(), String> {
+    if limits.max_storage_buffers_per_shader_stage < STORAGE_BINDINGS {
+        return Err(format!("the GPU step binds {} storage buffers but this adapter allows {} per shader stage", STORAGE_BINDINGS, limits.max_storage_buffers_per_shader_stage));
+    }
+    Ok(())
+}
+
+/// Blocking GPU -> CPU copy of a storage buffer through a staging buffer.
+pub fn read_buffer(device: &wgpu::Device, queue: &wgpu::Queue, buffer: &wgpu::Buffer) -> Vec<u32> {
+    let len = (buffer.size() / 4) as usize;
+    read_ranges(device, queue, buffer, len, [(0, 0, len)])
+}
+
+// Blocking copy of (source index, destination index, length) u32 ranges into a `len`-element vector
+pub(crate) fn read_ranges(device: &wgpu::Device, queue: &wgpu::Queue, buffer: &wgpu::Buffer, len: usize, ranges: impl IntoIterator<Item = (usize, usize, usize)>) -> Vec<u32> {
+    let staging = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback"),
+        size: (len * 4) as u64,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&Default::default());
+    for (source, destination, count) in ranges {
+        encoder.copy_buffer_to_buffer(buffer, (source * 4) as u64, &staging, (destination * 4) as u64, (count * 4) as u64);
+    }
+    queue.submit(Some(encoder.finish()));
+
+    let slice = staging.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| sender.send(result).unwrap());
+    device.poll(wgpu::Maintain::Wait);
+    receiver.recv().unwrap().expect("failed to map readback buffer");
+
+    let data = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+    staging.unmap();
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gray_scott;
+
+    #[test]
+    fn gray_scott_keeps_full_precision() {
+        let Some(adapter) = pollster::block_on(request_adapter(&wgpu::Instance::default(), None)) else { return };
+        let (device, queue) = pollster::block_on(request_device(&adapter)).unwrap();
+        // Each step feeds u less than half of what a cell can tell apart (see gray_scott.rs)
+        let cells = vec![gray_scott::encode(0.5, 0.0); 16 * 16];
+        let mut simulation = GpuSimulation::new(Arc::new(device), Arc::new(queue), 16, 16, Rule::CONWAY, Boundary::Wrap, Automaton::GrayScott, &cells, None).unwrap();
+        simulation.set_gray_scott(GrayScott { dt: 0.0001, ..GrayScott::CORAL });
+        for _ in 0..10 {
+            let mut encoder = simulation.device.create_command_encoder(&Default::default());
+            simulation.encode_steps(&mut encoder, 100, None);
+            simulation.queue.submit(Some(encoder.finish()));
+        }
+        assert!(gray_scott::decode(simulation.cells()[0]).0 > 0.502);
+    }
+
+    #[test]
+    fn skipping_chunks_steps_like_computing_every_cell() {
+        let Some(adapter) = pollster::block_on(request_adapter(&wgpu::Instance::default(), None)) else { return };
+        let (device, queue) = pollster::block_on(request_device(&adapter)).unwrap();
+        let (device, queue) = (Arc::new(device), Arc::new(queue));
+        // Chunks that don't fill the grid at its edges, and a patch of soup next to empty ones so most get skipped
+        let (width, height) = (160, 136);
+        let patch = crate::random_grid(48 * 40, 5, 0.4);
+        let mut cells = vec![0; (width * height) as usize];
+        for (row, line) in patch.chunks(48).enumerate() {
+            let start = (row + 90) * width as usize + 100;
+            cells[start..start + 48].copy_from_slice(line);
+        }
+        let cases = [
+            (Automaton::Life, Boundary::Wrap, Topology::Square),
+            (Automaton::Life, Boundary::Dead, Topology::Hex),
+            (Automaton::BriansBrain, Boundary::Mirror, Topology::Square),
+            (Automaton::LargerThanLife, Boundary::Wrap, Topology::Square),
+            (Automaton::Cyclic, Boundary::Wrap, Topology::Square),
+            // Steps the whole grid either way, since a tree anywhere may be struck
+            (Automaton::ForestFire, Boundary::Wrap, Topology::Square),
+        ];
+        for (automaton, boundary, topology) in cases {
+            let [mut skipping, mut whole] = [true, false].map(|skip| {
+                let mut simulation = GpuSimulation::new(device.clone(), queue.clone(), width, height, Rule::CONWAY, boundary, automaton, &cells, None).unwrap();
+                simulation.set_topology(topology);
+                simulation.set_ltl_rule(LtlRule { radius: 2, birth: (6, 9), survive: (5, 11) });
+                simulation.set_cyclic_rule(CyclicRule { states: 2, threshold: 2 });
+                simulation.set_chunk_skipping(skip).unwrap();
+                simulation
+            });
+            for (batch, generations) in [1, 7, 30, 0, 40].into_iter().enumerate() {
+                for simulation in [&mut skipping, &mut whole] {
+                    // An edit far from anything alive has to wake its chunks up
+                    if generations == 0 { simulation.set_cells(10 * width as usize + 10, &[1, 1, 1]); }
+                    let mut encoder = simulation.device.create_command_encoder(&Default::default());
+                    simulation.encode_steps(&mut encoder, generations, None);
+                    simulation.queue.submit(Some(encoder.finish()));
+                }
+                assert!(skipping.cells() == whole.cells(), "{} {} {}: batch {}", automaton, boundary, topology, batch);
+            }
+        }
+    }
+
+    #[test]
+    fn rock_paper_scissors_takes_its_own_threshold() {
+        let Some(adapter) = pollster::block_on(request_adapter(&wgpu::Instance::default(), None)) else { return };
+        let (device, queue) = pollster::block_on(request_device(&adapter)).unwrap();
+        let cells = rps::random_grid(48, 40, 3);
+        let mut simulation = GpuSimulation::new(Arc::new(device), Arc::new(queue), 48, 40, Rule::CONWAY, Boundary::Dead, Automaton::RockPaperScissors, &cells, None).unwrap();
+        // A cyclic threshold that would let every predator through must not leak into the species
+        simulation.set_cyclic_rule(CyclicRule { states: 3, threshold: 1 });
+        simulation.set_rps_threshold(3);
+        let mut encoder = simulation.device.create_command_encoder(&Default::default());
+        simulation.encode_steps(&mut encoder, 12, None);
+        simulation.queue.submit(Some(encoder.finish()));
+        let expected = (0..12).fold(cells, |cells, _| rps::step_grid(&cells, 48, 3, Boundary::Dead));
+        assert!(simulation.cells() == expected);
+    }
+}
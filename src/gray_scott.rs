@@ -0,0 +1,200 @@
+use std::fmt;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use crate::boundary::Boundary;
+use crate::WALL;
+
+/// A seeded cell: u 0.5 and v 0.25, what the initial squares and painting put down
+pub const SEED: u32 = 0x8000_4000;
+
+// v above which a cell counts as part of a pattern, for the population and patterns
+const LIVE_V: f32 = 0.1;
+// Concentrations are stored as 16-bit fractions of one
+const SCALE: f32 = 65535.0;
+
+/// Gray-Scott reaction-diffusion: feed and kill rates, how fast u and v diffuse, and the time step
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GrayScott {
+    pub feed: f32,
+    pub kill: f32,
+    pub diffusion_u: f32,
+    pub diffusion_v: f32,
+    pub dt: f32,
+}
+
+impl GrayScott {
+    /// Karl Sims' coral growth, F0.0545 K0.062
+    pub const CORAL: GrayScott = GrayScott { feed: 0.0545, kill: 0.062, diffusion_u: 1.0, diffusion_v: 0.5, dt: 1.0 };
+
+    pub fn parse_rate(text: &str) -> Result<f32, String> {
+        match text.parse() {
+            Ok(rate) if (0.0..=0.1).contains(&rate) => Ok(rate),
+            _ => Err(format!("invalid rate '{}' (expected 0 to 0.1)", text)),
+        }
+    }
+
+    /// One cell's concentrations after a time step, given the Laplacian of both
+    pub fn react(&self, (u, v): (f32, f32), (laplacian_u, laplacian_v): (f32, f32)) -> (f32, f32) {
+        let uvv = u * v * v;
+        (
+            u + self.dt * (self.diffusion_u * laplacian_u - uvv + self.feed * (1.0 - u)),
+            v + self.dt * (self.diffusion_v * laplacian_v + uvv - (self.feed + self.kill) * v),
+        )
+    }
+}
+
+impl fmt::Display for GrayScott {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "F{},K{}", self.feed, self.kill)
+    }
+}
+
+/// Packs u and v into a cell: 1 - u in the high 16 bits, v in the low 16, so the uniform field
+/// (u 1, v 0) is 0. v stops one short of the top so no cell can read as a wall (matches rules.wgsl).
+pub fn encode(u: f32, v: f32) -> u32 {
+    let quantize = |x: f32| (x.clamp(0.0, 1.0) * SCALE + 0.5).floor() as u32;
+    quantize(1.0 - u) << 16 | quantize(v).min(0xfffe)
+}
+
+/// (u, v) of a cell packed by `encode`
+pub fn decode(cell: u32) -> (f32, f32) {
+    (1.0 - (cell >> 16) as f32 / SCALE, (cell & 0xffff) as f32 / SCALE)
+}
+
+/// Whether enough of v has built up for the cell to count as part of a pattern
+pub fn is_live(cell: u32) -> bool {
+    cell != WALL && decode(cell).1 > LIVE_V
+}
+
+/// The uniform field with a few `SEED` squares dropped in, roughly one per 256x256 cells
+pub fn seed_grid(width: u32, height: u32, seed: u64) -> Vec<u32> {
+    let (width, height) = (width as usize, height as usize);
+    let mut grid = vec![0; width * height];
+    let mut rng = StdRng::seed_from_u64(seed);
+    let side = 16.min(width).min(height);
+    for _ in 0..(width * height / (256 * 256)).clamp(4, 256) {
+        let (x, y) = (rng.gen_range(0..=width - side), rng.gen_range(0..=height - side));
+        for row in y..y + side {
+            grid[row * width + x..row * width + x + side].fill(SEED);
+        }
+    }
+    grid
+}
+
+/// One time step of a `width`-wide grid from its quantised cells alone, which rounds u and v to 16 bits
+/// every step; `step_grid_into` carries them at full precision.
+pub fn step_grid(input: &[u32], width: usize, params: GrayScott, boundary: Boundary) -> Vec<u32> {
+    let concentrations: Vec<(f32, f32)> = input.iter().map(|&cell| decode(cell)).collect();
+    let mut output = vec![0; input.len()];
+    step_grid_into(input, &mut output, &concentrations, &mut vec![(0.0, 0.0); input.len()], width, params, boundary);
+    output
+}
+
+/// `step_grid` writing into `output` (the same length as `input`) instead of allocating it, from u and v
+/// at full precision in `concentrations` and with the next ones in `next` (both as long as the grid).
+/// A cell that doesn't hold its concentrations quantised any more was written since (painted, loaded,
+/// transformed) and starts over from what it holds.
+///
+/// The Laplacian weighs edge neighbours 0.2 and corners 0.05; neighbours off a dead edge and walls
+/// read as the uniform field.
+pub fn step_grid_into(input: &[u32], output: &mut [u32], concentrations: &[(f32, f32)], next: &mut [(f32, f32)], width: usize, params: GrayScott, boundary: Boundary) {
+    let height = input.len() / width;
+    let held = |index: usize| match concentrations[index] {
+        (u, v) if encode(u, v) == input[index] => (u, v),
+        _ => decode(input[index]),
+    };
+    output.par_iter_mut().zip(next.par_iter_mut()).enumerate().for_each(|(index, (out, next))| {
+        let cell = input[index];
+        if cell == WALL {
+            *out = WALL;
+            return;
+        }
+        let (x, y) = ((index % width) as i64, (index / width) as i64);
+        let (u, v) = held(index);
+        let (mut laplacian_u, mut laplacian_v) = (0.0, 0.0);
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if dx == 0 && dy == 0 { continue; }
+                let weight = if dx == 0 || dy == 0 { 0.2 } else { 0.05 };
+                let neighbour = match (boundary.resolve(x + dx, width), boundary.resolve(y + dy, height)) {
+                    (Some(nx), Some(ny)) if input[ny * width + nx] != WALL => held(ny * width + nx),
+                    _ => (1.0, 0.0),
+                };
+                laplacian_u += weight * (neighbour.0 - u);
+                laplacian_v += weight * (neighbour.1 - v);
+            }
+        }
+        *next = params.react((u, v), (laplacian_u, laplacian_v));
+        *out = encode(next.0, next.1);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding_round_trips_and_avoids_walls() {
+        assert_eq!(encode(1.0, 0.0), 0);
+        assert_eq!(encode(0.5, 0.25), SEED);
+        let (u, v) = decode(encode(0.3, 0.7));
+        assert!((u - 0.3).abs() < 1e-4 && (v - 0.7).abs() < 1e-4);
+        assert_ne!(encode(0.0, 1.0), WALL);
+        assert!(is_live(SEED) && !is_live(0) && !is_live(WALL));
+    }
+
+    #[test]
+    fn uniform_field_stays_put() {
+        let cells = vec![0; 24 * 16];
+        for boundary in [Boundary::Wrap, Boundary::Dead, Boundary::Mirror] {
+            assert_eq!(step_grid(&cells, 24, GrayScott::CORAL, boundary), cells);
+        }
+    }
+
+    #[test]
+    fn seeds_grow_and_walls_hold() {
+        let mut cells = seed_grid(64, 48, 5);
+        let seeded = cells.iter().filter(|&&cell| cell == SEED).count();
+        assert!(seeded >= 16 * 16);
+        cells[0] = WALL;
+        for _ in 0..200 {
+            cells = step_grid(&cells, 64, GrayScott::CORAL, Boundary::Wrap);
+        }
+        assert_eq!(cells[0], WALL);
+        assert!(cells.iter().any(|&cell| is_live(cell)));
+    }
+
+    #[test]
+    fn concentrations_keep_changes_too_small_for_the_cells() {
+        // Each step feeds u a few millionths, less than half of what a cell can tell apart
+        let slow = GrayScott { dt: 0.0001, ..GrayScott::CORAL };
+        let mut cells = vec![encode(0.5, 0.0); 16 * 16];
+        let mut concentrations = vec![(0.0, 0.0); cells.len()];
+        let (mut next_cells, mut next) = (cells.clone(), concentrations.clone());
+        for _ in 0..1000 {
+            step_grid_into(&cells, &mut next_cells, &concentrations, &mut next, 16, slow, Boundary::Wrap);
+            std::mem::swap(&mut cells, &mut next_cells);
+            std::mem::swap(&mut concentrations, &mut next);
+        }
+        assert!(concentrations[0].0 > 0.502 && decode(cells[0]).0 > 0.502);
+        assert_eq!(step_grid(&[encode(0.5, 0.0); 16], 4, slow, Boundary::Wrap), vec![encode(0.5, 0.0); 16]);
+        // A cell written from outside starts over from what it holds
+        cells[0] = SEED;
+        step_grid_into(&cells, &mut next_cells, &concentrations, &mut next, 16, slow, Boundary::Wrap);
+        assert!((next[0].1 - 0.25).abs() < 0.001);
+    }
+
+    #[test]
+    fn without_feed_and_kill_the_total_is_conserved() {
+        let still = GrayScott { feed: 0.0, kill: 0.0, ..GrayScott::CORAL };
+        let mut cells = vec![0; 32 * 32];
+        cells[16 * 32 + 16] = encode(0.2, 0.8);
+        // Diffusion only spreads u and v out and the reaction only turns u into v
+        let total = |cells: &[u32]| cells.iter().map(|&cell| decode(cell)).map(|(u, v)| (u + v) as f64).sum::<f64>();
+        let before = total(&cells);
+        for _ in 0..5 {
+            cells = step_grid(&cells, 32, still, Boundary::Wrap);
+        }
+        assert!((total(&cells) - before).abs() < 0.01, "{} became {}", before, total(&cells));
+    }
+}
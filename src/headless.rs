@@ -0,0 +1,158 @@
+use std::sync::Arc;
+use rust_gpu_life::{automaton::Automaton, bands::{self, BandedSimulation}, count_state, population::{PopulationCounter, Reduction}, snapshot::Snapshot, verify::Verifier, workgroup::WorkgroupSize, CpuSimulation, GpuSimulation, Simulation};
+use crate::adapter;
+use crate::cli::Args;
+use crate::tune;
+
+// --steps generations on the GPU without a window; with --verify any divergence from the CPU is an error,
+// so CI can run it
+pub fn run(args: &Args, initial: Snapshot) -> Result<(), String> {
+    if args.automaton == Automaton::LangtonsAnt {
+        return Err("Langton's ants only walk on the CPU, which --headless doesn't step (try --tui)".to_string());
+    }
+    let instance = adapter::create_instance(args.backends);
+    let adapter = pollster::block_on(adapter::select(&instance, None, args.adapter.as_ref()))?;
+    let info = adapter.get_info();
+    let (device, queue) = pollster::block_on(rust_gpu_life::gpu::request_device(&adapter))?;
+    let (device, queue) = (Arc::new(device), Arc::new(queue));
+    println!("Adapter: {} ({:?}, driver {} {})", info.name, info.backend, info.driver, info.driver_info);
+    let (width, height, rule) = (initial.width, initial.height, initial.rule);
+    println!("Grid: {}x{}, rule {}, {} boundary, {} steps", width, height, rule, args.boundary, args.steps);
+
+    let reach = bands::reach(args.automaton, args.ltl, args.lenia);
+    let layout = bands::plan(args.bands, width, height, reach, &device.limits())?;
+    if layout.bands > 1 {
+        // Bands are stepped under the grid's rule alone
+        if initial.regions.is_some() {
+            return Err("the loaded state has a rule map, which grids stepped in bands can't follow".to_string());
+        }
+        println!("Bands: {} of {} rows with {}-row halos, {} MB per buffer, {} MB in all", layout.bands, layout.rows(0).len(), layout.halo, layout.buffer_size(0) >> 20, layout.total_size() >> 20);
+        let mut gpu = BandedSimulation::new(device.clone(), queue.clone(), layout, rule, args.boundary, args.automaton, &initial.cells)?;
+        // Tuning steps the whole grid in one buffer, which is what doesn't fit
+        let size = args.workgroup.unwrap_or(WorkgroupSize::DEFAULT);
+        gpu.set_workgroup_size(size)?;
+        println!("Workgroup: {}", size);
+        configure(&mut gpu, args);
+        let generation = run_steps(&device, &queue, &mut gpu, args, &initial)?;
+        println!("Population after generation {}: {}", generation, gpu.population());
+        return Ok(());
+    }
+
+    let mut gpu = GpuSimulation::new(device.clone(), queue.clone(), width, height, rule, args.boundary, args.automaton, &initial.cells, None)?;
+    gpu.set_workgroup_size(tune::choose(&device, &queue, args, &initial, &info.name)?)?;
+    configure(&mut gpu, args);
+    gpu.set_rule_map(initial.regions.as_ref());
+    gpu.set_generation(initial.generation);
+    if !args.chunk_skipping { gpu.set_chunk_skipping(false)?; }
+    let generation = run_steps(&device, &queue, &mut gpu, args, &initial)?;
+    let population = gpu.population();
+    if args.verify.is_some() {
+        verify_population(&device, &queue, &gpu, [population, count_state(gpu.cells(), 2), count_state(gpu.cells(), 3)])?;
+    }
+    println!("Population after generation {}: {}", generation, population);
+    Ok(())
+}
+
+// What the run needs of the GPU simulation, whole or in bands
+trait Steps: Simulation {
+    fn encode(&mut self, encoder: &mut wgpu::CommandEncoder, generations: u32);
+    // Copies the latest generation for `verifier`; false while its staging slots are all busy
+    fn copy_to(&self, verifier: &mut Verifier, encoder: &mut wgpu::CommandEncoder, generation: usize) -> bool;
+}
+
+impl Steps for GpuSimulation {
+    fn encode(&mut self, encoder: &mut wgpu::CommandEncoder, generations: u32) {
+        self.encode_steps(encoder, generations, None);
+    }
+
+    fn copy_to(&self, verifier: &mut Verifier, encoder: &mut wgpu::CommandEncoder, generation: usize) -> bool {
+        verifier.encode(encoder, self.current_buffer(), generation)
+    }
+}
+
+impl Steps for BandedSimulation {
+    fn encode(&mut self, encoder: &mut wgpu::CommandEncoder, generations: u32) {
+        self.encode_steps(encoder, generations);
+    }
+
+    fn copy_to(&self, verifier: &mut Verifier, encoder: &mut wgpu::CommandEncoder, generation: usize) -> bool {
+        verifier.encode_parts(encoder, self.interiors(), generation)
+    }
+}
+
+// The command line's settings, on the GPU and on the CPU shadow alike
+pub fn configure(simulation: &mut dyn Simulation, args: &Args) {
+    simulation.set_topology(args.topology);
+    simulation.set_ltl_rule(args.ltl);
+    simulation.set_gray_scott(args.gray_scott);
+    simulation.set_cyclic_rule(args.cyclic);
+    simulation.set_rps_threshold(args.rps_threshold);
+    simulation.set_lenia(args.lenia);
+    simulation.set_forest_fire(args.forest_fire);
+    simulation.set_ant_rule(args.ant_rule);
+}
+
+// --steps generations of `gpu`, checked against a CPU shadow every --verify generations; returns the
+// generation it ends on
+fn run_steps(device: &wgpu::Device, queue: &wgpu::Queue, gpu: &mut impl Steps, args: &Args, initial: &Snapshot) -> Result<usize, String> {
+    let mut verifier = args.verify.map(|every| {
+        let mut shadow = CpuSimulation::new(initial.width, initial.height, initial.rule, args.boundary, args.automaton, initial.cells.clone());
+        configure(&mut shadow, args);
+        shadow.set_rule_map(initial.regions.as_ref());
+        Verifier::new(device, every, shadow, initial.generation as usize)
+    });
+
+    // Chunks end on the checkpoints, so every N-th generation is the one compared
+    let mut generation = initial.generation as usize;
+    let end = generation + args.steps as usize;
+    let mut verified = 0;
+    while generation < end {
+        let chunk = args.verify.unwrap_or(end - generation).min(end - generation);
+        let mut encoder = device.create_command_encoder(&Default::default());
+        gpu.encode(&mut encoder, chunk as u32);
+        generation += chunk;
+        if let Some(verifier) = &mut verifier {
+            // All staging slots busy: wait for the CPU to catch up rather than skip a checkpoint
+            while !gpu.copy_to(verifier, &mut encoder, generation) {
+                device.poll(wgpu::Maintain::Wait);
+                verified += check(verifier)?;
+            }
+        }
+        queue.submit(Some(encoder.finish()));
+        if let Some(verifier) = &mut verifier {
+            verifier.begin_readback();
+            device.poll(wgpu::Maintain::Poll);
+            verified += check(verifier)?;
+        }
+    }
+    device.poll(wgpu::Maintain::Wait);
+    if let Some(verifier) = &mut verifier {
+        verified += check(verifier)?;
+        println!("Verified {} checkpoints against the CPU, up to generation {}", verified, generation);
+    }
+    Ok(generation)
+}
+
+// Both GPU reductions have to come to the CPU's [live, state 2, state 3] counts of the same cells exactly
+fn verify_population(device: &wgpu::Device, queue: &wgpu::Queue, gpu: &GpuSimulation, expected: [u32; 3]) -> Result<(), String> {
+    let cell_count = gpu.cells().len();
+    for reduction in Reduction::ALL {
+        let mut counter = PopulationCounter::new(device, &gpu.buffers()[0], &gpu.buffers()[1], reduction);
+        let counted = counter.count_blocking(device, queue, gpu.parity(), cell_count)
+            .ok_or_else(|| format!("the {} population count never came back", reduction))?;
+        if counted != expected {
+            return Err(format!("the {} population count found {:?} [live, state 2, state 3], the CPU {:?}", reduction, counted, expected));
+        }
+    }
+    println!("Population counts match the CPU with both reductions");
+    Ok(())
+}
+
+// Number of checkpoints that arrived and matched
+fn check(verifier: &mut Verifier) -> Result<usize, String> {
+    match verifier.check() {
+        Some(Ok(_)) => Ok(1),
+        Some(Err(divergence)) => Err(divergence.to_string()),
+        None => Ok(0),
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::VecDeque;
+use rayon::prelude::*;
+use crate::readback::StagingRing;
+use crate::WALL;
+
+const WORKGROUP_SIZE: u32 = 256;
+// Widest cell `pack` stores, which sizes the budget and the GPU buffers
+const MAX_STATE_BITS: u32 = 2;
+
+/// Packs cells into `bits` bits each (1 or 2, see `Automaton::state_bits`), 32 / `bits` to a word;
+/// with 1 bit Life ages are dropped. Walls are left out (see `Walls`).
+pub fn pack(cells: &[u32], bits: u32) -> Vec<u32> {
+    cells.par_chunks((32 / bits) as usize).map(|chunk| {
+        chunk.iter().enumerate().fold(0, |word, (i, &cell)| {
+            let state = match cell {
+                WALL => 0,
+                _ if bits == 1 => (cell > 0) as u32,
+                _ => cell,
+            };
+            word | state << (i as u32 * bits)
+        })
+    }).collect()
+}
+
+/// The first `len` cells of a grid packed by `pack`; live Life cells come back with age 1.
+pub fn unpack(words: &[u32], bits: u32, len: usize) -> Vec<u32> {
+    let per_word = (32 / bits) as usize;
+    let mask = (1 << bits) - 1;
+    (0..len).map(|i| words[i / per_word] >> ((i % per_word) as u32 * bits) & mask).collect()
+}
+
+// Words `pack` produces for `cells` cells
+fn word_count(cells: usize, bits: u32) -> usize {
+    cells.div_ceil((32 / bits) as usize)
+}
+
+struct Frame {
+    generation: usize,
+    words: Vec<u32>,
+}
+
+/// The last few generations, bit-packed, so a run can be rewound.
+pub struct History {
+    frames: VecDeque<Frame>,
+    capacity: usize,
+    // Generations between snapshots
+    every: usize,
+    cells: usize,
+    bits: u32,
+}
+
+impl History {
+    /// Keeps up to `wanted` snapshots of a `cells`-cell grid at `bits` per cell, fewer if they wouldn't
+    /// fit in `budget` bytes at the widest cells.
+    pub fn new(wanted: usize, every: usize, cells: usize, bits: u32, budget: usize) -> History {
+        let capacity = wanted.min(budget / (word_count(cells, MAX_STATE_BITS) * 4).max(1));
+        History { frames: VecDeque::with_capacity(capacity), capacity, every, cells, bits }
+    }
+
+    /// Most snapshots kept at once.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Memory the snapshots currently take.
+    pub fn bytes(&self) -> usize {
+        self.frames.iter().map(|frame| frame.words.len() * 4).sum()
+    }
+
+    /// Most memory the snapshots can take.
+    pub fn budget(&self) -> usize {
+        self.capacity * word_count(self.cells, MAX_STATE_BITS) * 4
+    }
+
+    /// Whether `generation` is far enough past the newest snapshot to be kept; never with 0 bits per
+    /// cell, which means the automaton's cells don't fit.
+    pub fn due(&self, generation: usize) -> bool {
+        self.bits > 0 && self.capacity > 0 && self.frames.back().is_none_or(|frame| generation >= frame.generation + self.every)
+    }
+
+    /// Keeps `words`, packed with the current `bits`, dropping the oldest snapshot when full.
+    pub fn push(&mut self, generation: usize, words: Vec<u32>) {
+        if !self.due(generation) { return; }
+        if self.frames.len() == self.capacity { self.frames.pop_front(); }
+        self.frames.push_back(Frame { generation, words });
+    }
+
+    /// Packs and keeps `cells` if `generation` is due.
+    pub fn record(&mut self, generation: usize, cells: &[u32]) {
+        if self.due(generation) { self.push(generation, pack(cells, self.bits)); }
+    }
+
+    /// Forgets everything, e.g. when the grid is replaced; snapshots from now on take `bits` per cell.
+    pub fn clear(&mut self, bits: u32) {
+        self.frames.clear();
+        self.bits = bits;
+    }
+
+    /// Bits per cell of the snapshots.
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// The newest snapshot, unpacked, keeping everything.
+    pub fn latest(&self) -> Option<(usize, Vec<u32>)> {
+        self.frames.back().map(|frame| (frame.generation, unpack(&frame.words, self.bits, self.cells)))
+    }
+
+    /// The newest snapshot from before `generation`, unpacked; it and everything after it are dropped
+    /// except that snapshot itself, since stepping on from there computes them again.
+    pub fn rewind(&mut self, generation: usize) -> Option<(usize, Vec<u32>)> {
+        while self.frames.back().is_some_and(|frame| frame.generation >= generation) {
+            self.frames.pop_back();
+        }
+        self.frames.back().map(|frame| (frame.generation, unpack(&frame.words, self.bits, self.cells)))
+    }
+}
+
+// Uniform shared with the compute shader (see `Pack` in pack.wgsl)
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct PackParams {
+    cells: u32,
+    bits: u32,
+    _pad: [u32; 2],
+}
+
+/// `pack` on the GPU, read back asynchronously so the grid never has to come back at full size
+pub struct HistoryPacker {
+    pipeline: wgpu::ComputePipeline,
+    bind_groups: [wgpu::BindGroup; 2],
+    packed: wgpu::Buffer,
+    params: wgpu::Buffer,
+    staging: StagingRing,
+    cells: usize,
+    bits: u32,
+}
+
+impl HistoryPacker {
+    pub fn new(device: &wgpu::Device, buffer_a: &wgpu::Buffer, buffer_b: &wgpu::Buffer, cells: usize) -> HistoryPacker {
+        let size = (word_count(cells, MAX_STATE_BITS) * 4) as u64;
+        let packed = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("History Packed"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("History Params"),
+            size: std::mem::size_of::<PackParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let staging = StagingRing::new(device, "History Staging", size);
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry { binding: 0, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: true }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 1, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: false }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 2, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            ],
+            label: None,
+        });
+        let bind_group = |cells: &wgpu::Buffer| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: cells.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: packed.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: params.as_entire_binding() },
+            ],
+            label: None,
+        });
+        let bind_groups = [bind_group(buffer_a), bind_group(buffer_b)];
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("pack.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&layout], ..Default::default() });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("History Pack"), layout: Some(&pipeline_layout), module: &shader, entry_point: "main", compilation_options: Default::default(), cache: None,
+        });
+
+        // Zero bits, so the first `encode` writes the parameters
+        HistoryPacker { pipeline, bind_groups, packed, params, staging, cells, bits: 0 }
+    }
+
+    /// Packs the buffer for `parity` (0 = buffer A) with `bits` per cell if a staging slot is free,
+    /// otherwise skips this frame
+    pub fn encode(&mut self, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, parity: usize, bits: u32) {
+        let Some(staging) = self.staging.next() else { return };
+        if bits != self.bits {
+            self.bits = bits;
+            queue.write_buffer(&self.params, 0, bytemuck::bytes_of(&PackParams { cells: self.cells as u32, bits, _pad: [0; 2] }));
+        }
+
+        let words = word_count(self.cells, bits);
+        {
+            let groups = (words as u32).div_ceil(WORKGROUP_SIZE);
+            let groups_x = groups.min(65535);
+            let mut cpass = encoder.begin_compute_pass(&Default::default());
+            cpass.set_pipeline(&self.pipeline);
+            cpass.set_bind_group(0, &self.bind_groups[parity], &[]);
+            cpass.dispatch_workgroups(groups_x, groups.div_ceil(groups_x), 1);
+        }
+        encoder.copy_buffer_to_buffer(&self.packed, 0, staging, 0, (words * 4) as u64);
+    }
+
+    /// Maps the slot written by `encode`, which packed `generation`; must be called after that encoder was submitted
+    pub fn begin_readback(&mut self, generation: usize) {
+        self.staging.begin_tagged_readback(generation as u64);
+    }
+
+    /// Newest snapshot whose mapping has completed and its generation, if any; call after `device.poll`
+    pub fn try_read(&mut self) -> Option<(usize, Vec<u32>)> {
+        let (cells, bits) = (self.cells, self.bits);
+        self.staging.try_read_tagged(|bytes| bytemuck::cast_slice::<u8, u32>(bytes)[..word_count(cells, bits)].to_vec())
+            .map(|(generation, words)| (generation as usize, words))
+    }
+
+    /// Drops the snapshots still on their way back, e.g. after a rewind or a new grid
+    pub fn discard(&mut self) {
+        self.staging.discard();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_round_trips_ragged_grids() {
+        let cells: Vec<u32> = (0..77).map(|i| i % 4).collect();
+        assert_eq!(unpack(&pack(&cells, 2), 2, cells.len()), cells);
+        // One bit keeps only whether a cell is alive
+        let alive: Vec<u32> = cells.iter().map(|&cell| (cell > 0) as u32).collect();
+        assert_eq!(pack(&cells, 1).len(), 3);
+        assert_eq!(unpack(&pack(&cells, 1), 1, cells.len()), alive);
+    }
+
+    #[test]
+    fn full_history_drops_the_oldest() {
+        let mut history = History::new(3, 1, 64, 1, usize::MAX);
+        for generation in 0..5 {
+            history.record(generation, &[generation as u32; 64]);
+        }
+        assert_eq!(history.len(), 3);
+        // One bit per cell keeps generation 4's cells as alive
+        assert_eq!(history.latest(), Some((4, vec![1; 64])));
+        assert_eq!(history.rewind(5).map(|(generation, _)| generation), Some(4));
+        assert_eq!(history.rewind(4).map(|(generation, _)| generation), Some(3));
+        assert_eq!(history.rewind(3).map(|(generation, _)| generation), Some(2));
+        assert_eq!(history.rewind(2), None);
+    }
+
+    #[test]
+    fn rewinding_drops_the_future() {
+        let mut history = History::new(8, 2, 16, 1, usize::MAX);
+        for generation in 0..8 {
+            history.record(generation, &[1; 16]);
+        }
+        // Every other generation was kept
+        assert_eq!(history.len(), 4);
+        let (generation, cells) = history.rewind(5).unwrap();
+        assert_eq!((generation, cells), (4, vec![1; 16]));
+        // Stepping on from the rewound point keeps snapshots again
+        assert!(!history.due(5));
+        assert!(history.due(6));
+    }
+
+    #[test]
+    fn zero_bits_keeps_nothing() {
+        let mut history = History::new(8, 1, 16, 0, usize::MAX);
+        history.record(0, &[7; 16]);
+        assert!(history.is_empty());
+        history.clear(1);
+        history.record(1, &[1; 16]);
+        assert_eq!(history.len(), 1);
+    }
+
+    #[test]
+    fn budget_limits_the_capacity() {
+        // 4096x4096 at 2 bits is 4 MB a snapshot
+        let history = History::new(256, 1, 4096 * 4096, 1, 100 << 20);
+        assert_eq!(history.capacity(), 25);
+        assert_eq!(history.budget(), 100 << 20);
+    }
+}
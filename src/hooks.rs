@@ -0,0 +1,242 @@
+use std::fmt;
+
+/// A population level, in cells or in percent of the grid.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Threshold {
+    Cells(u32),
+    Percent(f64),
+}
+
+impl Threshold {
+    fn cells(self, grid_cells: usize) -> f64 {
+        match self {
+            Threshold::Cells(cells) => cells as f64,
+            Threshold::Percent(percent) => percent / 100.0 * grid_cells as f64,
+        }
+    }
+}
+
+/// When a hook fires.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Condition {
+    /// The population drops below the threshold
+    Below(Threshold),
+    /// The population rises above the threshold
+    Above(Threshold),
+    /// The generation count passes a multiple of this
+    Every(usize),
+    /// The grid dies out or stops evolving, the title's EXTINCT or STABLE
+    Settled,
+}
+
+/// What a hook does, the built-in ones the window has keys for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HookAction {
+    Reseed,
+    Screenshot,
+    SaveState,
+    NextRule,
+    Pause,
+    Quit,
+}
+
+const ACTIONS: [(HookAction, &str); 6] = [
+    (HookAction::Reseed, "reseed"),
+    (HookAction::Screenshot, "screenshot"),
+    (HookAction::SaveState, "save_state"),
+    (HookAction::NextRule, "next_rule"),
+    (HookAction::Pause, "pause"),
+    (HookAction::Quit, "quit"),
+];
+
+/// A condition and the action it triggers, from a `hook = "CONDITION: ACTION"` line of the config file
+/// or a --hook flag.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Hook {
+    pub condition: Condition,
+    pub action: HookAction,
+}
+
+impl Hook {
+    /// "population < 1%: reseed", "population > 5000: pause", "every 10000: screenshot" or
+    /// "settled: next_rule" (or "stable"); action names take _ or - between words.
+    pub fn parse(text: &str) -> Result<Hook, String> {
+        let (condition, action) = text.rsplit_once(':').ok_or_else(|| format!("expected CONDITION: ACTION, got '{}'", text))?;
+        Ok(Hook { condition: parse_condition(condition.trim())?, action: parse_action(action.trim())? })
+    }
+}
+
+fn parse_condition(text: &str) -> Result<Condition, String> {
+    if text == "settled" || text == "stable" { return Ok(Condition::Settled); }
+    if let Some(every) = text.strip_prefix("every ") {
+        return match number(every).parse() {
+            Ok(every) if every > 0 => Ok(Condition::Every(every)),
+            _ => Err(format!("expected every N generations with N at least 1, got '{}'", text)),
+        };
+    }
+    let threshold = |text: &str| match text.trim().strip_suffix('%') {
+        Some(percent) => match number(percent).parse() {
+            Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(Threshold::Percent(percent)),
+            _ => Err(format!("expected a percentage between 0 and 100, got '{}'", text.trim())),
+        },
+        None => number(text).parse().map(Threshold::Cells).map_err(|_| format!("expected a number of cells or a percentage, got '{}'", text.trim())),
+    };
+    match text.strip_prefix("population") {
+        Some(rest) if rest.trim_start().starts_with('<') => Ok(Condition::Below(threshold(&rest.trim_start()[1..])?)),
+        Some(rest) if rest.trim_start().starts_with('>') => Ok(Condition::Above(threshold(&rest.trim_start()[1..])?)),
+        _ => Err(format!("unknown condition '{}' (population < N, population > N, every N or settled)", text)),
+    }
+}
+
+fn parse_action(text: &str) -> Result<HookAction, String> {
+    let name = text.replace('-', "_");
+    ACTIONS.iter().find(|&&(_, known)| known == name).map(|&(action, _)| action).ok_or_else(|| {
+        let names: Vec<&str> = ACTIONS.iter().map(|&(_, name)| name).collect();
+        format!("unknown action '{}' ({})", text, names.join(", "))
+    })
+}
+
+// "10,000" and "10_000" read like 10000
+fn number(text: &str) -> String {
+    text.trim().replace([',', '_'], "")
+}
+
+impl fmt::Display for Threshold {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Threshold::Cells(cells) => write!(f, "{}", cells),
+            Threshold::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Condition::Below(threshold) => write!(f, "population < {}", threshold),
+            Condition::Above(threshold) => write!(f, "population > {}", threshold),
+            Condition::Every(every) => write!(f, "every {}", every),
+            Condition::Settled => f.write_str("settled"),
+        }
+    }
+}
+
+impl fmt::Display for HookAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(ACTIONS.iter().find(|&&(action, _)| action == *self).map_or("", |&(_, name)| name))
+    }
+}
+
+impl fmt::Display for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.condition, self.action)
+    }
+}
+
+/// The stats hooks are checked against, the ones the window keeps anyway.
+pub struct Stats {
+    pub generation: usize,
+    /// A population count read back since the last check, if one arrived
+    pub population: Option<u32>,
+    pub cells: usize,
+    pub settled: bool,
+}
+
+/// The hooks of a run and what each has seen. A population or settled hook fires when its condition
+/// starts to hold and again only once it has stopped holding, so a sparse grid isn't reseeded every frame
+/// while the count read back still shows the old one; `every` fires once per multiple passed.
+pub struct Hooks {
+    hooks: Vec<Hook>,
+    // Whether each hook's condition held at the last check that could tell
+    held: Vec<bool>,
+    // Generation of the last check
+    last: usize,
+}
+
+impl Hooks {
+    /// Checks from `generation` on.
+    pub fn new(hooks: Vec<Hook>, generation: usize) -> Hooks {
+        Hooks { held: vec![false; hooks.len()], hooks, last: generation }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Whether a hook is waiting for the grid to settle, which then decides what happens instead of the
+    /// usual pause.
+    pub fn on_settled(&self) -> bool {
+        self.hooks.iter().any(|hook| hook.condition == Condition::Settled)
+    }
+
+    /// The hooks that fire at `stats`, in the order they were given. Several generations in one frame are
+    /// checked as the latest of them, and nothing new happens within a generation except a population
+    /// count arriving; going back in time counts `every` from there.
+    pub fn check(&mut self, stats: &Stats) -> Vec<Hook> {
+        let last = std::mem::replace(&mut self.last, stats.generation);
+        let advanced = stats.generation > last;
+        if !advanced && stats.population.is_none() { return Vec::new(); }
+        self.hooks.iter().zip(&mut self.held).filter_map(|(&hook, held)| {
+            let holds = match hook.condition {
+                Condition::Every(every) => return (advanced && stats.generation / every > last / every).then_some(hook),
+                Condition::Below(threshold) => (stats.population? as f64) < threshold.cells(stats.cells),
+                Condition::Above(threshold) => stats.population? as f64 > threshold.cells(stats.cells),
+                Condition::Settled => stats.settled,
+            };
+            let fires = holds && !*held;
+            *held = holds;
+            fires.then_some(hook)
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hooks_read_back_what_they_print() {
+        for text in ["population < 1%: reseed", "population > 5000: pause", "every 10000: screenshot", "settled: next_rule", "population < 0.5%: save_state", "every 1: quit"] {
+            assert_eq!(Hook::parse(text).unwrap().to_string(), text);
+        }
+        let hook = Hook::parse("every 10,000 : save-state").unwrap();
+        assert_eq!(hook, Hook { condition: Condition::Every(10_000), action: HookAction::SaveState });
+        assert_eq!(Hook::parse("stable: pause").unwrap().condition, Condition::Settled);
+        assert!(Hook::parse("population < 1%").is_err());
+        assert!(Hook::parse("population = 1%: reseed").is_err());
+        assert!(Hook::parse("population < 120%: reseed").is_err());
+        assert!(Hook::parse("every 0: screenshot").is_err());
+        assert!(Hook::parse("settled: explode").unwrap_err().contains("next_rule"));
+    }
+
+    #[test]
+    fn levels_fire_once_until_they_stop_holding() {
+        let mut hooks = Hooks::new(vec![Hook::parse("population < 1%: reseed").unwrap()], 0);
+        let stats = |generation, population| Stats { generation, population, cells: 10_000, settled: false };
+        assert!(hooks.check(&stats(1, Some(500))).is_empty());
+        assert_eq!(hooks.check(&stats(2, Some(50))).len(), 1);
+        // Still low, or no news, while the reseed is on its way
+        assert!(hooks.check(&stats(3, Some(40))).is_empty());
+        assert!(hooks.check(&stats(1, None)).is_empty());
+        assert!(hooks.check(&stats(2, Some(800))).is_empty());
+        assert_eq!(hooks.check(&stats(3, Some(90))).len(), 1);
+        // Nothing is checked twice in a generation without a new count
+        assert!(hooks.check(&stats(3, None)).is_empty());
+    }
+
+    #[test]
+    fn every_fires_once_per_multiple_passed() {
+        let mut hooks = Hooks::new(vec![Hook::parse("every 100: screenshot").unwrap(), Hook::parse("settled: next_rule").unwrap()], 0);
+        assert!(hooks.on_settled());
+        let mut fired = |generation, settled| hooks.check(&Stats { generation, population: None, cells: 100, settled }).iter().map(|hook| hook.action).collect::<Vec<_>>();
+        assert!(fired(60, false).is_empty());
+        assert_eq!(fired(130, false), [HookAction::Screenshot]);
+        assert_eq!(fired(199, true), [HookAction::NextRule]);
+        assert_eq!(fired(200, true), [HookAction::Screenshot]);
+        // A frame that steps past several multiples fires once
+        assert_eq!(fired(550, false), [HookAction::Screenshot]);
+        // Reseeded back to 0, the count starts over
+        assert!(fired(0, false).is_empty());
+        assert_eq!(fired(100, true), [HookAction::Screenshot, HookAction::NextRule]);
+    }
+}
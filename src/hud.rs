@@ -0,0 +1,79 @@
+use web_time::{Duration, Instant};
+
+// How often the HUD text and the window title are rebuilt
+pub const HUD_INTERVAL: Duration = Duration::from_millis(100);
+pub const TITLE_INTERVAL: Duration = Duration::from_secs(1);
+
+// Lets something happen at most once per interval; the first call always passes
+pub struct Throttle {
+    interval: Duration,
+    last: Option<Instant>,
+}
+
+impl Throttle {
+    pub fn new(interval: Duration) -> Throttle {
+        Throttle { interval, last: None }
+    }
+
+    pub fn ready(&mut self) -> bool {
+        let now = Instant::now();
+        if self.last.is_some_and(|last| now - last < self.interval) { return false; }
+        self.last = Some(now);
+        true
+    }
+
+    // The next `ready` passes, e.g. right after the HUD is turned back on
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+// Status text in the top right corner of the frame, away from the side panel. egui lays it out in
+// points, so it follows the window's DPI scale
+pub fn show(context: &egui::Context, lines: &[String]) {
+    egui::Area::new(egui::Id::new("hud"))
+        .anchor(egui::Align2::RIGHT_TOP, [-8.0, 8.0])
+        .order(egui::Order::Foreground)
+        .interactable(false)
+        .show(context, |ui| {
+            egui::Frame::none().fill(egui::Color32::from_black_alpha(160)).rounding(4.0).inner_margin(6.0).show(ui, |ui| {
+                for line in lines {
+                    ui.label(egui::RichText::new(line).monospace().color(egui::Color32::WHITE));
+                }
+            });
+        });
+}
+
+// One bound action in the help: its name in keybinds.toml, its keys and what it's set to now, if anything
+pub struct HelpRow {
+    pub action: &'static str,
+    pub keys: String,
+    pub value: Option<String>,
+}
+
+// Every bound key in the middle of the frame, in two halves side by side so it fits a laptop screen. It
+// takes no input, so the simulation goes on underneath and keys still reach it
+pub fn show_help(context: &egui::Context, rows: &[HelpRow]) {
+    let text = |text: &str| egui::RichText::new(text).monospace().color(egui::Color32::WHITE);
+    let half = rows.len().div_ceil(2);
+    egui::Area::new(egui::Id::new("help"))
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .order(egui::Order::Foreground)
+        .interactable(false)
+        .show(context, |ui| {
+            egui::Frame::none().fill(egui::Color32::from_black_alpha(200)).rounding(4.0).inner_margin(10.0).show(ui, |ui| {
+                ui.label(text("Keys, with what they're set to now (remap them in keybinds.toml)").strong());
+                ui.add_space(4.0);
+                egui::Grid::new("help").spacing([12.0, 2.0]).show(ui, |ui| {
+                    for index in 0..half {
+                        for row in [rows.get(index), rows.get(index + half)].into_iter().flatten() {
+                            ui.label(text(&row.keys));
+                            ui.label(text(row.action).color(egui::Color32::GRAY));
+                            ui.label(text(row.value.as_deref().unwrap_or_default()).color(egui::Color32::LIGHT_GREEN));
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+}
@@ -0,0 +1,387 @@
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use rust_gpu_life::config::{self, Value};
+use winit::keyboard::KeyCode;
+
+// Everything a key can do. A key bound to several actions does the first one that applies, in this order,
+// so R turns a pending stamp and only resets the grid otherwise, F likewise flips it or follows the action,
+// and Escape closes the help before it cancels a stamp
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    ToggleBackend,
+    ToggleSplit,
+    NewWindow,
+    CycleCpuBackend,
+    Pause,
+    StopSearch,
+    Step,
+    StepBack,
+    Clear,
+    ToggleHelp,
+    CloseHelp,
+    TogglePanel,
+    CycleDisplay,
+    CycleTheme,
+    ToggleMinimap,
+    ToggleChunkOverlay,
+    ToggleChunkSkipping,
+    ToggleSeam,
+    ToggleInterpolation,
+    ToggleHud,
+    Screenshot,
+    Record,
+    SaveState,
+    LoadState,
+    // Slots 0 to 4, on the keys 1 to 5
+    SaveSlot(usize),
+    RestoreSlot(usize),
+    Copy,
+    Cut,
+    Paste,
+    RotateStamp,
+    FlipStamp,
+    Glider,
+    Spaceship,
+    GliderGun,
+    ToggleRain,
+    ToggleMutation,
+    RevertMutation,
+    CancelStamp,
+    ClearSelection,
+    Reset,
+    CycleBoundary,
+    CyclePresentMode,
+    CycleAutomaton,
+    CycleRule,
+    CycleRuleAndReset,
+    PanUp,
+    PanLeft,
+    PanDown,
+    PanRight,
+    ShiftGridUp,
+    ShiftGridLeft,
+    ShiftGridDown,
+    ShiftGridRight,
+    RotateGrid,
+    MirrorGridX,
+    MirrorGridY,
+    ResetView,
+    ToggleFollow,
+    FitBounds,
+    Faster,
+    Slower,
+}
+
+// (action, name in keybinds.toml, default keys)
+const DEFAULTS: &[(Action, &str, &[&str])] = &[
+    (Action::ToggleBackend, "toggle_backend", &["Space"]),
+    (Action::ToggleSplit, "toggle_split", &["KeyK"]),
+    (Action::NewWindow, "new_window", &["Ctrl+KeyN"]),
+    (Action::CycleCpuBackend, "cycle_cpu_backend", &["KeyO"]),
+    (Action::Pause, "pause", &["KeyP"]),
+    (Action::StopSearch, "stop_search", &["Enter"]),
+    (Action::Step, "step", &["KeyN", "ArrowRight"]),
+    (Action::StepBack, "step_back", &["ArrowLeft"]),
+    (Action::Clear, "clear", &["KeyC"]),
+    (Action::ToggleHelp, "toggle_help", &["Slash", "Ctrl+F1"]),
+    (Action::CloseHelp, "close_help", &["Escape"]),
+    (Action::TogglePanel, "toggle_panel", &["F1"]),
+    (Action::CycleDisplay, "cycle_display", &["F2"]),
+    (Action::CycleTheme, "cycle_theme", &["KeyT"]),
+    (Action::ToggleMinimap, "toggle_minimap", &["F3"]),
+    (Action::ToggleChunkOverlay, "toggle_chunk_overlay", &["F4"]),
+    (Action::ToggleChunkSkipping, "toggle_chunk_skipping", &["Ctrl+F4"]),
+    (Action::ToggleSeam, "toggle_seam", &["F5"]),
+    (Action::ToggleInterpolation, "toggle_interpolation", &["F6"]),
+    (Action::ToggleHud, "toggle_hud", &["KeyH"]),
+    (Action::Screenshot, "screenshot", &["F12"]),
+    (Action::Record, "record", &["F9"]),
+    (Action::SaveState, "save_state", &["Ctrl+KeyS"]),
+    (Action::LoadState, "load_state", &["Ctrl+KeyO"]),
+    (Action::SaveSlot(0), "save_slot_1", &["Ctrl+Digit1"]),
+    (Action::SaveSlot(1), "save_slot_2", &["Ctrl+Digit2"]),
+    (Action::SaveSlot(2), "save_slot_3", &["Ctrl+Digit3"]),
+    (Action::SaveSlot(3), "save_slot_4", &["Ctrl+Digit4"]),
+    (Action::SaveSlot(4), "save_slot_5", &["Ctrl+Digit5"]),
+    (Action::RestoreSlot(0), "restore_slot_1", &["Digit1"]),
+    (Action::RestoreSlot(1), "restore_slot_2", &["Digit2"]),
+    (Action::RestoreSlot(2), "restore_slot_3", &["Digit3"]),
+    (Action::RestoreSlot(3), "restore_slot_4", &["Digit4"]),
+    (Action::RestoreSlot(4), "restore_slot_5", &["Digit5"]),
+    (Action::Copy, "copy", &["Ctrl+KeyC"]),
+    (Action::Cut, "cut", &["Ctrl+KeyX"]),
+    (Action::Paste, "paste", &["Ctrl+KeyV"]),
+    (Action::RotateStamp, "rotate_stamp", &["KeyR"]),
+    (Action::FlipStamp, "flip_stamp", &["KeyF"]),
+    (Action::Glider, "glider", &["KeyG"]),
+    (Action::Spaceship, "spaceship", &["KeyL"]),
+    (Action::GliderGun, "glider_gun", &["KeyU"]),
+    (Action::ToggleRain, "toggle_rain", &["KeyI"]),
+    (Action::ToggleMutation, "toggle_mutation", &["KeyE"]),
+    (Action::RevertMutation, "revert_mutation", &["KeyZ"]),
+    (Action::CancelStamp, "cancel_stamp", &["Escape"]),
+    (Action::ClearSelection, "clear_selection", &["Escape"]),
+    (Action::Reset, "reset", &["KeyR"]),
+    (Action::CycleBoundary, "cycle_boundary", &["KeyB"]),
+    (Action::CyclePresentMode, "cycle_present_mode", &["KeyV"]),
+    (Action::CycleAutomaton, "cycle_automaton", &["KeyM"]),
+    (Action::CycleRule, "cycle_rule", &["KeyY", "Tab"]),
+    (Action::CycleRuleAndReset, "cycle_rule_and_reset", &["Ctrl+Tab"]),
+    (Action::PanUp, "pan_up", &["KeyW"]),
+    (Action::PanLeft, "pan_left", &["KeyA"]),
+    (Action::PanDown, "pan_down", &["KeyS"]),
+    (Action::PanRight, "pan_right", &["KeyD"]),
+    (Action::ShiftGridUp, "shift_grid_up", &["Ctrl+ArrowUp"]),
+    (Action::ShiftGridLeft, "shift_grid_left", &["Ctrl+ArrowLeft"]),
+    (Action::ShiftGridDown, "shift_grid_down", &["Ctrl+ArrowDown"]),
+    (Action::ShiftGridRight, "shift_grid_right", &["Ctrl+ArrowRight"]),
+    (Action::RotateGrid, "rotate_grid", &["Ctrl+KeyR"]),
+    (Action::MirrorGridX, "mirror_grid_x", &["Ctrl+KeyH"]),
+    (Action::MirrorGridY, "mirror_grid_y", &["Ctrl+KeyJ"]),
+    (Action::ResetView, "reset_view", &["Home"]),
+    (Action::ToggleFollow, "toggle_follow", &["KeyF"]),
+    (Action::FitBounds, "fit_bounds", &["End"]),
+    (Action::Faster, "faster", &["BracketRight", "Equal", "NumpadAdd"]),
+    (Action::Slower, "slower", &["BracketLeft", "Minus", "NumpadSubtract"]),
+];
+
+// A key, optionally with Ctrl held; bindings without Ctrl fire whether it's held or not
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Binding {
+    key: KeyCode,
+    ctrl: bool,
+}
+
+// "Ctrl+KeyS" -> S with Ctrl held; key names are winit's `KeyCode` names
+fn parse_binding(text: &str) -> Result<Binding, String> {
+    let (ctrl, name) = match text.strip_prefix("Ctrl+") {
+        Some(name) => (true, name),
+        None => (false, text),
+    };
+    let key = parse_key(name).ok_or_else(|| format!("unknown key '{}' (expected a key name such as KeyA, Digit1, F5, Space or ArrowUp)", name))?;
+    Ok(Binding { key, ctrl })
+}
+
+macro_rules! key_names {
+    ($($key:ident)*) => {
+        fn parse_key(name: &str) -> Option<KeyCode> {
+            match name {
+                $(stringify!($key) => Some(KeyCode::$key),)*
+                _ => None,
+            }
+        }
+
+        fn key_name(key: KeyCode) -> &'static str {
+            match key {
+                $(KeyCode::$key => stringify!($key),)*
+                _ => "?",
+            }
+        }
+    };
+}
+
+key_names! {
+    KeyA KeyB KeyC KeyD KeyE KeyF KeyG KeyH KeyI KeyJ KeyK KeyL KeyM KeyN KeyO KeyP KeyQ KeyR KeyS KeyT KeyU KeyV KeyW KeyX KeyY KeyZ
+    Digit0 Digit1 Digit2 Digit3 Digit4 Digit5 Digit6 Digit7 Digit8 Digit9
+    F1 F2 F3 F4 F5 F6 F7 F8 F9 F10 F11 F12
+    Space Enter Escape Tab Backspace Delete Insert Home End PageUp PageDown
+    ArrowUp ArrowDown ArrowLeft ArrowRight
+    BracketLeft BracketRight Minus Equal Comma Period Slash Backslash Semicolon Quote Backquote
+    Numpad0 Numpad1 Numpad2 Numpad3 Numpad4 Numpad5 Numpad6 Numpad7 Numpad8 Numpad9
+    NumpadAdd NumpadSubtract NumpadMultiply NumpadDivide NumpadDecimal NumpadEnter
+}
+
+// As on a keycap where that's shorter: "Ctrl+S", "1", "F5", "ArrowUp"
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = key_name(self.key);
+        let name = name.strip_prefix("Key").or_else(|| name.strip_prefix("Digit")).unwrap_or(name);
+        write!(f, "{}{}", if self.ctrl { "Ctrl+" } else { "" }, name)
+    }
+}
+
+// Which keys do what, in `DEFAULTS` order
+#[derive(Debug)]
+pub struct Keybinds {
+    bindings: Vec<(Action, Vec<Binding>)>,
+}
+
+impl Default for Keybinds {
+    fn default() -> Self {
+        let bindings = DEFAULTS.iter().map(|&(action, _, keys)| {
+            (action, keys.iter().map(|key| parse_binding(key).expect("default bindings are valid")).collect())
+        }).collect();
+        Keybinds { bindings }
+    }
+}
+
+impl Keybinds {
+    // The defaults with whatever `path` rebinds; a missing file is no error, a bad one is ignored as a whole
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &Path) -> Keybinds {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Keybinds::default(),
+            Err(e) => {
+                eprintln!("warning: could not read {}, using the default keys: {}", path.display(), e);
+                return Keybinds::default();
+            }
+        };
+        match Keybinds::parse(&text) {
+            Ok(keybinds) => {
+                println!("Keybinds: loaded {}", path.display());
+                keybinds
+            }
+            Err(errors) => {
+                for error in errors {
+                    eprintln!("{}: {}", path.display(), error);
+                }
+                eprintln!("warning: ignoring {}, using the default keys", path.display());
+                Keybinds::default()
+            }
+        }
+    }
+
+    // One `action = "Key"` or `action = ["Key", "Ctrl+Key"]` per line, # starts a comment. Listed actions
+    // lose their default keys, so `action = []` unbinds one. Every bad line is reported
+    #[cfg(not(target_arch = "wasm32"))]
+    fn parse(text: &str) -> Result<Keybinds, Vec<String>> {
+        let mut keybinds = Keybinds::default();
+        let mut errors = Vec::new();
+        for setting in config::parse(text) {
+            if let Err(e) = setting.and_then(|setting| keybinds.rebind(&setting.key, &setting.value).map_err(|e| setting.error(e))) {
+                errors.push(e);
+            }
+        }
+        if errors.is_empty() { Ok(keybinds) } else { Err(errors) }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn rebind(&mut self, name: &str, value: &Value) -> Result<(), String> {
+        let index = DEFAULTS.iter().position(|&(_, action_name, _)| action_name == name)
+            .ok_or_else(|| format!("unknown action '{}'", name))?;
+        self.bindings[index].1 = value.items().iter().map(|item| {
+            let key = item.quoted().map_err(|_| format!("expected a quoted key name, got {}", item))?;
+            parse_binding(key)
+        }).collect::<Result<_, _>>()?;
+        Ok(())
+    }
+
+    // Every action with a key, as (action, name in keybinds.toml, its keys), for the help
+    pub fn listing(&self) -> Vec<(Action, &'static str, String)> {
+        self.bindings.iter().zip(DEFAULTS).filter(|((_, bindings), _)| !bindings.is_empty()).map(|((action, bindings), &(_, name, _))| {
+            (*action, name, bindings.iter().map(Binding::to_string).collect::<Vec<_>>().join(", "))
+        }).collect()
+    }
+
+    // Actions bound to `key`, those that want Ctrl first when it's held
+    pub fn actions(&self, key: KeyCode, ctrl: bool) -> Vec<Action> {
+        let bound = |want_ctrl: bool| self.bindings.iter()
+            .filter(move |(_, bindings)| bindings.contains(&Binding { key, ctrl: want_ctrl }))
+            .map(|&(action, _)| action);
+        if ctrl { bound(true).chain(bound(false)).collect() } else { bound(false).collect() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_default_parses_and_every_action_has_a_name() {
+        let keybinds = Keybinds::default();
+        assert_eq!(keybinds.bindings.len(), DEFAULTS.len());
+        for (index, &(action, name, _)) in DEFAULTS.iter().enumerate() {
+            assert_eq!(DEFAULTS.iter().position(|&(other, ..)| other == action), Some(index), "{} is listed twice", name);
+            assert_eq!(DEFAULTS.iter().position(|&(_, other, _)| other == name), Some(index), "{} is named twice", name);
+        }
+        assert_eq!(keybinds.actions(KeyCode::KeyP, false), [Action::Pause]);
+        assert_eq!(keybinds.actions(KeyCode::ArrowRight, false), [Action::Step]);
+    }
+
+    #[test]
+    fn bindings_parse_with_and_without_ctrl() {
+        assert_eq!(parse_binding("Ctrl+KeyS"), Ok(Binding { key: KeyCode::KeyS, ctrl: true }));
+        assert_eq!(parse_binding("F5"), Ok(Binding { key: KeyCode::F5, ctrl: false }));
+        assert!(parse_binding("S").unwrap_err().contains("unknown key 'S'"));
+        assert!(parse_binding("Shift+KeyS").is_err());
+        assert!(parse_binding("Ctrl+").is_err());
+    }
+
+    #[test]
+    fn files_replace_the_keys_of_the_actions_they_list() {
+        let keybinds = Keybinds::parse("# Pause on Q instead\npause = \"KeyQ\"\nstep = [\"KeyN\", \"Ctrl+Period\"] # two keys\n\nclear = []\n").unwrap();
+        assert_eq!(keybinds.actions(KeyCode::KeyQ, false), [Action::Pause]);
+        assert!(keybinds.actions(KeyCode::KeyP, false).is_empty());
+        assert_eq!(keybinds.actions(KeyCode::Period, true), [Action::Step]);
+        assert!(keybinds.actions(KeyCode::ArrowRight, false).is_empty());
+        assert!(keybinds.actions(KeyCode::KeyC, false).is_empty());
+        // Unlisted actions keep their defaults
+        assert_eq!(keybinds.actions(KeyCode::KeyT, false), [Action::CycleTheme]);
+    }
+
+    #[test]
+    fn every_bad_line_is_reported() {
+        let errors = Keybinds::parse("pause = \"KeyQ\"\nexplode = \"KeyX\"\nstep = KeyN\nclear \"Delete\"\nreset = [\"KeyR\", \"Hyper+KeyR\"]\n").unwrap_err();
+        assert_eq!(errors.len(), 4);
+        assert!(errors[0].starts_with("line 2") && errors[0].contains("unknown action 'explode'"));
+        assert!(errors[1].starts_with("line 3") && errors[1].contains("quoted key name"));
+        assert!(errors[2].starts_with("line 4") && errors[2].contains("expected key = value"));
+        assert!(errors[3].starts_with("line 5") && errors[3].contains("unknown key"));
+    }
+
+    #[test]
+    fn shared_keys_do_what_comes_first_and_ctrl_bindings_win() {
+        let keybinds = Keybinds::default();
+        // Escape closes the help before it cancels a stamp, which comes before clearing the selection
+        assert_eq!(keybinds.actions(KeyCode::Escape, false), [Action::CloseHelp, Action::CancelStamp, Action::ClearSelection]);
+        // R turns a stamp before it resets; with Ctrl it rotates the grid, falling back on the plain bindings
+        assert_eq!(keybinds.actions(KeyCode::KeyR, false), [Action::RotateStamp, Action::Reset]);
+        assert_eq!(keybinds.actions(KeyCode::KeyR, true), [Action::RotateGrid, Action::RotateStamp, Action::Reset]);
+        // C clears, but Ctrl+C copies first
+        assert_eq!(keybinds.actions(KeyCode::KeyC, false), [Action::Clear]);
+        assert_eq!(keybinds.actions(KeyCode::KeyC, true), [Action::Copy, Action::Clear]);
+        assert_eq!(keybinds.actions(KeyCode::KeyO, false), [Action::CycleCpuBackend]);
+        // A Ctrl binding never fires without Ctrl
+        assert!(keybinds.actions(KeyCode::KeyS, false).iter().all(|&action| action != Action::SaveState));
+
+        // A key rebound onto one that's taken shares it, in the same order whichever line came first
+        let keybinds = Keybinds::parse("reset = \"KeyP\"").unwrap();
+        assert_eq!(keybinds.actions(KeyCode::KeyP, false), [Action::Pause, Action::Reset]);
+        assert_eq!(keybinds.actions(KeyCode::KeyR, false), [Action::RotateStamp]);
+
+        // F1 keeps the panel; with Ctrl it opens the help instead
+        assert_eq!(keybinds.actions(KeyCode::F1, false), [Action::TogglePanel]);
+        assert_eq!(keybinds.actions(KeyCode::F1, true), [Action::ToggleHelp, Action::TogglePanel]);
+    }
+
+    #[test]
+    fn the_listing_follows_the_bindings() {
+        let keybinds = Keybinds::parse("pause = [\"KeyQ\", \"Ctrl+Digit1\"]\nclear = []\n").unwrap();
+        let listing = keybinds.listing();
+        let keys = |action: Action| listing.iter().find(|&&(listed, ..)| listed == action).map(|(_, name, keys)| (*name, keys.as_str()));
+        assert_eq!(keys(Action::Pause), Some(("pause", "Q, Ctrl+1")));
+        assert_eq!(keys(Action::ToggleHelp), Some(("toggle_help", "Slash, Ctrl+F1")));
+        // Unbound actions are left out, the rest keep their order
+        assert_eq!(keys(Action::Clear), None);
+        assert_eq!(listing.len(), DEFAULTS.len() - 1);
+        assert_eq!(listing[0].0, Action::ToggleBackend);
+    }
+
+    #[test]
+    fn bindings_show_as_on_the_keycap() {
+        let shown = |text: &str| parse_binding(text).unwrap().to_string();
+        assert_eq!(shown("Ctrl+KeyS"), "Ctrl+S");
+        assert_eq!(shown("Digit1"), "1");
+        assert_eq!(shown("F5"), "F5");
+        assert_eq!(shown("ArrowUp"), "ArrowUp");
+        // Every default key has a name to show
+        for &(_, name, keys) in DEFAULTS {
+            for key in keys {
+                assert!(!shown(key).ends_with('?'), "{}'s {} has no name", name, key);
+            }
+        }
+    }
+}
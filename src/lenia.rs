@@ -0,0 +1,186 @@
+use std::fmt;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use crate::boundary::Boundary;
+use crate::WALL;
+
+/// Widest kernel: the step sums every cell within the radius directly, and the GPU's chunk skipping
+/// only looks one 64-cell chunk away
+pub const MAX_RADIUS: u32 = 32;
+
+/// A cell at full intensity, what painting puts down (the bits of 1.0)
+pub const FULL: u32 = 0x3f80_0000;
+
+// Intensity above which a cell counts as part of a creature, for patterns and the diff view
+const LIVE: f32 = 0.1;
+
+/// Lenia: cells hold an intensity from 0 to 1 and grow or shrink by how the ring-weighted average of
+/// everything within `radius` cells compares with `mu`, within about `sigma`, a `dt` fraction at a time
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Lenia {
+    pub radius: u32,
+    pub mu: f32,
+    pub sigma: f32,
+    pub dt: f32,
+}
+
+impl Lenia {
+    /// Bert Chan's Orbium, the glider most blobs settle into
+    pub const ORBIUM: Lenia = Lenia { radius: 13, mu: 0.15, sigma: 0.015, dt: 0.1 };
+
+    pub fn parse_radius(text: &str) -> Result<u32, String> {
+        match text.parse() {
+            Ok(radius) if (2..=MAX_RADIUS).contains(&radius) => Ok(radius),
+            _ => Err(format!("invalid kernel radius '{}' (expected 2 to {})", text, MAX_RADIUS)),
+        }
+    }
+
+    /// mu, sigma and dt alike: a fraction above 0 and at most 1
+    pub fn parse_fraction(text: &str) -> Result<f32, String> {
+        match text.parse() {
+            Ok(value) if value > 0.0 && value <= 1.0 => Ok(value),
+            _ => Err(format!("invalid Lenia parameter '{}' (expected above 0, up to 1)", text)),
+        }
+    }
+
+    /// The growth from an average `potential`: +1 right at `mu`, falling to -1 a few `sigma` away
+    pub fn growth(&self, potential: f32) -> f32 {
+        let d = potential - self.mu;
+        2.0 * (-d * d / (2.0 * self.sigma * self.sigma)).exp() - 1.0
+    }
+
+    /// The ring's weight at `distance` cells from the centre: a bump peaking halfway out, 0 at the centre
+    /// and from the radius on (matches rules.wgsl)
+    pub fn kernel_weight(&self, distance: f32) -> f32 {
+        let r = distance / self.radius as f32;
+        if r <= 0.0 || r >= 1.0 { return 0.0; }
+        (4.0 - 1.0 / (r * (1.0 - r))).exp()
+    }
+
+    // The cells the ring reaches, as offsets with weights that sum to 1
+    fn kernel(&self) -> Vec<(i64, i64, f32)> {
+        let radius = self.radius as i64;
+        let mut kernel: Vec<(i64, i64, f32)> = (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+            .map(|(dx, dy)| (dx, dy, self.kernel_weight(((dx * dx + dy * dy) as f32).sqrt())))
+            .filter(|&(_, _, weight)| weight > 0.0)
+            .collect();
+        let total: f32 = kernel.iter().map(|&(_, _, weight)| weight).sum();
+        kernel.iter_mut().for_each(|(_, _, weight)| *weight /= total);
+        kernel
+    }
+}
+
+impl fmt::Display for Lenia {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "R{},mu{},sigma{}", self.radius, self.mu, self.sigma)
+    }
+}
+
+/// A cell holding `intensity`, clamped to 0 to 1: the f32's bits, so 0 is empty and no cell can read
+/// as a wall (matches rules.wgsl)
+pub fn encode(intensity: f32) -> u32 {
+    intensity.clamp(0.0, 1.0).to_bits()
+}
+
+/// The intensity of a cell packed by `encode`; walls hold none
+pub fn decode(cell: u32) -> f32 {
+    if cell == WALL { 0.0 } else { f32::from_bits(cell) }
+}
+
+/// Whether the cell is bright enough to count as part of a creature
+pub fn is_live(cell: u32) -> bool {
+    decode(cell) > LIVE
+}
+
+/// An empty grid with a few noisy Gaussian blobs about a kernel across, roughly one per 128x128 cells
+pub fn seed_grid(width: u32, height: u32, seed: u64, radius: u32) -> Vec<u32> {
+    let (width, height) = (width as usize, height as usize);
+    let mut grid = vec![0; width * height];
+    let mut rng = StdRng::seed_from_u64(seed);
+    let spread = radius as f32;
+    let reach = (3 * radius) as i64;
+    for _ in 0..(width * height / (128 * 128)).clamp(3, 256) {
+        let (cx, cy) = (rng.gen_range(0..width) as i64, rng.gen_range(0..height) as i64);
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let (x, y) = ((cx + dx).rem_euclid(width as i64) as usize, (cy + dy).rem_euclid(height as i64) as usize);
+                let envelope = (-((dx * dx + dy * dy) as f32) / (2.0 * spread * spread)).exp();
+                let cell = &mut grid[y * width + x];
+                *cell = encode(decode(*cell).max(envelope * rng.gen_range(0.5..1.0)));
+            }
+        }
+    }
+    grid
+}
+
+/// One time step of a `width`-wide grid.
+pub fn step_grid(input: &[u32], width: usize, params: Lenia, boundary: Boundary) -> Vec<u32> {
+    let mut output = vec![0; input.len()];
+    step_grid_into(input, &mut output, width, params, boundary);
+    output
+}
+
+/// `step_grid` writing into `output` (the same length as `input`) instead of allocating it.
+///
+/// A direct convolution, so slow for wide kernels on big grids; walls and cells off a dead edge hold
+/// nothing but still take their share of the ring.
+pub fn step_grid_into(input: &[u32], output: &mut [u32], width: usize, params: Lenia, boundary: Boundary) {
+    let height = input.len() / width;
+    let kernel = params.kernel();
+    output.par_iter_mut().enumerate().for_each(|(index, out)| {
+        let cell = input[index];
+        if cell == WALL {
+            *out = WALL;
+            return;
+        }
+        let (x, y) = ((index % width) as i64, (index / width) as i64);
+        let potential: f32 = kernel.iter().map(|&(dx, dy, weight)| {
+            match (boundary.resolve(x + dx, width), boundary.resolve(y + dy, height)) {
+                (Some(nx), Some(ny)) => weight * decode(input[ny * width + nx]),
+                _ => 0.0,
+            }
+        }).sum();
+        *out = encode(decode(cell) + params.dt * params.growth(potential));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kernel_is_a_normalized_ring() {
+        let params = Lenia { radius: 5, ..Lenia::ORBIUM };
+        let kernel = params.kernel();
+        assert!((kernel.iter().map(|&(_, _, weight)| weight).sum::<f32>() - 1.0).abs() < 1e-5);
+        assert!(kernel.iter().all(|&(dx, dy, _)| (dx, dy) != (0, 0) && dx * dx + dy * dy < 25));
+        assert_eq!(params.kernel_weight(2.5), 1.0);
+        assert!(params.kernel_weight(1.0) < params.kernel_weight(2.0));
+    }
+
+    #[test]
+    fn encoding_round_trips_and_avoids_walls() {
+        assert_eq!(encode(0.0), 0);
+        assert_eq!(encode(1.0), FULL);
+        assert_eq!(encode(2.0), FULL);
+        assert_eq!(decode(encode(0.37)), 0.37);
+        assert!(is_live(FULL) && !is_live(0) && !is_live(WALL));
+    }
+
+    #[test]
+    fn empty_space_stays_empty_and_blobs_live() {
+        let cells = vec![0; 24 * 16];
+        assert_eq!(step_grid(&cells, 24, Lenia::ORBIUM, Boundary::Wrap), cells);
+
+        let params = Lenia { radius: 6, ..Lenia::ORBIUM };
+        let mut cells = seed_grid(64, 64, 3, params.radius);
+        assert!(cells.iter().any(|&cell| is_live(cell)));
+        cells[0] = WALL;
+        for _ in 0..20 {
+            cells = step_grid(&cells, 64, params, Boundary::Wrap);
+        }
+        assert_eq!(cells[0], WALL);
+        assert!(cells.iter().all(|&cell| cell == WALL || (0.0..=1.0).contains(&decode(cell))));
+    }
+}
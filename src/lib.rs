@@ -0,0 +1,214 @@
+//! Conway's Game of Life (and other Life-like rules) on the CPU with Rayon or the GPU with WGPU compute shaders.
+
+pub mod ant;
+pub mod automaton;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod autosave;
+pub mod bands;
+pub mod bitmap;
+pub mod bitpacked;
+pub mod boundary;
+pub mod bounds;
+pub mod changes;
+pub mod chunks;
+pub mod config;
+pub mod cpu;
+pub mod cyclic;
+pub mod downsample;
+pub mod fade;
+pub mod forest_fire;
+pub mod frame_stats;
+pub mod gpu;
+pub mod gray_scott;
+pub mod history;
+pub mod hooks;
+pub mod lenia;
+pub mod ltl;
+pub mod mutation;
+pub mod palette;
+pub mod walls;
+pub mod pattern;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod pipeline_cache;
+pub mod population;
+pub mod rain;
+mod readback;
+pub mod regions;
+pub mod replay;
+pub mod rps;
+pub mod rule;
+pub mod slots;
+pub mod snapshot;
+pub mod soup;
+pub mod sparkline;
+pub mod sparse;
+pub mod texture;
+pub mod timer;
+pub mod topology;
+pub mod transform;
+pub mod verify;
+pub mod workgroup;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use ant::AntRule;
+use automaton::Automaton;
+use boundary::Boundary;
+use cyclic::CyclicRule;
+use forest_fire::ForestFire;
+use gray_scott::GrayScott;
+use lenia::Lenia;
+use ltl::LtlRule;
+use regions::RuleMap;
+use rule::Rule;
+use topology::Topology;
+
+pub use bitpacked::BitpackedSimulation;
+pub use cpu::CpuSimulation;
+pub use gpu::GpuSimulation;
+pub use sparse::SparseSimulation;
+pub use texture::TextureSimulation;
+
+/// Live cells store how many generations they have survived, saturating here (matches rules.wgsl).
+pub const MAX_AGE: u32 = 65535;
+
+/// Obstacle cells: they never change and count as dead neighbours (matches rules.wgsl). Far above any
+/// age or state, so every automaton can have them.
+pub const WALL: u32 = u32::MAX;
+
+/// A `width` x `height` grid of cells that can be advanced one generation at a time.
+///
+/// Cells are stored row-major: in Life `0` for dead and the cell's age for live cells, other
+/// automata use small state numbers (see `Automaton`).
+pub trait Simulation {
+    /// Advances one generation.
+    fn step(&mut self);
+    /// The latest generation.
+    fn cells(&self) -> &[u32];
+    /// Overwrites a contiguous run of cells starting at row-major index `offset`.
+    fn set_cells(&mut self, offset: usize, cells: &[u32]);
+    /// Width of the grid in cells.
+    fn width(&self) -> u32;
+    /// Height of the grid in cells.
+    fn height(&self) -> u32;
+    /// Takes effect from the next generation.
+    fn set_rule(&mut self, rule: Rule);
+    /// Takes effect from the next generation.
+    fn set_boundary(&mut self, boundary: Boundary);
+    /// Takes effect from the next generation.
+    fn set_topology(&mut self, topology: Topology);
+    /// Takes effect from the next generation; existing cells are not converted.
+    fn set_automaton(&mut self, automaton: Automaton);
+    /// Radius and ranges used while the automaton is Larger than Life; takes effect from the next generation.
+    fn set_ltl_rule(&mut self, ltl: LtlRule);
+    /// Feed, kill, diffusion and time step used while the automaton is Gray-Scott; takes effect from the next generation.
+    fn set_gray_scott(&mut self, params: GrayScott);
+    /// How ants turn while the automaton is Langton's Ant; takes effect from the next generation.
+    fn set_ant_rule(&mut self, rule: AntRule);
+    /// State count and threshold used while the automaton is cyclic; takes effect from the next generation.
+    fn set_cyclic_rule(&mut self, rule: CyclicRule);
+    /// Predator neighbours that take a cell over while the automaton is rock-paper-scissors; takes effect from the next generation.
+    fn set_rps_threshold(&mut self, threshold: u32);
+    /// Kernel radius, growth centre and width and time step used while the automaton is Lenia; takes effect from the next generation.
+    fn set_lenia(&mut self, params: Lenia);
+    /// Growth and lightning probabilities and seed used while the automaton is forest fire; takes effect from the next generation.
+    fn set_forest_fire(&mut self, params: ForestFire);
+    /// The number of the latest generation, which the forest fire's dice are hashed with; counted up by
+    /// each `step` and set again wherever the caller's count jumps. Backends without dice ignore it.
+    fn set_generation(&mut self, _generation: u64) {}
+    /// Rules of their own for some tiles while the automaton is Life, `None` for the grid's rule everywhere;
+    /// takes effect from the next generation. Backends with one rule for the whole grid ignore it.
+    fn set_rule_map(&mut self, _map: Option<&RuleMap>) {}
+    /// Copies out a `width` x `height` rectangle at (`x`, `y`), row-major, wrapping around the edges.
+    fn read_rect(&self, x: usize, y: usize, width: usize, height: usize) -> Vec<u32> {
+        let cells = self.cells();
+        let mut rect = vec![0; width * height];
+        for (index, offset, len) in rect_spans(self.width() as usize, self.height() as usize, x, y, width, height) {
+            rect[offset..offset + len].copy_from_slice(&cells[index..index + len]);
+        }
+        rect
+    }
+    /// Overwrites a rectangle `width` cells wide at (`x`, `y`) with row-major `cells`, wrapping around the edges.
+    fn set_rect(&mut self, x: usize, y: usize, width: usize, cells: &[u32]) {
+        let height = cells.len() / width;
+        for (index, offset, len) in rect_spans(self.width() as usize, self.height() as usize, x, y, width, height) {
+            self.set_cells(index, &cells[offset..offset + len]);
+        }
+    }
+    /// Number of live cells in the latest generation; walls don't count.
+    fn population(&self) -> u32 {
+        self.cells().par_iter().filter(|&&cell| cell > 0 && cell != WALL).count() as u32
+    }
+    /// One flag per row, set for the rows `step` changed since the last call, so only those need
+    /// copying elsewhere; `None` when they aren't tracked and every row should be treated as changed.
+    fn take_changed_rows(&mut self) -> Option<Vec<bool>> {
+        None
+    }
+    /// How the last `step` went, for backends that pick between ways of stepping.
+    fn stepping(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// Runs of consecutive flagged rows of a `width`-wide grid, as (first cell, cell count).
+pub fn row_runs(rows: &[bool], width: usize) -> Vec<(usize, usize)> {
+    let mut runs = Vec::new();
+    let mut y = 0;
+    while y < rows.len() {
+        if !rows[y] { y += 1; continue; }
+        let run = rows[y..].iter().take_while(|&&changed| changed).count();
+        runs.push((y * width, run * width));
+        y += run;
+    }
+    runs
+}
+
+/// The contiguous row pieces of a `width` x `height` rectangle at (`x`, `y`) on a `grid_width` x
+/// `grid_height` torus, as (grid index, index into the rectangle, length). Rows crossing the right edge
+/// continue on the left; `width` must not exceed `grid_width`.
+pub fn rect_spans(grid_width: usize, grid_height: usize, x: usize, y: usize, width: usize, height: usize) -> impl Iterator<Item = (usize, usize, usize)> {
+    let split = width.min(grid_width - x);
+    (0..height).flat_map(move |row| {
+        let start = (y + row) % grid_height * grid_width;
+        [(start + x, row * width, split), (start, row * width + split, width - split)]
+    }).filter(|&(_, _, len)| len > 0)
+}
+
+/// The cell of a `grid_width` x `grid_height` torus at fractional coordinates that may lie past its edges,
+/// as the view gives them once it's panned across the seam. Cursor edits and stamps both go through this.
+pub fn wrap_cell(x: f64, y: f64, grid_width: usize, grid_height: usize) -> (usize, usize) {
+    let wrap = |value: f64, size: usize| value.floor().rem_euclid(size as f64) as usize;
+    (wrap(x, grid_width), wrap(y, grid_height))
+}
+
+/// Number of cells in `state`.
+pub fn count_state(cells: &[u32], state: u32) -> u32 {
+    cells.par_iter().filter(|&&cell| cell == state).count() as u32
+}
+
+/// Recolours the live cells as Immigration's two species (1 and 2), about half each.
+pub fn split_species(cells: &mut [u32], seed: u64) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    for cell in cells.iter_mut().filter(|cell| **cell > 0) {
+        *cell = rng.gen_range(1..=2);
+    }
+}
+
+/// Reproducible soup: the same seed and density always give the same grid, on either backend (see `soup`).
+pub fn random_grid(cells: usize, seed: u64, density: f32) -> Vec<u32> {
+    soup::grid(cells, seed, density)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_wrap_across_the_seam() {
+        assert_eq!(wrap_cell(3.7, 2.0, 8, 4), (3, 2));
+        assert_eq!(wrap_cell(-0.5, -1.0, 8, 4), (7, 3));
+        assert_eq!(wrap_cell(8.0, 4.5, 8, 4), (0, 0));
+        assert_eq!(wrap_cell(-17.25, 9.0, 8, 4), (6, 1));
+        assert_eq!(wrap_cell(-1e-18, 0.0, 8, 4), (7, 0));
+    }
+}
@@ -0,0 +1,266 @@
+// Buffer-backed generation step, composed after rules.wgsl (see gpu.rs)
+// WORKGROUP_X and WORKGROUP_Y are placeholders, replaced with the chosen size before compiling (see workgroup.rs)
+
+// Bind Group 0: Storage Buffers (Memory)
+// binding(0) is the Previous Frame (Read Only)
+// binding(1) is the Current Frame (Write Only)
+// binding(2) is the grid dimensions and active rule (Uniform)
+// binding(3) is two generations back, only written by main_history
+// binding(4) to binding(7) are only used by the *_chunks entry points (see chunks.rs): the chunks this
+// generation changes, the chunks whose history is behind, the chunks to compute and the chunk layout
+// binding(8) and binding(9) are the input generation's number and the output's, for the forest fire's dice
+// binding(10) is the rule map (see regions.rs): the tiles across, 0 when there is none, a table of birth
+// and survival masks, then one table index per tile
+// binding(11) and binding(12) are Gray-Scott's concentrations at full precision, u and v per cell, of the
+// input generation and the output's; a single element each while the automaton is another (see gpu.rs)
+@group(0) @binding(0) var<storage, read> cellStateIn: array<u32>;
+@group(0) @binding(1) var<storage, read_write> cellStateOut: array<u32>;
+@group(0) @binding(2) var<uniform> params: Params;
+@group(0) @binding(3) var<storage, read_write> history: array<u32>;
+@group(0) @binding(4) var<storage, read_write> chunkChanged: array<atomic<u32>>;
+@group(0) @binding(5) var<storage, read_write> chunkStale: array<atomic<u32>>;
+@group(0) @binding(6) var<storage, read> chunkList: array<u32>;
+@group(0) @binding(7) var<uniform> chunks: Chunks;
+@group(0) @binding(8) var<storage, read> clockIn: u32;
+@group(0) @binding(9) var<storage, read_write> clockOut: u32;
+@group(0) @binding(10) var<storage, read> ruleMap: array<u32>;
+@group(0) @binding(11) var<storage, read> concentrationsIn: array<vec2<f32>>;
+@group(0) @binding(12) var<storage, read_write> concentrationsOut: array<vec2<f32>>;
+
+// Must match `ChunkParams` in chunks.rs
+struct Chunks {
+    columns: u32,
+    rows: u32,
+    groups: vec2<u32>,
+};
+
+// Matches CHUNK_SIZE in chunks.rs
+const CHUNK_SIZE: u32 = 64u;
+
+// Match TILE and MAX_RULES in regions.rs
+const RULE_TILE: u32 = 32u;
+const RULE_TABLE: u32 = 16u;
+
+fn get_index(x: u32, y: u32) -> u32 {
+    return (y % params.height) * params.width + (x % params.width);
+}
+
+// Whether Gray-Scott's concentrations have buffers of their own
+fn precise_concentrations() -> bool {
+    return arrayLength(&concentrationsIn) == params.width * params.height;
+}
+
+// A Gray-Scott cell's concentrations: the full precision ones while the cell still holds them quantised,
+// the cell's own once something else has written it (painting, soups, transforms) (matches gray_scott.rs)
+fn gray_scott_concentrations(index: u32, status: u32) -> vec2<f32> {
+    if (precise_concentrations()) {
+        let kept = concentrationsIn[index];
+        if (gray_scott_encode(kept) == status) { return kept; }
+    }
+    return gray_scott_decode(status);
+}
+
+// Gray-Scott: the weighted Laplacian of both concentrations over the 3x3 neighbourhood, kept at full
+// precision in concentrationsOut and quantised into the cell
+fn gray_scott_cell(x: u32, y: u32) -> u32 {
+    let index = get_index(x, y);
+    let status = cellStateIn[index];
+    if (status == WALL) { return WALL; }
+    let centre = gray_scott_concentrations(index, status);
+    var laplacian = vec2<f32>(0.0);
+    for (var i = -1; i <= 1; i++) {
+        for (var j = -1; j <= 1; j++) {
+            if (i == 0 && j == 0) { continue; }
+            let nx = resolve(i32(x) + i, params.width);
+            let ny = resolve(i32(y) + j, params.height);
+            let on_grid = nx >= 0 && ny >= 0;
+            let neighbour_index = get_index(u32(max(nx, 0)), u32(max(ny, 0)));
+            let state = select(0u, cellStateIn[neighbour_index], on_grid);
+            // Walls and cells off a dead edge read as the uniform field
+            let neighbour = select(vec2<f32>(1.0, 0.0), gray_scott_concentrations(neighbour_index, state), on_grid && state != WALL);
+            laplacian += laplacian_weight(i, j) * (neighbour - centre);
+        }
+    }
+    let next = gray_scott_react(centre, laplacian);
+    if (precise_concentrations()) { concentrationsOut[index] = next; }
+    return gray_scott_encode(next);
+}
+
+// Cyclic: how many of the 3x3 neighbours already hold the cell's successor
+fn cyclic_cell(x: u32, y: u32) -> u32 {
+    let status = cellStateIn[get_index(x, y)];
+    let successor = cyclic_successor(status);
+    var successors = 0u;
+    for (var i = -1; i <= 1; i++) {
+        for (var j = -1; j <= 1; j++) {
+            if (i == 0 && j == 0) { continue; }
+            let nx = resolve(i32(x) + i, params.width);
+            let ny = resolve(i32(y) + j, params.height);
+            if (nx < 0 || ny < 0) { continue; }
+            successors += select(0u, 1u, cellStateIn[get_index(u32(nx), u32(ny))] == successor);
+        }
+    }
+    return cyclic_step(status, successors);
+}
+
+// Rock-paper-scissors: how many of the 3x3 neighbours hold each species
+fn rps_cell(x: u32, y: u32) -> u32 {
+    var counts = vec3<u32>(0u);
+    for (var i = -1; i <= 1; i++) {
+        for (var j = -1; j <= 1; j++) {
+            if (i == 0 && j == 0) { continue; }
+            let nx = resolve(i32(x) + i, params.width);
+            let ny = resolve(i32(y) + j, params.height);
+            if (nx < 0 || ny < 0) { continue; }
+            let state = cellStateIn[get_index(u32(nx), u32(ny))];
+            if (state >= 1u && state <= 3u) { counts[state - 1u] += 1u; }
+        }
+    }
+    return rps_step(cellStateIn[get_index(x, y)], counts);
+}
+
+// Lenia: the ring-weighted average of every cell within the radius, summed directly. Walls and cells off
+// a dead edge hold nothing but keep their share of the weight
+fn lenia_cell(x: u32, y: u32) -> u32 {
+    let r = i32(params.lenia_radius);
+    var potential = 0.0;
+    var total = 0.0;
+    for (var j = -r; j <= r; j++) {
+        for (var i = -r; i <= r; i++) {
+            let weight = lenia_kernel(length(vec2<f32>(f32(i), f32(j))));
+            if (weight == 0.0) { continue; }
+            total += weight;
+            let nx = resolve(i32(x) + i, params.width);
+            let ny = resolve(i32(y) + j, params.height);
+            if (nx < 0 || ny < 0) { continue; }
+            potential += weight * lenia_decode(cellStateIn[get_index(u32(nx), u32(ny))]);
+        }
+    }
+    return lenia_step(cellStateIn[get_index(x, y)], potential / total);
+}
+
+// Forest fire: whether any of the 3x3 neighbours is burning, and the cell's roll for this generation
+fn forest_fire_cell(x: u32, y: u32) -> u32 {
+    let index = get_index(x, y);
+    var fire_nearby = false;
+    for (var i = -1; i <= 1; i++) {
+        for (var j = -1; j <= 1; j++) {
+            if (i == 0 && j == 0) { continue; }
+            let nx = resolve(i32(x) + i, params.width);
+            let ny = resolve(i32(y) + j, params.height);
+            if (nx < 0 || ny < 0) { continue; }
+            fire_nearby = fire_nearby || cellStateIn[get_index(u32(nx), u32(ny))] == 2u;
+        }
+    }
+    return forest_fire_step(cellStateIn[index], fire_nearby, forest_roll(index, clockIn));
+}
+
+// `next_state`, except that Life cells in a painted tile of the rule map take that tile's rule; index 0
+// follows the grid's
+fn region_state(x: u32, y: u32, status: u32, neighbors: u32) -> u32 {
+    let columns = ruleMap[0];
+    if (params.automaton != AUTOMATON_LIFE || columns == 0u || status == WALL) { return next_state(status, neighbors); }
+    let index = ruleMap[1u + RULE_TABLE + (y / RULE_TILE) * columns + x / RULE_TILE];
+    if (index == 0u) { return next_state(status, neighbors); }
+    let masks = ruleMap[1u + index];
+    return life_state(status, neighbors, masks & 0xffffu, masks >> 16u);
+}
+
+fn next_cell(x: u32, y: u32) -> u32 {
+    if (params.automaton == AUTOMATON_GRAY_SCOTT) { return gray_scott_cell(x, y); }
+    if (params.automaton == AUTOMATON_CYCLIC) { return cyclic_cell(x, y); }
+    if (params.automaton == AUTOMATON_ROCK_PAPER_SCISSORS) { return rps_cell(x, y); }
+    if (params.automaton == AUTOMATON_LENIA) { return lenia_cell(x, y); }
+    if (params.automaton == AUTOMATON_FOREST_FIRE) { return forest_fire_cell(x, y); }
+
+    // Count Neighbors (edges handled according to the boundary mode)
+    var neighbors = 0u;
+    if (hex_neighbourhood()) {
+        for (var n = 0u; n < 6u; n++) {
+            let offset = hex_offset(y, n);
+            let nx = resolve(i32(x) + offset.x, params.width);
+            let ny = resolve(i32(y) + offset.y, params.height);
+            if (nx < 0 || ny < 0) { continue; }
+            neighbors += neighbor_weight(cellStateIn[get_index(u32(nx), u32(ny))]);
+        }
+        return region_state(x, y, cellStateIn[get_index(x, y)], neighbors);
+    }
+    let r = neighbourhood_radius();
+    for (var i = -r; i <= r; i++) {
+        for (var j = -r; j <= r; j++) {
+            if (i == 0 && j == 0) { continue; }
+            let nx = resolve(i32(x) + i, params.width);
+            let ny = resolve(i32(y) + j, params.height);
+            if (nx < 0 || ny < 0) { continue; }
+            neighbors += neighbor_weight(cellStateIn[get_index(u32(nx), u32(ny))]);
+        }
+    }
+    return region_state(x, y, cellStateIn[get_index(x, y)], neighbors);
+}
+
+// Compute shader (The Physics)
+@compute @workgroup_size(WORKGROUP_X, WORKGROUP_Y)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let x = global_id.x;
+    let y = global_id.y;
+    if (x >= params.width || y >= params.height) { return; }
+
+    if (x == 0u && y == 0u) { clockOut = clockIn + 1u; }
+    cellStateOut[get_index(x, y)] = next_cell(x, y);
+}
+
+// Same step for the last generation of a frame: the output buffer still holds the generation before
+// the input one, which is saved for change detection (see changes.wgsl)
+@compute @workgroup_size(WORKGROUP_X, WORKGROUP_Y)
+fn main_history(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let x = global_id.x;
+    let y = global_id.y;
+    if (x >= params.width || y >= params.height) { return; }
+
+    if (x == 0u && y == 0u) { clockOut = clockIn + 1u; }
+    let index = get_index(x, y);
+    history[index] = cellStateOut[index];
+    cellStateOut[index] = next_cell(x, y);
+}
+
+// The cell an invocation of the *_chunks entry points steps, dispatched indirectly with x and y covering
+// one chunk and z running through the chunk list; the grid size for invocations off the chunk or the grid
+fn chunk_cell(global_id: vec3<u32>) -> vec2<u32> {
+    let chunk = chunkList[global_id.z];
+    let cell = vec2<u32>(chunk % chunks.columns, chunk / chunks.columns) * CHUNK_SIZE + global_id.xy;
+    if (any(global_id.xy >= vec2<u32>(CHUNK_SIZE)) || cell.x >= params.width || cell.y >= params.height) {
+        return vec2<u32>(params.width, params.height);
+    }
+    return cell;
+}
+
+fn mark_changed(cell: vec2<u32>) {
+    let chunk = (cell.y / CHUNK_SIZE) * chunks.columns + cell.x / CHUNK_SIZE;
+    atomicStore(&chunkChanged[chunk], 1u);
+    atomicStore(&chunkStale[chunk], 1u);
+}
+
+// The step over the listed chunks only, marking the chunks it changes for the next list
+@compute @workgroup_size(WORKGROUP_X, WORKGROUP_Y)
+fn main_chunks(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let cell = chunk_cell(global_id);
+    if (cell.x >= params.width) { return; }
+
+    let index = get_index(cell.x, cell.y);
+    let next = next_cell(cell.x, cell.y);
+    cellStateOut[index] = next;
+    if (next != cellStateIn[index]) { mark_changed(cell); }
+}
+
+// Same as main_history over the listed chunks
+@compute @workgroup_size(WORKGROUP_X, WORKGROUP_Y)
+fn main_chunks_history(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let cell = chunk_cell(global_id);
+    if (cell.x >= params.width) { return; }
+
+    let index = get_index(cell.x, cell.y);
+    let next = next_cell(cell.x, cell.y);
+    history[index] = cellStateOut[index];
+    cellStateOut[index] = next;
+    if (next != cellStateIn[index]) { mark_changed(cell); }
+}
@@ -0,0 +1,139 @@
+// Texture-backed generation step, composed after rules.wgsl (see texture.rs)
+// WORKGROUP_X and WORKGROUP_Y are placeholders, replaced with the chosen size before compiling (see workgroup.rs)
+
+// Bind Group 0: R32Uint textures instead of buffers, so no index math
+// binding(0) is the Previous Frame (sampled, read with textureLoad)
+// binding(1) is the Current Frame (storage, write only)
+// binding(2) is the grid dimensions and active rule (Uniform)
+// binding(3) is the input generation's number in x, for the forest fire's dice (Uniform)
+@group(0) @binding(0) var cellStateIn: texture_2d<u32>;
+@group(0) @binding(1) var cellStateOut: texture_storage_2d<r32uint, write>;
+@group(0) @binding(2) var<uniform> params: Params;
+@group(0) @binding(3) var<uniform> clock: vec4<u32>;
+
+@compute @workgroup_size(WORKGROUP_X, WORKGROUP_Y)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let cell = vec2<i32>(global_id.xy);
+    if (global_id.x >= params.width || global_id.y >= params.height) { return; }
+
+    // Gray-Scott: the weighted Laplacian of both concentrations over the 3x3 neighbourhood
+    if (params.automaton == AUTOMATON_GRAY_SCOTT) {
+        let status = textureLoad(cellStateIn, cell, 0).r;
+        let centre = gray_scott_decode(status);
+        var laplacian = vec2<f32>(0.0);
+        for (var i = -1; i <= 1; i++) {
+            for (var j = -1; j <= 1; j++) {
+                if (i == 0 && j == 0) { continue; }
+                let nx = resolve(cell.x + i, params.width);
+                let ny = resolve(cell.y + j, params.height);
+                let on_grid = nx >= 0 && ny >= 0;
+                let state = select(0u, textureLoad(cellStateIn, vec2<i32>(max(nx, 0), max(ny, 0)), 0).r, on_grid);
+                laplacian += laplacian_weight(i, j) * (gray_scott_neighbour(state, on_grid) - centre);
+            }
+        }
+        textureStore(cellStateOut, cell, vec4<u32>(gray_scott_step(status, laplacian), 0u, 0u, 0u));
+        return;
+    }
+
+    // Cyclic: how many of the 3x3 neighbours already hold the cell's successor
+    if (params.automaton == AUTOMATON_CYCLIC) {
+        let status = textureLoad(cellStateIn, cell, 0).r;
+        let successor = cyclic_successor(status);
+        var successors = 0u;
+        for (var i = -1; i <= 1; i++) {
+            for (var j = -1; j <= 1; j++) {
+                if (i == 0 && j == 0) { continue; }
+                let nx = resolve(cell.x + i, params.width);
+                let ny = resolve(cell.y + j, params.height);
+                if (nx < 0 || ny < 0) { continue; }
+                successors += select(0u, 1u, textureLoad(cellStateIn, vec2<i32>(nx, ny), 0).r == successor);
+            }
+        }
+        textureStore(cellStateOut, cell, vec4<u32>(cyclic_step(status, successors), 0u, 0u, 0u));
+        return;
+    }
+
+    // Rock-paper-scissors: how many of the 3x3 neighbours hold each species
+    if (params.automaton == AUTOMATON_ROCK_PAPER_SCISSORS) {
+        var counts = vec3<u32>(0u);
+        for (var i = -1; i <= 1; i++) {
+            for (var j = -1; j <= 1; j++) {
+                if (i == 0 && j == 0) { continue; }
+                let nx = resolve(cell.x + i, params.width);
+                let ny = resolve(cell.y + j, params.height);
+                if (nx < 0 || ny < 0) { continue; }
+                let state = textureLoad(cellStateIn, vec2<i32>(nx, ny), 0).r;
+                if (state >= 1u && state <= 3u) { counts[state - 1u] += 1u; }
+            }
+        }
+        let status = textureLoad(cellStateIn, cell, 0).r;
+        textureStore(cellStateOut, cell, vec4<u32>(rps_step(status, counts), 0u, 0u, 0u));
+        return;
+    }
+
+    // Lenia: the ring-weighted average of every cell within the radius, summed directly
+    if (params.automaton == AUTOMATON_LENIA) {
+        let r = i32(params.lenia_radius);
+        var potential = 0.0;
+        var total = 0.0;
+        for (var j = -r; j <= r; j++) {
+            for (var i = -r; i <= r; i++) {
+                let weight = lenia_kernel(length(vec2<f32>(f32(i), f32(j))));
+                if (weight == 0.0) { continue; }
+                total += weight;
+                let nx = resolve(cell.x + i, params.width);
+                let ny = resolve(cell.y + j, params.height);
+                if (nx < 0 || ny < 0) { continue; }
+                potential += weight * lenia_decode(textureLoad(cellStateIn, vec2<i32>(nx, ny), 0).r);
+            }
+        }
+        let status = textureLoad(cellStateIn, cell, 0).r;
+        textureStore(cellStateOut, cell, vec4<u32>(lenia_step(status, potential / total), 0u, 0u, 0u));
+        return;
+    }
+
+    // Forest fire: whether any of the 3x3 neighbours is burning, and the cell's roll for this generation
+    if (params.automaton == AUTOMATON_FOREST_FIRE) {
+        var fire_nearby = false;
+        for (var i = -1; i <= 1; i++) {
+            for (var j = -1; j <= 1; j++) {
+                if (i == 0 && j == 0) { continue; }
+                let nx = resolve(cell.x + i, params.width);
+                let ny = resolve(cell.y + j, params.height);
+                if (nx < 0 || ny < 0) { continue; }
+                fire_nearby = fire_nearby || textureLoad(cellStateIn, vec2<i32>(nx, ny), 0).r == 2u;
+            }
+        }
+        let status = textureLoad(cellStateIn, cell, 0).r;
+        let roll = forest_roll(global_id.y * params.width + global_id.x, clock.x);
+        textureStore(cellStateOut, cell, vec4<u32>(forest_fire_step(status, fire_nearby, roll), 0u, 0u, 0u));
+        return;
+    }
+
+    var neighbors = 0u;
+    if (hex_neighbourhood()) {
+        for (var n = 0u; n < 6u; n++) {
+            let offset = hex_offset(global_id.y, n);
+            let nx = resolve(cell.x + offset.x, params.width);
+            let ny = resolve(cell.y + offset.y, params.height);
+            if (nx < 0 || ny < 0) { continue; }
+            neighbors += neighbor_weight(textureLoad(cellStateIn, vec2<i32>(nx, ny), 0).r);
+        }
+        let status = textureLoad(cellStateIn, cell, 0).r;
+        textureStore(cellStateOut, cell, vec4<u32>(next_state(status, neighbors), 0u, 0u, 0u));
+        return;
+    }
+    let r = neighbourhood_radius();
+    for (var i = -r; i <= r; i++) {
+        for (var j = -r; j <= r; j++) {
+            if (i == 0 && j == 0) { continue; }
+            let nx = resolve(cell.x + i, params.width);
+            let ny = resolve(cell.y + j, params.height);
+            if (nx < 0 || ny < 0) { continue; }
+            neighbors += neighbor_weight(textureLoad(cellStateIn, vec2<i32>(nx, ny), 0).r);
+        }
+    }
+
+    let status = textureLoad(cellStateIn, cell, 0).r;
+    textureStore(cellStateOut, cell, vec4<u32>(next_state(status, neighbors), 0u, 0u, 0u));
+}
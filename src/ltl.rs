@@ -0,0 +1,159 @@
+use std::fmt;
+use rayon::prelude::*;
+use crate::boundary::Boundary;
+use crate::{MAX_AGE, WALL};
+
+/// Largest neighbourhood radius accepted; the GPU loops over all (2r+1)² cells
+pub const MAX_RADIUS: u32 = 10;
+
+/// Larger than Life rule: a radius-r Moore neighbourhood (the cell itself not counted) with inclusive
+/// birth and survival ranges for the number of live cells in it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LtlRule {
+    pub radius: u32,
+    pub birth: (u32, u32),
+    pub survive: (u32, u32),
+}
+
+impl LtlRule {
+    /// Bosco's rule, R5,B34-45,S33-57
+    pub const BOSCO: LtlRule = LtlRule { radius: 5, birth: (34, 45), survive: (33, 57) };
+
+    pub fn parse_radius(text: &str) -> Result<u32, String> {
+        match text.parse() {
+            Ok(radius) if (1..=MAX_RADIUS).contains(&radius) => Ok(radius),
+            _ => Err(format!("invalid radius '{}' (expected 1 to {})", text, MAX_RADIUS)),
+        }
+    }
+
+    /// "34-45" -> (34, 45); a single number is a range of one
+    pub fn parse_range(text: &str) -> Result<(u32, u32), String> {
+        let invalid = || format!("invalid range '{}' (expected something like 34-45)", text);
+        let (low, high) = text.split_once('-').unwrap_or((text, text));
+        match (low.trim().parse(), high.trim().parse()) {
+            (Ok(low), Ok(high)) if low <= high => Ok((low, high)),
+            _ => Err(invalid()),
+        }
+    }
+
+    pub fn next_state(&self, cell: u32, neighbors: u32) -> u32 {
+        let (low, high) = if cell > 0 { self.survive } else { self.birth };
+        // Same ageing as Life: born at 1, survivors age by one
+        if (low..=high).contains(&neighbors) { (cell + 1).min(MAX_AGE) } else { 0 }
+    }
+}
+
+impl fmt::Display for LtlRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "R{},B{}-{},S{}-{}", self.radius, self.birth.0, self.birth.1, self.survive.0, self.survive.1)
+    }
+}
+
+/// One Larger than Life generation of a `width`-wide grid.
+///
+/// Each row is first turned into prefix sums of live cells (extended `radius` cells past both edges
+/// per `boundary`), so a cell's neighbourhood costs 2r+1 subtractions instead of (2r+1)² reads.
+pub fn step_grid(input: &[u32], width: usize, rule: LtlRule, boundary: Boundary) -> Vec<u32> {
+    let mut output = vec![0; input.len()];
+    step_grid_into(input, &mut output, &mut Vec::new(), width, rule, boundary);
+    output
+}
+
+/// `step_grid` writing into `output` (the same length as `input`) instead of allocating it, with the
+/// prefix sums in `prefix`, which is grown to fit and can be handed back for the next generation.
+pub fn step_grid_into(input: &[u32], output: &mut [u32], prefix: &mut Vec<u32>, width: usize, rule: LtlRule, boundary: Boundary) {
+    let height = input.len() / width;
+    let r = rule.radius as usize;
+    let stride = width + 2 * r + 1;
+    prefix.resize(height * stride, 0);
+    prefix.par_chunks_mut(stride).enumerate().for_each(|(y, sums)| {
+        let row = &input[y * width..(y + 1) * width];
+        // Left over from an earlier generation, maybe at another radius
+        sums[0] = 0;
+        for k in 0..width + 2 * r {
+            let live = boundary.resolve(k as i64 - r as i64, width).is_some_and(|x| row[x] > 0 && row[x] != WALL);
+            sums[k + 1] = sums[k] + live as u32;
+        }
+    });
+
+    output.par_iter_mut().enumerate().for_each(|(index, out)| {
+        let (x, y) = (index % width, index / width);
+        let mut neighbors = 0;
+        for dy in -(r as i64)..=r as i64 {
+            if let Some(ny) = boundary.resolve(y as i64 + dy, height) {
+                // Columns x-r..=x+r sit at x..=x+2r in the extended row
+                let sums = &prefix[ny * stride..(ny + 1) * stride];
+                neighbors += sums[x + 2 * r + 1] - sums[x];
+            }
+        }
+        let cell = input[index];
+        *out = if cell == WALL { WALL } else { rule.next_state(cell, neighbors - (cell > 0) as u32) };
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The straightforward (2r+1)² loop the compute shader runs
+    fn naive_step(input: &[u32], width: usize, rule: LtlRule, boundary: Boundary) -> Vec<u32> {
+        let height = input.len() / width;
+        let r = rule.radius as i64;
+        (0..input.len()).map(|index| {
+            let (x, y) = ((index % width) as i64, (index / width) as i64);
+            let mut neighbors = 0;
+            for dy in -r..=r {
+                for dx in -r..=r {
+                    if dx == 0 && dy == 0 { continue; }
+                    if let (Some(nx), Some(ny)) = (boundary.resolve(x + dx, width), boundary.resolve(y + dy, height)) {
+                        neighbors += (input[ny * width + nx] > 0) as u32;
+                    }
+                }
+            }
+            rule.next_state(input[index], neighbors)
+        }).collect()
+    }
+
+    #[test]
+    fn prefix_sums_match_the_naive_loop() {
+        for boundary in [Boundary::Wrap, Boundary::Dead, Boundary::Mirror] {
+            // Wider than tall, so rows and columns can't be mixed up
+            let mut fast = crate::random_grid(56 * 40, 3, 0.5);
+            let mut naive = fast.clone();
+            for generation in 1..=10 {
+                fast = step_grid(&fast, 56, LtlRule::BOSCO, boundary);
+                naive = naive_step(&naive, 56, LtlRule::BOSCO, boundary);
+                assert!(fast == naive, "{} grids diverge at generation {}", boundary, generation);
+            }
+        }
+    }
+
+    #[test]
+    fn prefix_sums_are_reused_across_radii() {
+        let cells = crate::random_grid(40 * 30, 5, 0.5);
+        let mut prefix = Vec::new();
+        let mut output = vec![0; cells.len()];
+        for radius in [5, 2, 7, 1] {
+            let rule = LtlRule { radius, ..LtlRule::BOSCO };
+            step_grid_into(&cells, &mut output, &mut prefix, 40, rule, Boundary::Wrap);
+            assert!(output == naive_step(&cells, 40, rule, Boundary::Wrap), "radius {} diverges", radius);
+        }
+    }
+
+    #[test]
+    fn radius_one_is_life() {
+        let conway = LtlRule { radius: 1, birth: (3, 3), survive: (2, 3) };
+        let cells = crate::random_grid(32 * 32, 9, 0.4);
+        let life = crate::cpu::step_grid(&cells, 32, crate::rule::Rule::CONWAY, Boundary::Wrap, crate::topology::Topology::Square, crate::automaton::CellAutomaton::Life);
+        assert_eq!(step_grid(&cells, 32, conway, Boundary::Wrap), life);
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(LtlRule::parse_range("34-45"), Ok((34, 45)));
+        assert_eq!(LtlRule::parse_range("7"), Ok((7, 7)));
+        assert!(LtlRule::parse_range("45-34").is_err());
+        assert!(LtlRule::parse_radius("0").is_err());
+        assert_eq!(LtlRule::BOSCO.to_string(), "R5,B34-45,S33-57");
+    }
+}
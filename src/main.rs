@@ -1,268 +1,3411 @@
+mod adapter;
+#[cfg(not(target_arch = "wasm32"))]
+mod band_window;
+#[cfg(not(target_arch = "wasm32"))]
+mod bench;
+mod camera;
+mod cli;
+mod cpu_worker;
+mod custom_rules;
+mod display;
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
+mod hud;
+mod keybinds;
+mod overlay;
+mod present;
+mod record;
+mod recovery;
+#[cfg(not(target_arch = "wasm32"))]
+mod screenshot;
+mod search;
+mod selection;
+mod split;
+mod stamp;
+mod stats;
+#[cfg(not(target_arch = "wasm32"))]
+mod themes;
+#[cfg(not(target_arch = "wasm32"))]
+mod tui;
+#[cfg(not(target_arch = "wasm32"))]
+mod tune;
+mod viewer;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use web_time::{Duration, Instant};
 use winit::{
     application::ApplicationHandler,
-    event::{WindowEvent, ElementState, KeyEvent},
-    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    event::{WindowEvent, ElementState, KeyEvent, MouseButton, MouseScrollDelta},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy},
     window::{Window, WindowId},
-    dpi::PhysicalSize,
-    keyboard::{KeyCode, PhysicalKey},
+    dpi::{PhysicalPosition, PhysicalSize},
+    keyboard::{KeyCode, ModifiersState, PhysicalKey},
 };
 use wgpu::util::DeviceExt;
-use rayon::prelude::*;
+use rust_gpu_life::{ant::{self, AntRule}, automaton::Automaton, bitmap, boundary::Boundary, bounds::{self, BoundingBox, BoundsTracker}, changes::{ChangeCounter, Changes, Settled, SteadyState}, count_state, cyclic::{self, CyclicRule}, downsample::Downsampler, fade::Fader, forest_fire::ForestFire, frame_stats::{FrameTimings, Timing}, gray_scott::{self, GrayScott}, history::{History, HistoryPacker}, hooks::{self, HookAction, Hooks}, lenia::{self, Lenia}, ltl::LtlRule, palette::Theme, pattern::{self, Pattern}, mutation::Mutation, population::PopulationCounter, rain::Rain, random_grid, regions::RuleMap, replay::{Replay, ReplayEvent, Session, SessionLog, Start}, rps, rule::{Rule, RULE_PRESETS, preset_name}, split_species, slots::{Slot, SLOTS}, snapshot::{Settings, Snapshot}, soup, sparkline::PopulationGraph, timer::GpuTimer, topology::Topology, transform::Transform, verify::Verifier, walls::Walls, wrap_cell, WALL};
+use rust_gpu_life::{cpu::CpuBackend, CpuSimulation, GpuSimulation, Simulation};
+#[cfg(not(target_arch = "wasm32"))]
+use rust_gpu_life::autosave::{self, Autosaver};
+#[cfg(not(target_arch = "wasm32"))]
+use rust_gpu_life::pipeline_cache::{self, PipelineCache};
+use camera::Camera;
+use cli::Args;
+use cpu_worker::{CpuWorker, Received, Run};
+use display::DisplayMode;
+use hud::{HelpRow, Throttle, HUD_INTERVAL, TITLE_INTERVAL};
+use keybinds::{Action, Keybinds};
+use overlay::{Action as PanelAction, Overlay, PanelState};
+use present::ResizeGesture;
+use record::Recorder;
+use search::{Outcome, SoupSearch};
+use selection::{Clip, Selection, SelectionRect};
+use split::Split;
+use stamp::{Stamp, StampPreview};
+use stats::{StatsLog, StatsRow};
+use viewer::Viewer;
+
+// Config (the command line's defaults are in cli.rs)
+// Generations per second; [ and ] halve and double it
+const DEFAULT_RATE: u32 = 60;
+const MAX_RATE: u32 = 1 << 20;
+// Caps on the work one frame may take, beyond which generations are dropped rather than owed
+const MAX_STEPS_PER_FRAME: u32 = 4096;
+const MAX_FRAME_TIME: Duration = Duration::from_millis(100);
+// Where Ctrl+S saves and Ctrl+O restores
+#[cfg(not(target_arch = "wasm32"))]
+const STATE_FILE: &str = "life_state.bin";
+// Most memory the rewind history may take; grids too big for a full history get a shorter one
+#[cfg(not(target_arch = "wasm32"))]
+const HISTORY_BUDGET: usize = 1 << 30;
+#[cfg(target_arch = "wasm32")]
+const HISTORY_BUDGET: usize = 256 << 20;
+// Reading GPU buffers back waits on the map callback, which the browser only runs once control returns to it
+const BLOCKING_READBACK: bool = cfg!(not(target_arch = "wasm32"));
+// Noise brush radius in cells, to start with and at most; Ctrl + wheel changes it
+const NOISE_RADIUS: usize = 8;
+const MAX_NOISE_RADIUS: usize = 512;
+// Seconds for the follow camera to close about two thirds of the way to the activity
+const FOLLOW_TIME: f64 = 0.4;
+// End leaves this much of the live cells' bounding box to spare on every side
+const FIT_MARGIN: f64 = 0.1;
+// Cells Ctrl+Shift+arrow shifts the grid by, instead of one
+const SHIFT_STRIDE: i32 = 16;
+// Slow motion only fades between generations up to this rate; any faster and each is on screen for a frame or two
+const MAX_BLEND_RATE: u32 = 30;
+// A dropped pattern whose row spans cover less than this fraction of the grid is written span by span
+const SPARSE_PATTERN: usize = 4;
+
+// Switching to CPU mode without reading the GPU back: the CPU thread's grid is still the GPU's as of
+// generation `from`, so it steps up to `to` while the GPU holds there
+#[derive(Clone, Copy)]
+struct FastForward {
+    from: usize,
+    to: usize,
+    stepped: usize,
+}
+
+struct GraphicsState {
+    window: Arc<Window>,
+    surface: wgpu::Surface<'static>,
+    // Kept to give the windows Ctrl+N opens surfaces of their own, which the browser build never does
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    instance: wgpu::Instance,
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    adapter: wgpu::Adapter,
+    // Those windows, drawing the same state buffers with cameras of their own
+    viewers: HashMap<WindowId, Viewer>,
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    config: wgpu::SurfaceConfiguration,
+    // Shown in the title so screenshots in bug reports say which GPU ran
+    adapter_name: String,
+    // What the surface can present with, for V to cycle through
+    present_modes: Vec<wgpu::PresentMode>,
+    render_pipeline: wgpu::RenderPipeline,
+    // Same shader and bindings, drawing the minimap into its corner of the window
+    minimap_pipeline: wgpu::RenderPipeline,
+    // Only drawn while zoomed in
+    minimap: bool,
+    // Tints the chunks the GPU step computed (see `ChunkTracker`)
+    chunk_overlay: bool,
+    // Line along the torus seam, F5
+    seam: bool,
+    // F6: cells fade from one generation to the next in slow motion, see `blend`
+    interpolating: bool,
+    // The first generation the other buffer holds the one before again after an edit
+    blend_from: usize,
+    // One per GPU state buffer, selected by the simulation's parity; the layout also binds the split view's
+    render_bind_group_layout: wgpu::BindGroupLayout,
+    render_bind_groups: [wgpu::BindGroup; 2],
+    camera_buffer: wgpu::Buffer,
+    // Rebuilt when the downsampler's texture changes with the window size
+    camera_bind_group_layout: wgpu::BindGroupLayout,
+    camera_bind_group: wgpu::BindGroup,
+    camera: Camera,
+    // Presets, then any from themes.toml; T goes to the next
+    themes: Vec<(String, Theme)>,
+    theme: usize,
+    palette_buffer: wgpu::Buffer,
+    // F eases the camera toward the centroid of the latest changes, held while nothing changes
+    following: bool,
+    follow_target: Option<(f64, f64)>,
+    // End eases the camera onto the bounding box, as (center, zoom) until it gets there
+    fitting: Option<((f64, f64), f32)>,
+    downsampler: Downsampler,
+    stamps: Vec<Stamp>,
+    stamp: Option<Stamp>,
+    // I drops one of `rain_patterns` (indices into `stamps`) every `rain_every` generations
+    raining: bool,
+    rain: Rain,
+    rain_every: usize,
+    rain_patterns: Vec<usize>,
+    // E flips a bit of the rule every so often (see mutation.rs); Z takes the latest flip back, as does the
+    // population dropping below `mutate_floor` of the grid under it
+    mutating: bool,
+    mutation: Mutation,
+    mutate_floor: f32,
+    // --hook and the config file's hook lines, checked every frame; `quitting` is set by a quit hook for the
+    // event loop to end the program after the frame
+    hooks: Hooks,
+    quitting: bool,
+    stamp_buffer: wgpu::Buffer,
+    stamp_mask_buffer: wgpu::Buffer,
+    // Only needed to rebind a grown mask, which the browser build never does
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    stamp_bind_group_layout: wgpu::BindGroupLayout,
+    stamp_bind_group: wgpu::BindGroup,
+    selection: Option<Selection>,
+    selecting: bool,
+    selection_buffer: wgpu::Buffer,
+    clip: Option<Clip>,
+    // Created on the first copy; on X11 the copied text only lives as long as this does
+    #[cfg(not(target_arch = "wasm32"))]
+    os_clipboard: Option<arboard::Clipboard>,
+    // The RLE our last copy put there; pasting it back uses the exact cells in `clip` instead
+    #[cfg(not(target_arch = "wasm32"))]
+    copied_rle: Option<String>,
+    population: PopulationCounter,
+    population_count: u32,
+    // Around the live cells, from the GPU a frame or two late, or counted in CPU mode while the HUD shows it
+    bounds_tracker: BoundsTracker,
+    bounds: Option<BoundingBox>,
+    // Sampled for the panel's graph
+    population_graph: PopulationGraph,
+    // Rolling frame timings for the HUD, printed as a table on exit
+    timings: FrameTimings,
+    // Cells in states 2 and 3: Immigration's blue species (the rest of the population is red), and
+    // rock-paper-scissors' second and third (the rest are the first)
+    species_counts: [u32; 2],
+    // Change detection: pauses once the grid dies out or stops evolving
+    change_counter: ChangeCounter,
+    fader: Fader,
+    steady: SteadyState,
+    // What the grid settled into and at which generation, shown until resumed
+    settled: Option<(Settled, usize)>,
+    // Ctrl+1..5 keep the grid here, 1..5 bring it back; the HUD notes which slot the grid came from
+    slots: [Option<Slot>; SLOTS],
+    restored: Option<usize>,
+    // None when the adapter can't do timestamp queries
+    timer: Option<GpuTimer>,
+    // The same for the render pass drawing the grid, in either mode
+    render_timer: Option<GpuTimer>,
+    gpu_time: Option<Duration>,
+    // CPU time of encoding the last steps and of the last submit, apart from the GPU's own time
+    encode_time: Duration,
+    submit_time: Duration,
+    // Steps on its own thread; see `CpuWorker`
+    cpu: CpuWorker,
+    cpu_backend: CpuBackend,
+    gpu: GpuSimulation,
+    grid_width: u32,
+    grid_height: u32,
+    rule: Rule,
+    boundary: Boundary,
+    topology: Topology,
+    automaton: Automaton,
+    ltl: LtlRule,
+    gray_scott: GrayScott,
+    // Ants a fresh soup starts with in Langton's Ant, and how they turn
+    ants: u32,
+    ant_rule: AntRule,
+    cyclic: CyclicRule,
+    rps_threshold: u32,
+    lenia: Lenia,
+    forest_fire: ForestFire,
+    density: f32,
+    using_cpu: bool,
+    // K: the CPU thread's grid drawn next to the GPU's, see split.rs
+    split: Option<Split>,
+    // Generation the CPU thread's grid last matched the GPU's with nothing changed on either since; None
+    // once an edit lands on the GPU at a later generation than that
+    cpu_base: Option<usize>,
+    // Set while a switch to CPU mode steps the thread up to the GPU, see `start_fast_forward`
+    fast_forward: Option<FastForward>,
+    paused: bool,
+    step_requested: bool,
+    target_rate: u32,
+    // Fixed timestep: generations owed from the real time since the last frame
+    last_frame: Instant,
+    owed: f64,
+    step: usize,
+    rate_timer: Instant,
+    rate_start_step: usize,
+    generations_per_second: f64,
+    cursor: Option<PhysicalPosition<f64>>,
+    paint_value: Option<u32>,
+    // Ctrl + left drag sprinkles fresh soup instead
+    noising: bool,
+    noise_radius: usize,
+    panning: bool,
+    // Nothing is simulated or drawn while the window can't be seen
+    occluded: bool,
+    minimized: bool,
+    // Sizes waiting for the next frame, and nothing stepped while the window is dragged or resized
+    resize_gesture: ResizeGesture,
+    recorder: Option<Recorder>,
+    record_path: Option<String>,
+    record_size: Option<(u32, u32)>,
+    stats: Option<StatsLog>,
+    overlay: Overlay,
+    // Shown in the overlay, which is drawn before the current frame's time is known
+    frame_time: Duration,
+    // Status text, rebuilt a few times a second rather than every frame; the title only once a second
+    hud_lines: Vec<String>,
+    hud_refresh: Throttle,
+    // The help's key list as (action, name, keys) while it's open; each action's setting is read every frame
+    help: Option<Vec<(Action, &'static str, String)>>,
+    title_refresh: Throttle,
+    // Written to the GPU buffer by the last CPU-mode frame that stepped
+    upload_bytes: usize,
+    // --verify: a CPU shadow the GPU is checked against
+    verifier: Option<Verifier>,
+    // Bit-packed recent generations for the left arrow; GPU snapshots are packed there and arrive late
+    history: History,
+    history_packer: HistoryPacker,
+    // Kept through fresh soups, clears and rewinds
+    walls: Walls,
+    // Tiles stepping under rules of their own in Life (see regions.rs), kept the same way; shared with the
+    // autosave thread
+    regions: Arc<RuleMap>,
+    // The panel's rule brush: while armed, left drags paint its rule onto tiles and right drags give them
+    // back to the grid's, the stroke in progress holding which
+    region_brush: Option<Rule>,
+    region_painting: Option<Option<Rule>>,
+    // Washes each painted rule's tiles in a colour of its own
+    region_tint: bool,
+    display: DisplayMode,
+    // --record-session: every edit, at the generation it was made
+    session_log: Option<SessionLog>,
+    // --replay: the recorded edits still to come; live edits are ignored until they run out
+    replay: Option<Replay>,
+    // soup-search: soups run one after another until Enter
+    search: Option<SoupSearch>,
+    // Off with --no-autosave, or when there's nowhere to put it
+    #[cfg(not(target_arch = "wasm32"))]
+    autosaver: Option<Autosaver>,
+    // Written back on exit; None where the backend has no pipeline cache
+    #[cfg(not(target_arch = "wasm32"))]
+    pipeline_cache: Option<PipelineCache>,
+    // Set when the device is lost, see recovery.rs
+    device_lost: Arc<AtomicBool>,
+}
+
+impl GraphicsState {
+    // The backend currently advancing the simulation
+    fn backend(&self) -> &dyn Simulation {
+        if self.using_cpu { &self.cpu } else { &self.gpu }
+    }
+
+    // Edits go to both backends: the GPU buffers are always what gets drawn
+    fn write_cells(&mut self, offset: usize, cells: &[u32]) {
+        self.cpu.set_cells(offset, cells);
+        self.gpu.set_cells(offset, cells);
+        if let Some(split) = &self.split { split.upload(&self.queue, offset, cells, false); }
+        self.walls.track(self.cell_count(), offset, cells);
+        let cells = cells.to_vec();
+        self.shadow(move |shadow| shadow.set_cells(offset, &cells));
+    }
+
+    fn write_rect(&mut self, x: usize, y: usize, width: usize, cells: &[u32]) {
+        self.cpu.set_rect(x, y, width, cells);
+        self.gpu.set_rect(x, y, width, cells);
+        if let Some(split) = &self.split {
+            for (index, offset, len) in rust_gpu_life::rect_spans(self.grid_width as usize, self.grid_height as usize, x, y, width, cells.len() / width) {
+                split.upload(&self.queue, index, &cells[offset..offset + len], false);
+            }
+        }
+        self.walls.track_rect(self.grid_width as usize, self.grid_height as usize, x, y, width, cells);
+        let cells = cells.to_vec();
+        self.shadow(move |shadow| shadow.set_rect(x, y, width, &cells));
+    }
+
+    // (width, height) in cells
+    fn grid(&self) -> (u32, u32) {
+        (self.grid_width, self.grid_height)
+    }
+
+    fn cell_count(&self) -> usize {
+        self.grid_width as usize * self.grid_height as usize
+    }
+
+    // Replays a change to the GPU on the --verify shadow, at the generation the GPU got it
+    fn shadow(&mut self, edit: impl FnOnce(&mut dyn Simulation) + 'static) {
+        if let Some(verifier) = &mut self.verifier { verifier.edit(self.step, edit); }
+    }
+
+    // Grid-resolution PNG of the latest generation, one pixel per cell
+    #[cfg(not(target_arch = "wasm32"))]
+    fn screenshot(&self) {
+        let cells = self.backend().cells().to_vec();
+        screenshot::save_png(cells, self.grid_width, self.grid_height, format!("life_{}.png", self.step));
+    }
+
+    // Whether the current backend's cells can be read right now; only GPU mode in the browser can't
+    fn cells_readable(&self, action: &str) -> bool {
+        if self.using_cpu || BLOCKING_READBACK { return true; }
+        eprintln!("{} needs the GPU cells back, which the browser can't wait for", action);
+        false
+    }
+
+    fn toggle_backend(&mut self) {
+        if self.split.is_some() {
+            println!("The split view runs both; K goes back to the GPU alone");
+            return;
+        }
+        // Pressed again while the CPU is still stepping up to the GPU, it stays in GPU mode
+        self.edit(ReplayEvent::Backend { cpu: !self.using_cpu && self.fast_forward.is_none() });
+    }
+
+    // CPU mode uploads every generation, so only the GPU -> CPU direction needs a copy, unless the CPU
+    // thread can step its own grid up to the GPU's instead
+    fn set_backend(&mut self, cpu: bool) {
+        if self.fast_forward.take().is_some() {
+            // Stopped part way, the thread's grid matches neither side any more
+            self.cpu_base = None;
+            self.cpu.jump(None);
+            if !cpu {
+                println!("Staying in GPU Mode");
+                return;
+            }
+        }
+        if self.using_cpu == cpu || (cpu && self.start_fast_forward()) || !self.cells_readable("Switching to CPU mode") { return; }
+        if !cpu && self.automaton == Automaton::LangtonsAnt {
+            eprintln!("Langton's ants only walk on the CPU");
+            return;
+        }
+        self.using_cpu = cpu;
+        // Generations the thread was still stepping would land on top of the GPU's
+        self.cpu.jump(None);
+        if self.using_cpu {
+            // The GPU has been running ahead, pull its state back before the next CPU step
+            self.cpu.set_cells(0, self.gpu.cells());
+        } else {
+            // The GPU grid was uploaded from the CPU meanwhile, so checking starts over from there
+            if let Some(verifier) = &mut self.verifier { verifier.restart(self.step, self.cpu.cells()); }
+            self.cpu_base = Some(self.step);
+        }
+        self.sync_generation();
+        println!("Switched to {}", if self.using_cpu { "CPU Mode" } else { "GPU Mode" });
+    }
+
+    // Steps the CPU thread from `cpu_base` up to the GPU's generation rather than copying the GPU's cells
+    // back, which the browser can't wait for and a big grid stalls on. Forest fires roll the same dice
+    // by generation on both sides, but Gray-Scott and Lenia round differently, so they read back.
+    fn start_fast_forward(&mut self) -> bool {
+        let Some(from) = self.cpu_base.filter(|_| !matches!(self.automaton, Automaton::GrayScott | Automaton::Lenia)) else { return false };
+        self.cpu.jump(None);
+        self.fast_forward = Some(FastForward { from, to: self.step, stepped: 0 });
+        if self.step > from { println!("Switching to CPU Mode: stepping the CPU from generation {} to {}", from, self.step); }
+        self.advance_fast_forward();
+        true
+    }
+
+    // Asks the thread for the generations still missing, and switches once it has stepped them all
+    fn advance_fast_forward(&mut self) {
+        let Some(mut fast_forward) = self.fast_forward.take() else { return };
+        if let Some(received) = self.cpu.receive() { fast_forward.stepped += received.steps; }
+        let left = (fast_forward.to - fast_forward.from).saturating_sub(fast_forward.stepped);
+        if left > 0 {
+            self.fast_forward = Some(fast_forward);
+            self.cpu.run(Run { generations: left.min(u32::MAX as usize) as u32, automaton: self.automaton, origin: self.activity_origin(), previous: false });
+            return;
+        }
+        self.using_cpu = true;
+        // The rows those generations changed are on the GPU already
+        self.cpu.take_changed_rows();
+        println!("Switched to CPU Mode");
+    }
+
+    // Copies the rows the CPU steps changed into the drawn GPU buffer, returning the bytes written
+    fn upload_changed_rows(&mut self) -> usize {
+        let runs = match self.cpu.take_changed_rows() {
+            Some(rows) => rust_gpu_life::row_runs(&rows, self.grid_width as usize),
+            None => vec![(0, self.cell_count())],
+        };
+        let cells = self.cpu.cells();
+        for &(offset, len) in &runs {
+            self.gpu.set_cells(offset, &cells[offset..offset + len]);
+        }
+        runs.iter().map(|&(_, len)| len * 4).sum()
+    }
+
+    // Uploads what came back from the CPU thread and counts its generations; changes come with the latest one
+    fn take_cpu_generations(&mut self, received: Received) -> Option<Changes> {
+        self.step += received.steps;
+        if received.steps > 0 { self.timings.record(true, Timing::Compute, received.busy); }
+        if !received.fresh { return None; }
+        // Upload to GPU, once for all generations and only the rows that changed
+        let upload_start = Instant::now();
+        self.upload_bytes = self.upload_changed_rows();
+        // The GPU leaves the previous generation in its other buffer, the diff view and blending need the same here
+        if let Some(previous) = received.previous.as_ref().filter(|_| self.wants_previous()) {
+            self.queue.write_buffer(&self.gpu.buffers()[self.gpu.parity() ^ 1], 0, bytemuck::cast_slice(previous));
+            self.upload_bytes += previous.len() * 4;
+        }
+        self.timings.record(true, Timing::Upload, upload_start.elapsed());
+        self.history.record(self.step, self.cpu.cells());
+        received.changes
+    }
+
+    // The split view's CPU side: counted apart from the GPU's generations and uploaded into its own buffers
+    fn take_split_generations(&mut self, received: Received) {
+        let (cell_count, wants_previous) = (self.cell_count(), self.wants_previous());
+        let Some(split) = &mut self.split else { return };
+        split.step += received.steps;
+        if received.steps > 0 { self.timings.record(true, Timing::Compute, received.busy); }
+        if !received.fresh { return; }
+        let upload_start = Instant::now();
+        let runs = match self.cpu.take_changed_rows() {
+            Some(rows) => rust_gpu_life::row_runs(&rows, self.grid_width as usize),
+            None => vec![(0, cell_count)],
+        };
+        let cells = self.cpu.cells();
+        for &(offset, len) in &runs {
+            split.upload(&self.queue, offset, &cells[offset..offset + len], false);
+        }
+        self.upload_bytes = runs.iter().map(|&(_, len)| len * 4).sum();
+        if let Some(previous) = received.previous.as_ref().filter(|_| wants_previous) {
+            split.upload(&self.queue, 0, previous, true);
+            self.upload_bytes += previous.len() * 4;
+        }
+        self.timings.record(true, Timing::Upload, upload_start.elapsed());
+    }
+
+    // Starts the CPU thread from the GPU's grid and draws the two side by side, or goes back to GPU mode alone
+    fn toggle_split(&mut self) {
+        // Either way the thread steps a grid of its own
+        self.cpu_base = None;
+        self.fast_forward = None;
+        if self.split.take().is_some() {
+            // Whatever the thread still owes is no longer drawn
+            self.cpu.jump(None);
+            self.fit_coverage();
+            println!("Split view: off");
+            return;
+        }
+        if self.automaton == Automaton::LangtonsAnt {
+            eprintln!("Langton's ants only walk on the CPU, there's no GPU side to compare");
+            return;
+        }
+        if !self.cells_readable("The split view") || self.replay.is_some() { return; }
+        // GPU mode drives the generation count, the population and everything else the HUD shows
+        if self.using_cpu { self.edit(ReplayEvent::Backend { cpu: false }); }
+        self.cpu.jump(None);
+        let cells = self.gpu.cells().to_vec();
+        self.cpu.set_cells(0, &cells);
+        self.sync_generation();
+        let viewport = camera::viewport((self.config.width / 2, self.config.height), self.grid()).2 as u32;
+        let split = Split::new(&self.device, &self.render_bind_group_layout, &self.gpu, viewport, self.step, |downsampler| {
+            camera_bind_group(&self.device, &self.camera_bind_group_layout, &self.camera_buffer, downsampler, &self.palette_buffer)
+        });
+        split.upload(&self.queue, 0, &cells, false);
+        split.upload(&self.queue, 0, &cells, true);
+        self.split = Some(split);
+        self.fit_coverage();
+        println!("Split view: CPU ({}) on the left, GPU on the right, from generation {}", self.cpu_backend_name(), self.step);
+    }
+
+    fn cycle_cpu_backend(&mut self) {
+        self.edit(ReplayEvent::CpuBackend(self.cpu_backend.next()));
+    }
+
+    // Rebuilds the CPU backend from the current one's cells; ages are lost going through the bit-packed one
+    fn set_cpu_backend(&mut self, backend: CpuBackend) {
+        self.cpu_backend = backend;
+        let mut cpu = self.cpu_backend.create(self.grid_width, self.grid_height, self.rule, self.boundary, self.automaton, self.cpu.cells().to_vec());
+        cpu.set_topology(self.topology);
+        cpu.set_ltl_rule(self.ltl);
+        cpu.set_gray_scott(self.gray_scott);
+        cpu.set_cyclic_rule(self.cyclic);
+        cpu.set_rps_threshold(self.rps_threshold);
+        cpu.set_lenia(self.lenia);
+        cpu.set_forest_fire(self.forest_fire);
+        cpu.set_ant_rule(self.ant_rule);
+        cpu.set_rule_map(self.saved_regions().as_ref());
+        self.cpu.jump(Some(cpu));
+        self.sync_generation();
+        println!("CPU backend: {}", self.cpu_backend_in_use());
+        self.warn_unmapped_backend();
+    }
+
+    // The rule map when anything is painted; an empty one is left off so Life steps the way it always has
+    fn saved_regions(&self) -> Option<RuleMap> {
+        (!self.regions.is_empty()).then(|| (*self.regions).clone())
+    }
+
+    // The map onto every backend, after the brush or a load changed it
+    fn apply_regions(&mut self) {
+        let map = self.saved_regions();
+        self.cpu.set_rule_map(map.as_ref());
+        self.gpu.set_rule_map(map.as_ref());
+        self.shadow(move |shadow| shadow.set_rule_map(map.as_ref()));
+        self.warn_unmapped_backend();
+    }
+
+    // Only the naive CPU backend looks up each cell's rule, the others step everything under the grid's
+    fn warn_unmapped_backend(&self) {
+        let backend = self.cpu_backend.for_automaton(self.automaton);
+        if backend != CpuBackend::Naive && !self.regions.is_empty() {
+            eprintln!("warning: the {} CPU backend ignores the rule map, so CPU mode and the split view won't follow it (the naive one does)", backend);
+        }
+    }
+
+    // Forest fires roll their dice by generation, so both sides count from wherever the grid jumped to
+    fn sync_generation(&mut self) {
+        self.cpu.set_generation(self.step as u64);
+        self.gpu.set_generation(self.step as u64);
+    }
+
+    // Only notes the size; the next frame configures the surface with whichever came last
+    fn resize(&mut self, new_size: PhysicalSize<u32>) {
+        let was_visible = self.visible();
+        self.minimized = new_size.width == 0 || new_size.height == 0;
+        if !self.minimized { self.resize_gesture.resized(new_size); }
+        if self.visible() && !was_visible { self.window.request_redraw(); }
+    }
+
+    fn apply_resize(&mut self) {
+        let Some(size) = self.resize_gesture.take_size() else { return };
+        if self.minimized { return; }
+        self.config.width = size.width;
+        self.config.height = size.height;
+        self.surface.configure(&self.device, &self.config);
+        self.fit_coverage();
+    }
+
+    // Any window being dragged or resized holds the simulation where it is
+    fn resizing(&self) -> bool {
+        self.resize_gesture.active() || self.viewers.values().any(|viewer| viewer.resize_gesture.active())
+    }
+
+    // Zoomed-out coverage at the resolution each pane draws the grid at
+    fn fit_coverage(&mut self) {
+        let viewport = camera::viewport(self.pane_size(), self.grid()).2 as u32;
+        if self.downsampler.resize(&self.device, self.gpu.buffers(), viewport) {
+            self.camera_bind_group = camera_bind_group(&self.device, &self.camera_bind_group_layout, &self.camera_buffer, &self.downsampler, &self.palette_buffer);
+        }
+        if let Some(split) = &mut self.split {
+            split.resize(&self.device, viewport, |downsampler| camera_bind_group(&self.device, &self.camera_bind_group_layout, &self.camera_buffer, downsampler, &self.palette_buffer));
+        }
+    }
+
+    fn set_occluded(&mut self, occluded: bool) {
+        let was_visible = self.visible();
+        self.occluded = occluded;
+        if self.visible() && !was_visible { self.window.request_redraw(); }
+    }
+
+    fn visible(&self) -> bool {
+        !self.occluded && !self.minimized
+    }
+
+    // Frames are only requested back to back while the simulation is running and on screen, or while the
+    // CPU thread still owes a step or is stepping up to the GPU, or the camera is on its way to a fit; a
+    // single step asked for during a resize waits for the frames after it
+    fn animating(&self) -> bool {
+        (!self.paused || self.step_requested || self.fast_forward.is_some() || self.fitting.is_some() || (self.using_cpu || self.split.is_some()) && self.cpu.pending() > 0) && self.on_screen()
+    }
+
+    // While the main window can't be seen, the first other window that can steps the simulation on its frames
+    fn stand_in(&self) -> Option<WindowId> {
+        if self.visible() { return None; }
+        self.viewers.iter().find(|(_, viewer)| viewer.visible()).map(|(&id, _)| id)
+    }
+
+    fn on_screen(&self) -> bool {
+        self.visible() || self.stand_in().is_some()
+    }
+
+    // A frame from whichever window steps the simulation
+    fn request_redraw(&self) {
+        match self.stand_in() {
+            Some(id) => self.viewers[&id].window.request_redraw(),
+            None => self.window.request_redraw(),
+        }
+    }
+
+    // Only unrecoverable surface errors are returned, everything else is handled here
+    fn redraw(&mut self) -> Result<(), wgpu::SurfaceError> {
+        // Minimized windows have no surface to acquire, and simulating unseen wastes the battery; another
+        // window on screen still steps it (see `stand_in`)
+        if !self.on_screen() { return Ok(()); }
+        let start = Instant::now();
+        self.apply_resize();
+        self.play_due_events();
+        self.rain();
+        self.mutate();
+        // After a pause or a stall only a bounded slice of time counts
+        let elapsed = std::mem::replace(&mut self.last_frame, start).elapsed().min(MAX_FRAME_TIME);
+        self.follow(elapsed);
+        // Resizing, like pausing, stops the clock rather than running up generations owed for afterwards;
+        // a single step asked for meanwhile is kept for when it's over
+        let mut generations = if self.resizing() {
+            0
+        } else if !self.paused {
+            self.owed += elapsed.as_secs_f64() * self.target_rate as f64;
+            let generations = (self.owed as u32).min(MAX_STEPS_PER_FRAME);
+            // Falling behind shows up as the achieved rate in the HUD, not as an ever-growing debt
+            self.owed = if generations == MAX_STEPS_PER_FRAME { self.owed.fract() } else { self.owed - generations as f64 };
+            generations
+        } else if std::mem::take(&mut self.step_requested) {
+            1
+        } else {
+            0
+        };
+        // A replay stops on the generations its events happened at, a soup search at its limit
+        if let Some(allowed) = self.replay.as_ref().and_then(|replay| replay.steps_allowed(self.step)) {
+            generations = generations.min(allowed.min(u32::MAX as usize) as u32);
+        }
+        if let Some(search) = &self.search {
+            generations = search.steps_left(self.step, generations);
+        }
+
+        // A switch to CPU mode holds the GPU where it is while the CPU thread steps up to it
+        if self.fast_forward.is_some() {
+            generations = 0;
+            self.advance_fast_forward();
+        }
+
+        // CPU LOGIC (Done first to avoid borrow conflicts): the thread is asked for this frame's generations
+        // and whatever it finished since the last frame is drawn
+        let mut cpu_changes = None;
+        let mut stepped = generations > 0;
+        // Whether the drawn buffer holds a new generation, which the diff view's fade steps against
+        let mut advanced = stepped && !self.using_cpu;
+        if self.using_cpu {
+            if generations > 0 {
+                self.cpu.run(Run { generations, automaton: self.automaton, origin: self.activity_origin(), previous: self.wants_previous() });
+            }
+            let received = self.cpu.receive();
+            stepped = received.is_some();
+            advanced = received.as_ref().is_some_and(|received| received.fresh && received.steps > 0);
+            cpu_changes = received.and_then(|received| self.take_cpu_generations(received));
+        } else if self.split.is_some() {
+            // The split view's CPU side is asked for the GPU's generations too, and falls behind if it can't keep up
+            if generations > 0 {
+                self.cpu.run(Run { generations, automaton: self.automaton, origin: self.activity_origin(), previous: self.wants_previous() });
+            }
+            if let Some(received) = self.cpu.receive() { self.take_split_generations(received); }
+        }
+
+        // get the GPU resources (Immutable Borrow starts here); a soup search only draws every few frames
+        let draw = self.visible() && self.search.as_mut().is_none_or(SoupSearch::draw_frame);
+        let frame = if !draw { None } else {
+            match self.surface.get_current_texture() {
+                Ok(frame) => Some(frame),
+                Err(e @ (wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated)) => {
+                    // Common when moving between monitors or resuming from sleep
+                    log::warn!("Surface {:?}, reconfiguring", e);
+                    if self.config.width > 0 && self.config.height > 0 {
+                        self.surface.configure(&self.device, &self.config);
+                    }
+                    self.window.request_redraw();
+                    return Ok(());
+                }
+                Err(wgpu::SurfaceError::Timeout) => {
+                    log::warn!("Timed out acquiring the next frame, skipping it");
+                    self.window.request_redraw();
+                    return Ok(());
+                }
+                Err(e @ wgpu::SurfaceError::OutOfMemory) => {
+                    log::error!("Out of memory acquiring the next frame");
+                    return Err(e);
+                }
+            }
+        };
+        let view = frame.as_ref().map(|frame| frame.texture.create_view(&wgpu::TextureViewDescriptor::default()));
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+
+        // GPU LOGIC (Only runs if NOT using CPU), the simulation alternates its buffers every generation
+        let cells = self.cell_count();
+        if !self.using_cpu {
+            if generations > 0 {
+                let encode_start = Instant::now();
+                self.gpu.encode_steps(&mut encoder, generations, self.timer.as_ref().map(GpuTimer::timestamp_writes));
+                self.encode_time = encode_start.elapsed();
+                if let Some(timer) = &mut self.timer { timer.resolve(&mut encoder); }
+                self.step += generations as usize;
+                let origin = self.activity_origin();
+                self.change_counter.encode(&self.queue, &mut encoder, self.gpu.parity(), cells, origin, None);
+                if let Some(verifier) = self.verifier.as_mut().filter(|verifier| verifier.due(self.step)) {
+                    verifier.encode(&mut encoder, self.gpu.current_buffer(), self.step);
+                }
+                if self.history.due(self.step) {
+                    self.history_packer.encode(&self.queue, &mut encoder, self.gpu.parity(), self.history.bits());
+                }
+            }
+
+            self.population.encode(&mut encoder, self.gpu.parity(), cells, None);
+            self.bounds_tracker.encode(&mut encoder, self.gpu.parity(), cells);
+        }
+
+        if advanced && self.display == DisplayMode::Diff { self.fader.encode(&mut encoder, self.gpu.parity(), cells); }
+
+        // The fade follows the timestep every frame, not just when the view moves
+        if self.interpolating { self.update_camera(); }
+        // Zoomed-out coverage of whichever buffer is drawn
+        self.downsampler.encode(&mut encoder, self.gpu.parity());
+        if let Some(split) = &self.split { split.downsampler.encode(&mut encoder, 0); }
+        // CPU mode has uploaded its latest generation by now, so the drawn buffer is what gets saved either way
+        #[cfg(not(target_arch = "wasm32"))]
+        let settings = self.settings();
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(autosaver) = &mut self.autosaver {
+            let regions = (!self.regions.is_empty()).then(|| self.regions.clone());
+            let header = autosave::Header { width: self.grid_width, height: self.grid_height, generation: self.step as u64, rule: self.rule, regions, settings };
+            autosaver.encode(&self.device, &mut encoder, self.gpu.current_buffer(), header);
+        }
+
+        // Render pass (runs on every drawn frame) with the overlay on top, plus a copy at the recording resolution
+        let mut actions = Vec::new();
+        if let Some(view) = &view {
+            self.encode_render(&mut encoder, view, self.window_size(), self.minimap_rect(), self.render_timer.as_ref().map(GpuTimer::render_timestamp_writes));
+            if let Some(timer) = &mut self.render_timer { timer.resolve(&mut encoder); }
+            // Idle frames come from input, so they always show its effect
+            if self.overlay.hud && (self.hud_refresh.ready() || !self.animating()) {
+                self.hud_lines = self.status_lines(cells, self.frame_time);
+            }
+            let panel = PanelState {
+                paused: self.paused, target_rate: self.target_rate, max_rate: MAX_RATE, rule: self.rule, density: self.density, using_cpu: self.using_cpu,
+                generation: self.step, generations_per_second: self.generations_per_second, population: self.population_count, population_graph: &self.population_graph, cells, frame_time: self.frame_time,
+                history: self.history.len(), history_capacity: self.history.capacity(), history_bytes: self.history.bytes(),
+                gray_scott: (self.automaton == Automaton::GrayScott).then_some(self.gray_scott),
+                lenia: (self.automaton == Automaton::Lenia).then_some(self.lenia),
+                forest_fire: (self.automaton == Automaton::ForestFire).then_some(self.forest_fire),
+                regions: (self.automaton == Automaton::Life).then_some(&*self.regions),
+                region_brush: self.region_brush,
+                region_tint: self.region_tint,
+            };
+            let help: Vec<HelpRow> = self.help.iter().flatten().map(|&(action, name, ref keys)| HelpRow { action: name, keys: keys.clone(), value: self.setting(action) }).collect();
+            actions = self.overlay.encode(&self.window, &self.device, &self.queue, &mut encoder, view, &panel, &self.hud_lines, &help);
+            if let Some(recorder) = &self.recorder {
+                self.encode_render(&mut encoder, recorder.view(), recorder.size(), None, None);
+                recorder.encode_copy(&mut encoder);
+            }
+        }
+
+        let submit_start = Instant::now();
+        self.queue.submit(Some(encoder.finish()));
+        self.submit_time = submit_start.elapsed();
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(autosaver) = &mut self.autosaver { autosaver.begin_readback(); }
+        if stepped { self.snapshot_for_replay(); }
+        if let Some(frame) = frame {
+            if let Some(recorder) = &mut self.recorder { recorder.capture(&self.device); }
+            let present_start = Instant::now();
+            frame.present();
+            self.timings.record(self.using_cpu, Timing::Present, self.submit_time + present_start.elapsed());
+        }
+        for action in actions { self.apply(action); }
+        // The other windows draw whatever this frame stepped to
+        let stand_in = self.stand_in();
+        for (id, viewer) in &self.viewers {
+            if viewer.visible() && Some(*id) != stand_in { viewer.window.request_redraw(); }
+        }
+        if self.animating() { self.request_redraw(); }
+
+        // Population and changes: GPU results trickle in a frame or two late, the CPU can just count
+        let (population, changes) = if self.using_cpu {
+            let species = match self.automaton {
+                Automaton::Immigration | Automaton::RockPaperScissors => [2, 3].map(|state| count_state(self.cpu.cells(), state)),
+                _ => [0; 2],
+            };
+            if self.overlay.hud { self.bounds = bounds::of_cells(self.cpu.cells(), self.grid_width as usize); }
+            (Some([self.cpu.population(), species[0], species[1]]), cpu_changes.map(|changes| (self.step, changes)))
+        } else {
+            self.population.begin_readback();
+            self.bounds_tracker.begin_readback();
+            self.change_counter.begin_readback(self.step);
+            if let Some(timer) = &mut self.timer { timer.begin_readback(); }
+            if let Some(verifier) = &mut self.verifier { verifier.begin_readback(); }
+            self.history_packer.begin_readback(self.step);
+            self.device.poll(wgpu::Maintain::Poll);
+            if let Some((generation, words)) = self.history_packer.try_read() {
+                self.history.push(generation, words);
+            }
+            if let Some(bounds) = self.bounds_tracker.try_read() { self.bounds = bounds; }
+            if let Some(time) = self.timer.as_mut().and_then(GpuTimer::try_read) {
+                self.gpu_time = Some(time);
+                self.timings.record(false, Timing::Compute, time);
+            }
+            if let Some(Err(divergence)) = self.verifier.as_mut().and_then(Verifier::check) {
+                eprintln!("{}", divergence);
+            }
+            (self.population.try_read(), self.change_counter.try_read())
+        };
+        if let Some(timer) = &mut self.render_timer {
+            timer.begin_readback();
+            self.device.poll(wgpu::Maintain::Poll);
+            if let Some(time) = timer.try_read() { self.timings.record(self.using_cpu, Timing::Render, time); }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(autosaver) = &mut self.autosaver {
+            self.device.poll(wgpu::Maintain::Poll);
+            autosaver.try_write();
+        }
+        if let Some([count, second, third]) = population {
+            self.population_count = count;
+            self.species_counts = [second, third];
+            self.population_graph.record(self.step, count);
+            // Taken back before the grid is empty, while there's still something for the old rule to run
+            let floor = (self.mutate_floor as f64 * self.cell_count() as f64) as u32;
+            if self.mutating && self.mutation.endangered(count, floor) {
+                println!("Population {} fell below {:.1}% of the grid", with_commas(count as u64), self.mutate_floor * 100.0);
+                self.revert_mutation();
+            }
+        }
+        if let Some(centroid) = changes.filter(|_| self.following).and_then(|(_, changes)| changes.centroid(self.grid_width, self.grid_height)) {
+            self.follow_target = Some(centroid);
+        }
+        // Late results from before a pause are drained but not judged
+        if !self.paused {
+            let settled = population.and_then(|[count, ..]| self.steady.observe_population(count))
+                .or_else(|| changes.and_then(|(_, changes)| self.steady.observe_changes(changes)));
+            if let Some(settled) = settled { self.settle(settled); }
+        }
+        self.run_hooks(population.map(|[count, ..]| count));
+        if self.search.as_ref().is_some_and(|search| search.at_limit(self.step)) {
+            self.finish_soup(None);
+        }
+
+        // Throughput, averaged over half a second so the HUD stays readable
+        let rate_elapsed = self.rate_timer.elapsed().as_secs_f64();
+        if rate_elapsed >= 0.5 {
+            self.generations_per_second = (self.step - self.rate_start_step) as f64 / rate_elapsed;
+            self.rate_timer = Instant::now();
+            self.rate_start_step = self.step;
+        }
+        if let Some(split) = &mut self.split { split.update_rate(); }
+
+        let duration = start.elapsed();
+        self.frame_time = duration;
+        self.timings.record(self.using_cpu, Timing::Frame, duration);
+        if let (Some(stats), Some((generation, changes))) = (&mut self.stats, changes) {
+            let mode = if self.using_cpu { "CPU" } else { "GPU" };
+            stats.log(&StatsRow { generation, population: changes.population, births: changes.births, deaths: changes.deaths, frame_time: duration, mode, rule: self.rule });
+        }
+        // The title is only for the taskbar, so once a second is plenty
+        if self.title_refresh.ready() || !self.animating() {
+            let mode = match (self.using_cpu, &self.split, self.fast_forward) {
+                (true, _, _) => format!("CPU {}", self.cpu_backend_name()),
+                (false, Some(split), _) => format!("CPU {} (Gen: {}) vs GPU", self.cpu_backend_name(), split.step),
+                (false, None, Some(fast_forward)) => format!("GPU, CPU catching up {}/{}", fast_forward.stepped, fast_forward.to - fast_forward.from),
+                (false, None, None) => "GPU".to_string(),
+            };
+            let status = match (self.settled, self.paused) {
+                (Some((settled, _)), _) => format!(" | {}", settled),
+                (None, true) => " | PAUSED".to_string(),
+                (None, false) => String::new(),
+            };
+            // Brian's Brain ignores the Life rule
+            let rule = match self.automaton {
+                Automaton::Life | Automaton::Generations => format!("{} | ", preset_name(self.rule).map_or_else(|| self.rule.to_string(), str::to_string)),
+                _ => String::new(),
+            };
+            self.window.set_title(&format!("Rust Life | {} | {} | {}Gen: {}{}", self.adapter_name, mode, rule, self.step, status));
+        }
+        Ok(())
+    }
+
+    // The HUD text, one line per topic; like the panel it shows the previous frame's time
+    // p50 / p99 of the current mode's timings over the last frames, which shows stutters the latest
+    // frame's time hides
+    fn timing_line(&self, cpu: bool) -> String {
+        let parts: Vec<String> = Timing::ALL.iter().filter_map(|&timing| {
+            let summary = self.timings.summary(cpu, timing)?;
+            Some(format!("{} {:.2?}/{:.2?}", timing.name(), summary.p50, summary.p99))
+        }).collect();
+        format!("p50/p99: {}", parts.join(", "))
+    }
+
+    // The backend picked, or the naive one and why when that one can't run the automaton
+    fn cpu_backend_in_use(&self) -> String {
+        match self.cpu_backend.for_automaton(self.automaton) {
+            backend if backend == self.cpu_backend => backend.to_string(),
+            backend => format!("{} ({} can't run {})", backend, self.cpu_backend, self.automaton),
+        }
+    }
+
+    // The sparse and auto backends say how they're stepping, e.g. "auto: lists"
+    fn cpu_backend_name(&self) -> String {
+        match self.cpu.stepping() {
+            Some(stepping) => format!("{}: {}", self.cpu_backend, stepping),
+            None => self.cpu_backend_in_use(),
+        }
+    }
+
+    fn status_lines(&self, cells: usize, duration: Duration) -> Vec<String> {
+        let lagging = !self.paused && !self.resizing() && self.generations_per_second < self.target_rate as f64 * 0.9;
+        let upload = format!("upload {:.2} MB/frame", self.upload_bytes as f64 / (1024.0 * 1024.0));
+        let mode = match (self.using_cpu, &self.split) {
+            (true, _) => format!("CPU (Rayon, {}, {})", self.cpu_backend_name(), upload),
+            (false, Some(_)) => format!("Split, CPU (Rayon, {}, {}) left, GPU (WGPU) right", self.cpu_backend_name(), upload),
+            (false, None) => "GPU (WGPU)".to_string(),
+        };
+        // Update Time is CPU-side (encoding and present), split out for the compute passes and the submit; the
+        // timestamps show what the compute passes really took
+        let gpu_time = match (self.using_cpu, self.gpu_time) {
+            (true, _) => String::new(),
+            (false, Some(time)) => format!(" (encode {:.2?}, submit {:.2?}, GPU compute: {:.2?})", self.encode_time, self.submit_time, time),
+            (false, None) => format!(" (encode {:.2?}, submit {:.2?}, GPU compute: n/a)", self.encode_time, self.submit_time),
+        };
+        // Brian's Brain ignores the Life rule
+        let rule = match self.automaton {
+            Automaton::Life | Automaton::Generations if self.mutating => format!("Rule: {} (mutating, next at gen {})", self.rule, self.mutation.due_at()),
+            Automaton::Life | Automaton::Generations => format!("Rule: {}", self.rule),
+            Automaton::LargerThanLife => format!("Automaton: ltl ({})", self.ltl),
+            Automaton::GrayScott => format!("Automaton: gray-scott ({})", self.gray_scott),
+            Automaton::LangtonsAnt => format!("Automaton: langtons-ant ({})", self.ant_rule),
+            Automaton::Cyclic => format!("Automaton: cyclic ({})", self.cyclic),
+            Automaton::Lenia => format!("Automaton: lenia ({})", self.lenia),
+            Automaton::ForestFire => format!("Automaton: forest-fire ({})", self.forest_fire),
+            Automaton::RockPaperScissors => format!("Automaton: rock-paper-scissors (threshold {})", self.rps_threshold),
+            automaton => format!("Automaton: {}", automaton),
+        };
+        // Which species is winning
+        let species = match self.automaton {
+            Automaton::Immigration => format!(", red {}, blue {}", with_commas((self.population_count - self.species_counts[0]) as u64), with_commas(self.species_counts[0] as u64)),
+            Automaton::RockPaperScissors => {
+                let [b, c] = self.species_counts;
+                format!(", A {}, B {}, C {}", with_commas(self.population_count.saturating_sub(b + c) as u64), with_commas(b as u64), with_commas(c as u64))
+            }
+            _ => String::new(),
+        };
+
+        let mut lines = vec![
+            format!("Mode: {}", mode),
+            format!("{} | Boundary: {}", rule, self.boundary),
+            format!("Gen: {}{} ({:.0} of {} gen/s{})", self.step, self.restored.map_or(String::new(), |slot| format!(", restored from slot {}", slot + 1)), self.generations_per_second, self.target_rate, if lagging { ", can't keep up" } else { "" }),
+            format!("Update Time: {:.2?}{}", duration, gpu_time),
+            self.timing_line(self.using_cpu),
+            format!("Population: {} ({:.1}%{}) of {} cells", with_commas(self.population_count as u64), self.population_count as f64 / cells as f64 * 100.0, species, with_commas(cells as u64)),
+            format!("bbox: {}", self.bounds.map_or_else(|| "empty".to_string(), |bounds| bounds.to_string())),
+            format!("Present: {} | Display: {}{}", present::name(self.config.present_mode), self.display, if self.interpolating { ", blended" } else { "" }),
+        ];
+        // The cell mouse editing would change, when the cursor is over the grid
+        if let Some(cursor) = self.cursor.filter(|&cursor| camera::in_viewport(self.pane_position(cursor), self.pane_size(), self.grid())) {
+            let (x, y) = self.window_to_cell(cursor);
+            let (x, y) = wrap_cell(x, y, self.grid_width as usize, self.grid_height as usize);
+            lines.push(format!("Cursor: cell ({}, {})", x, y));
+        }
+        // The GPU side's timings are the line above
+        if let Some(split) = &self.split {
+            let behind = self.step.saturating_sub(split.step);
+            lines.push(format!("CPU side: Gen {} ({:.0} gen/s{}) | {}", split.step, split.generations_per_second, if behind > 0 { format!(", {} behind", with_commas(behind as u64)) } else { String::new() }, self.timing_line(true)));
+        }
+        match (self.settled, self.paused) {
+            (Some((settled, generation)), _) => lines.push(format!("{} at generation {}", settled, generation)),
+            (None, true) => lines.push("PAUSED".to_string()),
+            (None, false) => {}
+        }
+        if let Some(recorder) = &self.recorder {
+            lines.push(format!("🔴 REC {} frames", recorder.frames));
+        }
+        if let Some(search) = &self.search {
+            lines.push(format!("🔎 SEARCH soup {} (seed {}), {} generations each, Enter stops", search.soups + 1, search.seed, with_commas(search.generations as u64)));
+        }
+        if let Some(replay) = &self.replay {
+            lines.push(format!("▶ REPLAY {} events left, edits are ignored", replay.remaining()));
+        }
+        lines
+    }
+
+    // Starts the history over from `cells`, dropping snapshots still on their way back
+    fn restart_history(&mut self, cells: &[u32]) {
+        self.history_packer.discard();
+        self.history.clear(self.automaton.state_bits(self.rule));
+        self.history.record(self.step, cells);
+    }
+
+    // `restart_history` for a grid only the GPU holds: it's packed there and read back like the later snapshots
+    fn restart_gpu_history(&mut self) {
+        self.history_packer.discard();
+        self.history.clear(self.automaton.state_bits(self.rule));
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        self.history_packer.encode(&self.queue, &mut encoder, self.gpu.parity(), self.history.bits());
+        self.queue.submit(Some(encoder.finish()));
+        self.history_packer.begin_readback(self.step);
+    }
+
+    // Loads the newest snapshot from before the current generation and pauses; stepping on from there
+    // computes the later generations again. Life ages restart at 1.
+    fn step_back(&mut self) {
+        if self.replay.is_some() { return; }
+        if self.fast_forward.is_some() { self.set_backend(true); }
+        let Some((generation, cells)) = self.history.rewind(self.step) else {
+            println!("No earlier generation in the history");
+            return;
+        };
+        self.log_event(&ReplayEvent::Rewind { to: generation });
+        self.rewind_to(generation, cells);
+        self.paused = true;
+        println!("Rewound to generation {} ({} earlier snapshots left)", generation, self.history.len() - 1);
+    }
+
+    fn rewind_to(&mut self, generation: usize, mut cells: Vec<u32>) {
+        self.restored = None;
+        self.walls.apply(&mut cells);
+        self.history_packer.discard();
+        self.cpu.jump(None);
+        self.write_cells(0, &cells);
+        self.step = generation;
+        self.rate_start_step = generation;
+        self.sync_generation();
+        self.cpu_base = Some(generation);
+        if let Some(split) = &mut self.split { split.restart(generation); }
+        if let Some(verifier) = &mut self.verifier { verifier.restart(generation, &cells); }
+        self.steady.reset();
+        self.settled = None;
+    }
+
+    // Stops stepping a grid that has nothing left to do; P or R gets it going again
+    fn settle(&mut self, settled: Settled) {
+        // The recording went on past this point, so a replay does too
+        if self.replay.is_some() { return; }
+        if self.search.is_some() {
+            self.finish_soup(Some(settled));
+            return;
+        }
+        // The next drop or mutation stirs it up again
+        if self.raining || self.mutating { return; }
+        // A hook waiting for this decides what happens instead, once
+        if self.hooks.on_settled() {
+            if self.settled.is_none() {
+                self.settled = Some((settled, self.step));
+                println!("{} at generation {}", settled, self.step);
+            }
+            return;
+        }
+        self.paused = true;
+        self.settled = Some((settled, self.step));
+        println!("{} at generation {}", settled, self.step);
+    }
+
+    // Logs the soup the search just finished (saving it if it's interesting) and starts the next one
+    fn finish_soup(&mut self, settled: Option<Settled>) {
+        if self.search.is_none() || !self.cells_readable("Soup search") { return; }
+        let population = self.backend().population();
+        let Some(search) = &mut self.search else { return };
+        let seed = search.seed;
+        let interesting = search.finish(&Outcome { seed, generations: self.step, settled, population });
+        if interesting {
+            #[cfg(not(target_arch = "wasm32"))]
+            self.save_state(&format!("soup_{}.bin", seed));
+        }
+        let next = rand::random();
+        if let Some(search) = &mut self.search { search.restart(next); }
+        self.edit(ReplayEvent::Reset { seed: next, density: self.density });
+    }
+
+    // Enter keeps the current soup and hands it over to the keyboard and mouse
+    fn stop_search(&mut self) {
+        let Some(search) = self.search.take() else { return };
+        self.target_rate = DEFAULT_RATE;
+        println!("Stopped the soup search after {} soups (results in {}), soup {} stays", search.soups, search.path, search.seed);
+    }
+
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        // Don't average the paused time into the rate
+        self.rate_timer = Instant::now();
+        self.rate_start_step = self.step;
+        if !self.paused {
+            self.settled = None;
+            self.steady.reset();
+        }
+        println!("{}", if self.paused { "Paused" } else { "Resumed" });
+    }
+
+    // Going between Life-like and Generations rules switches the automaton too; other Generations rules
+    // may not fit the history's snapshots
+    fn set_rule(&mut self, rule: Rule) {
+        self.rule = rule;
+        self.cpu.set_rule(rule);
+        self.gpu.set_rule(rule);
+        self.shadow(move |shadow| shadow.set_rule(rule));
+        let automaton = self.automaton.for_rule(rule);
+        if automaton != self.automaton {
+            self.set_automaton(automaton, 0);
+        } else if self.automaton.state_bits(rule) != self.history.bits() {
+            self.history_packer.discard();
+            self.history.clear(self.automaton.state_bits(rule));
+        }
+    }
+
+    // Y or Tab steps through the panel's rule presets, with Shift backwards; the grid carries on under the new rule
+    fn cycle_rule(&mut self, backwards: bool) {
+        let current = RULE_PRESETS.iter().position(|&(_, text)| text == self.rule.to_string());
+        let count = RULE_PRESETS.len();
+        let next = match (current, backwards) {
+            (Some(i), false) => (i + 1) % count,
+            (Some(i), true) => (i + count - 1) % count,
+            (None, _) => 0,
+        };
+        let (name, text) = RULE_PRESETS[next];
+        println!("Rule preset: {}", name);
+        self.edit(ReplayEvent::Rule(Rule::parse(text).expect("presets are valid rules")));
+    }
+
+    // Changes made in the overlay panel
+    fn apply(&mut self, action: PanelAction) {
+        match action {
+            PanelAction::TogglePause => self.toggle_pause(),
+            PanelAction::SetRate(rate) => self.target_rate = rate.clamp(1, MAX_RATE),
+            PanelAction::SetRule(rule) => self.edit(ReplayEvent::Rule(rule)),
+            PanelAction::SetDensity(density) => self.density = density,
+            PanelAction::Reseed => self.reset(),
+            PanelAction::ToggleBackend => self.toggle_backend(),
+            PanelAction::SetGrayScott(params) => self.edit(ReplayEvent::GrayScott(params)),
+            PanelAction::SetLenia(params) => self.edit(ReplayEvent::Lenia(params)),
+            PanelAction::SetForestFire(params) => self.edit(ReplayEvent::ForestFire(params)),
+            PanelAction::SaveRule(name, rule) => self.save_rule(&name, rule),
+            PanelAction::SetRegionBrush(brush) => {
+                self.region_brush = brush;
+                match brush {
+                    Some(rule) => println!("Rule brush: {} (left drag paints it, right drag gives tiles back to the grid's rule)", rule),
+                    None => println!("Rule brush: off"),
+                }
+            }
+            PanelAction::SetRegionTint(tint) => {
+                self.region_tint = tint;
+                self.update_camera();
+            }
+            PanelAction::ClearRegions => self.edit(ReplayEvent::Regions(RuleMap::new(self.grid_width, self.grid_height))),
+        }
+    }
+
+    // Kept for this run only in the browser
+    fn save_rule(&mut self, name: &str, rule: Rule) {
+        custom_rules::insert(&mut self.overlay.custom_rules, name, rule);
+        #[cfg(not(target_arch = "wasm32"))]
+        match custom_rules::save(&self.overlay.custom_rules) {
+            Ok(()) => println!("Saved {} as '{}' in {}", rule, name, custom_rules::RULES_FILE),
+            Err(e) => eprintln!("Failed to save the rule: {}", e),
+        }
+    }
+
+    // Live changes to the grid and the settings: logged for --record-session, then made. A replay
+    // owns the grid until its last event
+    fn edit(&mut self, event: ReplayEvent) {
+        if self.replay.is_some() { return; }
+        self.log_event(&event);
+        self.play(event);
+    }
+
+    fn log_event(&mut self, event: &ReplayEvent) {
+        let generation = self.edit_generation(event);
+        if let Some(log) = &mut self.session_log { log.log(generation, event); }
+    }
+
+    // The generation an edit lands on: in CPU mode the thread makes it once it has stepped the generations
+    // already asked for, unless the edit starts it over from the one drawn
+    fn edit_generation(&self, event: &ReplayEvent) -> usize {
+        let restarts = event.jumps() || matches!(event, ReplayEvent::Rewind { .. } | ReplayEvent::Backend { .. } | ReplayEvent::CpuBackend(_));
+        if self.using_cpu && !restarts { self.step + self.cpu.pending() } else { self.step }
+    }
+
+    // Makes a change, whether it comes from the keyboard and mouse or from a replay
+    fn play(&mut self, event: ReplayEvent) {
+        let backend = matches!(event, ReplayEvent::Backend { .. });
+        // Anything else finishes a switch to CPU mode still stepping up to the GPU by reading it back
+        if self.fast_forward.is_some() && !backend { self.set_backend(true); }
+        // An edit at a later generation than the CPU thread's grid would land on it too early
+        if !self.using_cpu && !backend && self.cpu_base != Some(self.step) { self.cpu_base = None; }
+        self.blend_from = self.step + 1;
+        let jumps = event.jumps();
+        match event {
+            ReplayEvent::Fill { x, y, width, height, value } => self.write_rect(x, y, width, &vec![value; width * height]),
+            ReplayEvent::Rect { x, y, width, cells } => self.write_rect(x, y, width, &cells),
+            ReplayEvent::Noise { x, y, radius, seed, density, value } => self.write_noise(x, y, radius, seed, density, value),
+            ReplayEvent::Clear { keep_walls } => self.clear_grid(keep_walls),
+            ReplayEvent::Reset { seed, density } => self.reseed(seed, density),
+            ReplayEvent::Rule(rule) => {
+                self.set_rule(rule);
+                println!("Rule: {}", rule);
+            }
+            ReplayEvent::Boundary(boundary) => self.set_boundary(boundary),
+            ReplayEvent::Automaton { automaton, seed } => self.set_automaton(automaton, seed),
+            ReplayEvent::GrayScott(params) => {
+                self.gray_scott = params;
+                self.cpu.set_gray_scott(params);
+                self.gpu.set_gray_scott(params);
+                self.shadow(move |shadow| shadow.set_gray_scott(params));
+            }
+            ReplayEvent::Lenia(params) => {
+                self.lenia = params;
+                self.cpu.set_lenia(params);
+                self.gpu.set_lenia(params);
+                self.shadow(move |shadow| shadow.set_lenia(params));
+            }
+            ReplayEvent::ForestFire(params) => {
+                self.forest_fire = params;
+                self.cpu.set_forest_fire(params);
+                self.gpu.set_forest_fire(params);
+                self.shadow(move |shadow| shadow.set_forest_fire(params));
+            }
+            ReplayEvent::CpuBackend(backend) => self.set_cpu_backend(backend),
+            ReplayEvent::Backend { cpu } => self.set_backend(cpu),
+            ReplayEvent::Transform(transform) => self.apply_transform(transform),
+            ReplayEvent::Rewind { to } => {
+                let (bits, len) = (self.automaton.state_bits(self.rule), self.cell_count());
+                match self.replay.as_ref().and_then(|replay| replay.rewound(to, bits, len)) {
+                    Some(cells) => self.rewind_to(to, cells),
+                    None => eprintln!("Replay out of step: no snapshot of generation {} to rewind to", to),
+                }
+            }
+            ReplayEvent::Restore { generation, rule, cells } => self.restore(generation, rule, cells),
+            ReplayEvent::Region { x, y, radius, rule } => match Arc::make_mut(&mut self.regions).paint(x, y, radius, rule) {
+                Ok(()) => self.apply_regions(),
+                Err(e) => eprintln!("Rule brush: {}", e),
+            },
+            ReplayEvent::Regions(map) if (map.columns(), map.rows()) != (self.regions.columns(), self.regions.rows()) => {
+                eprintln!("Rule map: {}x{} tiles don't fit the grid's {}x{}", map.columns(), map.rows(), self.regions.columns(), self.regions.rows());
+            }
+            ReplayEvent::Regions(map) => {
+                self.regions = Arc::new(map);
+                self.apply_regions();
+                println!("Rule map: {} painted rules", self.regions.rules().len());
+            }
+        }
+        if jumps { self.snapshot_for_replay(); }
+    }
+
+    // Plays the replay's events for the current generation; the controls come back after the last one
+    fn play_due_events(&mut self) {
+        while let Some(event) = self.replay.as_mut().and_then(|replay| replay.next_due(self.step)) {
+            self.log_event(&event);
+            self.play(event);
+        }
+        if self.replay.as_ref().is_some_and(|replay| replay.remaining() == 0) {
+            self.replay = None;
+            println!("Replay finished at generation {}", self.step);
+        }
+    }
+
+    // Keeps the cells of a generation the replay later rewinds to, where the history would have kept them
+    fn snapshot_for_replay(&mut self) {
+        if !self.replay.as_ref().is_some_and(|replay| replay.needs_snapshot(self.step)) { return; }
+        let cells = self.backend().cells().to_vec();
+        let bits = self.automaton.state_bits(self.rule);
+        if let Some(replay) = &mut self.replay { replay.snapshot(self.step, &cells, bits); }
+    }
+
+    // The diff view compares with the other GPU buffer, which CPU mode fills from then on
+    fn cycle_display(&mut self) {
+        self.display = self.display.next();
+        // Deaths from before the diff view went off would fade in where they no longer are
+        if self.display == DisplayMode::Diff {
+            let mut encoder = self.device.create_command_encoder(&Default::default());
+            self.fader.clear(&mut encoder);
+            self.queue.submit(Some(encoder.finish()));
+        }
+        self.update_camera();
+        println!("Display: {}", self.display);
+    }
+
+    // Unsupported modes are skipped, so this never hands the surface one it rejects
+    fn cycle_present_mode(&mut self) {
+        self.config.present_mode = present::next(self.config.present_mode, &self.present_modes);
+        self.surface.configure(&self.device, &self.config);
+        println!("Present mode: {}", present::name(self.config.present_mode));
+    }
+
+    fn cycle_boundary(&mut self) {
+        self.edit(ReplayEvent::Boundary(self.boundary.next()));
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) {
+        self.boundary = boundary;
+        self.cpu.set_boundary(self.boundary);
+        self.gpu.set_boundary(self.boundary);
+        let boundary = self.boundary;
+        self.shadow(move |shadow| shadow.set_boundary(boundary));
+        println!("Boundary: {}", self.boundary);
+    }
+
+    fn cycle_automaton(&mut self) {
+        self.edit(ReplayEvent::Automaton { automaton: self.automaton.next(), seed: rand::random() });
+    }
+
+    // Carries the pattern over: live Life cells start out firing, firing Brian's Brain cells come back at age 1
+    fn set_automaton(&mut self, automaton: Automaton, seed: u64) {
+        if !self.cells_readable("Switching automata") { return; }
+        let from = self.automaton;
+        self.automaton = automaton;
+        let mut cells: Vec<u32> = self.backend().cells().iter().map(|&cell| self.automaton.adopt(from, cell)).collect();
+        // Everything arrives as one species otherwise
+        if self.automaton == Automaton::Immigration { split_species(&mut cells, seed); }
+        // Rebuilt rather than switched, since the bit-packed backend can't hold Gray-Scott cells
+        let mut cpu = self.cpu_backend.create(self.grid_width, self.grid_height, self.rule, self.boundary, self.automaton, cells.clone());
+        cpu.set_topology(self.topology);
+        cpu.set_ltl_rule(self.ltl);
+        cpu.set_gray_scott(self.gray_scott);
+        cpu.set_cyclic_rule(self.cyclic);
+        cpu.set_rps_threshold(self.rps_threshold);
+        cpu.set_lenia(self.lenia);
+        cpu.set_forest_fire(self.forest_fire);
+        cpu.set_ant_rule(self.ant_rule);
+        self.cpu.jump(Some(cpu));
+        self.sync_generation();
+        self.gpu.set_automaton(self.automaton);
+        let automaton = self.automaton;
+        self.shadow(move |shadow| shadow.set_automaton(automaton));
+        self.write_cells(0, &cells);
+        if let Some(split) = &mut self.split { split.restart(self.step); }
+        // Snapshots of the old automaton can't be loaded into the new one
+        self.restart_history(&cells);
+        println!("Automaton: {}", self.automaton);
+    }
+
+    // The theme's background fills the letterbox bars around the square grid; the minimap, if given, is a second
+    // draw into its own rectangle. The clear covers the whole target, the draws only the pixels under the grid,
+    // so the fragment shader never runs for the bars
+    fn encode_render(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, target: (u32, u32), minimap: Option<(f64, f64, f64, f64)>, timestamp_writes: Option<wgpu::RenderPassTimestampWrites>) {
+        // The split view draws the CPU side into the left half and the GPU's into the right, each with its minimap
+        let gpu = (&self.render_bind_groups[self.gpu.parity()], &self.camera_bind_group);
+        let panes = match &self.split {
+            Some(split) => vec![(&split.render_bind_group, &split.camera_bind_group), gpu],
+            None => vec![gpu],
+        };
+        self.encode_panes(encoder, view, target, &panes, minimap, timestamp_writes);
+    }
+
+    // Draws each (cells, camera) pair of bind groups into its share of `target`, left to right
+    fn encode_panes(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, target: (u32, u32), panes: &[(&wgpu::BindGroup, &wgpu::BindGroup)], minimap: Option<(f64, f64, f64, f64)>, timestamp_writes: Option<wgpu::RenderPassTimestampWrites>) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.themes[self.theme].1.clear_color()),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes,
+            occlusion_query_set: None,
+        });
+        let pane = (target.0 / panes.len() as u32, target.1);
+        rpass.set_bind_group(2, &self.stamp_bind_group, &[]);
+        for (index, &(cells, camera)) in panes.iter().enumerate() {
+            let offset = (index as u32 * pane.0) as f64;
+            let (left, top, width, height) = camera::viewport(pane, self.grid());
+            rpass.set_viewport((offset + left) as f32, top as f32, width as f32, height as f32, 0.0, 1.0);
+            let (x, y, width, height) = camera::pixels_covered((offset + left, top, width, height), target);
+            rpass.set_scissor_rect(x, y, width, height);
+            rpass.set_pipeline(&self.render_pipeline);
+            rpass.set_bind_group(0, cells, &[]);
+            rpass.set_bind_group(1, camera, &[]);
+            rpass.draw(0..6, 0..1);
+
+            if let Some((left, top, width, height)) = minimap {
+                rpass.set_viewport((offset + left) as f32, top as f32, width as f32, height as f32, 0.0, 1.0);
+                rpass.set_scissor_rect((offset + left) as u32, top as u32, width as u32, height as u32);
+                rpass.set_pipeline(&self.minimap_pipeline);
+                rpass.draw(0..6, 0..1);
+            }
+        }
+    }
+
+    // Where the minimap goes on the window, if it's on and the view doesn't already show the whole grid
+    fn minimap_rect(&self) -> Option<(f64, f64, f64, f64)> {
+        if !self.minimap || self.camera.zoom <= 1.0 { return None; }
+        camera::minimap(self.pane_size(), self.grid())
+    }
+
+    // The key list over the frame; it pauses nothing, and the same key or Escape closes it
+    fn toggle_help(&mut self, keybinds: &Keybinds) {
+        self.help = match self.help {
+            Some(_) => None,
+            None => Some(keybinds.listing()),
+        };
+    }
+
+    // What an action's setting is now, shown next to its keys in the help
+    fn setting(&self, action: Action) -> Option<String> {
+        let on = |on: bool| if on { "on" } else { "off" }.to_string();
+        Some(match action {
+            Action::ToggleBackend => if self.using_cpu { "CPU" } else { "GPU" }.to_string(),
+            Action::ToggleSplit => on(self.split.is_some()),
+            Action::CycleCpuBackend => self.cpu_backend_in_use(),
+            Action::Pause => if self.paused { "paused" } else { "running" }.to_string(),
+            Action::TogglePanel => on(self.overlay.visible),
+            Action::ToggleHud => on(self.overlay.hud),
+            Action::CycleDisplay => self.display.to_string(),
+            Action::CycleTheme => self.themes[self.theme].0.clone(),
+            Action::ToggleMinimap => on(self.minimap),
+            Action::ToggleChunkOverlay => on(self.chunk_overlay),
+            Action::ToggleChunkSkipping => on(self.gpu.chunk_skipping()),
+            Action::ToggleSeam => on(self.seam),
+            Action::ToggleInterpolation => on(self.interpolating),
+            Action::ToggleFollow => on(self.following),
+            Action::ToggleRain => on(self.raining),
+            Action::ToggleMutation => on(self.mutating),
+            Action::Record => on(self.recorder.is_some()),
+            Action::CycleBoundary => self.boundary.to_string(),
+            Action::CyclePresentMode => present::name(self.config.present_mode).to_string(),
+            Action::CycleAutomaton => self.automaton.to_string(),
+            Action::CycleRule | Action::CycleRuleAndReset => self.rule.to_string(),
+            Action::Faster | Action::Slower => format!("{} gen/s, at most {} per frame", self.target_rate, MAX_STEPS_PER_FRAME),
+            _ => return None,
+        })
+    }
+
+    fn toggle_follow(&mut self) {
+        self.following = !self.following;
+        self.follow_target = None;
+        println!("Follow: {}", if self.following { "on" } else { "off" });
+    }
+
+    // Changes are measured from the cell opposite the view's center, so activity on screen never straddles
+    // the seam; what does is on the far side of the torus, where a jump is no worse than anything else
+    fn activity_origin(&self) -> (u32, u32) {
+        let (width, height) = self.grid();
+        let x = (self.camera.center[0] as f64 + width as f64 / 2.0).rem_euclid(width as f64) as u32;
+        let y = (self.camera.center[1] as f64 + height as f64 / 2.0).rem_euclid(height as f64) as u32;
+        (x.min(width - 1), y.min(height - 1))
+    }
+
+    // Eases the view toward the activity, or onto the bounding box while End's fit is on its way, frame
+    // rate independently
+    fn follow(&mut self, elapsed: Duration) {
+        let fraction = 1.0 - (-elapsed.as_secs_f64() / FOLLOW_TIME).exp();
+        if let Some((center, zoom)) = self.fitting {
+            if self.camera.ease_to_fit(center, zoom, fraction, self.grid()) { self.fitting = None; }
+            self.update_camera();
+            return;
+        }
+        let Some(target) = self.follow_target.filter(|_| self.following) else { return };
+        self.camera.ease_toward(target, fraction, self.grid());
+        self.update_camera();
+    }
+
+    // The whole of the live cells in view with a margin around them; an empty grid leaves the view alone
+    fn fit_bounds(&mut self) {
+        if self.using_cpu { self.bounds = bounds::of_cells(self.cpu.cells(), self.grid_width as usize); }
+        let Some(bounds) = self.bounds else {
+            println!("Nothing alive to fit the view to");
+            return;
+        };
+        let zoom = Camera::fit_zoom((bounds.width as f64, bounds.height as f64), FIT_MARGIN, self.grid());
+        self.fitting = Some((bounds.center(), zoom));
+        self.request_redraw();
+    }
+
+    fn toggle_minimap(&mut self) {
+        self.minimap = !self.minimap;
+        println!("Minimap: {}", if self.minimap { "on" } else { "off" });
+    }
+
+    fn toggle_chunk_overlay(&mut self) {
+        self.chunk_overlay = !self.chunk_overlay;
+        self.update_camera();
+        let note = if self.gpu.chunk_skipping() { "" } else { " (chunk skipping is off, Ctrl+F4 turns it on)" };
+        println!("Chunk overlay: {}{}", if self.chunk_overlay { "on" } else { "off" }, note);
+    }
+
+    fn cycle_theme(&mut self) {
+        self.theme = (self.theme + 1) % self.themes.len();
+        let (name, theme) = &self.themes[self.theme];
+        self.queue.write_buffer(&self.palette_buffer, 0, bytemuck::bytes_of(&theme.uniform()));
+        println!("Theme: {}", name);
+    }
+
+    fn toggle_interpolation(&mut self) {
+        self.interpolating = !self.interpolating;
+        self.update_camera();
+        println!("Slow-motion blending: {}", if self.interpolating { format!("on, up to {} gen/s", MAX_BLEND_RATE) } else { "off".to_string() });
+    }
+
+    // How far the frame fades from the previous generation to the latest: the fixed timestep's progress toward
+    // the next one. Crisp while paused (single steps included), when too fast to see, and until a generation
+    // after an edit, which leaves the other buffer out of step with the drawn one
+    fn blend(&self) -> f32 {
+        if !self.interpolating || self.paused || self.target_rate > MAX_BLEND_RATE || self.step < self.blend_from { return 1.0; }
+        self.owed.fract() as f32
+    }
+
+    // The CPU side uploads the generation before its latest too when something draws it
+    fn wants_previous(&self) -> bool {
+        self.display == DisplayMode::Diff || self.interpolating
+    }
+
+    fn toggle_seam(&mut self) {
+        self.seam = !self.seam;
+        self.update_camera();
+        println!("Seam: {}", if self.seam { "shown" } else { "hidden" });
+    }
+
+    // Computing every cell again shows what skipping the settled chunks saves in the HUD's GPU time
+    fn toggle_chunk_skipping(&mut self) {
+        let on = !self.gpu.chunk_skipping();
+        match self.gpu.set_chunk_skipping(on) {
+            Ok(()) => println!("Chunk skipping: {}", if on { "on" } else { "off" }),
+            Err(e) => eprintln!("Chunk skipping stays off: {}", e),
+        }
+    }
+
+    // Starts a new file each time; the first one goes to --record if given
+    fn toggle_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            let path = recorder.path.clone();
+            let frames = recorder.finish(&self.device);
+            println!("Finished recording {} ({} frames)", path, frames);
+            return;
+        }
+        let path = self.record_path.take().unwrap_or_else(|| format!("life_{}.mp4", self.step));
+        let size = self.record_size.unwrap_or(self.window_size());
+        match Recorder::start(&self.device, path, size, self.config.format) {
+            Ok(recorder) => self.recorder = Some(recorder),
+            Err(e) => eprintln!("Failed to start recording: {}", e),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.edit(ReplayEvent::Reset { seed: rand::random(), density: self.density });
+    }
+
+    // Fresh random soup from `seed` (or the demo circuit in Wireworld), restarting the generation count;
+    // a settled grid starts running again
+    fn reseed(&mut self, seed: u64, density: f32) {
+        self.density = density;
+        self.restored = None;
+        let shaped = if self.automaton == Automaton::Wireworld {
+            Some(demo_circuit(self.grid_width, self.grid_height))
+        } else {
+            println!("Seed: {}", seed);
+            shaped_soup(self.grid_width, self.grid_height, seed, density, self.automaton, self.ants, self.cyclic, self.lenia)
+        };
+        self.cpu.jump(None);
+        // A plain soup is hashed on the GPU instead of uploaded. The CPU side hashes the same one only when it
+        // steps or checks the grid; otherwise its grid falls behind and CPU mode reads the GPU's back
+        let grid = match shaped {
+            Some(mut grid) => {
+                self.walls.apply(&mut grid);
+                self.write_cells(0, &grid);
+                Some(grid)
+            }
+            None => {
+                self.gpu.write_soup(seed, density);
+                let hashed = (self.using_cpu || self.split.is_some() || self.verifier.is_some()).then(|| {
+                    let mut grid = random_grid(self.cell_count(), seed, density);
+                    self.walls.apply(&mut grid);
+                    grid
+                });
+                match &hashed {
+                    Some(grid) => {
+                        self.cpu.set_cells(0, grid);
+                        if let Some(split) = &self.split { split.upload(&self.queue, 0, grid, false); }
+                    }
+                    None => self.cpu_base = None,
+                }
+                hashed
+            }
+        };
+        self.step = 0;
+        self.rate_start_step = 0;
+        self.sync_generation();
+        if let Some(split) = &mut self.split { split.restart(0); }
+        if let (Some(verifier), Some(grid)) = (&mut self.verifier, &grid) { verifier.restart(0, grid); }
+        match &grid {
+            Some(grid) => self.restart_history(grid),
+            None => self.restart_gpu_history(),
+        }
+        self.steady.reset();
+        if self.settled.take().is_some() { self.paused = false; }
+    }
+
+    // One cell per press, SHIFT_STRIDE with Shift held
+    fn shift_grid(&mut self, dx: i32, dy: i32, shift: bool) {
+        let stride = if shift { SHIFT_STRIDE } else { 1 };
+        self.transform_grid(Transform::Shift { dx: dx * stride, dy: dy * stride });
+    }
+
+    fn transform_grid(&mut self, transform: Transform) {
+        if let Err(e) = transform.check(self.grid_width, self.grid_height) {
+            eprintln!("{}", e);
+            return;
+        }
+        self.edit(ReplayEvent::Transform(transform));
+    }
+
+    // The GPU moves its cells between the ping-pong buffers; CPU mode moves them on the host and uploads
+    // them as for any other write
+    fn apply_transform(&mut self, transform: Transform) {
+        let (width, height) = (self.grid_width as usize, self.grid_height as usize);
+        if self.using_cpu {
+            self.cpu.transform(transform);
+            self.gpu.set_cells(0, self.cpu.cells());
+        } else {
+            self.gpu.transform(transform);
+        }
+        self.walls.transform(transform, width, height);
+        self.shadow(move |shadow| {
+            let cells = transform.apply(shadow.cells(), width, height);
+            shadow.set_cells(0, &cells);
+        });
+        println!("Grid: {}", transform);
+    }
+
+    fn clear(&mut self, keep_walls: bool) {
+        self.edit(ReplayEvent::Clear { keep_walls });
+    }
+
+    // Empties the grid, keeping the walls unless told otherwise so a maze can be run again
+    fn clear_grid(&mut self, keep_walls: bool) {
+        let mut grid = vec![0; self.cell_count()];
+        if keep_walls { self.walls.apply(&mut grid); }
+        self.write_cells(0, &grid);
+        println!("Cleared the grid{}", if keep_walls { ", kept the walls" } else { "" });
+    }
+
+    // Left button paints live cells (or places the pending stamp, selects with Shift, sprinkles soup with Ctrl, or paints the armed rule brush), right button clears them (or the rule brush's tiles), middle button pans
+    fn mouse_input(&mut self, button_state: ElementState, button: MouseButton, shift: bool, alt: bool, ctrl: bool) {
+        // A click on the minimap moves the view there instead
+        if button_state == ElementState::Pressed && button == MouseButton::Left && self.minimap_rect().is_some() {
+            if let Some(cell) = self.cursor.and_then(|cursor| camera::minimap_cell(self.pane_position(cursor), self.pane_size(), self.grid())) {
+                self.fitting = None;
+                self.camera.jump_to(cell, self.grid());
+                self.update_camera();
+                return;
+            }
+        }
+        // Presses on the letterbox bars are ignored; releases still go through so drags end
+        let on_grid = self.cursor.is_some_and(|cursor| camera::in_viewport(self.pane_position(cursor), self.pane_size(), self.grid()));
+        if button_state == ElementState::Pressed && !on_grid { return; }
+        if self.stamp.is_some() && button == MouseButton::Left {
+            if button_state == ElementState::Pressed { self.place_stamp(); }
+            return;
+        }
+        if button == MouseButton::Left && (shift || self.selecting) {
+            self.selecting = button_state == ElementState::Pressed;
+            if let (true, Some(cursor)) = (self.selecting, self.cursor) {
+                self.selection = Some(Selection::new(self.window_to_cell(cursor)));
+                self.update_selection();
+            }
+            return;
+        }
+        if button == MouseButton::Left && (ctrl || self.noising) {
+            self.noising = button_state == ElementState::Pressed;
+            if let (true, Some(cursor)) = (self.noising, self.cursor) { self.noise_dab(cursor); }
+            return;
+        }
+        // Only Life reads the map
+        let brush = self.region_brush.filter(|_| self.automaton == Automaton::Life);
+        if let (Some(brush), MouseButton::Left | MouseButton::Right) = (brush, button) {
+            let rule = (button == MouseButton::Left).then_some(brush);
+            self.region_painting = (button_state == ElementState::Pressed).then_some(rule);
+            if let (true, Some(cursor)) = (self.region_painting.is_some(), self.cursor) {
+                let (x, y) = self.window_to_cell(cursor);
+                self.region_dab_at(x, y, rule);
+            }
+            return;
+        }
+        let value = match button {
+            MouseButton::Left if alt => WALL,
+            MouseButton::Left if self.automaton == Automaton::GrayScott => gray_scott::SEED,
+            MouseButton::Left if self.automaton == Automaton::Lenia => lenia::FULL,
+            MouseButton::Left => 1,
+            MouseButton::Right => 0,
+            MouseButton::Middle => {
+                self.panning = button_state == ElementState::Pressed;
+                self.fitting = None;
+                return;
+            }
+            _ => return,
+        };
+        match button_state {
+            ElementState::Pressed => {
+                self.paint_value = Some(value);
+                if let Some(position) = self.cursor { self.paint_stroke(position, position); }
+            }
+            // Left strokes paint cells or walls, whichever Alt said when they started
+            ElementState::Released => {
+                if self.paint_value.is_some_and(|painting| (painting == 0) == (value == 0)) { self.paint_value = None; }
+            }
+        }
+    }
+
+    // `position` is in physical pixels, the same as the surface and `window_size`, so it maps to cells
+    // without the scale factor
+    fn cursor_moved(&mut self, position: PhysicalPosition<f64>) {
+        let last = self.cursor.replace(position);
+        // The cursor's cell in the HUD follows it rather than waiting for the next refresh
+        self.hud_refresh.reset();
+        if self.panning {
+            if let Some(last) = last {
+                // Drag the grid along with the cursor
+                let cells_per_pixel = self.camera.view_cells(self.grid()).0 / camera::viewport(self.pane_size(), self.grid()).2;
+                let dx = (last.x - position.x) * cells_per_pixel;
+                let dy = (last.y - position.y) * cells_per_pixel;
+                self.camera.pan(dx, dy, self.grid());
+                self.update_camera();
+            }
+        }
+        if self.paint_value.is_some() {
+            self.paint_stroke(last.unwrap_or(position), position);
+        }
+        if self.noising {
+            self.noise_stroke(last.unwrap_or(position), position);
+        }
+        if let Some(rule) = self.region_painting {
+            for (x, y) in self.brush_line(last.unwrap_or(position), position) { self.region_dab_at(x, y, rule); }
+        }
+        if self.stamp.is_some() {
+            self.update_stamp_preview();
+        }
+        if self.selecting {
+            let cell = self.window_to_cell(position);
+            if let Some(selection) = &mut self.selection { selection.drag_to(cell); }
+            self.update_selection();
+        }
+    }
+
+    // Zooms, or with Ctrl held resizes the noise and rule brushes
+    fn mouse_wheel(&mut self, delta: MouseScrollDelta, ctrl: bool) {
+        let lines = scroll_lines(delta);
+        if ctrl {
+            // A quarter per notch, but at least one cell
+            let scaled = (self.noise_radius as f64 * 1.25f64.powf(lines)).round() as usize;
+            let radius = if lines > 0.0 { scaled.max(self.noise_radius + 1) } else { scaled.min(self.noise_radius.saturating_sub(1)) };
+            self.noise_radius = radius.clamp(1, MAX_NOISE_RADIUS);
+            println!("Noise and rule brushes: radius {}", self.noise_radius);
+            return;
+        }
+        let pane = self.pane_size();
+        let anchor = self.cursor.map_or((pane.0 as f64 / 2.0, pane.1 as f64 / 2.0), |cursor| self.pane_position(cursor));
+        self.fitting = None;
+        self.camera.zoom_at(1.2f64.powf(lines), anchor, pane, self.grid());
+        self.update_camera();
+    }
+
+    // WASD pans by a tenth of the view
+    fn pan_key(&mut self, dx: f64, dy: f64) {
+        self.fitting = None;
+        let (width, height) = self.camera.view_cells(self.grid());
+        self.camera.pan(dx * width / 10.0, dy * height / 10.0, self.grid());
+        self.update_camera();
+    }
+
+    // The other windows' cameras too, which take the display settings from this one
+    fn update_camera(&self) {
+        let blend = self.blend();
+        let uniform = |camera: Camera| Camera { display: self.display as u32, chunks: self.chunk_overlay as u32, seam: self.seam as u32, blend, regions: self.region_tint as u32, ..camera };
+        self.queue.write_buffer(&self.camera_buffer, 0, bytemuck::bytes_of(&uniform(self.camera)));
+        for viewer in self.viewers.values() {
+            self.queue.write_buffer(&viewer.camera_buffer, 0, bytemuck::bytes_of(&uniform(viewer.camera)));
+        }
+        self.update_stamp_preview();
+    }
+
+    // Pressing the key of the pending stamp again puts it away
+    fn select_stamp(&mut self, index: usize) {
+        let stamp = &self.stamps[index];
+        if self.stamp.as_ref().is_some_and(|pending| pending.name == stamp.name) {
+            self.stamp = None;
+        } else {
+            println!("Stamp: {} (click to place, R rotates, F flips, Esc cancels)", stamp.name);
+            self.stamp = Some(stamp.clone());
+        }
+        self.update_stamp_preview();
+    }
+
+    fn turn_stamp(&mut self, flip: bool) {
+        let Some(stamp) = &mut self.stamp else { return };
+        if flip { stamp.flip() } else { stamp.rotate() }
+        self.update_stamp_preview();
+    }
+
+    // Top-left cell of the pending stamp, centered on the cursor and wrapped onto the torus
+    fn stamp_origin(&self) -> Option<(usize, usize)> {
+        let (stamp, cursor) = (self.stamp.as_ref()?, self.cursor?);
+        let (x, y) = self.window_to_cell(cursor);
+        Some(wrap_cell(x - (stamp.width / 2) as f64, y - (stamp.height / 2) as f64, self.grid_width as usize, self.grid_height as usize))
+    }
+
+    fn update_stamp_preview(&self) {
+        let preview = match (&self.stamp, self.stamp_origin()) {
+            (Some(stamp), Some((x, y))) => {
+                self.queue.write_buffer(&self.stamp_mask_buffer, 0, bytemuck::cast_slice(&stamp.mask()));
+                StampPreview { origin: [x as u32, y as u32], extent: [stamp.width as u32, stamp.height as u32] }
+            }
+            _ => StampPreview::default(),
+        };
+        self.queue.write_buffer(&self.stamp_buffer, 0, bytemuck::bytes_of(&preview));
+    }
+
+    // Writes the whole footprint, dead cells included, so the pattern comes out exactly as drawn
+    fn place_stamp(&mut self) {
+        let (Some(stamp), Some((ox, oy))) = (self.stamp.clone(), self.stamp_origin()) else { return };
+        self.write_stamp(&stamp, ox, oy);
+    }
+
+    // Overwrites whatever is under it, wrapping across the seam
+    fn write_stamp(&mut self, stamp: &Stamp, ox: usize, oy: usize) {
+        if stamp.width > self.grid_width as usize || stamp.height > self.grid_height as usize {
+            eprintln!("The {} is {}x{} but the grid is only {}x{}", stamp.name, stamp.width, stamp.height, self.grid_width, self.grid_height);
+            return;
+        }
+        let cells: Vec<u32> = (0..stamp.height).flat_map(|y| stamp.row(y)).collect();
+        self.edit(ReplayEvent::Rect { x: ox, y: oy, width: stamp.width, cells });
+    }
+
+    fn toggle_rain(&mut self) {
+        self.raining = !self.raining;
+        if !self.raining {
+            println!("Rain: off");
+            return;
+        }
+        let names: Vec<&str> = self.rain_patterns.iter().map(|&index| self.stamps[index].name.as_str()).collect();
+        println!("Rain: on, a pattern every {} generations ({})", self.rain_every, names.join(", "));
+        // A grid that settled before the rain started runs again
+        if self.settled.take().is_some() {
+            self.paused = false;
+            self.steady.reset();
+        }
+    }
+
+    // Drops are edits like any other, so a recorded session replays them without raining itself
+    fn rain(&mut self) {
+        if !self.raining || self.replay.is_some() || self.search.is_some() { return; }
+        let (width, height) = (self.grid_width as usize, self.grid_height as usize);
+        let Some(drop) = self.rain.due(self.step, width, height) else { return };
+        let mut stamp = self.stamps[self.rain_patterns[drop.pattern]].clone();
+        for _ in 0..drop.turns { stamp.rotate(); }
+        if drop.flip { stamp.flip(); }
+        self.write_stamp(&stamp, drop.x, drop.y);
+    }
+
+    // Only the Life rule mutates, the other automata have nothing to flip
+    fn toggle_mutation(&mut self) {
+        if !self.mutating && !matches!(self.automaton, Automaton::Life | Automaton::Generations) {
+            println!("Mutation only changes Life-like rules");
+            return;
+        }
+        self.mutating = !self.mutating;
+        if !self.mutating {
+            println!("Mutation: locked at {}", self.rule);
+            return;
+        }
+        self.mutation.restart(self.step);
+        println!("Mutation: on, a rule bit flips every {} generations", self.mutation.every());
+        if self.settled.take().is_some() {
+            self.paused = false;
+            self.steady.reset();
+        }
+    }
+
+    // Mutations are rule changes like any other, so a recorded session replays them without mutating itself
+    fn mutate(&mut self) {
+        if !self.mutating || self.replay.is_some() || self.search.is_some() { return; }
+        if !matches!(self.automaton, Automaton::Life | Automaton::Generations) { return; }
+        let Some(rule) = self.mutation.due(self.step, self.rule, self.population_count) else { return };
+        println!("Generation {}: {} mutates", self.step, self.rule);
+        self.edit(ReplayEvent::Rule(rule));
+    }
+
+    // Hooks are edits like any other, so like rain and mutation they stay out of replays and soup searches
+    fn run_hooks(&mut self, population: Option<u32>) {
+        if self.hooks.is_empty() || self.replay.is_some() || self.search.is_some() { return; }
+        let stats = hooks::Stats { generation: self.step, population, cells: self.cell_count(), settled: self.settled.is_some() };
+        for hook in self.hooks.check(&stats) {
+            println!("Generation {}: hook \"{}\"", self.step, hook);
+            self.run_hook(hook.action);
+        }
+    }
+
+    fn run_hook(&mut self, action: HookAction) {
+        match action {
+            HookAction::Reseed => self.reset(),
+            #[cfg(not(target_arch = "wasm32"))]
+            HookAction::Screenshot => self.screenshot(),
+            #[cfg(not(target_arch = "wasm32"))]
+            HookAction::SaveState => self.save_state(&format!("life_{}.bin", self.step)),
+            #[cfg(target_arch = "wasm32")]
+            HookAction::Screenshot | HookAction::SaveState => println!("No files in the browser"),
+            // A new rule gets a grid that can settle again
+            HookAction::NextRule if matches!(self.automaton, Automaton::Life | Automaton::Generations) => {
+                self.cycle_rule(false);
+                self.steady.reset();
+                self.settled = None;
+            }
+            HookAction::NextRule => println!("next_rule only changes Life-like rules"),
+            HookAction::Pause => {
+                if !self.paused { self.toggle_pause(); }
+            }
+            HookAction::Quit => self.quitting = true,
+        }
+    }
+
+    // Z, or a population dying out under the latest mutation
+    fn revert_mutation(&mut self) {
+        if self.replay.is_some() { return; }
+        let Some(rule) = self.mutation.revert(self.step) else {
+            println!("No mutation to take back");
+            return;
+        };
+        println!("Generation {}: back to {}", self.step, rule);
+        self.edit(ReplayEvent::Rule(rule));
+    }
+
+    fn update_selection(&self) {
+        let rect = match self.selection {
+            Some(selection) => {
+                let (x, y, width, height) = selection.rect(self.grid());
+                SelectionRect { origin: [x as u32, y as u32], extent: [width as u32, height as u32] }
+            }
+            None => SelectionRect::default(),
+        };
+        self.queue.write_buffer(&self.selection_buffer, 0, bytemuck::bytes_of(&rect));
+    }
+
+    // Keeps the cells for Ctrl+V and puts their RLE on the OS clipboard; cutting also clears them
+    fn copy_selection(&mut self, cut: bool) {
+        let Some(selection) = self.selection else { return };
+        if !self.cells_readable("Copying") { return; }
+        let (x, y, width, height) = selection.rect(self.grid());
+        // Only the rectangle is read back in GPU mode
+        let cells = self.backend().read_rect(x, y, width, height);
+        if cut {
+            self.edit(ReplayEvent::Fill { x, y, width, height, value: 0 });
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.copy_to_os_clipboard(width, &cells);
+        println!("{} {}x{} cells", if cut { "Cut" } else { "Copied" }, width, height);
+        self.clip = Some(Clip { width, height, cells });
+    }
+
+    // As RLE, so other Life programs can paste it
+    #[cfg(not(target_arch = "wasm32"))]
+    fn copy_to_os_clipboard(&mut self, width: usize, cells: &[u32]) {
+        let states: Vec<u32> = cells.iter().map(|&cell| self.automaton.pattern_state(cell)).collect();
+        let rle = Pattern::from_cells(width, &states, Some(self.rle_rule())).to_rle();
+        let Some(clipboard) = self.os_clipboard() else { return };
+        match clipboard.set_text(rle.clone()) {
+            Ok(()) => self.copied_rle = Some(rle),
+            Err(e) => eprintln!("Failed to copy to the OS clipboard: {}", e),
+        }
+    }
 
-// Config
-// const GRID_SIZE: u32 = 1024;
-const GRID_SIZE: u32 = 1024 * 4;
-const WORKGROUP_SIZE: u32 = 8;
+    // Created on first use
+    #[cfg(not(target_arch = "wasm32"))]
+    fn os_clipboard(&mut self) -> Option<&mut arboard::Clipboard> {
+        if self.os_clipboard.is_none() {
+            match arboard::Clipboard::new() {
+                Ok(clipboard) => self.os_clipboard = Some(clipboard),
+                Err(e) => eprintln!("No OS clipboard, only cells copied here can be pasted: {}", e),
+            }
+        }
+        self.os_clipboard.as_mut()
+    }
 
-struct GraphicsState {
-    window: Arc<Window>,
-    surface: wgpu::Surface<'static>,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
-    config: wgpu::SurfaceConfiguration,
-    compute_pipeline: wgpu::ComputePipeline,
-    render_pipeline: wgpu::RenderPipeline,
-    bind_group_a: wgpu::BindGroup,
-    bind_group_b: wgpu::BindGroup,
-    buffer_a: wgpu::Buffer,
-    buffer_b: wgpu::Buffer,
-    cpu_buffer: Vec<u32>,
-    using_cpu: bool,
-    step: usize,
-}
+    // The rule line of the RLE we write
+    #[cfg(not(target_arch = "wasm32"))]
+    fn rle_rule(&self) -> String {
+        match self.automaton {
+            Automaton::Wireworld => "WireWorld".to_string(),
+            _ => self.rule.to_string(),
+        }
+    }
 
-impl GraphicsState {
-    fn resize(&mut self, new_size: PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
-        }
-    }
-
-    fn compute_cpu(&mut self) {
-        let size = GRID_SIZE as usize;
-        let input = &self.cpu_buffer;
-        
-        let next_state: Vec<u32> = (0..input.len()).into_par_iter().map(|index| {
-            let x = index % size;
-            let y = index / size;
-            
-            let mut neighbors = 0;
-            for i in -1..=1 {
-                for j in -1..=1 {
-                    if i == 0 && j == 0 { continue; }
-                    let nx = (x as i32 + i + size as i32) as usize % size;
-                    let ny = (y as i32 + j + size as i32) as usize % size;
-                    neighbors += input[ny * size + nx];
-                }
+    // Ctrl+V: RLE copied in another program becomes a pending stamp; our own copy pastes the exact cells
+    fn paste(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let text = self.os_clipboard().and_then(|clipboard| clipboard.get_text().ok()).map(|text| text.replace("\r\n", "\n"));
+            if let Some(text) = text.filter(|text| self.copied_rle.as_ref() != Some(text)) {
+                self.stamp_from_rle(&text);
+                return;
             }
-            
-            let status = input[index];
-            if status == 1 && (neighbors < 2 || neighbors > 3) {
-                0
-            } else if status == 0 && neighbors == 3 {
-                1
-            } else {
-                status
+        }
+        self.paste_clip();
+    }
+
+    // Malformed text only warns, like a bad pattern file
+    #[cfg(not(target_arch = "wasm32"))]
+    fn stamp_from_rle(&mut self, text: &str) {
+        let pattern = match stamp::clipboard_pattern(text, self.grid_width as usize, self.grid_height as usize) {
+            Ok(pattern) => pattern,
+            Err(e) => { eprintln!("Can't paste from the clipboard: {}", e); return; }
+        };
+        if let Some(rule) = &pattern.rule {
+            let active = self.rle_rule();
+            if !rule.eq_ignore_ascii_case(&active) && !Rule::parse(rule).is_ok_and(|rule| rule.to_string() == active) {
+                eprintln!("warning: the pasted pattern is for rule {} but {} is running", rule, active);
             }
-        }).collect();
+        }
+        let stamp = Stamp::from_pattern("clipboard pattern".to_string(), &pattern);
+        self.fit_stamp_mask(stamp.mask().len());
+        println!("Stamp: {}x{} pattern from the clipboard (click to place, R rotates, F flips, Esc cancels)", stamp.width, stamp.height);
+        self.stamp = Some(stamp);
+        self.update_stamp_preview();
+    }
+
+    // Grows the preview mask for a stamp of `len` words
+    #[cfg(not(target_arch = "wasm32"))]
+    fn fit_stamp_mask(&mut self, len: usize) {
+        let size = (len * 4) as u64;
+        if size <= self.stamp_mask_buffer.size() { return; }
+        self.stamp_mask_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Stamp Mask"), size, usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST, mapped_at_creation: false,
+        });
+        self.stamp_bind_group = stamp_bind_group(&self.device, &self.stamp_bind_group_layout, &self.stamp_buffer, &self.stamp_mask_buffer, &self.selection_buffer);
+    }
+
+    // The clip's top-left corner goes under the cursor
+    fn paste_clip(&mut self) {
+        let (Some(clip), Some(cursor)) = (self.clip.take(), self.cursor) else { return };
+        let (x, y) = self.window_to_cell(cursor);
+        let (x, y) = wrap_cell(x, y, self.grid_width as usize, self.grid_height as usize);
+        self.edit(ReplayEvent::Rect { x, y, width: clip.width, cells: clip.cells.clone() });
+        println!("Pasted {}x{} cells at ({}, {})", clip.width, clip.height, x, y);
+        self.clip = Some(clip);
+    }
+
+    // Unwrapped cell coordinates under the cursor; the quad stretches the view over the whole window
+    // Odd hex rows are drawn half a cell right
+    fn window_to_cell(&self, position: PhysicalPosition<f64>) -> (f64, f64) {
+        let (x, y) = self.camera.window_to_cell(self.pane_position(position), self.pane_size(), self.grid());
+        (x - self.topology.row_shift(y.floor() as i64), y)
+    }
+
+    fn window_size(&self) -> (u32, u32) {
+        (self.config.width, self.config.height)
+    }
+
+    // Where the grid is drawn: the window, or one half of it in the split view
+    fn pane_size(&self) -> (u32, u32) {
+        let panes = if self.split.is_some() { 2 } else { 1 };
+        (self.config.width / panes, self.config.height)
+    }
+
+    // A window position as the same spot in the left pane; both show the same cells
+    fn pane_position(&self, position: PhysicalPosition<f64>) -> (f64, f64) {
+        let width = self.pane_size().0 as f64;
+        let x = if self.split.is_some() && position.x >= width { position.x - width } else { position.x };
+        (x, position.y)
+    }
+
+    // Paints along the line between two cursor positions so fast drags don't leave gaps
+    fn paint_stroke(&mut self, from: PhysicalPosition<f64>, to: PhysicalPosition<f64>) {
+        let Some(value) = self.paint_value else { return };
+        let (width, height) = (self.grid_width as usize, self.grid_height as usize);
+
+        // Cover every cell under one screen pixel, otherwise strokes are invisible when zoomed out
+        let view = self.camera.view_cells(self.grid()).0;
+        let cells_per_pixel = view / camera::viewport(self.pane_size(), self.grid()).2;
+        let brush = cells_per_pixel.ceil().max(1.0) as usize;
+
+        let (fx, fy) = self.window_to_cell(from);
+        let (tx, ty) = self.window_to_cell(to);
+        let steps = ((tx - fx).abs().max((ty - fy).abs()) / brush as f64).ceil().max(1.0) as usize;
+        for i in 0..=steps {
+            let t = i as f64 / steps as f64;
+            // Wrap onto the torus, like the view does; a brush on the seam carries on across it
+            let (x, y) = wrap_cell(fx + (tx - fx) * t, fy + (ty - fy) * t, width, height);
+            self.edit(ReplayEvent::Fill { x, y, width: brush.min(width), height: brush.min(height), value });
+        }
+    }
+
+    // Noise dabs along the line between two cursor positions; `from` already got one
+    fn noise_stroke(&mut self, from: PhysicalPosition<f64>, to: PhysicalPosition<f64>) {
+        for (x, y) in self.brush_line(from, to) { self.noise_dab_at(x, y); }
+    }
+
+    // Cells along the line between two cursor positions a brush radius apart, `from` left out
+    fn brush_line(&self, from: PhysicalPosition<f64>, to: PhysicalPosition<f64>) -> Vec<(f64, f64)> {
+        let (fx, fy) = self.window_to_cell(from);
+        let (tx, ty) = self.window_to_cell(to);
+        let steps = ((tx - fx).abs().max((ty - fy).abs()) / self.noise_radius as f64).ceil() as usize;
+        (1..=steps).map(|i| {
+            let t = i as f64 / steps as f64;
+            (fx + (tx - fx) * t, fy + (ty - fy) * t)
+        }).collect()
+    }
+
+    fn noise_dab(&mut self, position: PhysicalPosition<f64>) {
+        let (x, y) = self.window_to_cell(position);
+        self.noise_dab_at(x, y);
+    }
+
+    // A fresh seed per dab, logged with it so a replay sprinkles the same cells
+    fn noise_dab_at(&mut self, x: f64, y: f64) {
+        let (width, height) = (self.grid_width as usize, self.grid_height as usize);
+        let (x, y) = wrap_cell(x, y, width, height);
+        let value = match self.automaton {
+            Automaton::GrayScott => gray_scott::SEED,
+            Automaton::Lenia => lenia::FULL,
+            _ => 1,
+        };
+        self.edit(ReplayEvent::Noise { x, y, radius: self.noise_radius, seed: rand::random(), density: self.density, value });
+    }
+
+    // Dabs that wouldn't change a tile stay out of the session log, since a stroke goes over the same
+    // tiles many times
+    fn region_dab_at(&mut self, x: f64, y: f64, rule: Option<Rule>) {
+        let (x, y) = wrap_cell(x, y, self.grid_width as usize, self.grid_height as usize);
+        if self.regions.painted(x, y, self.noise_radius, rule) { return; }
+        self.edit(ReplayEvent::Region { x, y, radius: self.noise_radius, rule });
+    }
+
+    // A disk of soup written row by row, so only those rows are uploaded; walls under it stay
+    fn write_noise(&mut self, x: usize, y: usize, radius: usize, seed: u64, density: f32, value: u32) {
+        let width = self.grid_width as usize;
+        for (left, row, mut cells) in soup::disk(width, self.grid_height as usize, x, y, radius, seed, density, value) {
+            for (dx, cell) in cells.iter_mut().enumerate() {
+                if self.walls.contains(row * width + (left + dx) % width) { *cell = WALL; }
+            }
+            self.write_rect(left, row, cells.len(), &cells);
+        }
+    }
+
+    // Blocking readback in GPU mode, then encoded and written off the event loop
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_state(&self, path: &str) {
+        let snapshot = Snapshot { width: self.grid_width, height: self.grid_height, generation: self.step as u64, rule: self.rule, regions: self.saved_regions(), settings: Some(self.settings()), cells: self.backend().cells().to_vec() };
+        let path = path.to_string();
+        std::thread::spawn(move || match snapshot.save(Path::new(&path)) {
+            Ok(()) => println!("Saved generation {} to {}", snapshot.generation, path),
+            Err(e) => eprintln!("Failed to save state: {}", e),
+        });
+    }
+
+    // What a saved state runs under besides its rule, restored by --load and --resume
+    #[cfg(not(target_arch = "wasm32"))]
+    fn settings(&self) -> Settings {
+        Settings {
+            automaton: self.automaton, boundary: self.boundary, topology: self.topology, ltl: self.ltl, gray_scott: self.gray_scott,
+            ant_rule: self.ant_rule, cyclic: self.cyclic, rps_threshold: self.rps_threshold, lenia: self.lenia, forest_fire: self.forest_fire,
+        }
+    }
+
+    // The buffers are allocated for one grid size, so only states of that size can be restored in place
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_state(&mut self, path: &Path) {
+        let snapshot = match Snapshot::load(path) {
+            Ok(snapshot) => snapshot,
+            Err(e) => { eprintln!("Failed to load state: {}", e); return; }
+        };
+        if (snapshot.width, snapshot.height) != self.grid() {
+            eprintln!("Failed to load state: it is {}x{} but the grid is {}x{} (restart with --load)", snapshot.width, snapshot.height, self.grid_width, self.grid_height);
+            return;
+        }
+        println!("Restoring generation {} ({}) from {}", snapshot.generation, snapshot.rule, path.display());
+        if snapshot.settings.is_some_and(|settings| settings != self.settings()) {
+            println!("It was saved as {} under other settings, which only --load brings back", snapshot.settings.unwrap().automaton);
+        }
+        self.edit(ReplayEvent::Restore { generation: snapshot.generation as usize, rule: snapshot.rule, cells: snapshot.cells });
+        // States saved without a map clear the one painted here
+        let regions = snapshot.regions.unwrap_or_else(|| RuleMap::new(self.grid_width, self.grid_height));
+        if regions != *self.regions { self.edit(ReplayEvent::Regions(regions)); }
+    }
+
+    // Ctrl+1..5: the latest generation into a slot, packed; a blocking readback in GPU mode
+    fn save_slot(&mut self, index: usize) {
+        if !self.cells_readable("Saving a slot") { return; }
+        let slot = Slot::new(self.step, self.rule, self.automaton, self.backend().cells());
+        println!("Saved generation {} to slot {} ({:.1} MB)", slot.generation, index + 1, slot.bytes() as f64 / (1024.0 * 1024.0));
+        self.slots[index] = Some(slot);
+    }
+
+    // 1..5: back to a saved slot, as a restore so sessions replay it
+    fn restore_slot(&mut self, index: usize) {
+        if self.replay.is_some() { return; }
+        let Some(slot) = &self.slots[index] else {
+            println!("Slot {} is empty (Ctrl+{} saves into it)", index + 1, index + 1);
+            return;
+        };
+        if self.automaton.for_rule(slot.rule) != slot.automaton {
+            eprintln!("Slot {} holds a {} grid; switch back to {} to restore it", index + 1, slot.automaton, slot.automaton);
+            return;
+        }
+        let (generation, rule) = (slot.generation, slot.rule);
+        let mut cells = slot.cells();
+        self.walls.apply(&mut cells);
+        println!("Restoring generation {} from slot {}", generation, index + 1);
+        self.edit(ReplayEvent::Restore { generation, rule, cells });
+        self.restored = Some(index);
+    }
+
+    fn restore(&mut self, generation: usize, rule: Rule, cells: Vec<u32>) {
+        self.restored = None;
+        self.set_rule(rule);
+        self.cpu.jump(None);
+        self.write_cells(0, &cells);
+        self.step = generation;
+        self.rate_start_step = self.step;
+        self.sync_generation();
+        self.cpu_base = Some(generation);
+        if let Some(split) = &mut self.split { split.restart(generation); }
+        if let Some(verifier) = &mut self.verifier { verifier.restart(self.step, &cells); }
+        self.restart_history(&cells);
+    }
+
+    // Whether `action` does anything right now; a key bound to several actions does the first that does
+    fn applies(&self, action: Action) -> bool {
+        match action {
+            Action::RotateStamp | Action::FlipStamp | Action::CancelStamp => self.stamp.is_some(),
+            Action::ClearSelection => self.selection.is_some(),
+            Action::CloseHelp => self.help.is_some(),
+            _ => true,
+        }
+    }
+
+    fn perform(&mut self, action: Action, shift: bool) {
+        match action {
+            Action::ToggleBackend => self.toggle_backend(),
+            Action::ToggleSplit => self.toggle_split(),
+            Action::CycleCpuBackend => self.cycle_cpu_backend(),
+            Action::Pause => self.toggle_pause(),
+            Action::StopSearch => self.stop_search(),
+            // Single step, only meaningful while paused
+            Action::Step => {
+                if self.paused { self.step_requested = true; }
+            }
+            Action::StepBack => self.step_back(),
+            Action::Clear => self.clear(!shift),
+            Action::CloseHelp => self.help = None,
+            Action::TogglePanel => self.overlay.toggle(),
+            Action::CycleDisplay => self.cycle_display(),
+            Action::CycleTheme => self.cycle_theme(),
+            Action::ToggleMinimap => self.toggle_minimap(),
+            Action::ToggleChunkOverlay => self.toggle_chunk_overlay(),
+            Action::ToggleSeam => self.toggle_seam(),
+            Action::ToggleInterpolation => self.toggle_interpolation(),
+            Action::ToggleChunkSkipping => self.toggle_chunk_skipping(),
+            Action::ToggleFollow => self.toggle_follow(),
+            Action::FitBounds => self.fit_bounds(),
+            Action::ToggleHud => {
+                self.overlay.toggle_hud();
+                self.hud_refresh.reset();
+            }
+            // No files or child processes in the browser
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::Screenshot => self.screenshot(),
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::Record => self.toggle_recording(),
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::SaveState => self.save_state(STATE_FILE),
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::LoadState => self.load_state(Path::new(STATE_FILE)),
+            #[cfg(target_arch = "wasm32")]
+            Action::Screenshot | Action::Record | Action::SaveState | Action::LoadState => {}
+            Action::SaveSlot(index) => self.save_slot(index),
+            Action::RestoreSlot(index) => self.restore_slot(index),
+            Action::Copy => self.copy_selection(false),
+            Action::Cut => self.copy_selection(true),
+            Action::Paste => self.paste(),
+            Action::RotateStamp => self.turn_stamp(false),
+            Action::FlipStamp => self.turn_stamp(true),
+            Action::Glider => self.select_stamp(0),
+            Action::Spaceship => self.select_stamp(1),
+            Action::GliderGun => self.select_stamp(2),
+            Action::ToggleRain => self.toggle_rain(),
+            Action::ToggleMutation => self.toggle_mutation(),
+            Action::RevertMutation => self.revert_mutation(),
+            Action::CancelStamp => {
+                self.stamp = None;
+                self.update_stamp_preview();
+            }
+            Action::ClearSelection => {
+                self.selection = None;
+                self.update_selection();
+            }
+            Action::Reset => self.reset(),
+            Action::CycleBoundary => self.cycle_boundary(),
+            Action::CyclePresentMode => self.cycle_present_mode(),
+            Action::CycleAutomaton => self.cycle_automaton(),
+            Action::CycleRule => self.cycle_rule(shift),
+            Action::CycleRuleAndReset => {
+                self.cycle_rule(shift);
+                self.reset();
+            }
+            Action::PanUp => self.pan_key(0.0, -1.0),
+            Action::PanLeft => self.pan_key(-1.0, 0.0),
+            Action::PanDown => self.pan_key(0.0, 1.0),
+            Action::PanRight => self.pan_key(1.0, 0.0),
+            Action::ShiftGridUp => self.shift_grid(0, -1, shift),
+            Action::ShiftGridLeft => self.shift_grid(-1, 0, shift),
+            Action::ShiftGridDown => self.shift_grid(0, 1, shift),
+            Action::ShiftGridRight => self.shift_grid(1, 0, shift),
+            Action::RotateGrid => self.transform_grid(Transform::Rotate),
+            Action::MirrorGridX => self.transform_grid(Transform::MirrorX),
+            Action::MirrorGridY => self.transform_grid(Transform::MirrorY),
+            Action::ResetView => {
+                self.fitting = None;
+                self.camera = Camera::new(self.grid());
+                self.update_camera();
+            }
+            // Handled by `key_pressed`, which has the event loop and the keybinds
+            Action::NewWindow | Action::ToggleHelp => {}
+            Action::Faster => {
+                self.target_rate = (self.target_rate * 2).min(MAX_RATE);
+                println!("Target rate: {} gen/s", self.target_rate);
+            }
+            Action::Slower => {
+                self.target_rate = (self.target_rate / 2).max(1);
+                println!("Target rate: {} gen/s", self.target_rate);
+            }
+        }
+    }
+
+    // Replaces the grid with a pattern file, e.g. one dropped onto the window
+    fn load_pattern(&mut self, path: &Path) {
+        let pattern = match Pattern::load(path) {
+            Ok(pattern) => pattern,
+            Err(e) => { eprintln!("Failed to load pattern: {}", e); return; }
+        };
+        let (cells, clipped) = pattern.place(self.grid_width as usize, self.grid_height as usize, None);
+        warn_clipped(clipped, pattern.cells.len());
+        // A sparse pattern goes in a row span at a time after a clear rather than as a whole grid, which would
+        // also put the whole grid in the session log
+        let spans = pattern::row_spans(&cells);
+        if spans.iter().map(|(_, _, states)| states.len()).sum::<usize>() < self.cell_count() / SPARSE_PATTERN {
+            self.edit(ReplayEvent::Clear { keep_walls: false });
+            for (x, y, states) in spans {
+                self.edit(ReplayEvent::Rect { x, y, width: states.len(), cells: states });
+            }
+        } else {
+            let mut grid = vec![0u32; self.cell_count()];
+            for (x, y, state) in cells { grid[y * self.grid_width as usize + x] = state; }
+            self.edit(ReplayEvent::Rect { x: 0, y: 0, width: self.grid_width as usize, cells: grid });
+        }
+        println!("Loaded pattern {} ({}x{}, rule {})", path.display(), pattern.width, pattern.height, pattern.rule.as_deref().unwrap_or("unspecified"));
+    }
+
+    // The first action bound to `key` that applies, from any window; opening another needs the event loop
+    fn key_pressed(&mut self, key: KeyCode, keybinds: &Keybinds, modifiers: ModifiersState, event_loop: &ActiveEventLoop) {
+        let actions = keybinds.actions(key, modifiers.control_key());
+        match actions.into_iter().find(|&action| self.applies(action)) {
+            Some(Action::NewWindow) => self.open_viewer(event_loop),
+            Some(Action::ToggleHelp) => self.toggle_help(keybinds),
+            Some(action) => self.perform(action, modifiers.shift_key()),
+            None => {}
+        }
+    }
+
+    // Ctrl+N: another window onto the grid, starting from this window's view
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_viewer(&mut self, event_loop: &ActiveEventLoop) {
+        let attributes = Window::default_attributes().with_title("Rust Life | Viewer").with_inner_size(self.window.inner_size());
+        let window = match event_loop.create_window(attributes) {
+            Ok(window) => Arc::new(window),
+            Err(e) => { eprintln!("Could not open another window: {}", e); return; }
+        };
+        let camera = |buffer: &wgpu::Buffer, downsampler: &Downsampler| camera_bind_group(&self.device, &self.camera_bind_group_layout, buffer, downsampler, &self.palette_buffer);
+        match Viewer::new(&self.instance, &self.adapter, &self.device, window, &self.config, self.gpu.buffers(), self.grid(), self.camera, camera) {
+            Ok(viewer) => {
+                viewer.window.request_redraw();
+                self.viewers.insert(viewer.window.id(), viewer);
+                self.update_camera();
+                println!("Windows open: {}", self.viewers.len() + 1);
+            }
+            Err(e) => eprintln!("Could not open another window: {}", e),
+        }
+    }
+
+    // The page has the one canvas
+    #[cfg(target_arch = "wasm32")]
+    fn open_viewer(&mut self, _event_loop: &ActiveEventLoop) {}
 
-        self.cpu_buffer = next_state;
+    // Input to one of the other windows, which only moves its own view
+    fn viewer_event(&mut self, id: WindowId, event: WindowEvent) {
+        let grid = self.grid();
+        let Some(viewer) = self.viewers.get_mut(&id) else { return };
+        let moved = match event {
+            WindowEvent::Resized(size) => {
+                viewer.resized(size);
+                false
+            }
+            WindowEvent::ScaleFactorChanged { .. } => {
+                let size = viewer.window.inner_size();
+                viewer.resized(size);
+                false
+            }
+            WindowEvent::Moved(_) => {
+                viewer.resize_gesture.moved();
+                false
+            }
+            WindowEvent::Occluded(occluded) => {
+                viewer.occluded = occluded;
+                false
+            }
+            WindowEvent::CursorMoved { position, .. } => viewer.cursor_moved(position, grid),
+            WindowEvent::CursorLeft { .. } => {
+                viewer.cursor = None;
+                false
+            }
+            WindowEvent::MouseInput { state, button: MouseButton::Middle, .. } => {
+                viewer.panning = state == ElementState::Pressed;
+                false
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                viewer.zoom(scroll_lines(delta), grid);
+                true
+            }
+            _ => false,
+        };
+        if moved { self.update_camera(); }
+    }
+
+    // Draws the GPU buffers into another window through its camera; the main window's frames do the stepping
+    fn redraw_viewer(&mut self, id: WindowId) -> Result<(), wgpu::SurfaceError> {
+        if self.stand_in() == Some(id) { self.redraw()?; }
+        let grid = self.grid();
+        if let Some(viewer) = self.viewers.get_mut(&id) {
+            let camera = |buffer: &wgpu::Buffer, downsampler: &Downsampler| camera_bind_group(&self.device, &self.camera_bind_group_layout, buffer, downsampler, &self.palette_buffer);
+            viewer.resize(&self.device, self.gpu.buffers(), grid, camera);
+        }
+        let Some(viewer) = self.viewers.get(&id).filter(|viewer| viewer.visible()) else { return Ok(()) };
+        let Some(frame) = viewer.acquire(&self.device)? else { return Ok(()) };
+        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        viewer.downsampler.encode(&mut encoder, self.gpu.parity());
+        let minimap = if self.minimap && viewer.camera.zoom > 1.0 { camera::minimap(viewer.size(), self.grid()) } else { None };
+        self.encode_panes(&mut encoder, &view, viewer.size(), &[(&self.render_bind_groups[self.gpu.parity()], &viewer.camera_bind_group)], minimap, None);
+        self.queue.submit(Some(encoder.finish()));
+        frame.present();
+        Ok(())
+    }
+
+    // The main window closed with others still open: window `id` takes its place, panel and HUD included.
+    // The main window's camera buffer and coverage stay, since the split view binds them too
+    fn promote(&mut self, id: WindowId) {
+        let Some(mut viewer) = self.viewers.remove(&id) else { return };
+        std::mem::swap(&mut self.window, &mut viewer.window);
+        std::mem::swap(&mut self.surface, &mut viewer.surface);
+        std::mem::swap(&mut self.config, &mut viewer.config);
+        std::mem::swap(&mut self.resize_gesture, &mut viewer.resize_gesture);
+        self.camera = viewer.camera;
+        self.fitting = None;
+        (self.cursor, self.occluded, self.minimized) = (viewer.cursor, viewer.occluded, viewer.minimized);
+        (self.panning, self.paint_value, self.noising, self.selecting, self.region_painting) = (false, None, false, false, None);
+        // Dropping the rest closes the old main window
+        drop(viewer);
+        self.fit_coverage();
+        self.update_camera();
+        self.hud_refresh.reset();
+        self.window.request_redraw();
+    }
+
+    fn device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Acquire)
+    }
+
+    // Where to pick up once the device is lost, with the rule map as it is now whichever generation the
+    // cells come from
+    fn recovery_snapshot(&self) -> Snapshot {
+        Snapshot { regions: self.saved_regions(), ..self.recovery_cells() }
     }
+
+    // CPU mode's grid never left the CPU; otherwise the newest of the rewind history and this run's latest
+    // autosave, which may be a few generations behind
+    fn recovery_cells(&self) -> Snapshot {
+        let (width, height, rule) = (self.grid_width, self.grid_height, self.rule);
+        if self.using_cpu {
+            return Snapshot { width, height, generation: self.step as u64, rule, regions: None, settings: None, cells: self.cpu.cells().to_vec() };
+        }
+        let latest = self.history.latest().map(|(generation, mut cells)| {
+            self.walls.apply(&mut cells);
+            Snapshot { width, height, generation: generation as u64, rule, regions: None, settings: None, cells }
+        });
+        #[cfg(not(target_arch = "wasm32"))]
+        let latest = match (latest, self.autosaver.as_ref().and_then(Autosaver::latest)) {
+            (Some(kept), Some(saved)) => Some(if saved.generation > kept.generation { saved } else { kept }),
+            (kept, saved) => kept.or(saved),
+        };
+        latest.unwrap_or_else(|| {
+            eprintln!("warning: no rewind history or autosave to pick up from, using the CPU's last copy of the grid");
+            Snapshot { width, height, generation: self.step as u64, rule, regions: None, settings: None, cells: self.cpu.cells().to_vec() }
+        })
+    }
+
+    // The command line as things stand, to set the GPU up again with: changes made since carry over, and
+    // files that are only opened once (stats, the soup search's results) are handed over in `Carried`
+    fn recovery_args(&self, args: &Args) -> Args {
+        let mut args = args.clone();
+        (args.boundary, args.topology, args.automaton, args.ltl, args.gray_scott) = (self.boundary, self.topology, self.automaton, self.ltl, self.gray_scott);
+        (args.ants, args.ant_rule, args.cyclic, args.lenia, args.cpu_backend, args.density) = (self.ants, self.ant_rule, self.cyclic, self.lenia, self.cpu_backend, self.density);
+        (args.rps_threshold, args.forest_fire) = (self.rps_threshold, self.forest_fire);
+        args.theme = self.themes[self.theme].0.clone();
+        args.rain = self.raining;
+        args.mutate = self.mutating;
+        args.stats_out = None;
+        args.soup_search = false;
+        args
+    }
+
+    // Takes what the state set up in this one's place can't rebuild; the rest goes with the lost device
+    fn carry(&mut self) -> Carried {
+        if let Some(recorder) = self.recorder.take() {
+            recorder.finish(&self.device);
+            println!("Recording stopped with the lost device");
+        }
+        Carried {
+            session_log: self.session_log.take(), replay: self.replay.take(), stats: self.stats.take(), search: self.search.take(),
+            using_cpu: self.using_cpu, split: self.split.is_some(), paused: self.paused, target_rate: self.target_rate,
+        }
+    }
+
+    // Closing the last window, or a quit hook
+    fn finish(&mut self) {
+        if let Some(recorder) = self.recorder.take() { recorder.finish(&self.device); }
+        if let Some(stats) = &mut self.stats { stats.flush(); }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Err(e) = self.pipeline_cache.as_ref().map_or(Ok(()), PipelineCache::save) {
+            eprintln!("warning: {}", e);
+        }
+        print!("{}", self.timings.table());
+    }
+
+    fn take_over(&mut self, carried: Carried) {
+        (self.session_log, self.replay, self.stats, self.search) = (carried.session_log, carried.replay, carried.stats, carried.search);
+        (self.using_cpu, self.paused, self.target_rate) = (carried.using_cpu, carried.paused, carried.target_rate);
+        if carried.split { self.toggle_split(); }
+        println!("GPU set up again at generation {}", self.step);
+    }
+}
+
+// What a state whose device was lost hands on to the one set up in its place
+struct Carried {
+    session_log: Option<SessionLog>,
+    replay: Option<Replay>,
+    stats: Option<StatsLog>,
+    search: Option<SoupSearch>,
+    using_cpu: bool,
+    split: bool,
+    paused: bool,
+    target_rate: u32,
 }
 
+// Sent back once the GPU is set up
+type Initialized = Result<GraphicsState, String>;
+
 struct App {
     state: Option<GraphicsState>,
+    args: Args,
+    // Consumed when the window is created
+    initial: Option<Snapshot>,
+    session_log: Option<SessionLog>,
+    replay: Option<Replay>,
+    modifiers: ModifiersState,
+    keybinds: Keybinds,
+    // From a state whose device was lost, while its replacement is set up
+    carried: Option<Carried>,
+    // The browser can't block on the async setup, so its result comes back as a user event
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    proxy: EventLoopProxy<Initialized>,
 }
 
-impl ApplicationHandler for App {
+impl ApplicationHandler<Initialized> for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        if self.state.is_some() { return; }
-        let window = Arc::new(event_loop.create_window(
-            winit::window::Window::default_attributes().with_title("Initializing...")
-        ).unwrap());
-        let state = pollster::block_on(init_gpu(window.clone()));
-        self.state = Some(state);
+        let Some(initial) = self.initial.take() else { return };
+        let (session_log, replay) = (self.session_log.take(), self.replay.take());
+        let attributes = winit::window::Window::default_attributes().with_title("Initializing...");
+        // Into a canvas appended to the page's body, sized by its CSS (see index.html)
+        #[cfg(target_arch = "wasm32")]
+        let attributes = winit::platform::web::WindowAttributesExtWebSys::with_append(attributes, true);
+        let window = Arc::new(event_loop.create_window(attributes).unwrap());
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.user_event(event_loop, pollster::block_on(init_gpu(window, &self.args, initial, session_log, replay)));
+        #[cfg(target_arch = "wasm32")]
+        {
+            let (args, proxy) = (self.args.clone(), self.proxy.clone());
+            wasm_bindgen_futures::spawn_local(async move {
+                let _ = proxy.send_event(init_gpu(window, &args, initial, session_log, replay).await);
+            });
+        }
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, initialized: Initialized) {
+        let carried = self.carried.take();
+        let mut state = match initialized {
+            Ok(state) => state,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                log::error!("{}", e);
+                event_loop.exit();
+                return;
+            }
+        };
+        if let Some(carried) = carried {
+            state.take_over(carried);
+        } else {
+            if self.args.record.is_some() { state.toggle_recording(); }
+            if self.args.split { state.toggle_split(); }
+            // A replay may rewind to where it starts
+            state.snapshot_for_replay();
+        }
 
         // manually request the very first frame to start the loop.
-        self.state.as_ref().unwrap().window.request_redraw();
+        state.window.request_redraw();
+        self.state = Some(state);
     }
 
-    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
+        if self.state.as_ref().is_some_and(GraphicsState::device_lost) {
+            self.recover(event_loop);
+        }
         if let Some(state) = &mut self.state {
+            // Input needs a frame to show its effect even when idle (painting, panning, single steps, resuming...)
+            let input = !matches!(event, WindowEvent::RedrawRequested);
+            // The windows Ctrl+N opened: keys act on the simulation as in the main window
+            if id != state.window.id() {
+                match event {
+                    WindowEvent::CloseRequested => { state.viewers.remove(&id); }
+                    WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers.state(),
+                    WindowEvent::KeyboardInput { event: KeyEvent { state: ElementState::Pressed, physical_key: PhysicalKey::Code(key), .. }, .. } => {
+                        state.key_pressed(key, &self.keybinds, self.modifiers, event_loop);
+                    }
+                    WindowEvent::RedrawRequested => {
+                        if state.redraw_viewer(id).is_err() { event_loop.exit(); }
+                        // It stands in for a hidden main window's frames, hooks included
+                        if std::mem::take(&mut state.quitting) {
+                            state.finish();
+                            event_loop.exit();
+                        }
+                    }
+                    event => state.viewer_event(id, event),
+                }
+                if input {
+                    state.request_redraw();
+                    if let Some(viewer) = state.viewers.get(&id).filter(|viewer| viewer.visible()) { viewer.window.request_redraw(); }
+                }
+                return;
+            }
+            // The overlay sees events first so clicks and typing in its widgets stay there; button releases
+            // still go through so drags that end over the panel finish
+            let released = matches!(event, WindowEvent::MouseInput { state: ElementState::Released, .. });
+            if state.overlay.on_window_event(&state.window, &event) && !released {
+                state.window.request_redraw();
+                return;
+            }
             match event {
-                WindowEvent::CloseRequested => event_loop.exit(),
+                WindowEvent::CloseRequested => {
+                    // Only the last window closing ends the program
+                    if let Some(&next) = state.viewers.keys().next() {
+                        state.promote(next);
+                        return;
+                    }
+                    state.finish();
+                    event_loop.exit();
+                }
                 WindowEvent::Resized(physical_size) => state.resize(physical_size),
+                WindowEvent::Moved(_) => state.resize_gesture.moved(),
+                // Moving to a monitor with another scale changes the physical size, which everything here works in;
+                // a Resized with the final size usually follows
+                WindowEvent::ScaleFactorChanged { .. } => state.resize(state.window.inner_size()),
+                WindowEvent::Occluded(occluded) => state.set_occluded(occluded),
+                WindowEvent::DroppedFile(path) => state.load_pattern(&path),
+                WindowEvent::CursorMoved { position, .. } => state.cursor_moved(position),
+                WindowEvent::CursorLeft { .. } => {
+                    state.cursor = None;
+                    state.update_stamp_preview();
+                }
+                WindowEvent::MouseInput { state: button_state, button, .. } => state.mouse_input(button_state, button, self.modifiers.shift_key(), self.modifiers.alt_key(), self.modifiers.control_key()),
+                WindowEvent::MouseWheel { delta, .. } => state.mouse_wheel(delta, self.modifiers.control_key()),
+                WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers.state(),
                 
-                WindowEvent::KeyboardInput { event: KeyEvent { state: ElementState::Pressed, physical_key: PhysicalKey::Code(KeyCode::Space), .. }, .. } => {
-                    state.using_cpu = !state.using_cpu;
-                    println!("Switched to {}", if state.using_cpu { "CPU Mode" } else { "GPU Mode" });
-                },
+                WindowEvent::KeyboardInput { event: KeyEvent { state: ElementState::Pressed, physical_key: PhysicalKey::Code(key), .. }, .. } => {
+                    state.key_pressed(key, &self.keybinds, self.modifiers, event_loop);
+                }
 
                 WindowEvent::RedrawRequested => {
-                    let start = Instant::now();
-
-                    // CPU LOGIC (Done first to avoid borrow conflicts)
-                    if state.using_cpu {
-                        state.compute_cpu();
-                        
-                        // Upload to GPU
-                        let buffer_dest = if state.step % 2 == 0 { &state.buffer_a } else { &state.buffer_b };
-                        state.queue.write_buffer(buffer_dest, 0, bytemuck::cast_slice(&state.cpu_buffer));
-                    }
-
-                    // get the GPU resources (Immutable Borrow starts here)
-                    let frame = match state.surface.get_current_texture() {
-                        Ok(frame) => frame,
-                        Err(_) => return,
-                    };
-                    let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
-                    let mut encoder = state.device.create_command_encoder(&Default::default());
-
-                    // Select Bind Group
-                    let bind_group = if state.step % 2 == 0 { &state.bind_group_a } else { &state.bind_group_b };
-
-                    // GPU LOGIC (Only runs if NOT using CPU)
-                    if !state.using_cpu {
-                        let mut cpass = encoder.begin_compute_pass(&Default::default());
-                        cpass.set_pipeline(&state.compute_pipeline);
-                        cpass.set_bind_group(0, bind_group, &[]);
-                        cpass.dispatch_workgroups(GRID_SIZE / WORKGROUP_SIZE, GRID_SIZE / WORKGROUP_SIZE, 1);
+                    if state.redraw().is_err() { event_loop.exit(); }
+                    if std::mem::take(&mut state.quitting) {
+                        state.finish();
+                        event_loop.exit();
                     }
-
-                    // Render pass (Always runs to show result)
-                    {
-                        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                            label: None,
-                            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                                view: &view,
-                                resolve_target: None,
-                                ops: wgpu::Operations {
-                                    load: wgpu::LoadOp::Clear(wgpu::Color { r: 0.1, g: 0.1, b: 0.3, a: 1.0 }),
-                                    store: wgpu::StoreOp::Store,
-                                },
-                            })],
-                            depth_stencil_attachment: None,
-                            timestamp_writes: None,
-                            occlusion_query_set: None,
-                        });
-                        rpass.set_pipeline(&state.render_pipeline);
-                        rpass.set_bind_group(0, bind_group, &[]);
-                        rpass.draw(0..6, 0..1);
-                    }
-
-                    state.queue.submit(Some(encoder.finish()));
-                    frame.present();
-                    state.step += 1;
-                    state.window.request_redraw();
-
-                    let duration = start.elapsed();
-                    let mode = if state.using_cpu { "CPU (Rayon)" } else { "GPU (WGPU)" };
-                    
-                    state.window.set_title(&format!(
-                        "Rust Life | Mode: {} | Update Time: {:.2?} | {} Cells", 
-                        mode, duration, GRID_SIZE * GRID_SIZE
-                    ));
                 }
                 _ => {}
             }
+            if input && state.on_screen() {
+                state.request_redraw();
+            }
+        }
+    }
+
+    // Block in the event loop instead of spinning while there's nothing to animate
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if let Some(state) = &self.state {
+            event_loop.set_control_flow(if state.animating() { ControlFlow::Poll } else { ControlFlow::Wait });
+        }
+    }
+}
+
+impl App {
+    // Sets the GPU up again once the device is lost, on the same window; the old state (and with it the old
+    // device and surface) goes first, since some backends allow only one surface per window. The windows
+    // Ctrl+N opened close with it
+    fn recover(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(mut lost) = self.state.take() else { return };
+        let (snapshot, args, window) = (lost.recovery_snapshot(), lost.recovery_args(&self.args), lost.window.clone());
+        self.carried = Some(lost.carry());
+        drop(lost);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.user_event(event_loop, pollster::block_on(init_gpu(window, &args, snapshot, None, None)));
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = event_loop;
+            let proxy = self.proxy.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let _ = proxy.send_event(init_gpu(window, &args, snapshot, None, None).await);
+            });
         }
     }
 }
 
-async fn init_gpu(window: Arc<Window>) -> GraphicsState {
-    let instance = wgpu::Instance::default();
-    let surface = instance.create_surface(window.clone()).unwrap();
-    let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
-        power_preference: wgpu::PowerPreference::HighPerformance,
-        compatible_surface: Some(&surface),
-        ..Default::default()
-    }).await.unwrap();
-    let (device, queue) = adapter.request_device(&Default::default(), None).await.unwrap();
+async fn init_gpu(window: Arc<Window>, args: &Args, initial: Snapshot, session_log: Option<SessionLog>, replay: Option<Replay>) -> Result<GraphicsState, String> {
+    let instance = adapter::create_instance(args.backends);
+    let surface = instance.create_surface(window.clone()).map_err(|e| format!("could not create a window surface: {}", e))?;
+    let adapter = adapter::select(&instance, Some(&surface), args.adapter.as_ref()).await?;
+    let info = adapter.get_info();
+    println!("Adapter: {} ({:?}, driver {} {})", info.name, info.backend, info.driver, info.driver_info);
+    let adapter_name = info.name.clone();
+    // A software rasterizer runs the compute shader far slower than Rayon, so it only draws
+    let software = info.device_type == wgpu::DeviceType::Cpu;
+    if software {
+        eprintln!("warning: no hardware GPU available, starting in CPU mode on the software adapter");
+    }
+    let (device, queue) = rust_gpu_life::gpu::request_device(&adapter).await?;
+    let device_lost = recovery::watch(&device, args.panic_on_error);
+    let (device, queue) = (Arc::new(device), Arc::new(queue));
+    let (grid_width, grid_height) = (initial.width, initial.height);
+    #[cfg(not(target_arch = "wasm32"))]
+    let pipeline_cache = pipeline_cache::path().and_then(|path| PipelineCache::open(&device, &info, path));
+    #[cfg(not(target_arch = "wasm32"))]
+    let cache = pipeline_cache.as_ref().map(|cache| cache.get().clone());
+    #[cfg(target_arch = "wasm32")]
+    let cache: Option<Arc<wgpu::PipelineCache>> = None;
+
     let caps = surface.get_capabilities(&adapter);
-    let format = caps.formats[0];
+    let format = *caps.formats.first().ok_or("the adapter cannot present to this window")?;
+    // Wayland and the browser may not have sized the window yet: the surface is then configured on the
+    // first Resized with a real size, and nothing is drawn until then
+    let size = window.inner_size();
+    let sized = size.width > 0 && size.height > 0;
     let config = wgpu::SurfaceConfiguration {
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
         format,
-        width: window.inner_size().width,
-        height: window.inner_size().height,
-        present_mode: wgpu::PresentMode::Fifo,
+        width: size.width.max(1),
+        height: size.height.max(1),
+        present_mode: present::choose(args.present_mode, &caps.present_modes),
         alpha_mode: caps.alpha_modes[0],
         view_formats: vec![],
         desired_maximum_frame_latency: 2,
     };
-    surface.configure(&device, &config);
-
-    let mut initial_data = vec![0u32; (GRID_SIZE * GRID_SIZE) as usize];
-    for i in 0..initial_data.len() {
-        if rand::random::<f32>() > 0.8 { initial_data[i] = 1; }
+    if config.present_mode != args.present_mode {
+        eprintln!("warning: present mode {} is not supported here, using vsync", present::name(args.present_mode));
+    }
+    if sized {
+        surface.configure(&device, &config);
     }
 
-    let buffer_a = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some("Buffer A"),
-        contents: bytemuck::cast_slice(&initial_data),
-        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-    });
-    let buffer_b = device.create_buffer(&wgpu::BufferDescriptor {
-        label: Some("Buffer B"),
-        size: (initial_data.len() * 4) as u64,
-        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-        mapped_at_creation: false,
+    // Timed to show what the pipeline cache saves; the step's time includes uploading the grid
+    let compiling = Instant::now();
+    let mut gpu = recovery::scoped(&device, "the simulation's buffers and pipelines", || {
+        GpuSimulation::new(device.clone(), queue.clone(), grid_width, grid_height, initial.rule, args.boundary, args.automaton, &initial.cells, cache.clone())
+    }).await??;
+    let step_time = compiling.elapsed();
+    // Timing dispatches needs a blocking poll, which the browser doesn't have
+    #[cfg(not(target_arch = "wasm32"))]
+    gpu.set_workgroup_size(tune::choose(&device, &queue, args, &initial, &adapter_name)?)?;
+    #[cfg(target_arch = "wasm32")]
+    if let Some(size) = args.workgroup {
+        gpu.set_workgroup_size(size)?;
+    }
+    let cell_count = grid_width as usize * grid_height as usize;
+    let mut history = History::new(args.history, args.history_every, cell_count, args.automaton.state_bits(initial.rule), HISTORY_BUDGET);
+    history.record(initial.generation as usize, &initial.cells);
+    if history.capacity() < args.history {
+        println!("History: only {} of {} generations fit in {} MB", history.capacity(), args.history, HISTORY_BUDGET >> 20);
+    } else if history.capacity() > 0 {
+        println!("History: {} generations, up to {} MB", history.capacity(), history.budget().div_ceil(1 << 20));
+    }
+    let mut walls = Walls::default();
+    walls.track(cell_count, 0, &initial.cells);
+    let history_packer = HistoryPacker::new(&device, &gpu.buffers()[0], &gpu.buffers()[1], cell_count);
+    let verifier = args.verify.map(|every| {
+        let mut shadow = CpuSimulation::new(grid_width, grid_height, initial.rule, args.boundary, args.automaton, initial.cells.clone());
+        shadow.set_topology(args.topology);
+        shadow.set_ltl_rule(args.ltl);
+        shadow.set_gray_scott(args.gray_scott);
+        shadow.set_cyclic_rule(args.cyclic);
+        shadow.set_rps_threshold(args.rps_threshold);
+        shadow.set_lenia(args.lenia);
+        shadow.set_forest_fire(args.forest_fire);
+        shadow.set_ant_rule(args.ant_rule);
+        Verifier::new(&device, every, shadow, initial.generation as usize)
     });
+    let mut cpu = args.cpu_backend.create(grid_width, grid_height, initial.rule, args.boundary, args.automaton, initial.cells);
+    gpu.set_topology(args.topology);
+    cpu.set_topology(args.topology);
+    gpu.set_ltl_rule(args.ltl);
+    cpu.set_ltl_rule(args.ltl);
+    gpu.set_gray_scott(args.gray_scott);
+    gpu.set_cyclic_rule(args.cyclic);
+    gpu.set_rps_threshold(args.rps_threshold);
+    gpu.set_lenia(args.lenia);
+    gpu.set_forest_fire(args.forest_fire);
+    gpu.set_generation(initial.generation);
+    cpu.set_gray_scott(args.gray_scott);
+    cpu.set_cyclic_rule(args.cyclic);
+    cpu.set_rps_threshold(args.rps_threshold);
+    cpu.set_lenia(args.lenia);
+    cpu.set_forest_fire(args.forest_fire);
+    cpu.set_generation(initial.generation);
+    cpu.set_ant_rule(args.ant_rule);
+    if !args.chunk_skipping { gpu.set_chunk_skipping(false)?; }
+    let population = PopulationCounter::new(&device, &gpu.buffers()[0], &gpu.buffers()[1], args.reduction);
+    let bounds_tracker = BoundsTracker::new(&device, &gpu.buffers()[0], &gpu.buffers()[1], grid_width);
+    let change_counter = ChangeCounter::new(&device, &gpu, args.reduction);
+    let fader = Fader::new(&device, &gpu);
+    let stats = match &args.stats_out {
+        Some(path) => Some(StatsLog::open(path.clone(), args.stats_format, args.stats_every)?),
+        None => None,
+    };
+    let search = match (args.soup_search, args.seed) {
+        (true, Some(seed)) => Some(SoupSearch::open(args.search_out.clone(), args.search_generations, args.search_draw_every, args.search_save, seed)?),
+        _ => None,
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    let saved_rules = custom_rules::load();
+    #[cfg(target_arch = "wasm32")]
+    let saved_rules = Vec::new();
+    let overlay = Overlay::new(&window, &device, format, initial.rule, saved_rules);
+    #[cfg(not(target_arch = "wasm32"))]
+    let custom_themes = themes::load();
+    #[cfg(target_arch = "wasm32")]
+    let custom_themes = Vec::new();
+    let themes: Vec<(String, Theme)> = Theme::PRESETS.iter().map(|&(name, theme)| (name.to_string(), theme)).chain(custom_themes).collect();
+    let theme = themes.iter().position(|(name, _)| *name == args.theme).ok_or_else(|| {
+        let names: Vec<&str> = themes.iter().map(|(name, _)| name.as_str()).collect();
+        format!("unknown theme '{}' (expected one of {})", args.theme, names.join(", "))
+    })?;
+    let timer = GpuTimer::new(&device, &queue);
+    if timer.is_none() {
+        println!("Timestamp queries are not supported by this adapter, GPU compute time won't be shown");
+    }
+    let render_timer = GpuTimer::new(&device, &queue);
 
-    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+    let [bind_group_layout, camera_bind_group_layout, stamp_bind_group_layout] = render_bind_group_layouts(&device);
+    // Bind group n draws buffer n, with the other one as the previous generation
+    let buffers = gpu.buffers();
+    let render_bind_groups = [0, 1].map(|n| device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &bind_group_layout,
         entries: &[
-            wgpu::BindGroupLayoutEntry { binding: 0, visibility: wgpu::ShaderStages::COMPUTE | wgpu::ShaderStages::FRAGMENT, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: true }, has_dynamic_offset: false, min_binding_size: None }, count: None },
-            wgpu::BindGroupLayoutEntry { binding: 1, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: false }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            wgpu::BindGroupEntry { binding: 0, resource: buffers[n].as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 1, resource: gpu.params_buffer().as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 2, resource: buffers[n ^ 1].as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 3, resource: gpu.active_chunks_buffer().as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 4, resource: gpu.rule_map_buffer().as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 5, resource: fader.levels_buffer().as_entire_binding() },
         ],
         label: None,
+    }));
+
+    let camera = Camera::new((grid_width, grid_height));
+    let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Camera"),
+        contents: bytemuck::bytes_of(&camera),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    });
+    let palette_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Palette"),
+        contents: bytemuck::bytes_of(&themes[theme].1.uniform()),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
     });
+    let downsampler = Downsampler::new(&device, gpu.buffers(), grid_width, grid_height, camera::viewport((config.width, config.height), (grid_width, grid_height)).2 as u32);
+    let camera_bind_group = camera_bind_group(&device, &camera_bind_group_layout, &camera_buffer, &downsampler, &palette_buffer);
 
-    let bind_group_a = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        layout: &bind_group_layout,
+    // Sized for the largest built-in stamp and grown for bigger pasted ones; turning one never changes its cell count
+    let stamps = Stamp::library();
+    #[cfg(not(target_arch = "wasm32"))]
+    let rain_config = stamp::load_rain(&stamps);
+    #[cfg(target_arch = "wasm32")]
+    let rain_config = rust_gpu_life::rain::RainConfig::default();
+    let rain_patterns: Vec<usize> = rain_config.patterns.iter().filter_map(|name| stamps.iter().position(|stamp| stamp.name == *name)).collect();
+    // Rain and mutation draw from the session's seed, so --seed repeats both
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let rain = Rain::new(rain_config.every, rain_patterns.len(), seed, initial.generation as usize);
+    let mutation = Mutation::new(args.mutate_every, seed, initial.generation as usize);
+    let hooks = Hooks::new(args.hooks.clone(), initial.generation as usize);
+    let mask_len = stamps.iter().map(|stamp| stamp.mask().len()).max().unwrap_or(1);
+    let stamp_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Stamp"),
+        contents: bytemuck::bytes_of(&StampPreview::default()),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    });
+    let stamp_mask_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Stamp Mask"), size: (mask_len * 4) as u64, usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST, mapped_at_creation: false,
+    });
+    let selection_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Selection"),
+        contents: bytemuck::bytes_of(&SelectionRect::default()),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    });
+    let stamp_bind_group = stamp_bind_group(&device, &stamp_bind_group_layout, &stamp_buffer, &stamp_mask_buffer, &selection_buffer);
+
+    let shader = device.create_shader_module(wgpu::include_wgsl!("render.wgsl"));
+    let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&bind_group_layout, &camera_bind_group_layout, &stamp_bind_group_layout], ..Default::default() });
+
+    let pipeline = |fragment: &str| device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(fragment), layout: Some(&render_pipeline_layout),
+        vertex: wgpu::VertexState { module: &shader, entry_point: "vs_main", buffers: &[], compilation_options: Default::default() },
+        fragment: Some(wgpu::FragmentState { module: &shader, entry_point: fragment, targets: &[Some(wgpu::ColorTargetState { format, blend: None, write_mask: wgpu::ColorWrites::ALL })], compilation_options: Default::default() }),
+        primitive: wgpu::PrimitiveState::default(), depth_stencil: None, multisample: wgpu::MultisampleState::default(), multiview: None, cache: cache.as_deref(),
+    });
+    let compiling = Instant::now();
+    let (render_pipeline, minimap_pipeline) = recovery::scoped(&device, "the render pipelines", || (pipeline("fs_main"), pipeline("fs_minimap"))).await?;
+    #[cfg(not(target_arch = "wasm32"))]
+    let cached = match &pipeline_cache {
+        Some(cache) if cache.loaded() > 0 => format!("{} KB from the pipeline cache", cache.loaded().div_ceil(1024)),
+        Some(_) => "pipeline cache empty".to_string(),
+        None => "no pipeline cache on this backend".to_string(),
+    };
+    #[cfg(target_arch = "wasm32")]
+    let cached = "no pipeline cache on this backend";
+    println!("Pipelines: step {:.1} ms, render {:.1} ms ({})", step_time.as_secs_f64() * 1000.0, compiling.elapsed().as_secs_f64() * 1000.0, cached);
+
+    let mut state = GraphicsState {
+        window, surface, instance, adapter, viewers: HashMap::new(), device, queue, config, adapter_name, present_modes: caps.present_modes, render_pipeline, minimap_pipeline, minimap: true, chunk_overlay: false, seam: false, interpolating: false, blend_from: 0, render_bind_group_layout: bind_group_layout, render_bind_groups,
+        camera_buffer, camera_bind_group_layout, camera_bind_group, camera, themes, theme, palette_buffer, following: false, follow_target: None, fitting: None, downsampler,
+        stamps, stamp: None, raining: args.rain, rain, rain_every: rain_config.every, rain_patterns, mutating: args.mutate, mutation, mutate_floor: args.mutate_floor, hooks, quitting: false, stamp_buffer, stamp_mask_buffer, stamp_bind_group_layout, stamp_bind_group,
+        selection: None, selecting: false, selection_buffer, clip: None,
+        #[cfg(not(target_arch = "wasm32"))]
+        os_clipboard: None,
+        #[cfg(not(target_arch = "wasm32"))]
+        copied_rle: None,
+        population,
+        population_count: 0,
+        bounds_tracker,
+        bounds: None,
+        population_graph: PopulationGraph::default(),
+        timings: FrameTimings::default(),
+        species_counts: [0; 2],
+        change_counter, fader,
+        steady: SteadyState::default(),
+        settled: None,
+        slots: Default::default(),
+        restored: None,
+        timer,
+        render_timer,
+        gpu_time: None,
+        encode_time: Duration::ZERO,
+        submit_time: Duration::ZERO,
+        cpu: CpuWorker::new(cpu),
+        cpu_backend: args.cpu_backend,
+        gpu,
+        grid_width,
+        grid_height,
+        rule: initial.rule,
+        boundary: args.boundary,
+        topology: args.topology,
+        automaton: args.automaton,
+        ltl: args.ltl,
+        gray_scott: args.gray_scott,
+        ants: args.ants,
+        ant_rule: args.ant_rule,
+        cyclic: args.cyclic,
+        rps_threshold: args.rps_threshold,
+        lenia: args.lenia,
+        forest_fire: args.forest_fire,
+        density: args.density,
+        // Ants only walk on the CPU
+        using_cpu: software || args.automaton == Automaton::LangtonsAnt,
+        split: None,
+        cpu_base: Some(initial.generation as usize),
+        fast_forward: None,
+        paused: false,
+        step_requested: false,
+        target_rate: if search.is_some() { MAX_RATE } else { DEFAULT_RATE },
+        last_frame: Instant::now(),
+        owed: 0.0,
+        step: initial.generation as usize,
+        rate_timer: Instant::now(),
+        rate_start_step: initial.generation as usize,
+        generations_per_second: 0.0,
+        cursor: None,
+        paint_value: None,
+        noising: false,
+        noise_radius: NOISE_RADIUS,
+        panning: false,
+        occluded: false,
+        minimized: !sized,
+        resize_gesture: ResizeGesture::new(),
+        recorder: None,
+        record_path: args.record.clone(),
+        record_size: args.record_size,
+        stats,
+        overlay,
+        frame_time: Duration::ZERO,
+        hud_lines: Vec::new(),
+        help: None,
+        hud_refresh: Throttle::new(HUD_INTERVAL),
+        title_refresh: Throttle::new(TITLE_INTERVAL),
+        upload_bytes: 0,
+        verifier,
+        history,
+        history_packer,
+        walls,
+        regions: Arc::new(RuleMap::new(grid_width, grid_height)),
+        region_brush: None,
+        region_painting: None,
+        region_tint: true,
+        display: DisplayMode::Normal,
+        session_log,
+        replay,
+        search,
+        #[cfg(not(target_arch = "wasm32"))]
+        autosaver: args.autosave.then(autosave::path).flatten().map(|path| {
+            let schedule = autosave::Schedule::new(args.autosave_every, Duration::from_secs(args.autosave_seconds), initial.generation as usize, std::time::Instant::now());
+            Autosaver::new(path, schedule)
+        }),
+        #[cfg(not(target_arch = "wasm32"))]
+        pipeline_cache,
+        device_lost,
+    };
+    // The camera buffer was made before the tint was on
+    state.update_camera();
+    // Through `edit`, so a recorded session starts with the loaded map too
+    if let Some(map) = initial.regions { state.edit(ReplayEvent::Regions(map)); }
+    Ok(state)
+}
+
+// render.wgsl's bind group layouts: the cells with what's drawn from them (group 0), the camera, coverage and
+// palette (group 1), and the editing overlays, stamp preview and selection (group 2)
+fn render_bind_group_layouts(device: &wgpu::Device) -> [wgpu::BindGroupLayout; 3] {
+    [
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry { binding: 0, visibility: wgpu::ShaderStages::FRAGMENT, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: true }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 1, visibility: wgpu::ShaderStages::FRAGMENT, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 2, visibility: wgpu::ShaderStages::FRAGMENT, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: true }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 3, visibility: wgpu::ShaderStages::FRAGMENT, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: true }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 4, visibility: wgpu::ShaderStages::FRAGMENT, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: true }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 5, visibility: wgpu::ShaderStages::FRAGMENT, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: true }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            ],
+            label: None,
+        }),
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry { binding: 0, visibility: wgpu::ShaderStages::FRAGMENT, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 1, visibility: wgpu::ShaderStages::FRAGMENT, ty: wgpu::BindingType::Texture { sample_type: wgpu::TextureSampleType::Float { filterable: false }, view_dimension: wgpu::TextureViewDimension::D2, multisampled: false }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 2, visibility: wgpu::ShaderStages::FRAGMENT, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 3, visibility: wgpu::ShaderStages::FRAGMENT, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            ],
+            label: None,
+        }),
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry { binding: 0, visibility: wgpu::ShaderStages::FRAGMENT, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 1, visibility: wgpu::ShaderStages::FRAGMENT, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: true }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 2, visibility: wgpu::ShaderStages::FRAGMENT, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            ],
+            label: None,
+        }),
+    ]
+}
+
+fn stamp_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, stamp_buffer: &wgpu::Buffer, mask_buffer: &wgpu::Buffer, selection_buffer: &wgpu::Buffer) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
         entries: &[
-            wgpu::BindGroupEntry { binding: 0, resource: buffer_a.as_entire_binding() },
-            wgpu::BindGroupEntry { binding: 1, resource: buffer_b.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 0, resource: stamp_buffer.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 1, resource: mask_buffer.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 2, resource: selection_buffer.as_entire_binding() },
         ],
         label: None,
-    });
-    let bind_group_b = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        layout: &bind_group_layout,
+    })
+}
+
+fn camera_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, camera_buffer: &wgpu::Buffer, downsampler: &Downsampler, palette_buffer: &wgpu::Buffer) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
         entries: &[
-            wgpu::BindGroupEntry { binding: 0, resource: buffer_b.as_entire_binding() },
-            wgpu::BindGroupEntry { binding: 1, resource: buffer_a.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 0, resource: camera_buffer.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(downsampler.coverage()) },
+            wgpu::BindGroupEntry { binding: 2, resource: downsampler.params_buffer().as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 3, resource: palette_buffer.as_entire_binding() },
         ],
         label: None,
-    });
+    })
+}
 
-    let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
-    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&bind_group_layout], ..Default::default() });
+fn warn_clipped(clipped: usize, total: usize) {
+    if clipped > 0 {
+        eprintln!("warning: {} of the pattern's {} live cells are past the grid's edges and were left out", with_commas(clipped as u64), with_commas(total as u64));
+    }
+}
 
-    let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-        label: None, layout: Some(&pipeline_layout), module: &shader, entry_point: "main", compilation_options: Default::default(), cache: None,
-    });
+// The pattern if one was given, otherwise a random soup (or the demo circuit, soup makes no sense in Wireworld);
+// a soup's seed is kept in `args` for the session log
+fn initial_grid(args: &mut Args, pattern: Option<&Pattern>) -> Vec<u32> {
+    let cell_count = args.width as usize * args.height as usize;
+    match pattern {
+        Some(pattern) => {
+            let mut grid = vec![0u32; cell_count];
+            let (cells, clipped) = pattern.place(args.width as usize, args.height as usize, args.origin);
+            warn_clipped(clipped, pattern.cells.len());
+            for (x, y, state) in cells { grid[y * args.width as usize + x] = state; }
+            grid
+        }
+        None if args.automaton == Automaton::Wireworld => demo_circuit(args.width, args.height),
+        None if args.wedges => rps::wedges(args.width, args.height),
+        None => {
+            let seed = *args.seed.get_or_insert_with(|| {
+                let seed = rand::random();
+                println!("Seed: {}", seed);
+                seed
+            });
+            // A forest fire's dice follow the soup's seed unless --seed already chose it
+            args.forest_fire.seed = seed;
+            soup(args.width, args.height, seed, args.density, args.automaton, args.ants, args.cyclic, args.lenia)
+        }
+    }
+}
 
-    let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: None, layout: Some(&pipeline_layout),
-        vertex: wgpu::VertexState { module: &shader, entry_point: "vs_main", buffers: &[], compilation_options: Default::default() },
-        fragment: Some(wgpu::FragmentState { module: &shader, entry_point: "fs_main", targets: &[Some(wgpu::ColorTargetState { format, blend: None, write_mask: wgpu::ColorWrites::ALL })], compilation_options: Default::default() }),
-        primitive: wgpu::PrimitiveState::default(), depth_stencil: None, multisample: wgpu::MultisampleState::default(), multiview: None, cache: None,
-    });
+// Takes the grid size, settings and starting cells from a recorded session
+fn replay_start(args: &mut Args, session: Session) -> Snapshot {
+    (args.width, args.height) = (session.width, session.height);
+    (args.boundary, args.topology, args.automaton, args.ltl, args.gray_scott, args.cpu_backend) = (session.boundary, session.topology, session.automaton, session.ltl, session.gray_scott, session.cpu_backend);
+    (args.ants, args.ant_rule, args.cyclic, args.rps_threshold, args.lenia, args.forest_fire) = (session.ants, session.ant_rule, session.cyclic, session.rps_threshold, session.lenia, session.forest_fire);
+    // Recording the replay again logs the same start
+    let cells = match session.start {
+        Start::Soup { seed, density } => {
+            (args.seed, args.density) = (Some(seed), density);
+            soup(session.width, session.height, seed, density, session.automaton, session.ants, session.cyclic, session.lenia)
+        }
+        Start::Cells(cells) => {
+            args.seed = None;
+            cells
+        }
+    };
+    Snapshot { width: session.width, height: session.height, generation: session.generation as u64, rule: session.rule, regions: None, settings: None, cells }
+}
+
+// A saved state's settings over the command line's, so --resume carries on under what the grid ran as
+fn load_settings(args: &mut Args, settings: Settings) {
+    (args.automaton, args.boundary, args.topology, args.ltl, args.gray_scott) = (settings.automaton, settings.boundary, settings.topology, settings.ltl, settings.gray_scott);
+    (args.ant_rule, args.cyclic, args.rps_threshold, args.lenia, args.forest_fire) = (settings.ant_rule, settings.cyclic, settings.rps_threshold, settings.lenia, settings.forest_fire);
+}
+
+// What --record-session writes first: a soup is logged by its seed, anything else cell by cell
+fn session_header(args: &Args, initial: &Snapshot, soup: bool) -> Session {
+    let start = match args.seed {
+        Some(seed) if soup => Start::Soup { seed, density: args.density },
+        _ => Start::Cells(initial.cells.clone()),
+    };
+    Session {
+        width: initial.width, height: initial.height, generation: initial.generation as usize, rule: initial.rule, boundary: args.boundary, topology: args.topology,
+        automaton: args.automaton, ltl: args.ltl, gray_scott: args.gray_scott, ants: args.ants, ant_rule: args.ant_rule, cyclic: args.cyclic, rps_threshold: args.rps_threshold, lenia: args.lenia, forest_fire: args.forest_fire,
+        cpu_backend: args.cpu_backend, start,
+    }
+}
+
+// Random cells, split between the two species in Immigration; Gray-Scott gets a few seeded squares instead,
+// Langton's Ant `ants` ants on an empty grid, cyclic rules uniform values, rock-paper-scissors every cell
+// one of the species and Lenia blobs a kernel across
+#[allow(clippy::too_many_arguments)]
+fn soup(width: u32, height: u32, seed: u64, density: f32, automaton: Automaton, ants: u32, cyclic: CyclicRule, lenia: Lenia) -> Vec<u32> {
+    shaped_soup(width, height, seed, density, automaton, ants, cyclic, lenia).unwrap_or_else(|| random_grid(width as usize * height as usize, seed, density))
+}
+
+// `soup` for the automata that start from something other than the plain soup the GPU can hash itself
+#[allow(clippy::too_many_arguments)]
+fn shaped_soup(width: u32, height: u32, seed: u64, density: f32, automaton: Automaton, ants: u32, cyclic: CyclicRule, lenia: Lenia) -> Option<Vec<u32>> {
+    match automaton {
+        Automaton::GrayScott => Some(gray_scott::seed_grid(width, height, seed)),
+        Automaton::LangtonsAnt => Some(ant::seed_grid(width, height, seed, ants)),
+        Automaton::Cyclic => Some(cyclic::random_grid(width, height, seed, cyclic.states)),
+        Automaton::RockPaperScissors => Some(rps::random_grid(width, height, seed)),
+        Automaton::Lenia => Some(lenia::seed_grid(width, height, seed, lenia.radius)),
+        Automaton::Immigration => {
+            let mut grid = random_grid(width as usize * height as usize, seed, density);
+            split_species(&mut grid, seed);
+            Some(grid)
+        }
+        _ => None,
+    }
+}
+
+// The built-in Wireworld circuit centered on an empty grid
+fn demo_circuit(width: u32, height: u32) -> Vec<u32> {
+    let mut grid = vec![0u32; width as usize * height as usize];
+    if let Err(e) = Pattern::wireworld_demo().write_centered(&mut grid, width as usize) {
+        eprintln!("No room for the demo circuit: {}", e);
+    }
+    grid
+}
+
+// 1234567 -> "1,234,567"
+// Notches of the scroll wheel, with touchpads' pixels counted 50 to the notch
+fn scroll_lines(delta: MouseScrollDelta) -> f64 {
+    match delta {
+        MouseScrollDelta::LineDelta(_, y) => y as f64,
+        MouseScrollDelta::PixelDelta(position) => position.y / 50.0,
+    }
+}
 
-    GraphicsState {
-        window, surface, device, queue, config, compute_pipeline, render_pipeline, bind_group_a, bind_group_b, buffer_a, buffer_b,
-        cpu_buffer: initial_data,
-        using_cpu: false,
-        step: 0,
+fn with_commas(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) { out.push(','); }
+        out.push(c);
     }
+    out
 }
 
 fn main() {
-    let event_loop = EventLoop::new().unwrap();
+    #[cfg(not(target_arch = "wasm32"))]
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+    #[cfg(target_arch = "wasm32")]
+    {
+        std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+        console_log::init_with_level(log::Level::Warn).expect("logging is only set up once");
+    }
+    let mut args = Args::parse().unwrap_or_else(|e| exit_with_error(&e));
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some((input, output)) = &args.convert {
+        use rust_gpu_life::pattern::Format;
+        let pattern = Pattern::load(input).unwrap_or_else(|e| exit_with_error(&e));
+        pattern.save(output).unwrap_or_else(|e| exit_with_error(&e));
+        let (from, to) = (Format::from_path(input), Format::from_path(output));
+        println!("Converted {} ({}) to {} ({})", input.display(), from, output.display(), to);
+        match pattern.bounding_box() {
+            Some((left, top, width, height)) => println!("Bounding box {}x{} at ({}, {}), population {}", width, height, left, top, pattern.cells.len()),
+            None => println!("The pattern is empty"),
+        }
+        if let Some(rule) = pattern.rule.as_ref().filter(|_| !to.holds_rule()) {
+            println!("The rule ({}) is left out, {} has no line for it", rule, to);
+        }
+        return;
+    }
+    if args.pattern.is_some() && args.image.is_some() {
+        exit_with_error("--pattern and --image can't be combined");
+    }
+    if args.origin.is_some() && args.pattern.is_none() {
+        exit_with_error("--origin places a --pattern");
+    }
+    // Cells that don't fit are left out with a warning, so a huge macrocell pattern can be looked at in part
+    let pattern = args.pattern.as_ref().map(|path| Pattern::load(path).unwrap_or_else(|e| exit_with_error(&e)));
+    // A picture is scaled to fit, so it starts the grid like any other pattern
+    let pattern = pattern.or_else(|| args.image.as_ref().map(|path| {
+        bitmap::load(path, args.width, args.height, args.threshold, args.dither).unwrap_or_else(|e| exit_with_error(&e))
+    }));
+
+    // A saved state brings its own grid size and rule, a recorded session everything it started with
+    let mut replay = None;
+    let initial = match (args.load.clone(), args.replay.clone()) {
+        _ if args.load.is_some() && pattern.is_some() => exit_with_error("--load and --pattern (or --image) can't be combined"),
+        (Some(_), Some(_)) => exit_with_error("--load and --replay can't be combined"),
+        (None, Some(_)) if pattern.is_some() => exit_with_error("--pattern (or --image) and --replay can't be combined"),
+        (Some(path), None) => Snapshot::load(&path).unwrap_or_else(|e| exit_with_error(&e)),
+        (None, Some(path)) => {
+            let (session, events) = Replay::load(&path).unwrap_or_else(|e| exit_with_error(&e));
+            println!("Replaying {} ({} events)", path.display(), events.remaining());
+            replay = Some(events);
+            replay_start(&mut args, session)
+        }
+        (None, None) => Snapshot { width: args.width, height: args.height, generation: 0, rule: args.rule, regions: None, settings: None, cells: initial_grid(&mut args, pattern.as_ref()) },
+    };
+    // Saved states from before the settings were kept run as the command line says
+    if let Some(settings) = initial.settings { load_settings(&mut args, settings); }
+    // Generations rules run as their own automaton, whether they come from --rule or a saved state
+    args.automaton = args.automaton.for_rule(initial.rule);
+    let soup = args.load.is_none() && pattern.is_none() && args.automaton != Automaton::Wireworld && !args.wedges;
+    if args.soup_search && (!soup || args.replay.is_some()) {
+        exit_with_error("soup-search needs a random soup, so no --load, --pattern, --image, --replay, --wedges or Wireworld");
+    }
+    let session_log = args.record_session.clone().map(|path| {
+        SessionLog::create(path, &session_header(&args, &initial, soup)).unwrap_or_else(|e| exit_with_error(&e))
+    });
+
+    // Hex rows alternate, so an odd height would make the wrap join two rows of the same kind
+    if args.topology == Topology::Hex && initial.height % 2 == 1 {
+        exit_with_error(&format!("--topology hex needs an even grid height, not {}", initial.height));
+    }
+    if args.workgroup.is_some() && args.tune {
+        exit_with_error("--workgroup and --tune can't be combined");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if args.list_adapters {
+            adapter::list(args.backends).unwrap_or_else(|e| exit_with_error(&e));
+            return;
+        }
+        if args.bench_cpu {
+            bench::run_cpu(&args, initial).unwrap_or_else(|e| exit_with_error(&e));
+            return;
+        }
+        if args.bench {
+            bench::run(&args, initial).unwrap_or_else(|e| exit_with_error(&e));
+            return;
+        }
+        if args.headless {
+            headless::run(&args, initial).unwrap_or_else(|e| exit_with_error(&e));
+            return;
+        }
+        if args.tui {
+            tui::run(&args, initial).unwrap_or_else(|e| exit_with_error(&e));
+            return;
+        }
+        if band_window::wanted(&args, &initial).unwrap_or_else(|e| exit_with_error(&e)) {
+            band_window::run(args, initial).unwrap_or_else(|e| exit_with_error(&e));
+            return;
+        }
+        // This run's first autosave replaces it
+        if args.load.is_none() && args.replay.is_none() {
+            if let Some((path, age)) = autosave::path().and_then(|path| autosave::recent(&path).map(|age| (path, age))) {
+                println!("An autosave from {} minutes ago is at {}; --resume picks up from it", age.as_secs() / 60, path.display());
+            }
+        }
+    }
+
+    let event_loop = EventLoop::<Initialized>::with_user_event().build().unwrap();
     event_loop.set_control_flow(ControlFlow::Poll);
-    let mut app = App { state: None };
-    event_loop.run_app(&mut app).unwrap();
-}
\ No newline at end of file
+    let proxy = event_loop.create_proxy();
+    #[cfg(not(target_arch = "wasm32"))]
+    let keybinds = Keybinds::load(&args.keybinds);
+    #[cfg(target_arch = "wasm32")]
+    let keybinds = Keybinds::default();
+    let app = App { state: None, args, initial: Some(initial), session_log, replay, modifiers: ModifiersState::default(), keybinds, carried: None, proxy };
+    #[cfg(not(target_arch = "wasm32"))]
+    event_loop.run_app(&mut { app }).unwrap();
+    // Returns straight away, the browser drives the loop from here
+    #[cfg(target_arch = "wasm32")]
+    winit::platform::web::EventLoopExtWebSys::spawn_app(event_loop, app);
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    std::process::exit(1);
+}
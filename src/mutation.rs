@@ -0,0 +1,109 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use crate::rule::Rule;
+
+/// `rule` with one of its birth or survival bits flipped, picked by `rng`. B0 is left alone: a rule
+/// that births on empty neighbourhoods fills the grid on the next generation instead of evolving.
+pub fn mutate(rule: Rule, rng: &mut impl Rng) -> Rule {
+    match rng.gen_range(1..18) {
+        bit @ 1..=8 => Rule { birth: rule.birth ^ (1 << bit), ..rule },
+        bit => Rule { survive: rule.survive ^ (1 << (bit - 9)), ..rule },
+    }
+}
+
+/// Evolution mode: the rule mutates every `every` generations from its own seeded generator, so a seed
+/// always evolves the same way, and remembers the rule it mutated from for taking it back.
+pub struct Mutation {
+    every: usize,
+    rng: StdRng,
+    // Generation the next mutation is due at
+    next: usize,
+    // The rule before the latest mutation and the population it had then
+    previous: Option<(Rule, u32)>,
+}
+
+impl Mutation {
+    /// Mutates every `every` generations from `generation` on.
+    pub fn new(every: usize, seed: u64, generation: usize) -> Mutation {
+        Mutation { every, rng: StdRng::seed_from_u64(seed), next: generation + every, previous: None }
+    }
+
+    pub fn every(&self) -> usize {
+        self.every
+    }
+
+    /// Generation the next mutation is due at.
+    pub fn due_at(&self) -> usize {
+        self.next
+    }
+
+    /// `rule` mutated, if a mutation is due by `generation`; `population` is the grid's at the time. At
+    /// most one comes per call like rain drops, and going back in time brings the next one closer again.
+    pub fn due(&mut self, generation: usize, rule: Rule, population: u32) -> Option<Rule> {
+        self.next = self.next.min(generation + self.every);
+        if generation < self.next { return None; }
+        self.next = generation + self.every;
+        self.previous = Some((rule, population));
+        Some(mutate(rule, &mut self.rng))
+    }
+
+    /// Whether `population` has dropped below `floor` since the latest mutation, which a grid already
+    /// that empty before it hasn't.
+    pub fn endangered(&self, population: u32, floor: u32) -> bool {
+        self.previous.is_some_and(|(_, before)| population < floor && before >= floor)
+    }
+
+    /// The rule before the latest mutation, once; the next one is a full `every` after `generation`.
+    pub fn revert(&mut self, generation: usize) -> Option<Rule> {
+        self.next = generation + self.every;
+        self.previous.take().map(|(rule, _)| rule)
+    }
+
+    /// Counts from `generation` again, for when mutation is unlocked after a while.
+    pub fn restart(&mut self, generation: usize) {
+        self.next = generation + self.every;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mutations_flip_one_bit_and_follow_the_seed() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..200 {
+            let mutated = mutate(Rule::CONWAY, &mut rng);
+            let flipped = (mutated.birth ^ Rule::CONWAY.birth).count_ones() + (mutated.survive ^ Rule::CONWAY.survive).count_ones();
+            assert_eq!(flipped, 1);
+            assert_eq!(mutated.birth & 1, 0);
+            assert!(mutated.birth < 1 << 9 && mutated.survive < 1 << 9 && mutated.states == 2);
+        }
+        let rules = |seed| {
+            let mut mutation = Mutation::new(10, seed, 0);
+            let mut rule = Rule::CONWAY;
+            (0..100).filter_map(|generation| {
+                rule = mutation.due(generation, rule, 100)?;
+                Some(rule)
+            }).collect::<Vec<_>>()
+        };
+        assert_eq!(rules(5).len(), 9);
+        assert_eq!(rules(5), rules(5));
+        assert_ne!(rules(5), rules(6));
+    }
+
+    #[test]
+    fn reverts_once_to_the_rule_before() {
+        let mut mutation = Mutation::new(10, 1, 0);
+        let mutated = mutation.due(10, Rule::CONWAY, 500).unwrap();
+        assert_ne!(mutated, Rule::CONWAY);
+        assert!(mutation.endangered(40, 50));
+        assert!(!mutation.endangered(60, 50));
+        assert_eq!(mutation.revert(15), Some(Rule::CONWAY));
+        assert_eq!(mutation.revert(15), None);
+        assert!(!mutation.endangered(40, 50));
+        assert_eq!(mutation.due_at(), 25);
+        // A grid that was already sparse isn't blamed on the mutation
+        mutation.due(25, Rule::CONWAY, 30);
+        assert!(!mutation.endangered(20, 50));
+    }
+}
@@ -0,0 +1,370 @@
+use std::time::Duration;
+use winit::{event::WindowEvent, window::Window};
+use rust_gpu_life::forest_fire::ForestFire;
+use rust_gpu_life::gray_scott::GrayScott;
+use rust_gpu_life::lenia::{self, Lenia};
+use rust_gpu_life::regions::{self, RuleMap};
+use rust_gpu_life::rule::{Rule, RULE_PRESETS, preset_name};
+use rust_gpu_life::sparkline::PopulationGraph;
+use crate::hud::{self, HelpRow};
+
+// What the panel shows, copied out of the simulation each frame
+pub struct PanelState<'a> {
+    pub paused: bool,
+    pub target_rate: u32,
+    pub max_rate: u32,
+    pub rule: Rule,
+    pub density: f32,
+    pub using_cpu: bool,
+    pub generation: usize,
+    pub generations_per_second: f64,
+    pub population: u32,
+    pub population_graph: &'a PopulationGraph,
+    pub cells: usize,
+    pub frame_time: Duration,
+    // Snapshots held for stepping back, the most that fit, and their memory
+    pub history: usize,
+    pub history_capacity: usize,
+    pub history_bytes: usize,
+    // Only while the automaton is Gray-Scott
+    pub gray_scott: Option<GrayScott>,
+    // Only while the automaton is Lenia
+    pub lenia: Option<Lenia>,
+    // Only while the automaton is forest fire
+    pub forest_fire: Option<ForestFire>,
+    // Only while the automaton is Life, with the armed rule brush and whether painted tiles are tinted
+    pub regions: Option<&'a RuleMap>,
+    pub region_brush: Option<Rule>,
+    pub region_tint: bool,
+}
+
+// Changes made through the panel, applied by the caller after the frame's UI has run
+pub enum Action {
+    TogglePause,
+    SetRate(u32),
+    SetRule(Rule),
+    SetDensity(f32),
+    Reseed,
+    ToggleBackend,
+    SetGrayScott(GrayScott),
+    SetLenia(Lenia),
+    SetForestFire(ForestFire),
+    // Adds the rule to the saved custom rules under the name
+    SaveRule(String, Rule),
+    // Arms the rule brush with a rule, or puts it away
+    SetRegionBrush(Option<Rule>),
+    SetRegionTint(bool),
+    // Every tile back to the grid's rule
+    ClearRegions,
+}
+
+// egui side panel drawn over the grid; F1 hides it. The HUD text and the help's key list share its render
+// pass, H hides the HUD
+pub struct Overlay {
+    context: egui::Context,
+    winit: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    pub visible: bool,
+    pub hud: bool,
+    rule_text: String,
+    rule_error: Option<String>,
+    // Saved from the rule table, offered in the rule selector after the presets
+    pub custom_rules: Vec<(String, Rule)>,
+    rule_name: String,
+    // The rule brush's rulestring, kept while it isn't armed
+    region_text: String,
+    region_error: Option<String>,
+}
+
+impl Overlay {
+    pub fn new(window: &Window, device: &wgpu::Device, format: wgpu::TextureFormat, rule: Rule, custom_rules: Vec<(String, Rule)>) -> Overlay {
+        let context = egui::Context::default();
+        let winit = egui_winit::State::new(context.clone(), egui::ViewportId::ROOT, window, Some(window.scale_factor() as f32), None, None);
+        let renderer = egui_wgpu::Renderer::new(device, format, None, 1, false);
+        Overlay { context, winit, renderer, visible: true, hud: true, rule_text: rule.to_string(), rule_error: None, custom_rules, rule_name: String::new(), region_text: "B2/S".to_string(), region_error: None }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn toggle_hud(&mut self) {
+        self.hud = !self.hud;
+    }
+
+    // True when egui took the event, e.g. a click on the panel or typing into the rule field
+    pub fn on_window_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        self.visible && self.winit.on_window_event(window, event).consumed
+    }
+
+    // Runs the panel and draws it, the HUD `lines` and the `help` rows, if any, over `view`, which already
+    // holds the grid
+    #[allow(clippy::too_many_arguments)]
+    pub fn encode(&mut self, window: &Window, device: &wgpu::Device, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, state: &PanelState, lines: &[String], help: &[HelpRow]) -> Vec<Action> {
+        let mut actions = Vec::new();
+        if !self.visible && !self.hud && help.is_empty() { return actions; }
+        let input = self.winit.take_egui_input(window);
+        let context = self.context.clone();
+        let output = context.run(input, |context| {
+            if self.visible { self.panel(context, state, &mut actions); }
+            if self.hud { hud::show(context, lines); }
+            if !help.is_empty() { hud::show_help(context, help); }
+        });
+        self.winit.handle_platform_output(window, output.platform_output);
+
+        let jobs = self.context.tessellate(output.shapes, output.pixels_per_point);
+        let size = window.inner_size();
+        let screen = egui_wgpu::ScreenDescriptor { size_in_pixels: [size.width, size.height], pixels_per_point: output.pixels_per_point };
+        for (id, delta) in &output.textures_delta.set {
+            self.renderer.update_texture(device, queue, *id, delta);
+        }
+        let callbacks = self.renderer.update_buffers(device, queue, encoder, &jobs, &screen);
+        // Load, not clear: the panel goes on top of the grid
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("egui"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        }).forget_lifetime();
+        self.renderer.render(&mut rpass, &jobs, &screen);
+        drop(rpass);
+        queue.submit(callbacks);
+        for id in &output.textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+        actions
+    }
+
+    fn panel(&mut self, context: &egui::Context, state: &PanelState, actions: &mut Vec<Action>) {
+        egui::SidePanel::left("controls").resizable(false).show(context, |ui| {
+            egui::CollapsingHeader::new("Simulation").default_open(true).show(ui, |ui| {
+                if ui.button(if state.paused { "Resume" } else { "Pause" }).clicked() {
+                    actions.push(Action::TogglePause);
+                }
+                let mut rate = state.target_rate;
+                if ui.add(egui::Slider::new(&mut rate, 1..=state.max_rate).logarithmic(true).text("gen/s")).changed() {
+                    actions.push(Action::SetRate(rate));
+                }
+                if ui.button(if state.using_cpu { "Switch to GPU" } else { "Switch to CPU" }).clicked() {
+                    actions.push(Action::ToggleBackend);
+                }
+            });
+
+            egui::CollapsingHeader::new("Rule").default_open(true).show(ui, |ui| {
+                let current = state.rule.to_string();
+                let custom = self.custom_rules.iter().find(|(_, rule)| *rule == state.rule).map(|(name, _)| name.as_str());
+                let selected = preset_name(state.rule).or(custom).unwrap_or("Custom");
+                egui::ComboBox::from_id_salt("rule").selected_text(selected).show_ui(ui, |ui| {
+                    for (name, text) in RULE_PRESETS {
+                        if ui.selectable_label(text == current, name).clicked() {
+                            self.rule_text = text.to_string();
+                            self.rule_error = None;
+                            actions.push(Action::SetRule(Rule::parse(text).expect("presets are valid rules")));
+                        }
+                    }
+                    if !self.custom_rules.is_empty() { ui.separator(); }
+                    for (name, rule) in &self.custom_rules {
+                        if ui.selectable_label(*rule == state.rule, name).clicked() {
+                            self.rule_text = rule.to_string();
+                            self.rule_error = None;
+                            actions.push(Action::SetRule(*rule));
+                        }
+                    }
+                });
+                // Typed rules apply on Enter
+                let field = ui.text_edit_singleline(&mut self.rule_text);
+                if field.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                    match Rule::parse(&self.rule_text) {
+                        Ok(rule) => {
+                            self.rule_error = None;
+                            actions.push(Action::SetRule(rule));
+                        }
+                        Err(e) => self.rule_error = Some(e),
+                    }
+                }
+                if let Some(error) = &self.rule_error {
+                    ui.colored_label(egui::Color32::LIGHT_RED, error);
+                }
+                self.rule_table(ui, state.rule, actions);
+            });
+
+            if let Some(params) = state.gray_scott {
+                egui::CollapsingHeader::new("Gray-Scott").default_open(true).show(ui, |ui| {
+                    let mut changed = params;
+                    ui.add(egui::Slider::new(&mut changed.feed, 0.0..=0.1).text("feed"));
+                    ui.add(egui::Slider::new(&mut changed.kill, 0.0..=0.1).text("kill"));
+                    ui.add(egui::Slider::new(&mut changed.diffusion_u, 0.0..=1.0).text("diffusion u"));
+                    ui.add(egui::Slider::new(&mut changed.diffusion_v, 0.0..=1.0).text("diffusion v"));
+                    // Much past 1 the explicit step blows up
+                    ui.add(egui::Slider::new(&mut changed.dt, 0.1..=1.5).text("dt"));
+                    if changed != params {
+                        actions.push(Action::SetGrayScott(changed));
+                    }
+                });
+            }
+
+            if let Some(params) = state.lenia {
+                egui::CollapsingHeader::new("Lenia").default_open(true).show(ui, |ui| {
+                    let mut changed = params;
+                    ui.add(egui::Slider::new(&mut changed.radius, 2..=lenia::MAX_RADIUS).text("radius"));
+                    ui.add(egui::Slider::new(&mut changed.mu, 0.01..=0.5).text("mu"));
+                    ui.add(egui::Slider::new(&mut changed.sigma, 0.001..=0.1).logarithmic(true).text("sigma"));
+                    ui.add(egui::Slider::new(&mut changed.dt, 0.01..=1.0).text("dt"));
+                    if changed != params {
+                        actions.push(Action::SetLenia(changed));
+                    }
+                });
+            }
+
+            if let Some(params) = state.forest_fire {
+                egui::CollapsingHeader::new("Forest fire").default_open(true).show(ui, |ui| {
+                    let mut changed = params;
+                    ui.add(egui::Slider::new(&mut changed.growth, 0.0001..=0.1).logarithmic(true).text("growth p"));
+                    ui.add(egui::Slider::new(&mut changed.lightning, 0.0000001..=0.01).logarithmic(true).text("lightning f"));
+                    if changed != params {
+                        actions.push(Action::SetForestFire(changed));
+                    }
+                });
+            }
+
+            if let Some(map) = state.regions {
+                egui::CollapsingHeader::new("Regions").default_open(false).show(ui, |ui| self.regions(ui, map, state, actions));
+            }
+
+            egui::CollapsingHeader::new("Soup").default_open(true).show(ui, |ui| {
+                let mut density = state.density;
+                if ui.add(egui::Slider::new(&mut density, 0.0..=1.0).text("density")).changed() {
+                    actions.push(Action::SetDensity(density));
+                }
+                if ui.button("Re-randomize").clicked() {
+                    actions.push(Action::Reseed);
+                }
+            });
+
+            egui::CollapsingHeader::new("Stats").default_open(true).show(ui, |ui| {
+                ui.label(format!("Generation: {}", state.generation));
+                ui.label(format!("Rate: {:.0} gen/s", state.generations_per_second));
+                ui.label(format!("Population: {} ({:.1}%)", state.population, state.population as f64 / state.cells as f64 * 100.0));
+                population_graph(ui, state.population_graph);
+                ui.label(format!("Frame time: {:.2?}", state.frame_time));
+                ui.label(format!("History: {} of {} ({:.1} MB)", state.history, state.history_capacity, state.history_bytes as f64 / (1 << 20) as f64));
+            });
+            ui.separator();
+            ui.weak("F1 hides this panel, H the HUD, ? lists the keys");
+        });
+    }
+
+    // The rule brush and what the map holds: each painted rule with its tint and how many tiles run it.
+    // Typing a rule and pressing Enter arms the brush with it
+    fn regions(&mut self, ui: &mut egui::Ui, map: &RuleMap, state: &PanelState, actions: &mut Vec<Action>) {
+        ui.horizontal(|ui| {
+            let field = ui.add(egui::TextEdit::singleline(&mut self.region_text).desired_width(90.0));
+            let entered = field.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+            let mut armed = state.region_brush.is_some();
+            let toggled = ui.checkbox(&mut armed, "Brush")
+                .on_hover_text("Left drag paints the rule onto whole 32x32 tiles, right drag gives them back to the grid's rule; Ctrl+wheel sizes it")
+                .changed();
+            if toggled && !armed {
+                actions.push(Action::SetRegionBrush(None));
+            } else if toggled || entered {
+                match Rule::parse(&self.region_text).and_then(|rule| regions::check_rule(rule).map(|()| rule)) {
+                    Ok(rule) => {
+                        self.region_error = None;
+                        actions.push(Action::SetRegionBrush(Some(rule)));
+                    }
+                    Err(e) => self.region_error = Some(e),
+                }
+            }
+        });
+        if let Some(error) = &self.region_error {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+        let mut tint = state.region_tint;
+        if ui.checkbox(&mut tint, "Tint painted tiles").changed() {
+            actions.push(Action::SetRegionTint(tint));
+        }
+        let counts = map.counts();
+        ui.label(format!("{} (the grid's): {} tiles", state.rule, counts[0]));
+        for (index, (rule, count)) in map.rules().iter().zip(&counts[1..]).enumerate() {
+            ui.horizontal(|ui| {
+                // The hue render.wgsl tints the rule's tiles with
+                let hue = ((index + 1) as f32 * 0.382).fract();
+                ui.colored_label(egui::Color32::from(egui::ecolor::Hsva::new(hue, 1.0, 1.0, 1.0)), "■");
+                ui.label(format!("{}: {} tiles", rule, count));
+            });
+        }
+        if ui.add_enabled(!map.is_empty(), egui::Button::new("Clear")).clicked() {
+            actions.push(Action::ClearRegions);
+        }
+    }
+
+    // Birth and survival for each neighbour count, applied as soon as a box is ticked. Anything goes,
+    // B0 included
+    fn rule_table(&mut self, ui: &mut egui::Ui, rule: Rule, actions: &mut Vec<Action>) {
+        egui::Grid::new("rule_table").spacing([2.0, 2.0]).show(ui, |ui| {
+            ui.label("");
+            for count in 0..=8 {
+                ui.label(count.to_string());
+            }
+            ui.end_row();
+            for (label, survive) in [("B", false), ("S", true)] {
+                ui.label(label);
+                for count in 0..=8 {
+                    let mask = if survive { rule.survive } else { rule.birth };
+                    let mut set = mask >> count & 1 == 1;
+                    if ui.checkbox(&mut set, "").changed() {
+                        let mut changed = rule;
+                        let mask = if survive { &mut changed.survive } else { &mut changed.birth };
+                        *mask ^= 1 << count;
+                        self.rule_text = changed.to_string();
+                        self.rule_error = None;
+                        actions.push(Action::SetRule(changed));
+                    }
+                }
+                ui.end_row();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.rule_name).hint_text("name").desired_width(90.0));
+            let name = self.rule_name.trim();
+            if ui.add_enabled(!name.is_empty(), egui::Button::new("Save")).clicked() {
+                actions.push(Action::SaveRule(name.to_string(), rule));
+                self.rule_name.clear();
+            }
+            if ui.button("Copy").on_hover_text("Copy the rulestring to the clipboard").clicked() {
+                ui.output_mut(|output| output.copied_text = rule.to_string());
+            }
+        });
+    }
+}
+
+// Population over the last few thousand generations, scaled to its own min and max; hovering reads off a sample
+fn population_graph(ui: &mut egui::Ui, graph: &PopulationGraph) {
+    let size = egui::vec2(ui.available_width(), 48.0);
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    let (Some((low, high)), Some(&(first, _)), Some(&(last, _))) = (graph.range(), graph.samples().front(), graph.samples().back()) else { return };
+    let span = (last - first).max(1) as f32;
+    let point = |generation: usize, population: u32| {
+        let x = rect.left() + (generation - first) as f32 / span * rect.width();
+        let y = rect.bottom() - (population - low) as f32 / (high - low).max(1) as f32 * rect.height();
+        egui::pos2(x, y)
+    };
+    let points: Vec<egui::Pos2> = graph.samples().iter().map(|&(generation, population)| point(generation, population)).collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, egui::Color32::LIGHT_GREEN)));
+    painter.text(rect.left_top(), egui::Align2::LEFT_TOP, high.to_string(), egui::FontId::monospace(9.0), egui::Color32::GRAY);
+    painter.text(rect.left_bottom(), egui::Align2::LEFT_BOTTOM, low.to_string(), egui::FontId::monospace(9.0), egui::Color32::GRAY);
+
+    let Some(hover) = response.hover_pos() else { return };
+    let nearest = graph.samples().iter().min_by(|a, b| (point(a.0, a.1).x - hover.x).abs().total_cmp(&(point(b.0, b.1).x - hover.x).abs()));
+    if let Some(&(generation, population)) = nearest {
+        painter.circle_filled(point(generation, population), 2.5, egui::Color32::WHITE);
+        response.on_hover_text_at_pointer(format!("Generation {}: {}", generation, population));
+    }
+}
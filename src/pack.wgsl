@@ -0,0 +1,31 @@
+// History snapshots: 32 / bits cells per word, low bits first; with one bit only whether a cell is alive.
+// Walls are left out, the app puts them back itself
+struct Pack {
+    cells: u32,
+    bits: u32,
+    _pad: vec2<u32>,
+}
+
+// Matches WALL in lib.rs
+const WALL: u32 = 0xffffffffu;
+
+@group(0) @binding(0) var<storage, read> cellState: array<u32>;
+@group(0) @binding(1) var<storage, read_write> packed: array<u32>;
+@group(0) @binding(2) var<uniform> pack: Pack;
+
+// One word per invocation, dispatched as a 2D grid of 1D workgroups to stay under the per-dimension limit
+@compute @workgroup_size(256)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let word = global_id.y * groups.x * 256u + global_id.x;
+    let per_word = 32u / pack.bits;
+    let first = word * per_word;
+    if (first >= pack.cells) { return; }
+
+    var bits = 0u;
+    for (var i = 0u; i < per_word && first + i < pack.cells; i++) {
+        let state = cellState[first + i];
+        let value = select(select(min(state, 1u), state, pack.bits == 2u), 0u, state == WALL);
+        bits |= value << (i * pack.bits);
+    }
+    packed[word] = bits;
+}
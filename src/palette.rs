@@ -0,0 +1,235 @@
+use crate::config::{self, Line, Value};
+
+/// A colour as red, green and blue from 0 to 1, written to the surface as is
+pub type Rgb = [f32; 3];
+
+/// The colours the grid is drawn in. Every automaton picks from these: Life ages run down `live`, and
+/// the multi-state automata (Brian's Brain, Wireworld, Immigration, ants) take their extra states from
+/// `states`, so a theme only has to keep those three apart for all of them to stay readable.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Theme {
+    /// Around the grid, where the window's shape doesn't match it
+    pub background: Rgb,
+    pub dead: Rgb,
+    pub wall: Rgb,
+    /// Newborn, middle-aged and old live cells; also Generations' alive state and Gray-Scott's gradient
+    pub live: [Rgb; 3],
+    /// Wireworld's conductor, electron head and tail; the other multi-state automata reuse them
+    pub states: [Rgb; 3],
+    /// Cyclic states and ant colours go round the hue circle; otherwise they run down `live`, which
+    /// leaves lightness to tell them apart when hue can't
+    pub wheel: bool,
+}
+
+impl Theme {
+    /// The original colours: white-hot newborns cooling to blue on a dark blue field
+    pub const CLASSIC: Theme = Theme {
+        background: [0.1, 0.1, 0.3],
+        dead: [0.0, 0.0, 0.1],
+        wall: [0.45, 0.45, 0.45],
+        live: [[1.0, 0.9, 1.0], [0.6, 0.2, 1.0], [0.1, 0.35, 0.8]],
+        states: [[1.0, 0.8, 0.1], [0.2, 0.5, 1.0], [1.0, 0.2, 0.1]],
+        wheel: true,
+    };
+
+    /// Built-in themes by name, `--theme` and T go through them in this order
+    pub const PRESETS: [(&'static str, Theme); 5] = [
+        ("classic", Theme::CLASSIC),
+        ("green-phosphor", Theme {
+            background: [0.01, 0.03, 0.01],
+            dead: [0.0, 0.02, 0.0],
+            wall: [0.15, 0.3, 0.15],
+            live: [[0.75, 1.0, 0.7], [0.2, 0.9, 0.3], [0.05, 0.4, 0.1]],
+            states: [[0.85, 1.0, 0.75], [0.3, 0.75, 0.3], [0.1, 0.35, 0.12]],
+            wheel: false,
+        }),
+        // Matplotlib's inferno colour map
+        ("inferno", Theme {
+            background: [0.05, 0.02, 0.1],
+            dead: [0.0, 0.0, 0.016],
+            wall: [0.35, 0.35, 0.4],
+            live: [[0.988, 1.0, 0.643], [0.867, 0.3176, 0.227], [0.576, 0.149, 0.404]],
+            states: [[0.988, 1.0, 0.643], [0.576, 0.149, 0.404], [0.867, 0.3176, 0.227]],
+            wheel: false,
+        }),
+        // Matplotlib's viridis colour map, even in lightness and readable with any colour vision
+        ("viridis", Theme {
+            background: [0.1, 0.05, 0.15],
+            dead: [0.05, 0.01, 0.08],
+            wall: [0.5, 0.5, 0.5],
+            live: [[0.992, 0.906, 0.145], [0.129, 0.569, 0.549], [0.231, 0.322, 0.545]],
+            states: [[0.992, 0.906, 0.145], [0.231, 0.322, 0.545], [0.369, 0.788, 0.384]],
+            wheel: false,
+        }),
+        // White on black, with the Okabe-Ito yellow, blue and vermillion for the extra states, which stay
+        // apart under protanopia, deuteranopia and tritanopia (see the tests)
+        ("high-contrast", Theme {
+            background: [0.0, 0.0, 0.0],
+            dead: [0.0, 0.0, 0.0],
+            wall: [0.5, 0.5, 0.5],
+            live: [[1.0, 1.0, 1.0], [0.337, 0.706, 0.914], [0.0, 0.447, 0.698]],
+            states: [[0.941, 0.894, 0.259], [0.0, 0.447, 0.698], [0.835, 0.369, 0.0]],
+            wheel: false,
+        }),
+    ];
+
+    pub fn preset(name: &str) -> Option<Theme> {
+        Theme::PRESETS.iter().find(|(preset, _)| *preset == name).map(|&(_, theme)| theme)
+    }
+
+    pub fn clear_color(&self) -> wgpu::Color {
+        let [r, g, b] = self.background.map(f64::from);
+        wgpu::Color { r, g, b, a: 1.0 }
+    }
+
+    pub fn uniform(&self) -> PaletteUniform {
+        let rgba = |[r, g, b]: Rgb| [r, g, b, 1.0];
+        PaletteUniform {
+            background: rgba(self.background), dead: rgba(self.dead), wall: rgba(self.wall),
+            live: self.live.map(rgba), states: self.states.map(rgba), wheel: self.wheel as u32, _pad: [0; 3],
+        }
+    }
+}
+
+/// Uniform shared with the fragment shader (see `Palette` in render.wgsl)
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PaletteUniform {
+    background: [f32; 4],
+    dead: [f32; 4],
+    wall: [f32; 4],
+    live: [[f32; 4]; 3],
+    states: [[f32; 4]; 3],
+    wheel: u32,
+    _pad: [u32; 3],
+}
+
+/// "#1a2b3c" (the # is optional) -> its colour
+pub fn parse_hex(text: &str) -> Result<Rgb, String> {
+    let digits = text.strip_prefix('#').unwrap_or(text);
+    let invalid = || format!("invalid colour '{}' (expected #rrggbb)", text);
+    if digits.len() != 6 || !digits.is_ascii() { return Err(invalid()); }
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).map(|c| c as f32 / 255.0).map_err(|_| invalid());
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// Themes from a themes file: a `[name]` line starts each, followed by `key = value` lines overriding the
+/// theme named by `base` (classic if left out). Colours are quoted "#rrggbb", `live` and `states` lists of
+/// three, `wheel` true or false; # starts a comment. Bad lines come back as messages and are skipped.
+pub fn parse_themes(text: &str) -> (Vec<(String, Theme)>, Vec<String>) {
+    let mut themes: Vec<(String, Theme)> = Vec::new();
+    let mut errors = Vec::new();
+    for line in config::parse_sections(text) {
+        let result = line.and_then(|line| match line {
+            Line::Section(name) => {
+                themes.push((name, Theme::CLASSIC));
+                Ok(())
+            }
+            Line::Setting(setting) => match themes.split_last_mut() {
+                Some(((_, theme), earlier)) => set_field(theme, &setting.key, &setting.value, earlier),
+                None => Err("expected a [name] line before the theme's colours".to_string()),
+            }.map_err(|e| setting.error(e)),
+        });
+        if let Err(e) = result {
+            errors.push(e);
+        }
+    }
+    (themes, errors)
+}
+
+// `earlier` are the themes before this one in the file, which `base` can name as well as the presets
+fn set_field(theme: &mut Theme, key: &str, value: &Value, earlier: &[(String, Theme)]) -> Result<(), String> {
+    let colour = |value: &Value| parse_hex(value.quoted()?);
+    let three = |value: &Value| -> Result<[Rgb; 3], String> {
+        let Value::List(items) = value else { return Err("expected a list of three colours".to_string()) };
+        match items.iter().map(colour).collect::<Result<Vec<_>, _>>()?[..] {
+            [a, b, c] => Ok([a, b, c]),
+            _ => Err("expected a list of three colours".to_string()),
+        }
+    };
+    match key {
+        "base" => {
+            let name = value.quoted()?;
+            *theme = earlier.iter().rev().find(|(saved, _)| saved == name).map(|&(_, theme)| theme)
+                .or_else(|| Theme::preset(name)).ok_or_else(|| format!("unknown base theme '{}'", name))?;
+        }
+        "background" => theme.background = colour(value)?,
+        "dead" => theme.dead = colour(value)?,
+        "wall" => theme.wall = colour(value)?,
+        "live" => theme.live = three(value)?,
+        "states" => theme.states = three(value)?,
+        "wheel" => theme.wheel = value.text()?.parse().map_err(|_| format!("expected true or false, got {}", value))?,
+        other => return Err(format!("unknown key '{}' (expected base, background, dead, wall, live, states or wheel)", other)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Machado, Oliveira and Fernandes (2009) at full severity, on linear RGB
+    const DEFICIENCIES: [(&str, [[f32; 3]; 3]); 3] = [
+        ("protanopia", [[0.152286, 1.052583, -0.204868], [0.114503, 0.786281, 0.099216], [-0.003882, -0.048116, 1.051998]]),
+        ("deuteranopia", [[0.367322, 0.860646, -0.227968], [0.280085, 0.672501, 0.047413], [-0.011820, 0.042940, 0.968881]]),
+        ("tritanopia", [[1.255528, -0.076749, -0.178779], [-0.078411, 0.930809, 0.147602], [0.004733, 0.691367, 0.303900]]),
+    ];
+
+    fn linear(c: f32) -> f32 {
+        if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    }
+
+    // CIELAB of a linear RGB colour
+    fn lab([r, g, b]: Rgb) -> Rgb {
+        let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.9505;
+        let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+        let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.089;
+        let f = |t: f32| if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+        [116.0 * f(y) - 16.0, 500.0 * (f(x) - f(y)), 200.0 * (f(y) - f(z))]
+    }
+
+    // As seen with `matrix`, in CIELAB
+    fn seen(colour: Rgb, matrix: &[[f32; 3]; 3]) -> Rgb {
+        let linear = colour.map(linear);
+        lab(matrix.map(|row| (row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2]).clamp(0.0, 1.0)))
+    }
+
+    #[test]
+    fn high_contrast_states_stay_apart_without_colour_vision() {
+        let theme = Theme::preset("high-contrast").unwrap();
+        let colours = [theme.dead, theme.states[0], theme.states[1], theme.states[2]];
+        for (name, matrix) in &DEFICIENCIES {
+            for (i, a) in colours.iter().enumerate() {
+                for b in &colours[i + 1..] {
+                    let (a, b) = (seen(*a, matrix), seen(*b, matrix));
+                    let difference = a.iter().zip(b).map(|(a, b)| (a - b).powi(2)).sum::<f32>().sqrt();
+                    // A CIELAB difference of 30 is plain at a glance
+                    assert!(difference > 30.0, "{:?} and {:?} are only {} apart with {}", a, b, difference, name);
+                }
+            }
+        }
+        // Without the wheel, cyclic states and ant colours darken down `live`
+        for (_, theme) in Theme::PRESETS.iter().filter(|(_, theme)| !theme.wheel) {
+            let lightness = theme.live.map(|colour| lab(colour.map(linear))[0]);
+            assert!(lightness[0] > lightness[1] && lightness[1] > lightness[2]);
+        }
+    }
+
+    #[test]
+    fn themes_file_overrides_a_base() {
+        let text = "# mine\n[dusk]\nbase = \"inferno\"\ndead = \"#000000\" # pitch black\nlive = [\"#ffffff\", \"#ff8800\", \"#883300\"]\n\
+                    wheel = true\ncolour = \"#123456\"\n[dawn]\nbase = \"dusk\"\nwall = \"#80808\"\n";
+        let (themes, errors) = parse_themes(text);
+        assert_eq!(themes.len(), 2);
+        let (name, dusk) = &themes[0];
+        assert_eq!(name, "dusk");
+        assert_eq!(dusk.dead, [0.0; 3]);
+        assert_eq!(dusk.live[1], [1.0, 136.0 / 255.0, 0.0]);
+        assert_eq!(dusk.states, Theme::preset("inferno").unwrap().states);
+        assert!(dusk.wheel);
+        // Built on dusk, with the bad wall left out
+        assert_eq!(themes[1].1, *dusk);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("line 7") && errors[1].starts_with("line 10"));
+    }
+}
@@ -0,0 +1,608 @@
+use std::path::Path;
+
+// Golly keeps RLE body lines under this length
+const RLE_LINE_LENGTH: usize = 70;
+
+/// Highest state RLE's 'A' to 'X' notation can write
+pub const MAX_RLE_STATE: u32 = 24;
+
+// First line of a Life 1.06 file
+const LIFE_106_HEADER: &str = "#Life 1.06";
+
+// First line of a Golly macrocell file, before the program that wrote it
+const MACROCELL_HEADER: &str = "[M2]";
+
+// A pattern file can describe more cells than fit in memory; this many live cells is about 800 MB expanded
+const MAX_CELLS: usize = 1 << 25;
+
+// Rows or columns a pattern may span, far past any grid it could be placed on
+const MAX_EXTENT: usize = 1 << 24;
+
+// Dead cells between two live ones that `row_spans` writes rather than starting another span
+const SPAN_GAP: usize = 32;
+
+// A clock loop feeding two wires, each through a diode: electrons pass the top one and stop at the bottom one
+const WIREWORLD_DEMO: &str = "x = 36, y = 13, rule = WireWorld\n18.2A$12.7A.16A$12.A5.2A$12.A$.4A7.A$A4.A6.A$A4.7A$A4.A6.A$.CB2A7.A$12.A$12.A6.2A$12.7A.16A$19.2A!";
+
+/// The pattern file formats, told apart by extension
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Rle,
+    Plaintext,
+    Life106,
+    /// Golly's quadtree format, read only
+    Macrocell,
+}
+
+impl Format {
+    /// .cells or .txt for plaintext, .lif or .life for Life 1.06, .mc for macrocell, RLE for anything else
+    pub fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("cells" | "txt") => Format::Plaintext,
+            Some("lif" | "life") => Format::Life106,
+            Some("mc") => Format::Macrocell,
+            _ => Format::Rle,
+        }
+    }
+
+    /// Whether the format has a line for the rule; the others drop it
+    pub fn holds_rule(self) -> bool {
+        matches!(self, Format::Rle | Format::Macrocell)
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Format::Rle => "RLE",
+            Format::Plaintext => "plaintext",
+            Format::Life106 => "Life 1.06",
+            Format::Macrocell => "macrocell",
+        })
+    }
+}
+
+/// A decoded pattern: (x, y, state) of every non-dead cell relative to the top-left of its bounding box.
+/// Two-state patterns only use state 1.
+pub struct Pattern {
+    pub width: usize,
+    pub height: usize,
+    pub rule: Option<String>,
+    pub cells: Vec<(usize, usize, u32)>,
+}
+
+impl Pattern {
+    /// In the format `Format::from_path` picks for the file
+    pub fn load(path: &Path) -> Result<Pattern, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("could not read pattern '{}': {}", path.display(), e))?;
+        Pattern::parse(&text, Format::from_path(path)).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Writes the format `load` would read back from `path`
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = self.serialize(Format::from_path(path)).map_err(|e| format!("{}: {}", path.display(), e))?;
+        std::fs::write(path, text).map_err(|e| format!("could not write pattern '{}': {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str, format: Format) -> Result<Pattern, String> {
+        match format {
+            Format::Rle => Pattern::parse_rle(text),
+            Format::Plaintext => Pattern::parse_plaintext(text),
+            Format::Life106 => Pattern::parse_life106(text),
+            Format::Macrocell => Pattern::parse_macrocell(text),
+        }
+    }
+
+    /// The text `parse` reads back, or why the pattern doesn't fit the format
+    pub fn serialize(&self, format: Format) -> Result<String, String> {
+        match format {
+            Format::Rle => match self.cells.iter().map(|&(_, _, state)| state).max() {
+                Some(state) if state > MAX_RLE_STATE => Err(format!("RLE only holds states up to {}, the pattern has state {}", MAX_RLE_STATE, state)),
+                _ => Ok(self.to_rle()),
+            },
+            Format::Plaintext => self.to_plaintext(),
+            Format::Life106 => self.to_life106(),
+            Format::Macrocell => Err("macrocell files can be read but not written (write .rle instead)".to_string()),
+        }
+    }
+
+    /// Where the live cells are, as (left, top, width, height) within the pattern; None when it's empty
+    pub fn bounding_box(&self) -> Option<(usize, usize, usize, usize)> {
+        let (left, right) = (self.cells.iter().map(|&(x, _, _)| x).min()?, self.cells.iter().map(|&(x, _, _)| x).max()?);
+        let (top, bottom) = (self.cells.iter().map(|&(_, y, _)| y).min()?, self.cells.iter().map(|&(_, y, _)| y).max()?);
+        Some((left, top, right - left + 1, bottom - top + 1))
+    }
+
+    /// Built-in Wireworld circuit, since random soup makes no sense there
+    pub fn wireworld_demo() -> Pattern {
+        Pattern::parse_rle(WIREWORLD_DEMO).expect("the built-in circuit is valid RLE")
+    }
+
+    /// Golly RLE: '#' comment lines, a "x = m, y = n, rule = ..." header, then a run-length body.
+    /// Multi-state bodies use '.' for state 0 and 'A' onwards for states 1 to 24.
+    pub fn parse_rle(text: &str) -> Result<Pattern, String> {
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#'));
+
+        let header = lines.next().ok_or("empty pattern file")?;
+        let (mut width, mut height, mut rule) = (None, None, None);
+        for field in header.split(',') {
+            let (key, value) = field.split_once('=').ok_or_else(|| format!("malformed header field '{}'", field.trim()))?;
+            let value = value.trim();
+            match key.trim() {
+                "x" => width = Some(value.parse::<usize>().map_err(|_| format!("invalid width '{}'", value))?),
+                "y" => height = Some(value.parse::<usize>().map_err(|_| format!("invalid height '{}'", value))?),
+                "rule" => rule = Some(value.to_string()),
+                _ => {} // Unknown header keys are ignored, as Golly does
+            }
+        }
+        let width = width.ok_or("header is missing 'x = ...'")?;
+        let height = height.ok_or("header is missing 'y = ...'")?;
+        if width > MAX_EXTENT || height > MAX_EXTENT { return Err(too_large()); }
+
+        // Runs are checked before they're taken, so a short body can't ask for more than fits in memory
+        let mut cells = Vec::new();
+        let (mut x, mut y) = (0usize, 0usize);
+        let mut run: Option<usize> = None;
+        'body: for line in lines {
+            for c in line.chars() {
+                match c {
+                    '0'..='9' => {
+                        let digit = c as usize - '0' as usize;
+                        run = Some(run.unwrap_or(0).checked_mul(10).and_then(|r| r.checked_add(digit)).ok_or("run count overflow")?);
+                        continue;
+                    }
+                    'b' | '.' => x = within_extent(x, run.unwrap_or(1))?,
+                    '$' => { y = within_extent(y, run.unwrap_or(1))?; x = 0; }
+                    '!' => break 'body,
+                    c if c.is_ascii_alphabetic() => {
+                        // Any other letter is a live cell, as in Golly's two-state reader
+                        let state = match c {
+                            'A'..='X' => c as u32 - 'A' as u32 + 1,
+                            _ => 1,
+                        };
+                        let end = within_extent(x, run.unwrap_or(1))?;
+                        if y >= MAX_EXTENT { return Err(too_large()); }
+                        if cells.len() + (end - x) > MAX_CELLS {
+                            return Err(format!("pattern too large: more than the {} live cells that can be read", MAX_CELLS));
+                        }
+                        cells.extend((x..end).map(|x| (x, y, state)));
+                        x = end;
+                    }
+                    c if c.is_whitespace() => {}
+                    c => return Err(format!("unexpected character '{}' in pattern body", c)),
+                }
+                run = None;
+            }
+        }
+
+        // Trust the body over the header if they disagree
+        let width = cells.iter().map(|&(x, _, _)| x + 1).max().unwrap_or(0).max(width);
+        let height = cells.iter().map(|&(_, y, _)| y + 1).max().unwrap_or(0).max(height);
+        Ok(Pattern { width, height, rule, cells })
+    }
+
+    /// Plaintext (.cells): '!' comment lines, then one line per row with 'O' (or '*') for live cells and '.'
+    /// for dead ones. Rows may stop short; an empty line is an empty row.
+    pub fn parse_plaintext(text: &str) -> Result<Pattern, String> {
+        let mut cells = Vec::new();
+        let rows: Vec<&str> = text.lines().map(str::trim_end).filter(|l| !l.starts_with('!')).collect();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                match c {
+                    'O' | '*' => cells.push((x, y, 1)),
+                    '.' => {}
+                    c => return Err(format!("unexpected character '{}' in row {}", c, y + 1)),
+                }
+            }
+        }
+        let width = rows.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+        Ok(Pattern { width, height: rows.len(), rule: None, cells })
+    }
+
+    /// Life 1.06: a "#Life 1.06" line, then one "x y" line per live cell, relative to any origin. The pattern
+    /// starts at the top-left live cell.
+    pub fn parse_life106(text: &str) -> Result<Pattern, String> {
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+        match lines.next() {
+            Some(LIFE_106_HEADER) => {}
+            Some(header) if header.starts_with("#Life") => return Err(format!("'{}' isn't supported, only Life 1.06", header)),
+            _ => return Err(format!("expected a '{}' line first", LIFE_106_HEADER)),
+        }
+        let mut points = Vec::new();
+        for line in lines.filter(|l| !l.starts_with('#')) {
+            let coordinate = |field: Option<&str>| field.and_then(|f| f.parse::<i64>().ok()).ok_or_else(|| format!("expected 'x y', got '{}'", line));
+            let mut fields = line.split_whitespace();
+            let point = (coordinate(fields.next())?, coordinate(fields.next())?);
+            if fields.next().is_some() { return Err(format!("expected 'x y', got '{}'", line)); }
+            points.push(point);
+        }
+        let left = points.iter().map(|&(x, _)| x).min().unwrap_or(0);
+        let top = points.iter().map(|&(_, y)| y).min().unwrap_or(0);
+        // Coordinates far apart would overflow the subtraction or span more than any grid
+        let offset = |at: i64, from: i64| at.checked_sub(from).and_then(|d| usize::try_from(d).ok()).filter(|&d| d < MAX_EXTENT).ok_or_else(too_large);
+        let mut cells = points.iter().map(|&(x, y)| Ok((offset(x, left)?, offset(y, top)?, 1))).collect::<Result<Vec<_>, String>>()?;
+        // Listed in any order, possibly twice
+        cells.sort_unstable_by_key(|&(x, y, _)| (y, x));
+        cells.dedup();
+        let width = cells.iter().map(|&(x, _, _)| x + 1).max().unwrap_or(0);
+        let height = cells.last().map_or(0, |&(_, y, _)| y + 1);
+        Ok(Pattern { width, height, rule: None, cells })
+    }
+
+    /// Golly macrocell: a "[M2]" line, '#R' with the rule among other '#' lines, then one quadtree node per
+    /// line, numbered from 1. A line of '.', '*' and '$' is an 8x8 leaf, each row ended by '$' with trailing
+    /// dead cells left out; "level nw ne sw se" is a 2^level square of four earlier nodes (0 for an empty
+    /// quadrant), and multi-state files give level 1 nodes as four states instead. The last node holds the
+    /// whole pattern, which starts at its top-left live cell.
+    pub fn parse_macrocell(text: &str) -> Result<Pattern, String> {
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+        match lines.next() {
+            Some(header) if header.starts_with(MACROCELL_HEADER) => {}
+            _ => return Err(format!("expected a '{}' line first", MACROCELL_HEADER)),
+        }
+        let mut rule = None;
+        let mut nodes: Vec<(u32, Node)> = Vec::new();
+        // Live cells under each node, so a small file describing far too many is refused before expanding it
+        let mut populations: Vec<u64> = Vec::new();
+        for line in lines {
+            if let Some(comment) = line.strip_prefix('#') {
+                if let Some(text) = comment.strip_prefix('R') { rule = Some(text.trim().to_string()); }
+                continue;
+            }
+            if line.starts_with(['.', '*', '$']) {
+                let bits = parse_leaf(line)?;
+                nodes.push((3, Node::Leaf(bits)));
+                populations.push(bits.count_ones() as u64);
+                continue;
+            }
+            let fields: Result<Vec<u64>, _> = line.split_whitespace().map(str::parse).collect();
+            let Ok(&[level, nw, ne, sw, se]) = fields.as_deref() else { return Err(format!("expected 'level nw ne sw se', got '{}'", line)) };
+            match level {
+                1 => {
+                    let states = [nw, ne, sw, se].map(|state| state as u32);
+                    nodes.push((1, Node::States(states)));
+                    populations.push(states.iter().filter(|&&state| state > 0).count() as u64);
+                }
+                2..=63 => {
+                    let children = [nw, ne, sw, se].map(|child| child as usize);
+                    let mut population = 0u64;
+                    for &child in children.iter().filter(|&&child| child > 0) {
+                        match nodes.get(child - 1) {
+                            Some(&(child_level, _)) if child_level as u64 == level - 1 => population = population.saturating_add(populations[child - 1]),
+                            Some(&(child_level, _)) => return Err(format!("node {} is level {}, too big or small for a quadrant of level {}", child, child_level, level)),
+                            None => return Err(format!("node {} refers to node {}, which doesn't come before it", nodes.len() + 1, child)),
+                        }
+                    }
+                    nodes.push((level as u32, Node::Branch(children)));
+                    populations.push(population);
+                }
+                _ => return Err(format!("level {} is out of range (1 to 63)", level)),
+            }
+        }
+
+        let population = populations.last().copied().unwrap_or(0);
+        if population > MAX_CELLS as u64 {
+            return Err(format!("the pattern has {} live cells, more than the {} that can be expanded", population, MAX_CELLS));
+        }
+        let mut points = Vec::with_capacity(population as usize);
+        expand(&nodes, nodes.len(), (0, 0), &mut points);
+        let left = points.iter().map(|&(x, _, _)| x).min().unwrap_or(0);
+        let top = points.iter().map(|&(_, y, _)| y).min().unwrap_or(0);
+        let mut cells: Vec<(usize, usize, u32)> = points.iter().map(|&(x, y, state)| ((x - left) as usize, (y - top) as usize, state)).collect();
+        cells.sort_unstable_by_key(|&(x, y, _)| (y, x));
+        let width = cells.iter().map(|&(x, _, _)| x + 1).max().unwrap_or(0);
+        let height = cells.last().map_or(0, |&(_, y, _)| y + 1);
+        Ok(Pattern { width, height, rule, cells })
+    }
+
+    /// The non-zero cells of a row-major `width`-wide block, values taken as states
+    pub fn from_cells(width: usize, cells: &[u32], rule: Option<String>) -> Pattern {
+        let height = cells.len().checked_div(width).unwrap_or(0);
+        let cells = (0..width * height).filter(|&i| cells[i] > 0).map(|i| (i % width, i / width, cells[i])).collect();
+        Pattern { width, height, rule, cells }
+    }
+
+    /// Golly RLE that `parse_rle` reads back; trailing dead cells and rows are left out.
+    /// Patterns with states above 1 are written in the multi-state notation.
+    pub fn to_rle(&self) -> String {
+        let mut grid = vec![0; self.width * self.height];
+        for &(x, y, state) in &self.cells {
+            grid[y * self.width + x] = state;
+        }
+        let multi_state = grid.iter().any(|&state| state > 1);
+        let tag = |state: u32| match (state, multi_state) {
+            (0, false) => 'b',
+            (_, false) => 'o',
+            (0, true) => '.',
+            (state, true) => char::from(b'A' + (state - 1).min(23) as u8),
+        };
+
+        let mut tokens = Vec::new();
+        let mut rows_pending = 0;
+        for row in grid.chunks(self.width.max(1)) {
+            let mut x = 0;
+            while x < row.len() {
+                let state = row[x];
+                let run = row[x..].iter().take_while(|&&cell| cell == state).count();
+                x += run;
+                if state == 0 && x == row.len() { break; }
+                if rows_pending > 0 {
+                    tokens.push(run_token(rows_pending, '$'));
+                    rows_pending = 0;
+                }
+                tokens.push(run_token(run, tag(state)));
+            }
+            rows_pending += 1;
+        }
+        tokens.push("!".to_string());
+
+        let mut text = format!("x = {}, y = {}", self.width, self.height);
+        if let Some(rule) = &self.rule {
+            text += &format!(", rule = {}", rule);
+        }
+        let mut line = String::new();
+        for token in tokens {
+            if line.len() + token.len() > RLE_LINE_LENGTH {
+                text += &format!("\n{}", line);
+                line.clear();
+            }
+            line += &token;
+        }
+        text + "\n" + &line + "\n"
+    }
+
+    /// Plaintext that `parse_plaintext` reads back, with trailing dead cells left out. There's no room for the
+    /// rule or for more than two states.
+    pub fn to_plaintext(&self) -> Result<String, String> {
+        if self.cells.iter().any(|&(_, _, state)| state > 1) {
+            return Err("plaintext only holds two states (write .rle instead)".to_string());
+        }
+        let mut rows = vec![Vec::new(); self.height];
+        for &(x, y, _) in &self.cells {
+            let row = &mut rows[y];
+            if row.len() <= x { row.resize(x + 1, '.'); }
+            row[x] = 'O';
+        }
+        Ok(rows.iter().map(|row| row.iter().collect::<String>() + "\n").collect())
+    }
+
+    /// Life 1.06 that `parse_life106` reads back, row by row. Like plaintext it has no room for the rule
+    /// or for more than two states.
+    pub fn to_life106(&self) -> Result<String, String> {
+        if self.cells.iter().any(|&(_, _, state)| state > 1) {
+            return Err("Life 1.06 only holds two states (write .rle instead)".to_string());
+        }
+        let mut cells = self.cells.clone();
+        cells.sort_unstable_by_key(|&(x, y, _)| (y, x));
+        Ok(cells.iter().fold(format!("{}\n", LIFE_106_HEADER), |text, &(x, y, _)| text + &format!("{} {}\n", x, y)))
+    }
+
+    pub fn fits(&self, grid_width: usize, grid_height: usize) -> Result<(), String> {
+        if self.width > grid_width || self.height > grid_height {
+            return Err(format!("pattern is {}x{} but the grid is only {}x{}", self.width, self.height, grid_width, grid_height));
+        }
+        Ok(())
+    }
+
+    /// The cells that land on a `grid_width` x `grid_height` grid with the pattern's top-left corner at `origin`
+    /// (centered without one), and how many fell off its edges
+    pub fn place(&self, grid_width: usize, grid_height: usize, origin: Option<(i64, i64)>) -> (Vec<(usize, usize, u32)>, usize) {
+        let (ox, oy) = origin.unwrap_or(((grid_width as i64 - self.width as i64) / 2, (grid_height as i64 - self.height as i64) / 2));
+        let placed: Vec<(usize, usize, u32)> = self.cells.iter().filter_map(|&(x, y, state)| {
+            let (x, y) = (ox + x as i64, oy + y as i64);
+            ((0..grid_width as i64).contains(&x) && (0..grid_height as i64).contains(&y)).then_some((x as usize, y as usize, state))
+        }).collect();
+        let clipped = self.cells.len() - placed.len();
+        (placed, clipped)
+    }
+
+    /// Writes the cells' states into a `grid_width`-wide grid, centered
+    pub fn write_centered(&self, grid: &mut [u32], grid_width: usize) -> Result<(), String> {
+        let grid_height = grid.len() / grid_width;
+        self.fits(grid_width, grid_height)?;
+        let ox = (grid_width - self.width) / 2;
+        let oy = (grid_height - self.height) / 2;
+        for &(x, y, state) in &self.cells {
+            grid[(oy + y) * grid_width + ox + x] = state;
+        }
+        Ok(())
+    }
+}
+
+// `start` moved on by `run` cells, as long as that stays within the largest pattern
+fn within_extent(start: usize, run: usize) -> Result<usize, String> {
+    start.checked_add(run).filter(|&end| end <= MAX_EXTENT).ok_or_else(too_large)
+}
+
+fn too_large() -> String {
+    format!("pattern too large: more than {} cells across or down", MAX_EXTENT)
+}
+
+fn run_token(run: usize, tag: char) -> String {
+    if run == 1 { tag.to_string() } else { format!("{}{}", run, tag) }
+}
+
+/// Placed cells (as `Pattern::place` gives them) as (x, y, states) spans along their rows, for writing a
+/// sparse pattern a span at a time instead of as a whole grid. Cells less than `SPAN_GAP` dead cells apart
+/// share a span, which holds the dead cells between them too.
+pub fn row_spans(cells: &[(usize, usize, u32)]) -> Vec<(usize, usize, Vec<u32>)> {
+    let mut sorted = cells.to_vec();
+    sorted.sort_unstable_by_key(|&(x, y, _)| (y, x));
+    let mut spans: Vec<(usize, usize, Vec<u32>)> = Vec::new();
+    for (x, y, state) in sorted {
+        match spans.last_mut() {
+            Some((start, row, states)) if *row == y && x <= *start + states.len() + SPAN_GAP => {
+                states.resize(x - *start, 0);
+                states.push(state);
+            }
+            _ => spans.push((x, y, vec![state])),
+        }
+    }
+    spans
+}
+
+// A macrocell node: an 8x8 leaf of live cells as bits (y * 8 + x), a 2x2 block of states (level 1, in
+// multi-state files), or the nw, ne, sw and se quadrants as node numbers (0 for empty)
+enum Node {
+    Leaf(u64),
+    States([u32; 4]),
+    Branch([usize; 4]),
+}
+
+// An 8x8 leaf line such as ".*$..*$***$": '*' live, '.' dead, '$' ends a row
+fn parse_leaf(line: &str) -> Result<u64, String> {
+    let (mut bits, mut x, mut y) = (0u64, 0, 0);
+    for c in line.chars() {
+        if c == '$' {
+            (x, y) = (0, y + 1);
+            continue;
+        }
+        if x >= 8 || y >= 8 { return Err(format!("leaf '{}' is larger than 8x8", line)); }
+        match c {
+            '*' => bits |= 1 << (y * 8 + x),
+            '.' => {}
+            _ => return Err(format!("unexpected '{}' in leaf '{}'", c, line)),
+        }
+        x += 1;
+    }
+    Ok(bits)
+}
+
+// The (x, y, state) of every live cell under node `index` (1-based, 0 is empty) with its corner at `corner`
+fn expand(nodes: &[(u32, Node)], index: usize, corner: (u64, u64), cells: &mut Vec<(u64, u64, u32)>) {
+    if index == 0 { return; }
+    let (x, y) = corner;
+    match &nodes[index - 1] {
+        (_, Node::Leaf(bits)) => cells.extend((0..64).filter(|bit| (bits >> bit) & 1 == 1).map(|bit| (x + bit % 8, y + bit / 8, 1))),
+        (_, Node::States(states)) => cells.extend((0..4).filter(|&i| states[i] > 0).map(|i| (x + i as u64 % 2, y + i as u64 / 2, states[i]))),
+        (level, Node::Branch(children)) => {
+            let half = 1u64 << (level - 1);
+            for (i, &child) in children.iter().enumerate() {
+                expand(nodes, child, (x + half * (i as u64 % 2), y + half * (i as u64 / 2)), cells);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rle_round_trips() {
+        let glider = Pattern::parse_rle("x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!").unwrap();
+        assert_eq!(glider.to_rle(), "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+
+        // Blank rows collapse into one run, long bodies wrap
+        let mut cells = vec![0; 100 * 5];
+        cells[0] = 1;
+        cells.iter_mut().skip(400).step_by(2).for_each(|cell| *cell = 1);
+        let sparse = Pattern::from_cells(100, &cells, None);
+        let rle = sparse.to_rle();
+        assert!(rle.starts_with("x = 100, y = 5\no4$obo"));
+        assert!(rle.lines().all(|line| line.len() <= RLE_LINE_LENGTH));
+        let parsed = Pattern::parse_rle(&rle).unwrap();
+        assert_eq!((parsed.width, parsed.height, parsed.cells), (100, 5, sparse.cells));
+    }
+
+    #[test]
+    fn rle_past_the_largest_pattern_is_an_error() {
+        for body in ["18446744073709551615$2$o!", "18446744073709551615b2bo!", "100000000000o!", "16777216bo!", "16777216$o!"] {
+            let error = Pattern::parse_rle(&format!("x = 1, y = 1\n{}", body)).err().unwrap();
+            assert!(error.starts_with("pattern too large"), "{}: {}", body, error);
+        }
+        assert!(Pattern::parse_rle("x = 100000000, y = 1\no!").err().unwrap().starts_with("pattern too large"));
+        // Up to the edge is fine
+        let edge = Pattern::parse_rle("x = 1, y = 1\n16777215bo!").unwrap();
+        assert_eq!((edge.width, edge.cells), (MAX_EXTENT, vec![(MAX_EXTENT - 1, 0, 1)]));
+    }
+
+    #[test]
+    fn life106_past_the_largest_pattern_is_an_error() {
+        for points in [format!("{} 0\n{} 0", i64::MIN, i64::MAX), format!("0 {}\n0 {}", i64::MAX, i64::MIN), "0 0\n16777216 0".to_string()] {
+            let error = Pattern::parse_life106(&format!("#Life 1.06\n{}", points)).err().unwrap();
+            assert!(error.starts_with("pattern too large"), "{}: {}", points, error);
+        }
+        let edge = Pattern::parse_life106(&format!("#Life 1.06\n{} 0\n{} 0", i64::MAX - 16777215, i64::MAX)).unwrap();
+        assert_eq!((edge.width, edge.height), (MAX_EXTENT, 1));
+    }
+
+    #[test]
+    fn multi_state_round_trips() {
+        let demo = Pattern::wireworld_demo();
+        assert_eq!((demo.width, demo.height), (36, 13));
+        assert!(demo.cells.contains(&(1, 8, 3)) && demo.cells.contains(&(2, 8, 2)));
+        let parsed = Pattern::parse_rle(&demo.to_rle()).unwrap();
+        assert_eq!((parsed.rule.as_deref(), parsed.cells), (Some("WireWorld"), demo.cells));
+    }
+
+    #[test]
+    fn plaintext_round_trips() {
+        let glider = Pattern::parse_plaintext("!Name: Glider\n.O\n..O\nOOO\n").unwrap();
+        assert_eq!((glider.width, glider.height), (3, 3));
+        assert_eq!(glider.cells, Pattern::parse_rle("x = 3, y = 3\nbo$2bo$3o!").unwrap().cells);
+        assert_eq!(glider.to_plaintext().unwrap(), ".O\n..O\nOOO\n");
+
+        // Blank rows survive, letters other than O don't parse, and more than two states don't fit
+        let gapped = Pattern::parse_plaintext("O\n\n.O").unwrap();
+        assert_eq!((gapped.height, gapped.to_plaintext().unwrap()), (3, "O\n\n.O\n".to_string()));
+        assert!(Pattern::parse_plaintext("OX").is_err());
+        assert!(Pattern::wireworld_demo().to_plaintext().is_err());
+    }
+
+    #[test]
+    fn life106_round_trips() {
+        let glider = Pattern::parse_life106("#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n1 1\n").unwrap();
+        assert_eq!((glider.width, glider.height), (3, 3));
+        assert_eq!(glider.cells, Pattern::parse_rle("x = 3, y = 3\nbo$2bo$3o!").unwrap().cells);
+        assert_eq!(glider.to_life106().unwrap(), "#Life 1.06\n1 0\n2 1\n0 2\n1 2\n2 2\n");
+
+        assert!(Pattern::parse_life106("#Life 1.05\n#P 0 0\n.O").err().unwrap().contains("1.05"));
+        assert!(Pattern::parse_life106("#Life 1.06\n1 2 3").is_err());
+        assert!(Pattern::wireworld_demo().to_life106().is_err());
+    }
+
+    #[test]
+    fn every_format_round_trips_within_its_limits() {
+        let glider = Pattern::parse_rle("x = 5, y = 4, rule = B3/S23\n$2bo$3bo$b3o!").unwrap();
+        assert_eq!(glider.bounding_box(), Some((1, 1, 3, 3)));
+        for format in [Format::Rle, Format::Plaintext, Format::Life106] {
+            let parsed = Pattern::parse(&glider.serialize(format).unwrap(), format).unwrap();
+            assert_eq!(parsed.rule.is_some(), format.holds_rule());
+            assert_eq!(parsed.bounding_box().map(|(_, _, width, height)| (width, height)), Some((3, 3)));
+            assert_eq!(parsed.cells.len(), 5);
+        }
+
+        let mut cells = vec![0; 30];
+        (0..30).for_each(|state| cells[state] = state as u32);
+        let cyclic = Pattern::from_cells(30, &cells, None);
+        assert!(cyclic.serialize(Format::Rle).unwrap_err().contains("state 29"));
+        assert_eq!(Pattern::from_cells(3, &[], None).bounding_box(), None);
+        assert_eq!(Format::from_path(Path::new("gun.lif")), Format::Life106);
+    }
+
+    #[test]
+    fn macrocell_expands_nested_nodes() {
+        // Node 1 is a glider, node 2 one cell in its leaf's bottom row; node 3 puts them in opposite corners of
+        // a 16x16 square, and node 4 repeats that square down its diagonal
+        let text = "[M2] (golly 4.2)\n#R B3/S23\n#G 0\n.*$..*$***$\n$$$$$$$*$\n4 1 0 0 2\n5 3 0 0 3\n";
+        let pattern = Pattern::parse_macrocell(text).unwrap();
+        assert_eq!((pattern.width, pattern.height, pattern.rule.as_deref()), (25, 32, Some("B3/S23")));
+        assert_eq!(pattern.cells.len(), 12);
+        assert!([(1, 0, 1), (8, 15, 1), (17, 16, 1), (18, 18, 1), (24, 31, 1)].iter().all(|cell| pattern.cells.contains(cell)));
+
+        // Multi-state level 1 nodes, and nodes that refer ahead or to the wrong level
+        let states = Pattern::parse_macrocell("[M2]\n1 0 3 2 0\n2 0 1 0 1\n").unwrap();
+        assert_eq!(states.cells, [(1, 0, 3), (0, 1, 2), (1, 2, 3), (0, 3, 2)]);
+        assert!(Pattern::parse_macrocell("[M2]\n4 2 0 0 0\n").err().unwrap().contains("doesn't come before"));
+        assert!(Pattern::parse_macrocell("[M2]\n*$\n5 1 0 0 0\n").err().unwrap().contains("level 3"));
+        assert!(Pattern::parse_macrocell("[M2]\n*********$\n").is_err());
+
+        // Clipped to a 20x20 grid from its top-left corner, then written as spans along the rows
+        let (placed, clipped) = pattern.place(20, 20, Some((0, 0)));
+        assert_eq!((placed.len(), clipped), (11, 1));
+        let spans = row_spans(&[(0, 0, 1), (2, 0, 1), (100, 0, 1), (5, 3, 2)]);
+        assert_eq!(spans, [(0, 0, vec![1, 0, 1]), (100, 0, vec![1]), (5, 3, vec![2])]);
+    }
+}
@@ -0,0 +1,85 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// wgpu's pipeline cache, kept on disk between runs so a restart doesn't compile the shaders from
+/// scratch. Only backends with `Features::PIPELINE_CACHE` (Vulkan) have one. The file starts with the
+/// adapter's cache key, so another GPU or driver starts over rather than being fed a stale blob.
+pub struct PipelineCache {
+    cache: Arc<wgpu::PipelineCache>,
+    path: PathBuf,
+    key: String,
+    loaded: usize,
+}
+
+/// Where the cache goes: next to the autosave, as `rust_gpu_life/pipeline_cache.bin`.
+pub fn path() -> Option<PathBuf> {
+    crate::autosave::path().map(|autosave| autosave.with_file_name("pipeline_cache.bin"))
+}
+
+impl PipelineCache {
+    /// Loads the cache for `info`'s adapter from `path`, or starts an empty one when the file is missing,
+    /// unreadable or from another adapter. `None` when the device can't cache pipelines.
+    pub fn open(device: &wgpu::Device, info: &wgpu::AdapterInfo, path: PathBuf) -> Option<PipelineCache> {
+        if !device.features().contains(wgpu::Features::PIPELINE_CACHE) { return None; }
+        let key = wgpu::util::pipeline_cache_key(info)?;
+        let bytes = std::fs::read(&path).unwrap_or_default();
+        let data = decode(&key, &bytes);
+        // SAFETY: the data was written by `save` from `get_data` on an adapter with the same key, and
+        // `fallback` makes wgpu start empty if the driver rejects it anyway
+        let cache = unsafe {
+            device.create_pipeline_cache(&wgpu::PipelineCacheDescriptor { label: Some("Pipeline Cache"), data, fallback: true })
+        };
+        Some(PipelineCache { cache: Arc::new(cache), path, key, loaded: data.map_or(0, <[u8]>::len) })
+    }
+
+    /// For the `cache` field of pipeline descriptors.
+    pub fn get(&self) -> &Arc<wgpu::PipelineCache> {
+        &self.cache
+    }
+
+    /// Bytes read from disk at startup; 0 means the pipelines were compiled from scratch.
+    pub fn loaded(&self) -> usize {
+        self.loaded
+    }
+
+    /// Writes what the driver has cached so far back to disk.
+    pub fn save(&self) -> Result<(), String> {
+        let Some(data) = self.cache.get_data() else { return Ok(()) };
+        write(&self.path, &encode(&self.key, &data))
+    }
+}
+
+// The adapter's key on the first line, then the driver's blob
+fn encode(key: &str, data: &[u8]) -> Vec<u8> {
+    [key.as_bytes(), b"\n", data].concat()
+}
+
+// The blob, if `bytes` holds one for `key`
+fn decode<'a>(key: &str, bytes: &'a [u8]) -> Option<&'a [u8]> {
+    let data = bytes.strip_prefix(key.as_bytes())?.strip_prefix(b"\n")?;
+    (!data.is_empty()).then_some(data)
+}
+
+// Written aside and renamed over the old file like the autosave, so a crash mid-write can't truncate it
+fn write(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("could not create '{}': {}", dir.display(), e))?;
+    }
+    let partial = path.with_extension("bin.partial");
+    std::fs::write(&partial, bytes).map_err(|e| format!("could not write pipeline cache '{}': {}", partial.display(), e))?;
+    std::fs::rename(&partial, path).map_err(|e| format!("could not move '{}' to '{}': {}", partial.display(), path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blobs_only_load_for_their_adapter() {
+        let bytes = encode("wgpu_pipeline_cache_vulkan_4318_9861", &[1, 2, 3]);
+        assert_eq!(decode("wgpu_pipeline_cache_vulkan_4318_9861", &bytes), Some(&[1, 2, 3][..]));
+        assert_eq!(decode("wgpu_pipeline_cache_vulkan_4098_29695", &bytes), None);
+        assert_eq!(decode("wgpu_pipeline_cache_vulkan_4318_986", &bytes), None);
+        assert_eq!(decode("wgpu_pipeline_cache_vulkan_4318_9861", b""), None);
+    }
+}
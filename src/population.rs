@@ -0,0 +1,165 @@
+use std::fmt;
+use crate::readback::StagingRing;
+
+const WORKGROUP_SIZE: u32 = 256;
+// Live cells, then cells in states 2 and 3
+const COUNTERS_SIZE: u64 = 3 * 4;
+// A workgroup's partial sums, padded to a vec4
+const PARTIAL_SIZE: u64 = 4 * 4;
+
+/// How the GPU counters sum over the whole grid
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Reduction {
+    /// Every counted cell adds to one global counter with atomicAdd
+    Atomic,
+    /// Each workgroup sums its cells in shared memory and writes one partial sum, then a second,
+    /// single-workgroup dispatch sums the partials: no atomics for millions of invocations to contend on
+    Workgroup,
+}
+
+impl Reduction {
+    pub const ALL: [Reduction; 2] = [Reduction::Atomic, Reduction::Workgroup];
+
+    pub fn parse(text: &str) -> Result<Reduction, String> {
+        match text {
+            "atomic" => Ok(Reduction::Atomic),
+            "workgroup" => Ok(Reduction::Workgroup),
+            _ => Err(format!("invalid reduction '{}' (expected atomic or workgroup)", text)),
+        }
+    }
+}
+
+impl fmt::Display for Reduction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Reduction::Atomic => "atomic",
+            Reduction::Workgroup => "workgroup",
+        })
+    }
+}
+
+/// Workgroups to dispatch over `cell_count` cells, 256 to a group, as a 2D grid that stays under the
+/// per-dimension limit; the shaders index cells the same way
+pub(crate) fn dispatch_size(cell_count: usize) -> (u32, u32) {
+    let groups = (cell_count as u32).div_ceil(WORKGROUP_SIZE);
+    let groups_x = groups.clamp(1, 65535);
+    (groups_x, groups.div_ceil(groups_x))
+}
+
+/// Live-cell count (plus the cells in states 2 and 3, the species after the first in Immigration and
+/// rock-paper-scissors) reduced on the GPU the `Reduction` way and read back asynchronously
+pub struct PopulationCounter {
+    pipeline: wgpu::ComputePipeline,
+    // The second dispatch, only in the workgroup reduction
+    partials_pipeline: Option<wgpu::ComputePipeline>,
+    bind_groups: [wgpu::BindGroup; 2],
+    counter: wgpu::Buffer,
+    staging: StagingRing,
+}
+
+impl PopulationCounter {
+    pub fn new(device: &wgpu::Device, buffer_a: &wgpu::Buffer, buffer_b: &wgpu::Buffer, reduction: Reduction) -> PopulationCounter {
+        let counter = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Population Counter"),
+            size: COUNTERS_SIZE,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // One set of counts per workgroup
+        let (groups_x, groups_y) = dispatch_size((buffer_a.size() / 4) as usize);
+        let partials = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Population Partials"),
+            size: groups_x as u64 * groups_y as u64 * PARTIAL_SIZE,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let staging = StagingRing::new(device, "Population Staging", COUNTERS_SIZE);
+
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry { binding, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only }, has_dynamic_offset: false, min_binding_size: None }, count: None };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[storage(0, true), storage(1, false), storage(2, false)],
+            label: None,
+        });
+        let bind_group = |cells: &wgpu::Buffer| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: cells.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: counter.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: partials.as_entire_binding() },
+            ],
+            label: None,
+        });
+        let bind_groups = [bind_group(buffer_a), bind_group(buffer_b)];
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("population.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&layout], ..Default::default() });
+        let create_pipeline = |label, entry_point| device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(label), layout: Some(&pipeline_layout), module: &shader, entry_point, compilation_options: Default::default(), cache: None,
+        });
+        let pipeline = match reduction {
+            Reduction::Atomic => create_pipeline("Population", "main"),
+            Reduction::Workgroup => create_pipeline("Population Tiles", "reduce_tiles"),
+        };
+        let partials_pipeline = (reduction == Reduction::Workgroup).then(|| create_pipeline("Population Partials", "reduce_partials"));
+
+        PopulationCounter { pipeline, partials_pipeline, bind_groups, counter, staging }
+    }
+
+    /// Counts the buffer for `parity` (0 = buffer A) if a staging slot is free, otherwise skips this frame.
+    /// `timestamp_writes` times the pass, both dispatches of it with the workgroup reduction.
+    pub fn encode(&mut self, encoder: &mut wgpu::CommandEncoder, parity: usize, cell_count: usize, timestamp_writes: Option<wgpu::ComputePassTimestampWrites>) {
+        let Some(staging) = self.staging.next() else { return };
+
+        // The workgroup reduction overwrites the counters rather than adding to them
+        if self.partials_pipeline.is_none() { encoder.clear_buffer(&self.counter, 0, None); }
+        {
+            let (groups_x, groups_y) = dispatch_size(cell_count);
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None, timestamp_writes });
+            cpass.set_pipeline(&self.pipeline);
+            cpass.set_bind_group(0, &self.bind_groups[parity], &[]);
+            cpass.dispatch_workgroups(groups_x, groups_y, 1);
+            if let Some(partials_pipeline) = &self.partials_pipeline {
+                cpass.set_pipeline(partials_pipeline);
+                cpass.dispatch_workgroups(1, 1, 1);
+            }
+        }
+        encoder.copy_buffer_to_buffer(&self.counter, 0, staging, 0, COUNTERS_SIZE);
+    }
+
+    /// Maps the slot written by `encode`; must be called after that encoder was submitted
+    pub fn begin_readback(&mut self) {
+        self.staging.begin_readback();
+    }
+
+    /// Newest [live, in state 2, in state 3] counts whose mapping has completed, if any; call after `device.poll`
+    pub fn try_read(&mut self) -> Option<[u32; 3]> {
+        self.staging.try_read(|bytes| {
+            let counts = bytemuck::cast_slice::<u8, u32>(bytes);
+            [counts[0], counts[1], counts[2]]
+        })
+    }
+
+    /// `encode` and the readback in one go, waiting for the GPU; for headless runs
+    pub fn count_blocking(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, parity: usize, cell_count: usize) -> Option<[u32; 3]> {
+        let mut encoder = device.create_command_encoder(&Default::default());
+        self.encode(&mut encoder, parity, cell_count, None);
+        queue.submit(Some(encoder.finish()));
+        self.begin_readback();
+        device.poll(wgpu::Maintain::Wait);
+        self.try_read()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dispatches_cover_every_cell() {
+        assert_eq!(dispatch_size(256), (1, 1));
+        assert_eq!(dispatch_size(257), (2, 1));
+        let (x, y) = dispatch_size(16384 * 16384);
+        assert!(x <= 65535 && (x * y) as usize * 256 >= 16384 * 16384);
+        assert_eq!(Reduction::ALL.map(|reduction| Reduction::parse(&reduction.to_string())), [Ok(Reduction::Atomic), Ok(Reduction::Workgroup)]);
+    }
+}
@@ -0,0 +1,79 @@
+// Population count: every live cell adds one to a global counter, cells in state 2 (Immigration's blue
+// species, rock-paper-scissors' second) also to a second one and cells in state 3 to a third. `main` adds with atomics; the workgroup reduction sums each workgroup's
+// cells in `reduce_tiles` and the workgroups' sums in `reduce_partials` instead
+// Walls aren't alive (matches WALL in lib.rs)
+const WALL: u32 = 0xffffffffu;
+
+@group(0) @binding(0) var<storage, read> cellState: array<u32>;
+@group(0) @binding(1) var<storage, read_write> population: array<atomic<u32>, 3>;
+// The workgroup reduction's (live, state 2, state 3) sums, one per workgroup of the first dispatch; w is unused
+@group(0) @binding(2) var<storage, read_write> partials: array<vec4<u32>>;
+
+var<workgroup> tile: array<vec4<u32>, 256>;
+
+// What one cell adds to the (live, state 2, state 3) counts
+fn tally(index: u32) -> vec4<u32> {
+    let state = cellState[index];
+    return vec4<u32>(u32(state > 0u && state != WALL), u32(state == 2u), u32(state == 3u), 0u);
+}
+
+// Dispatched as a 2D grid of 1D workgroups to stay under the per-dimension workgroup limit
+@compute @workgroup_size(256)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let index = global_id.y * groups.x * 256u + global_id.x;
+    if (index >= arrayLength(&cellState)) { return; }
+
+    let counts = tally(index);
+    if (counts.x > 0u) {
+        atomicAdd(&population[0], 1u);
+    }
+    if (counts.y > 0u) {
+        atomicAdd(&population[1], 1u);
+    }
+    if (counts.z > 0u) {
+        atomicAdd(&population[2], 1u);
+    }
+}
+
+// Halves the tile until tile[0] holds the sum of all 256; every invocation of the workgroup has to call it
+fn reduce_tile(local: u32) {
+    workgroupBarrier();
+    for (var stride = 128u; stride > 0u; stride >>= 1u) {
+        if (local < stride) {
+            tile[local] += tile[local + stride];
+        }
+        workgroupBarrier();
+    }
+}
+
+// First level of the workgroup reduction, dispatched like `main`: one partial sum per workgroup
+@compute @workgroup_size(256)
+fn reduce_tiles(@builtin(global_invocation_id) global_id: vec3<u32>, @builtin(local_invocation_index) local: u32, @builtin(workgroup_id) group: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let index = global_id.y * groups.x * 256u + global_id.x;
+    // No early return: the barriers need the whole workgroup
+    var counts = vec4<u32>(0u);
+    if (index < arrayLength(&cellState)) {
+        counts = tally(index);
+    }
+    tile[local] = counts;
+    reduce_tile(local);
+    if (local == 0u) {
+        partials[group.y * groups.x + group.x] = tile[0];
+    }
+}
+
+// Second level, a single workgroup: each invocation sums every 256th partial, then the tile sums those
+@compute @workgroup_size(256)
+fn reduce_partials(@builtin(local_invocation_index) local: u32) {
+    var counts = vec4<u32>(0u);
+    for (var i = local; i < arrayLength(&partials); i += 256u) {
+        counts += partials[i];
+    }
+    tile[local] = counts;
+    reduce_tile(local);
+    if (local == 0u) {
+        atomicStore(&population[0], tile[0].x);
+        atomicStore(&population[1], tile[0].y);
+        atomicStore(&population[2], tile[0].z);
+    }
+}
@@ -0,0 +1,73 @@
+use web_time::{Duration, Instant};
+use wgpu::PresentMode;
+use winit::dpi::PhysicalSize;
+
+// The modes V cycles through, in this order
+const MODES: [PresentMode; 3] = [PresentMode::Fifo, PresentMode::Mailbox, PresentMode::Immediate];
+
+pub fn parse(text: &str) -> Result<PresentMode, String> {
+    match text {
+        "fifo" => Ok(PresentMode::Fifo),
+        "mailbox" => Ok(PresentMode::Mailbox),
+        "immediate" => Ok(PresentMode::Immediate),
+        _ => Err(format!("invalid present mode '{}' (expected fifo, mailbox or immediate)", text)),
+    }
+}
+
+pub fn name(mode: PresentMode) -> &'static str {
+    match mode {
+        PresentMode::Fifo => "vsync",
+        PresentMode::Mailbox => "mailbox",
+        PresentMode::Immediate => "immediate",
+        _ => "auto",
+    }
+}
+
+// `wanted` if the surface supports it, otherwise Fifo, which every surface does
+pub fn choose(wanted: PresentMode, supported: &[PresentMode]) -> PresentMode {
+    if supported.contains(&wanted) { wanted } else { PresentMode::Fifo }
+}
+
+// The mode after `current` that the surface supports, wrapping around to Fifo
+pub fn next(current: PresentMode, supported: &[PresentMode]) -> PresentMode {
+    let start = MODES.iter().position(|&mode| mode == current).unwrap_or(0);
+    (1..=MODES.len()).map(|i| MODES[(start + i) % MODES.len()])
+        .find(|mode| supported.contains(mode))
+        .unwrap_or(PresentMode::Fifo)
+}
+
+// How long after the last Resized or Moved a window still counts as being dragged or resized
+const GESTURE_SETTLE: Duration = Duration::from_millis(150);
+
+// Dragging a window's border sends Resized dozens of times a second, and on Windows the modal loop the drag
+// runs in keeps asking for frames all the while. Sizes wait here for the next frame, which configures the
+// surface once with the latest; the simulation holds still until the events have stopped for a moment
+pub struct ResizeGesture {
+    pending: Option<PhysicalSize<u32>>,
+    last_event: Option<Instant>,
+}
+
+impl ResizeGesture {
+    pub fn new() -> ResizeGesture {
+        ResizeGesture { pending: None, last_event: None }
+    }
+
+    pub fn resized(&mut self, size: PhysicalSize<u32>) {
+        self.pending = Some(size);
+        self.moved();
+    }
+
+    // Moving the window runs the same modal loop without changing the size
+    pub fn moved(&mut self) {
+        self.last_event = Some(Instant::now());
+    }
+
+    // The size to configure the surface with, once per size reported
+    pub fn take_size(&mut self) -> Option<PhysicalSize<u32>> {
+        self.pending.take()
+    }
+
+    pub fn active(&self) -> bool {
+        self.last_event.is_some_and(|last| last.elapsed() < GESTURE_SETTLE)
+    }
+}
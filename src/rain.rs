@@ -0,0 +1,135 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use crate::config::{self, Value};
+
+/// Stamps rain drops from unless the rain file names others: small patterns that grow or travel
+pub const DEFAULT_PATTERNS: [&str; 5] = ["glider", "lightweight spaceship", "R-pentomino", "acorn", "diehard"];
+
+/// How it rains: a pattern every `every` generations, picked from `patterns` (stamp names)
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RainConfig {
+    pub every: usize,
+    pub patterns: Vec<String>,
+}
+
+impl Default for RainConfig {
+    fn default() -> Self {
+        RainConfig { every: 200, patterns: DEFAULT_PATTERNS.map(str::to_string).to_vec() }
+    }
+}
+
+/// Settings from a rain file, over the defaults: `every = 200` and `patterns = ["glider", "acorn"]`, naming
+/// stamps from `known`; # starts a comment (see `config::parse`). Bad lines come back as messages and are skipped.
+pub fn parse_config(text: &str, known: &[&str]) -> (RainConfig, Vec<String>) {
+    let mut config = RainConfig::default();
+    let mut errors = Vec::new();
+    for setting in config::parse(text) {
+        if let Err(e) = setting.and_then(|setting| set_field(&mut config, &setting.key, &setting.value, known).map_err(|e| setting.error(e))) {
+            errors.push(e);
+        }
+    }
+    (config, errors)
+}
+
+fn set_field(config: &mut RainConfig, key: &str, value: &Value, known: &[&str]) -> Result<(), String> {
+    match key {
+        "every" => config.every = match value.text().map(str::parse) {
+            Ok(Ok(every)) if every > 0 => every,
+            _ => return Err(format!("expected a number of generations, got {}", value)),
+        },
+        "patterns" => {
+            let Value::List(items) = value else { return Err("expected a list of stamp names".to_string()) };
+            let patterns = items.iter().map(|item| {
+                let name = item.quoted().map_err(|_| format!("expected a quoted name, got {}", item))?;
+                match known.contains(&name) {
+                    true => Ok(name.to_string()),
+                    false => Err(format!("unknown stamp '{}' (expected one of {})", name, known.join(", "))),
+                }
+            }).collect::<Result<Vec<_>, _>>()?;
+            if patterns.is_empty() { return Err("expected at least one stamp".to_string()); }
+            config.patterns = patterns;
+        }
+        other => return Err(format!("unknown key '{}' (expected every or patterns)", other)),
+    }
+    Ok(())
+}
+
+/// One drop: which of the patterns, where the top-left corner of its footprint lands and how it's turned
+/// (quarter turns clockwise, then mirrored if `flip`)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Raindrop {
+    pub pattern: usize,
+    pub x: usize,
+    pub y: usize,
+    pub turns: u32,
+    pub flip: bool,
+}
+
+/// When and where patterns drop, from its own seeded generator, so a seed always rains the same
+pub struct Rain {
+    every: usize,
+    patterns: usize,
+    rng: StdRng,
+    // Generation the next drop is due at
+    next: usize,
+}
+
+impl Rain {
+    /// Drops one of `patterns` patterns every `every` generations from `generation` on.
+    pub fn new(every: usize, patterns: usize, seed: u64, generation: usize) -> Rain {
+        Rain { every, patterns, rng: StdRng::seed_from_u64(seed), next: generation + every }
+    }
+
+    /// The drop due by `generation` on a `width` x `height` grid, if any. At most one comes per call, so
+    /// skipping ahead doesn't make it pour, and going back in time brings the next one closer again.
+    pub fn due(&mut self, generation: usize, width: usize, height: usize) -> Option<Raindrop> {
+        self.next = self.next.min(generation + self.every);
+        if generation < self.next || self.patterns == 0 { return None; }
+        self.next = generation + self.every;
+        Some(Raindrop {
+            pattern: self.rng.gen_range(0..self.patterns),
+            x: self.rng.gen_range(0..width),
+            y: self.rng.gen_range(0..height),
+            turns: self.rng.gen_range(0..4),
+            flip: self.rng.gen_bool(0.5),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_are_spaced_and_seeded() {
+        let drops = |seed| {
+            let mut rain = Rain::new(10, 3, seed, 100);
+            (100..200).filter_map(|generation| rain.due(generation, 64, 32)).collect::<Vec<_>>()
+        };
+        let first = drops(5);
+        assert_eq!(first.len(), 9);
+        assert!(first.iter().all(|drop| drop.pattern < 3 && drop.x < 64 && drop.y < 32 && drop.turns < 4));
+        assert_eq!(first, drops(5));
+        assert_ne!(first, drops(6));
+
+        // A jump ahead gets one drop, a rewind brings the next one back within reach
+        let mut rain = Rain::new(10, 3, 5, 0);
+        assert!(rain.due(1000, 64, 32).is_some());
+        assert!(rain.due(1005, 64, 32).is_none());
+        assert!(rain.due(20, 64, 32).is_none());
+        assert!(rain.due(30, 64, 32).is_some());
+    }
+
+    #[test]
+    fn config_names_known_stamps() {
+        let known = ["glider", "acorn", "diehard"];
+        let text = "every = 50 # often\npatterns = [\"glider\", \"acorn\"]\nevery = 0\npatterns = [\"pulsar\"]\nwind = 3\n";
+        let (config, errors) = parse_config(text, &known);
+        assert_eq!(config, RainConfig { every: 50, patterns: vec!["glider".to_string(), "acorn".to_string()] });
+        assert_eq!(errors.len(), 3);
+        assert!(errors[1].starts_with("line 4") && errors[1].contains("unknown stamp 'pulsar'"));
+        assert_eq!(parse_config("", &known).0, RainConfig::default());
+        // A # in a quoted name is part of it
+        let (config, errors) = parse_config("patterns = [\"glider #2\", \"acorn\"] # two of them\n", &["glider #2", "acorn"]);
+        assert_eq!((config.patterns, errors.len()), (vec!["glider #2".to_string(), "acorn".to_string()], 0));
+    }
+}
@@ -0,0 +1,103 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+// Staging buffers in flight: with three, a result may arrive two frames late but submit never waits
+const SLOTS: usize = 3;
+
+// Map status shared with the map_async callback
+const PENDING: u8 = 0;
+const MAPPED: u8 = 1;
+const FAILED: u8 = 2;
+
+struct Slot {
+    buffer: wgpu::Buffer,
+    status: Arc<AtomicU8>,
+    in_flight: bool,
+    sequence: u64,
+    // Caller's label for the result, e.g. the generation it was taken at
+    tag: u64,
+}
+
+// Small ring of staging buffers for results that are read back a few frames late
+pub(crate) struct StagingRing {
+    slots: Vec<Slot>,
+    encoded: Option<usize>,
+    sequence: u64,
+    latest: u64,
+}
+
+impl StagingRing {
+    pub fn new(device: &wgpu::Device, label: &str, size: u64) -> StagingRing {
+        let slots = (0..SLOTS).map(|_| Slot {
+            buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            status: Arc::new(AtomicU8::new(PENDING)),
+            in_flight: false,
+            sequence: 0,
+            tag: 0,
+        }).collect();
+        StagingRing { slots, encoded: None, sequence: 0, latest: 0 }
+    }
+
+    // A free staging buffer to copy this frame's result into, or None to skip the frame
+    pub fn next(&mut self) -> Option<&wgpu::Buffer> {
+        let slot = self.slots.iter().position(|slot| !slot.in_flight)?;
+        self.encoded = Some(slot);
+        Some(&self.slots[slot].buffer)
+    }
+
+    // Maps the buffer handed out by `next`; must be called after that encoder was submitted
+    pub fn begin_readback(&mut self) {
+        self.begin_tagged_readback(0);
+    }
+
+    // `begin_readback` with a tag that `try_read_tagged` hands back alongside the result
+    pub fn begin_tagged_readback(&mut self, tag: u64) {
+        let Some(index) = self.encoded.take() else { return };
+        self.sequence += 1;
+        let slot = &mut self.slots[index];
+        slot.in_flight = true;
+        slot.sequence = self.sequence;
+        slot.tag = tag;
+        let status = slot.status.clone();
+        slot.buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            status.store(if result.is_ok() { MAPPED } else { FAILED }, Ordering::Release);
+        });
+    }
+
+    // Results still in flight are unmapped when they arrive but never returned, e.g. once they're out of date
+    pub fn discard(&mut self) {
+        for slot in &mut self.slots {
+            slot.sequence = 0;
+        }
+    }
+
+    // Newest result whose mapping has completed, if any; call after `device.poll`
+    pub fn try_read<T>(&mut self, read: impl Fn(&[u8]) -> T) -> Option<T> {
+        self.try_read_tagged(read).map(|(_, value)| value)
+    }
+
+    // `try_read` along with the tag the result was mapped with
+    pub fn try_read_tagged<T>(&mut self, read: impl Fn(&[u8]) -> T) -> Option<(u64, T)> {
+        let mut result = None;
+        for slot in self.slots.iter_mut().filter(|slot| slot.in_flight) {
+            match slot.status.swap(PENDING, Ordering::Acquire) {
+                PENDING => continue,
+                FAILED => { slot.in_flight = false; continue; }
+                _ => {}
+            }
+            let value = read(&slot.buffer.slice(..).get_mapped_range());
+            slot.buffer.unmap();
+            slot.in_flight = false;
+            if slot.sequence > self.latest {
+                self.latest = slot.sequence;
+                result = Some((slot.tag, value));
+            }
+        }
+        result
+    }
+}
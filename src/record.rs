@@ -0,0 +1,166 @@
+use std::collections::VecDeque;
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::JoinHandle;
+
+// Frames queued for the writer thread before the event loop has to wait for ffmpeg
+const QUEUED_FRAMES: usize = 8;
+// Copies being mapped at once: a frame reaches the writer a frame or two late, and the event loop only
+// waits for the GPU when all of them are still in flight
+const READBACKS: usize = 3;
+const FRAME_RATE: u32 = 60;
+
+// Renders each frame a second time into an offscreen texture and streams it to an ffmpeg child process
+pub struct Recorder {
+    pub path: String,
+    pub frames: u64,
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    readbacks: Vec<wgpu::Buffer>,
+    // The readback this frame's copy goes to, always one that isn't in flight
+    target: usize,
+    // Readbacks being mapped, oldest first, with the channel their map_async callback answers on
+    in_flight: VecDeque<(usize, Receiver<Result<(), wgpu::BufferAsyncError>>)>,
+    width: u32,
+    height: u32,
+    padded_row: u32,
+    sender: Option<SyncSender<Vec<u8>>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl Recorder {
+    pub fn start(device: &wgpu::Device, path: String, size: (u32, u32), format: wgpu::TextureFormat) -> Result<Recorder, String> {
+        let pix_fmt = match format {
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => "bgra",
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => "rgba",
+            other => return Err(format!("can't record a {:?} surface, only 8-bit RGBA or BGRA", other)),
+        };
+        // The usual video pixel formats need even dimensions
+        let (width, height) = ((size.0 & !1).max(2), (size.1 & !1).max(2));
+
+        let mut child = spawn_ffmpeg(&path, width, height, pix_fmt)?;
+        let mut stdin = child.stdin.take().unwrap();
+        let (sender, receiver) = mpsc::sync_channel::<Vec<u8>>(QUEUED_FRAMES);
+        let writer_path = path.clone();
+        let writer = std::thread::spawn(move || {
+            for frame in receiver {
+                if let Err(e) = stdin.write_all(&frame) {
+                    eprintln!("Recording stopped, ffmpeg closed its input: {}", e);
+                    break;
+                }
+            }
+            drop(stdin);
+            match child.wait() {
+                Ok(status) if status.success() => println!("Saved recording {}", writer_path),
+                Ok(status) => eprintln!("ffmpeg exited with {} while writing {}", status, writer_path),
+                Err(e) => eprintln!("Failed to wait for ffmpeg: {}", e),
+            }
+        });
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Recording"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&Default::default());
+        // Texture copies need rows padded to 256 bytes
+        let padded_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readbacks = (0..READBACKS).map(|_| device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Recording Readback"),
+            size: padded_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })).collect();
+        println!("Recording {}x{} to {}", width, height, path);
+
+        Ok(Recorder { path, frames: 0, texture, view, readbacks, target: 0, in_flight: VecDeque::new(), width, height, padded_row, sender: Some(sender), writer: Some(writer) })
+    }
+
+    // Render target for the copy of the frame that gets recorded
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    // Call after rendering into `view()`, in the same encoder
+    pub fn encode_copy(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture { texture: &self.texture, mip_level: 0, origin: wgpu::Origin3d::ZERO, aspect: wgpu::TextureAspect::All },
+            wgpu::ImageCopyBuffer { buffer: &self.readbacks[self.target], layout: wgpu::ImageDataLayout { offset: 0, bytes_per_row: Some(self.padded_row), rows_per_image: None } },
+            wgpu::Extent3d { width: self.width, height: self.height, depth_or_array_layers: 1 },
+        );
+    }
+
+    // Starts mapping this frame's copy and hands the frames whose mapping has landed to the writer thread,
+    // in order; the GPU is only waited for when no readback is free for the next frame
+    pub fn capture(&mut self, device: &wgpu::Device) {
+        if self.sender.is_none() { return; }
+        let (done, mapped) = mpsc::channel();
+        self.readbacks[self.target].slice(..).map_async(wgpu::MapMode::Read, move |result| { let _ = done.send(result); });
+        self.in_flight.push_back((self.target, mapped));
+        device.poll(wgpu::Maintain::Poll);
+        self.collect();
+        if self.in_flight.len() == READBACKS {
+            device.poll(wgpu::Maintain::Wait);
+            self.collect();
+        }
+        self.target = (0..READBACKS).find(|&index| self.in_flight.iter().all(|&(busy, _)| busy != index)).expect("a readback was just collected");
+    }
+
+    // Sends on the frames mapped so far, stopping at the oldest one still in flight
+    fn collect(&mut self) {
+        while let Some(result) = self.in_flight.front().and_then(|(_, mapped)| mapped.try_recv().ok()) {
+            let (index, _) = self.in_flight.pop_front().unwrap();
+            let readback = &self.readbacks[index];
+            if result.is_err() {
+                eprintln!("Failed to map a recording frame, skipping it");
+                continue;
+            }
+            let row = (self.width * 4) as usize;
+            let frame = readback.slice(..).get_mapped_range().chunks(self.padded_row as usize).flat_map(|padded| &padded[..row]).copied().collect();
+            readback.unmap();
+            let Some(sender) = &self.sender else { continue };
+            if sender.send(frame).is_err() {
+                // The writer gave up (ffmpeg died), it has already said why
+                self.sender = None;
+            }
+            self.frames += 1;
+        }
+    }
+
+    // Collects the frames still being mapped, flushes the queued ones and waits for ffmpeg to finalize the
+    // file; returns how many frames it got
+    pub fn finish(mut self, device: &wgpu::Device) -> u64 {
+        device.poll(wgpu::Maintain::Wait);
+        self.collect();
+        self.sender = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+        self.frames
+    }
+}
+
+fn spawn_ffmpeg(path: &str, width: u32, height: u32, pix_fmt: &str) -> Result<Child, String> {
+    let mut command = Command::new("ffmpeg");
+    command.args(["-loglevel", "error", "-y", "-f", "rawvideo", "-pix_fmt", pix_fmt])
+        .args(["-s", &format!("{}x{}", width, height), "-r", &FRAME_RATE.to_string(), "-i", "-"]);
+    // GIFs pick their own palette, everything else gets the widely playable yuv420p
+    if !path.ends_with(".gif") {
+        command.args(["-pix_fmt", "yuv420p"]);
+    }
+    command.arg(path).stdin(Stdio::piped());
+    command.spawn().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => "recording needs ffmpeg, but it was not found on PATH".to_string(),
+        _ => format!("could not start ffmpeg: {}", e),
+    })
+}
@@ -0,0 +1,47 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+// GPU errors without the crash: validation errors are logged instead of panicking (wgpu's default), and a
+// lost device (a driver reset, say) raises a flag the event loop checks to set the GPU up again from the
+// last grid it has. --panic-on-error keeps the old behaviour for debugging.
+
+// Errors logged one by one before only every hundredth is, so a flood of them doesn't drown the console
+const LOGGED_IN_FULL: u32 = 10;
+
+// Installs the handlers on `device`; the flag is set once it's lost
+pub fn watch(device: &wgpu::Device, panic_on_error: bool) -> Arc<AtomicBool> {
+    let lost = Arc::new(AtomicBool::new(false));
+    if panic_on_error { return lost; }
+    let errors = AtomicU32::new(0);
+    device.on_uncaptured_error(Box::new(move |error| {
+        let count = errors.fetch_add(1, Ordering::Relaxed) + 1;
+        if count <= LOGGED_IN_FULL || count.is_multiple_of(100) {
+            eprintln!("GPU error #{} (carrying on, --panic-on-error stops here instead): {}", count, error);
+            log::error!("GPU error #{}: {}", count, error);
+        }
+    }));
+    let flag = lost.clone();
+    device.set_device_lost_callback(move |reason, message| {
+        // Our own doing, when a device is replaced or the app exits
+        if matches!(reason, wgpu::DeviceLostReason::Destroyed | wgpu::DeviceLostReason::Dropped) { return; }
+        eprintln!("warning: the GPU device was lost ({:?}: {}), setting it up again", reason, message);
+        log::warn!("GPU device lost ({:?}): {}", reason, message);
+        flag.store(true, Ordering::Release);
+    });
+    lost
+}
+
+// Runs `create` inside validation and out-of-memory error scopes, so a failure comes back as an error
+// naming `what` rather than going to the handler above
+pub async fn scoped<T>(device: &wgpu::Device, what: &str, create: impl FnOnce() -> T) -> Result<T, String> {
+    device.push_error_scope(wgpu::ErrorFilter::OutOfMemory);
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let value = create();
+    let validation = device.pop_error_scope().await;
+    let memory = device.pop_error_scope().await;
+    match (memory, validation) {
+        (Some(e), _) => Err(format!("out of GPU memory creating {} (try a smaller grid): {}", what, e)),
+        (None, Some(e)) => Err(format!("could not create {}: {}", what, e)),
+        (None, None) => Ok(value),
+    }
+}
@@ -0,0 +1,281 @@
+use crate::rule::Rule;
+
+/// Side of a rule map's square tiles, in cells (matches RULE_TILE in life.wgsl and render.wgsl)
+pub const TILE: usize = 32;
+
+/// Rules a map can hold, the grid's own at index 0 included (matches RULE_TABLE in life.wgsl)
+pub const MAX_RULES: usize = 16;
+
+/// Words before the tiles in the GPU copy: the tiles across, then one packed rule per table entry
+pub const HEADER_WORDS: usize = 1 + MAX_RULES;
+
+/// Which Life-like rule each 32x32 tile of the grid runs under, for worlds whose regions follow different
+/// rules. Tiles hold an index into a small table: 0 follows the grid's own rule, so changing that still
+/// changes every unpainted tile, and the rest are the rules painted with. Only Life reads the map; cells
+/// count their neighbours across tile edges as usual, whatever rule those neighbours run under.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RuleMap {
+    columns: usize,
+    rows: usize,
+    // Row-major table indices; `rules[index - 1]` for anything but 0
+    tiles: Vec<u8>,
+    rules: Vec<Rule>,
+}
+
+/// Tiles needed across `cells` cells.
+pub fn tiles_across(cells: u32) -> usize {
+    (cells as usize).div_ceil(TILE)
+}
+
+/// Length in words of the GPU copy for a `width` x `height` grid (see `RuleMap::words`).
+pub fn buffer_words(width: u32, height: u32) -> usize {
+    HEADER_WORDS + tiles_across(width) * tiles_across(height)
+}
+
+/// Regions run two-state rules only: a Generations rule's fading cells would mean nothing to the tiles
+/// around them.
+pub fn check_rule(rule: Rule) -> Result<(), String> {
+    if rule.is_generations() {
+        return Err(format!("regions take Life-like rules, not the Generations rule {}", rule));
+    }
+    Ok(())
+}
+
+impl RuleMap {
+    /// Every tile following the grid's rule.
+    pub fn new(width: u32, height: u32) -> RuleMap {
+        let (columns, rows) = (tiles_across(width), tiles_across(height));
+        RuleMap { columns, rows, tiles: vec![0; columns * rows], rules: Vec::new() }
+    }
+
+    /// A map from its table and tiles, as saved; checks they fit a `width` x `height` grid and each other.
+    pub fn from_parts(width: u32, height: u32, rules: Vec<Rule>, tiles: Vec<u8>) -> Result<RuleMap, String> {
+        let mut map = RuleMap::new(width, height);
+        if rules.len() >= MAX_RULES {
+            return Err(format!("rule map holds {} rules, at most {} fit besides the grid's", rules.len(), MAX_RULES - 1));
+        }
+        for &rule in &rules { check_rule(rule)?; }
+        if tiles.len() != map.tiles.len() {
+            return Err(format!("expected {} rule map tiles for a {}x{} grid but found {}", map.tiles.len(), width, height, tiles.len()));
+        }
+        if let Some(&index) = tiles.iter().find(|&&index| index as usize > rules.len()) {
+            return Err(format!("rule map tile refers to rule {} of {}", index, rules.len()));
+        }
+        (map.rules, map.tiles) = (rules, tiles);
+        Ok(map)
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The painted rules, index 1 onwards.
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Table indices, row-major.
+    pub fn tiles(&self) -> &[u8] {
+        &self.tiles
+    }
+
+    /// Whether every tile follows the grid's rule.
+    pub fn is_empty(&self) -> bool {
+        self.tiles.iter().all(|&index| index == 0)
+    }
+
+    /// Table index of the tile holding cell (`x`, `y`).
+    pub fn index_at(&self, x: usize, y: usize) -> usize {
+        self.tiles[y / TILE * self.columns + x / TILE] as usize
+    }
+
+    /// The rule cell (`x`, `y`) runs under when the grid's is `base`.
+    pub fn rule_at(&self, x: usize, y: usize, base: Rule) -> Rule {
+        match self.index_at(x, y) {
+            0 => base,
+            index => self.rules[index - 1],
+        }
+    }
+
+    // Tile indices touched by the square `radius` cells around (`x`, `y`), wrapping around the edges like
+    // the grid; never more than the whole map
+    fn brush(&self, x: usize, y: usize, radius: usize) -> impl Iterator<Item = usize> + '_ {
+        let span = move |centre: usize, tiles: usize| {
+            let first = (centre as i64 - radius as i64).div_euclid(TILE as i64);
+            let last = (centre + radius) as i64 / TILE as i64;
+            let count = ((last - first + 1) as usize).min(tiles);
+            (0..count).map(move |n| (first + n as i64).rem_euclid(tiles as i64) as usize)
+        };
+        span(y, self.rows).flat_map(move |row| span(x, self.columns).map(move |column| row * self.columns + column))
+    }
+
+    /// Whether the tiles the brush would paint already run under `rule` (`None` for the grid's).
+    pub fn painted(&self, x: usize, y: usize, radius: usize, rule: Option<Rule>) -> bool {
+        let index = match rule {
+            Some(rule) => match self.rules.iter().position(|&painted| painted == rule) {
+                Some(position) => position + 1,
+                None => return false,
+            },
+            None => 0,
+        };
+        self.brush(x, y, radius).all(|tile| self.tiles[tile] as usize == index)
+    }
+
+    /// Sets every tile within `radius` cells of (`x`, `y`) to `rule`, or back to the grid's with `None`.
+    /// Rules no tile runs under any more leave the table, so only `MAX_RULES - 1` in use at once count.
+    pub fn paint(&mut self, x: usize, y: usize, radius: usize, rule: Option<Rule>) -> Result<(), String> {
+        let index = match rule {
+            Some(rule) => {
+                check_rule(rule)?;
+                match self.rules.iter().position(|&painted| painted == rule) {
+                    Some(position) => position + 1,
+                    None if self.rules.len() + 1 < MAX_RULES => {
+                        self.rules.push(rule);
+                        self.rules.len()
+                    }
+                    None => return Err(format!("the rule map already holds {} rules; paint one of them over another first", MAX_RULES - 1)),
+                }
+            }
+            None => 0,
+        };
+        let tiles: Vec<usize> = self.brush(x, y, radius).collect();
+        for tile in tiles {
+            self.tiles[tile] = index as u8;
+        }
+        self.prune();
+        Ok(())
+    }
+
+    // Drops the rules no tile refers to and renumbers the rest in order
+    fn prune(&mut self) {
+        let mut used = [false; MAX_RULES];
+        for &index in &self.tiles { used[index as usize] = true; }
+        if used[1..=self.rules.len()].iter().all(|&used| used) { return; }
+        let mut renumbered = [0u8; MAX_RULES];
+        let mut kept = Vec::new();
+        for (position, &rule) in self.rules.iter().enumerate() {
+            if used[position + 1] {
+                kept.push(rule);
+                renumbered[position + 1] = kept.len() as u8;
+            }
+        }
+        for index in &mut self.tiles { *index = renumbered[*index as usize]; }
+        self.rules = kept;
+    }
+
+    /// How many tiles run under each rule, the grid's first.
+    pub fn counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.rules.len() + 1];
+        for &index in &self.tiles { counts[index as usize] += 1; }
+        counts
+    }
+
+    /// The GPU copy: the tiles across, the table with birth in the low and survival in the high 16 bits of
+    /// each entry (entry 0 unused, the shader takes the grid's rule from its parameters), then one index
+    /// per tile. Tiles across is 0 for an empty map, which the shader skips.
+    pub fn words(&self) -> Vec<u32> {
+        let mut words = vec![0; HEADER_WORDS];
+        if !self.is_empty() { words[0] = self.columns as u32; }
+        for (entry, rule) in words[2..].iter_mut().zip(&self.rules) {
+            *entry = rule.birth | rule.survive << 16;
+        }
+        words.extend(self.tiles.iter().map(|&index| index as u32));
+        words
+    }
+
+    /// Little-endian rule count, each rule's birth and survival masks, then one byte per tile.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(4 + self.rules.len() * 8 + self.tiles.len());
+        out.extend_from_slice(&(self.rules.len() as u32).to_le_bytes());
+        for rule in &self.rules {
+            out.extend_from_slice(&rule.birth.to_le_bytes());
+            out.extend_from_slice(&rule.survive.to_le_bytes());
+        }
+        out.extend_from_slice(&self.tiles);
+        out
+    }
+
+    /// The inverse of `encode` for a `width` x `height` grid.
+    pub fn decode(bytes: &[u8], width: u32, height: u32) -> Result<RuleMap, String> {
+        let u32_at = |offset: usize| bytes.get(offset..offset + 4).map(|word| u32::from_le_bytes(word.try_into().unwrap())).ok_or("truncated rule map");
+        let count = u32_at(0)? as usize;
+        if count >= MAX_RULES {
+            return Err(format!("rule map holds {} rules, at most {} fit besides the grid's", count, MAX_RULES - 1));
+        }
+        let rules = (0..count).map(|n| Ok(Rule { birth: u32_at(4 + n * 8)?, survive: u32_at(8 + n * 8)?, states: 2 })).collect::<Result<Vec<_>, &str>>()?;
+        RuleMap::from_parts(width, height, rules, bytes[4 + count * 8..].to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeds() -> Rule {
+        Rule::parse("B2/S").unwrap()
+    }
+
+    #[test]
+    fn brushes_paint_whole_tiles_and_wrap() {
+        // 3 x 2 tiles, the last column and row only partly on the grid
+        let mut map = RuleMap::new(80, 40);
+        assert_eq!((map.columns(), map.rows()), (3, 2));
+        assert!(map.is_empty());
+        map.paint(40, 10, 0, Some(seeds())).unwrap();
+        assert_eq!(map.tiles(), &[0, 1, 0, 0, 0, 0]);
+        assert_eq!(map.rule_at(63, 31, Rule::CONWAY), seeds());
+        assert_eq!(map.rule_at(64, 31, Rule::CONWAY), Rule::CONWAY);
+        assert!(map.painted(40, 20, 8, Some(seeds())));
+        assert!(!map.painted(33, 0, 5, None));
+
+        // Across the left edge onto the last column
+        map.paint(2, 35, 4, Some(Rule::parse("B36/S23").unwrap())).unwrap();
+        assert_eq!(map.tiles(), &[2, 1, 2, 2, 0, 2]);
+        // A brush bigger than the grid covers it once
+        map.paint(0, 0, 1000, Some(seeds())).unwrap();
+        assert_eq!(map.counts(), vec![0, 6]);
+    }
+
+    #[test]
+    fn unused_rules_leave_the_table() {
+        let mut map = RuleMap::new(64, 32);
+        map.paint(0, 0, 0, Some(seeds())).unwrap();
+        map.paint(32, 0, 0, Some(Rule::parse("B36/S23").unwrap())).unwrap();
+        map.paint(0, 0, 0, None).unwrap();
+        assert_eq!(map.rules(), &[Rule::parse("B36/S23").unwrap()]);
+        assert_eq!(map.tiles(), &[0, 1]);
+
+        // The table fills up, but painting over an old rule frees its place
+        let mut map = RuleMap::new(32 * MAX_RULES as u32, 32);
+        for n in 0..MAX_RULES - 1 {
+            map.paint(n * TILE, 0, 0, Some(Rule { birth: 1 << 3, survive: n as u32, states: 2 })).unwrap();
+        }
+        let extra = Rule { birth: 1 << 2, survive: 0, states: 2 };
+        assert!(map.paint(480, 0, 0, Some(extra)).is_err());
+        map.paint(0, 0, 0, None).unwrap();
+        map.paint(480, 0, 0, Some(extra)).unwrap();
+        assert!(map.paint(0, 0, 0, Some(Rule::parse("345/2/4").unwrap())).unwrap_err().contains("Life-like"));
+    }
+
+    #[test]
+    fn words_and_bytes_round_trip() {
+        let mut map = RuleMap::new(70, 33);
+        assert_eq!(map.words()[0], 0);
+        map.paint(69, 32, 0, Some(seeds())).unwrap();
+        let words = map.words();
+        assert_eq!(words.len(), buffer_words(70, 33));
+        assert_eq!((words[0], words[2]), (3, seeds().birth | seeds().survive << 16));
+        assert_eq!(words[HEADER_WORDS + 5], 1);
+
+        assert_eq!(RuleMap::decode(&map.encode(), 70, 33).unwrap(), map);
+        assert!(RuleMap::decode(&map.encode(), 64, 33).is_err());
+        let mut bad = map.encode();
+        *bad.last_mut().unwrap() = 2;
+        assert!(RuleMap::decode(&bad, 70, 33).is_err());
+        assert!(RuleMap::decode(&[1, 0], 70, 33).is_err());
+    }
+}
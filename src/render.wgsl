@@ -0,0 +1,481 @@
+// Must match `Params` in rules.wgsl
+struct Params {
+    width: u32,
+    height: u32,
+    birth: u32,
+    survive: u32,
+    boundary: u32,
+    automaton: u32,
+    radius: u32,
+    birth_min: u32,
+    birth_max: u32,
+    survive_min: u32,
+    survive_max: u32,
+    feed: f32,
+    kill: f32,
+    diffusion_u: f32,
+    diffusion_v: f32,
+    dt: f32,
+    states: u32,
+    topology: u32,
+    cyclic_states: u32,
+    cyclic_threshold: u32,
+    rps_threshold: u32,
+    lenia_radius: u32,
+    lenia_mu: f32,
+    lenia_sigma: f32,
+    lenia_dt: f32,
+    forest_growth: u32,
+    forest_lightning: u32,
+    forest_key: u32,
+};
+
+const AUTOMATON_LIFE: u32 = 0u;
+const AUTOMATON_BRIANS_BRAIN: u32 = 1u;
+const AUTOMATON_LTL: u32 = 2u;
+const AUTOMATON_WIREWORLD: u32 = 3u;
+const AUTOMATON_IMMIGRATION: u32 = 4u;
+const AUTOMATON_GRAY_SCOTT: u32 = 5u;
+const AUTOMATON_GENERATIONS: u32 = 6u;
+const AUTOMATON_LANGTONS_ANT: u32 = 7u;
+const AUTOMATON_CYCLIC: u32 = 8u;
+const AUTOMATON_LENIA: u32 = 9u;
+const AUTOMATON_FOREST_FIRE: u32 = 10u;
+const AUTOMATON_ROCK_PAPER_SCISSORS: u32 = 11u;
+
+// Forest fire trees and flames, whatever the theme: the picture only reads as a forest in these
+const FOREST_TREE: vec3<f32> = vec3<f32>(0.13, 0.55, 0.13);
+const FOREST_FIRE: vec3<f32> = vec3<f32>(1.0, 0.55, 0.0);
+
+// Matches `Topology` in topology.rs
+const TOPOLOGY_HEX: u32 = 1u;
+
+// Matches WALL in lib.rs
+const WALL: u32 = 0xffffffffu;
+
+// Set on the cell an ant stands on, over the colour in the low byte (matches ANT in ant.rs)
+const ANT: u32 = 0x100u;
+
+// Matches `DisplayMode` in display.rs
+const DISPLAY_NORMAL: u32 = 0u;
+const DISPLAY_DIFF: u32 = 1u;
+const DISPLAY_AGE: u32 = 2u;
+
+// Live cells store their age in generations, saturating here (matches MAX_AGE in lib.rs)
+const MAX_AGE: u32 = 65535u;
+
+// Camera: view center in cells and zoom factor (1.0 = whole grid, drawn into a viewport of the grid's shape),
+// plus one of the DISPLAY_* values and whether the chunk overlay is on
+struct Camera {
+    center: vec2<f32>,
+    zoom: f32,
+    display: u32,
+    chunks: u32,
+    // 1 draws the seam where the torus wraps
+    seam: u32,
+    // How far to fade from the previous generation to the latest, 1 for the latest alone
+    blend: f32,
+    // 1 tints the rule map's painted regions
+    regions: u32,
+};
+
+// Matches CHUNK_SIZE in chunks.rs
+const CHUNK_SIZE: u32 = 64u;
+
+// Match TILE and MAX_RULES in regions.rs
+const RULE_TILE: u32 = 32u;
+const RULE_TABLE: u32 = 16u;
+
+// Cell size in pixels over which the lines between cells fade in: any smaller and they'd alias into a grey wash
+const GRID_LINES_FROM: f32 = 8.0;
+const GRID_LINES_FULL: f32 = 16.0;
+
+// Pending stamp footprint on the grid, extent is zero when none is selected
+struct Stamp {
+    origin: vec2<u32>,
+    extent: vec2<u32>,
+};
+
+// Shift-drag selection, same layout as Stamp
+struct Selection {
+    origin: vec2<u32>,
+    extent: vec2<u32>,
+};
+
+// Bind Group 0: the latest generation (Read Only), the simulation parameters, the generation before
+// the latest, which is the other ping-pong buffer (for the diff view), the chunks the latest
+// generation computed (for the chunk overlay, see chunks.rs), the rule map (see life.wgsl) and how
+// recently each cell died (for the diff view, see fade.rs)
+@group(0) @binding(0) var<storage, read> cellState: array<u32>;
+@group(0) @binding(1) var<uniform> params: Params;
+@group(0) @binding(2) var<storage, read> previousState: array<u32>;
+@group(0) @binding(3) var<storage, read> activeChunks: array<u32>;
+@group(0) @binding(4) var<storage, read> ruleMap: array<u32>;
+@group(0) @binding(5) var<storage, read> fadeLevels: array<u32>;
+
+// Matches FADE_GENERATIONS in fade.rs
+const FADE_GENERATIONS: u32 = 8u;
+
+// Must match `DownsampleParams` in downsample.rs
+struct Downsample {
+    width: u32,
+    height: u32,
+    factor: u32,
+    _pad: u32,
+};
+
+// Must match `PaletteUniform` in palette.rs
+struct Palette {
+    background: vec4<f32>,
+    dead: vec4<f32>,
+    wall: vec4<f32>,
+    live: array<vec4<f32>, 3>,
+    states: array<vec4<f32>, 3>,
+    wheel: u32,
+    _pad0: u32,
+    _pad1: u32,
+    _pad2: u32,
+};
+
+// Bind Group 1: Render-only state, including the zoomed-out coverage (see downsample.wgsl) and the theme
+@group(1) @binding(0) var<uniform> camera: Camera;
+@group(1) @binding(1) var coverage: texture_2d<f32>;
+@group(1) @binding(2) var<uniform> downsample: Downsample;
+@group(1) @binding(3) var<uniform> palette: Palette;
+
+// Bind Group 2: Stamp preview, one mask bit per footprint cell (row-major), and the selection
+@group(2) @binding(0) var<uniform> stamp: Stamp;
+@group(2) @binding(1) var<storage, read> stamp_mask: array<u32>;
+@group(2) @binding(2) var<uniform> selection: Selection;
+
+fn get_index(x: u32, y: u32) -> u32 {
+    return (y % params.height) * params.width + (x % params.width);
+}
+
+// Vertex shader (The Geometry)
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) v_index: u32) -> VertexOutput {
+    // Defines a full-screen triangle to draw on
+    var pos = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, -1.0), vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, 1.0), vec2<f32>(-1.0, 1.0)
+    );
+    var output: VertexOutput;
+    output.position = vec4<f32>(pos[v_index], 0.0, 1.0);
+    // Convert Position to UV coordinates for texture mapping
+    output.uv = (pos[v_index] + 1.0) * 0.5;
+    output.uv.y = 1.0 - output.uv.y; // Flip Y
+    return output;
+}
+
+// The cell drawn at a point of the wrapped grid plane. Hex grids draw odd rows half a cell to the right and
+// hand the top corners of each cell to the row above, so the staggered rows read as hexagons up close
+fn drawn_cell(point: vec2<f32>) -> vec2<u32> {
+    var row = i32(floor(point.y));
+    var shift = 0.0;
+    if (params.topology == TOPOLOGY_HEX) {
+        let u = fract(point.x - 0.5 * f32(row & 1));
+        if (point.y - f32(row) < 0.25 * abs(2.0 * u - 1.0)) { row -= 1; }
+        shift = 0.5 * f32(row & 1);
+    }
+    let size = vec2<i32>(i32(params.width), i32(params.height));
+    let cell = vec2<i32>(i32(floor(point.x - shift)), row);
+    return vec2<u32>(((cell % size) + size) % size);
+}
+
+// Between the theme's three live colours, newborn at 0 to old at 1
+fn gradient(t: f32) -> vec3<f32> {
+    if (t < 0.5) {
+        return mix(palette.live[0].rgb, palette.live[1].rgb, t * 2.0);
+    }
+    return mix(palette.live[1].rgb, palette.live[2].rgb, t * 2.0 - 1.0);
+}
+
+// Fully saturated colour at `t` round the hue circle
+fn hue(t: f32) -> vec3<f32> {
+    return clamp(abs(fract(t + vec3<f32>(0.0, 2.0 / 3.0, 1.0 / 3.0)) * 6.0 - 3.0) - 1.0, vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// Colours for open-ended sets of states: round the hue circle, or down the live gradient in themes without it
+fn cycle_color(t: f32) -> vec3<f32> {
+    if (palette.wheel == 1u) {
+        return hue(t);
+    }
+    return gradient(t);
+}
+
+// Colour of one cell, from the theme (see palette.rs)
+fn cell_color(state: u32) -> vec3<f32> {
+    var color = palette.dead.rgb;
+    if (state == WALL) {
+        color = palette.wall.rgb;
+    } else if (params.automaton == AUTOMATON_BRIANS_BRAIN) {
+        // Firing as newborns, the refractory trail as an electron head
+        if (state == 1u) {
+            color = palette.live[0].rgb;
+        } else if (state == 2u) {
+            color = palette.states[1].rgb;
+        }
+    } else if (params.automaton == AUTOMATON_GENERATIONS) {
+        // Alive as newborns, then the fading states run through the extra colours down to a dim ember
+        if (state == 1u) {
+            color = palette.live[0].rgb;
+        } else if (state > 1u) {
+            let t = min(f32(state - 2u) / f32(max(params.states, 4u) - 3u), 1.0);
+            let ember = mix(palette.states[2].rgb, palette.dead.rgb, 0.7);
+            if (t < 0.5) {
+                color = mix(palette.states[0].rgb, palette.states[2].rgb, t * 2.0);
+            } else {
+                color = mix(palette.states[2].rgb, ember, t * 2.0 - 1.0);
+            }
+        }
+    } else if (params.automaton == AUTOMATON_WIREWORLD) {
+        // Conductor, electron head and tail
+        if (state >= 1u && state <= 3u) {
+            color = palette.states[state - 1u].rgb;
+        }
+    } else if (params.automaton == AUTOMATON_IMMIGRATION) {
+        // The two species as an electron tail and head
+        if (state == 1u) {
+            color = palette.states[2].rgb;
+        } else if (state == 2u) {
+            color = palette.states[1].rgb;
+        }
+    } else if (params.automaton == AUTOMATON_LANGTONS_ANT) {
+        // Ants over their cell as an electron tail; colour 1 as newborns and the rest spread out
+        let colour = state & 0xffu;
+        if ((state & ANT) != 0u) {
+            color = palette.states[2].rgb;
+        } else if (colour == 1u) {
+            color = palette.live[0].rgb;
+        } else if (colour > 1u) {
+            color = cycle_color(fract(f32(colour - 2u) * 0.382));
+        }
+    } else if (params.automaton == AUTOMATON_CYCLIC) {
+        // The states evenly spread, so each one chases the next colour along
+        color = cycle_color(f32(state) / f32(params.cyclic_states));
+    } else if (params.automaton == AUTOMATON_LENIA) {
+        // The intensity (an f32's bits, see rules.wgsl) fades in from the background and runs up the live
+        // gradient from its oldest colour to the newborn one
+        let a = clamp(bitcast<f32>(state), 0.0, 1.0);
+        color = mix(color, gradient(1.0 - a), smoothstep(0.0, 0.3, a));
+    } else if (params.automaton == AUTOMATON_ROCK_PAPER_SCISSORS) {
+        // The three species as Wireworld's three colours, which every theme keeps apart
+        if (state >= 1u && state <= 3u) {
+            color = palette.states[state - 1u].rgb;
+        }
+    } else if (params.automaton == AUTOMATON_FOREST_FIRE) {
+        // Empty ground stays the background
+        if (state == 1u) {
+            color = FOREST_TREE;
+        } else if (state == 2u) {
+            color = FOREST_FIRE;
+        }
+    } else if (params.automaton == AUTOMATON_GRAY_SCOTT) {
+        // A smooth gradient over v (the low 16 bits, see rules.wgsl), which rarely gets past 0.4
+        let v = clamp(f32(state & 0xffffu) / 65535.0 * 2.5, 0.0, 1.0);
+        if (v < 0.5) {
+            color = mix(color, palette.live[2].rgb, v * 2.0);
+        } else {
+            color = mix(palette.live[2].rgb, mix(palette.live[1].rgb, palette.live[0].rgb, v * 2.0 - 1.0), v * 2.0 - 1.0);
+        }
+    } else if (state > 0u) {
+        // Alive cells by age, down the live gradient
+        color = gradient(clamp(log2(f32(state)) / 8.0, 0.0, 1.0));
+    }
+    return color;
+}
+
+// Whether a cell counts as alive for the diff view: the states that count as neighbours (see rules.wgsl)
+fn is_live(state: u32) -> bool {
+    if (state == WALL) { return false; }
+    switch params.automaton {
+        case AUTOMATON_BRIANS_BRAIN, AUTOMATON_GENERATIONS: { return state == 1u; }
+        case AUTOMATON_WIREWORLD: { return state == 2u; }
+        case AUTOMATON_GRAY_SCOTT: { return f32(state & 0xffffu) / 65535.0 > 0.1; }
+        case AUTOMATON_LENIA: { return bitcast<f32>(state) > 0.1; }
+        default: { return state > 0u; }
+    }
+}
+
+// Births green, deaths red fading back to the dead colour over the generations after, survivors white.
+// A level buffer shorter than the grid (the split view's CPU side) fades nothing but the latest deaths
+fn diff_color(index: u32) -> vec3<f32> {
+    let state = cellState[index];
+    if (state == WALL) { return palette.wall.rgb; }
+    let now = is_live(state);
+    let then = is_live(previousState[index]);
+    if (now && then) { return vec3<f32>(1.0, 1.0, 1.0); }
+    if (now) { return vec3<f32>(0.2, 1.0, 0.3); }
+    var level = select(0u, fadeLevels[index], index < arrayLength(&fadeLevels));
+    if (then) { level = FADE_GENERATIONS; }
+    return mix(cell_color(0u), vec3<f32>(1.0, 0.2, 0.15), f32(level) / f32(FADE_GENERATIONS));
+}
+
+// Heat map over the whole age range on a log scale: newborn blue through green and yellow to red at MAX_AGE.
+// Automata without ages keep their own colours
+fn age_color(state: u32) -> vec3<f32> {
+    let aged = params.automaton == AUTOMATON_LIFE || params.automaton == AUTOMATON_LTL;
+    if (!aged || state == 0u || state == WALL) { return cell_color(state); }
+    let t = log2(f32(state)) / log2(f32(MAX_AGE));
+    let blue = vec3<f32>(0.1, 0.3, 1.0);
+    let green = vec3<f32>(0.1, 0.9, 0.3);
+    let yellow = vec3<f32>(1.0, 0.9, 0.1);
+    let red = vec3<f32>(1.0, 0.1, 0.05);
+    if (t < 1.0 / 3.0) { return mix(blue, green, t * 3.0); }
+    if (t < 2.0 / 3.0) { return mix(green, yellow, t * 3.0 - 1.0); }
+    return mix(yellow, red, t * 3.0 - 2.0);
+}
+
+fn coverage_at(texel: vec2<i32>) -> vec3<f32> {
+    let texels = vec2<i32>(textureDimensions(coverage));
+    return textureLoad(coverage, ((texel % texels) + texels) % texels, 0).rgb;
+}
+
+// Bilinear blend of the four nearest coverage texels, wrapping like the grid (the format can't be filtered)
+fn smoothed_coverage(cell: vec2<f32>) -> vec3<f32> {
+    let p = cell / f32(downsample.factor) - 0.5;
+    let base = floor(p);
+    let t = p - base;
+    let texel = vec2<i32>(base);
+    let top = mix(coverage_at(texel), coverage_at(texel + vec2<i32>(1, 0)), t.x);
+    let bottom = mix(coverage_at(texel + vec2<i32>(0, 1)), coverage_at(texel + vec2<i32>(1, 1)), t.x);
+    return mix(top, bottom, t.y);
+}
+
+// Whether zoomed-out views blend by coverage: Gray-Scott's concentrations and Lenia's intensities vary
+// smoothly already, cyclic and rock-paper-scissors grids are all colours with no dead background to blend
+// towards, and coverage can't tell a forest's trees from its fires
+fn covered() -> bool {
+    return params.automaton != AUTOMATON_GRAY_SCOTT && params.automaton != AUTOMATON_CYCLIC && params.automaton != AUTOMATON_LENIA
+        && params.automaton != AUTOMATON_FOREST_FIRE && params.automaton != AUTOMATON_ROCK_PAPER_SCISSORS;
+}
+
+// Zoomed-out colour around a cell from the coverage: how much of the area is alive and walls
+fn coverage_color(cell: vec2<f32>) -> vec3<f32> {
+    let coverage = smoothed_coverage(cell);
+    var live = cell_color(1u);
+    if (params.automaton == AUTOMATON_IMMIGRATION) {
+        // Blend the species by their share of the live cells
+        live = mix(live, cell_color(2u), coverage.y / max(coverage.x, 1e-6));
+    }
+    return mix(mix(cell_color(0u), live, coverage.x), palette.wall.rgb, coverage.z);
+}
+
+// Fragment shader (Visuals)
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Map pixel coordinate on screen to a cell in grid through the camera
+    let size = vec2<f32>(f32(params.width), f32(params.height));
+    let cell = camera.center + (in.uv - 0.5) * (size / camera.zoom);
+    // Cells per screen pixel; derivatives have to be taken before any branching
+    let cells_per_pixel = fwidth(cell.x);
+    // Wrap the view coordinates since the topology is toroidal anyway
+    let wrapped = cell - size * floor(cell / size);
+    // Nearest cell lookup keeps zoomed-in cells crisp squares (or hexagons)
+    let picked = drawn_cell(wrapped);
+    let x = picked.x;
+    let y = picked.y;
+
+    // Colour: zoomed out, blend by how much of the area is alive instead of picking one cell per pixel.
+    // Coverage knows nothing of ages or the previous generation, so those always sample the cell, as do
+    // the automata `covered` leaves out
+    var color: vec3<f32>;
+    let index = get_index(x, y);
+    if (camera.display == DISPLAY_DIFF) {
+        color = diff_color(index);
+    } else if (camera.display == DISPLAY_AGE) {
+        color = age_color(cellState[index]);
+    } else if (cells_per_pixel > 1.0 && downsample.factor > 1u && covered()) {
+        color = coverage_color(wrapped);
+    } else {
+        color = cell_color(cellState[index]);
+        // Slow motion: the cells fade from the previous generation over the time until the next one
+        if (camera.blend < 1.0) {
+            color = mix(cell_color(previousState[index]), color, camera.blend);
+        }
+    }
+
+    // Rule map: each painted rule's tiles washed in a hue of their own, whatever the theme; tiles following
+    // the grid's rule stay as they are
+    let tiles_across = ruleMap[0];
+    if (camera.regions == 1u && tiles_across != 0u) {
+        let rule = ruleMap[1u + RULE_TABLE + (y / RULE_TILE) * tiles_across + x / RULE_TILE];
+        if (rule > 0u) { color = mix(color, hue(fract(f32(rule) * 0.382)), 0.3); }
+    }
+
+    // Stamp preview, offsets taken modulo the grid so it wraps like the stamp itself
+    let dx = (x + params.width - stamp.origin.x) % params.width;
+    let dy = (y + params.height - stamp.origin.y) % params.height;
+    if (dx < stamp.extent.x && dy < stamp.extent.y) {
+        let bit = dy * stamp.extent.x + dx;
+        let live = ((stamp_mask[bit / 32u] >> (bit % 32u)) & 1u) == 1u;
+        color = mix(color, select(vec3<f32>(0.2, 0.6, 0.3), vec3<f32>(0.3, 1.0, 0.4), live), select(0.25, 0.6, live));
+    }
+
+    // Selection highlight, wrapping the same way
+    let sx = (x + params.width - selection.origin.x) % params.width;
+    let sy = (y + params.height - selection.origin.y) % params.height;
+    if (sx < selection.extent.x && sy < selection.extent.y) {
+        color = mix(color, vec3<f32>(1.0, 0.85, 0.2), 0.25);
+    }
+
+    // Chunk overlay: the chunks the GPU computed last generation tinted cyan
+    let columns = (params.width + CHUNK_SIZE - 1u) / CHUNK_SIZE;
+    if (camera.chunks == 1u && activeChunks[(y / CHUNK_SIZE) * columns + x / CHUNK_SIZE] == 1u) {
+        color = mix(color, vec3<f32>(0.1, 0.8, 0.9), 0.2);
+    }
+
+    // Lines between cells once they're big enough to edit one by one, in the theme's wall colour. Hex rows
+    // are staggered, so only the lines between rows are drawn there
+    let pixels_per_cell = 1.0 / cells_per_pixel;
+    let fade = smoothstep(GRID_LINES_FROM, GRID_LINES_FULL, pixels_per_cell);
+    if (fade > 0.0) {
+        let within = fract(wrapped);
+        let pixels_to_edge = min(within, 1.0 - within) * pixels_per_cell;
+        var on_line = pixels_to_edge.y < 0.5;
+        if (params.topology != TOPOLOGY_HEX) { on_line = on_line || pixels_to_edge.x < 0.5; }
+        if (on_line) { color = mix(color, palette.wall.rgb, 0.4 * fade); }
+    }
+
+    // A faint line where the last row and column meet the first ones, so a view panned across the
+    // seam shows where it is
+    if (camera.seam == 1u && any(min(wrapped, size - wrapped) < vec2<f32>(cells_per_pixel))) {
+        color = mix(color, vec3<f32>(0.9, 0.4, 0.9), 0.35);
+    }
+    return vec4<f32>(color, 1.0);
+}
+
+// Minimap, drawn into its own small viewport in a corner: the whole grid from the same coverage (cells
+// straight from the buffer while the coverage isn't kept up), with the main view outlined
+@fragment
+fn fs_minimap(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(f32(params.width), f32(params.height));
+    let cell = in.uv * size;
+    let cells_per_pixel = fwidth(cell.x);
+
+    var color: vec3<f32>;
+    if (downsample.factor > 1u && covered()) {
+        color = coverage_color(cell);
+    } else {
+        color = cell_color(cellState[get_index(u32(cell.x), u32(cell.y))]);
+    }
+
+    // The view's outline, split across the edges when it wraps around the torus
+    let half = size / camera.zoom * 0.5;
+    let offset = cell - camera.center;
+    let apart = abs(offset - size * round(offset / size));
+    if (all(apart <= half) && any(apart >= half - cells_per_pixel * 1.5)) {
+        color = vec3<f32>(1.0, 0.85, 0.2);
+    }
+    // A frame sets the minimap off from the grid behind it
+    if (any(min(cell, size - cell) < vec2<f32>(cells_per_pixel))) {
+        color = vec3<f32>(0.6, 0.6, 0.7);
+    }
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,549 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use crate::ant::AntRule;
+use crate::automaton::Automaton;
+use crate::boundary::Boundary;
+use crate::cpu::CpuBackend;
+use crate::cyclic::CyclicRule;
+use crate::forest_fire::ForestFire;
+use crate::gray_scott::GrayScott;
+use crate::history;
+use crate::lenia::Lenia;
+use crate::ltl::LtlRule;
+use crate::regions::{RuleMap, TILE};
+use crate::rule::Rule;
+use crate::topology::Topology;
+use crate::transform::Transform;
+
+// 2: soups come from the hash in soup.rs, so a seed logged by version 1 would give a different grid
+// 3: the topology is logged after the boundary
+// 4: the ants and their rule are logged after Gray-Scott
+// 5: the cyclic rule is logged after the ants
+// 6: the Lenia parameters are logged after the cyclic rule
+// 7: the forest fire's probabilities and seed are logged after Lenia
+// 8: the rock-paper-scissors threshold is logged after the cyclic rule, which it used to share
+const MAGIC: &str = "rust-gpu-life session 8";
+
+/// A change made to the grid or the simulation settings; cells are addressed on the grid, not the
+/// window, so a session replays the same at any window size.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ReplayEvent {
+    /// A `width` x `height` block set to one value: a brush stroke, or a cut
+    Fill { x: usize, y: usize, width: usize, height: usize, value: u32 },
+    /// A block written cell by cell: a stamp, a paste or a dropped pattern
+    Rect { x: usize, y: usize, width: usize, cells: Vec<u32> },
+    /// A disk of fresh soup from `seed`, `radius` cells around (`x`, `y`), live cells set to `value`: the noise brush
+    Noise { x: usize, y: usize, radius: usize, seed: u64, density: f32, value: u32 },
+    Clear { keep_walls: bool },
+    /// A fresh soup from `seed`
+    Reset { seed: u64, density: f32 },
+    Rule(Rule),
+    Boundary(Boundary),
+    /// `seed` splits the species when switching to Immigration
+    Automaton { automaton: Automaton, seed: u64 },
+    GrayScott(GrayScott),
+    Lenia(Lenia),
+    ForestFire(ForestFire),
+    CpuBackend(CpuBackend),
+    /// CPU or GPU mode
+    Backend { cpu: bool },
+    /// The whole grid shifted, turned or mirrored
+    Transform(Transform),
+    /// Back to the history snapshot of generation `to`
+    Rewind { to: usize },
+    /// A saved state loaded over the grid
+    Restore { generation: usize, rule: Rule, cells: Vec<u32> },
+    /// The tiles `radius` cells around (`x`, `y`) set to `rule`, or back to the grid's with `None`: the
+    /// rule brush
+    Region { x: usize, y: usize, radius: usize, rule: Option<Rule> },
+    /// The whole rule map replaced, by a saved state's
+    Regions(RuleMap),
+}
+
+/// Where a session's grid starts out
+#[derive(Clone, PartialEq, Debug)]
+pub enum Start {
+    /// A random soup (or Gray-Scott seed squares, or ants), grown again from the seed
+    Soup { seed: u64, density: f32 },
+    /// Anything else: a pattern, a saved state or the Wireworld demo
+    Cells(Vec<u32>),
+}
+
+/// The settings a session starts from.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Session {
+    pub width: u32,
+    pub height: u32,
+    pub generation: usize,
+    pub rule: Rule,
+    pub boundary: Boundary,
+    pub topology: Topology,
+    pub automaton: Automaton,
+    pub ltl: LtlRule,
+    pub gray_scott: GrayScott,
+    pub ants: u32,
+    pub ant_rule: AntRule,
+    pub cyclic: CyclicRule,
+    pub rps_threshold: u32,
+    pub lenia: Lenia,
+    pub forest_fire: ForestFire,
+    pub cpu_backend: CpuBackend,
+    pub start: Start,
+}
+
+impl fmt::Display for ReplayEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayEvent::Fill { x, y, width, height, value } => write!(f, "fill {} {} {} {} {}", x, y, width, height, value),
+            ReplayEvent::Rect { x, y, width, cells } => write!(f, "rect {} {} {} {}", x, y, width, encode_cells(cells)),
+            ReplayEvent::Noise { x, y, radius, seed, density, value } => write!(f, "noise {} {} {} {} {} {}", x, y, radius, seed, density, value),
+            ReplayEvent::Clear { keep_walls } => write!(f, "clear {}", if *keep_walls { "cells" } else { "all" }),
+            ReplayEvent::Reset { seed, density } => write!(f, "reset {} {}", seed, density),
+            ReplayEvent::Rule(rule) => write!(f, "rule {}", rule),
+            ReplayEvent::Boundary(boundary) => write!(f, "boundary {}", boundary),
+            ReplayEvent::Automaton { automaton, seed } => write!(f, "automaton {} {}", automaton, seed),
+            ReplayEvent::GrayScott(params) => write!(f, "gray-scott {}", gray_scott_fields(params)),
+            ReplayEvent::Lenia(params) => write!(f, "lenia {}", lenia_fields(params)),
+            ReplayEvent::ForestFire(params) => write!(f, "forest-fire {}", forest_fire_fields(params)),
+            ReplayEvent::CpuBackend(backend) => write!(f, "cpu-backend {}", backend),
+            ReplayEvent::Backend { cpu } => write!(f, "mode {}", if *cpu { "cpu" } else { "gpu" }),
+            ReplayEvent::Transform(transform) => write!(f, "transform {}", transform),
+            ReplayEvent::Rewind { to } => write!(f, "rewind {}", to),
+            ReplayEvent::Restore { generation, rule, cells } => write!(f, "restore {} {} {}", generation, rule, encode_cells(cells)),
+            ReplayEvent::Region { x, y, radius, rule: Some(rule) } => write!(f, "region {} {} {} {}", x, y, radius, rule),
+            ReplayEvent::Region { x, y, radius, rule: None } => write!(f, "region {} {} {} grid", x, y, radius),
+            ReplayEvent::Regions(map) => write!(f, "regions {}", rule_map_fields(map)),
+        }
+    }
+}
+
+impl ReplayEvent {
+    /// The inverse of `Display`, from the whitespace-separated fields after the generation
+    pub fn parse(fields: &[&str]) -> Result<ReplayEvent, String> {
+        let number = |index: usize| parse_field::<usize>(fields, index);
+        let event = match fields {
+            ["fill", _, _, _, _, value] => ReplayEvent::Fill { x: number(1)?, y: number(2)?, width: number(3)?, height: number(4)?, value: parse_value(value)? },
+            ["rect", _, _, _, cells] => ReplayEvent::Rect { x: number(1)?, y: number(2)?, width: number(3)?, cells: decode_cells(cells)? },
+            ["noise", _, _, _, _, _, value] => ReplayEvent::Noise { x: number(1)?, y: number(2)?, radius: number(3)?, seed: parse_field(fields, 4)?, density: parse_field(fields, 5)?, value: parse_value(value)? },
+            ["clear", "cells"] => ReplayEvent::Clear { keep_walls: true },
+            ["clear", "all"] => ReplayEvent::Clear { keep_walls: false },
+            ["reset", _, _] => ReplayEvent::Reset { seed: parse_field(fields, 1)?, density: parse_field(fields, 2)? },
+            ["rule", rule] => ReplayEvent::Rule(Rule::parse(rule)?),
+            ["boundary", boundary] => ReplayEvent::Boundary(Boundary::parse(boundary)?),
+            ["automaton", automaton, _] => ReplayEvent::Automaton { automaton: Automaton::parse(automaton)?, seed: parse_field(fields, 2)? },
+            ["gray-scott", params @ ..] => ReplayEvent::GrayScott(parse_gray_scott(params)?),
+            ["lenia", params @ ..] => ReplayEvent::Lenia(parse_lenia(params)?),
+            ["forest-fire", params @ ..] => ReplayEvent::ForestFire(parse_forest_fire(params)?),
+            ["cpu-backend", backend] => ReplayEvent::CpuBackend(CpuBackend::parse(backend)?),
+            ["mode", "cpu"] => ReplayEvent::Backend { cpu: true },
+            ["mode", "gpu"] => ReplayEvent::Backend { cpu: false },
+            ["transform", transform @ ..] => ReplayEvent::Transform(Transform::parse(transform)?),
+            ["rewind", _] => ReplayEvent::Rewind { to: number(1)? },
+            ["restore", _, rule, cells] => ReplayEvent::Restore { generation: number(1)?, rule: Rule::parse(rule)?, cells: decode_cells(cells)? },
+            ["region", _, _, _, "grid"] => ReplayEvent::Region { x: number(1)?, y: number(2)?, radius: number(3)?, rule: None },
+            ["region", _, _, _, rule] => ReplayEvent::Region { x: number(1)?, y: number(2)?, radius: number(3)?, rule: Some(Rule::parse(rule)?) },
+            ["regions", map @ ..] => ReplayEvent::Regions(parse_rule_map(map)?),
+            _ => return Err(format!("unknown event '{}'", fields.join(" "))),
+        };
+        Ok(event)
+    }
+
+    /// Whether the event starts the grid on a new timeline, after which the history starts over
+    pub fn jumps(&self) -> bool {
+        matches!(self, ReplayEvent::Reset { .. } | ReplayEvent::Automaton { .. } | ReplayEvent::Restore { .. })
+    }
+}
+
+/// Appends a session to a file: the starting settings, then one line per event, prefixed with the
+/// generation it happened at.
+pub struct SessionLog {
+    pub path: String,
+    writer: BufWriter<File>,
+}
+
+impl SessionLog {
+    pub fn create(path: String, session: &Session) -> Result<SessionLog, String> {
+        let file = File::create(&path).map_err(|e| format!("could not create session log '{}': {}", path, e))?;
+        let mut log = SessionLog { path, writer: BufWriter::new(file) };
+        let start = match &session.start {
+            Start::Soup { seed, density } => format!("soup {} {}", seed, density),
+            Start::Cells(cells) => format!("cells {}", encode_cells(cells)),
+        };
+        let header = [
+            MAGIC.to_string(),
+            format!("grid {} {}", session.width, session.height),
+            format!("generation {}", session.generation),
+            format!("rule {}", session.rule),
+            format!("boundary {}", session.boundary),
+            format!("topology {}", session.topology),
+            format!("automaton {}", session.automaton),
+            format!("ltl {}", ltl_fields(&session.ltl)),
+            format!("gray-scott {}", gray_scott_fields(&session.gray_scott)),
+            format!("ants {} {}", session.ants, session.ant_rule),
+            format!("cyclic {}", cyclic_fields(&session.cyclic)),
+            format!("rps {}", session.rps_threshold),
+            format!("lenia {}", lenia_fields(&session.lenia)),
+            format!("forest-fire {}", forest_fire_fields(&session.forest_fire)),
+            format!("cpu-backend {}", session.cpu_backend),
+            start,
+        ];
+        for line in header {
+            writeln!(log.writer, "{}", line).map_err(|e| format!("could not write session log '{}': {}", log.path, e))?;
+        }
+        log.flush();
+        Ok(log)
+    }
+
+    /// Written straight through, so a crash right after the weird artifact still leaves a usable log
+    pub fn log(&mut self, generation: usize, event: &ReplayEvent) {
+        if let Err(e) = writeln!(self.writer, "{} {}", generation, event) {
+            eprintln!("Failed to write to the session log {}: {}", self.path, e);
+        }
+        self.flush();
+    }
+
+    pub fn flush(&mut self) {
+        if let Err(e) = self.writer.flush() {
+            eprintln!("Failed to flush the session log {}: {}", self.path, e);
+        }
+    }
+}
+
+/// A recorded session being played back: its events are handed out as the simulation reaches their
+/// generations.
+pub struct Replay {
+    events: VecDeque<(usize, ReplayEvent)>,
+    // Generations some rewind goes back to, with the cells the replay had when it got there, packed
+    // like the history packs them
+    rewind_targets: HashSet<usize>,
+    snapshots: HashMap<usize, Vec<u32>>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<(Session, Replay), String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("could not read session '{}': {}", path.display(), e))?;
+        Replay::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<(Session, Replay), String> {
+        let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+        if lines.next().map(|(_, line)| line.trim()) != Some(MAGIC) {
+            return Err("not a session log".to_string());
+        }
+        let mut header = |key: &str| -> Result<Vec<String>, String> {
+            match lines.next() {
+                Some((_, line)) if line.split_whitespace().next() == Some(key) => Ok(line.split_whitespace().skip(1).map(str::to_string).collect()),
+                Some((number, _)) => Err(format!("line {}: expected '{}'", number + 1, key)),
+                None => Err(format!("missing '{}'", key)),
+            }
+        };
+        let grid = header("grid")?;
+        let (width, height) = (parse_field(&fields(&grid), 0)?, parse_field(&fields(&grid), 1)?);
+        let generation = parse_field(&fields(&header("generation")?), 0)?;
+        let rule = Rule::parse(&header("rule")?.join(" "))?;
+        let boundary = Boundary::parse(&header("boundary")?.join(" "))?;
+        let topology = Topology::parse(&header("topology")?.join(" "))?;
+        let automaton = Automaton::parse(&header("automaton")?.join(" "))?;
+        let ltl = parse_ltl(&fields(&header("ltl")?))?;
+        let gray_scott = parse_gray_scott(&fields(&header("gray-scott")?))?;
+        let (ants, ant_rule) = match fields(&header("ants")?)[..] {
+            [count, rule] => (parse_field(&[count], 0)?, AntRule::parse(rule)?),
+            _ => return Err("expected 'ants COUNT RULE'".to_string()),
+        };
+        let cyclic = parse_cyclic(&fields(&header("cyclic")?))?;
+        let rps_threshold = parse_rps(&fields(&header("rps")?))?;
+        let lenia = parse_lenia(&fields(&header("lenia")?))?;
+        let forest_fire = parse_forest_fire(&fields(&header("forest-fire")?))?;
+        let cpu_backend = CpuBackend::parse(&header("cpu-backend")?.join(" "))?;
+        let start = match lines.next().map(|(_, line)| line.split_whitespace().collect::<Vec<_>>()) {
+            Some(start) if start.len() == 3 && start[0] == "soup" => Start::Soup { seed: parse_field(&start, 1)?, density: parse_field(&start, 2)? },
+            Some(start) if start.len() == 2 && start[0] == "cells" => Start::Cells(decode_cells(start[1])?),
+            _ => return Err("expected 'soup SEED DENSITY' or 'cells CELLS'".to_string()),
+        };
+        if let Start::Cells(cells) = &start {
+            if cells.len() != width as usize * height as usize {
+                return Err(format!("expected {} starting cells but found {}", width as usize * height as usize, cells.len()));
+            }
+        }
+
+        let mut events = VecDeque::new();
+        for (number, line) in lines {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let generation = parse_field(&fields, 0).map_err(|e| format!("line {}: {}", number + 1, e))?;
+            let event = ReplayEvent::parse(&fields[1..]).map_err(|e| format!("line {}: {}", number + 1, e))?;
+            events.push_back((generation, event));
+        }
+        let rewind_targets = events.iter().filter_map(|(_, event)| match event {
+            ReplayEvent::Rewind { to } => Some(*to),
+            _ => None,
+        }).collect();
+        let session = Session { width, height, generation, rule, boundary, topology, automaton, ltl, gray_scott, ants, ant_rule, cyclic, rps_threshold, lenia, forest_fire, cpu_backend, start };
+        Ok((session, Replay { events, rewind_targets, snapshots: HashMap::new() }))
+    }
+
+    /// Events still to come.
+    pub fn remaining(&self) -> usize {
+        self.events.len()
+    }
+
+    /// The next event if it happened at `generation`, or earlier if the playback has fallen out of step.
+    pub fn next_due(&mut self, generation: usize) -> Option<ReplayEvent> {
+        if self.events.front().is_some_and(|&(at, _)| at <= generation) {
+            self.events.pop_front().map(|(_, event)| event)
+        } else {
+            None
+        }
+    }
+
+    /// How many generations may be stepped from `generation` without passing the next event or a
+    /// generation a rewind needs a snapshot of.
+    pub fn steps_allowed(&self, generation: usize) -> Option<usize> {
+        let next_event = self.events.front().map(|&(at, _)| at);
+        let next_target = self.rewind_targets.iter().copied().filter(|&target| target > generation).min();
+        next_event.into_iter().chain(next_target).map(|at| at.saturating_sub(generation)).min()
+    }
+
+    /// Whether a rewind goes back to `generation`, so `snapshot` should see its cells.
+    pub fn needs_snapshot(&self, generation: usize) -> bool {
+        self.rewind_targets.contains(&generation)
+    }
+
+    /// Keeps the cells at `generation` if a rewind goes back there; call when stepping lands on a
+    /// generation and after an event that `jumps`, which is when the history records one.
+    pub fn snapshot(&mut self, generation: usize, cells: &[u32], bits: u32) {
+        if bits > 0 && self.needs_snapshot(generation) {
+            self.snapshots.insert(generation, history::pack(cells, bits));
+        }
+    }
+
+    /// The cells a rewind to `generation` restores, as the history would have unpacked them.
+    pub fn rewound(&self, generation: usize, bits: u32, len: usize) -> Option<Vec<u32>> {
+        self.snapshots.get(&generation).map(|words| history::unpack(words, bits, len))
+    }
+}
+
+// A header's values as the &str fields the parsers take
+fn fields(values: &[String]) -> Vec<&str> {
+    values.iter().map(String::as_str).collect()
+}
+
+pub(crate) fn parse_field<T: std::str::FromStr>(fields: &[&str], index: usize) -> Result<T, String> {
+    let field = fields.get(index).ok_or("missing field")?;
+    field.parse().map_err(|_| format!("invalid field '{}'", field))
+}
+
+fn parse_value(text: &str) -> Result<u32, String> {
+    text.parse().map_err(|_| format!("invalid cell value '{}'", text))
+}
+
+pub(crate) fn ltl_fields(rule: &LtlRule) -> String {
+    format!("{} {}-{} {}-{}", rule.radius, rule.birth.0, rule.birth.1, rule.survive.0, rule.survive.1)
+}
+
+pub(crate) fn parse_ltl(fields: &[&str]) -> Result<LtlRule, String> {
+    match fields {
+        [radius, birth, survive] => Ok(LtlRule { radius: LtlRule::parse_radius(radius)?, birth: LtlRule::parse_range(birth)?, survive: LtlRule::parse_range(survive)? }),
+        _ => Err("expected 'ltl RADIUS BIRTH SURVIVE'".to_string()),
+    }
+}
+
+pub(crate) fn cyclic_fields(rule: &CyclicRule) -> String {
+    format!("{} {}", rule.states, rule.threshold)
+}
+
+pub(crate) fn parse_cyclic(fields: &[&str]) -> Result<CyclicRule, String> {
+    match fields {
+        [states, threshold] => Ok(CyclicRule { states: CyclicRule::parse_states(states)?, threshold: CyclicRule::parse_threshold(threshold)? }),
+        _ => Err("expected 'cyclic STATES THRESHOLD'".to_string()),
+    }
+}
+
+pub(crate) fn parse_rps(fields: &[&str]) -> Result<u32, String> {
+    match fields {
+        [threshold] => CyclicRule::parse_threshold(threshold),
+        _ => Err("expected 'rps THRESHOLD'".to_string()),
+    }
+}
+
+pub(crate) fn gray_scott_fields(params: &GrayScott) -> String {
+    format!("{} {} {} {} {}", params.feed, params.kill, params.diffusion_u, params.diffusion_v, params.dt)
+}
+
+pub(crate) fn parse_gray_scott(fields: &[&str]) -> Result<GrayScott, String> {
+    match fields {
+        [feed, kill, diffusion_u, diffusion_v, dt] => {
+            let number = |text: &str| text.parse::<f32>().map_err(|_| format!("invalid Gray-Scott parameter '{}'", text));
+            Ok(GrayScott { feed: number(feed)?, kill: number(kill)?, diffusion_u: number(diffusion_u)?, diffusion_v: number(diffusion_v)?, dt: number(dt)? })
+        }
+        _ => Err("expected five Gray-Scott parameters".to_string()),
+    }
+}
+
+pub(crate) fn lenia_fields(params: &Lenia) -> String {
+    format!("{} {} {} {}", params.radius, params.mu, params.sigma, params.dt)
+}
+
+pub(crate) fn parse_lenia(fields: &[&str]) -> Result<Lenia, String> {
+    match fields {
+        [radius, mu, sigma, dt] => Ok(Lenia { radius: Lenia::parse_radius(radius)?, mu: Lenia::parse_fraction(mu)?, sigma: Lenia::parse_fraction(sigma)?, dt: Lenia::parse_fraction(dt)? }),
+        _ => Err("expected four Lenia parameters".to_string()),
+    }
+}
+
+pub(crate) fn forest_fire_fields(params: &ForestFire) -> String {
+    format!("{} {} {}", params.growth, params.lightning, params.seed)
+}
+
+pub(crate) fn parse_forest_fire(fields: &[&str]) -> Result<ForestFire, String> {
+    match fields {
+        [growth, lightning, _] => Ok(ForestFire { growth: ForestFire::parse_probability(growth)?, lightning: ForestFire::parse_probability(lightning)?, seed: parse_field(fields, 2)? }),
+        _ => Err("expected 'forest-fire GROWTH LIGHTNING SEED'".to_string()),
+    }
+}
+
+// "COLUMNS ROWS RULES TILES": the painted rules comma-separated, or "-" for none, and the tiles' table
+// indices as runs like cells
+fn rule_map_fields(map: &RuleMap) -> String {
+    let rules = map.rules().iter().map(Rule::to_string).collect::<Vec<_>>().join(",");
+    let tiles = map.tiles().iter().map(|&index| index as u32).collect::<Vec<_>>();
+    format!("{} {} {} {}", map.columns(), map.rows(), if rules.is_empty() { "-" } else { &rules }, encode_cells(&tiles))
+}
+
+fn parse_rule_map(fields: &[&str]) -> Result<RuleMap, String> {
+    match fields {
+        [_, _, rules, tiles] => {
+            let (columns, rows) = (parse_field::<u32>(fields, 0)?, parse_field::<u32>(fields, 1)?);
+            let rules = rules.split(',').filter(|rule| *rule != "-").map(Rule::parse).collect::<Result<Vec<_>, _>>()?;
+            let tiles = decode_cells(tiles)?.into_iter().map(|index| u8::try_from(index).map_err(|_| format!("invalid rule map tile {}", index))).collect::<Result<Vec<_>, _>>()?;
+            RuleMap::from_parts(columns * TILE as u32, rows * TILE as u32, rules, tiles)
+        }
+        _ => Err("expected 'regions COLUMNS ROWS RULES TILES'".to_string()),
+    }
+}
+
+/// Cells as comma-separated runs, "RUN*VALUE" or just "VALUE" for a run of one: [0, 0, 0, 1] -> "3*0,1"
+pub fn encode_cells(cells: &[u32]) -> String {
+    let mut runs = Vec::new();
+    let mut cells = cells.iter().copied().peekable();
+    while let Some(value) = cells.next() {
+        let mut run = 1;
+        while cells.next_if_eq(&value).is_some() {
+            run += 1;
+        }
+        runs.push(if run == 1 { value.to_string() } else { format!("{}*{}", run, value) });
+    }
+    runs.join(",")
+}
+
+pub fn decode_cells(text: &str) -> Result<Vec<u32>, String> {
+    let mut cells = Vec::new();
+    for run in text.split(',').filter(|run| !run.is_empty()) {
+        let (count, value) = match run.split_once('*') {
+            Some((count, value)) => (count.parse().map_err(|_| format!("invalid run '{}'", run))?, parse_value(value)?),
+            None => (1, parse_value(run)?),
+        };
+        cells.resize(cells.len() + count, value);
+    }
+    Ok(cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WALL;
+
+    fn session(start: Start) -> Session {
+        Session {
+            width: 4, height: 2, generation: 7, rule: Rule::parse("B36/S23").unwrap(), boundary: Boundary::Mirror, topology: Topology::Hex, automaton: Automaton::Immigration,
+            ltl: LtlRule::BOSCO, gray_scott: GrayScott { feed: 0.03, ..GrayScott::CORAL }, ants: 3, ant_rule: AntRule::parse("LLRR").unwrap(), cyclic: CyclicRule { states: 5, threshold: 3 }, rps_threshold: 2, lenia: Lenia { radius: 9, ..Lenia::ORBIUM }, forest_fire: ForestFire { seed: 11, ..ForestFire::DROSSEL_SCHWABL }, cpu_backend: CpuBackend::Bitpacked, start,
+        }
+    }
+
+    #[test]
+    fn cells_round_trip() {
+        let cells = vec![0, 0, 0, 1, WALL, WALL, 7, 0];
+        assert_eq!(encode_cells(&cells), format!("3*0,1,2*{},7,0", WALL));
+        assert_eq!(decode_cells(&encode_cells(&cells)).unwrap(), cells);
+        assert!(decode_cells("2*x").is_err());
+    }
+
+    #[test]
+    fn events_round_trip() {
+        let mut regions = RuleMap::new(100, 70);
+        regions.paint(50, 50, 20, Some(Rule::parse("B36/S23").unwrap())).unwrap();
+        regions.paint(0, 0, 0, Some(Rule::parse("B2/S").unwrap())).unwrap();
+        let events = [
+            ReplayEvent::Fill { x: 3, y: 4, width: 2, height: 1, value: WALL },
+            ReplayEvent::Rect { x: 0, y: 1, width: 2, cells: vec![1, 0, 0, 1] },
+            ReplayEvent::Noise { x: 5, y: 6, radius: 12, seed: 77, density: 0.25, value: 1 },
+            ReplayEvent::Clear { keep_walls: false },
+            ReplayEvent::Reset { seed: u64::MAX, density: 0.35 },
+            ReplayEvent::Rule(Rule::parse("B3678/S34678").unwrap()),
+            ReplayEvent::Boundary(Boundary::Dead),
+            ReplayEvent::Automaton { automaton: Automaton::GrayScott, seed: 42 },
+            ReplayEvent::GrayScott(GrayScott { kill: 0.0612, dt: 0.7, ..GrayScott::CORAL }),
+            ReplayEvent::Lenia(Lenia { mu: 0.26, sigma: 0.036, ..Lenia::ORBIUM }),
+            ReplayEvent::ForestFire(ForestFire { growth: 0.05, lightning: 0.0002, seed: 3 }),
+            ReplayEvent::CpuBackend(CpuBackend::Naive),
+            ReplayEvent::Backend { cpu: true },
+            ReplayEvent::Transform(Transform::Shift { dx: -3, dy: 8 }),
+            ReplayEvent::Transform(Transform::MirrorY),
+            ReplayEvent::Rewind { to: 90 },
+            ReplayEvent::Restore { generation: 80_000, rule: Rule::CONWAY, cells: vec![0; 8] },
+            ReplayEvent::Region { x: 40, y: 2, radius: 6, rule: Some(Rule::parse("B2/S").unwrap()) },
+            ReplayEvent::Region { x: 0, y: 0, radius: 0, rule: None },
+            ReplayEvent::Regions(RuleMap::new(64, 40)),
+            ReplayEvent::Regions(regions),
+        ];
+        for event in events {
+            let line = event.to_string();
+            assert_eq!(ReplayEvent::parse(&line.split_whitespace().collect::<Vec<_>>()).unwrap(), event, "{}", line);
+        }
+    }
+
+    #[test]
+    fn sessions_round_trip() {
+        let path = std::env::temp_dir().join(format!("rust_gpu_life_session_{}.log", std::process::id()));
+        for start in [Start::Soup { seed: 5, density: 0.2 }, Start::Cells(vec![0, 1, 0, 0, 2, 0, 0, 1])] {
+            let mut log = SessionLog::create(path.display().to_string(), &session(start.clone())).unwrap();
+            log.log(7, &ReplayEvent::Clear { keep_walls: true });
+            log.log(12, &ReplayEvent::Reset { seed: 9, density: 0.5 });
+            log.log(0, &ReplayEvent::Rewind { to: 0 });
+            drop(log);
+
+            let (loaded, mut replay) = Replay::load(&path).unwrap();
+            assert_eq!(loaded, session(start));
+            assert_eq!(replay.remaining(), 3);
+            assert_eq!(replay.next_due(6), None);
+            assert_eq!(replay.steps_allowed(0), Some(7));
+            assert_eq!(replay.next_due(7), Some(ReplayEvent::Clear { keep_walls: true }));
+            assert_eq!(replay.steps_allowed(7), Some(5));
+            assert_eq!(replay.next_due(12), Some(ReplayEvent::Reset { seed: 9, density: 0.5 }));
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn playback_stops_where_rewinds_need_snapshots() {
+        let text = format!("{}\ngrid 2 2\ngeneration 0\nrule B3/S23\nboundary wrap\ntopology square\nautomaton life\nltl 5 34-45 33-57\ngray-scott 0.0545 0.062 1 0.5 1\nants 1 RL\ncyclic 14 1\nrps 1\nlenia 13 0.15 0.015 0.1\nforest-fire 0.01 0.00001 0\ncpu-backend naive\ncells 4*0\n100 rewind 40\n", MAGIC);
+        let (_, mut replay) = Replay::parse(&text).unwrap();
+        assert_eq!(replay.steps_allowed(0), Some(40));
+        assert_eq!(replay.steps_allowed(40), Some(60));
+
+        replay.snapshot(40, &[5, 0, 0, 1], 1);
+        replay.snapshot(41, &[1, 1, 1, 1], 1);
+        assert_eq!(replay.rewound(40, 1, 4), Some(vec![1, 0, 0, 1]));
+        assert_eq!(replay.rewound(41, 1, 4), None);
+    }
+
+    #[test]
+    fn rejects_bad_sessions() {
+        assert!(Replay::parse("not a session").is_err());
+        let header = format!("{}\ngrid 2 2\ngeneration 0\nrule B3/S23\nboundary wrap\ntopology square\nautomaton life\nltl 5 34-45 33-57\ngray-scott 0.0545 0.062 1 0.5 1\nants 1 RL\ncyclic 14 1\nrps 1\nlenia 13 0.15 0.015 0.1\nforest-fire 0.01 0.00001 0\ncpu-backend naive\n", MAGIC);
+        assert!(Replay::parse(&format!("{}cells 3*0\n", header)).err().unwrap().contains("starting cells"));
+        assert!(Replay::parse(&format!("{}soup 1 0.2\n5 explode\n", header)).err().unwrap().contains("line 17"));
+    }
+}
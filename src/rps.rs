@@ -0,0 +1,121 @@
+use std::f64::consts::PI;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use crate::boundary::Boundary;
+use crate::WALL;
+
+/// Species 1 to this; 0 is empty ground
+pub const SPECIES: u32 = 3;
+
+/// Neighbours of its predator that take a species cell over, unless --rps-threshold says otherwise
+pub const THRESHOLD: u32 = 1;
+
+/// The species that beats `species`: 1 beats 2, 2 beats 3 and 3 beats 1
+pub fn predator(species: u32) -> u32 {
+    (species + 1) % SPECIES + 1
+}
+
+/// A cell's next state from how many of its neighbours hold each species. A species cell turns into its
+/// predator once at least `threshold` neighbours are that predator; empty ground goes to the species with
+/// the most neighbours, if it has at least `threshold` and no other species has as many.
+pub fn next_state(cell: u32, counts: [u32; 3], threshold: u32) -> u32 {
+    match cell {
+        0 => {
+            let most = counts.iter().copied().max().unwrap_or(0);
+            let mut leaders = (0..3).filter(|&s| counts[s] == most);
+            match (leaders.next(), leaders.next()) {
+                (Some(leader), None) if most >= threshold => leader as u32 + 1,
+                _ => 0,
+            }
+        }
+        1..=SPECIES if counts[predator(cell) as usize - 1] >= threshold => predator(cell),
+        // Walls and anything else stay put
+        other => other,
+    }
+}
+
+/// Every cell one of the three species at random
+pub fn random_grid(width: u32, height: u32, seed: u64) -> Vec<u32> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..width as usize * height as usize).map(|_| rng.gen_range(1..=SPECIES)).collect()
+}
+
+/// Three solid wedges meeting in the middle of the grid, one per species, so the fronts curl into spirals
+/// around a single point instead of the many a soup starts
+pub fn wedges(width: u32, height: u32) -> Vec<u32> {
+    let (cx, cy) = (width as f64 / 2.0, height as f64 / 2.0);
+    (0..height).flat_map(|y| (0..width).map(move |x| {
+        let angle = (y as f64 + 0.5 - cy).atan2(x as f64 + 0.5 - cx) + PI;
+        ((angle / (2.0 * PI / SPECIES as f64)) as u32).min(SPECIES - 1) + 1
+    })).collect()
+}
+
+/// One generation of a `width`-wide grid.
+pub fn step_grid(input: &[u32], width: usize, threshold: u32, boundary: Boundary) -> Vec<u32> {
+    let mut output = vec![0; input.len()];
+    step_grid_into(input, &mut output, width, threshold, boundary);
+    output
+}
+
+/// `step_grid` writing into `output` (the same length as `input`) instead of allocating it.
+///
+/// Always the 3x3 Moore neighbourhood; walls, empty cells and neighbours off a dead edge are no species.
+pub fn step_grid_into(input: &[u32], output: &mut [u32], width: usize, threshold: u32, boundary: Boundary) {
+    let height = input.len() / width;
+    output.par_iter_mut().enumerate().for_each(|(index, out)| {
+        let cell = input[index];
+        if cell == WALL {
+            *out = WALL;
+            return;
+        }
+        let (x, y) = ((index % width) as i64, (index / width) as i64);
+        let mut counts = [0; 3];
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if dx == 0 && dy == 0 { continue; }
+                if let (Some(nx), Some(ny)) = (boundary.resolve(x + dx, width), boundary.resolve(y + dy, height)) {
+                    let neighbour = input[ny * width + nx];
+                    if (1..=SPECIES).contains(&neighbour) { counts[neighbour as usize - 1] += 1; }
+                }
+            }
+        }
+        *out = next_state(cell, counts, threshold);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn species_fall_to_their_predators() {
+        assert_eq!([1, 2, 3].map(predator), [3, 1, 2]);
+        let cells = [
+            1, 1, 1,
+            1, 2, 1,
+            1, 1, 3,
+        ];
+        assert_eq!(step_grid(&cells, 3, 1, Boundary::Dead), vec![
+            1, 1, 1,
+            1, 1, 3,
+            1, 3, 2,
+        ]);
+        // A higher threshold needs more of them around
+        assert_eq!(step_grid(&cells, 3, 2, Boundary::Dead), vec![
+            1, 1, 1,
+            1, 1, 1,
+            1, 1, 3,
+        ]);
+        // Empty ground goes to a clear majority only; walls stay
+        assert_eq!(step_grid(&[1, 0, 1, WALL, 2, 0, 3], 7, 1, Boundary::Dead), vec![1, 1, 1, WALL, 2, 0, 3]);
+    }
+
+    #[test]
+    fn starts_hold_every_species() {
+        let soup = random_grid(32, 32, 7);
+        assert!((1..=SPECIES).all(|species| soup.contains(&species)) && !soup.contains(&0));
+        let wedges = wedges(30, 30);
+        let counts = (1..=SPECIES).map(|species| wedges.iter().filter(|&&cell| cell == species).count()).collect::<Vec<_>>();
+        assert!(counts.iter().all(|&count| count > 250 && count < 350), "{:?}", counts);
+    }
+}
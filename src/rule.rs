@@ -0,0 +1,162 @@
+use std::fmt;
+
+/// Most states a Generations rule can have, as in Golly
+pub const MAX_STATES: u32 = 256;
+
+/// Life-like rule as neighbour-count bitmasks: bit n set means n live neighbours cause birth/survival.
+/// Generations rules have more than two `states`: cells that don't survive fade through the states past 1
+/// before they're dead, counting as dead neighbours but unable to be born meanwhile.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rule {
+    pub birth: u32,
+    pub survive: u32,
+    pub states: u32,
+}
+
+impl Rule {
+    pub const CONWAY: Rule = Rule { birth: 1 << 3, survive: 1 << 2 | 1 << 3, states: 2 };
+
+    /// Accepts "B3/S23" (either order, any case) and the older "23/3" survival/birth notation; a third part
+    /// ("345/2/4", "B2/S345/C4") gives a Generations rule's number of states
+    pub fn parse(text: &str) -> Result<Rule, String> {
+        let invalid = || format!("invalid rulestring '{}' (expected something like B3/S23 or 345/2/4)", text);
+        let parts: Vec<&str> = text.trim().split('/').collect();
+        if parts.len() < 2 || parts.len() > 3 {
+            return Err(invalid());
+        }
+        let states = |digits: &str| match digits.parse::<u32>() {
+            Ok(states) if (2..=MAX_STATES).contains(&states) => Ok(states),
+            _ => Err(format!("invalid rulestring '{}' (a Generations rule has 2 to {} states)", text, MAX_STATES)),
+        };
+
+        let mut rule = Rule { birth: 0, survive: 0, states: 2 };
+        let numeric = parts.iter().all(|part| !part.starts_with(|c: char| c.is_ascii_alphabetic()));
+        if numeric {
+            rule.survive = digit_mask(parts[0]).ok_or_else(invalid)?;
+            rule.birth = digit_mask(parts[1]).ok_or_else(invalid)?;
+            if let Some(count) = parts.get(2) {
+                rule.states = states(count)?;
+            }
+            return Ok(rule);
+        }
+
+        let (mut seen_b, mut seen_s, mut seen_c) = (false, false, false);
+        for part in parts {
+            let mut chars = part.chars();
+            match chars.next().map(|c| c.to_ascii_uppercase()) {
+                Some('B') if !seen_b => { rule.birth = digit_mask(chars.as_str()).ok_or_else(invalid)?; seen_b = true; }
+                Some('S') if !seen_s => { rule.survive = digit_mask(chars.as_str()).ok_or_else(invalid)?; seen_s = true; }
+                // Golly writes C, some older programs G
+                Some('C' | 'G') if !seen_c => { rule.states = states(chars.as_str())?; seen_c = true; }
+                _ => return Err(invalid()),
+            }
+        }
+        if !seen_b || !seen_s {
+            return Err(invalid());
+        }
+        Ok(rule)
+    }
+
+    pub fn next_state(&self, alive: bool, neighbors: u32) -> bool {
+        let mask = if alive { self.survive } else { self.birth };
+        (mask >> neighbors) & 1 == 1
+    }
+
+    /// Whether dying cells fade through extra states
+    pub fn is_generations(&self) -> bool {
+        self.states > 2
+    }
+}
+
+fn digit_mask(digits: &str) -> Option<u32> {
+    digits.chars().try_fold(0u32, |mask, c| match c.to_digit(10) {
+        Some(n) if n <= 8 => Some(mask | 1 << n),
+        _ => None,
+    })
+}
+
+impl fmt::Display for Rule {
+    // Generations rules in the survival/birth/states notation Golly uses for them
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = |mask: u32| (0..=8).filter(|n| mask >> n & 1 == 1).map(|n| char::from(b'0' + n as u8)).collect::<String>();
+        if self.is_generations() {
+            write!(f, "{}/{}/{}", digits(self.survive), digits(self.birth), self.states)
+        } else {
+            write!(f, "B{}/S{}", digits(self.birth), digits(self.survive))
+        }
+    }
+}
+
+/// Named rules, offered in the window's rule selector, cycled through by Y or Tab and taken by name on the
+/// command line; any other rulestring can be typed in
+pub const RULE_PRESETS: [(&str, &str); 11] = [
+    ("Conway", "B3/S23"),
+    ("HighLife", "B36/S23"),
+    ("Day & Night", "B3678/S34678"),
+    ("Seeds", "B2/S"),
+    ("Life without Death", "B3/S012345678"),
+    ("Maze", "B3/S12345"),
+    ("Anneal", "B4678/S35678"),
+    ("Replicator", "B1357/S1357"),
+    ("2x2", "B36/S125"),
+    ("Star Wars", "345/2/4"),
+    ("Brian's Brain", "/2/3"),
+];
+
+/// The name `rule` is offered under, if it's a preset
+pub fn preset_name(rule: Rule) -> Option<&'static str> {
+    let text = rule.to_string();
+    RULE_PRESETS.iter().find(|(_, preset)| *preset == text).map(|(name, _)| *name)
+}
+
+/// A preset by name, ignoring case, spaces and punctuation: "day-night", "DayNight" and "2x2" all work
+pub fn rule_preset(name: &str) -> Option<Rule> {
+    let key = |text: &str| text.chars().filter(char::is_ascii_alphanumeric).map(|c| c.to_ascii_lowercase()).collect::<String>();
+    RULE_PRESETS.iter().find(|(preset, _)| key(preset) == key(name)).map(|(_, text)| Rule::parse(text).expect("presets are valid rules"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_life_like_rules() {
+        assert_eq!(Rule::parse("B3/S23"), Ok(Rule::CONWAY));
+        assert_eq!(Rule::parse("s23/b3"), Ok(Rule::CONWAY));
+        assert_eq!(Rule::parse("23/3"), Ok(Rule::CONWAY));
+        assert_eq!(Rule::CONWAY.to_string(), "B3/S23");
+        assert!(Rule::parse("B3").is_err());
+        assert!(Rule::parse("B9/S23").is_err());
+    }
+
+    #[test]
+    fn parses_generations_rules() {
+        let star_wars = Rule { birth: 1 << 2, survive: 1 << 3 | 1 << 4 | 1 << 5, states: 4 };
+        assert_eq!(Rule::parse("345/2/4"), Ok(star_wars));
+        assert_eq!(Rule::parse("B2/S345/C4"), Ok(star_wars));
+        assert_eq!(Rule::parse("b2/s345/g4"), Ok(star_wars));
+        assert_eq!(star_wars.to_string(), "345/2/4");
+        assert_eq!(Rule::parse(&star_wars.to_string()), Ok(star_wars));
+        // Two states is plain Life-like
+        assert_eq!(Rule::parse("23/3/2"), Ok(Rule::CONWAY));
+        assert!(Rule::parse("345/2/1").is_err());
+        assert!(Rule::parse("345/2/257").is_err());
+        assert!(Rule::parse("B2/S345/C4/C5").is_err());
+    }
+
+    #[test]
+    fn presets_are_found_by_name_and_rule() {
+        for (name, text) in RULE_PRESETS {
+            let rule = Rule::parse(text).unwrap();
+            assert_eq!(rule_preset(name), Some(rule));
+            assert_eq!(preset_name(rule), Some(name));
+        }
+        let day_night = Rule::parse("B3678/S34678").unwrap();
+        for name in ["day-night", "DayNight", "day & night", "DAY_NIGHT"] {
+            assert_eq!(rule_preset(name), Some(day_night));
+        }
+        assert_eq!(rule_preset("2x2"), Rule::parse("B36/S125").ok());
+        assert_eq!(rule_preset("nightday"), None);
+        assert_eq!(preset_name(Rule::parse("B36/S234").unwrap()), None);
+    }
+}
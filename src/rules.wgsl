@@ -0,0 +1,308 @@
+// Rules shared by the compute shaders; each file that includes this declares `params`
+
+// Grid dimensions plus the rule bitmasks: bit n is set if n live neighbours cause birth / survival
+struct Params {
+    width: u32,
+    height: u32,
+    birth: u32,
+    survive: u32,
+    boundary: u32, // One of the BOUNDARY_* values
+    automaton: u32, // One of the AUTOMATON_* values
+    // Larger than Life: neighbourhood radius and inclusive birth / survival ranges
+    radius: u32,
+    birth_min: u32,
+    birth_max: u32,
+    survive_min: u32,
+    survive_max: u32,
+    // Gray-Scott: feed and kill rates, diffusion rates of u and v, time step
+    feed: f32,
+    kill: f32,
+    diffusion_u: f32,
+    diffusion_v: f32,
+    dt: f32,
+    // Generations: cells that don't survive fade through states 2 to states - 1 before they're dead
+    states: u32,
+    topology: u32, // One of the TOPOLOGY_* values
+    // Cyclic: cells hold 0 to cyclic_states - 1 and advance with at least cyclic_threshold neighbours on the next value
+    cyclic_states: u32,
+    cyclic_threshold: u32,
+    // Rock-paper-scissors: predator neighbours it takes to take a species cell over
+    rps_threshold: u32,
+    // Lenia: kernel radius, the potential growth peaks at and how wide the peak is, time step
+    lenia_radius: u32,
+    lenia_mu: f32,
+    lenia_sigma: f32,
+    lenia_dt: f32,
+    // Forest fire: growth and lightning probabilities as bounds on a 24-bit roll, and the key rolls are hashed with
+    forest_growth: u32,
+    forest_lightning: u32,
+    forest_key: u32,
+};
+
+// Matches `Boundary` in boundary.rs
+const BOUNDARY_WRAP: u32 = 0u;
+const BOUNDARY_DEAD: u32 = 1u;
+const BOUNDARY_MIRROR: u32 = 2u;
+
+// Matches `Topology` in topology.rs
+const TOPOLOGY_SQUARE: u32 = 0u;
+const TOPOLOGY_HEX: u32 = 1u;
+
+// Matches `Automaton` in automaton.rs
+const AUTOMATON_LIFE: u32 = 0u;
+const AUTOMATON_BRIANS_BRAIN: u32 = 1u;
+const AUTOMATON_LTL: u32 = 2u;
+const AUTOMATON_WIREWORLD: u32 = 3u;
+const AUTOMATON_IMMIGRATION: u32 = 4u;
+const AUTOMATON_GRAY_SCOTT: u32 = 5u;
+const AUTOMATON_GENERATIONS: u32 = 6u;
+const AUTOMATON_LANGTONS_ANT: u32 = 7u;
+const AUTOMATON_CYCLIC: u32 = 8u;
+const AUTOMATON_LENIA: u32 = 9u;
+const AUTOMATON_FOREST_FIRE: u32 = 10u;
+const AUTOMATON_ROCK_PAPER_SCISSORS: u32 = 11u;
+
+// Immigration counts each blue neighbour as this, so one sum carries both colours (matches automaton.rs)
+const BLUE_NEIGHBOUR: u32 = 16u;
+
+// Live cells store their age in generations, saturating here (matches MAX_AGE in lib.rs)
+const MAX_AGE: u32 = 65535u;
+
+// Obstacles that never change and count as dead neighbours (matches WALL in lib.rs)
+const WALL: u32 = 0xffffffffu;
+
+// Maps a neighbour coordinate on an axis `extent` cells long back onto the grid, or -1 when it falls
+// off a dead edge
+fn resolve(c: i32, extent: u32) -> i32 {
+    let size = i32(extent);
+    if (c >= 0 && c < size) { return c; }
+    switch params.boundary {
+        case BOUNDARY_DEAD: { return -1; }
+        case BOUNDARY_MIRROR: { return clamp(c, 0, size - 1); }
+        case BOUNDARY_WRAP, default: { return ((c % size) + size) % size; }
+    }
+}
+
+// Gray-Scott cells pack 1 - u in the high 16 bits and v in the low 16, so the uniform field is 0; v stops
+// one short of the top so no cell reads as a wall (matches gray_scott.rs)
+fn gray_scott_decode(cell: u32) -> vec2<f32> {
+    return vec2<f32>(1.0 - f32(cell >> 16u) / 65535.0, f32(cell & 0xffffu) / 65535.0);
+}
+
+fn gray_scott_encode(concentrations: vec2<f32>) -> u32 {
+    let quantized = vec2<u32>(floor(clamp(vec2<f32>(1.0 - concentrations.x, concentrations.y), vec2<f32>(0.0), vec2<f32>(1.0)) * 65535.0 + 0.5));
+    return (quantized.x << 16u) | min(quantized.y, 0xfffeu);
+}
+
+// A Gray-Scott neighbour's concentrations; walls and cells off a dead edge (-1) read as the uniform field
+fn gray_scott_neighbour(state: u32, on_grid: bool) -> vec2<f32> {
+    if (!on_grid || state == WALL) { return vec2<f32>(1.0, 0.0); }
+    return gray_scott_decode(state);
+}
+
+// Weight of a neighbour in the 3x3 Laplacian: 0.2 along the edges, 0.05 on the corners
+fn laplacian_weight(i: i32, j: i32) -> f32 {
+    return select(0.05, 0.2, i == 0 || j == 0);
+}
+
+// One Gray-Scott time step of a cell's concentrations given the Laplacian of both (matches `GrayScott::react`)
+fn gray_scott_react(c: vec2<f32>, laplacian: vec2<f32>) -> vec2<f32> {
+    let uvv = c.x * c.y * c.y;
+    let u = c.x + params.dt * (params.diffusion_u * laplacian.x - uvv + params.feed * (1.0 - c.x));
+    let v = c.y + params.dt * (params.diffusion_v * laplacian.y + uvv - (params.feed + params.kill) * c.y);
+    return vec2<f32>(u, v);
+}
+
+// The same step on the cell's quantised concentrations alone
+fn gray_scott_step(status: u32, laplacian: vec2<f32>) -> u32 {
+    if (status == WALL) { return WALL; }
+    return gray_scott_encode(gray_scott_react(gray_scott_decode(status), laplacian));
+}
+
+// The value after a cyclic cell's (matches cyclic.rs)
+fn cyclic_successor(status: u32) -> u32 {
+    return (status + 1u) % params.cyclic_states;
+}
+
+// A cyclic cell moves on to its successor once enough neighbours hold it
+fn cyclic_step(status: u32, successors: u32) -> u32 {
+    if (status == WALL) { return WALL; }
+    return select(status, cyclic_successor(status), successors >= params.cyclic_threshold);
+}
+
+// The rock-paper-scissors species that beats `species`: 1 beats 2, 2 beats 3 and 3 beats 1 (matches rps.rs)
+fn rps_predator(species: u32) -> u32 {
+    return (species + 1u) % 3u + 1u;
+}
+
+// A species cell falls to its predator with enough of it around; empty ground goes to a clear majority with enough.
+// `counts` holds how many neighbours are species 1, 2 and 3
+fn rps_step(status: u32, counts: vec3<u32>) -> u32 {
+    if (status == 0u) {
+        let most = max(counts.x, max(counts.y, counts.z));
+        let leaders = vec3<u32>(counts == vec3<u32>(most));
+        if (most < params.rps_threshold || leaders.x + leaders.y + leaders.z > 1u) { return 0u; }
+        return select(select(3u, 2u, leaders.y == 1u), 1u, leaders.x == 1u);
+    }
+    if (status > 3u) { return status; }
+    let predator = rps_predator(status);
+    return select(status, predator, counts[predator - 1u] >= params.rps_threshold);
+}
+
+// Lenia cells hold the bits of an f32 from 0 to 1, so 0 is empty; walls hold nothing (matches lenia.rs)
+fn lenia_decode(cell: u32) -> f32 {
+    if (cell == WALL) { return 0.0; }
+    return bitcast<f32>(cell);
+}
+
+// The ring's weight at `distance` cells: a bump peaking halfway out, 0 at the centre and from the radius on
+fn lenia_kernel(distance: f32) -> f32 {
+    let r = distance / f32(params.lenia_radius);
+    if (r <= 0.0 || r >= 1.0) { return 0.0; }
+    return exp(4.0 - 1.0 / (r * (1.0 - r)));
+}
+
+// One Lenia time step of a cell given the ring-weighted average around it: growth is +1 at mu, -1 far off
+fn lenia_step(status: u32, potential: f32) -> u32 {
+    if (status == WALL) { return WALL; }
+    let d = potential - params.lenia_mu;
+    let growth = 2.0 * exp(-d * d / (2.0 * params.lenia_sigma * params.lenia_sigma)) - 1.0;
+    return bitcast<u32>(clamp(lenia_decode(status) + params.lenia_dt * growth, 0.0, 1.0));
+}
+
+// PCG hash (Jarzynski & Olano), the same as in soup.wgsl and soup.rs
+fn pcg(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// Cell `index`'s 24 random bits for `generation` (matches `roll` in forest_fire.rs)
+fn forest_roll(index: u32, generation: u32) -> u32 {
+    return pcg(index ^ pcg(generation ^ params.forest_key)) >> 8u;
+}
+
+// Forest fire: burning turns empty, a tree catches from a burning neighbour or by lightning, empty grows a tree
+fn forest_fire_step(status: u32, fire_nearby: bool, roll: u32) -> u32 {
+    switch status {
+        case 0u: { return select(0u, 1u, roll < params.forest_growth); }
+        case 1u: { return select(1u, 2u, fire_nearby || roll < params.forest_lightning); }
+        case 2u: { return 0u; }
+        default: { return status; }
+    }
+}
+
+// Half the side of the square neighbourhood: 1 is the usual 3x3 Moore neighbourhood
+fn neighbourhood_radius() -> i32 {
+    if (params.automaton == AUTOMATON_LTL) { return i32(params.radius); }
+    return 1;
+}
+
+// Neighbours of a hex cell (matches topology.rs): six for even rows, then six for odd ones, which are
+// drawn half a cell to the right
+var<private> HEX_OFFSETS: array<vec2<i32>, 12> = array<vec2<i32>, 12>(
+    vec2<i32>(-1, -1), vec2<i32>(0, -1), vec2<i32>(-1, 0), vec2<i32>(1, 0), vec2<i32>(-1, 1), vec2<i32>(0, 1),
+    vec2<i32>(0, -1), vec2<i32>(1, -1), vec2<i32>(-1, 0), vec2<i32>(1, 0), vec2<i32>(0, 1), vec2<i32>(1, 1),
+);
+
+// Whether the six hex neighbours replace the square neighbourhood; Larger than Life keeps its squares
+fn hex_neighbourhood() -> bool {
+    return params.topology == TOPOLOGY_HEX && params.automaton != AUTOMATON_LTL;
+}
+
+// Offset of the `n`th of the six neighbours of a cell in row `y`
+fn hex_offset(y: u32, n: u32) -> vec2<i32> {
+    return HEX_OFFSETS[(y % 2u) * 6u + n];
+}
+
+// Whether a neighbour in this state adds to the neighbour count
+fn counts(state: u32) -> u32 {
+    if (state == WALL) { return 0u; }
+    switch params.automaton {
+        case AUTOMATON_BRIANS_BRAIN, AUTOMATON_GENERATIONS: { return select(0u, 1u, state == 1u); } // Firing / alive only
+        case AUTOMATON_WIREWORLD: { return select(0u, 1u, state == 2u); } // Electron heads only
+        case AUTOMATON_GRAY_SCOTT: { return select(0u, 1u, gray_scott_decode(state).y > 0.1); } // Enough v built up
+        case AUTOMATON_LENIA: { return select(0u, 1u, lenia_decode(state) > 0.1); } // Bright enough
+        case AUTOMATON_LIFE, default: { return min(state, 1u); }
+    }
+}
+
+// What a neighbour in this state adds to the count passed to next_state
+fn neighbor_weight(state: u32) -> u32 {
+    if (params.automaton == AUTOMATON_IMMIGRATION && state == 2u) { return BLUE_NEIGHBOUR; }
+    return counts(state);
+}
+
+// Whether two values of a cell are the same state; Life cells getting older doesn't count as a change
+fn same_state(a: u32, b: u32) -> bool {
+    switch params.automaton {
+        case AUTOMATON_BRIANS_BRAIN, AUTOMATON_WIREWORLD, AUTOMATON_IMMIGRATION, AUTOMATON_GRAY_SCOTT, AUTOMATON_GENERATIONS, AUTOMATON_LANGTONS_ANT, AUTOMATON_CYCLIC, AUTOMATON_LENIA, AUTOMATON_FOREST_FIRE, AUTOMATON_ROCK_PAPER_SCISSORS: { return a == b; }
+        case AUTOMATON_LIFE, default: { return (a > 0u) == (b > 0u); }
+    }
+}
+
+// Life-like Rules (B3/S23 for Conway) with the given masks, which life.wgsl also takes from its rule map
+fn life_state(status: u32, neighbors: u32, birth: u32, survive: u32) -> u32 {
+    var mask = birth;
+    if (status > 0u) {
+        mask = survive;
+    }
+    if (((mask >> neighbors) & 1u) == 1u) {
+        return min(status + 1u, MAX_AGE); // Born at age 1 / Survive and age
+    }
+    return 0u; // Die
+}
+
+fn next_state(status: u32, neighbors: u32) -> u32 {
+    if (status == WALL) { return WALL; }
+    switch params.automaton {
+        // Ants walk on the CPU (see ant.rs), their cells stay as they are here
+        case AUTOMATON_LANGTONS_ANT: { return status; }
+        case AUTOMATON_BRIANS_BRAIN: {
+            // Ready fires on exactly two firing neighbours, firing turns refractory, refractory turns ready
+            if (status == 0u) { return select(0u, 1u, neighbors == 2u); }
+            return (status + 1u) % 3u;
+        }
+        case AUTOMATON_GENERATIONS: {
+            // Dead cells are born by the birth mask, live ones that don't survive start fading, fading ones
+            // fade on until they're dead and can't be born meanwhile
+            if (status == 0u) { return (params.birth >> neighbors) & 1u; }
+            if (status == 1u && ((params.survive >> neighbors) & 1u) == 1u) { return 1u; }
+            return (status + 1u) % params.states;
+        }
+        case AUTOMATON_WIREWORLD: {
+            // Head turns tail, tail turns conductor, conductor turns head next to one or two heads
+            switch status {
+                case 0u: { return 0u; }
+                case 1u: { return select(1u, 2u, neighbors == 1u || neighbors == 2u); }
+                case 2u: { return 3u; }
+                default: { return 1u; }
+            }
+        }
+        case AUTOMATON_IMMIGRATION: {
+            // B3/S23 for both colours; newborns take the majority colour of their three parents
+            let blue = neighbors / BLUE_NEIGHBOUR;
+            let live = neighbors % BLUE_NEIGHBOUR + blue;
+            if (status == 0u) {
+                if (live != 3u) { return 0u; }
+                return select(1u, 2u, blue >= 2u);
+            }
+            return select(0u, status, live == 2u || live == 3u);
+        }
+        case AUTOMATON_LTL: {
+            var low = params.birth_min;
+            var high = params.birth_max;
+            if (status > 0u) {
+                low = params.survive_min;
+                high = params.survive_max;
+            }
+            if (neighbors >= low && neighbors <= high) {
+                return min(status + 1u, MAX_AGE);
+            }
+            return 0u;
+        }
+        case AUTOMATON_LIFE, default: {
+            return life_state(status, neighbors, params.birth, params.survive);
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use std::thread;
+use image::{Rgb, RgbImage};
+
+// Matches the fragment shader: live cells white, dead cells the void blue
+const LIVE: Rgb<u8> = Rgb([255, 255, 255]);
+const DEAD: Rgb<u8> = Rgb([0, 0, 26]);
+
+// Encodes a grid-resolution PNG on a background thread so the event loop keeps running
+pub fn save_png(cells: Vec<u32>, width: u32, height: u32, path: String) {
+    thread::spawn(move || {
+        let image = RgbImage::from_fn(width, height, |x, y| {
+            if cells[(y * width + x) as usize] != 0 { LIVE } else { DEAD }
+        });
+        match image.save(&path) {
+            Ok(()) => println!("Saved screenshot {}", path),
+            Err(e) => eprintln!("Failed to save screenshot {}: {}", path, e),
+        }
+    });
+}
@@ -0,0 +1,174 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use rust_gpu_life::changes::Settled;
+use web_time::Instant;
+
+const CSV_HEADER: &str = "seed,generations,outcome,population,seconds";
+// Soups searched before "unusually populous" means anything
+const MIN_SOUPS_FOR_AVERAGE: usize = 10;
+
+// One soup as it ended: settled into something, or still evolving at the generation limit
+pub struct Outcome {
+    pub seed: u64,
+    pub generations: usize,
+    pub settled: Option<Settled>,
+    pub population: u32,
+}
+
+// soup-search: runs soup after soup for a fixed number of generations each, appending how each one
+// ended to a CSV file
+pub struct SoupSearch {
+    pub path: String,
+    writer: BufWriter<File>,
+    pub generations: usize,
+    // Only every this many frames are drawn, the rest just step
+    draw_every: u32,
+    frame: u32,
+    // Save soups that are still evolving at the limit or end up twice as populous as the average
+    save: bool,
+    pub seed: u64,
+    pub soups: usize,
+    population_total: f64,
+    started: Instant,
+}
+
+impl SoupSearch {
+    // Results are appended, so a search can be resumed into the same file
+    pub fn open(path: String, generations: usize, draw_every: u32, save: bool, seed: u64) -> Result<SoupSearch, String> {
+        let file = OpenOptions::new().create(true).append(true).open(&path).map_err(|e| format!("could not open search results '{}': {}", path, e))?;
+        let empty = file.metadata().map(|metadata| metadata.len() == 0).unwrap_or(true);
+        let mut writer = BufWriter::new(file);
+        if empty {
+            writeln!(writer, "{}", CSV_HEADER).map_err(|e| format!("could not write search results '{}': {}", path, e))?;
+        }
+        Ok(SoupSearch { path, writer, generations, draw_every, frame: 0, save, seed, soups: 0, population_total: 0.0, started: Instant::now() })
+    }
+
+    // Generations the current soup may still step, at most `wanted`, when it's at `generation`
+    pub fn steps_left(&self, generation: usize, wanted: u32) -> u32 {
+        wanted.min(self.generations.saturating_sub(generation).min(u32::MAX as usize) as u32)
+    }
+
+    // Whether the current soup has run its course without settling
+    pub fn at_limit(&self, generation: usize) -> bool {
+        generation >= self.generations
+    }
+
+    // Whether this frame should be drawn
+    pub fn draw_frame(&mut self) -> bool {
+        self.frame = (self.frame + 1) % self.draw_every;
+        self.frame == 0
+    }
+
+    // Logs the soup that just ended and returns whether it's worth saving
+    pub fn finish(&mut self, outcome: &Outcome) -> bool {
+        let seconds = self.started.elapsed().as_secs_f64();
+        let result = match outcome.settled {
+            Some(Settled::Extinct) => "extinct",
+            Some(Settled::Stable) => "stable",
+            Some(Settled::Oscillating) => "period-2",
+            None => "running",
+        };
+        let written = writeln!(self.writer, "{},{},{},{},{:.3}", outcome.seed, outcome.generations, result, outcome.population, seconds)
+            .and_then(|()| self.writer.flush());
+        if let Err(e) = written {
+            eprintln!("Failed to write search results to {}: {}", self.path, e);
+        }
+        println!("Soup {} (seed {}): {} after {} generations, population {}", self.soups + 1, outcome.seed, result, outcome.generations, outcome.population);
+
+        let populous = self.soups >= MIN_SOUPS_FOR_AVERAGE && outcome.population as f64 >= 2.0 * self.population_total / self.soups as f64;
+        self.soups += 1;
+        self.population_total += outcome.population as f64;
+        self.save && (outcome.settled.is_none() || populous)
+    }
+
+    // The next soup starts from `seed`
+    pub fn restart(&mut self, seed: u64) {
+        self.seed = seed;
+        self.started = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A results file of its own in the temp directory, removed first so it starts empty
+    fn results(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("rust_gpu_life_search_{}_{}.csv", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    fn outcome(seed: u64, settled: Option<Settled>, population: u32) -> Outcome {
+        Outcome { seed, generations: 100, settled, population }
+    }
+
+    #[test]
+    fn soups_stop_at_the_generation_limit() {
+        let path = results("limit");
+        let search = SoupSearch::open(path.clone(), 100, 1, false, 1).unwrap();
+        assert_eq!(search.steps_left(0, 16), 16);
+        assert_eq!(search.steps_left(90, 16), 10);
+        assert_eq!(search.steps_left(100, 16), 0);
+        // Stepped past it, as a reset can leave the count, still stops
+        assert_eq!(search.steps_left(150, 16), 0);
+        assert!(!search.at_limit(99));
+        assert!(search.at_limit(100));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn only_every_nth_frame_is_drawn() {
+        let path = results("draw");
+        let mut search = SoupSearch::open(path.clone(), 100, 3, false, 1).unwrap();
+        let drawn: Vec<bool> = (0..6).map(|_| search.draw_frame()).collect();
+        assert_eq!(drawn, [false, false, true, false, false, true]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn running_and_populous_soups_are_saved() {
+        let path = results("save");
+        let mut search = SoupSearch::open(path.clone(), 100, 1, true, 1).unwrap();
+        // Still evolving at the limit is always worth a look
+        assert!(search.finish(&outcome(1, None, 10)));
+        // Nothing counts as populous until there's an average to compare with
+        assert!(!search.finish(&outcome(2, Some(Settled::Stable), 1000)));
+        for seed in 3..=MIN_SOUPS_FOR_AVERAGE as u64 {
+            assert!(!search.finish(&outcome(seed, Some(Settled::Extinct), 0)));
+        }
+        // The average is now 101 over 10 soups
+        assert!(!search.finish(&outcome(11, Some(Settled::Oscillating), 201)));
+        // and 110 over 11
+        assert!(search.finish(&outcome(12, Some(Settled::Stable), 221)));
+        std::fs::remove_file(path).unwrap();
+
+        let path = results("no_save");
+        let mut search = SoupSearch::open(path.clone(), 100, 1, false, 1).unwrap();
+        assert!(!search.finish(&outcome(1, None, 10)));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn results_append_under_one_header() {
+        let path = results("append");
+        let mut search = SoupSearch::open(path.clone(), 100, 1, false, 7).unwrap();
+        search.finish(&outcome(7, Some(Settled::Extinct), 0));
+        drop(search);
+        // Resumed into the same file
+        let mut search = SoupSearch::open(path.clone(), 100, 1, false, 8).unwrap();
+        search.finish(&outcome(8, Some(Settled::Oscillating), 42));
+        search.finish(&outcome(9, None, 17));
+        drop(search);
+        let text = std::fs::read_to_string(&path).unwrap();
+        let rows: Vec<Vec<&str>> = text.lines().map(|line| line.split(',').take(4).collect()).collect();
+        assert_eq!(rows, [
+            vec!["seed", "generations", "outcome", "population"],
+            vec!["7", "100", "extinct", "0"],
+            vec!["8", "100", "period-2", "42"],
+            vec!["9", "100", "running", "17"],
+        ]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,42 @@
+// Uniform shared with the fragment shader (see `Selection` in render.wgsl)
+#[repr(C)]
+#[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SelectionRect {
+    pub origin: [u32; 2],
+    pub extent: [u32; 2], // Zero when nothing is selected
+}
+
+// A shift-drag between two unwrapped cell positions, so a selection can cross the edge of the torus
+#[derive(Clone, Copy)]
+pub struct Selection {
+    anchor: (f64, f64),
+    corner: (f64, f64),
+}
+
+impl Selection {
+    pub fn new(cell: (f64, f64)) -> Selection {
+        Selection { anchor: cell, corner: cell }
+    }
+
+    pub fn drag_to(&mut self, cell: (f64, f64)) {
+        self.corner = cell;
+    }
+
+    // Top-left cell wrapped onto the grid, plus width and height (both inclusive of the end cells)
+    pub fn rect(&self, grid: (u32, u32)) -> (usize, usize, usize, usize) {
+        let span = |a: f64, b: f64, size: u32| {
+            let size = size as i64;
+            let (low, high) = (a.min(b).floor() as i64, a.max(b).floor() as i64);
+            (low.rem_euclid(size) as usize, (high - low + 1).min(size) as usize)
+        };
+        let ((x, width), (y, height)) = (span(self.anchor.0, self.corner.0, grid.0), span(self.anchor.1, self.corner.1, grid.1));
+        (x, y, width, height)
+    }
+}
+
+// What Ctrl+C / Ctrl+X took, cell states included
+pub struct Clip {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<u32>,
+}
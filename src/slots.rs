@@ -0,0 +1,65 @@
+use crate::automaton::Automaton;
+use crate::history::{pack, unpack};
+use crate::rule::Rule;
+use crate::WALL;
+
+/// How many grids can be kept at once.
+pub const SLOTS: usize = 5;
+
+/// A grid kept in memory to come back to, packed like the rewind history: a Life grid of 4096x4096
+/// takes 2 MB. Walls are left out (see `Walls`), and Life ages come back as 1.
+pub struct Slot {
+    pub generation: usize,
+    pub rule: Rule,
+    pub automaton: Automaton,
+    // 0 when the automaton's cells don't pack, and `words` are the cells themselves
+    bits: u32,
+    words: Vec<u32>,
+    len: usize,
+}
+
+impl Slot {
+    pub fn new(generation: usize, rule: Rule, automaton: Automaton, cells: &[u32]) -> Slot {
+        let bits = automaton.state_bits(rule);
+        let words = match bits {
+            0 => cells.iter().map(|&cell| if cell == WALL { 0 } else { cell }).collect(),
+            _ => pack(cells, bits),
+        };
+        Slot { generation, rule, automaton, bits, words, len: cells.len() }
+    }
+
+    pub fn cells(&self) -> Vec<u32> {
+        match self.bits {
+            0 => self.words.clone(),
+            bits => unpack(&self.words, bits, self.len),
+        }
+    }
+
+    /// Memory the grid takes.
+    pub fn bytes(&self) -> usize {
+        self.words.len() * 4
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn life_packs_to_a_bit_per_cell() {
+        let cells: Vec<u32> = (0..4096).map(|i| if i % 3 == 0 { i % 7 } else if i == 5 { WALL } else { 0 }).collect();
+        let slot = Slot::new(12, Rule::CONWAY, Automaton::Life, &cells);
+        assert_eq!(slot.bytes(), 4096 / 8);
+        let expected: Vec<u32> = cells.iter().map(|&cell| (cell > 0 && cell != WALL) as u32).collect();
+        assert_eq!(slot.cells(), expected);
+    }
+
+    #[test]
+    fn unpackable_cells_are_kept_whole() {
+        let cells = vec![0, 70_000, WALL, 3];
+        let slot = Slot::new(0, Rule::CONWAY, Automaton::GrayScott, &cells);
+        assert_eq!(slot.cells(), vec![0, 70_000, 0, 3]);
+        let wires = vec![0, 1, 2, 3];
+        assert_eq!(Slot::new(0, Rule::CONWAY, Automaton::Wireworld, &wires).cells(), wires);
+    }
+}
@@ -0,0 +1,317 @@
+use std::path::Path;
+use crate::ant::AntRule;
+use crate::automaton::Automaton;
+use crate::boundary::Boundary;
+use crate::cyclic::CyclicRule;
+use crate::forest_fire::ForestFire;
+use crate::gray_scott::GrayScott;
+use crate::lenia::Lenia;
+use crate::ltl::LtlRule;
+use crate::regions::RuleMap;
+use crate::replay;
+use crate::rule::{Rule, MAX_STATES};
+use crate::topology::Topology;
+
+const MAGIC: &[u8; 4] = b"LIFE";
+const VERSION: u32 = 5;
+const HEADER_LEN: usize = 4 + 4 + 4 + 4 + 8 + 4 + 4 + 4 + 4 + 4;
+// Version 4 had no settings
+const V4_HEADER_LEN: usize = HEADER_LEN - 4;
+// Version 3 had no rule map
+const V3_HEADER_LEN: usize = V4_HEADER_LEN - 4;
+// Version 2 rules had no state count, they were all Life-like
+const V2_HEADER_LEN: usize = V3_HEADER_LEN - 4;
+// Version 1 grids were square too, with one size field
+const V1_HEADER_LEN: usize = V2_HEADER_LEN - 4;
+
+/// Everything needed to resume a run: the grid, how far it has got and the rule it runs under
+pub struct Snapshot {
+    pub width: u32,
+    pub height: u32,
+    pub generation: u64,
+    pub rule: Rule,
+    pub cells: Vec<u32>,
+    /// Only when some region runs under a rule of its own
+    pub regions: Option<RuleMap>,
+    /// What the rule runs in; files from before version 5, and grids saved without them, leave it to the
+    /// command line
+    pub settings: Option<Settings>,
+}
+
+/// The automaton a grid runs as, its edges and the parameters of every automaton, so a resumed grid
+/// carries on as it was rather than under whatever the command line says
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Settings {
+    pub automaton: Automaton,
+    pub boundary: Boundary,
+    pub topology: Topology,
+    pub ltl: LtlRule,
+    pub gray_scott: GrayScott,
+    pub ant_rule: AntRule,
+    pub cyclic: CyclicRule,
+    pub rps_threshold: u32,
+    pub lenia: Lenia,
+    pub forest_fire: ForestFire,
+}
+
+impl Settings {
+    /// One line per setting, written like a session log's header
+    pub fn encode(&self) -> String {
+        [
+            format!("automaton {}", self.automaton),
+            format!("boundary {}", self.boundary),
+            format!("topology {}", self.topology),
+            format!("ltl {}", replay::ltl_fields(&self.ltl)),
+            format!("gray-scott {}", replay::gray_scott_fields(&self.gray_scott)),
+            format!("ant-rule {}", self.ant_rule),
+            format!("cyclic {}", replay::cyclic_fields(&self.cyclic)),
+            format!("rps {}", self.rps_threshold),
+            format!("lenia {}", replay::lenia_fields(&self.lenia)),
+            format!("forest-fire {}", replay::forest_fire_fields(&self.forest_fire)),
+        ].join("\n")
+    }
+
+    pub fn decode(text: &str) -> Result<Settings, String> {
+        let mut lines = text.lines();
+        let mut line = |key: &str| -> Result<Vec<&str>, String> {
+            let fields: Vec<&str> = lines.next().unwrap_or_default().split_whitespace().collect();
+            match fields.split_first() {
+                Some((first, rest)) if *first == key => Ok(rest.to_vec()),
+                _ => Err(format!("expected the '{}' setting", key)),
+            }
+        };
+        Ok(Settings {
+            automaton: Automaton::parse(&line("automaton")?.join(" "))?,
+            boundary: Boundary::parse(&line("boundary")?.join(" "))?,
+            topology: Topology::parse(&line("topology")?.join(" "))?,
+            ltl: replay::parse_ltl(&line("ltl")?)?,
+            gray_scott: replay::parse_gray_scott(&line("gray-scott")?)?,
+            ant_rule: AntRule::parse(&line("ant-rule")?.join(" "))?,
+            cyclic: replay::parse_cyclic(&line("cyclic")?)?,
+            rps_threshold: replay::parse_rps(&line("rps")?)?,
+            lenia: replay::parse_lenia(&line("lenia")?)?,
+            forest_fire: replay::parse_forest_fire(&line("forest-fire")?)?,
+        })
+    }
+}
+
+impl Snapshot {
+    pub fn load(path: &Path) -> Result<Snapshot, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("could not read state '{}': {}", path.display(), e))?;
+        Snapshot::decode(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.encode()).map_err(|e| format!("could not write state '{}': {}", path.display(), e))
+    }
+
+    /// Little-endian header (magic, version, width, height, generation, rule masks and states, the rule map's and the
+    /// settings' lengths in bytes), the rule map if it has one (see `RuleMap::encode`), the settings as text if there
+    /// are any (see `Settings::encode`), then (run, value) u32 pairs. Soups are mostly
+    /// dead space, so the runs keep a 4096x4096 grid far below its 64 MB in memory.
+    pub fn encode(&self) -> Vec<u8> {
+        let regions = self.regions.as_ref().map(RuleMap::encode).unwrap_or_default();
+        let settings = self.settings.as_ref().map(Settings::encode).unwrap_or_default();
+        let mut out = Vec::with_capacity(HEADER_LEN);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&self.width.to_le_bytes());
+        out.extend_from_slice(&self.height.to_le_bytes());
+        out.extend_from_slice(&self.generation.to_le_bytes());
+        out.extend_from_slice(&self.rule.birth.to_le_bytes());
+        out.extend_from_slice(&self.rule.survive.to_le_bytes());
+        out.extend_from_slice(&self.rule.states.to_le_bytes());
+        out.extend_from_slice(&(regions.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(settings.len() as u32).to_le_bytes());
+        out.extend_from_slice(&regions);
+        out.extend_from_slice(settings.as_bytes());
+
+        let mut cells = self.cells.iter().copied().peekable();
+        while let Some(value) = cells.next() {
+            let mut run = 1u32;
+            while run < u32::MAX && cells.next_if_eq(&value).is_some() {
+                run += 1;
+            }
+            out.extend_from_slice(&run.to_le_bytes());
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Snapshot, String> {
+        if bytes.len() < V1_HEADER_LEN || &bytes[..4] != MAGIC {
+            return Err("not a saved state file".to_string());
+        }
+        let u32_at = |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        // Version 1 lacks the height field, everything after it moves up; versions before 3 lack the states,
+        // before 4 the rule map, before 5 the settings
+        let (width, height, rest, header_len) = match u32_at(4) {
+            1 => (u32_at(8), u32_at(8), 12, V1_HEADER_LEN),
+            2 if bytes.len() >= V2_HEADER_LEN => (u32_at(8), u32_at(12), 16, V2_HEADER_LEN),
+            3 if bytes.len() >= V3_HEADER_LEN => (u32_at(8), u32_at(12), 16, V3_HEADER_LEN),
+            4 if bytes.len() >= V4_HEADER_LEN => (u32_at(8), u32_at(12), 16, V4_HEADER_LEN),
+            VERSION if bytes.len() >= HEADER_LEN => (u32_at(8), u32_at(12), 16, HEADER_LEN),
+            2..=4 | VERSION => return Err("not a saved state file".to_string()),
+            version => return Err(format!("unsupported state version {} (expected {})", version, VERSION)),
+        };
+        if width == 0 || height == 0 {
+            return Err(format!("grid size is {}x{}", width, height));
+        }
+        let generation = u64::from_le_bytes(bytes[rest..rest + 8].try_into().unwrap());
+        let states = if header_len >= V3_HEADER_LEN { u32_at(rest + 16) } else { 2 };
+        if !(2..=MAX_STATES).contains(&states) {
+            return Err(format!("rule has {} states", states));
+        }
+        let rule = Rule { birth: u32_at(rest + 8), survive: u32_at(rest + 12), states };
+
+        let regions_len = if header_len >= V4_HEADER_LEN { u32_at(rest + 20) as usize } else { 0 };
+        let Some(regions) = bytes.get(header_len..header_len + regions_len) else {
+            return Err("truncated rule map".to_string());
+        };
+        let regions = match regions_len {
+            0 => None,
+            _ => Some(RuleMap::decode(regions, width, height)?),
+        };
+        let settings_start = header_len + regions_len;
+        let settings_len = if header_len == HEADER_LEN { u32_at(rest + 24) as usize } else { 0 };
+        let Some(settings) = bytes.get(settings_start..settings_start + settings_len) else {
+            return Err("truncated settings".to_string());
+        };
+        let settings = match settings_len {
+            0 => None,
+            _ => Some(Settings::decode(std::str::from_utf8(settings).map_err(|_| "settings aren't text".to_string())?)?),
+        };
+
+        let pairs = bytes[settings_start + settings_len..].chunks_exact(8);
+        if !pairs.remainder().is_empty() {
+            return Err("truncated cell data".to_string());
+        }
+        let cell_count = width as usize * height as usize;
+        let mut cells = Vec::new();
+        for pair in pairs {
+            let run = u32::from_le_bytes(pair[..4].try_into().unwrap()) as usize;
+            let value = u32::from_le_bytes(pair[4..].try_into().unwrap());
+            if cells.len() + run > cell_count {
+                return Err(format!("more cells than a {}x{} grid holds", width, height));
+            }
+            cells.resize(cells.len() + run, value);
+        }
+        if cells.len() != cell_count {
+            return Err(format!("expected {} cells but found {}", cell_count, cells.len()));
+        }
+        Ok(Snapshot { width, height, generation, rule, cells, regions, settings })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let mut cells = vec![0; 48];
+        cells[9] = 1;
+        cells[10] = 7;
+        cells[47] = 65535;
+        let rule = Rule::parse("B36/S23").unwrap();
+        let snapshot = Snapshot { width: 8, height: 6, generation: 1234, rule, cells: cells.clone(), regions: None, settings: None };
+
+        let decoded = Snapshot::decode(&snapshot.encode()).unwrap();
+        assert_eq!((decoded.width, decoded.height, decoded.generation, decoded.rule), (8, 6, 1234, rule));
+        assert_eq!(decoded.cells, cells);
+        assert!(decoded.regions.is_none());
+    }
+
+    #[test]
+    fn keeps_the_rule_map() {
+        let mut regions = RuleMap::new(40, 8);
+        regions.paint(39, 0, 0, Some(Rule::parse("B2/S").unwrap())).unwrap();
+        let snapshot = Snapshot { width: 40, height: 8, generation: 5, rule: Rule::CONWAY, cells: vec![1; 320], regions: Some(regions.clone()), settings: None };
+        let bytes = snapshot.encode();
+        let decoded = Snapshot::decode(&bytes).unwrap();
+        assert_eq!(decoded.regions, Some(regions));
+        assert_eq!(decoded.cells, vec![1; 320]);
+
+        // Version 3 files had no rule map
+        let mut old = bytes.clone();
+        old[4] = 3;
+        let map_len = u32::from_le_bytes(bytes[V3_HEADER_LEN..V4_HEADER_LEN].try_into().unwrap()) as usize;
+        old.drain(V3_HEADER_LEN..HEADER_LEN + map_len);
+        let decoded = Snapshot::decode(&old).unwrap();
+        assert!(decoded.regions.is_none());
+        assert_eq!(decoded.cells, vec![1; 320]);
+    }
+
+    #[test]
+    fn keeps_the_settings() {
+        let settings = Settings {
+            automaton: Automaton::LangtonsAnt,
+            boundary: Boundary::Dead,
+            topology: Topology::Hex,
+            ltl: LtlRule { radius: 3, birth: (4, 9), survive: (2, 11) },
+            gray_scott: GrayScott { feed: 0.03, kill: 0.061, ..GrayScott::CORAL },
+            ant_rule: AntRule::parse("RLR").unwrap(),
+            cyclic: CyclicRule { states: 9, threshold: 2 },
+            rps_threshold: 4,
+            lenia: Lenia { radius: 10, ..Lenia::ORBIUM },
+            forest_fire: ForestFire { growth: 0.02, lightning: 0.0005, seed: 77 },
+        };
+        let snapshot = Snapshot { width: 4, height: 2, generation: 9, rule: Rule::CONWAY, cells: vec![0, 1, 2, 3, 0, 0, 0, 5], regions: None, settings: Some(settings) };
+        let bytes = snapshot.encode();
+        let decoded = Snapshot::decode(&bytes).unwrap();
+        assert_eq!(decoded.settings, Some(settings));
+        assert_eq!(decoded.cells, snapshot.cells);
+
+        // Version 4 files had none
+        let mut old = bytes.clone();
+        old[4] = 4;
+        let settings_len = u32::from_le_bytes(bytes[V4_HEADER_LEN..HEADER_LEN].try_into().unwrap()) as usize;
+        old.drain(V4_HEADER_LEN..HEADER_LEN + settings_len);
+        let decoded = Snapshot::decode(&old).unwrap();
+        assert!(decoded.settings.is_none());
+        assert_eq!(decoded.cells, snapshot.cells);
+    }
+
+    #[test]
+    fn rejects_bad_files() {
+        let snapshot = Snapshot { width: 4, height: 4, generation: 0, rule: Rule::CONWAY, cells: vec![0; 16], regions: None, settings: None };
+        let bytes = snapshot.encode();
+
+        assert!(Snapshot::decode(b"not a state file at all").is_err());
+        assert!(Snapshot::decode(&bytes[..bytes.len() - 1]).is_err());
+        let mut future = bytes.clone();
+        future[4] = 6;
+        assert!(Snapshot::decode(&future).err().unwrap().contains("version"));
+    }
+
+    #[test]
+    fn reads_square_version_1_files() {
+        // Header without the height, then 16 dead cells as a single run
+        let mut bytes = MAGIC.to_vec();
+        for word in [1u32, 4] {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes.extend_from_slice(&7u64.to_le_bytes());
+        for word in [Rule::CONWAY.birth, Rule::CONWAY.survive, 16, 0] {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        let decoded = Snapshot::decode(&bytes).unwrap();
+        assert_eq!((decoded.width, decoded.height, decoded.generation), (4, 4, 7));
+        assert_eq!(decoded.cells, vec![0; 16]);
+    }
+
+    #[test]
+    fn keeps_generations_states() {
+        let rule = Rule::parse("345/2/4").unwrap();
+        let snapshot = Snapshot { width: 4, height: 1, generation: 3, rule, cells: vec![0, 1, 2, 3], regions: None, settings: None };
+        let decoded = Snapshot::decode(&snapshot.encode()).unwrap();
+        assert_eq!(decoded.rule, rule);
+        assert_eq!(decoded.cells, vec![0, 1, 2, 3]);
+
+        // Version 2 files were all Life-like
+        let mut bytes = snapshot.encode();
+        bytes[4] = 2;
+        bytes.drain(V2_HEADER_LEN..HEADER_LEN);
+        assert_eq!(Snapshot::decode(&bytes).unwrap().rule.states, 2);
+    }
+}
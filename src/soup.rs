@@ -0,0 +1,161 @@
+use rayon::prelude::*;
+
+const WORKGROUP_SIZE: u32 = 256;
+
+/// PCG hash (Jarzynski & Olano); soup.wgsl has the same function, so both sides draw the same soup.
+pub fn pcg(value: u32) -> u32 {
+    let state = value.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+/// The per-soup key the cell hashes are mixed with, from all 64 bits of `seed`.
+pub fn key(seed: u64) -> u32 {
+    pcg(seed as u32 ^ pcg((seed >> 32) as u32))
+}
+
+/// `density` as a bound on the top 24 bits of a cell's hash, so the comparison is exact on both sides.
+pub fn threshold(density: f32) -> u32 {
+    (density.clamp(0.0, 1.0) * (1 << 24) as f32).round() as u32
+}
+
+/// Whether cell `index` starts out alive in the soup for `key` and `threshold`.
+pub fn is_live(index: u32, key: u32, threshold: u32) -> bool {
+    pcg(index ^ key) >> 8 < threshold
+}
+
+/// Reproducible soup: the same seed and density always give the same grid, here and from `SoupWriter`.
+pub fn grid(cells: usize, seed: u64, density: f32) -> Vec<u32> {
+    let (key, threshold) = (key(seed), threshold(density));
+    (0..cells as u32).into_par_iter().map(|index| is_live(index, key, threshold) as u32).collect()
+}
+
+/// Rows of a disk of soup, `radius` cells around (`x`, `y`) on a `grid_width` x `grid_height` torus, as
+/// (x, y, cells) with live cells set to `value`. Rows may run off the right edge and wrap, like
+/// `Simulation::set_rect`; the cells are the ones a whole-grid soup from `seed` would have there.
+#[allow(clippy::too_many_arguments)]
+pub fn disk(grid_width: usize, grid_height: usize, x: usize, y: usize, radius: usize, seed: u64, density: f32, value: u32) -> Vec<(usize, usize, Vec<u32>)> {
+    let (key, threshold) = (key(seed), threshold(density));
+    let radius = radius.min((grid_width.min(grid_height).max(1) - 1) / 2) as isize;
+    (-radius..=radius).map(|dy| {
+        let half = ((radius * radius - dy * dy) as f64).sqrt().floor() as isize;
+        let row = (y as isize + dy).rem_euclid(grid_height as isize) as usize;
+        let left = (x as isize - half).rem_euclid(grid_width as isize) as usize;
+        let cells = (0..2 * half as usize + 1).map(|dx| {
+            let index = row * grid_width + (left + dx) % grid_width;
+            if is_live(index as u32, key, threshold) { value } else { 0 }
+        }).collect();
+        (left, row, cells)
+    }).collect()
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SoupParams {
+    key: u32,
+    threshold: u32,
+    _pad: [u32; 2],
+}
+
+/// `grid` written straight into a cell buffer by a compute shader, so a reset doesn't upload the whole grid.
+/// Walls already in the buffer stay.
+pub struct SoupWriter {
+    pipeline: wgpu::ComputePipeline,
+    bind_groups: [wgpu::BindGroup; 2],
+    params: wgpu::Buffer,
+}
+
+impl SoupWriter {
+    pub fn new(device: &wgpu::Device, buffer_a: &wgpu::Buffer, buffer_b: &wgpu::Buffer) -> SoupWriter {
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Soup Params"),
+            size: std::mem::size_of::<SoupParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry { binding: 0, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only: false }, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 1, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            ],
+            label: None,
+        });
+        let bind_group = |cells: &wgpu::Buffer| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: cells.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: params.as_entire_binding() },
+            ],
+            label: None,
+        });
+        let bind_groups = [bind_group(buffer_a), bind_group(buffer_b)];
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("soup.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&layout], ..Default::default() });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Soup"), layout: Some(&pipeline_layout), module: &shader, entry_point: "main", compilation_options: Default::default(), cache: None,
+        });
+
+        SoupWriter { pipeline, bind_groups, params }
+    }
+
+    /// Fills the buffer for `parity` (0 = buffer A), `cell_count` cells long, with the soup for `seed` and `density`
+    pub fn encode(&self, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, parity: usize, cell_count: usize, seed: u64, density: f32) {
+        queue.write_buffer(&self.params, 0, bytemuck::bytes_of(&SoupParams { key: key(seed), threshold: threshold(density), _pad: [0; 2] }));
+        let groups = (cell_count as u32).div_ceil(WORKGROUP_SIZE);
+        let groups_x = groups.min(65535);
+        let mut cpass = encoder.begin_compute_pass(&Default::default());
+        cpass.set_pipeline(&self.pipeline);
+        cpass.set_bind_group(0, &self.bind_groups[parity], &[]);
+        cpass.dispatch_workgroups(groups_x, groups.div_ceil(groups_x), 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn density_is_respected() {
+        for density in [0.1, 0.35, 0.8] {
+            let live: u32 = grid(256 * 256, 12345, density).iter().sum();
+            assert!((live as f32 / 65536.0 - density).abs() < 0.01, "density {}: {} live", density, live);
+        }
+        assert!(grid(1000, 1, 0.0).iter().all(|&cell| cell == 0));
+        assert!(grid(1000, 1, 1.0).iter().all(|&cell| cell == 1));
+    }
+
+    #[test]
+    fn seeds_give_different_soups() {
+        assert_eq!(grid(4096, 7, 0.5), grid(4096, 7, 0.5));
+        assert_ne!(grid(4096, 7, 0.5), grid(4096, 8, 0.5));
+        // Seeds differing only in the high half too
+        assert_ne!(grid(4096, 7, 0.5), grid(4096, 7 | 1 << 40, 0.5));
+    }
+
+    #[test]
+    fn disks_are_round_and_wrap() {
+        let rows = disk(16, 16, 0, 15, 3, 5, 1.0, 1);
+        // Lattice points within distance 3
+        assert_eq!(rows.iter().map(|(_, _, cells)| cells.len()).sum::<usize>(), 29);
+        assert!(rows.iter().all(|(_, _, cells)| cells.iter().all(|&cell| cell == 1)));
+        assert_eq!(rows[0], (0, 12, vec![1]));
+        assert_eq!(rows[3].0, 13);
+        assert_eq!(rows[6].1, 2);
+        // The same cells as the whole-grid soup
+        let soup = grid(16 * 16, 9, 0.5);
+        for (x, y, cells) in disk(16, 16, 8, 8, 4, 9, 0.5, 1) {
+            for (dx, &cell) in cells.iter().enumerate() {
+                assert_eq!(cell, soup[y * 16 + (x + dx) % 16]);
+            }
+        }
+    }
+
+    #[test]
+    fn pcg_matches_the_reference() {
+        // Values from the published GLSL pcg_hash
+        assert_eq!(pcg(0), 129708002);
+        assert_eq!(pcg(1), 2831084092);
+    }
+}
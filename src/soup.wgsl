@@ -0,0 +1,29 @@
+// Random soup: a cell is alive when its PCG hash falls under the density threshold, the same test as
+// soup::is_live on the CPU, so both backends start from identical grids
+struct Soup {
+    key: u32,
+    threshold: u32,
+    _pad: vec2<u32>,
+}
+
+// Walls stay where they are (matches WALL in lib.rs)
+const WALL: u32 = 0xffffffffu;
+
+@group(0) @binding(0) var<storage, read_write> cellState: array<u32>;
+@group(0) @binding(1) var<uniform> soup: Soup;
+
+// Matches soup::pcg
+fn pcg(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// Dispatched as a 2D grid of 1D workgroups to stay under the per-dimension workgroup limit
+@compute @workgroup_size(256)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let index = global_id.y * groups.x * 256u + global_id.x;
+    if (index >= arrayLength(&cellState) || cellState[index] == WALL) { return; }
+
+    cellState[index] = select(0u, 1u, (pcg(index ^ soup.key) >> 8u) < soup.threshold);
+}
@@ -0,0 +1,65 @@
+use std::collections::VecDeque;
+
+/// Generations the population graph looks back over
+pub const WINDOW: usize = 4096;
+/// Generations between its samples, so it holds at most WINDOW / SPACING of them
+pub const SPACING: usize = 8;
+
+/// The population over the last `WINDOW` generations, one sample every `SPACING`, for the panel's graph.
+#[derive(Default)]
+pub struct PopulationGraph {
+    samples: VecDeque<(usize, u32)>,
+}
+
+impl PopulationGraph {
+    /// Takes the population counted at `generation` if it's due. Going back in time (a rewind or a fresh
+    /// soup) drops the samples from after it, so the graph follows the grid onto its new timeline.
+    pub fn record(&mut self, generation: usize, population: u32) {
+        while self.samples.back().is_some_and(|&(last, _)| last > generation) {
+            self.samples.pop_back();
+        }
+        match self.samples.back_mut() {
+            Some(last) if last.0 == generation => last.1 = population,
+            Some(&mut (last, _)) if generation < last + SPACING => {}
+            _ => self.samples.push_back((generation, population)),
+        }
+        while self.samples.front().is_some_and(|&(first, _)| first + WINDOW < generation) {
+            self.samples.pop_front();
+        }
+    }
+
+    /// (generation, population), oldest first
+    pub fn samples(&self) -> &VecDeque<(usize, u32)> {
+        &self.samples
+    }
+
+    /// Lowest and highest population sampled, which the graph's axis spans
+    pub fn range(&self) -> Option<(u32, u32)> {
+        let populations = self.samples.iter().map(|&(_, population)| population);
+        Some((populations.clone().min()?, populations.max()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_are_spaced_and_follow_rewinds() {
+        let mut graph = PopulationGraph::default();
+        for generation in 0..=WINDOW + 4 * SPACING {
+            graph.record(generation, generation as u32);
+        }
+        assert_eq!(graph.samples().len(), WINDOW / SPACING + 1);
+        assert_eq!(graph.samples().front(), Some(&(4 * SPACING, 4 * SPACING as u32)));
+        assert_eq!(graph.range(), Some((4 * SPACING as u32, (WINDOW + 4 * SPACING) as u32)));
+
+        // Back to generation 100: later samples go, and the next one is due at 104
+        graph.record(100, 7);
+        graph.record(101, 9);
+        assert_eq!(graph.samples().iter().rev().take(2).collect::<Vec<_>>(), vec![&(96, 96), &(88, 88)]);
+        // A sample's own generation counted again replaces it
+        graph.record(96, 5);
+        assert_eq!(graph.samples().back(), Some(&(96, 5)));
+    }
+}
@@ -0,0 +1,221 @@
+use rayon::prelude::*;
+use crate::ant::AntRule;
+use crate::automaton::{Automaton, CellAutomaton, Stepping};
+use crate::boundary::Boundary;
+use crate::cpu::step_grid_into;
+use crate::cyclic::CyclicRule;
+use crate::forest_fire::ForestFire;
+use crate::gray_scott::GrayScott;
+use crate::lenia::Lenia;
+use crate::ltl::LtlRule;
+use crate::rule::Rule;
+use crate::topology::Topology;
+use crate::{Simulation, WALL};
+
+/// The `auto` CPU backend steps by lists while at most 1 in this many cells is occupied, and the whole
+/// grid otherwise; each listed cell costs about as much as a hundred dense ones
+pub const AUTO_SHARE: usize = 100;
+
+/// CPU backend that only steps the occupied cells (any state but dead and walls) and their neighbours,
+/// from a sorted list of them kept next to the full grid it hands out.
+///
+/// Covers the automata stepped cell by cell on a one-cell neighbourhood. Rules that bring empty space to
+/// life (B0) step the whole grid, as does a grid with more than `limit` occupied cells.
+pub struct SparseSimulation {
+    cells: Vec<u32>,
+    // Where a whole-grid step writes the next generation
+    spare: Vec<u32>,
+    // Indices of the occupied cells, ascending
+    occupied: Vec<usize>,
+    width: u32,
+    height: u32,
+    rule: Rule,
+    boundary: Boundary,
+    topology: Topology,
+    automaton: Automaton,
+    limit: Option<usize>,
+    // Whether the last step went by the list
+    listed: bool,
+    // Rows that differed before and after a step, until `take_changed_rows`
+    changed: Vec<bool>,
+}
+
+impl SparseSimulation {
+    pub fn new(width: u32, height: u32, rule: Rule, boundary: Boundary, automaton: Automaton, cells: Vec<u32>, limit: Option<usize>) -> SparseSimulation {
+        assert_eq!(cells.len(), width as usize * height as usize, "grid must be width x height cells");
+        let occupied = occupied(&cells, 0);
+        SparseSimulation { spare: vec![0; cells.len()], cells, occupied, width, height, rule, boundary, topology: Topology::Square, automaton, limit, listed: true, changed: vec![false; height as usize] }
+    }
+
+    // One generation of the whole grid, the list rebuilt after
+    fn step_dense(&mut self, automaton: CellAutomaton) {
+        let width = self.width as usize;
+        step_grid_into(&self.cells, &mut self.spare, width, self.rule, self.boundary, self.topology, automaton);
+        self.changed.par_iter_mut().zip(self.cells.par_chunks(width).zip(self.spare.par_chunks(width)))
+            .for_each(|(changed, (before, after))| *changed |= before != after);
+        std::mem::swap(&mut self.cells, &mut self.spare);
+        self.occupied = occupied(&self.cells, 0);
+    }
+}
+
+// Indices of the cells that are neither dead nor walls, counted from `offset`
+fn occupied(cells: &[u32], offset: usize) -> Vec<usize> {
+    cells.par_iter().enumerate().filter(|&(_, &cell)| cell != 0 && cell != WALL).map(|(index, _)| offset + index).collect()
+}
+
+impl Simulation for SparseSimulation {
+    fn step(&mut self) {
+        // Automata that step whole grids are never listed to begin with, see `CpuBackend::create`
+        let Stepping::Cell(automaton) = self.automaton.stepping() else { return };
+        // A dead cell with no neighbours only stays dead without B0
+        self.listed = automaton.next_state(self.rule, 0, 0) == 0 && self.limit.is_none_or(|limit| self.occupied.len() <= limit);
+        if !self.listed {
+            self.step_dense(automaton);
+            return;
+        }
+        let (width, height) = (self.width as usize, self.height as usize);
+        let (cells, rule, boundary, topology) = (&self.cells, self.rule, self.boundary, self.topology);
+        let neighbour = move |index: usize, (dx, dy): (i64, i64)| {
+            let (x, y) = ((index % width) as i64, (index / width) as i64);
+            Some(boundary.resolve(y + dy, height)? * width + boundary.resolve(x + dx, width)?)
+        };
+        // Everything else is dead with no occupied neighbours, or a wall, and stays that way
+        let mut candidates: Vec<usize> = self.occupied.par_iter().flat_map_iter(|&index| {
+            std::iter::once(index).chain(topology.offsets(index / width).iter().filter_map(move |&offset| neighbour(index, offset)))
+        }).collect();
+        candidates.par_sort_unstable();
+        candidates.dedup();
+        let next: Vec<(usize, u32)> = candidates.into_par_iter().map(|index| {
+            let neighbors = topology.offsets(index / width).iter()
+                .filter_map(|&offset| neighbour(index, offset))
+                .map(|other| automaton.neighbor_weight(cells[other]))
+                .sum();
+            (index, automaton.next_state(rule, cells[index], neighbors))
+        }).collect();
+
+        for &(index, state) in &next {
+            if self.cells[index] != state {
+                self.cells[index] = state;
+                self.changed[index / width] = true;
+            }
+        }
+        self.occupied = next.into_iter().filter(|&(_, state)| state != 0 && state != WALL).map(|(index, _)| index).collect();
+    }
+
+    fn cells(&self) -> &[u32] {
+        &self.cells
+    }
+
+    fn set_cells(&mut self, offset: usize, cells: &[u32]) {
+        self.cells[offset..offset + cells.len()].copy_from_slice(cells);
+        let start = self.occupied.partition_point(|&index| index < offset);
+        let end = self.occupied.partition_point(|&index| index < offset + cells.len());
+        self.occupied.splice(start..end, occupied(cells, offset));
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn set_rule(&mut self, rule: Rule) {
+        self.rule = rule;
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) {
+        self.boundary = boundary;
+    }
+
+    fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    fn set_automaton(&mut self, automaton: Automaton) {
+        self.automaton = automaton;
+    }
+
+    // Never runs Larger than Life, Gray-Scott, ants, cyclic rules, Lenia or forest fires, see `step`
+    fn set_ltl_rule(&mut self, _ltl: LtlRule) {}
+
+    fn set_gray_scott(&mut self, _params: GrayScott) {}
+
+    fn set_ant_rule(&mut self, _rule: AntRule) {}
+
+    fn set_cyclic_rule(&mut self, _rule: CyclicRule) {}
+
+    fn set_rps_threshold(&mut self, _threshold: u32) {}
+
+    fn set_lenia(&mut self, _params: Lenia) {}
+
+    fn set_forest_fire(&mut self, _params: ForestFire) {}
+
+    fn take_changed_rows(&mut self) -> Option<Vec<bool>> {
+        Some(std::mem::replace(&mut self.changed, vec![false; self.height as usize]))
+    }
+
+    fn population(&self) -> u32 {
+        self.occupied.len() as u32
+    }
+
+    fn stepping(&self) -> Option<&'static str> {
+        Some(if self.listed { "lists" } else { "whole grid" })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use crate::cpu::CpuSimulation;
+
+    #[test]
+    fn matches_dense_on_random_grids() {
+        let automata = [Automaton::Life, Automaton::BriansBrain, Automaton::Generations, Automaton::Wireworld, Automaton::Immigration];
+        let rules = ["B3/S23", "B36/S23", "B2/S", "B0123/S012345678", "345/2/4"].map(|rule| Rule::parse(rule).unwrap());
+        let mut rng = StdRng::seed_from_u64(11);
+        for seed in 0..40 {
+            let (width, height) = (rng.gen_range(8..70), rng.gen_range(8..70));
+            let automaton = automata[rng.gen_range(0..automata.len())];
+            let rule = if automaton == Automaton::Generations { rules[4] } else { rules[rng.gen_range(0..4)] };
+            let boundary = [Boundary::Wrap, Boundary::Dead, Boundary::Mirror][rng.gen_range(0..3)];
+            let topology = if rng.gen_bool(0.3) { Topology::Hex } else { Topology::Square };
+            let mut cells = crate::random_grid(width * height, seed, rng.gen_range(0.01..0.4));
+            if automaton == Automaton::Immigration { crate::split_species(&mut cells, seed); }
+            cells.iter_mut().step_by(rng.gen_range(5..40)).for_each(|cell| *cell = WALL);
+            // Half the grids go by lists only while almost empty
+            let limit = rng.gen_bool(0.5).then(|| width * height / 20);
+
+            let mut dense = CpuSimulation::new(width as u32, height as u32, rule, boundary, automaton, cells.clone());
+            let mut sparse = SparseSimulation::new(width as u32, height as u32, rule, boundary, automaton, cells, limit);
+            dense.set_topology(topology);
+            sparse.set_topology(topology);
+            for generation in 1..=60 {
+                dense.step();
+                sparse.step();
+                assert!(dense.cells() == sparse.cells(), "{}x{} {} {} {} grids diverge at generation {}", width, height, automaton, rule, boundary, generation);
+                assert_eq!(dense.population(), sparse.population());
+                assert_eq!(dense.take_changed_rows(), sparse.take_changed_rows());
+            }
+        }
+    }
+
+    #[test]
+    fn edits_keep_the_list_sorted() {
+        let mut sparse = SparseSimulation::new(16, 16, Rule::CONWAY, Boundary::Wrap, Automaton::Life, vec![0; 256], None);
+        // A blinker, then a block written across it that also clears one of its cells
+        sparse.set_rect(6, 7, 3, &[1, 1, 1]);
+        sparse.set_rect(8, 6, 2, &[1, 1, 0, 1, 1, 1]);
+        assert_eq!(sparse.occupied, vec![6 * 16 + 8, 6 * 16 + 9, 7 * 16 + 6, 7 * 16 + 7, 7 * 16 + 9, 8 * 16 + 8, 8 * 16 + 9]);
+        sparse.set_cells(7 * 16 + 6, &[0, 0]);
+        assert_eq!(sparse.population(), 5);
+        // The middle of the C dies and a cell is born beside it
+        sparse.step();
+        assert_eq!(sparse.stepping(), Some("lists"));
+        assert_eq!(sparse.occupied, vec![6 * 16 + 8, 6 * 16 + 9, 7 * 16 + 10, 8 * 16 + 8, 8 * 16 + 9]);
+        assert_eq!(sparse.cells()[7 * 16 + 10], 1);
+        assert_eq!(sparse.cells()[6 * 16 + 8], 2);
+    }
+}
@@ -0,0 +1,82 @@
+use web_time::Instant;
+use rust_gpu_life::{downsample::Downsampler, GpuSimulation, Simulation};
+
+// Split view: the CPU thread steps its own copy of the grid next to the compute shader, and the window
+// shows it in the left half with the GPU's in the right. Both start from the same cells and take the same
+// edits; the CPU side is asked for as many generations as the GPU steps, so a slower one falls behind.
+
+// The CPU side's cells on the GPU, drawn with the same pipelines as the GPU's own buffers
+pub struct Split {
+    // Latest generation, and the one before it for the diff view
+    buffers: [wgpu::Buffer; 2],
+    pub render_bind_group: wgpu::BindGroup,
+    pub downsampler: Downsampler,
+    pub camera_bind_group: wgpu::BindGroup,
+    // Generation the CPU side has reached, and its rate as the HUD shows it
+    pub step: usize,
+    rate_timer: Instant,
+    rate_start_step: usize,
+    pub generations_per_second: f64,
+}
+
+impl Split {
+    // `camera` binds the camera, palette and the new downsampler's coverage, like the GPU side's group 1
+    pub fn new(device: &wgpu::Device, render_layout: &wgpu::BindGroupLayout, gpu: &GpuSimulation, viewport: u32, step: usize, camera: impl FnOnce(&Downsampler) -> wgpu::BindGroup) -> Split {
+        let size = gpu.current_buffer().size();
+        let buffers = ["Split CPU Cells", "Split CPU Previous"].map(|label| device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label), size, usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST, mapped_at_creation: false,
+        }));
+        // Zeroed, so the chunk overlay never tints the CPU side: it steps every cell
+        let no_chunks = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Split No Chunks"), size: gpu.active_chunks_buffer().size(), usage: wgpu::BufferUsages::STORAGE, mapped_at_creation: false,
+        });
+        // A single level, so the diff view shows the CPU side's latest deaths without fading them
+        let no_fade = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Split No Fade"), size: 4, usage: wgpu::BufferUsages::STORAGE, mapped_at_creation: false,
+        });
+        let render_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: render_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: buffers[0].as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: gpu.params_buffer().as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: buffers[1].as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: no_chunks.as_entire_binding() },
+                // The CPU side steps under the same rule map
+                wgpu::BindGroupEntry { binding: 4, resource: gpu.rule_map_buffer().as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 5, resource: no_fade.as_entire_binding() },
+            ],
+            label: None,
+        });
+        let downsampler = Downsampler::new(device, &buffers, gpu.width(), gpu.height(), viewport);
+        let camera_bind_group = camera(&downsampler);
+        Split { buffers, render_bind_group, downsampler, camera_bind_group, step, rate_timer: Instant::now(), rate_start_step: step, generations_per_second: 0.0 }
+    }
+
+    // Writes cells of the latest generation, or of the one before it with `previous`
+    pub fn upload(&self, queue: &wgpu::Queue, offset: usize, cells: &[u32], previous: bool) {
+        queue.write_buffer(&self.buffers[previous as usize], (offset * 4) as u64, bytemuck::cast_slice(cells));
+    }
+
+    // Keeps the bind groups in step with a window whose size calls for another coverage texture
+    pub fn resize(&mut self, device: &wgpu::Device, viewport: u32, camera: impl FnOnce(&Downsampler) -> wgpu::BindGroup) {
+        if self.downsampler.resize(device, &self.buffers, viewport) {
+            self.camera_bind_group = camera(&self.downsampler);
+        }
+    }
+
+    // Both sides start over from the same grid at `step`
+    pub fn restart(&mut self, step: usize) {
+        self.step = step;
+        self.rate_start_step = step;
+        self.rate_timer = Instant::now();
+    }
+
+    // Averaged over half a second, like the GPU side's rate
+    pub fn update_rate(&mut self) {
+        let elapsed = self.rate_timer.elapsed().as_secs_f64();
+        if elapsed < 0.5 { return; }
+        self.generations_per_second = (self.step - self.rate_start_step) as f64 / elapsed;
+        self.rate_timer = Instant::now();
+        self.rate_start_step = self.step;
+    }
+}
@@ -0,0 +1,124 @@
+use rust_gpu_life::pattern::Pattern;
+#[cfg(not(target_arch = "wasm32"))]
+use rust_gpu_life::rain::{self, RainConfig};
+
+// Built-in stamps, decoded once at startup
+const LIBRARY: [(&str, &str); 6] = [
+    ("glider", "x = 3, y = 3\nbob$2bo$3o!"),
+    ("lightweight spaceship", "x = 5, y = 4\nbo2bo$o4b$o3bo$4o!"),
+    ("Gosper glider gun", "x = 36, y = 9\n24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!"),
+    ("R-pentomino", "x = 3, y = 3\nb2o$2ob$bo!"),
+    ("acorn", "x = 7, y = 3\nbo5b$3bo3b$2o2b3o!"),
+    ("diehard", "x = 8, y = 3\n6bob$2o6b$bo3b3o!"),
+];
+
+// How often rain drops stamps and which (see rain::parse_config)
+#[cfg(not(target_arch = "wasm32"))]
+pub const RAIN_FILE: &str = "rain.toml";
+
+// A missing file leaves the defaults; bad lines are reported and skipped
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_rain(stamps: &[Stamp]) -> RainConfig {
+    let text = match std::fs::read_to_string(RAIN_FILE) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return RainConfig::default(),
+        Err(e) => {
+            eprintln!("warning: could not read {}: {}", RAIN_FILE, e);
+            return RainConfig::default();
+        }
+    };
+    let names: Vec<&str> = stamps.iter().map(|stamp| stamp.name.as_str()).collect();
+    let (config, errors) = rain::parse_config(&text, &names);
+    for error in errors {
+        eprintln!("{}: {}", RAIN_FILE, error);
+    }
+    config
+}
+
+// RLE text from the clipboard, as long as it holds cells that fit the grid
+#[cfg(not(target_arch = "wasm32"))]
+pub fn clipboard_pattern(text: &str, grid_width: usize, grid_height: usize) -> Result<Pattern, String> {
+    let pattern = Pattern::parse_rle(text).map_err(|e| format!("it doesn't hold an RLE pattern: {}", e))?;
+    if pattern.cells.is_empty() {
+        return Err("the pattern on it is empty".to_string());
+    }
+    pattern.fits(grid_width, grid_height)?;
+    Ok(pattern)
+}
+
+// Uniform shared with the fragment shader (see `Stamp` in render.wgsl)
+#[repr(C)]
+#[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct StampPreview {
+    pub origin: [u32; 2],
+    pub extent: [u32; 2], // Zero when no stamp is pending
+}
+
+// A pattern footprint that can be turned before it is placed
+#[derive(Clone)]
+pub struct Stamp {
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<u32>, // Row-major states, dead cells included so stamps overwrite what is under them
+}
+
+impl Stamp {
+    pub fn library() -> Vec<Stamp> {
+        LIBRARY.iter().map(|&(name, rle)| {
+            Stamp::from_pattern(name.to_string(), &Pattern::parse_rle(rle).expect("built-in stamps are valid RLE"))
+        }).collect()
+    }
+
+    pub fn from_pattern(name: String, pattern: &Pattern) -> Stamp {
+        let mut cells = vec![0; pattern.width * pattern.height];
+        for &(x, y, state) in &pattern.cells {
+            cells[y * pattern.width + x] = state;
+        }
+        Stamp { name, width: pattern.width, height: pattern.height, cells }
+    }
+
+    // Quarter turn clockwise
+    pub fn rotate(&mut self) {
+        let (width, height) = (self.height, self.width);
+        self.cells = (0..width * height).map(|i| {
+            let (x, y) = (i % width, i / width);
+            self.cells[(self.height - 1 - x) * self.width + y]
+        }).collect();
+        (self.width, self.height) = (width, height);
+    }
+
+    // Mirror left to right
+    pub fn flip(&mut self) {
+        for row in self.cells.chunks_mut(self.width) {
+            row.reverse();
+        }
+    }
+
+    pub fn row(&self, y: usize) -> Vec<u32> {
+        self.cells[y * self.width..(y + 1) * self.width].to_vec()
+    }
+
+    // One bit per cell for the preview's storage buffer
+    pub fn mask(&self) -> Vec<u32> {
+        let mut mask = vec![0u32; self.cells.len().div_ceil(32)];
+        for (i, _) in self.cells.iter().enumerate().filter(|(_, &state)| state > 0) {
+            mask[i / 32] |= 1 << (i % 32);
+        }
+        mask
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clipboard_rejects_runs_past_the_largest_pattern() {
+        for text in ["x = 1, y = 1\n18446744073709551615$2$o!", "x = 1, y = 1\n100000000000o!"] {
+            assert!(clipboard_pattern(text, 64, 64).err().unwrap().contains("pattern too large"), "{}", text);
+        }
+        assert!(clipboard_pattern("x = 3, y = 3\nbob$2bo$3o!", 2, 2).is_err());
+        assert_eq!(clipboard_pattern("x = 3, y = 3\nbob$2bo$3o!", 64, 64).unwrap().cells.len(), 5);
+    }
+}
@@ -0,0 +1,88 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use web_time::{Duration, Instant};
+use rust_gpu_life::rule::Rule;
+
+// Rows are buffered and written out at least this often, so a crash loses little
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+const CSV_HEADER: &str = "generation,population,births,deaths,frame_ms,mode,rule";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StatsFormat {
+    Csv,
+    JsonLines,
+}
+
+impl StatsFormat {
+    pub fn parse(text: &str) -> Result<StatsFormat, String> {
+        match text {
+            "csv" => Ok(StatsFormat::Csv),
+            "jsonl" => Ok(StatsFormat::JsonLines),
+            _ => Err(format!("invalid stats format '{}' (expected csv or jsonl)", text)),
+        }
+    }
+}
+
+// One sampled generation
+pub struct StatsRow<'a> {
+    pub generation: usize,
+    pub population: u32,
+    pub births: u32,
+    pub deaths: u32,
+    pub frame_time: Duration,
+    pub mode: &'a str,
+    // The Life rule in force, which evolution mode changes as it goes
+    pub rule: Rule,
+}
+
+// Appends a row for the generations change detection samples, at most one per `every` generations
+pub struct StatsLog {
+    pub path: String,
+    writer: BufWriter<File>,
+    format: StatsFormat,
+    every: usize,
+    last_logged: Option<usize>,
+    last_flush: Instant,
+}
+
+impl StatsLog {
+    // A new or empty CSV file starts with the header; existing runs are appended to
+    pub fn open(path: String, format: StatsFormat, every: usize) -> Result<StatsLog, String> {
+        let file = OpenOptions::new().create(true).append(true).open(&path).map_err(|e| format!("could not open stats file '{}': {}", path, e))?;
+        let empty = file.metadata().map(|metadata| metadata.len() == 0).unwrap_or(true);
+        let mut writer = BufWriter::new(file);
+        if format == StatsFormat::Csv && empty {
+            writeln!(writer, "{}", CSV_HEADER).map_err(|e| format!("could not write stats file '{}': {}", path, e))?;
+        }
+        Ok(StatsLog { path, writer, format, every, last_logged: None, last_flush: Instant::now() })
+    }
+
+    pub fn log(&mut self, row: &StatsRow) {
+        // Generations run backwards after a reset or a restored state
+        if self.last_logged.is_some_and(|last| row.generation >= last && row.generation < last + self.every) { return; }
+        self.last_logged = Some(row.generation);
+
+        let frame_ms = row.frame_time.as_secs_f64() * 1000.0;
+        let written = match self.format {
+            StatsFormat::Csv => writeln!(self.writer, "{},{},{},{},{:.3},{},{}", row.generation, row.population, row.births, row.deaths, frame_ms, row.mode, row.rule),
+            StatsFormat::JsonLines => writeln!(
+                self.writer,
+                "{{\"generation\":{},\"population\":{},\"births\":{},\"deaths\":{},\"frame_ms\":{:.3},\"mode\":\"{}\",\"rule\":\"{}\"}}",
+                row.generation, row.population, row.births, row.deaths, frame_ms, row.mode, row.rule
+            ),
+        };
+        if let Err(e) = written {
+            eprintln!("Failed to write stats to {}: {}", self.path, e);
+        }
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush();
+        }
+    }
+
+    pub fn flush(&mut self) {
+        self.last_flush = Instant::now();
+        if let Err(e) = self.writer.flush() {
+            eprintln!("Failed to flush stats to {}: {}", self.path, e);
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::cell::OnceCell;
+use std::sync::Arc;
+use wgpu::util::DeviceExt;
+use crate::ant::AntRule;
+use crate::automaton::Automaton;
+use crate::boundary::Boundary;
+use crate::cyclic::CyclicRule;
+use crate::forest_fire::ForestFire;
+use crate::gray_scott::GrayScott;
+use crate::lenia::Lenia;
+use crate::gpu::Params;
+use crate::ltl::LtlRule;
+use crate::rps;
+use crate::rule::Rule;
+use crate::topology::Topology;
+use crate::workgroup::WorkgroupSize;
+use crate::Simulation;
+
+/// Compute shader backend on a pair of `R32Uint` textures instead of storage buffers.
+///
+/// Only the headless benchmark uses it so far; the window still draws `GpuSimulation`'s buffers.
+pub struct TextureSimulation {
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    pipeline: wgpu::ComputePipeline,
+    bind_groups: [wgpu::BindGroup; 2],
+    textures: [wgpu::Texture; 2],
+    params_buffer: wgpu::Buffer,
+    // The input generation's number in x, written before each step
+    clock_buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    rule: Rule,
+    boundary: Boundary,
+    topology: Topology,
+    automaton: Automaton,
+    ltl: LtlRule,
+    gray_scott: GrayScott,
+    cyclic: CyclicRule,
+    rps_threshold: u32,
+    lenia: Lenia,
+    forest_fire: ForestFire,
+    generation: u64,
+    parity: usize,
+    mirror: OnceCell<Vec<u32>>,
+}
+
+impl TextureSimulation {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(device: Arc<wgpu::Device>, queue: Arc<wgpu::Queue>, width: u32, height: u32, rule: Rule, boundary: Boundary, automaton: Automaton, cells: &[u32]) -> Result<TextureSimulation, String> {
+        let max_size = device.limits().max_texture_dimension_2d;
+        if width.max(height) > max_size {
+            return Err(format!("a {}x{} grid doesn't fit in a texture, this adapter allows at most {}x{}", width, height, max_size, max_size));
+        }
+
+        let texture = |label| device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R32Uint,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let textures = [texture("Texture A"), texture("Texture B")];
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Params"),
+            contents: bytemuck::bytes_of(&Params::new(width, height, rule, boundary, Topology::Square, automaton, LtlRule::BOSCO, GrayScott::CORAL, CyclicRule::SPIRALS, rps::THRESHOLD, Lenia::ORBIUM, ForestFire::DROSSEL_SCHWABL)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let clock_buffer = device.create_buffer(&wgpu::BufferDescriptor { label: Some("Clock"), size: 16, usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST, mapped_at_creation: false });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry { binding: 0, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Texture { sample_type: wgpu::TextureSampleType::Uint, view_dimension: wgpu::TextureViewDimension::D2, multisampled: false }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 1, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::StorageTexture { access: wgpu::StorageTextureAccess::WriteOnly, format: wgpu::TextureFormat::R32Uint, view_dimension: wgpu::TextureViewDimension::D2 }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 2, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 3, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            ],
+            label: None,
+        });
+        let views = textures.each_ref().map(|texture| texture.create_view(&Default::default()));
+        // Bind group n reads texture n and writes the other one
+        let bind_group = |src: &wgpu::TextureView, dst: &wgpu::TextureView| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(src) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(dst) },
+                wgpu::BindGroupEntry { binding: 2, resource: params_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: clock_buffer.as_entire_binding() },
+            ],
+            label: None,
+        });
+        let bind_groups = [bind_group(&views[0], &views[1]), bind_group(&views[1], &views[0])];
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("life_texture.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(WorkgroupSize::DEFAULT.shader_source(concat!(include_str!("rules.wgsl"), include_str!("life_texture.wgsl"))).into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&bind_group_layout], ..Default::default() });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None, layout: Some(&pipeline_layout), module: &shader, entry_point: "main", compilation_options: Default::default(), cache: None,
+        });
+
+        let mut simulation = TextureSimulation { device, queue, pipeline, bind_groups, textures, params_buffer, clock_buffer, width, height, rule, boundary, topology: Topology::Square, automaton, ltl: LtlRule::BOSCO, gray_scott: GrayScott::CORAL, cyclic: CyclicRule::SPIRALS, rps_threshold: rps::THRESHOLD, lenia: Lenia::ORBIUM, forest_fire: ForestFire::DROSSEL_SCHWABL, generation: 0, parity: 0, mirror: OnceCell::new() };
+        simulation.set_cells(0, cells);
+        Ok(simulation)
+    }
+
+    fn write_params(&self) {
+        let params = Params::new(self.width, self.height, self.rule, self.boundary, self.topology, self.automaton, self.ltl, self.gray_scott, self.cyclic, self.rps_threshold, self.lenia, self.forest_fire);
+        self.queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+    }
+
+    // `width` x `height` cells at (x, y) of the current texture from the start of `cells`
+    fn write_block(&self, x: u32, y: u32, width: u32, height: u32, cells: &[u32]) {
+        self.queue.write_texture(
+            wgpu::ImageCopyTexture { texture: &self.textures[self.parity], mip_level: 0, origin: wgpu::Origin3d { x, y, z: 0 }, aspect: wgpu::TextureAspect::All },
+            bytemuck::cast_slice(&cells[..(width * height) as usize]),
+            wgpu::ImageDataLayout { offset: 0, bytes_per_row: Some(width * 4), rows_per_image: None },
+            wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        );
+    }
+
+    // Blocking copy of the current texture; texture copies pad each row to 256 bytes
+    fn read_texture(&self) -> Vec<u32> {
+        let padded_row = (self.width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback"),
+            size: padded_row as u64 * self.height as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        encoder.copy_texture_to_buffer(
+            self.textures[self.parity].as_image_copy(),
+            wgpu::ImageCopyBuffer { buffer: &staging, layout: wgpu::ImageDataLayout { offset: 0, bytes_per_row: Some(padded_row), rows_per_image: None } },
+            wgpu::Extent3d { width: self.width, height: self.height, depth_or_array_layers: 1 },
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = staging.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| sender.send(result).unwrap());
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver.recv().unwrap().expect("failed to map readback buffer");
+
+        let row = (self.width * 4) as usize;
+        let data = slice.get_mapped_range().chunks(padded_row as usize).flat_map(|padded| bytemuck::cast_slice::<u8, u32>(&padded[..row]).to_vec()).collect();
+        staging.unmap();
+        data
+    }
+}
+
+impl Simulation for TextureSimulation {
+    fn step(&mut self) {
+        // One generation per submit, so the clock is written fresh each time
+        self.queue.write_buffer(&self.clock_buffer, 0, bytemuck::bytes_of(&[self.generation as u32, 0, 0, 0]));
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("Life (texture)"), timestamp_writes: None });
+            cpass.set_pipeline(&self.pipeline);
+            cpass.set_bind_group(0, &self.bind_groups[self.parity], &[]);
+            let (groups_x, groups_y) = WorkgroupSize::DEFAULT.groups(self.width, self.height);
+            cpass.dispatch_workgroups(groups_x, groups_y, 1);
+        }
+        self.queue.submit(Some(encoder.finish()));
+        self.parity ^= 1;
+        self.generation += 1;
+        self.mirror.take();
+    }
+
+    fn cells(&self) -> &[u32] {
+        self.mirror.get_or_init(|| self.read_texture())
+    }
+
+    // A run of cells may start and end mid-row, so it goes up as at most three rectangles
+    fn set_cells(&mut self, offset: usize, cells: &[u32]) {
+        let grid_width = self.width as usize;
+        let mut written = 0;
+        while written < cells.len() {
+            let (x, y) = ((offset + written) % grid_width, (offset + written) / grid_width);
+            let rest = &cells[written..];
+            let (width, height) = if x == 0 && rest.len() >= grid_width { (grid_width, rest.len() / grid_width) } else { (rest.len().min(grid_width - x), 1) };
+            self.write_block(x as u32, y as u32, width as u32, height as u32, rest);
+            written += width * height;
+        }
+        if let Some(mirror) = self.mirror.get_mut() {
+            mirror[offset..offset + cells.len()].copy_from_slice(cells);
+        }
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn set_rule(&mut self, rule: Rule) {
+        self.rule = rule;
+        self.write_params();
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) {
+        self.boundary = boundary;
+        self.write_params();
+    }
+
+    fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+        self.write_params();
+    }
+
+    fn set_automaton(&mut self, automaton: Automaton) {
+        self.automaton = automaton;
+        self.write_params();
+    }
+
+    fn set_ltl_rule(&mut self, ltl: LtlRule) {
+        self.ltl = ltl;
+        self.write_params();
+    }
+
+    fn set_gray_scott(&mut self, params: GrayScott) {
+        self.gray_scott = params;
+        self.write_params();
+    }
+
+    // Ants walk on the CPU, the step leaves their cells alone
+    fn set_ant_rule(&mut self, _rule: AntRule) {}
+
+    fn set_cyclic_rule(&mut self, rule: CyclicRule) {
+        self.cyclic = rule;
+        self.write_params();
+    }
+
+    fn set_rps_threshold(&mut self, threshold: u32) {
+        self.rps_threshold = threshold;
+        self.write_params();
+    }
+
+    fn set_lenia(&mut self, params: Lenia) {
+        self.lenia = params;
+        self.write_params();
+    }
+
+    fn set_forest_fire(&mut self, params: ForestFire) {
+        self.forest_fire = params;
+        self.write_params();
+    }
+
+    fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpu::GpuSimulation;
+
+    #[test]
+    fn textures_step_like_buffers() {
+        // Machines without any adapter, not even a software one, have nothing to compare
+        let Some(adapter) = pollster::block_on(crate::gpu::request_adapter(&wgpu::Instance::default(), None)) else { return };
+        let (device, queue) = pollster::block_on(crate::gpu::request_device(&adapter)).unwrap();
+        let (device, queue) = (Arc::new(device), Arc::new(queue));
+        let (width, height) = (48, 40);
+        let cells = crate::random_grid((width * height) as usize, 7, 0.35);
+        for boundary in [Boundary::Wrap, Boundary::Dead, Boundary::Mirror] {
+            let mut texture = TextureSimulation::new(device.clone(), queue.clone(), width, height, Rule::CONWAY, boundary, Automaton::Life, &cells).unwrap();
+            let mut buffer = GpuSimulation::new(device.clone(), queue.clone(), width, height, Rule::CONWAY, boundary, Automaton::Life, &cells, None).unwrap();
+            for _ in 0..20 {
+                texture.step();
+                buffer.step();
+            }
+            assert_eq!(texture.cells(), buffer.cells(), "{:?}", boundary);
+        }
+    }
+}
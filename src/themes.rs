@@ -0,0 +1,21 @@
+use rust_gpu_life::palette::{self, Theme};
+
+// Custom themes, after the presets: a [name] line, then "#rrggbb" colours over a `base` theme (see palette::parse_themes)
+pub const THEMES_FILE: &str = "themes.toml";
+
+// A missing file is no error; bad lines are reported and skipped
+pub fn load() -> Vec<(String, Theme)> {
+    let text = match std::fs::read_to_string(THEMES_FILE) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+        Err(e) => {
+            eprintln!("warning: could not read {}: {}", THEMES_FILE, e);
+            return Vec::new();
+        }
+    };
+    let (themes, errors) = palette::parse_themes(&text);
+    for error in errors {
+        eprintln!("{}: {}", THEMES_FILE, error);
+    }
+    themes
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+use crate::readback::StagingRing;
+
+/// Measures how long the compute passes of a frame, or its render pass, actually ran on the GPU, using
+/// timestamp queries
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    staging: StagingRing,
+    period: f64, // Nanoseconds per timestamp tick
+}
+
+impl GpuTimer {
+    /// `None` when the device was created without `Features::TIMESTAMP_QUERY`
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<GpuTimer> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor { label: Some("Pass Timestamps"), ty: wgpu::QueryType::Timestamp, count: 2 });
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Resolve"),
+            size: 16,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let staging = StagingRing::new(device, "Timestamp Staging", 16);
+        Some(GpuTimer { query_set, resolve_buffer, staging, period: queue.get_timestamp_period() as f64 })
+    }
+
+    /// Pass to the compute pass being timed
+    pub fn timestamp_writes(&self) -> wgpu::ComputePassTimestampWrites<'_> {
+        wgpu::ComputePassTimestampWrites { query_set: &self.query_set, beginning_of_pass_write_index: Some(0), end_of_pass_write_index: Some(1) }
+    }
+
+    /// Pass to the render pass being timed
+    pub fn render_timestamp_writes(&self) -> wgpu::RenderPassTimestampWrites<'_> {
+        wgpu::RenderPassTimestampWrites { query_set: &self.query_set, beginning_of_pass_write_index: Some(0), end_of_pass_write_index: Some(1) }
+    }
+
+    /// Copies the timestamps out after the timed pass if a staging slot is free, otherwise skips this frame
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let Some(staging) = self.staging.next() else { return };
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, staging, 0, 16);
+    }
+
+    /// Must be called after the encoder passed to `resolve` was submitted
+    pub fn begin_readback(&mut self) {
+        self.staging.begin_readback();
+    }
+
+    /// Newest measurement whose mapping has completed, if any; call after `device.poll`
+    pub fn try_read(&mut self) -> Option<Duration> {
+        let period = self.period;
+        self.staging.try_read(|bytes| {
+            let [start, end] = bytemuck::cast_slice::<u8, u64>(bytes) else { unreachable!() };
+            Duration::from_nanos((end.saturating_sub(*start) as f64 * period) as u64)
+        })
+    }
+}
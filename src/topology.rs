@@ -0,0 +1,70 @@
+use std::fmt;
+
+/// How cells are arranged, and so which are neighbours; the discriminants are the values rules.wgsl expects.
+/// Only the one-cell neighbourhoods follow it: Larger than Life and Gray-Scott always count squares.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum Topology {
+    /// Square cells with the 8 neighbours of the Moore neighbourhood
+    Square = 0,
+    /// Hexagonal cells with 6 neighbours, stored row by row with the odd rows shifted half a cell right
+    Hex = 1,
+}
+
+// (dx, dy) of each neighbour; hex rows take their diagonal neighbours from the columns the shift lines up
+const MOORE: [(i64, i64); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+const HEX_EVEN: [(i64, i64); 6] = [(-1, -1), (0, -1), (-1, 0), (1, 0), (-1, 1), (0, 1)];
+const HEX_ODD: [(i64, i64); 6] = [(0, -1), (1, -1), (-1, 0), (1, 0), (0, 1), (1, 1)];
+
+impl Topology {
+    pub fn parse(text: &str) -> Result<Topology, String> {
+        match text {
+            "square" => Ok(Topology::Square),
+            "hex" => Ok(Topology::Hex),
+            _ => Err(format!("invalid topology '{}' (expected square or hex)", text)),
+        }
+    }
+
+    /// Offsets of the neighbours of a cell in row `y` (matches `hex_offset` in rules.wgsl).
+    pub fn offsets(self, y: usize) -> &'static [(i64, i64)] {
+        match self {
+            Topology::Square => &MOORE,
+            Topology::Hex if y.is_multiple_of(2) => &HEX_EVEN,
+            Topology::Hex => &HEX_ODD,
+        }
+    }
+
+    /// How far right row `y` is drawn, in cells.
+    pub fn row_shift(self, y: i64) -> f64 {
+        match self {
+            Topology::Hex if y.rem_euclid(2) == 1 => 0.5,
+            _ => 0.0,
+        }
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Topology::Square => "square",
+            Topology::Hex => "hex",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_neighbours_are_mutual() {
+        // On a torus with an even height every neighbour has the cell as a neighbour in turn
+        for y in 0..4i64 {
+            for &(dx, dy) in Topology::Hex.offsets(y as usize) {
+                let back = Topology::Hex.offsets((y + dy).rem_euclid(4) as usize);
+                assert!(back.contains(&(-dx, -dy)), "row {} offset ({}, {})", y, dx, dy);
+            }
+        }
+        assert_eq!(Topology::Square.offsets(1).len(), 8);
+    }
+}
@@ -0,0 +1,176 @@
+use std::fmt;
+use rayon::prelude::*;
+
+const WORKGROUP_SIZE: u32 = 16;
+
+/// A move of the whole grid. Every cell goes somewhere and comes from somewhere, so the GPU writes it
+/// from one ping-pong buffer into the other.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Transform {
+    /// By (`dx`, `dy`) cells, wrapping around the edges
+    Shift { dx: i32, dy: i32 },
+    /// A quarter turn clockwise, square grids only
+    Rotate,
+    /// Left to right
+    MirrorX,
+    /// Top to bottom
+    MirrorY,
+}
+
+impl Transform {
+    /// From the fields `Display` writes: "shift DX DY", "rotate", "mirror-x" or "mirror-y"
+    pub fn parse(fields: &[&str]) -> Result<Transform, String> {
+        let offset = |text: &str| text.parse::<i32>().map_err(|_| format!("invalid shift '{}'", text));
+        match fields {
+            ["shift", dx, dy] => Ok(Transform::Shift { dx: offset(dx)?, dy: offset(dy)? }),
+            ["rotate"] => Ok(Transform::Rotate),
+            ["mirror-x"] => Ok(Transform::MirrorX),
+            ["mirror-y"] => Ok(Transform::MirrorY),
+            _ => Err(format!("invalid transform '{}' (expected shift DX DY, rotate, mirror-x or mirror-y)", fields.join(" "))),
+        }
+    }
+
+    /// A quarter turn would swap the sides of a grid that isn't square, which the buffers can't hold.
+    pub fn check(self, width: u32, height: u32) -> Result<(), String> {
+        if self == Transform::Rotate && width != height {
+            return Err(format!("a {}x{} grid can't be rotated, only square ones can", width, height));
+        }
+        Ok(())
+    }
+
+    /// Where the cell that ends up at (`x`, `y`) comes from; transform.wgsl does the same.
+    pub fn source(self, x: usize, y: usize, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Transform::Shift { dx, dy } => ((x as i64 - dx as i64).rem_euclid(width as i64) as usize, (y as i64 - dy as i64).rem_euclid(height as i64) as usize),
+            Transform::Rotate => (y, width - 1 - x),
+            Transform::MirrorX => (width - 1 - x, y),
+            Transform::MirrorY => (x, height - 1 - y),
+        }
+    }
+
+    /// The CPU version, for CPU mode and anything else kept per cell (walls, the --verify shadow).
+    pub fn apply<T: Copy + Send + Sync>(self, cells: &[T], width: usize, height: usize) -> Vec<T> {
+        (0..width * height).into_par_iter().map(|index| {
+            let (x, y) = self.source(index % width, index / width, width, height);
+            cells[y * width + x]
+        }).collect()
+    }
+
+    fn params(self, width: u32, height: u32) -> TransformParams {
+        let (kind, shift) = match self {
+            Transform::Shift { dx, dy } => (0, [dx.rem_euclid(width as i32) as u32, dy.rem_euclid(height as i32) as u32]),
+            Transform::Rotate => (1, [0; 2]),
+            Transform::MirrorX => (2, [0; 2]),
+            Transform::MirrorY => (3, [0; 2]),
+        };
+        TransformParams { width, height, kind, _pad: 0, shift, _pad2: [0; 2] }
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Transform::Shift { dx, dy } => write!(f, "shift {} {}", dx, dy),
+            Transform::Rotate => f.write_str("rotate"),
+            Transform::MirrorX => f.write_str("mirror-x"),
+            Transform::MirrorY => f.write_str("mirror-y"),
+        }
+    }
+}
+
+// Uniform shared with transform.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct TransformParams {
+    width: u32,
+    height: u32,
+    kind: u32,
+    _pad: u32,
+    // Shifts taken modulo the grid size, so they're never negative
+    shift: [u32; 2],
+    _pad2: [u32; 2],
+}
+
+/// Runs a `Transform` on the GPU, reading one ping-pong buffer and writing the other.
+pub struct GridTransformer {
+    pipeline: wgpu::ComputePipeline,
+    // Bind group n reads buffer n and writes the other one
+    bind_groups: [wgpu::BindGroup; 2],
+    params: wgpu::Buffer,
+}
+
+impl GridTransformer {
+    pub fn new(device: &wgpu::Device, buffer_a: &wgpu::Buffer, buffer_b: &wgpu::Buffer) -> GridTransformer {
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Transform Params"),
+            size: std::mem::size_of::<TransformParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Storage { read_only }, has_dynamic_offset: false, min_binding_size: None }, count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                storage(0, true),
+                storage(1, false),
+                wgpu::BindGroupLayoutEntry { binding: 2, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            ],
+            label: None,
+        });
+        let bind_group = |from: &wgpu::Buffer, to: &wgpu::Buffer| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: from.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: to.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: params.as_entire_binding() },
+            ],
+            label: None,
+        });
+        let bind_groups = [bind_group(buffer_a, buffer_b), bind_group(buffer_b, buffer_a)];
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("transform.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor { bind_group_layouts: &[&layout], ..Default::default() });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Transform"), layout: Some(&pipeline_layout), module: &shader, entry_point: "main", compilation_options: Default::default(), cache: None,
+        });
+
+        GridTransformer { pipeline, bind_groups, params }
+    }
+
+    /// Writes the buffer for `parity` (0 = buffer A), transformed, into the other one.
+    pub fn encode(&self, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, parity: usize, width: u32, height: u32, transform: Transform) {
+        queue.write_buffer(&self.params, 0, bytemuck::bytes_of(&transform.params(width, height)));
+        let mut cpass = encoder.begin_compute_pass(&Default::default());
+        cpass.set_pipeline(&self.pipeline);
+        cpass.set_bind_group(0, &self.bind_groups[parity], &[]);
+        cpass.dispatch_workgroups(width.div_ceil(WORKGROUP_SIZE), height.div_ceil(WORKGROUP_SIZE), 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transforms_move_cells() {
+        // 0 1 2
+        // 3 4 5
+        // 6 7 8
+        let cells: Vec<u32> = (0..9).collect();
+        assert_eq!(Transform::Shift { dx: 1, dy: -1 }.apply(&cells, 3, 3), vec![5, 3, 4, 8, 6, 7, 2, 0, 1]);
+        assert_eq!(Transform::Rotate.apply(&cells, 3, 3), vec![6, 3, 0, 7, 4, 1, 8, 5, 2]);
+        assert_eq!(Transform::MirrorX.apply(&cells, 3, 3), vec![2, 1, 0, 5, 4, 3, 8, 7, 6]);
+        assert_eq!(Transform::MirrorY.apply(&cells, 3, 3), vec![6, 7, 8, 3, 4, 5, 0, 1, 2]);
+        let turned = (0..4).fold(cells.clone(), |turned, _| Transform::Rotate.apply(&turned, 3, 3));
+        assert_eq!(turned, cells);
+    }
+
+    #[test]
+    fn only_square_grids_rotate() {
+        assert!(Transform::Rotate.check(4, 4).is_ok());
+        assert!(Transform::Rotate.check(4, 2).is_err());
+        assert!(Transform::MirrorX.check(4, 2).is_ok());
+    }
+}
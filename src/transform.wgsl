@@ -0,0 +1,43 @@
+// Whole-grid shift, quarter turn or mirror, from one ping-pong buffer into the other (see transform.rs).
+// Each cell fetches the one that lands on it, as in Transform::source
+
+// Must match `TransformParams` in transform.rs
+struct GridTransform {
+    width: u32,
+    height: u32,
+    // 0 shift, 1 rotate, 2 mirror left to right, 3 mirror top to bottom
+    kind: u32,
+    _pad: u32,
+    shift: vec2<u32>,
+    _pad2: vec2<u32>,
+};
+
+@group(0) @binding(0) var<storage, read> cells_in: array<u32>;
+@group(0) @binding(1) var<storage, read_write> cells_out: array<u32>;
+@group(0) @binding(2) var<uniform> grid_transform: GridTransform;
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let width = grid_transform.width;
+    let height = grid_transform.height;
+    let x = global_id.x;
+    let y = global_id.y;
+    if (x >= width || y >= height) { return; }
+
+    var source = vec2<u32>(x, y);
+    switch grid_transform.kind {
+        case 0u: {
+            source = vec2<u32>((x + width - grid_transform.shift.x) % width, (y + height - grid_transform.shift.y) % height);
+        }
+        case 1u: {
+            source = vec2<u32>(y, width - 1u - x);
+        }
+        case 2u: {
+            source = vec2<u32>(width - 1u - x, y);
+        }
+        default: {
+            source = vec2<u32>(x, height - 1u - y);
+        }
+    }
+    cells_out[y * width + x] = cells_in[source.y * width + source.x];
+}
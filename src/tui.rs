@@ -0,0 +1,328 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use ratatui::buffer::Buffer;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::style::{Color, Style};
+use rust_gpu_life::{automaton::Automaton, downsample::Downsampler, snapshot::Snapshot, GpuSimulation, Simulation, WALL};
+use crate::adapter;
+use crate::cli::Args;
+use crate::tune;
+
+// The terminal is redrawn at most this often, however fast the grid runs
+const FRAME: Duration = Duration::from_millis(100);
+const DEFAULT_SPEED: u32 = 60;
+const MAX_SPEED: u32 = 1 << 20;
+
+// Where the generations run: the GPU with its coverage pass for zoomed-out views, or a CPU backend when
+// there's no adapter at all (or ants to walk)
+#[allow(clippy::large_enum_variant)]
+enum Engine {
+    Gpu { gpu: GpuSimulation, downsampler: Downsampler, device: Arc<wgpu::Device>, queue: Arc<wgpu::Queue> },
+    Cpu(Box<dyn Simulation + Send>),
+}
+
+impl Engine {
+    fn steps(&mut self, generations: u32) {
+        match self {
+            Engine::Gpu { gpu, device, queue, .. } => {
+                let mut encoder = device.create_command_encoder(&Default::default());
+                gpu.encode_steps(&mut encoder, generations, None);
+                queue.submit(Some(encoder.finish()));
+                // Keeps a speed the GPU can't reach from queueing up work
+                device.poll(wgpu::Maintain::Wait);
+            }
+            Engine::Cpu(cpu) => (0..generations).for_each(|_| cpu.step()),
+        }
+    }
+
+    // (live, state 2, walls) fractions of each `factor` x `factor` block in a `width` x `height` block rectangle
+    // at block (`x`, `y`); blocks on the grid's last row and column are cut short
+    fn coverage(&mut self, factor: u32, x: u32, y: u32, width: u32, height: u32) -> Vec<[f32; 3]> {
+        match self {
+            Engine::Gpu { gpu, .. } if factor == 1 => gpu.read_rect(x as usize, y as usize, width as usize, height as usize).into_iter().map(cell_coverage).collect(),
+            Engine::Gpu { gpu, downsampler, device, queue } => {
+                downsampler.set_factor(device, gpu.buffers(), factor);
+                let mut encoder = device.create_command_encoder(&Default::default());
+                downsampler.encode(&mut encoder, gpu.parity());
+                queue.submit(Some(encoder.finish()));
+                downsampler.read(device, queue, x, y, width, height)
+            }
+            Engine::Cpu(cpu) => {
+                let (grid_width, grid_height) = (cpu.width(), cpu.height());
+                let cells = cpu.cells();
+                let mut texels = Vec::with_capacity((width * height) as usize);
+                for ty in y..y + height {
+                    for tx in x..x + width {
+                        let (x0, y0) = (tx * factor, ty * factor);
+                        let (x1, y1) = ((x0 + factor).min(grid_width), (y0 + factor).min(grid_height));
+                        let mut sum = [0.0; 3];
+                        for cy in y0..y1 {
+                            for &cell in &cells[(cy * grid_width + x0) as usize..(cy * grid_width + x1) as usize] {
+                                let [live, second, walls] = cell_coverage(cell);
+                                sum = [sum[0] + live, sum[1] + second, sum[2] + walls];
+                            }
+                        }
+                        let area = ((x1 - x0) * (y1 - y0)) as f32;
+                        texels.push(sum.map(|total| total / area));
+                    }
+                }
+                texels
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Engine::Gpu { .. } => "gpu",
+            Engine::Cpu(_) => "cpu",
+        }
+    }
+}
+
+// The part of the grid on screen: `factor` cells per block, two blocks per character cell (one above the other)
+struct View {
+    factor: u32,
+    // Top-left block
+    x: u32,
+    y: u32,
+}
+
+// Live cells in the window with no window: the grid is drawn with half-block characters, two blocks per
+// character, and refreshed at most every FRAME. The terminal is put back on exit, and on a panic by the hook
+// ratatui installs
+pub fn run(args: &Args, initial: Snapshot) -> Result<(), String> {
+    let (width, height, rule) = (initial.width, initial.height, initial.rule);
+    let gpu = if args.automaton == Automaton::LangtonsAnt { Err("Langton's ants only walk on the CPU".to_string()) } else { engine(args, &initial) };
+    let mut engine = match gpu {
+        Ok(engine) => engine,
+        Err(e) => {
+            eprintln!("Running on the CPU: {}", e);
+            let mut cpu = args.cpu_backend.create(width, height, rule, args.boundary, args.automaton, initial.cells);
+            cpu.set_topology(args.topology);
+            cpu.set_ltl_rule(args.ltl);
+            cpu.set_gray_scott(args.gray_scott);
+            cpu.set_cyclic_rule(args.cyclic);
+            cpu.set_rps_threshold(args.rps_threshold);
+            cpu.set_lenia(args.lenia);
+            cpu.set_forest_fire(args.forest_fire);
+            cpu.set_generation(initial.generation);
+            cpu.set_ant_rule(args.ant_rule);
+            cpu.set_rule_map(initial.regions.as_ref());
+            Engine::Cpu(cpu)
+        }
+    };
+
+    let mut terminal = ratatui::try_init().map_err(|e| format!("could not set up the terminal: {}", e))?;
+    let result = run_loop(&mut terminal, &mut engine, (width, height), initial.generation, args.automaton == Automaton::Immigration);
+    ratatui::restore();
+    result
+}
+
+fn engine(args: &Args, initial: &Snapshot) -> Result<Engine, String> {
+    let instance = adapter::create_instance(args.backends);
+    let adapter = pollster::block_on(adapter::select(&instance, None, args.adapter.as_ref()))?;
+    let info = adapter.get_info();
+    let (device, queue) = pollster::block_on(rust_gpu_life::gpu::request_device(&adapter))?;
+    let (device, queue) = (Arc::new(device), Arc::new(queue));
+    let mut gpu = GpuSimulation::new(device.clone(), queue.clone(), initial.width, initial.height, initial.rule, args.boundary, args.automaton, &initial.cells, None)?;
+    gpu.set_workgroup_size(tune::choose(&device, &queue, args, initial, &info.name)?)?;
+    gpu.set_topology(args.topology);
+    gpu.set_ltl_rule(args.ltl);
+    gpu.set_gray_scott(args.gray_scott);
+    gpu.set_cyclic_rule(args.cyclic);
+    gpu.set_rps_threshold(args.rps_threshold);
+    gpu.set_lenia(args.lenia);
+    gpu.set_forest_fire(args.forest_fire);
+    gpu.set_rule_map(initial.regions.as_ref());
+    gpu.set_generation(initial.generation);
+    if !args.chunk_skipping { gpu.set_chunk_skipping(false)?; }
+    let downsampler = Downsampler::new(&device, gpu.buffers(), initial.width, initial.height, initial.width);
+    Ok(Engine::Gpu { gpu, downsampler, device, queue })
+}
+
+// `species` colours state 2 apart from the other live cells, as Immigration's second species
+fn run_loop(terminal: &mut ratatui::DefaultTerminal, engine: &mut Engine, grid: (u32, u32), mut generation: u64, species: bool) -> Result<(), String> {
+    let io = |e: std::io::Error| format!("terminal error: {}", e);
+    let mut speed = DEFAULT_SPEED;
+    let mut paused = false;
+    let mut owed = 0.0;
+    let mut view = None;
+    let mut last_step = Instant::now();
+    let mut last_draw = None::<Instant>;
+    let mut rate = (generation, Instant::now(), 0.0);
+    loop {
+        // Blocks of the grid that fit, less the status line
+        let size = terminal.size().map_err(io)?;
+        let blocks = (size.width.max(1) as u32, size.height.saturating_sub(1).max(1) as u32 * 2);
+        let view = view.get_or_insert_with(|| fit(grid, blocks));
+        clamp(view, grid, blocks);
+
+        let timeout = last_draw.map_or(Duration::ZERO, |drawn| FRAME.saturating_sub(drawn.elapsed()));
+        if event::poll(if paused { timeout } else { timeout.min(Duration::from_millis(1)) }).map_err(io)? {
+            if let Event::Key(key) = event::read().map_err(io)? {
+                if key.kind != KeyEventKind::Press { continue; }
+                let pan = |visible: u32| (visible / 4).max(1);
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('p') | KeyCode::Char(' ') => paused = !paused,
+                    KeyCode::Char('n') if paused => {
+                        engine.steps(1);
+                        generation += 1;
+                    }
+                    KeyCode::Char(']') => speed = (speed * 2).min(MAX_SPEED),
+                    KeyCode::Char('[') => speed = (speed / 2).max(1),
+                    KeyCode::Left | KeyCode::Char('a') => view.x = view.x.saturating_sub(pan(blocks.0)),
+                    KeyCode::Right | KeyCode::Char('d') => view.x += pan(blocks.0),
+                    KeyCode::Up | KeyCode::Char('w') => view.y = view.y.saturating_sub(pan(blocks.1)),
+                    KeyCode::Down | KeyCode::Char('s') => view.y += pan(blocks.1),
+                    KeyCode::Char('+') | KeyCode::Char('=') => zoom(view, (view.factor / 2).max(1), blocks),
+                    KeyCode::Char('-') => zoom(view, (view.factor * 2).min(fit(grid, blocks).factor), blocks),
+                    KeyCode::Home => *view = fit(grid, blocks),
+                    _ => {}
+                }
+                clamp(view, grid, blocks);
+            }
+        }
+
+        // Generations are owed by the clock, but never more than a frame's worth at once
+        let now = Instant::now();
+        if !paused {
+            owed = (owed + now.duration_since(last_step).as_secs_f64() * speed as f64).min(speed as f64 * FRAME.as_secs_f64() + 1.0);
+            let due = owed as u32;
+            if due > 0 {
+                engine.steps(due);
+                generation += due as u64;
+                owed -= due as f64;
+            }
+        }
+        last_step = now;
+
+        if last_draw.is_some_and(|drawn| drawn.elapsed() < FRAME) { continue; }
+        last_draw = Some(Instant::now());
+        let elapsed = rate.1.elapsed().as_secs_f64();
+        if elapsed >= 1.0 {
+            rate = (generation, Instant::now(), (generation - rate.0) as f64 / elapsed);
+        }
+        let shown = (blocks.0.min(grid.0.div_ceil(view.factor) - view.x), blocks.1.min(grid.1.div_ceil(view.factor) - view.y));
+        let texels = engine.coverage(view.factor, view.x, view.y, shown.0, shown.1);
+        let status = format!(
+            " gen {} | {} gen/s{} ({:.0} actual) | 1:{} at {},{} | {} | p pause  [ ] speed  arrows pan  +/- zoom  q quit",
+            generation, speed, if paused { " paused" } else { "" }, rate.2, view.factor, view.x * view.factor, view.y * view.factor, engine.name(),
+        );
+        terminal.draw(|frame| {
+            let area = frame.area();
+            draw(frame.buffer_mut(), &texels, shown, species);
+            frame.buffer_mut().set_string(0, area.height.saturating_sub(1), status, Style::default().fg(Color::Black).bg(Color::Gray));
+        }).map_err(io)?;
+    }
+}
+
+// The whole grid on screen
+fn fit(grid: (u32, u32), blocks: (u32, u32)) -> View {
+    View { factor: grid.0.div_ceil(blocks.0).max(grid.1.div_ceil(blocks.1)).max(1), x: 0, y: 0 }
+}
+
+// Changes the factor keeping the middle of the screen where it is
+fn zoom(view: &mut View, factor: u32, blocks: (u32, u32)) {
+    let centre = ((view.x + blocks.0 / 2) * view.factor, (view.y + blocks.1 / 2) * view.factor);
+    view.factor = factor;
+    view.x = (centre.0 / factor).saturating_sub(blocks.0 / 2);
+    view.y = (centre.1 / factor).saturating_sub(blocks.1 / 2);
+}
+
+// Stops panning past the grid's right and bottom edges
+fn clamp(view: &mut View, grid: (u32, u32), blocks: (u32, u32)) {
+    view.x = view.x.min(grid.0.div_ceil(view.factor).saturating_sub(blocks.0));
+    view.y = view.y.min(grid.1.div_ceil(view.factor).saturating_sub(blocks.1));
+}
+
+fn cell_coverage(cell: u32) -> [f32; 3] {
+    match cell {
+        WALL => [0.0, 0.0, 1.0],
+        0 => [0.0, 0.0, 0.0],
+        2 => [1.0, 1.0, 0.0],
+        _ => [1.0, 0.0, 0.0],
+    }
+}
+
+// White for live cells, blue for the second species and grey for walls, as in the window
+fn colour([live, second, walls]: [f32; 3], species: bool) -> Color {
+    let second = if species { second } else { 0.0 };
+    let first = live - second;
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0) as u8;
+    Color::Rgb(channel(first + walls * 0.5), channel(first + second * 0.4 + walls * 0.5), channel(first + second + walls * 0.5))
+}
+
+// Upper half block: the foreground is the top block and the background the one below it
+fn draw(buffer: &mut Buffer, texels: &[[f32; 3]], (width, height): (u32, u32), species: bool) {
+    for row in 0..height.div_ceil(2) {
+        for x in 0..width {
+            let top = texels[(row * 2 * width + x) as usize];
+            let bottom = if row * 2 + 1 < height { texels[((row * 2 + 1) * width + x) as usize] } else { [0.0; 3] };
+            if let Some(cell) = buffer.cell_mut((x as u16, row as u16)) {
+                cell.set_char('▀').set_fg(colour(top, species)).set_bg(colour(bottom, species));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::layout::Rect;
+    use super::*;
+
+    #[test]
+    fn fit_shows_the_whole_grid() {
+        for (grid, blocks, factor) in [((1000, 600), (100, 60), 10), ((1000, 600), (100, 50), 12), ((1001, 600), (100, 60), 11), ((10, 10), (100, 60), 1)] {
+            let view = fit(grid, blocks);
+            assert_eq!((view.factor, view.x, view.y), (factor, 0, 0), "{:?} in {:?}", grid, blocks);
+            assert!(grid.0.div_ceil(view.factor) <= blocks.0 && grid.1.div_ceil(view.factor) <= blocks.1);
+        }
+    }
+
+    #[test]
+    fn zoom_keeps_the_middle_of_the_screen() {
+        let blocks = (40, 30);
+        let mut view = View { factor: 8, x: 10, y: 20 };
+        zoom(&mut view, 4, blocks);
+        assert_eq!((view.factor, view.x, view.y), (4, 40, 55));
+        zoom(&mut view, 8, blocks);
+        assert_eq!((view.factor, view.x, view.y), (8, 10, 20));
+
+        // Zooming out near the top-left corner can't go past it
+        let mut view = View { factor: 1, x: 0, y: 0 };
+        zoom(&mut view, 2, blocks);
+        assert_eq!((view.x, view.y), (0, 0));
+    }
+
+    #[test]
+    fn clamp_stops_at_the_far_edges() {
+        let (grid, blocks) = ((1000, 600), (40, 30));
+        let mut view = View { factor: 10, x: 500, y: 100 };
+        clamp(&mut view, grid, blocks);
+        assert_eq!((view.x, view.y), (60, 30));
+        let mut view = View { factor: 10, x: 12, y: 3 };
+        clamp(&mut view, grid, blocks);
+        assert_eq!((view.x, view.y), (12, 3));
+        // A grid smaller than the screen stays at the corner
+        let mut view = View { factor: 32, x: 5, y: 5 };
+        clamp(&mut view, grid, blocks);
+        assert_eq!((view.x, view.y), (0, 0));
+    }
+
+    #[test]
+    fn draws_two_blocks_per_character() {
+        // 2x3 blocks: live over dead, a wall over a second-species cell, then a lone row over nothing
+        let texels = [cell_coverage(1), cell_coverage(WALL), cell_coverage(0), cell_coverage(2), cell_coverage(1), cell_coverage(0)];
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 2, 2));
+        draw(&mut buffer, &texels, (2, 3), true);
+        let (white, black) = (Color::Rgb(255, 255, 255), Color::Rgb(0, 0, 0));
+        let cell = |x, y| buffer.cell((x, y)).unwrap();
+        assert_eq!((cell(0, 0).symbol(), cell(0, 0).fg, cell(0, 0).bg), ("▀", white, black));
+        assert_eq!((cell(1, 0).fg, cell(1, 0).bg), (Color::Rgb(127, 127, 127), Color::Rgb(0, 102, 255)));
+        assert_eq!((cell(0, 1).fg, cell(0, 1).bg), (white, black));
+        assert_eq!((cell(1, 1).fg, cell(1, 1).bg), (black, black));
+        // Without species the second one is drawn like any live cell
+        assert_eq!(colour(cell_coverage(2), false), white);
+    }
+}
@@ -0,0 +1,74 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use rust_gpu_life::{snapshot::Snapshot, workgroup::{self, WorkgroupSize}, GpuSimulation, Simulation};
+use crate::cli::Args;
+
+// Where --tune remembers the fastest size for each adapter
+const CACHE_FILE: &str = "workgroup_cache.txt";
+// Dispatches timed per candidate, after a few untimed ones so the pipeline is warm
+const TUNE_DISPATCHES: u32 = 200;
+const WARMUP_DISPATCHES: u32 = 10;
+
+// The workgroup size to step with: --workgroup, else a fresh --tune, else whatever the last tune on this
+// adapter picked, else 8x8
+pub fn choose(device: &Arc<wgpu::Device>, queue: &Arc<wgpu::Queue>, args: &Args, initial: &Snapshot, adapter: &str) -> Result<WorkgroupSize, String> {
+    let (size, source) = if let Some(size) = args.workgroup {
+        (size, "--workgroup")
+    } else if args.tune {
+        let size = tune(device, queue, args, initial)?;
+        if let Err(e) = workgroup::remember(Path::new(CACHE_FILE), adapter, size) {
+            eprintln!("warning: {}", e);
+        }
+        (size, "tuned")
+    } else if let Some(size) = workgroup::cached(Path::new(CACHE_FILE), adapter) {
+        (size, "tuned earlier, see --tune")
+    } else {
+        (WorkgroupSize::DEFAULT, "default")
+    };
+    println!("Workgroup: {} ({})", size, source);
+    Ok(size)
+}
+
+// Times every candidate on a scratch copy of the starting grid and prints a table; the fastest wins
+fn tune(device: &Arc<wgpu::Device>, queue: &Arc<wgpu::Queue>, args: &Args, initial: &Snapshot) -> Result<WorkgroupSize, String> {
+    let mut gpu = GpuSimulation::new(device.clone(), queue.clone(), initial.width, initial.height, initial.rule, args.boundary, args.automaton, &initial.cells, None)?;
+    gpu.set_topology(args.topology);
+    gpu.set_ltl_rule(args.ltl);
+    gpu.set_gray_scott(args.gray_scott);
+    gpu.set_cyclic_rule(args.cyclic);
+    gpu.set_rps_threshold(args.rps_threshold);
+    gpu.set_lenia(args.lenia);
+    gpu.set_forest_fire(args.forest_fire);
+    // Every cell, so a starting grid with little going on still tells the sizes apart
+    gpu.set_chunk_skipping(false)?;
+    println!("Tuning the workgroup size on a {}x{} grid, {} dispatches each:", initial.width, initial.height, TUNE_DISPATCHES);
+    println!("  {:<8}{:>12}{:>10}", "size", "gen/s", "ms/gen");
+
+    let mut best: Option<(WorkgroupSize, f64)> = None;
+    for size in WorkgroupSize::CANDIDATES {
+        if let Err(e) = gpu.set_workgroup_size(size) {
+            println!("  {:<8}skipped, {}", size.to_string(), e);
+            continue;
+        }
+        dispatch(device, queue, &mut gpu, WARMUP_DISPATCHES);
+        let start = Instant::now();
+        dispatch(device, queue, &mut gpu, TUNE_DISPATCHES);
+        let seconds = start.elapsed().as_secs_f64();
+        println!("  {:<8}{:>12.0}{:>10.3}", size.to_string(), TUNE_DISPATCHES as f64 / seconds, seconds * 1000.0 / TUNE_DISPATCHES as f64);
+        if !best.is_some_and(|(_, fastest)| fastest <= seconds) {
+            best = Some((size, seconds));
+        }
+    }
+    let (size, _) = best.ok_or("none of the candidate workgroup sizes fit this adapter")?;
+    println!("Fastest: {}", size);
+    Ok(size)
+}
+
+// One submit of `dispatches` generations, waited on
+fn dispatch(device: &wgpu::Device, queue: &wgpu::Queue, gpu: &mut GpuSimulation, dispatches: u32) {
+    let mut encoder = device.create_command_encoder(&Default::default());
+    gpu.encode_steps(&mut encoder, dispatches, None);
+    queue.submit(Some(encoder.finish()));
+    device.poll(wgpu::Maintain::Wait);
+}
@@ -0,0 +1,134 @@
+use std::collections::VecDeque;
+use std::fmt;
+use crate::cpu::CpuSimulation;
+use crate::readback::StagingRing;
+use crate::Simulation;
+
+// A change made to the GPU grid or its settings, replayed on the shadow at the same generation
+type Edit = Box<dyn FnOnce(&mut dyn Simulation)>;
+
+/// The first cell where the GPU and the CPU shadow disagree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    pub generation: usize,
+    pub x: usize,
+    pub y: usize,
+    pub gpu: u32,
+    pub cpu: u32,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "GPU and CPU diverge at generation {}: cell ({}, {}) is {} on the GPU but {} on the CPU", self.generation, self.x, self.y, self.gpu, self.cpu)
+    }
+}
+
+/// Checks the GPU against a CPU shadow: every `every` generations the GPU grid is copied back
+/// asynchronously, the shadow is stepped up to the same generation and the two are compared cell by cell.
+pub struct Verifier {
+    shadow: CpuSimulation,
+    staging: StagingRing,
+    every: usize,
+    // Generation the shadow holds
+    generation: usize,
+    // Generation of the last copy encoded
+    checkpoint: usize,
+    // Copies in flight as (tag, generation); results whose tag is gone predate a restart
+    pending: VecDeque<(u64, usize)>,
+    encoded: Option<u64>,
+    next_tag: u64,
+    // In generation order, applied before the shadow steps away from that generation
+    edits: VecDeque<(usize, Edit)>,
+}
+
+impl Verifier {
+    /// `shadow` holds the same cells and settings as the GPU at `generation`.
+    pub fn new(device: &wgpu::Device, every: usize, mut shadow: CpuSimulation, generation: usize) -> Verifier {
+        shadow.set_generation(generation as u64);
+        let bytes = shadow.cells().len() as u64 * 4;
+        let staging = StagingRing::new(device, "Verify Staging", bytes);
+        Verifier { shadow, staging, every, generation, checkpoint: generation, pending: VecDeque::new(), encoded: None, next_tag: 0, edits: VecDeque::new() }
+    }
+
+    /// Whether the GPU at `generation` is far enough past the last checkpoint to be copied back.
+    pub fn due(&self, generation: usize) -> bool {
+        generation >= self.checkpoint + self.every
+    }
+
+    /// Copies `buffer`, the GPU grid at `generation`, if a staging slot is free; returns whether it did.
+    pub fn encode(&mut self, encoder: &mut wgpu::CommandEncoder, buffer: &wgpu::Buffer, generation: usize) -> bool {
+        self.encode_parts(encoder, [(buffer, 0, buffer.size())], generation)
+    }
+
+    /// `encode` for a grid kept in several buffers, such as bands: `parts` are (buffer, byte offset, bytes)
+    /// in the grid's order.
+    pub fn encode_parts<'a>(&mut self, encoder: &mut wgpu::CommandEncoder, parts: impl IntoIterator<Item = (&'a wgpu::Buffer, u64, u64)>, generation: usize) -> bool {
+        let Some(staging) = self.staging.next() else { return false };
+        let mut into = 0;
+        for (buffer, offset, size) in parts {
+            encoder.copy_buffer_to_buffer(buffer, offset, staging, into, size);
+            into += size;
+        }
+        self.encoded = Some(self.next_tag);
+        self.pending.push_back((self.next_tag, generation));
+        self.next_tag += 1;
+        self.checkpoint = generation;
+        true
+    }
+
+    /// Maps the copy written by `encode`; must be called after that encoder was submitted.
+    pub fn begin_readback(&mut self) {
+        if let Some(tag) = self.encoded.take() {
+            self.staging.begin_tagged_readback(tag);
+        }
+    }
+
+    /// Queues a change the GPU received at `generation`, e.g. painted cells or a new rule.
+    pub fn edit(&mut self, generation: usize, edit: impl FnOnce(&mut dyn Simulation) + 'static) {
+        self.edits.push_back((generation, Box::new(edit)));
+    }
+
+    /// Starts over from `cells` at `generation`, when the GPU grid was replaced wholesale or stepped
+    /// elsewhere (e.g. in CPU mode). Queued settings still apply.
+    pub fn restart(&mut self, generation: usize, cells: &[u32]) {
+        for (_, edit) in self.edits.drain(..) {
+            edit(&mut self.shadow);
+        }
+        self.shadow.set_cells(0, cells);
+        self.shadow.set_generation(generation as u64);
+        self.generation = generation;
+        self.checkpoint = generation;
+        self.pending.clear();
+    }
+
+    /// Compares the newest copy that has arrived, if any, returning the generation it verified; call
+    /// after `device.poll`. After a divergence the shadow continues from the GPU's cells.
+    pub fn check(&mut self) -> Option<Result<usize, Divergence>> {
+        let (tag, cells) = self.staging.try_read_tagged(|bytes| bytemuck::cast_slice::<u8, u32>(bytes).to_vec())?;
+        // Older copies that were overtaken are skipped, the shadow steps through them
+        while self.pending.front().is_some_and(|&(pending, _)| pending < tag) {
+            self.pending.pop_front();
+        }
+        if self.pending.front().is_none_or(|&(pending, _)| pending != tag) { return None; }
+        let (_, generation) = self.pending.pop_front()?;
+
+        while self.generation < generation {
+            while self.edits.front().is_some_and(|&(at, _)| at <= self.generation) {
+                let (_, edit) = self.edits.pop_front().unwrap();
+                edit(&mut self.shadow);
+            }
+            self.shadow.step();
+            self.generation += 1;
+        }
+
+        let width = self.shadow.width() as usize;
+        match self.shadow.cells().iter().zip(&cells).position(|(cpu, gpu)| cpu != gpu) {
+            None => Some(Ok(generation)),
+            Some(index) => {
+                let divergence = Divergence { generation, x: index % width, y: index / width, gpu: cells[index], cpu: self.shadow.cells()[index] };
+                self.shadow.set_cells(0, &cells);
+                Some(Err(divergence))
+            }
+        }
+    }
+}
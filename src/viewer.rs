@@ -0,0 +1,114 @@
+use std::sync::Arc;
+use winit::{dpi::{PhysicalPosition, PhysicalSize}, window::Window};
+use rust_gpu_life::downsample::Downsampler;
+use crate::camera::{self, Camera};
+use crate::present::ResizeGesture;
+
+// Ctrl+N: another window onto the same simulation. It shares the device, the state buffers and the
+// pipelines with the main window and only draws; the main window's frames step the generations. Each
+// has its own surface and camera, and its own downsampler since the coverage follows the window size.
+
+pub struct Viewer {
+    pub window: Arc<Window>,
+    pub surface: wgpu::Surface<'static>,
+    pub config: wgpu::SurfaceConfiguration,
+    pub camera: Camera,
+    pub camera_buffer: wgpu::Buffer,
+    pub downsampler: Downsampler,
+    pub camera_bind_group: wgpu::BindGroup,
+    pub cursor: Option<PhysicalPosition<f64>>,
+    // Middle drag pans; nothing is painted from here
+    pub panning: bool,
+    pub occluded: bool,
+    pub minimized: bool,
+    // Its own drags and resizes hold the simulation like the main window's
+    pub resize_gesture: ResizeGesture,
+}
+
+impl Viewer {
+    // Presents in the main window's format, which the shared render pipelines were built for. `camera`
+    // binds the new camera buffer and downsampler with the palette, like the main window's group 1
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(instance: &wgpu::Instance, adapter: &wgpu::Adapter, device: &wgpu::Device, window: Arc<Window>, main: &wgpu::SurfaceConfiguration, buffers: &[wgpu::Buffer; 2], grid: (u32, u32), view: Camera, camera: impl FnOnce(&wgpu::Buffer, &Downsampler) -> wgpu::BindGroup) -> Result<Viewer, String> {
+        let surface = instance.create_surface(window.clone()).map_err(|e| format!("could not create a window surface: {}", e))?;
+        let caps = surface.get_capabilities(adapter);
+        if !caps.formats.contains(&main.format) {
+            return Err(format!("the new window cannot present in {:?} like the first one", main.format));
+        }
+        let size = window.inner_size();
+        let present_mode = if caps.present_modes.contains(&main.present_mode) { main.present_mode } else { wgpu::PresentMode::Fifo };
+        let config = wgpu::SurfaceConfiguration { width: size.width.max(1), height: size.height.max(1), present_mode, alpha_mode: caps.alpha_modes[0], ..main.clone() };
+        let minimized = size.width == 0 || size.height == 0;
+        if !minimized { surface.configure(device, &config); }
+
+        let camera_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Viewer Camera"),
+            size: std::mem::size_of::<Camera>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let downsampler = Downsampler::new(device, buffers, grid.0, grid.1, camera::viewport((config.width, config.height), grid).2 as u32);
+        let camera_bind_group = camera(&camera_buffer, &downsampler);
+        Ok(Viewer { window, surface, config, camera: view, camera_buffer, downsampler, camera_bind_group, cursor: None, panning: false, occluded: false, minimized, resize_gesture: ResizeGesture::new() })
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.config.width, self.config.height)
+    }
+
+    pub fn visible(&self) -> bool {
+        !self.occluded && !self.minimized
+    }
+
+    // Notes the size for the next frame to configure the surface with
+    pub fn resized(&mut self, new_size: PhysicalSize<u32>) {
+        self.minimized = new_size.width == 0 || new_size.height == 0;
+        if !self.minimized { self.resize_gesture.resized(new_size); }
+    }
+
+    // Reconfigures the surface with the latest size noted, if any, and refits the coverage; `camera`
+    // rebinds a replaced coverage texture
+    pub fn resize(&mut self, device: &wgpu::Device, buffers: &[wgpu::Buffer; 2], grid: (u32, u32), camera: impl FnOnce(&wgpu::Buffer, &Downsampler) -> wgpu::BindGroup) {
+        let Some(new_size) = self.resize_gesture.take_size() else { return };
+        if self.minimized { return; }
+        self.config.width = new_size.width;
+        self.config.height = new_size.height;
+        self.surface.configure(device, &self.config);
+        if self.downsampler.resize(device, buffers, camera::viewport(self.size(), grid).2 as u32) {
+            self.camera_bind_group = camera(&self.camera_buffer, &self.downsampler);
+        }
+    }
+
+    // The next frame, or None to skip this one; lost surfaces are reconfigured like the main window's
+    pub fn acquire(&self, device: &wgpu::Device) -> Result<Option<wgpu::SurfaceTexture>, wgpu::SurfaceError> {
+        match self.surface.get_current_texture() {
+            Ok(frame) => Ok(Some(frame)),
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(device, &self.config);
+                self.window.request_redraw();
+                Ok(None)
+            }
+            Err(wgpu::SurfaceError::Timeout) => {
+                self.window.request_redraw();
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Middle drag moves the grid along with the cursor, as in the main window
+    pub fn cursor_moved(&mut self, position: PhysicalPosition<f64>, grid: (u32, u32)) -> bool {
+        let last = self.cursor.replace(position);
+        let Some(last) = last.filter(|_| self.panning) else { return false };
+        let cells_per_pixel = self.camera.view_cells(grid).0 / camera::viewport(self.size(), grid).2;
+        self.camera.pan((last.x - position.x) * cells_per_pixel, (last.y - position.y) * cells_per_pixel, grid);
+        true
+    }
+
+    // Zooms around the cursor, or the middle of the window without one
+    pub fn zoom(&mut self, lines: f64, grid: (u32, u32)) {
+        let size = self.size();
+        let anchor = self.cursor.map_or((size.0 as f64 / 2.0, size.1 as f64 / 2.0), |cursor| (cursor.x, cursor.y));
+        self.camera.zoom_at(1.2f64.powf(lines), anchor, size, grid);
+    }
+}
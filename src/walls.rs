@@ -0,0 +1,89 @@
+use crate::transform::Transform;
+use crate::WALL;
+
+/// Where the walls are, followed from the cells written to a grid, so they can be put back over
+/// cells that came from somewhere without them: a fresh soup, a cleared grid or a rewound generation.
+#[derive(Default)]
+pub struct Walls {
+    // Empty until the first wall, so grids without any don't pay for it
+    cells: Vec<bool>,
+}
+
+impl Walls {
+    /// Follows a run of `cells` written at row-major index `offset` of a `len`-cell grid.
+    pub fn track(&mut self, len: usize, offset: usize, cells: &[u32]) {
+        if self.cells.is_empty() {
+            if !cells.contains(&WALL) { return; }
+            self.cells = vec![false; len];
+        }
+        for (wall, &cell) in self.cells[offset..offset + cells.len()].iter_mut().zip(cells) {
+            *wall = cell == WALL;
+        }
+    }
+
+    /// `track` for a rectangle of a `grid_width` x `grid_height` grid, as written by `Simulation::set_rect`.
+    pub fn track_rect(&mut self, grid_width: usize, grid_height: usize, x: usize, y: usize, width: usize, cells: &[u32]) {
+        for (index, offset, len) in crate::rect_spans(grid_width, grid_height, x, y, width, cells.len() / width) {
+            self.track(grid_width * grid_height, index, &cells[offset..offset + len]);
+        }
+    }
+
+    /// Puts the walls back into a whole grid.
+    pub fn apply(&self, cells: &mut [u32]) {
+        for (cell, &wall) in cells.iter_mut().zip(&self.cells) {
+            if wall { *cell = WALL; }
+        }
+    }
+
+    /// Whether the cell at row-major `index` is a wall.
+    pub fn contains(&self, index: usize) -> bool {
+        self.cells.get(index).is_some_and(|&wall| wall)
+    }
+
+    /// Moves the walls of a `width` x `height` grid along with its cells.
+    pub fn transform(&mut self, transform: Transform, width: usize, height: usize) {
+        if self.cells.is_empty() { return; }
+        self.cells = transform.apply(&self.cells, width, height);
+    }
+
+    pub fn count(&self) -> usize {
+        self.cells.iter().filter(|&&wall| wall).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walls_follow_writes() {
+        let mut walls = Walls::default();
+        walls.track(16, 0, &[1; 16]);
+        assert_eq!(walls.count(), 0);
+        // A 2x2 block of walls across the right edge of a 4x4 grid
+        walls.track_rect(4, 4, 3, 1, 2, &[WALL; 4]);
+        assert_eq!(walls.count(), 4);
+        assert!(walls.contains(7) && walls.contains(4) && !walls.contains(6));
+        walls.track(16, 4, &[0]);
+        let mut cells = vec![1; 16];
+        walls.apply(&mut cells);
+        assert_eq!(cells.iter().filter(|&&cell| cell == WALL).count(), 3);
+        assert_eq!((cells[7], cells[8], cells[11]), (WALL, WALL, WALL));
+    }
+
+    #[test]
+    fn walls_move_with_the_grid() {
+        let mut walls = Walls::default();
+        walls.transform(Transform::Rotate, 3, 3);
+        assert_eq!(walls.count(), 0);
+        // A wall in the top-right corner of a 3x3 grid
+        walls.track(9, 2, &[WALL]);
+        walls.transform(Transform::Rotate, 3, 3);
+        assert!(walls.contains(8));
+        walls.transform(Transform::Shift { dx: 1, dy: 0 }, 3, 3);
+        assert!(walls.contains(6));
+        walls.transform(Transform::MirrorY, 3, 3);
+        assert!(walls.contains(0));
+        assert_eq!(walls.count(), 1);
+    }
+}
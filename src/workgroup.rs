@@ -0,0 +1,140 @@
+use std::fmt;
+use std::path::Path;
+
+/// Workgroup dimensions of the generation step. The shaders declare `@workgroup_size(WORKGROUP_X, WORKGROUP_Y)`
+/// and the placeholders are filled in before compiling.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct WorkgroupSize {
+    pub x: u32,
+    pub y: u32,
+}
+
+impl WorkgroupSize {
+    pub const DEFAULT: WorkgroupSize = WorkgroupSize { x: 8, y: 8 };
+
+    /// What `--tune` times; all of them fit the 256 invocations every adapter allows.
+    pub const CANDIDATES: [WorkgroupSize; 8] = [
+        WorkgroupSize { x: 8, y: 4 },
+        WorkgroupSize { x: 8, y: 8 },
+        WorkgroupSize { x: 16, y: 8 },
+        WorkgroupSize { x: 8, y: 16 },
+        WorkgroupSize { x: 16, y: 16 },
+        WorkgroupSize { x: 32, y: 4 },
+        WorkgroupSize { x: 32, y: 8 },
+        WorkgroupSize { x: 64, y: 4 },
+    ];
+
+    /// "16x8" -> 16 wide, 8 tall
+    pub fn parse(text: &str) -> Result<WorkgroupSize, String> {
+        let invalid = || format!("invalid workgroup size '{}' (expected WxH, e.g. 16x8)", text);
+        let (x, y) = text.split_once('x').ok_or_else(invalid)?;
+        match (x.parse().map_err(|_| invalid())?, y.parse().map_err(|_| invalid())?) {
+            (0, _) | (_, 0) => Err(invalid()),
+            (x, y) => Ok(WorkgroupSize { x, y }),
+        }
+    }
+
+    pub fn invocations(self) -> u32 {
+        self.x * self.y
+    }
+
+    /// Workgroups covering a `width` x `height` grid, rounded up; the shaders bounds-check the ragged edge.
+    pub fn groups(self, width: u32, height: u32) -> (u32, u32) {
+        (width.div_ceil(self.x), height.div_ceil(self.y))
+    }
+
+    /// Whether the device can dispatch workgroups this big.
+    pub fn check(self, limits: &wgpu::Limits) -> Result<(), String> {
+        if self.x > limits.max_compute_workgroup_size_x || self.y > limits.max_compute_workgroup_size_y || self.invocations() > limits.max_compute_invocations_per_workgroup {
+            return Err(format!(
+                "workgroup size {} is too big for this adapter (at most {}x{} and {} invocations)",
+                self, limits.max_compute_workgroup_size_x, limits.max_compute_workgroup_size_y, limits.max_compute_invocations_per_workgroup
+            ));
+        }
+        Ok(())
+    }
+
+    /// `source` with the WORKGROUP_X and WORKGROUP_Y placeholders replaced.
+    pub fn shader_source(self, source: &str) -> String {
+        source.replace("WORKGROUP_X", &self.x.to_string()).replace("WORKGROUP_Y", &self.y.to_string())
+    }
+}
+
+impl fmt::Display for WorkgroupSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{}", self.x, self.y)
+    }
+}
+
+/// The size `--tune` last picked on `adapter`, if the cache file at `path` has one.
+pub fn cached(path: &Path, adapter: &str) -> Option<WorkgroupSize> {
+    lookup(&std::fs::read_to_string(path).ok()?, adapter)
+}
+
+/// Records `size` as the pick for `adapter`, replacing any earlier one.
+pub fn remember(path: &Path, adapter: &str, size: WorkgroupSize) -> Result<(), String> {
+    let text = std::fs::read_to_string(path).unwrap_or_default();
+    std::fs::write(path, update(&text, adapter, size)).map_err(|e| format!("could not write workgroup cache '{}': {}", path.display(), e))
+}
+
+// One "WxH adapter name" line per adapter; names have spaces, sizes don't
+fn lookup(text: &str, adapter: &str) -> Option<WorkgroupSize> {
+    text.lines().find_map(|line| {
+        let (size, name) = line.split_once(' ')?;
+        if name == adapter { WorkgroupSize::parse(size).ok() } else { None }
+    })
+}
+
+fn update(text: &str, adapter: &str, size: WorkgroupSize) -> String {
+    let mut out: String = text.lines()
+        .filter(|line| !matches!(line.split_once(' '), Some((_, name)) if name == adapter))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    out.push_str(&format!("{} {}\n", size, adapter));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(WorkgroupSize::parse("16x8"), Ok(WorkgroupSize { x: 16, y: 8 }));
+        assert_eq!(WorkgroupSize::parse("16x8").unwrap().to_string(), "16x8");
+        assert!(WorkgroupSize::parse("16").is_err());
+        assert!(WorkgroupSize::parse("0x8").is_err());
+        assert!(WorkgroupSize::parse("ax8").is_err());
+    }
+
+    #[test]
+    fn groups_round_up() {
+        assert_eq!(WorkgroupSize { x: 16, y: 8 }.groups(100, 16), (7, 2));
+        assert_eq!(WorkgroupSize::DEFAULT.groups(8, 1), (1, 1));
+    }
+
+    #[test]
+    fn checks_limits() {
+        let limits = wgpu::Limits::default();
+        assert!(WorkgroupSize::CANDIDATES.iter().all(|size| size.check(&limits).is_ok()));
+        assert!(WorkgroupSize { x: 32, y: 16 }.check(&limits).is_err());
+        assert!(WorkgroupSize { x: 1, y: 512 }.check(&limits).is_err());
+    }
+
+    #[test]
+    fn fills_placeholders() {
+        let source = "@compute @workgroup_size(WORKGROUP_X, WORKGROUP_Y)";
+        assert_eq!(WorkgroupSize { x: 32, y: 4 }.shader_source(source), "@compute @workgroup_size(32, 4)");
+    }
+
+    #[test]
+    fn cache_keeps_one_line_per_adapter() {
+        let text = update("", "NVIDIA GeForce RTX 3070", WorkgroupSize { x: 16, y: 8 });
+        let text = update(&text, "llvmpipe (LLVM 15.0.7, 256 bits)", WorkgroupSize::DEFAULT);
+        let text = update(&text, "NVIDIA GeForce RTX 3070", WorkgroupSize { x: 32, y: 8 });
+        assert_eq!(text.lines().count(), 2);
+        assert_eq!(lookup(&text, "NVIDIA GeForce RTX 3070"), Some(WorkgroupSize { x: 32, y: 8 }));
+        assert_eq!(lookup(&text, "llvmpipe (LLVM 15.0.7, 256 bits)"), Some(WorkgroupSize::DEFAULT));
+        assert_eq!(lookup(&text, "Apple M2"), None);
+    }
+}